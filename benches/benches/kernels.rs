@@ -0,0 +1,111 @@
+// Baselines for the stages optimization work targets, on each kernel
+//
+//   cd benches && cargo bench                # everything
+//   cargo bench -- lex_prelude               # one group
+//   cargo bench -- --save-baseline before    # then compare a change with --baseline before
+//
+// lex_prelude lexes the standard prelude (the largest source every Lumen run reads),
+// parse_deep parses deeply nested and long flat expressions, and run_* executes small
+// CPU-bound programs (trial-division primes, Machin's pi in integers). Programs run
+// without the prelude on both kernels, so the kernels are timed on the same work.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use lumen_stream::kernel::lexer;
+use lumen_stream::languages::lumen;
+use lumen_stream::Interpreter;
+use microcode_2::kernel::_1_ingest;
+use microcode_2::languages;
+use microcode_2::schema::LanguageSchema;
+
+const PRIMES: &str = "\
+count = 0
+n = 2
+while n < 2000
+    d = 2
+    prime = true
+    while d * d <= n
+        if n % d == 0
+            prime = false
+            break
+        d = d + 1
+    if prime
+        count = count + 1
+    n = n + 1
+count
+";
+
+const PI: &str = "\
+fn arctan_inv(x, one)
+    total = one // x
+    term = total
+    x2 = x * x
+    n = 1
+    sign = -1
+    while term != 0
+        term = term // x2
+        total = total + sign * (term // (2 * n + 1))
+        sign = -sign
+        n = n + 1
+    return total
+
+one = 10 ** 210
+pi = 4 * (4 * arctan_inv(5, one) - arctan_inv(239, one))
+pi // 10 ** 10
+";
+
+fn lumen_schema() -> LanguageSchema {
+    languages::by_name("lumen").expect("lumen is built in").get_schema()
+}
+
+fn lex_prelude(c: &mut Criterion) {
+    let prelude = lumen_stream::includes::process_includes(include_str!("../../lib_lumen/prelude.lm"))
+        .expect("the prelude's includes resolve");
+    let schema = lumen_schema();
+    let mut registry = lumen::registry::Registry::new();
+    lumen::dispatcher::register_all(&mut registry);
+
+    let mut group = c.benchmark_group("lex_prelude");
+    group.bench_function("stream", |b| b.iter(|| lexer::lex(black_box(&prelude), &registry.tokens).unwrap()));
+    group.bench_function("microcode", |b| b.iter(|| _1_ingest::lex(black_box(&prelude), &schema).unwrap()));
+    group.finish();
+}
+
+fn parse_deep(c: &mut Criterion) {
+    let schema = lumen_schema();
+    let stream = Interpreter::new();
+    let mut group = c.benchmark_group("parse_deep");
+    for depth in [16, 64, 256] {
+        // ((((1 + 1) * 2) + 3) ...) nested `depth` deep, and as many terms in one flat sum
+        let mut nested = "1".to_string();
+        for i in 0..depth {
+            nested = format!("({} {} {})", nested, if i % 2 == 0 { "+" } else { "*" }, i % 10);
+        }
+        let nested = format!("x = {}\n", nested);
+        let flat = format!("x = {}\n", vec!["1"; depth * 4].join(" + "));
+
+        for (shape, source) in [("nested", &nested), ("flat", &flat)] {
+            group.bench_with_input(BenchmarkId::new(format!("stream/{}", shape), depth), source, |b, source| {
+                b.iter(|| stream.check(black_box(source)).unwrap())
+            });
+            group.bench_with_input(BenchmarkId::new(format!("microcode/{}", shape), depth), source, |b, source| {
+                b.iter(|| microcode_2::parse_program(black_box(source), &schema).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn run_programs(c: &mut Criterion) {
+    let schema = lumen_schema();
+    let stream = Interpreter::new().without_prelude();
+    for (name, program) in [("run_primes", PRIMES), ("run_pi", PI)] {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(20);
+        group.bench_function("stream", |b| b.iter(|| stream.eval(black_box(program)).unwrap()));
+        group.bench_function("microcode", |b| b.iter(|| microcode_2::run(black_box(program), &schema, &[]).unwrap()));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, lex_prelude, parse_deep, run_programs);
+criterion_main!(benches);
@@ -0,0 +1,125 @@
+// Build script: reduce the standard prelude once, when the microcode kernel is built
+//
+// lib_lumen/prelude.lm and the library files it includes are expanded and run through
+// stages 1-3 of the microcode kernel (the same source files, compiled into this script),
+// and the instructions are written to $OUT_DIR as a saved program (see
+// src_microcode/kernel/serial.rs):
+//
+//   prelude.cbor   the reduced prelude, in CBOR
+//   prelude.rs     PROGRAM (those bytes) and LINES (lines in the expanded prelude text)
+//
+// The microcode binary links its user program after PROGRAM instead of parsing the
+// prelude text at every start. A prelude that does not reduce fails the build.
+//
+// It also sets LUMEN_RUSTC (the compiler's version line), part of the plugin ABI string
+// (see src_microcode/plugin.rs): a plugin only loads if it was built by the same compiler;
+// and LUMEN_BUILD_ID, a digest of the microcode kernel's source files and that line, part
+// of --cache keys (see src_microcode/kernel/cache.rs): a rebuilt kernel whose sources
+// changed never reads back what the old one reduced.
+
+use std::env;
+use std::fs;
+use std::hash::{DefaultHasher, Hasher};
+use std::path::Path;
+
+// Stages 1-3 and the Lumen schema: the library's own source files (linted there)
+#[allow(dead_code, unused, clippy::all)]
+#[path = "src_microcode/schema.rs"]
+mod schema;
+
+#[allow(dead_code, unused, clippy::all)]
+#[path = "src_microcode/kernel"]
+mod kernel {
+    pub mod _1_ingest;
+    pub mod _2_structure;
+    pub mod _3_reduce;
+    pub mod bigfloat {
+        include!("lib_lumen/bigfloat.rs");
+    }
+    pub mod control_flow {
+        include!("lib_lumen/control_flow.rs");
+    }
+    pub mod eval;
+    pub mod lexeme_trie {
+        include!("lib_lumen/lexeme_trie.rs");
+    }
+    pub mod primitives;
+    pub mod repr {
+        include!("lib_lumen/repr.rs");
+    }
+    pub mod serial;
+}
+
+#[allow(dead_code, unused, clippy::all)]
+#[path = "src_microcode/languages/lumen/schema.rs"]
+mod lumen_schema;
+
+mod embedded_files {
+    include!("lib_lumen/prelude.rs");
+}
+
+mod includes {
+    include!("lib_lumen/includes.rs");
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=lib_lumen");
+    println!("cargo:rerun-if-changed=src_microcode");
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = std::process::Command::new(rustc)
+        .arg("--version")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=LUMEN_RUSTC={}", version);
+    let mut digest = DefaultHasher::new();
+    digest.write(version.as_bytes());
+    for dir in ["src_microcode", "lib_lumen"] {
+        hash_files(Path::new(dir), &mut digest);
+    }
+    println!("cargo:rustc-env=LUMEN_BUILD_ID={:016x}", digest.finish());
+
+    let expanded = includes::process_includes(include_str!("lib_lumen/prelude.lm"))
+        .unwrap_or_else(|e| panic!("prelude: include error: {}", e));
+    let schema = lumen_schema::get_schema();
+    let program = kernel::_1_ingest::lex(&expanded, &schema)
+        .and_then(|tokens| kernel::_2_structure::process_structure(tokens, &schema))
+        .and_then(|tokens| kernel::_3_reduce::parse(tokens, &schema))
+        .unwrap_or_else(|errors| {
+            let lines: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            panic!("prelude does not reduce:\n{}", lines.join("\n"))
+        });
+    let saved = kernel::serial::SavedProgram::new("lumen", program);
+    let bytes = kernel::serial::save(&saved, kernel::serial::Format::Cbor).expect("prelude: encode");
+
+    let out = env::var("OUT_DIR").expect("OUT_DIR");
+    let out = Path::new(&out);
+    fs::write(out.join("prelude.cbor"), bytes).expect("write prelude.cbor");
+    fs::write(
+        out.join("prelude.rs"),
+        format!(
+            "pub static PROGRAM: &[u8] = include_bytes!(\"prelude.cbor\");\npub const LINES: usize = {};\n",
+            expanded.matches('\n').count()
+        ),
+    )
+    .expect("write prelude.rs");
+}
+
+/// Feed the path and contents of every file under `dir` to `digest`, in path order
+fn hash_files(dir: &Path, digest: &mut DefaultHasher) {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("{}: {}", dir.display(), e))
+        .map(|entry| entry.expect("read_dir entry").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            hash_files(&path, digest);
+        } else {
+            digest.write(path.to_string_lossy().as_bytes());
+            digest.write(&fs::read(&path).unwrap_or_default());
+        }
+    }
+}
@@ -0,0 +1,10 @@
+pair = extern("json:parse", "[1, 2]")
+extern("print_native", pair)
+
+roll = extern("random:int", 6, 6)
+extern("print_native", roll * 7)
+
+if extern("fs:exists", "no/such/file.txt")
+    extern("print_native", 0)
+else
+    extern("print_native", extern("json:stringify", pair))
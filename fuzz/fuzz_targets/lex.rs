@@ -0,0 +1,12 @@
+// Stage 1 of both kernels: source text to tokens
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lumen_lang_fuzz::Input;
+use microcode_2::kernel::_1_ingest;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(input) = Input::new(data) else { return };
+    let _ = _1_ingest::lex(&input.source, &input.schema);
+    let _ = lumen_stream::kernel::lexer::lex(&input.source, &input.stream_tokens());
+});
@@ -0,0 +1,12 @@
+// Stages 1-3 of both kernels: source text to a parsed program, never run
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lumen_lang_fuzz::Input;
+use lumen_stream::Interpreter;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(input) = Input::new(data) else { return };
+    let _ = microcode_2::parse_program(&input.source, &input.schema);
+    let _ = Interpreter::new().with_language(input.language).check(&input.source);
+});
@@ -0,0 +1,21 @@
+// Stage 2 of both kernels: tokens to block structure (indentation or braces)
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lumen_lang_fuzz::Input;
+use lumen_stream::languages::{lumen, python_core, rust_core};
+use microcode_2::kernel::{_1_ingest, _2_structure};
+
+fuzz_target!(|data: &[u8]| {
+    let Some(input) = Input::new(data) else { return };
+    if let Ok(tokens) = _1_ingest::lex(&input.source, &input.schema) {
+        let _ = _2_structure::process_structure(tokens, &input.schema);
+    }
+    if let Ok(tokens) = lumen_stream::kernel::lexer::lex(&input.source, &input.stream_tokens()) {
+        let _ = match input.language {
+            "lumen" => lumen::structure::structural::process_indentation(&input.source, tokens),
+            "rust_core" => rust_core::structure::structural::process_tokens(tokens),
+            _ => python_core::structure::structural::process_indentation(&input.source, tokens),
+        };
+    }
+});
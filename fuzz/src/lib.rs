@@ -0,0 +1,59 @@
+// Fuzz inputs shared by the targets in fuzz_targets/
+//
+// The first byte of an input picks one of the languages both kernels run and whether
+// the rest is used as it is (any text, even invalid UTF-8, read lossily) or as the
+// choices of microcode_2::fuzzing::program, which builds a program from the language's
+// own lexemes. Each target runs one stage on both kernels; the stage may return an
+// error for any input, but must not panic or run past libFuzzer's -timeout.
+
+use lumen_stream::kernel::registry::TokenRegistry;
+use lumen_stream::languages::{lumen, python_core, rust_core};
+use microcode_2::fuzzing;
+use microcode_2::languages;
+use microcode_2::schema::LanguageSchema;
+
+/// The languages both kernels run, by --lang name
+pub const LANGUAGES: [&str; 3] = ["lumen", "rust_core", "python_core"];
+
+/// One fuzz input: a source text in a language
+pub struct Input {
+    pub language: &'static str,
+    pub schema: LanguageSchema,
+    pub source: String,
+}
+
+impl Input {
+    /// The input `data` stands for (None for an empty one)
+    pub fn new(data: &[u8]) -> Option<Input> {
+        let (&selector, rest) = data.split_first()?;
+        let language = LANGUAGES[usize::from(selector & 0x7f) % LANGUAGES.len()];
+        let schema = languages::by_name(language)?.get_schema();
+        let source = if selector & 0x80 == 0 {
+            fuzzing::program(rest, &schema)
+        } else {
+            String::from_utf8_lossy(rest).into_owned()
+        };
+        Some(Input { language, schema, source })
+    }
+
+    /// The stream kernel's token registry for the language
+    pub fn stream_tokens(&self) -> TokenRegistry {
+        match self.language {
+            "lumen" => {
+                let mut registry = lumen::registry::Registry::new();
+                lumen::dispatcher::register_all(&mut registry);
+                registry.tokens
+            }
+            "rust_core" => {
+                let mut registry = rust_core::registry::Registry::new();
+                rust_core::register_all(&mut registry);
+                registry.tokens
+            }
+            _ => {
+                let mut registry = python_core::registry::Registry::new();
+                python_core::register_all(&mut registry);
+                registry.tokens
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// The `arr` extern backend: array operations that call back into the program, shared by both
+// kernels
+//
+//   extern("arr:sort_by", items, compare)   a sorted copy of ARRAY `items`, in the order the
+//                                          program's function compare(a, b) gives: negative
+//                                          when a goes first, positive when b does, 0 to keep
+//                                          them as they are (the sort is stable)
+//
+// The function crosses the boundary as a value, named where the argument goes:
+//
+//   fn by_length(a, b)
+//       return len(a) - len(b)
+//   words = extern("arr:sort_by", words, by_length)
+//
+// The host calls it back while the extern call runs, as many times as the sort needs; an
+// error in it ends the call. extern_async cannot pass a function, since its call runs
+// alongside the program.
+
+use std::cmp::Ordering;
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "arr:" in a selector
+pub const CAPABILITIES: &[&str] = &["sort_by"];
+
+/// The error of a call whose arguments are not an array and a function
+pub fn expects(name: &str) -> ExternError {
+    ExternError::invalid_argument(format!("arr:{} expects an array and a function", name))
+}
+
+/// The error of a comparison that gave `got` (its repr) instead of an integer
+pub fn not_an_order(got: &str) -> ExternError {
+    ExternError::invalid_argument(format!("arr:sort_by: compare must return an integer, got {}", got))
+}
+
+/// `items` sorted stably by `compare` (a merge sort, so the program's function is called
+/// O(n log n) times); the first error it gives stops the sort
+pub fn sort_by<V>(mut items: Vec<V>, compare: &mut dyn FnMut(&V, &V) -> Result<Ordering, ExternError>) -> Result<Vec<V>, ExternError> {
+    if items.len() < 2 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let (left, right) = (sort_by(items, compare)?, sort_by(right, compare)?);
+    let mut sorted = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Equal items keep their order: the left one goes first
+        let next = if compare(a, b)? == Ordering::Greater { right.next() } else { left.next() };
+        sorted.extend(next);
+    }
+    sorted.extend(left);
+    sorted.extend(right);
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_stably_and_stops_at_an_error() {
+        let words = vec!["pear", "fig", "apple", "kiwi", "date"];
+        let mut calls = 0;
+        let sorted = sort_by(words.clone(), &mut |a: &&str, b: &&str| {
+            calls += 1;
+            Ok(a.len().cmp(&b.len()))
+        });
+        assert_eq!(sorted, Ok(vec!["fig", "pear", "kiwi", "date", "apple"]));
+        assert!(calls <= 8);
+
+        let failed = sort_by(words, &mut |_: &&str, _: &&str| Err(not_an_order("null")));
+        assert_eq!(failed, Err(ExternError::invalid_argument("arr:sort_by: compare must return an integer, got null")));
+        assert_eq!(sort_by(Vec::<i32>::new(), &mut |_, _| unreachable!()), Ok(vec![]));
+    }
+}
@@ -0,0 +1,403 @@
+// Decimal floating point for REAL values, shared by both kernels
+// Included by both kernels (like transcendental.rs) so the rounding rule lives in one place.
+//
+// A REAL of precision p is a decimal float: mantissa * 10^exponent with at most p
+// significant digits. Each operation computes its result exactly (as a rational) and
+// rounds it once, half away from zero, to the precision of the result, so every REAL
+// a kernel holds is the correctly rounded value of the operation that made it. The
+// kernels keep storing REALs as a reduced (numerator, denominator) pair; after
+// rounding the denominator is a power of ten dividing 10^-exponent, and the mantissa
+// never grows beyond p digits however long a computation runs.
+//
+// Rounding a value that already has at most p digits leaves it unchanged, so results
+// of transcendental.rs (rounded there with guard digits) pass through exactly.
+//
+// inf, -inf and nan are REALs too, held as n/0: (1, 0), (-1, 0) and (0, 0). They come
+// from a REAL divided by zero (x/0 is inf with the sign of x, 0/0 is nan), from
+// transcendental.rs (ln(0) is -inf, ln of a negative is nan), and from overflow: a REAL
+// whose decimal exponent would pass MAX_EXPONENT is inf, one below -MAX_EXPONENT is 0.
+// They then follow IEEE 754: inf - inf, 0 * inf and inf / inf are nan, x / inf is 0, and
+// anything with a nan is nan. nan is unordered: every comparison with it is false except
+// !=, even with itself. They print as "inf", "-inf" and "nan", and have no integer part.
+// Integer and rational division by zero stay errors.
+
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+/// The largest decimal exponent of a finite REAL: beyond it a REAL is inf
+pub const MAX_EXPONENT: i64 = 1_000_000;
+
+/// mantissa * 10^exponent, with no trailing zeros in the mantissa (zero is 0 * 10^0)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigFloat {
+    pub mantissa: BigInt,
+    pub exponent: i64,
+}
+
+impl BigFloat {
+    /// num/den rounded to `precision` significant digits, halves away from zero
+    /// A precision of 0 is taken as 1.
+    pub fn round(num: &BigInt, den: &BigInt, precision: usize) -> BigFloat {
+        if num.is_zero() {
+            return BigFloat { mantissa: BigInt::zero(), exponent: 0 };
+        }
+        let negative = num.is_negative() != den.is_negative();
+        let (num, den) = (num.abs(), den.abs());
+        let precision = precision.max(1) as i64;
+
+        // |num/den| is below 10^(digits(num) - digits(den) + 1); aim the mantissa at
+        // `precision` digits and move the exponent until it has exactly that many
+        let mut exponent = digit_count(&num) as i64 - digit_count(&den) as i64 + 1 - precision;
+        let (low, high) = (pow10(precision as u32 - 1), pow10(precision as u32));
+        let mut mantissa = loop {
+            let mantissa = scaled_round(&num, &den, exponent);
+            if mantissa >= high {
+                exponent += 1;
+            } else if mantissa < low {
+                exponent -= 1;
+            } else {
+                break mantissa;
+            }
+        };
+
+        let ten = BigInt::from(10);
+        while (&mantissa % &ten).is_zero() {
+            mantissa /= &ten;
+            exponent += 1;
+        }
+        BigFloat { mantissa: if negative { -mantissa } else { mantissa }, exponent }
+    }
+
+    /// The exact value as a reduced (numerator, denominator)
+    pub fn to_rational(&self) -> (BigInt, BigInt) {
+        if self.exponent >= 0 {
+            return (&self.mantissa * pow10(self.exponent as u32), BigInt::one());
+        }
+        // The denominator is 10^-exponent: cancel the factors 2 and 5 the mantissa shares
+        let (mut num, mut den) = (self.mantissa.clone(), pow10((-self.exponent) as u32));
+        for factor in [BigInt::from(2), BigInt::from(5)] {
+            while (&num % &factor).is_zero() && (&den % &factor).is_zero() {
+                num /= &factor;
+                den /= &factor;
+            }
+        }
+        (num, den)
+    }
+
+    /// Positional decimal notation, without trailing zeros after the point
+    pub fn to_decimal_string(&self) -> String {
+        let sign = if self.mantissa.is_negative() { "-" } else { "" };
+        let digits = self.mantissa.abs().to_string();
+        if self.exponent >= 0 {
+            if self.mantissa.is_zero() {
+                return "0".to_string();
+            }
+            return format!("{}{}{}", sign, digits, "0".repeat(self.exponent as usize));
+        }
+        let fraction = (-self.exponent) as usize;
+        let digits = format!("{:0>width$}", digits, width = fraction + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - fraction);
+        format!("{}{}.{}", sign, whole, fraction)
+    }
+}
+
+/// num/den as a REAL of `precision` digits, as a reduced (numerator, denominator):
+/// inf and nan stay as they are, and a value past MAX_EXPONENT becomes inf (or 0)
+pub fn round_real(num: &BigInt, den: &BigInt, precision: usize) -> (BigInt, BigInt) {
+    if den.is_zero() {
+        return (num.signum(), BigInt::zero());
+    }
+    if num.is_zero() {
+        return (BigInt::zero(), BigInt::one());
+    }
+    // log2 |num/den| is within 1 of this: a value far out of range is not rounded first
+    let bits = num.bits() as i64 - den.bits() as i64;
+    let negative = num.is_negative() != den.is_negative();
+    if decimal_exponent(bits - 1, 1) > MAX_EXPONENT as f64 + 1.0 {
+        return infinity(negative);
+    }
+    if decimal_exponent(bits + 1, 1) < -(MAX_EXPONENT as f64) - 1.0 {
+        return (BigInt::zero(), BigInt::one());
+    }
+
+    let value = BigFloat::round(num, den, precision);
+    let exponent = value.exponent + digit_count(&value.mantissa) as i64 - 1;
+    if exponent > MAX_EXPONENT {
+        infinity(negative)
+    } else if exponent < -MAX_EXPONENT {
+        (BigInt::zero(), BigInt::one())
+    } else {
+        value.to_rational()
+    }
+}
+
+/// How a REAL prints: inf, -inf, nan, or its decimal notation rounded to `precision`
+pub fn decimal_string(num: &BigInt, den: &BigInt, precision: usize) -> String {
+    match den.is_zero() {
+        true => special_name(num).to_string(),
+        false => BigFloat::round(num, den, precision).to_decimal_string(),
+    }
+}
+
+/// num/den in positional notation with exactly `places` digits after the point, rounded
+/// half away from zero; with `grouping` > 0 the digits are grouped that many at a time from
+/// the point outward, separated by spaces. inf and nan are written as themselves.
+pub fn fixed_string(num: &BigInt, den: &BigInt, places: usize, grouping: usize) -> String {
+    if den.is_zero() {
+        return special_name(num).to_string();
+    }
+    let scaled = scaled_round(&num.abs(), &den.abs(), -(places as i64));
+    let sign = if num.is_negative() != den.is_negative() && !scaled.is_zero() { "-" } else { "" };
+    let digits = format!("{:0>width$}", scaled.to_string(), width = places + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - places);
+    match places {
+        0 => format!("{}{}", sign, grouped(whole, grouping, true)),
+        _ => format!("{}{}.{}", sign, grouped(whole, grouping, true), grouped(fraction, grouping, false)),
+    }
+}
+
+/// num/den truncated toward zero (an error for inf and nan)
+pub fn integer_part(num: &BigInt, den: &BigInt) -> Result<BigInt, String> {
+    match den.is_zero() {
+        true => Err(format!("{} has no integer part", special_name(num))),
+        false => Ok(num / den),
+    }
+}
+
+/// a op b for + - * / when an operand is inf or nan or the division is by zero; None when
+/// the ordinary arithmetic applies (or for another operator)
+pub fn special_arithmetic(op: &str, a: (&BigInt, &BigInt), b: (&BigInt, &BigInt)) -> Option<(BigInt, BigInt)> {
+    if !matches!(op, "+" | "-" | "*" | "/") {
+        return None;
+    }
+    let (a_special, b_special) = (a.1.is_zero(), b.1.is_zero());
+    let (a_sign, b_sign) = (sign(a), sign(b));
+    if !a_special && !b_special {
+        // x/0 is inf with the sign of x, and 0/0 is nan
+        return (op == "/" && b.0.is_zero()).then(|| special(a_sign));
+    }
+    if is_nan(a) || is_nan(b) {
+        return Some(special(0));
+    }
+    match op {
+        "+" | "-" => {
+            let b_sign = if op == "-" { -b_sign } else { b_sign };
+            Some(match (a_special, b_special) {
+                (true, true) if a_sign != b_sign => special(0),
+                (true, _) => special(a_sign),
+                _ => special(b_sign),
+            })
+        }
+        // 0 * inf is nan
+        "*" => Some(special(a_sign * b_sign)),
+        _ => Some(match (a_special, b_special) {
+            (true, true) => special(0),
+            (true, false) => special(a_sign * if b_sign == 0 { 1 } else { b_sign }),
+            (false, _) => (BigInt::zero(), BigInt::one()),
+        }),
+    }
+}
+
+/// base ** exponent when the base is inf or nan or the result is past MAX_EXPONENT (inf)
+/// or below -MAX_EXPONENT (0); None when the exact power applies
+pub fn special_power(base: (&BigInt, &BigInt), exponent: u32) -> Option<(BigInt, BigInt)> {
+    // x ** 0 is 1, inf and nan included
+    if exponent == 0 {
+        return base.1.is_zero().then(|| (BigInt::one(), BigInt::one()));
+    }
+    if base.0.is_zero() && !base.1.is_zero() {
+        return None;
+    }
+    let negative = base.0.is_negative() && exponent % 2 == 1;
+    if base.1.is_zero() {
+        return Some(if is_nan(base) { special(0) } else { infinity(negative) });
+    }
+    let bits = base.0.bits() as i64 - base.1.bits() as i64;
+    if bits > 1 && decimal_exponent(bits - 1, exponent) > MAX_EXPONENT as f64 + 1.0 {
+        return Some(infinity(negative));
+    }
+    if bits < -1 && decimal_exponent(bits + 1, exponent) < -(MAX_EXPONENT as f64) - 1.0 {
+        return Some((BigInt::zero(), BigInt::one()));
+    }
+    None
+}
+
+/// a op b for the comparison operators, where a or b may be inf or nan (nan is unordered,
+/// so only != holds for it); None for any other operator
+pub fn comparison(op: &str, a: (&BigInt, &BigInt), b: (&BigInt, &BigInt)) -> Option<bool> {
+    let ordering = compare(a, b);
+    Some(match op {
+        "==" => ordering == Some(Ordering::Equal),
+        "!=" => ordering != Some(Ordering::Equal),
+        "<" => ordering == Some(Ordering::Less),
+        ">" => ordering == Some(Ordering::Greater),
+        "<=" => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        ">=" => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        _ => return None,
+    })
+}
+
+/// The order of a and b (denominators positive or zero); None when either is nan
+fn compare(a: (&BigInt, &BigInt), b: (&BigInt, &BigInt)) -> Option<Ordering> {
+    if is_nan(a) || is_nan(b) {
+        return None;
+    }
+    // An infinity is beyond every finite value
+    let rank = |value: (&BigInt, &BigInt)| if value.1.is_zero() { sign(value) } else { 0 };
+    Some(match (a.1.is_zero(), b.1.is_zero()) {
+        (false, false) => (a.0 * b.1).cmp(&(b.0 * a.1)),
+        _ => rank(a).cmp(&rank(b)),
+    })
+}
+
+fn is_nan(value: (&BigInt, &BigInt)) -> bool {
+    value.0.is_zero() && value.1.is_zero()
+}
+
+/// -1, 0 or 1
+fn sign(value: (&BigInt, &BigInt)) -> i32 {
+    if value.0.is_zero() {
+        0
+    } else if value.0.is_negative() != value.1.is_negative() {
+        -1
+    } else {
+        1
+    }
+}
+
+/// inf with the sign of `sign`, or nan for 0
+fn special(sign: i32) -> (BigInt, BigInt) {
+    (BigInt::from(sign), BigInt::zero())
+}
+
+fn infinity(negative: bool) -> (BigInt, BigInt) {
+    special(if negative { -1 } else { 1 })
+}
+
+fn special_name(num: &BigInt) -> &'static str {
+    match num.sign() {
+        num_bigint::Sign::Plus => "inf",
+        num_bigint::Sign::Minus => "-inf",
+        num_bigint::Sign::NoSign => "nan",
+    }
+}
+
+/// `digits` with a space between every `size` of them (counted from the end when `from_end`)
+fn grouped(digits: &str, size: usize, from_end: bool) -> String {
+    if size == 0 {
+        return digits.to_string();
+    }
+    let mut out = String::with_capacity(digits.len() + digits.len() / size);
+    for (index, digit) in digits.chars().enumerate() {
+        let position = if from_end { digits.len() - index } else { index };
+        if index > 0 && position % size == 0 {
+            out.push(' ');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// log10 of (2^bits)^exponent
+fn decimal_exponent(bits: i64, exponent: u32) -> f64 {
+    bits as f64 * exponent as f64 * std::f64::consts::LOG10_2
+}
+
+/// num/den scaled by 10^-exponent and rounded to an integer, halves away from zero
+/// (num and den are positive)
+fn scaled_round(num: &BigInt, den: &BigInt, exponent: i64) -> BigInt {
+    let (num, den) = if exponent >= 0 {
+        (num.clone(), den * pow10(exponent as u32))
+    } else {
+        (num * pow10((-exponent) as u32), den.clone())
+    };
+    (num * 2 + &den) / (den * 2)
+}
+
+fn pow10(digits: u32) -> BigInt {
+    BigInt::from(10).pow(digits)
+}
+
+/// Number of decimal digits in |v| (0 for zero)
+fn digit_count(v: &BigInt) -> u32 {
+    if v.is_zero() {
+        0
+    } else {
+        v.abs().to_string().len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(num: i64, den: i64, precision: usize) -> String {
+        BigFloat::round(&BigInt::from(num), &BigInt::from(den), precision).to_decimal_string()
+    }
+
+    #[test]
+    fn rounds_to_significant_digits_half_away_from_zero() {
+        assert_eq!(round(2, 3, 5), "0.66667");
+        assert_eq!(round(-2, 3, 5), "-0.66667");
+        assert_eq!(round(1, 20, 5), "0.05");
+        assert_eq!(round(105, 100, 15), "1.05");
+        assert_eq!(round(1, 8, 2), "0.13");
+        assert_eq!(round(-1, 8, 2), "-0.13");
+        assert_eq!(round(999_999, 1_000_000, 3), "1");
+        assert_eq!(round(123_456_789, 1, 5), "123460000");
+        assert_eq!(round(1, 3000, 2), "0.00033");
+        assert_eq!(round(0, 7, 4), "0");
+        // A value with few enough digits is kept exactly
+        let value = BigFloat::round(&BigInt::from(-125), &BigInt::from(1000), 3);
+        assert_eq!(value, BigFloat { mantissa: BigInt::from(-125), exponent: -3 });
+        assert_eq!(value.to_rational(), (BigInt::from(-1), BigInt::from(8)));
+        assert_eq!(BigFloat::round(&BigInt::from(-125), &BigInt::from(1000), 2).to_decimal_string(), "-0.13");
+    }
+
+    #[test]
+    fn writes_fixed_places_in_digit_groups() {
+        let fixed = |num: i64, den: i64, places: usize, grouping: usize| {
+            fixed_string(&BigInt::from(num), &BigInt::from(den), places, grouping)
+        };
+        assert_eq!(fixed(1234567, 1, 2, 3), "1 234 567.00");
+        assert_eq!(fixed(22, 7, 10, 5), "3.14285 71429");
+        assert_eq!(fixed(-1, 8, 2, 0), "-0.13");
+        assert_eq!(fixed(-1, 300, 2, 0), "0.00");
+        assert_eq!(fixed(5, 2, 0, 3), "3");
+        assert_eq!(fixed(1, 0, 4, 3), "inf");
+    }
+
+    #[test]
+    fn gives_inf_and_nan_by_ieee_rules() {
+        let value = |num: i64, den: i64| (BigInt::from(num), BigInt::from(den));
+        let (one, zero, inf, minus_inf, nan) = (value(1, 1), value(0, 1), value(1, 0), value(-1, 0), value(0, 0));
+        let pair = |v: &(BigInt, BigInt)| (v.0.clone(), v.1.clone());
+        let apply = |op: &str, a: &(BigInt, BigInt), b: &(BigInt, BigInt)| special_arithmetic(op, (&a.0, &a.1), (&b.0, &b.1));
+        assert_eq!(apply("/", &one, &zero), Some(pair(&inf)));
+        assert_eq!(apply("/", &value(-3, 2), &zero), Some(pair(&minus_inf)));
+        assert_eq!(apply("/", &zero, &zero), Some(pair(&nan)));
+        assert_eq!(apply("-", &inf, &inf), Some(pair(&nan)));
+        assert_eq!(apply("+", &inf, &one), Some(pair(&inf)));
+        assert_eq!(apply("*", &minus_inf, &value(-2, 1)), Some(pair(&inf)));
+        assert_eq!(apply("*", &inf, &zero), Some(pair(&nan)));
+        assert_eq!(apply("/", &one, &minus_inf), Some(pair(&zero)));
+        assert_eq!(apply("+", &nan, &one), Some(pair(&nan)));
+        assert_eq!(apply("+", &one, &one), None);
+        assert_eq!(apply("%", &inf, &one), None);
+
+        let holds = |op: &str, a: &(BigInt, BigInt), b: &(BigInt, BigInt)| comparison(op, (&a.0, &a.1), (&b.0, &b.1)).unwrap();
+        assert!(holds("==", &inf, &inf) && holds("<", &minus_inf, &inf) && holds(">", &inf, &value(10i64.pow(18), 1)));
+        assert!(!holds("==", &nan, &nan) && holds("!=", &nan, &nan) && !holds("<=", &nan, &one) && !holds(">", &nan, &one));
+
+        // Overflow to inf and underflow to 0
+        let huge = BigInt::from(10).pow(MAX_EXPONENT as u32 + 1);
+        assert_eq!(round_real(&-huge.clone(), &BigInt::one(), 15), pair(&minus_inf));
+        assert_eq!(round_real(&BigInt::one(), &huge, 15), pair(&zero));
+        assert_eq!(special_power((&BigInt::from(10), &BigInt::one()), 2_000_000), Some(pair(&inf)));
+        assert_eq!(special_power((&nan.0, &nan.1), 0), Some(pair(&one)));
+        assert_eq!(decimal_string(&minus_inf.0, &minus_inf.1, 15), "-inf");
+        assert_eq!(integer_part(&nan.0, &nan.1).unwrap_err(), "nan has no integer part");
+    }
+}
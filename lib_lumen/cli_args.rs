@@ -0,0 +1,159 @@
+// Command-line reading shared by lumen-lang and both kernel binaries
+// Included by all three (next to program_files.rs, which it reads program paths with),
+// so a subcommand's arguments and a kernel's flags are taken apart the same way.
+//
+// A command line is read front to back: the program (<file... | dir | - | -e code>),
+// then flags in any order, then the program's own arguments. The flags end at `--`
+// or at the first argument the caller does not know as a flag. A flag that takes a
+// value reads the argument after it, whatever that looks like.
+
+/// Where the program source comes from
+#[allow(dead_code)] // lumen-lang passes the program on to a kernel unread
+pub enum Input {
+    /// Source files run as one program (a directory stands for its .lm files)
+    Files(Vec<String>),
+    /// Source read from standard input ("-", or no arguments with piped input)
+    Stdin,
+    /// Source text given on the command line with -e
+    Inline(String),
+}
+
+/// Whether a known flag is read alone or with the argument after it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Switch,
+    Value,
+}
+
+/// A flag read from the command line, with its value if it takes one
+#[derive(Debug, PartialEq)]
+pub struct Flag<'a> {
+    pub name: &'a str,
+    pub value: Option<&'a str>,
+}
+
+/// The arguments not read yet
+pub struct Args<'a> {
+    args: &'a [String],
+}
+
+impl<'a> Args<'a> {
+    pub fn new(args: &'a [String]) -> Self {
+        Args { args }
+    }
+
+    /// The next argument, left unread
+    pub fn peek(&self) -> Option<&'a str> {
+        self.args.first().map(String::as_str)
+    }
+
+    pub fn next(&mut self) -> Option<&'a str> {
+        let (first, rest) = self.args.split_first()?;
+        self.args = rest;
+        Some(first)
+    }
+
+    /// Read `word` if it is the next argument
+    pub fn next_if(&mut self, word: &str) -> bool {
+        let found = self.peek() == Some(word);
+        if found {
+            self.args = &self.args[1..];
+        }
+        found
+    }
+
+    /// Read `flag VALUE` if `flag` is the next argument
+    #[allow(dead_code)] // Only lumen-lang looks for one flag at a time
+    pub fn value_of(&mut self, flag: &str) -> Result<Option<&'a str>, String> {
+        if !self.next_if(flag) {
+            return Ok(None);
+        }
+        self.next().map(Some).ok_or_else(|| format!("{} requires an argument", flag))
+    }
+
+    /// Read the program at the front: -e CODE, - for standard input, or a path and any
+    /// .lm files right after it (None when no arguments are left)
+    pub fn input(&mut self) -> Result<Option<Input>, String> {
+        if self.next_if("-e") {
+            let code = self.next().ok_or("-e requires an argument")?;
+            return Ok(Some(Input::Inline(code.to_string())));
+        }
+        if self.next_if("-") {
+            return Ok(Some(Input::Stdin));
+        }
+        if self.args.is_empty() {
+            return Ok(None);
+        }
+        let paths = crate::program_files::leading_paths(self.args);
+        self.args = &self.args[paths.len()..];
+        Ok(Some(Input::Files(paths)))
+    }
+
+    /// Read the next flag, if `arity` knows it
+    /// None where the flags end: after a `--`, before an argument `arity` does not know,
+    /// or at the end of the arguments.
+    pub fn next_flag(&mut self, arity: impl Fn(&str) -> Option<Arity>) -> Option<Result<Flag<'a>, String>> {
+        if self.next_if("--") {
+            return None;
+        }
+        let name = self.peek()?;
+        let arity = arity(name)?;
+        self.args = &self.args[1..];
+        Some(match arity {
+            Arity::Switch => Ok(Flag { name, value: None }),
+            Arity::Value => self
+                .next()
+                .map(|value| Flag { name, value: Some(value) })
+                .ok_or_else(|| format!("{} requires an argument", name)),
+        })
+    }
+
+    /// The arguments not read yet
+    pub fn rest(&self) -> &'a [String] {
+        self.args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn arity(flag: &str) -> Option<Arity> {
+        match flag {
+            "--check" => Some(Arity::Switch),
+            "--lang" => Some(Arity::Value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn flags_end_at_the_first_unknown_argument_or_after_a_double_dash() {
+        let args = strings(&["-e", "print(1)", "--lang", "lumen", "--check", "--", "--check", "x"]);
+        let mut args = Args::new(&args);
+        assert!(matches!(args.input(), Ok(Some(Input::Inline(code))) if code == "print(1)"));
+        assert_eq!(args.next_flag(arity), Some(Ok(Flag { name: "--lang", value: Some("lumen") })));
+        assert_eq!(args.next_flag(arity), Some(Ok(Flag { name: "--check", value: None })));
+        assert_eq!(args.next_flag(arity), None);
+        assert_eq!(args.rest(), ["--check", "x"]);
+
+        let args = strings(&["-", "--check", "7", "--check"]);
+        let mut args = Args::new(&args);
+        assert!(matches!(args.input(), Ok(Some(Input::Stdin))));
+        assert_eq!(args.next_flag(arity), Some(Ok(Flag { name: "--check", value: None })));
+        assert_eq!(args.next_flag(arity), None);
+        assert_eq!(args.rest(), ["7", "--check"]);
+    }
+
+    #[test]
+    fn a_missing_value_names_its_flag() {
+        let args = strings(&["--lang"]);
+        assert_eq!(Args::new(&args).next_flag(arity), Some(Err("--lang requires an argument".to_string())));
+        assert_eq!(Args::new(&args).value_of("--lang"), Err("--lang requires an argument".to_string()));
+        let args = strings(&["-e"]);
+        assert!(matches!(Args::new(&args).input(), Err(e) if e == "-e requires an argument"));
+    }
+}
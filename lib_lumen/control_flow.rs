@@ -0,0 +1,206 @@
+// Shared control-flow rules for break, continue and return
+// Included by both kernels so loops and function calls treat signals identically.
+//
+// A statement finishes with a Signal. Each kernel maps its own control type onto
+// Signal and asks the rules below what the enclosing construct does with it:
+//
+//   signal     loop (while/for/until)     function call boundary
+//   Normal     next iteration             call completes
+//   Continue   next iteration             error: break/continue outside of loop
+//   Break      exit loop, then Normal     error: break/continue outside of loop
+//   Return     propagate outward          call completes with the returned value
+//
+// if/else blocks are transparent: they pass every signal outward unchanged.
+//
+// `exit status` ends the whole program. It is not a signal: it unwinds through loops and
+// calls on the error path (like error()), as LumenError::Exit, which nothing but the
+// binaries (and embedding hosts) look at; they turn it into the process exit status. An
+// error message never reads as an exit, whatever its text.
+//
+// A runtime error collects the position of each statement it leaves, in a field beside
+// its message, so the diagnostics can point at the innermost one that belongs to the user
+// program (an error raised inside a prelude function is shown at the user's call).
+
+/// How a statement or block finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Normal,
+    Break,
+    Continue,
+    Return,
+}
+
+/// What a loop does after its body finishes with a signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopStep {
+    /// Evaluate the loop condition and run the next iteration
+    Next,
+    /// Leave this loop; execution resumes after it normally
+    Exit,
+    /// Leave this loop and hand the signal to the enclosing construct
+    Propagate,
+}
+
+/// Rule for a loop body that finished with `signal`
+pub fn at_loop(signal: Signal) -> LoopStep {
+    match signal {
+        Signal::Normal | Signal::Continue => LoopStep::Next,
+        Signal::Break => LoopStep::Exit,
+        Signal::Return => LoopStep::Propagate,
+    }
+}
+
+/// Rule for a function body that finished with `signal`
+/// break and continue never cross a call boundary into the caller's loop.
+pub fn at_function(signal: Signal) -> Result<(), String> {
+    match signal {
+        Signal::Normal | Signal::Return => Ok(()),
+        Signal::Break | Signal::Continue => Err("break/continue outside of loop".to_string()),
+    }
+}
+
+/// What stops a statement or expression short of finishing: an error, or the program's `exit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LumenError {
+    /// An error, with where it happened
+    Raised(LocatedError),
+    /// `exit status`: ends the whole program with that status
+    Exit(i32),
+}
+
+impl LumenError {
+    /// The status of an `exit`, or None for an error
+    pub fn exit_status(&self) -> Option<i32> {
+        match self {
+            LumenError::Exit(status) => Some(*status),
+            LumenError::Raised(_) => None,
+        }
+    }
+
+    /// The error, for a stage that runs no `exit` (lexing, parsing)
+    pub fn into_located(self) -> LocatedError {
+        match self {
+            LumenError::Raised(error) => error,
+            LumenError::Exit(_) => LocatedError::new(self.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for LumenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LumenError::Raised(error) => write!(f, "{}", error),
+            LumenError::Exit(status) => write!(f, "exit with status {}", status),
+        }
+    }
+}
+
+impl From<String> for LumenError {
+    fn from(message: String) -> Self {
+        LumenError::Raised(LocatedError::new(message))
+    }
+}
+
+impl From<&str> for LumenError {
+    fn from(message: &str) -> Self {
+        LumenError::Raised(LocatedError::new(message))
+    }
+}
+
+/// An error message and where it happened: the token a syntax error stopped at, or the
+/// statements a runtime error left, innermost first. A position is (line, col), with
+/// col 0 when only the line is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocatedError {
+    pub message: String,
+    pub positions: Vec<(usize, usize)>,
+}
+
+impl LocatedError {
+    /// An error not yet located
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into(), positions: Vec::new() }
+    }
+
+    /// An error at `line`:`col` (line 0: past the end of the text, so not located)
+    pub fn at(message: impl Into<String>, line: usize, col: usize) -> Self {
+        let positions = if line == 0 { Vec::new() } else { vec![(line, col)] };
+        Self { message: message.into(), positions }
+    }
+}
+
+/// The message after the innermost position ("at 5:9: Division by zero"), for hosts
+/// that show an error as one line
+impl std::fmt::Display for LocatedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.positions.first() {
+            Some((line, 0)) => write!(f, "at line {}: {}", line, self.message),
+            Some((line, col)) => write!(f, "at {}:{}: {}", line, col, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Mark an error leaving the statement at `line`:`col` with that position
+/// A position already listed (recursion) is not added again, and an exit passes unchanged.
+pub fn at_statement(mut error: LumenError, line: usize, col: usize) -> LumenError {
+    if let LumenError::Raised(located) = &mut error {
+        if line != 0 && !located.positions.contains(&(line, col)) {
+            located.positions.push((line, col));
+        }
+    }
+    error
+}
+
+/// Check an `exit` operand: process statuses are 0-255
+pub fn check_exit_status(status: Option<i64>) -> Result<i32, String> {
+    match status {
+        Some(status @ 0..=255) => Ok(status as i32),
+        _ => Err("exit status must be an integer from 0 to 255".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Signal; 4] = [Signal::Normal, Signal::Break, Signal::Continue, Signal::Return];
+
+    #[test]
+    fn loop_rules_cover_every_signal() {
+        let steps: Vec<LoopStep> = ALL.iter().map(|s| at_loop(*s)).collect();
+        assert_eq!(steps, vec![LoopStep::Next, LoopStep::Exit, LoopStep::Next, LoopStep::Propagate]);
+    }
+
+    #[test]
+    fn function_boundary_stops_loop_signals() {
+        assert!(at_function(Signal::Normal).is_ok());
+        assert!(at_function(Signal::Return).is_ok());
+        assert!(at_function(Signal::Break).is_err());
+        assert!(at_function(Signal::Continue).is_err());
+    }
+
+    #[test]
+    fn only_an_exit_has_a_status() {
+        assert_eq!(LumenError::Exit(3).exit_status(), Some(3));
+        assert_eq!(LumenError::from("exit requested with status 7").exit_status(), None);
+        assert_eq!(check_exit_status(Some(255)), Ok(255));
+        assert!(check_exit_status(Some(256)).is_err());
+        assert!(check_exit_status(None).is_err());
+    }
+
+    #[test]
+    fn errors_collect_statement_positions() {
+        let inner = at_statement("Division by zero".into(), 2, 5);
+        let outer = at_statement(inner, 4, 1);
+        let LumenError::Raised(located) = &outer else { panic!("{:?}", outer) };
+        assert_eq!(located.message, "Division by zero");
+        assert_eq!(located.positions, [(2, 5), (4, 1)]);
+        assert_eq!(outer.to_string(), "at 2:5: Division by zero");
+        assert_eq!(at_statement(outer.clone(), 2, 5), outer);
+        // A message that reads like a position is no position
+        let raised = at_statement("at 4:1: boom".into(), 4, 1);
+        assert_eq!(raised, LumenError::Raised(LocatedError { message: "at 4:1: boom".to_string(), positions: vec![(4, 1)] }));
+        assert_eq!(at_statement(LumenError::Exit(3), 4, 1), LumenError::Exit(3));
+    }
+}
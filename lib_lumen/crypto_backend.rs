@@ -0,0 +1,329 @@
+// The `crypto` extern backend: cryptographic hashes, shared by both kernels
+//
+//   extern("crypto:sha256", data)   SHA-256 of data, as 64 lowercase hex digits
+//   extern("crypto:md5", data)      MD5, as 32 hex digits
+//   extern("crypto:blake3", data)   BLAKE3 (its default 32-byte hash), as 64 hex digits
+//
+// `data` is a STRING (hashed as its UTF-8) or BYTES, so a program can checksum a file or
+// key a cache by content:
+//
+//   sum = extern("crypto:sha256", extern("fs:read", "release.tar"))
+//   key = extern("crypto:blake3", source)
+//
+// MD5 is broken for security and is here to match the checksums other tools publish. The
+// hashes are written out here (as the json and random backends are) rather than taken
+// from a crate, so the stream kernel has no dependency for them; a kernel passes a STRING's
+// bytes or a BYTES value as Data.
+
+use std::fmt::Write;
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "crypto:" in a selector
+pub const CAPABILITIES: &[&str] = &["blake3", "md5", "sha256"];
+
+/// An argument, as the backend sees it
+pub enum Arg<'a> {
+    /// A STRING's UTF-8 or a BYTES value
+    Data(&'a [u8]),
+    Other,
+}
+
+/// Run capability `name` of the backend: the hash of its one argument, in hex
+pub fn call(name: &str, args: &[Arg]) -> Result<String, ExternError> {
+    if !CAPABILITIES.contains(&name) {
+        return Err(ExternError::new("unavailable", format!("crypto has no capability '{}'", name)));
+    }
+    let data = match args {
+        [Arg::Data(data)] => data,
+        [_] => return Err(ExternError::invalid_argument(format!("crypto:{} expects a STRING or BYTES", name))),
+        _ => return Err(ExternError::invalid_argument(format!("crypto:{} expects 1 argument, got {}", name, args.len()))),
+    };
+    let digest = match name {
+        "sha256" => sha256(data).to_vec(),
+        "md5" => md5(data).to_vec(),
+        _ => blake3(data).to_vec(),
+    };
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    Ok(hex)
+}
+
+/// `data` with the Merkle–Damgård padding of SHA-256 and MD5: a 1 bit, zeros, and the bit
+/// length (big- or little-endian) to fill the last 64-byte block
+fn padded(data: &[u8], big_endian: bool) -> Vec<u8> {
+    let bits = (data.len() as u64).wrapping_mul(8);
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&if big_endian { bits.to_be_bytes() } else { bits.to_le_bytes() });
+    message
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The initial hash of SHA-256, also BLAKE3's IV
+const SHA256_IV: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+/// SHA-256 (FIPS 180-4)
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = SHA256_IV;
+    for block in padded(data, true).chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hash;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(SHA256_K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, add) in hash.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(add);
+        }
+    }
+    let mut digest = [0u8; 32];
+    for (out, word) in digest.chunks_mut(4).zip(hash) {
+        out.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Per-round shifts of MD5
+const MD5_S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// MD5 (RFC 1321)
+fn md5(data: &[u8]) -> [u8; 16] {
+    // The constants are floor(2^32 * |sin(i + 1)|)
+    let k: Vec<u32> = (0..64).map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32).collect();
+    let mut hash: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for block in padded(data, false).chunks(64) {
+        let mut m = [0u32; 16];
+        for (i, word) in block.chunks(4).enumerate() {
+            m[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = hash;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a.wrapping_add(f).wrapping_add(k[i]).wrapping_add(m[g]).rotate_left(MD5_S[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (word, add) in hash.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(add);
+        }
+    }
+    let mut digest = [0u8; 16];
+    for (out, word) in digest.chunks_mut(4).zip(hash) {
+        out.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+// BLAKE3: the input in 1024-byte chunks, each compressed 64 bytes at a time into a chaining
+// value, and the chunks' values merged pairwise up a binary tree whose left subtrees are
+// full; the last compression (of the only chunk, or of the root parent) is marked ROOT.
+
+const BLAKE3_CHUNK: usize = 1024;
+const BLAKE3_BLOCK: usize = 64;
+const CHUNK_START: u32 = 1;
+const CHUNK_END: u32 = 2;
+const PARENT: u32 = 4;
+const ROOT: u32 = 8;
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// The quarter-round of BLAKE3's compression function
+fn g(state: &mut [u32; 16], [a, b, c, d]: [usize; 4], mx: u32, my: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+/// Compress one block into the chaining value `cv`; the first 8 words are the new value
+fn compress(cv: &[u32; 8], block: &[u32; 16], counter: u64, block_len: u32, flags: u32) -> [u32; 16] {
+    let mut state = [0u32; 16];
+    state[..8].copy_from_slice(cv);
+    state[8..12].copy_from_slice(&SHA256_IV[..4]);
+    state[12] = counter as u32;
+    state[13] = (counter >> 32) as u32;
+    state[14] = block_len;
+    state[15] = flags;
+    let mut m = *block;
+    for round in 0..7 {
+        g(&mut state, [0, 4, 8, 12], m[0], m[1]);
+        g(&mut state, [1, 5, 9, 13], m[2], m[3]);
+        g(&mut state, [2, 6, 10, 14], m[4], m[5]);
+        g(&mut state, [3, 7, 11, 15], m[6], m[7]);
+        g(&mut state, [0, 5, 10, 15], m[8], m[9]);
+        g(&mut state, [1, 6, 11, 12], m[10], m[11]);
+        g(&mut state, [2, 7, 8, 13], m[12], m[13]);
+        g(&mut state, [3, 4, 9, 14], m[14], m[15]);
+        if round < 6 {
+            m = MSG_PERMUTATION.map(|i| m[i]);
+        }
+    }
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= cv[i];
+    }
+    state
+}
+
+/// A block's bytes as little-endian words, zero-padded
+fn block_words(bytes: &[u8]) -> [u32; 16] {
+    let mut padded = [0u8; BLAKE3_BLOCK];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    let mut words = [0u32; 16];
+    for (word, four) in words.iter_mut().zip(padded.chunks(4)) {
+        *word = u32::from_le_bytes([four[0], four[1], four[2], four[3]]);
+    }
+    words
+}
+
+/// The last compression of a chunk or parent, held back until it is known whether it is the root
+struct Output {
+    cv: [u32; 8],
+    block: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        let words = compress(&self.cv, &self.block, self.counter, self.block_len, self.flags);
+        words[..8].try_into().expect("8 words")
+    }
+
+    fn root_hash(&self) -> [u8; 32] {
+        let words = compress(&self.cv, &self.block, 0, self.block_len, self.flags | ROOT);
+        let mut hash = [0u8; 32];
+        for (out, word) in hash.chunks_mut(4).zip(words) {
+            out.copy_from_slice(&word.to_le_bytes());
+        }
+        hash
+    }
+}
+
+/// Chunk number `counter` (at most 1024 bytes, empty only for empty input)
+fn chunk_output(chunk: &[u8], counter: u64) -> Output {
+    let mut cv = SHA256_IV;
+    let blocks: Vec<&[u8]> = if chunk.is_empty() { vec![chunk] } else { chunk.chunks(BLAKE3_BLOCK).collect() };
+    let last = blocks.len() - 1;
+    for (i, block) in blocks[..last].iter().enumerate() {
+        let start = if i == 0 { CHUNK_START } else { 0 };
+        let words = compress(&cv, &block_words(block), counter, BLAKE3_BLOCK as u32, start);
+        cv = words[..8].try_into().expect("8 words");
+    }
+    Output {
+        cv,
+        block: block_words(blocks[last]),
+        counter,
+        block_len: blocks[last].len() as u32,
+        flags: if last == 0 { CHUNK_START | CHUNK_END } else { CHUNK_END },
+    }
+}
+
+/// The subtree over `input`, whose first chunk is number `counter`
+fn subtree(input: &[u8], counter: u64) -> Output {
+    if input.len() <= BLAKE3_CHUNK {
+        return chunk_output(input, counter);
+    }
+    // The left subtree takes the largest power of two of chunks that leaves some to the right
+    let chunks = input.len().div_ceil(BLAKE3_CHUNK);
+    let left_chunks = 1 << (usize::BITS - 1 - (chunks - 1).leading_zeros());
+    let (left, right) = input.split_at(left_chunks * BLAKE3_CHUNK);
+    let mut block = [0u32; 16];
+    block[..8].copy_from_slice(&subtree(left, counter).chaining_value());
+    block[8..].copy_from_slice(&subtree(right, counter + left_chunks as u64).chaining_value());
+    Output { cv: SHA256_IV, block, counter: 0, block_len: BLAKE3_BLOCK as u32, flags: PARENT }
+}
+
+/// BLAKE3's default 32-byte hash
+fn blake3(data: &[u8]) -> [u8; 32] {
+    subtree(data, 0).root_hash()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(name: &str, data: &[u8]) -> String {
+        call(name, &[Arg::Data(data)]).unwrap()
+    }
+
+    #[test]
+    fn hashes_match_the_published_test_vectors() {
+        assert_eq!(hash("sha256", b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hash("sha256", b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            hash("sha256", b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(hash("md5", b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hash("md5", b"The quick brown fox jumps over the lazy dog"), "9e107d9d372bb6826bd81d3542a419d6");
+        assert_eq!(hash("blake3", b""), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        assert_eq!(hash("blake3", b"abc"), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+    }
+
+    #[test]
+    fn blake3_builds_a_tree_over_chunks() {
+        // The official vectors' input: bytes counting 0, 1, ..., 250, 0, 1, ...
+        let input: Vec<u8> = (0..2049).map(|i| (i % 251) as u8).collect();
+        assert_eq!(hash("blake3", &input[..1]), "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213");
+        assert_eq!(hash("blake3", &input[..1024]), "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7");
+        assert_eq!(hash("blake3", &input[..1025]), "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444");
+    }
+
+    #[test]
+    fn takes_one_string_or_bytes() {
+        assert_eq!(call("sha256", &[Arg::Other]), Err(ExternError::invalid_argument("crypto:sha256 expects a STRING or BYTES")));
+        assert_eq!(call("md5", &[]), Err(ExternError::invalid_argument("crypto:md5 expects 1 argument, got 0")));
+        assert!(call("sha1", &[Arg::Data(b"")]).is_err());
+    }
+}
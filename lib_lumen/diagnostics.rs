@@ -0,0 +1,184 @@
+// Error rendering shared by both kernel binaries
+// Included like host_config.rs so both print diagnostics the same way:
+//
+//   LumenError: Expected '}'
+//    --> program.lm:3:11
+//     |
+//   3 |     print(x
+//     |           ^
+//
+// An error comes with its positions as (line, column): a syntax error the token it stopped
+// at, a runtime error the statements it unwound through, innermost first. The first one in
+// the user program is shown; errors located only in the prelude, or nowhere, are printed
+// without an excerpt. The message itself is never searched for a location. Line numbers
+// count from the start of the text the kernel parsed: the prelude, if any, then each
+// program file in turn.
+//
+// ANSI colors are used only when stderr is a terminal and NO_COLOR is unset or empty.
+//
+// A parser that recovers from errors reports all of them, one after another.
+// Warnings are printed the same way, in yellow.
+//
+// Exit statuses: a program that does not parse exits with 2, an uncaught runtime error
+// with 1, and `exit status` with its own status.
+
+use std::io::{self, IsTerminal};
+
+/// The user program an error is reported against
+pub struct Sources<'a> {
+    /// (name shown after -->, text) of each program file, in the order they were joined
+    pub files: Vec<(&'a str, &'a str)>,
+    /// Line of the parsed text where the first file begins (1 when there is no prelude)
+    pub first_line: usize,
+}
+
+impl<'a> Sources<'a> {
+    /// The file, line within it, and line text for a line of the parsed text
+    fn find(&self, line: usize) -> Option<(&'a str, usize, &'a str)> {
+        let mut line = line.checked_sub(self.first_line)?;
+        for (name, text) in &self.files {
+            let count = text.lines().count();
+            if line < count {
+                return text.lines().nth(line).map(|t| (*name, line + 1, t));
+            }
+            line -= count;
+        }
+        None
+    }
+}
+
+/// Process exit status for a program that does not lex or parse
+pub const EXIT_SYNTAX_ERROR: i32 = 2;
+/// Process exit status for an uncaught runtime error
+pub const EXIT_RUNTIME_ERROR: i32 = 1;
+
+const RED: &str = "\x1b[1;31m";
+#[allow(dead_code)] // Only the stream kernel reports warnings
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Print an error to stderr, at the first of `positions` in the user program
+/// A syntax error has the position of the token it stopped at; a runtime error those of
+/// the statements it left, innermost first. `kind` prefixes the header (e.g. "RuntimeError").
+pub fn report_at(kind: &str, message: &str, positions: &[(usize, usize)], sources: &Sources) {
+    eprint!("{}", render_at(kind, message, positions, sources, use_color()));
+}
+
+/// Print a warning to stderr, located like an error
+#[allow(dead_code)] // Only the stream kernel reports warnings
+pub fn warn(message: &str, positions: &[(usize, usize)], sources: &Sources) {
+    eprint!("{}", render_in(YELLOW, "", message, locate(positions, sources), sources, use_color()));
+}
+
+fn use_color() -> bool {
+    let disabled = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !disabled && io::stderr().is_terminal()
+}
+
+/// Format an error; see `report_at`
+pub fn render_at(kind: &str, message: &str, positions: &[(usize, usize)], sources: &Sources, color: bool) -> String {
+    render_in(RED, kind, message, locate(positions, sources), sources, color)
+}
+
+/// The first of `positions` in the user program; column 0 stands for the whole line
+fn locate(positions: &[(usize, usize)], sources: &Sources) -> Option<(usize, Option<usize>)> {
+    let &(line, col) = positions.iter().find(|(line, _)| sources.find(*line).is_some())?;
+    Some((line, (col != 0).then_some(col)))
+}
+
+/// Format a message with its header, and carets in `style` at `location` if it is in the user program
+fn render_in(
+    style: &str,
+    kind: &str,
+    message: &str,
+    location: Option<(usize, Option<usize>)>,
+    sources: &Sources,
+    color: bool,
+) -> String {
+    let paint = |style: &str, text: &str| {
+        if color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+
+    let header = if kind.is_empty() { message.to_string() } else { format!("{}: {}", kind, message) };
+    let mut out = format!("{}\n", paint(style, &header));
+
+    // Only lines of the user program get an excerpt
+    let Some((name, line, text, col)) =
+        location.and_then(|(line, col)| sources.find(line).map(|(name, line, text)| (name, line, text, col)))
+    else {
+        return out;
+    };
+
+    let gutter = " ".repeat(line.to_string().len());
+    let position = match col {
+        Some(col) => format!("{}:{}:{}", name, line, col),
+        None => format!("{}:{}", name, line),
+    };
+    out.push_str(&format!("{}{} {}\n", gutter, paint(BLUE, "-->"), position));
+    out.push_str(&format!("{} {}\n", gutter, paint(BLUE, "|")));
+    out.push_str(&format!("{} {} {}\n", paint(BLUE, &line.to_string()), paint(BLUE, "|"), paint(BOLD, text)));
+    if let Some(col) = col.filter(|c| *c >= 1) {
+        // Keep tabs so the caret lines up under the same character
+        let indent: String = text
+            .chars()
+            .take(col - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let word = text
+            .chars()
+            .skip(col - 1)
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .count();
+        let carets = "^".repeat(word.max(1));
+        out.push_str(&format!("{} {} {}{}\n", gutter, paint(BLUE, "|"), indent, paint(style, &carets)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_takes_location_from_positions_only() {
+        let sources = Sources { files: vec![("prog.json", "{\n  \"a\": ?\n}\n")], first_line: 1 };
+        // A message that reads like a location is shown as it is
+        let out = render_at("ParseError", "Expected value at line 1 column 6", &[], &sources, false);
+        assert_eq!(out, "ParseError: Expected value at line 1 column 6\n");
+        // Column 0 marks the whole line
+        let out = render_at("IndentationError", "Indentation mismatch", &[(2, 0)], &sources, false);
+        assert_eq!(out, "IndentationError: Indentation mismatch\n --> prog.json:2\n  |\n2 |   \"a\": ?\n");
+    }
+
+    #[test]
+    fn render_points_at_user_line() {
+        let sources = Sources { files: vec![("prog.lm", "x = 1\nprint(total)\n")], first_line: 11 };
+        let out = render_at("ParseError", "Undefined", &[(12, 7)], &sources, false);
+        assert_eq!(out, "ParseError: Undefined\n --> prog.lm:2:7\n  |\n2 | print(total)\n  |       ^^^^^\n");
+        // Prelude locations get the header only
+        assert_eq!(render_at("LumenError", "bad", &[(4, 1)], &sources, false), "LumenError: bad\n");
+    }
+
+    #[test]
+    fn render_points_at_innermost_user_statement() {
+        let sources = Sources { files: vec![("prog.lm", "fn f(x)\n    return sqrt(x)\nprint(f(-1))\n")], first_line: 11 };
+        let out = render_at("LumenError", "negative argument", &[(3, 5), (12, 5), (13, 1)], &sources, false);
+        assert!(out.starts_with("LumenError: negative argument\n --> prog.lm:2:5\n"), "{}", out);
+        // The message is shown as raised, whatever it reads like
+        let out = render_at("LumenError", "at 1:1: boom", &[(13, 1)], &sources, false);
+        assert!(out.starts_with("LumenError: at 1:1: boom\n --> prog.lm:3:1\n"), "{}", out);
+    }
+
+    #[test]
+    fn render_finds_file_of_joined_program() {
+        let sources = Sources { files: vec![("a.lm", "x = 1\n"), ("b.lm", "y = 2\nz = )\n")], first_line: 1 };
+        let out = render_at("ParseError", "Unexpected token: )", &[(3, 5)], &sources, false);
+        assert!(out.contains(" --> b.lm:2:5\n"), "{}", out);
+    }
+}
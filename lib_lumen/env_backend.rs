@@ -0,0 +1,78 @@
+// The `env` extern backend: the environment variables of the process, shared by both kernels
+//
+//   extern("env:get", name)   the value of variable `name` as a STRING, or null when it is not
+//                             set (or its value is not valid UTF-8)
+//   extern("env:vars")        every variable as a MAP of STRINGs, in name order (variables
+//                             whose name or value is not valid UTF-8 are left out)
+//
+// so a program reads its configuration the way a command-line tool would:
+//
+//   home = extern("env:get", "HOME")
+//   if home == null
+//       home = "."
+//
+// Reading is all: the program cannot set a variable of the host. The variables may hold
+// secrets, so a sandboxed run reaches them only with --allow-env (--allow-env=get lets
+// it look up a name it knows but not list them all).
+
+use std::env;
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "env:" in a selector
+pub const CAPABILITIES: &[&str] = &["get", "vars"];
+
+/// What a capability returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Text(String),
+    Null,
+    /// (name, value) pairs in name order
+    Vars(Vec<(String, String)>),
+}
+
+/// Run capability `name` of the backend; `args` are its arguments, Some for a STRING and
+/// None for any other value
+pub fn call(name: &str, args: &[Option<&str>]) -> Result<Outcome, ExternError> {
+    match (name, args) {
+        ("get", [Some(variable)]) => Ok(get(variable)?.map_or(Outcome::Null, Outcome::Text)),
+        ("get", _) => Err(ExternError::invalid_argument("env:get expects the name of a variable")),
+        ("vars", []) => Ok(Outcome::Vars(vars())),
+        ("vars", _) => Err(ExternError::invalid_argument(format!("env:vars expects 0 arguments, got {}", args.len()))),
+        _ => Err(ExternError::new("unavailable", format!("env has no capability '{}'", name))),
+    }
+}
+
+fn get(variable: &str) -> Result<Option<String>, ExternError> {
+    // env::var panics on these instead of answering
+    if variable.is_empty() || variable.contains(['=', '\0']) {
+        return Err(ExternError::invalid_argument(format!("env:get: '{}' is not the name of a variable", variable)));
+    }
+    Ok(env::var(variable).ok())
+}
+
+fn vars() -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect();
+    vars.sort();
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_variables_of_the_process() {
+        let path = env::var("PATH").unwrap();
+        assert_eq!(call("get", &[Some("PATH")]), Ok(Outcome::Text(path.clone())));
+        assert_eq!(call("get", &[Some("LUMEN_SURELY_NOT_SET")]), Ok(Outcome::Null));
+        assert!(matches!(call("vars", &[]), Ok(Outcome::Vars(vars)) if vars.contains(&("PATH".to_string(), path))));
+
+        assert!(call("get", &[Some("A=B")]).is_err());
+        assert_eq!(call("get", &[None]), Err(ExternError::invalid_argument("env:get expects the name of a variable")));
+        assert!(call("vars", &[None]).is_err());
+        assert!(call("set", &[]).is_err());
+    }
+}
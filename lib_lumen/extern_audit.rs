@@ -0,0 +1,153 @@
+// An audit log of extern calls, shared by both kernels
+//
+//   --audit FILE     append a line to FILE for every extern call ("-": standard error)
+//
+// Each line is a JSON object, so a host running third-party code can review (or feed to
+// its own tools) what the program touched:
+//
+//   {"at":1760500000123,"selector":"fs:read","args":["\"notes.txt\""],"micros":48,"ok":true}
+//   {"at":1760500000125,"selector":"fs:write","args":["\"/etc/passwd\"","\"x\""],"micros":3,
+//    "ok":false,"code":"denied","error":"extern \"fs:write\" is not allowed in this run ..."}
+//
+//   at        when the call started, in milliseconds since 1970 (UTC)
+//   selector  the selector as the program wrote it
+//   args      each argument as the kernel prints it (repr), cut at ARG_CHARS characters
+//   micros    how long the call took
+//   ok        whether it succeeded; if not, code and error are the ExternError's
+//
+// Calls the run's policy denies are logged too. Mocked calls reach no host capability and
+// are not. An extern_async call is logged once it finishes. A line that cannot be written
+// (a full disk) is dropped rather than stopping the program.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Characters of an argument's repr kept in the log
+pub const ARG_CHARS: usize = 80;
+
+/// Where a run's extern calls are logged (clones share the destination)
+#[derive(Clone)]
+pub struct ExternAudit {
+    sink: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl ExternAudit {
+    /// A log written to `sink` (an embedding host's own file or buffer)
+    pub fn new(sink: impl Write + Send + 'static) -> Self {
+        Self { sink: Arc::new(Mutex::new(Box::new(sink))) }
+    }
+
+    /// A log appended to the file at `path` (created if missing), or standard error for "-"
+    pub fn open(path: &str) -> Result<Self, String> {
+        if path == "-" {
+            return Ok(Self::new(io::stderr()));
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open audit log {}: {}", path, e))?;
+        Ok(Self::new(file))
+    }
+
+    /// Log one call: started at `started`, it took `took` and failed with `failure` (code,
+    /// message) or succeeded
+    pub fn record(&self, selector: &str, args: &[String], started: SystemTime, took: Duration, failure: Option<(&str, &str)>) {
+        let at = started.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis());
+        let args: Vec<String> = args.iter().map(|arg| quote(&cut(arg))).collect();
+        let mut line = format!(
+            "{{\"at\":{},\"selector\":{},\"args\":[{}],\"micros\":{},\"ok\":{}",
+            at,
+            quote(selector),
+            args.join(","),
+            took.as_micros(),
+            failure.is_none()
+        );
+        if let Some((code, message)) = failure {
+            line.push_str(&format!(",\"code\":{},\"error\":{}", quote(code), quote(message)));
+        }
+        line.push_str("}\n");
+        let mut sink = self.sink.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = sink.write_all(line.as_bytes()).and_then(|_| sink.flush());
+    }
+}
+
+impl fmt::Debug for ExternAudit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ExternAudit")
+    }
+}
+
+/// Two audits are equal when they write to the same place
+impl PartialEq for ExternAudit {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.sink, &other.sink)
+    }
+}
+
+impl Eq for ExternAudit {}
+
+/// `text`, cut to ARG_CHARS characters
+fn cut(text: &str) -> String {
+    match text.char_indices().nth(ARG_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// `text` as a JSON string
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sink the test can read back
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn records_one_json_line_per_call() {
+        let buffer = Buffer::default();
+        let audit = ExternAudit::new(buffer.clone());
+        let started = UNIX_EPOCH + Duration::from_millis(1500);
+        audit.record("fs:read", &["\"a\nb\"".to_string()], started, Duration::from_micros(42), None);
+        audit.record("net:connect", &["x".repeat(100)], started, Duration::ZERO, Some(("denied", "not \"allowed\"")));
+        let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines[0], r#"{"at":1500,"selector":"fs:read","args":["\"a\nb\""],"micros":42,"ok":true}"#);
+        let cut = format!("{}...", "x".repeat(ARG_CHARS));
+        assert_eq!(lines[1], format!(r#"{{"at":1500,"selector":"net:connect","args":["{}"],"micros":0,"ok":false,"code":"denied","error":"not \"allowed\""}}"#, cut));
+        assert_eq!(audit.clone(), audit);
+        assert_ne!(ExternAudit::new(io::sink()), audit);
+    }
+}
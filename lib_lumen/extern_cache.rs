@@ -0,0 +1,95 @@
+// Opt-in caching of extern results, shared by both kernels
+//
+// A program marks a selector cacheable, and from then on a call of it with the same
+// arguments as an earlier one gets that call's value back without reaching the host:
+//
+//   cache_extern("env:get")          serve repeated env:get calls from the cache
+//   clear_extern_cache("fs:read")    forget fs:read's results, e.g. after writing the file
+//   clear_extern_cache()             forget every cached result
+//
+// Selectors are matched as written ("fs:read" and "fs|mem:read" are cached apart) and
+// arguments by value. Only successes are kept: a call that failed runs again next time.
+// The cache lasts for the run and is not bounded, so it suits calls of a few distinct
+// arguments (configuration, constant files), not every read of a program.
+//
+// extern and extern_try use the cache; extern_async always makes its call. A mock shadows
+// a cached result as it shadows the capability, and a call served from the cache reaches
+// no host capability, so --audit does not log it.
+
+use std::collections::{HashMap, HashSet};
+
+/// Results of the cacheable selectors by (selector, argument key)
+#[derive(Debug, Clone)]
+pub struct ExternCache<V> {
+    cacheable: HashSet<String>,
+    results: HashMap<(String, String), V>,
+}
+
+impl<V: Clone> Default for ExternCache<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Clone> ExternCache<V> {
+    /// A cache with no cacheable selector
+    pub fn new() -> Self {
+        ExternCache { cacheable: HashSet::new(), results: HashMap::new() }
+    }
+
+    /// Keep the results of `selector` from now on
+    pub fn mark(&mut self, selector: &str) {
+        self.cacheable.insert(selector.to_string());
+    }
+
+    pub fn is_cacheable(&self, selector: &str) -> bool {
+        self.cacheable.contains(selector)
+    }
+
+    /// The value an earlier call of `selector` with arguments `key` gave
+    pub fn get(&self, selector: &str, key: &str) -> Option<V> {
+        self.results.get(&(selector.to_string(), key.to_string())).cloned()
+    }
+
+    /// Keep `value` as the result of `selector` with arguments `key`, if the selector is cacheable
+    pub fn insert(&mut self, selector: &str, key: String, value: V) {
+        if self.is_cacheable(selector) {
+            self.results.insert((selector.to_string(), key), value);
+        }
+    }
+
+    /// Drop the results of `selector`, or of every selector (they stay cacheable)
+    pub fn clear(&mut self, selector: Option<&str>) {
+        match selector {
+            None => self.results.clear(),
+            Some(selector) => self.results.retain(|(cached, _), _| cached != selector),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_marked_selectors_until_cleared() {
+        let mut cache = ExternCache::new();
+        cache.insert("env:get", "HOME".to_string(), 1);
+        assert_eq!(cache.get("env:get", "HOME"), None);
+
+        cache.mark("env:get");
+        cache.mark("fs:read");
+        cache.insert("env:get", "HOME".to_string(), 1);
+        cache.insert("fs:read", "a.txt".to_string(), 2);
+        assert_eq!(cache.get("env:get", "HOME"), Some(1));
+        assert_eq!(cache.get("env:get", "PATH"), None);
+        assert_eq!(cache.get("fs|mem:read", "a.txt"), None);
+
+        cache.clear(Some("fs:read"));
+        assert_eq!(cache.get("fs:read", "a.txt"), None);
+        assert_eq!(cache.get("env:get", "HOME"), Some(1));
+        cache.clear(None);
+        assert_eq!(cache.get("env:get", "HOME"), None);
+        assert!(cache.is_cacheable("env:get"));
+    }
+}
@@ -0,0 +1,131 @@
+// The error of a failed extern call, shared by both kernels
+//
+//   code        what went wrong, as a word a program can branch on: "not_found",
+//               "permission_denied", "timed_out", "invalid_argument", "unavailable" (no
+//               capability answers the selector), "denied" (the run's policy), "failed", ...
+//   message     the text `extern` raises ("fs:read: notes.txt: No such file or directory ...")
+//   retriable   whether the same call may succeed if made again (a timeout, a reset connection)
+//   errno       the operating system's error number, when the host reported one
+//   exit        the status, when a function of the program a capability called back ran
+//               `exit`: the call ends the program instead of failing (not in the MAP)
+//
+// `extern` raises the message as before; `extern_try` returns the whole error as a MAP with
+// these four keys, so a program can retry, fall back or report. A backend returning a plain
+// String error gets code "failed".
+
+use std::fmt;
+use std::io;
+
+/// A failed extern call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternError {
+    pub code: String,
+    pub message: String,
+    pub retriable: bool,
+    pub errno: Option<i32>,
+    pub exit: Option<i32>,
+}
+
+impl ExternError {
+    /// An error with `code`, not retriable and without an errno
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self { code: code.to_string(), message: message.into(), retriable: false, errno: None, exit: None }
+    }
+
+    /// Arguments the capability cannot take
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new("invalid_argument", message)
+    }
+
+    /// A call that took longer than its timeout (see extern_timeout.rs); it may succeed if made again
+    pub fn timed_out(message: impl Into<String>) -> Self {
+        Self { retriable: true, ..Self::new("timed_out", message) }
+    }
+
+    /// An I/O failure, coded by its kind, with the host's errno
+    pub fn io(message: impl Into<String>, error: &io::Error) -> Self {
+        use io::ErrorKind::*;
+        let (code, retriable) = match error.kind() {
+            NotFound => ("not_found", false),
+            PermissionDenied => ("permission_denied", false),
+            AlreadyExists => ("already_exists", false),
+            ConnectionRefused => ("connection_refused", false),
+            ConnectionReset => ("connection_reset", true),
+            ConnectionAborted => ("connection_aborted", true),
+            NotConnected => ("not_connected", false),
+            AddrInUse => ("address_in_use", false),
+            AddrNotAvailable => ("address_not_available", false),
+            BrokenPipe => ("broken_pipe", false),
+            WouldBlock => ("would_block", true),
+            TimedOut => ("timed_out", true),
+            Interrupted => ("interrupted", true),
+            InvalidInput => ("invalid_argument", false),
+            InvalidData => ("invalid_data", false),
+            UnexpectedEof => ("unexpected_eof", false),
+            Unsupported => ("unsupported", false),
+            _ => ("io", false),
+        };
+        Self { code: code.to_string(), message: message.into(), retriable, errno: error.raw_os_error(), exit: None }
+    }
+}
+
+impl fmt::Display for ExternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for ExternError {
+    fn from(message: String) -> Self {
+        Self::new("failed", message)
+    }
+}
+
+impl From<&str> for ExternError {
+    fn from(message: &str) -> Self {
+        Self::new("failed", message)
+    }
+}
+
+/// `extern` raises the message
+impl From<ExternError> for String {
+    fn from(error: ExternError) -> Self {
+        error.message
+    }
+}
+
+/// `extern` raises the message, or ends the program if a function it called back ran `exit`
+impl From<ExternError> for crate::kernel::control_flow::LumenError {
+    fn from(error: ExternError) -> Self {
+        match error.exit {
+            Some(status) => Self::Exit(status),
+            None => Self::from(error.message),
+        }
+    }
+}
+
+/// What a call back into the program failed with
+impl From<crate::kernel::control_flow::LumenError> for ExternError {
+    fn from(error: crate::kernel::control_flow::LumenError) -> Self {
+        match error {
+            crate::kernel::control_flow::LumenError::Raised(error) => Self::new("failed", error.message),
+            exit => Self { exit: exit.exit_status(), ..Self::new("failed", exit.to_string()) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_keep_their_kind_and_errno() {
+        let missing = io::Error::from_raw_os_error(2);
+        let error = ExternError::io("fs:read: notes.txt: gone", &missing);
+        assert_eq!((error.code.as_str(), error.retriable, error.errno), ("not_found", false, Some(2)));
+        let timeout = ExternError::io("net:recv: slow", &io::Error::from(io::ErrorKind::TimedOut));
+        assert_eq!((timeout.code.as_str(), timeout.retriable, timeout.errno), ("timed_out", true, None));
+        assert_eq!(ExternError::from("bad".to_string()).code, "failed");
+        assert_eq!(String::from(error), "fs:read: notes.txt: gone");
+    }
+}
@@ -0,0 +1,140 @@
+// Which extern capabilities a run may reach, shared by both kernels
+//
+//   --allow-BACKEND            every capability of the backend (--allow-net, --allow-io, ...)
+//   --allow-BACKEND=CAP,...    only these capabilities of it (--allow-net=connect,send,recv)
+//   --allow-fs=read            fs:read, fs:exists and fs:list_dir
+//   --allow-fs=write           fs:write, fs:append, fs:remove and fs:open (which creates files)
+//   --deny-all                 no backend at all, unless another flag allows it
+//
+// Without any of these flags every capability is reachable. With one or more, the run is
+// sandboxed: only the backends (and capabilities) the --allow flags name are, so a host
+// can run untrusted code with, say, `--allow-fs=read --allow-json`. Flags add up, in any
+// order. Capabilities without a backend (print_native, debug_info, value_type) only write
+// to stdout or look at a value, and stay reachable.
+//
+// A denied capability is as good as missing: extern_available() is false for it, FEATURES
+// does not list it and a fallback selector moves past it, but calling it is an error that
+// names the flag that would allow it. Mocks are program code and are never denied.
+
+/// The --allow-fs=... names that stand for several fs capabilities
+const FS_GROUPS: &[(&str, &[&str])] = &[("read", &["read", "exists", "list_dir"]), ("write", &["write", "append", "remove", "open"])];
+
+/// Allowed capabilities of a run (the default allows every one)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExternPolicy {
+    /// None: no flag was given, so everything is allowed; otherwise each allowed backend with
+    /// its allowed capabilities (None: all of them)
+    sandbox: Option<Vec<(String, Option<Vec<String>>)>>,
+}
+
+impl ExternPolicy {
+    /// Whether `flag` is --deny-all or an --allow-BACKEND[=CAPS] flag
+    pub fn is_flag(flag: &str) -> bool {
+        flag == "--deny-all" || flag.starts_with("--allow-")
+    }
+
+    /// Apply a --deny-all or --allow-BACKEND[=CAPS] flag; None if `flag` is neither
+    pub fn apply_flag(&mut self, flag: &str) -> Option<Result<(), String>> {
+        if flag == "--deny-all" {
+            self.sandbox.get_or_insert_with(Vec::new);
+            return Some(Ok(()));
+        }
+        let rule = flag.strip_prefix("--allow-")?;
+        let (backend, capabilities) = match rule.split_once('=') {
+            Some((backend, list)) => (backend, Some(list)),
+            None => (rule, None),
+        };
+        if backend.is_empty() || !backend.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Some(Err(format!("{} must name a backend, as in --allow-net", flag)));
+        }
+        let capabilities = match capabilities {
+            None => None,
+            Some(list) => {
+                let mut names = Vec::new();
+                for name in list.split(',') {
+                    if name.is_empty() {
+                        return Some(Err(format!("{} lists an empty capability", flag)));
+                    }
+                    match FS_GROUPS.iter().find(|(group, _)| backend == "fs" && *group == name) {
+                        Some((_, members)) => names.extend(members.iter().map(|m| m.to_string())),
+                        None => names.push(name.to_string()),
+                    }
+                }
+                Some(names)
+            }
+        };
+        let sandbox = self.sandbox.get_or_insert_with(Vec::new);
+        match sandbox.iter_mut().find(|(name, _)| name == backend) {
+            Some((_, allowed)) => match (allowed.as_mut(), capabilities) {
+                (Some(allowed), Some(names)) => allowed.extend(names),
+                _ => *allowed = None,
+            },
+            None => sandbox.push((backend.to_string(), capabilities)),
+        }
+        Some(Ok(()))
+    }
+
+    /// Whether the run may reach capability `capability` of `backend` (None: no backend)
+    pub fn allows(&self, backend: Option<&str>, capability: &str) -> bool {
+        let (Some(sandbox), Some(backend)) = (&self.sandbox, backend) else {
+            return true;
+        };
+        sandbox.iter().any(|(name, allowed)| {
+            name == backend && allowed.as_ref().is_none_or(|allowed| allowed.iter().any(|c| c == capability))
+        })
+    }
+
+    /// `allows` for a whole selector, "capability" or "backend:capability"
+    pub fn allows_selector(&self, selector: &str) -> bool {
+        match selector.split_once(':') {
+            Some((backend, capability)) => self.allows(Some(backend), capability),
+            None => self.allows(None, selector),
+        }
+    }
+}
+
+/// The error for calling a capability the policy denies
+pub fn denied(backend: &str, capability: &str) -> String {
+    let group = FS_GROUPS.iter().find(|(_, members)| backend == "fs" && members.contains(&capability));
+    let flag = match group {
+        Some((group, _)) => format!("--allow-fs={}", group),
+        None => format!("--allow-{}", backend),
+    };
+    format!("extern \"{}:{}\" is not allowed in this run (run with {} to allow it)", backend, capability, flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(flags: &[&str]) -> ExternPolicy {
+        let mut policy = ExternPolicy::default();
+        for flag in flags {
+            policy.apply_flag(flag).unwrap().unwrap();
+        }
+        policy
+    }
+
+    #[test]
+    fn allow_flags_whitelist_backends() {
+        let open = ExternPolicy::default();
+        assert!(open.allows_selector("fs:remove") && open.allows_selector("net:connect"));
+
+        let sandbox = policy(&["--allow-fs=read", "--allow-net=connect,send"]);
+        assert!(sandbox.allows_selector("fs:read") && sandbox.allows_selector("fs:list_dir"));
+        assert!(!sandbox.allows_selector("fs:write") && !sandbox.allows_selector("fs:open"));
+        assert!(sandbox.allows_selector("net:send") && !sandbox.allows_selector("net:listen"));
+        assert!(!sandbox.allows_selector("time:now"));
+        assert!(sandbox.allows_selector("print_native"));
+        assert!(policy(&["--allow-fs=read", "--allow-fs"]).allows_selector("fs:remove"));
+
+        let none = policy(&["--deny-all"]);
+        assert!(!none.allows_selector("json:parse") && none.allows_selector("debug_info"));
+        assert!(policy(&["--deny-all", "--allow-json"]).allows_selector("json:parse"));
+
+        assert_eq!(ExternPolicy::default().apply_flag("--allow"), None);
+        assert!(ExternPolicy::default().apply_flag("--allow-=read").unwrap().is_err());
+        assert_eq!(denied("fs", "write"), "extern \"fs:write\" is not allowed in this run (run with --allow-fs=write to allow it)");
+        assert!(denied("net", "listen").ends_with("(run with --allow-net to allow it)"));
+    }
+}
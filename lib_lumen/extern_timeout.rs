@@ -0,0 +1,145 @@
+// Timeouts of extern calls, shared by both kernels
+//
+//   --extern-timeout MS           every extern call of the run may take at most MS milliseconds
+//   --extern-timeout net=MS       calls of the net backend may (the flag adds up, in any order)
+//   --extern-timeout net:recv=MS  net:recv may
+//
+//   extern_timeout("net", 500)    the program's own: calls of net from now on
+//   extern_timeout("net", null)   no longer
+//
+// A target is "*" (every call), a backend ("net"), or a capability ("net:recv", or
+// "print_native" for one without a backend); a call takes the timeout of its capability,
+// else of its backend, else of "*". The host's timeouts (the flag, or ExecutionLimits for
+// an embedder) and the program's apply together, and the shorter wins: a program can
+// shorten the host's timeouts but never lengthen or remove them.
+//
+// A timed call runs on a thread of its own while the program waits for it. Past its
+// timeout the call fails with code "timed_out" (retriable), which extern_try catches, and
+// the program goes on; the thread cannot be stopped from outside, so it is left to finish
+// and its result is dropped. A stuck capability costs a thread, not the run. A call that
+// passes one of the program's functions (arr:sort_by) runs in the program, which the thread
+// cannot reach, and is not timed; nor is a mock. An extern_async call counts its timeout
+// from its start, and extern_await gives the error once it is over.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// The timeouts of a run's calls by target (the host's or the program's)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExternTimeouts {
+    targets: HashMap<String, Duration>,
+}
+
+/// How a timed call ended without a result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stopped {
+    /// It took longer than its timeout
+    TimedOut,
+    /// It never gave one (it panicked, or no thread could run it); why
+    Failed(String),
+}
+
+impl ExternTimeouts {
+    /// Give `target` (see above) `timeout`, or with None take its timeout away
+    pub fn set(&mut self, target: &str, timeout: Option<Duration>) -> Result<(), String> {
+        let well_formed = match target.split_once(':') {
+            Some((backend, capability)) => is_name(backend) && is_name(capability),
+            None => target == "*" || is_name(target),
+        };
+        if !well_formed {
+            return Err(format!("'{}' is not an extern timeout target: write *, a backend (net) or a capability (net:recv)", target));
+        }
+        match timeout {
+            Some(timeout) => self.targets.insert(target.to_string(), timeout),
+            None => self.targets.remove(target),
+        };
+        Ok(())
+    }
+
+    /// Apply the value of an --extern-timeout flag: MS, or TARGET=MS
+    pub fn apply_flag(&mut self, value: &str) -> Result<(), String> {
+        let (target, millis) = value.split_once('=').unwrap_or(("*", value));
+        match millis.parse::<u64>() {
+            Ok(millis) if millis > 0 => self.set(target, Some(Duration::from_millis(millis))),
+            _ => Err(format!("--extern-timeout expects MS or TARGET=MS with a positive integer, got '{}'", value)),
+        }
+    }
+
+    /// The timeout of a call of `capability` of `backend`
+    pub fn of(&self, backend: Option<&str>, capability: &str) -> Option<Duration> {
+        let own = match backend {
+            Some(backend) => self.targets.get(&format!("{}:{}", backend, capability)),
+            None => self.targets.get(capability),
+        };
+        own.or_else(|| backend.and_then(|backend| self.targets.get(backend))).or_else(|| self.targets.get("*")).copied()
+    }
+
+    /// The timeout of a call when the host's timeouts are `self` and the program's `program`
+    pub fn with(&self, program: &ExternTimeouts, backend: Option<&str>, capability: &str) -> Option<Duration> {
+        match (self.of(backend, capability), program.of(backend, capability)) {
+            (Some(host), Some(own)) => Some(host.min(own)),
+            (host, own) => host.or(own),
+        }
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// The message of a call of `selector` that took longer than `timeout`
+pub fn timed_out(selector: &str, timeout: Duration) -> String {
+    format!("extern \"{}\" timed out after {} ms", selector, timeout.as_millis())
+}
+
+/// Run `call` on a thread of its own and wait at most `timeout` for its result
+pub fn run<T: Send + 'static>(timeout: Duration, call: impl FnOnce() -> T + Send + 'static) -> Result<T, Stopped> {
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("extern call".to_string())
+        .spawn(move || {
+            // Nobody listens once the call timed out
+            let _ = sender.send(call());
+        })
+        .map_err(|e| Stopped::Failed(format!("no thread to run the call on: {}", e)))?;
+    receiver.recv_timeout(timeout).map_err(|e| match e {
+        RecvTimeoutError::Timeout => Stopped::TimedOut,
+        RecvTimeoutError::Disconnected => Stopped::Failed("the call panicked".to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_closest_and_shortest_timeout_wins() {
+        let mut host = ExternTimeouts::default();
+        host.apply_flag("1000").unwrap();
+        host.apply_flag("net=500").unwrap();
+        assert_eq!(host.of(Some("net"), "recv"), Some(Duration::from_millis(500)));
+        assert_eq!(host.of(Some("fs"), "read"), Some(Duration::from_millis(1000)));
+        assert_eq!(host.of(None, "print_native"), Some(Duration::from_millis(1000)));
+
+        let mut program = ExternTimeouts::default();
+        program.set("net:recv", Some(Duration::from_millis(50))).unwrap();
+        program.set("fs", Some(Duration::from_secs(60))).unwrap();
+        assert_eq!(host.with(&program, Some("net"), "recv"), Some(Duration::from_millis(50)));
+        assert_eq!(host.with(&program, Some("fs"), "read"), Some(Duration::from_millis(1000)));
+        program.set("fs", None).unwrap();
+        assert_eq!(ExternTimeouts::default().with(&program, Some("fs"), "read"), None);
+
+        assert!(host.apply_flag("net=0").is_err());
+        assert!(program.set("net|fs", Some(Duration::ZERO)).is_err());
+    }
+
+    #[test]
+    fn stops_waiting_at_the_timeout() {
+        assert_eq!(run(Duration::from_secs(5), || 7), Ok(7));
+        let stuck = run(Duration::from_millis(10), || thread::sleep(Duration::from_secs(5)));
+        assert_eq!(stuck, Err(Stopped::TimedOut));
+        assert_eq!(timed_out("net:recv", Duration::from_millis(10)), "extern \"net:recv\" timed out after 10 ms");
+    }
+}
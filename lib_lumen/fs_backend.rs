@@ -0,0 +1,120 @@
+// The `fs` extern backend: files and directories, shared by both kernels
+//
+//   extern("fs:open", path, mode)     ready a file: "r" it must exist and be readable,
+//                                     "w" created or emptied, "a" created if missing;
+//                                     returns the path
+//   extern("fs:read", path)           the file's text
+//   extern("fs:write", path, text)    replace the file's text, creating the file
+//   extern("fs:append", path, text)   add text to the end of the file, creating it
+//   extern("fs:exists", path)         true if there is a file or directory at path
+//   extern("fs:remove", path)         delete a file or an empty directory
+//   extern("fs:list_dir", path)       the names of a directory's entries, sorted
+//
+// Every argument is a string; relative paths are from the working directory. A kernel
+// passes the arguments as Some(text), or None for a value that is not a string, and turns
+// the Outcome into its own value. A failure is an error naming the selector and the path
+// ("fs:read: notes.txt: No such file or directory (os error 2)"), coded by its kind with the
+// host's errno (see extern_error.rs).
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "fs:" in a selector
+pub const CAPABILITIES: &[&str] = &["append", "exists", "list_dir", "open", "read", "remove", "write"];
+
+/// What a capability returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Text(String),
+    Bool(bool),
+    Names(Vec<String>),
+    Null,
+}
+
+/// Run capability `name` of the backend on its arguments
+pub fn call(name: &str, args: &[Option<&str>]) -> Result<Outcome, ExternError> {
+    let arity = match name {
+        "open" | "write" | "append" => 2,
+        "read" | "exists" | "remove" | "list_dir" => 1,
+        _ => return Err(ExternError::new("unavailable", format!("fs has no capability '{}'", name))),
+    };
+    if args.len() != arity {
+        return Err(ExternError::invalid_argument(format!("fs:{} expects {} arguments, got {}", name, arity, args.len())));
+    }
+    let args = args
+        .iter()
+        .map(|arg| arg.ok_or_else(|| ExternError::invalid_argument(format!("fs:{} arguments must be strings", name))))
+        .collect::<Result<Vec<&str>, _>>()?;
+    let path = args[0];
+    let failed = |e: std::io::Error| ExternError::io(format!("fs:{}: {}: {}", name, path, e), &e);
+
+    match name {
+        "open" => {
+            let mut options = OpenOptions::new();
+            match args[1] {
+                "r" => options.read(true),
+                "w" => options.write(true).create(true).truncate(true),
+                "a" => options.append(true).create(true),
+                mode => return Err(ExternError::invalid_argument(format!("fs:open mode must be \"r\", \"w\" or \"a\", got \"{}\"", mode))),
+            };
+            options.open(path).map_err(failed)?;
+            Ok(Outcome::Text(path.to_string()))
+        }
+        "read" => fs::read_to_string(path).map(Outcome::Text).map_err(failed),
+        "write" => fs::write(path, args[1]).map(|_| Outcome::Null).map_err(failed),
+        "append" => OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(args[1].as_bytes()))
+            .map(|_| Outcome::Null)
+            .map_err(failed),
+        "exists" => Ok(Outcome::Bool(fs::symlink_metadata(path).is_ok())),
+        "remove" => {
+            let metadata = fs::symlink_metadata(path).map_err(failed)?;
+            let removed = if metadata.is_dir() { fs::remove_dir(path) } else { fs::remove_file(path) };
+            removed.map(|_| Outcome::Null).map_err(failed)
+        }
+        _ => {
+            let mut names = fs::read_dir(path)
+                .map_err(failed)?
+                .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .collect::<Result<Vec<String>, _>>()
+                .map_err(failed)?;
+            names.sort();
+            Ok(Outcome::Names(names))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_writes_and_lists_real_files() {
+        let dir = std::env::temp_dir().join(format!("lumen-fs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dir_name = dir.to_str().unwrap();
+        let file = dir.join("notes.txt");
+        let path = file.to_str().unwrap();
+
+        assert_eq!(call("exists", &[Some(path)]), Ok(Outcome::Bool(false)));
+        assert_eq!(call("open", &[Some(path), Some("w")]), Ok(Outcome::Text(path.to_string())));
+        call("write", &[Some(path), Some("one\n")]).unwrap();
+        call("append", &[Some(path), Some("two\n")]).unwrap();
+        assert_eq!(call("read", &[Some(path)]), Ok(Outcome::Text("one\ntwo\n".to_string())));
+        assert_eq!(call("list_dir", &[Some(dir_name)]), Ok(Outcome::Names(vec!["notes.txt".to_string()])));
+        call("remove", &[Some(path)]).unwrap();
+        assert_eq!(call("exists", &[Some(path)]), Ok(Outcome::Bool(false)));
+
+        let error = call("read", &[Some(path)]).unwrap_err();
+        assert!(error.message.starts_with(&format!("fs:read: {}: ", path)), "{}", error);
+        assert_eq!(error.code, "not_found");
+        assert!(call("open", &[Some(path), Some("x")]).unwrap_err().message.contains("mode"));
+        assert_eq!(call("write", &[Some(path), None]).unwrap_err(), ExternError::invalid_argument("fs:write arguments must be strings"));
+        call("remove", &[Some(dir_name)]).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+// Host configuration: typed constants injected into the initial environment
+// Included by both kernel binaries (like prelude.rs) so the CLI accepts the same syntax.
+//
+// Sources:
+//   --define NAME=value     one constant; a bare word value is taken as a string
+//   --config file.toml      flat TOML subset: `NAME = value` lines and # comments
+//
+// Values: integers (42, -7), decimals (1.5, a REAL), booleans (true/false),
+// and double-quoted strings ("text", with \" \\ \n \t escapes).
+// Each kernel converts ConfigValue into its own value type.
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+
+/// A typed constant supplied by the host
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Integer(BigInt),
+    Real { numerator: BigInt, denominator: BigInt, precision: usize },
+    Bool(bool),
+    String(String),
+}
+
+/// Parse a `--define NAME=value` argument
+pub fn parse_define(arg: &str) -> Result<(String, ConfigValue), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("--define expects NAME=value, got '{}'", arg))?;
+    let name = check_name(name.trim())?;
+    let value = parse_value(value.trim(), true)?;
+    Ok((name, value))
+}
+
+/// Parse the contents of a `--config` file (flat TOML subset)
+pub fn parse_config(source: &str) -> Result<Vec<(String, ConfigValue)>, String> {
+    let mut constants: Vec<(String, ConfigValue)> = Vec::new();
+    for (index, raw) in source.lines().enumerate() {
+        let line_no = index + 1;
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(format!("line {}: tables are not supported in config files", line_no));
+        }
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected NAME = value", line_no))?;
+        let name = check_name(name.trim()).map_err(|e| format!("line {}: {}", line_no, e))?;
+        let value = parse_value(value.trim(), false).map_err(|e| format!("line {}: {}", line_no, e))?;
+        if constants.iter().any(|(n, _)| *n == name) {
+            return Err(format!("line {}: '{}' is defined more than once", line_no, name));
+        }
+        constants.push((name, value));
+    }
+    Ok(constants)
+}
+
+/// Add constants to a list, reporting names that are already present
+pub fn merge(
+    into: &mut Vec<(String, ConfigValue)>,
+    constants: Vec<(String, ConfigValue)>,
+) -> Result<(), String> {
+    for (name, value) in constants {
+        if into.iter().any(|(n, _)| *n == name) {
+            return Err(format!("Host constant '{}' is defined more than once", name));
+        }
+        into.push((name, value));
+    }
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<String, String> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("'{}' is not a valid constant name", name));
+    }
+    Ok(name.to_string())
+}
+
+/// Remove a trailing # comment that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escape_next = false;
+    for (i, ch) in line.char_indices() {
+        if escape_next {
+            escape_next = false;
+        } else if ch == '\\' && in_string {
+            escape_next = true;
+        } else if ch == '"' {
+            in_string = !in_string;
+        } else if ch == '#' && !in_string {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str, allow_bare: bool) -> Result<ConfigValue, String> {
+    if text.is_empty() {
+        return Err("missing value".to_string());
+    }
+    if text == "true" || text == "false" {
+        return Ok(ConfigValue::Bool(text == "true"));
+    }
+    if let Some(inner) = text.strip_prefix('"') {
+        let inner = inner
+            .strip_suffix('"')
+            .ok_or_else(|| format!("unterminated string {}", text))?;
+        return unescape(inner).map(ConfigValue::String);
+    }
+    if let Some(value) = parse_number(text) {
+        return Ok(value);
+    }
+    if allow_bare {
+        return Ok(ConfigValue::String(text.to_string()));
+    }
+    Err(format!("unsupported value '{}' (use an integer, decimal, boolean, or quoted string)", text))
+}
+
+fn parse_number(text: &str) -> Option<ConfigValue> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (int_part, frac_part) = match digits.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (digits, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if !all_digits(int_part) || !frac_part.is_none_or(all_digits) {
+        return None;
+    }
+
+    let sign = if text.starts_with('-') { "-" } else { "" };
+    match frac_part {
+        None => text.parse::<BigInt>().ok().map(ConfigValue::Integer),
+        Some(frac) => {
+            let numerator: BigInt = format!("{}{}{}", sign, int_part, frac).parse().ok()?;
+            let scale = BigInt::from(10).pow(frac.len() as u32);
+            let divisor = numerator.gcd(&scale);
+            let (numerator, denominator) = (numerator / &divisor, scale / &divisor);
+            // Same precision rule as decimal literals: significant digits, at least 15
+            let significant = format!("{}{}", int_part, frac).trim_start_matches('0').len();
+            Some(ConfigValue::Real { numerator, denominator, precision: significant.max(15) })
+        }
+    }
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('"') => result.push('"'),
+            Some('\\') => result.push('\\'),
+            other => return Err(format!("unsupported escape \\{}", other.map(String::from).unwrap_or_default())),
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,64 @@
+// Expansion of `include "path"` lines against the embedded library (lib_lumen/prelude.rs)
+// Included by the microcode binary, the stream library (which the stream binary uses) and
+// build.rs, which reduces the standard prelude when the microcode kernel is built.
+// Expects an `embedded_files` module at the crate root.
+
+/// Generic embedded file lookup: queries the embedded virtual filesystem by path
+/// The kernel performs a simple path-based lookup with no semantic knowledge
+pub fn get_embedded_file(path: &str) -> Option<&'static str> {
+    crate::embedded_files::EMBEDDED_FILES
+        .iter()
+        .find(|(p, _)| *p == path)
+        .map(|(_, contents)| *contents)
+}
+
+/// Process include directives in source code
+/// Recursively expands `include "path"` directives by inlining embedded file contents
+pub fn process_includes(source: &str) -> Result<String, String> {
+    let mut result = String::new();
+    let mut processed_files = std::collections::HashSet::new();
+
+    fn process_recursive(
+        source: &str,
+        processed_files: &mut std::collections::HashSet<String>,
+        result: &mut String,
+    ) -> Result<(), String> {
+        for line in source.lines() {
+            let trimmed = line.trim();
+
+            // Check if line is an include directive
+            if trimmed.starts_with("include ") {
+                // Extract the file path from: include "path"
+                let rest = trimmed.strip_prefix("include ").unwrap().trim();
+
+                if !rest.starts_with('"') || !rest.ends_with('"') {
+                    return Err(format!("Invalid include syntax: {}", line));
+                }
+
+                let path = &rest[1..rest.len()-1];
+
+                // Prevent circular includes
+                if processed_files.contains(path) {
+                    continue; // Skip already processed files
+                }
+                processed_files.insert(path.to_string());
+
+                // Retrieve from embedded virtual filesystem
+                let file_contents = get_embedded_file(path)
+                    .ok_or_else(|| format!("File not found in embedded filesystem: {}", path))?;
+
+                // Recursively process the included file
+                process_recursive(file_contents, processed_files, result)?;
+                result.push('\n');
+            } else {
+                // Regular line - keep it
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+        Ok(())
+    }
+
+    process_recursive(source, &mut processed_files, &mut result)?;
+    Ok(result)
+}
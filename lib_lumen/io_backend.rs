@@ -0,0 +1,121 @@
+// The `io` extern backend: standard input and output, shared by both kernels
+//
+//   extern("io:read_line")      the next line of stdin without its line ending, or null at
+//                               the end of input
+//   extern("io:read_all")       the rest of stdin ("" at the end of input)
+//   extern("io:read_bytes")     the rest of stdin as BYTES, for binary input (an image,
+//                               an archive) that is not UTF-8
+//   extern("io:write_bytes", b) BYTES b to stdout as they are, with no line ending
+//
+// so a program can be a filter in a shell pipeline:
+//
+//   line = extern("io:read_line")
+//   while line != null
+//       print(string_to_upper(line))
+//       line = extern("io:read_line")
+//
+// or one that works on a binary file: `lumen-lang flip.lm < photo.png > flipped.png`.
+//
+// read_line and read_all read text; a line ending is "\n" or "\r\n". A program read from
+// stdin (`-`) has already consumed it, so it finds the end of input.
+
+use std::io::{self, BufRead, Write};
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "io:" in a selector
+pub const CAPABILITIES: &[&str] = &["read_all", "read_bytes", "read_line", "write_bytes"];
+
+/// An argument, as the backend sees it
+pub enum Arg<'a> {
+    Bytes(&'a [u8]),
+    /// Any other value, which no capability accepts
+    Other,
+}
+
+/// What a capability gives; a kernel turns Null into null
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Text(String),
+    Bytes(Vec<u8>),
+    Null,
+}
+
+/// Run capability `name` of the backend on stdin or stdout
+pub fn call(name: &str, args: &[Arg]) -> Result<Outcome, ExternError> {
+    if !CAPABILITIES.contains(&name) {
+        return Err(ExternError::new("unavailable", format!("io has no capability '{}'", name)));
+    }
+    if name == "write_bytes" {
+        return match args {
+            [Arg::Bytes(bytes)] => write(bytes, &mut io::stdout().lock()),
+            [_] => Err(ExternError::invalid_argument("io:write_bytes expects BYTES")),
+            _ => Err(ExternError::invalid_argument(format!("io:write_bytes expects 1 argument, got {}", args.len()))),
+        };
+    }
+    if !args.is_empty() {
+        return Err(ExternError::invalid_argument(format!("io:{} expects 0 arguments, got {}", name, args.len())));
+    }
+    read(name, &mut io::stdin().lock())
+}
+
+/// Write `bytes` to `output`
+fn write(bytes: &[u8], output: &mut impl Write) -> Result<Outcome, ExternError> {
+    let failed = |e: io::Error| ExternError::io(format!("io:write_bytes: {}", e), &e);
+    output.write_all(bytes).and_then(|_| output.flush()).map_err(failed)?;
+    Ok(Outcome::Null)
+}
+
+/// Run reading capability `name` on `input`
+fn read(name: &str, input: &mut impl BufRead) -> Result<Outcome, ExternError> {
+    let failed = |e: io::Error| ExternError::io(format!("io:{}: {}", name, e), &e);
+    let mut text = String::new();
+    if name == "read_bytes" {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes).map_err(failed)?;
+        return Ok(Outcome::Bytes(bytes));
+    }
+    if name == "read_all" {
+        input.read_to_string(&mut text).map_err(failed)?;
+        return Ok(Outcome::Text(text));
+    }
+    if input.read_line(&mut text).map_err(failed)? == 0 {
+        return Ok(Outcome::Null);
+    }
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+    Ok(Outcome::Text(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_lines_then_the_rest() {
+        let text = |text: &str| Ok(Outcome::Text(text.to_string()));
+        let mut input = io::Cursor::new("first\r\nsecond\n\nrest\nof it");
+        assert_eq!(read("read_line", &mut input), text("first"));
+        assert_eq!(read("read_line", &mut input), text("second"));
+        assert_eq!(read("read_line", &mut input), text(""));
+        assert_eq!(read("read_all", &mut input), text("rest\nof it"));
+        assert_eq!(read("read_line", &mut input), Ok(Outcome::Null));
+        assert_eq!(read("read_all", &mut input), text(""));
+        assert_eq!(call("read_line", &[Arg::Other]), Err(ExternError::invalid_argument("io:read_line expects 0 arguments, got 1")));
+        assert!(call("write", &[]).is_err());
+    }
+
+    #[test]
+    fn reads_and_writes_bytes_that_are_not_utf8() {
+        let png = [0x89, b'P', b'N', b'G', 0xff];
+        assert_eq!(read("read_bytes", &mut io::Cursor::new(png)), Ok(Outcome::Bytes(png.to_vec())));
+        let mut output = Vec::new();
+        assert_eq!(write(&png, &mut output), Ok(Outcome::Null));
+        assert_eq!(output, png);
+        assert_eq!(call("write_bytes", &[Arg::Other]), Err(ExternError::invalid_argument("io:write_bytes expects BYTES")));
+    }
+}
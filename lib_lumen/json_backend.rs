@@ -0,0 +1,408 @@
+// The `json` extern backend: JSON text to and from Lumen values, shared by both kernels
+//
+//   extern("json:parse", text)              the value the JSON text describes
+//   extern("json:stringify", value)         value as compact JSON text
+//   extern("json:stringify", value, indent) ... laid out one item per line, nested items
+//                                           indented by `indent` more spaces
+//
+//   JSON                  Lumen
+//   null, true, false     null, true, false
+//   1, -20                INTEGER (of any size)
+//   1.5, 2e3, -0.25E-2    REAL, of the precision a literal with those digits would have
+//   "text"                STRING
+//   [ ... ]               ARRAY
+//   { "key": ... }        MAP, its entries in the order of the text (a repeated key keeps its
+//                         first place and its last value)
+//
+// stringify writes a REAL in its decimal notation, so 2.0 comes back as the INTEGER 2.
+// RATIONALs, inf and nan have no JSON form (convert a RATIONAL with real() first), nor do
+// functions, kinds or handles: stringify is an error for them. A kernel turns its values
+// into a Json tree and back; numbers travel as their text and `number` reads one.
+
+use num_bigint::BigInt;
+use num_traits::{One, Pow};
+
+/// The capabilities of the backend, as they follow "json:" in a selector
+pub const CAPABILITIES: &[&str] = &["parse", "stringify"];
+
+/// Deepest nesting of arrays and objects parse accepts
+const MAX_DEPTH: usize = 512;
+
+/// Largest decimal exponent of a number parse accepts (a REAL beyond it would be inf)
+const MAX_EXPONENT: i64 = 1_000_000;
+
+/// A JSON value, as a kernel builds it for stringify and takes it from parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// A number as its JSON text (stringify checks the text a kernel gives it)
+    Number(String),
+    Text(String),
+    List(Vec<Json>),
+    Map(Vec<(String, Json)>),
+}
+
+/// A JSON number as a Lumen number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Number {
+    Int(BigInt),
+    Real { numerator: BigInt, denominator: BigInt, precision: usize },
+}
+
+/// json:parse: the value `text` describes
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { text, pos: 0 };
+    parser.skip_space();
+    let value = parser.value(0)?;
+    parser.skip_space();
+    if parser.pos < text.len() {
+        return Err(parser.error("unexpected text after the value"));
+    }
+    Ok(value)
+}
+
+/// json:stringify: `value` as JSON text, laid out with `indent` spaces per level if given
+pub fn stringify(value: &Json, indent: Option<usize>) -> Result<String, String> {
+    let mut out = String::new();
+    write_value(&mut out, value, indent, 0)?;
+    Ok(out)
+}
+
+/// The Lumen number a JSON number's text (as parse gives it) stands for
+pub fn number(text: &str) -> Number {
+    let (mantissa, exponent) = text.split_once(['e', 'E']).unwrap_or((text, "0"));
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits: BigInt = format!("{}{}", whole, fraction).parse().expect("parse checked the number");
+    if mantissa.len() == text.len() && fraction.is_empty() {
+        return Number::Int(digits);
+    }
+    // As a REAL literal: at least 15 digits, more if the text has more significant digits
+    let significant = format!("{}{}", whole, fraction).trim_start_matches(['-', '0']).len();
+    let precision = significant.max(15);
+    let exponent = exponent.parse::<i64>().expect("parse checked the exponent") - fraction.len() as i64;
+    let scale: BigInt = BigInt::from(10).pow(exponent.unsigned_abs());
+    if exponent >= 0 {
+        Number::Real { numerator: digits * scale, denominator: BigInt::one(), precision }
+    } else {
+        Number::Real { numerator: digits, denominator: scale, precision }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        format!("json:parse: {} at line {} column {}", message, line, column)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &str) -> Result<(), String> {
+        self.skip_space();
+        if self.peek() != Some(byte) {
+            return Err(self.error(message));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, String> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error(&format!("nesting deeper than {}", MAX_DEPTH)));
+        }
+        match self.peek() {
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_space();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(Json::List(items));
+                }
+                loop {
+                    self.skip_space();
+                    items.push(self.value(depth + 1)?);
+                    self.skip_space();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => break,
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+                self.pos += 1;
+                Ok(Json::List(items))
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut entries: Vec<(String, Json)> = Vec::new();
+                self.skip_space();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(Json::Map(entries));
+                }
+                loop {
+                    self.skip_space();
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error("expected a string key"));
+                    }
+                    let key = self.string()?;
+                    self.expect(b':', "expected ':'")?;
+                    self.skip_space();
+                    let value = self.value(depth + 1)?;
+                    match entries.iter_mut().find(|(k, _)| *k == key) {
+                        Some(entry) => entry.1 = value,
+                        None => entries.push((key, value)),
+                    }
+                    self.skip_space();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => break,
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+                self.pos += 1;
+                Ok(Json::Map(entries))
+            }
+            Some(b'"') => Ok(Json::Text(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => {
+                for (word, value) in [("null", Json::Null), ("true", Json::Bool(true)), ("false", Json::Bool(false))] {
+                    if self.text[self.pos..].starts_with(word) {
+                        self.pos += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error(if self.peek().is_none() { "unexpected end of text" } else { "expected a value" }))
+            }
+        }
+    }
+
+    /// A string; the parser is at its opening quote
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let Some(ch) = rest.chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            match ch {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                '\\' => {
+                    let escape = rest[1..].chars().next();
+                    self.pos += 2;
+                    out.push(match escape {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => {
+                            self.pos -= 2;
+                            return Err(self.error("invalid escape"));
+                        }
+                    });
+                }
+                ch if (ch as u32) < 0x20 => return Err(self.error("control character in string")),
+                ch => {
+                    out.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// The character of a \uXXXX escape (two of them for a surrogate pair); the parser is
+    /// just past the 'u'
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let first = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&first) {
+            if !self.text[self.pos..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            self.pos += 2;
+            let second = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&second) {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate in \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let hex = self.text.get(self.pos..self.pos + 4).filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()));
+        let code = hex.map(|h| u32::from_str_radix(h, 16).expect("four hex digits"));
+        let code = code.ok_or_else(|| self.error("expected four hex digits after \\u"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let from = parser.pos;
+            while matches!(parser.peek(), Some(b'0'..=b'9')) {
+                parser.pos += 1;
+            }
+            parser.pos - from
+        };
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        let whole_start = self.pos;
+        if digits(self) == 0 || (self.text.as_bytes()[whole_start] == b'0' && self.pos - whole_start > 1) {
+            self.pos = start;
+            return Err(self.error("invalid number"));
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if digits(self) == 0 {
+                return Err(self.error("expected digits after '.'"));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            let exponent_start = self.pos;
+            if digits(self) == 0 {
+                return Err(self.error("expected digits in the exponent"));
+            }
+            let exponent = self.text[exponent_start..self.pos].parse::<i64>().unwrap_or(i64::MAX);
+            if exponent > MAX_EXPONENT {
+                // A REAL this large would be inf, and one this small 0; both are refused
+                self.pos = start;
+                return Err(self.error("number out of range"));
+            }
+        }
+        Ok(Json::Number(self.text[start..self.pos].to_string()))
+    }
+}
+
+fn write_value(out: &mut String, value: &Json, indent: Option<usize>, depth: usize) -> Result<(), String> {
+    match value {
+        Json::Null => out.push_str("null"),
+        Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::Number(text) => {
+            let mut check = Parser { text, pos: 0 };
+            if check.number().is_err() || check.pos != text.len() {
+                return Err(format!("json:stringify: {} has no JSON form", text));
+            }
+            out.push_str(text);
+        }
+        Json::Text(text) => write_string(out, text),
+        Json::List(items) => {
+            write_items(out, '[', ']', items, indent, depth, |out, item| write_value(out, item, indent, depth + 1))?;
+        }
+        Json::Map(entries) => {
+            write_items(out, '{', '}', entries, indent, depth, |out, (key, value)| {
+                write_string(out, key);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                write_value(out, value, indent, depth + 1)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Items between brackets, one per line when laid out
+fn write_items<T>(
+    out: &mut String,
+    open: char,
+    close: char,
+    items: &[T],
+    indent: Option<usize>,
+    depth: usize,
+    mut write: impl FnMut(&mut String, &T) -> Result<(), String>,
+) -> Result<(), String> {
+    out.push(open);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if let Some(width) = indent {
+            out.push('\n');
+            out.push_str(&" ".repeat(width * (depth + 1)));
+        }
+        write(out, item)?;
+    }
+    if let (Some(width), false) = (indent, items.is_empty()) {
+        out.push('\n');
+        out.push_str(&" ".repeat(width * depth));
+    }
+    out.push(close);
+    Ok(())
+}
+
+fn write_string(out: &mut String, text: &str) {
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_writes_nested_values() {
+        let text = r#" {"name": "Ada \"L\"\né😀", "tags": [1, -2.50, 3e2, true, null], "name": "Ada", "empty": {}} "#;
+        let value = parse(text).unwrap();
+        assert_eq!(
+            stringify(&value, None).unwrap(),
+            r#"{"name":"Ada","tags":[1,-2.50,3e2,true,null],"empty":{}}"#
+        );
+        assert_eq!(stringify(&value, Some(2)).unwrap(), "{\n  \"name\": \"Ada\",\n  \"tags\": [\n    1,\n    -2.50,\n    3e2,\n    true,\n    null\n  ],\n  \"empty\": {}\n}");
+        assert_eq!(parse(r#""é😀\t""#), Ok(Json::Text("é😀\t".to_string())));
+        assert_eq!(stringify(&Json::Text("a\"\u{1}".to_string()), None).unwrap(), r#""a\"\u0001""#);
+
+        assert_eq!(number("12345678901234567890123"), Number::Int("12345678901234567890123".parse().unwrap()));
+        let real = |n: i64, d: i64, precision| Number::Real { numerator: BigInt::from(n), denominator: BigInt::from(d), precision };
+        assert_eq!(number("-2.50"), real(-250, 100, 15));
+        assert_eq!(number("3e2"), real(300, 1, 15));
+        assert_eq!(number("25E-1"), real(25, 10, 15));
+
+        assert_eq!(parse("[1, 2").unwrap_err(), "json:parse: expected ',' or ']' at line 1 column 6");
+        assert_eq!(parse("{\n  \"a\" 1}").unwrap_err(), "json:parse: expected ':' at line 2 column 7");
+        assert!(parse("01").unwrap_err().contains("invalid number"));
+        assert!(parse("[] x").unwrap_err().contains("after the value"));
+        assert!(parse(&"[".repeat(600)).unwrap_err().contains("nesting"));
+        assert_eq!(stringify(&Json::Number("1/3".to_string()), None).unwrap_err(), "json:stringify: 1/3 has no JSON form");
+    }
+}
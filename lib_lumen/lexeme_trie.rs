@@ -0,0 +1,84 @@
+// Maximal-munch matching of a language's multi-character lexemes
+// Included by both kernels' lexers, built once per language (TokenRegistry / LanguageSchema).
+//
+// The lexemes are kept in a prefix trie over their bytes, so finding the longest one
+// at a position walks at most as many bytes as the longest lexeme has, however many
+// lexemes the language defines. A lexeme can be flagged as a word (a keyword): the lexer
+// decides whether a match of it is accepted (e.g. not inside an identifier), and when a
+// match is refused the next shorter lexeme matching at the position is tried.
+
+/// The multi-character lexemes of a language
+#[derive(Debug, Clone, Default)]
+pub struct LexemeTrie {
+    nodes: Vec<TrieNode>,
+    longest: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    /// Next byte and the node it leads to, sorted by byte
+    children: Vec<(u8, usize)>,
+    /// The lexeme ending here, and whether it is a word
+    lexeme: Option<(&'static str, bool)>,
+}
+
+impl LexemeTrie {
+    /// A trie of `lexemes`, each with its word flag
+    pub fn new(lexemes: impl IntoIterator<Item = (&'static str, bool)>) -> Self {
+        let mut trie = LexemeTrie { nodes: vec![TrieNode::default()], longest: 0 };
+        for (lexeme, word) in lexemes {
+            let mut node = 0;
+            for &byte in lexeme.as_bytes() {
+                node = match trie.nodes[node].children.binary_search_by_key(&byte, |&(b, _)| b) {
+                    Ok(found) => trie.nodes[node].children[found].1,
+                    Err(at) => {
+                        trie.nodes.push(TrieNode::default());
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children.insert(at, (byte, child));
+                        child
+                    }
+                };
+            }
+            trie.nodes[node].lexeme = Some((lexeme, word));
+            trie.longest = trie.longest.max(lexeme.len());
+        }
+        trie
+    }
+
+    /// Length in bytes of the longest lexeme (0 if there are none)
+    pub fn longest(&self) -> usize {
+        self.longest
+    }
+
+    /// The longest lexeme `input` starts with that `accept` (given the lexeme and its
+    /// word flag) agrees to
+    pub fn longest_match(&self, input: &[u8], accept: impl Fn(&'static str, bool) -> bool) -> Option<&'static str> {
+        self.find(0, input, 0, &accept)
+    }
+
+    fn find(&self, node: usize, input: &[u8], depth: usize, accept: &impl Fn(&'static str, bool) -> bool) -> Option<&'static str> {
+        let children = &self.nodes[node].children;
+        let child = input.get(depth).and_then(|byte| children.binary_search_by_key(byte, |&(b, _)| b).ok());
+        // Longer lexemes (further down) first, then the one ending here
+        child
+            .and_then(|found| self.find(children[found].1, input, depth + 1, accept))
+            .or_else(|| self.nodes[node].lexeme.filter(|&(lexeme, word)| accept(lexeme, word)).map(|(lexeme, _)| lexeme))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_longest_accepted_lexeme() {
+        let trie = LexemeTrie::new([("==", false), ("=>", false), ("===", false), ("if", true), ("iff", true)]);
+        assert_eq!(trie.longest(), 3);
+        assert_eq!(trie.longest_match(b"=== 1", |_, _| true), Some("==="));
+        assert_eq!(trie.longest_match(b"==1", |_, _| true), Some("=="));
+        assert_eq!(trie.longest_match(b"=1", |_, _| true), None);
+        // A refused word falls back to the next shorter lexeme
+        assert_eq!(trie.longest_match(b"iffy", |lexeme, word| !word || lexeme != "iff"), Some("if"));
+        assert_eq!(trie.longest_match(b"iffy", |_, word| !word), None);
+    }
+}
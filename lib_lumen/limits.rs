@@ -0,0 +1,167 @@
+// Execution limits, shared by both kernels
+//
+//   --max-depth N    at most N nested calls of user-defined functions
+//   --max-steps N    at most N steps; a step is one loop iteration or one function call
+//   --max-time MS    at most MS milliseconds of wall-clock time
+//   --max-heap N     at most N bytes held in variables
+//   --allow-BACKEND / --deny-all   which extern capabilities it may reach (extern_policy.rs)
+//   --audit FILE     a log of every extern call it makes (extern_audit.rs)
+//   --extern-timeout [TARGET=]MS   how long its extern calls may take (extern_timeout.rs)
+//
+// Straight-line code always finishes, so runaway recursion and infinite loops are
+// exactly what steps and depth count. Lumen calls do not nest on the native stack in
+// either kernel, so without --max-depth a runaway recursion only stops when memory runs
+// out. Time and heap are checked every CHECK_INTERVAL steps; the heap is the kernel's
+// estimate of the values bound in every scope (strings, digits of numbers, elements of
+// arrays and maps), so a single huge value is only caught once it is bound. Together the
+// limits let a host run an untrusted program knowing it stops, the extern policy
+// knowing what it can touch, and the audit log knowing what it did.
+//
+// Going over a limit stops the program with an error that starts with LIMIT_EXCEEDED and
+// names the flag; limit_exceeded tells it apart from the program's own runtime errors.
+
+use std::time::{Duration, Instant};
+
+use super::extern_audit::ExternAudit;
+use super::extern_policy::ExternPolicy;
+use super::extern_timeout::ExternTimeouts;
+
+/// Start of every limit error
+pub const LIMIT_EXCEEDED: &str = "Resource limit exceeded";
+
+/// Steps between two checks of the time and heap limits
+pub const CHECK_INTERVAL: u64 = 1024;
+
+/// Limits of a run, from the command line or an embedding host (None: unlimited)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    pub max_depth: Option<usize>,
+    pub max_steps: Option<u64>,
+    pub max_time: Option<Duration>,
+    pub max_heap: Option<usize>,
+    /// Extern capabilities the program may reach
+    pub externs: ExternPolicy,
+    /// Where its extern calls are logged, if anywhere
+    pub audit: Option<ExternAudit>,
+    /// How long its extern calls may take
+    pub extern_timeouts: ExternTimeouts,
+}
+
+/// Call depth, step count and start time of a running program, checked against its limits
+#[derive(Debug, Clone)]
+pub struct Usage {
+    limits: ExecutionLimits,
+    depth: usize,
+    steps: u64,
+    started: Instant,
+}
+
+impl Default for Usage {
+    fn default() -> Self {
+        Usage::new(ExecutionLimits::default())
+    }
+}
+
+impl Usage {
+    pub fn new(limits: ExecutionLimits) -> Self {
+        Usage { limits, depth: 0, steps: 0, started: Instant::now() }
+    }
+
+    /// The limits being checked
+    pub fn limits(&self) -> &ExecutionLimits {
+        &self.limits
+    }
+
+    /// Count one loop iteration (or call)
+    pub fn step(&mut self) -> Result<(), String> {
+        self.steps += 1;
+        match (self.limits.max_steps, self.limits.max_time) {
+            (Some(max), _) if self.steps > max => {
+                Err(format!("{}: more than {} steps (--max-steps)", LIMIT_EXCEEDED, max))
+            }
+            (_, Some(max)) if self.steps.is_multiple_of(CHECK_INTERVAL) && self.started.elapsed() > max => {
+                Err(format!("{}: running for more than {} ms (--max-time)", LIMIT_EXCEEDED, max.as_millis()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the kernel should measure its heap now (and pass it to check_heap)
+    pub fn heap_check_due(&self) -> bool {
+        self.limits.max_heap.is_some() && self.steps.is_multiple_of(CHECK_INTERVAL)
+    }
+
+    /// Check the bytes held in variables against --max-heap
+    pub fn check_heap(&self, bytes: usize) -> Result<(), String> {
+        match self.limits.max_heap {
+            Some(max) if bytes > max => {
+                Err(format!("{}: more than {} bytes held in variables (--max-heap)", LIMIT_EXCEEDED, max))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Count a call of a user-defined function; pair with leave_call when it returns
+    pub fn enter_call(&mut self) -> Result<(), String> {
+        self.step()?;
+        match self.limits.max_depth {
+            Some(max) if self.depth >= max => {
+                Err(format!("{}: call depth over {} (--max-depth)", LIMIT_EXCEEDED, max))
+            }
+            _ => {
+                self.depth += 1;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn leave_call(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+/// Whether an error message is a limit error
+pub fn limit_exceeded(message: &str) -> bool {
+    message.starts_with(LIMIT_EXCEEDED)
+}
+
+/// Value of a --max-depth / --max-steps / --max-time / --max-heap flag: a positive integer
+pub fn parse_limit(flag: &str, value: &str) -> Result<u64, String> {
+    match value.parse::<u64>() {
+        Ok(limit) if limit > 0 => Ok(limit),
+        _ => Err(format!("{} expects a positive integer, got '{}'", flag, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_stops_at_limits() {
+        let mut usage = Usage::new(ExecutionLimits { max_depth: Some(2), max_steps: Some(4), ..Default::default() });
+        assert!(usage.enter_call().is_ok());
+        assert!(usage.enter_call().is_ok());
+        assert!(usage.enter_call().unwrap_err().contains("--max-depth"));
+        usage.leave_call();
+        assert!(usage.enter_call().is_ok());
+        assert!(usage.step().unwrap_err().contains("more than 4 steps"));
+        assert!(parse_limit("--max-steps", "0").is_err());
+    }
+
+    #[test]
+    fn time_and_heap_are_checked_at_intervals() {
+        let limits = ExecutionLimits { max_time: Some(Duration::ZERO), max_heap: Some(100), ..Default::default() };
+        let mut usage = Usage::new(limits);
+        for _ in 1..CHECK_INTERVAL {
+            usage.step().unwrap();
+            assert!(!usage.heap_check_due());
+        }
+        let error = usage.step().unwrap_err();
+        assert!(error.contains("--max-time"), "{}", error);
+        assert!(usage.heap_check_due());
+        assert!(usage.check_heap(100).is_ok());
+        assert!(limit_exceeded(&usage.check_heap(101).unwrap_err()));
+        assert!(!limit_exceeded("Division by zero"));
+    }
+}
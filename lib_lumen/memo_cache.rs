@@ -0,0 +1,184 @@
+// Memoization cache, shared by both kernels
+//
+// While MEMOIZATION = true, a user-defined function's result is kept under its name and
+// arguments, and a later call with the same arguments gets it back without running the
+// function. The cache holds at most `capacity` results (DEFAULT_CAPACITY until the
+// program calls memo_capacity); when it is full, the least recently used result is
+// evicted to make room. Programs see and control it through builtins:
+//
+//   memo_stats()        map of hits, misses, evictions, entries and capacity
+//   memo_stats(name)    the same counts for one function
+//   memo_clear()        drop every cached result (the counts are kept)
+//   memo_clear(name)    drop one function's results, e.g. after what it reads changed
+//   memo_capacity(n)    hold at most n results, evicting the oldest now if there are more
+//
+// A hit or a miss is only counted while MEMOIZATION is on (the kernels do not consult
+// the cache otherwise).
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Results kept when a program does not call memo_capacity
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Counts of a whole cache or of one function's part of it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+/// Function results by (function name, argument key), least recently used first out
+#[derive(Debug, Clone)]
+pub struct MemoCache<V> {
+    entries: HashMap<(String, String), (V, u64)>,
+    /// Last use of each entry, oldest first
+    recency: BTreeMap<u64, (String, String)>,
+    tick: u64,
+    capacity: usize,
+    functions: HashMap<String, MemoStats>,
+}
+
+impl<V: Clone> Default for MemoCache<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Clone> MemoCache<V> {
+    /// An empty cache of DEFAULT_CAPACITY results
+    pub fn new() -> Self {
+        MemoCache {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            capacity: DEFAULT_CAPACITY,
+            functions: HashMap::new(),
+        }
+    }
+
+    /// The result cached for `function` with arguments `key`, counted as a hit or a miss
+    pub fn get(&mut self, function: &str, key: String) -> Option<V> {
+        let key = (function.to_string(), key);
+        let stats = self.functions.entry(key.0.clone()).or_default();
+        let Some((value, last_use)) = self.entries.get_mut(&key) else {
+            stats.misses += 1;
+            return None;
+        };
+        stats.hits += 1;
+        self.tick += 1;
+        let key = self.recency.remove(last_use).expect("every entry has a recency");
+        *last_use = self.tick;
+        let value = value.clone();
+        self.recency.insert(self.tick, key);
+        Some(value)
+    }
+
+    /// Keep `value` as the result of `function` with arguments `key`
+    pub fn insert(&mut self, function: &str, key: String, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = (function.to_string(), key);
+        self.tick += 1;
+        if let Some((_, last_use)) = self.entries.get(&key) {
+            self.recency.remove(last_use);
+        } else {
+            self.evict_to(self.capacity - 1);
+            self.functions.entry(key.0.clone()).or_default().entries += 1;
+        }
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+    }
+
+    /// Drop the results of `function`, or of every function
+    pub fn clear(&mut self, function: Option<&str>) {
+        match function {
+            None => {
+                self.entries.clear();
+                self.recency.clear();
+                self.functions.values_mut().for_each(|stats| stats.entries = 0);
+            }
+            Some(function) => {
+                self.recency.retain(|_, (name, _)| name != function);
+                self.entries.retain(|(name, _), _| name != function);
+                if let Some(stats) = self.functions.get_mut(function) {
+                    stats.entries = 0;
+                }
+            }
+        }
+    }
+
+    /// Hold at most `capacity` results, evicting the least recently used ones beyond it
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict_to(capacity);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Counts of `function`, or of the whole cache
+    pub fn stats(&self, function: Option<&str>) -> MemoStats {
+        match function {
+            Some(function) => self.functions.get(function).copied().unwrap_or_default(),
+            None => self.functions.values().fold(MemoStats::default(), |total, stats| MemoStats {
+                hits: total.hits + stats.hits,
+                misses: total.misses + stats.misses,
+                evictions: total.evictions + stats.evictions,
+                entries: total.entries + stats.entries,
+            }),
+        }
+    }
+
+    fn evict_to(&mut self, size: usize) {
+        while self.entries.len() > size {
+            let Some((_, key)) = self.recency.pop_first() else { break };
+            self.entries.remove(&key);
+            let stats = self.functions.entry(key.0).or_default();
+            stats.entries -= 1;
+            stats.evictions += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_least_recently_used_result() {
+        let mut cache = MemoCache::new();
+        cache.set_capacity(2);
+        cache.insert("f", "1".to_string(), 10);
+        cache.insert("f", "2".to_string(), 20);
+        // Using f(1) makes f(2) the oldest
+        assert_eq!(cache.get("f", "1".to_string()), Some(10));
+        cache.insert("g", "1".to_string(), 30);
+        assert_eq!(cache.get("f", "2".to_string()), None);
+        assert_eq!(cache.get("f", "1".to_string()), Some(10));
+        assert_eq!(cache.stats(Some("f")), MemoStats { hits: 2, misses: 1, evictions: 1, entries: 1 });
+        assert_eq!(cache.stats(None), MemoStats { hits: 2, misses: 1, evictions: 1, entries: 2 });
+
+        cache.set_capacity(1);
+        assert_eq!(cache.stats(None).entries, 1);
+        assert_eq!(cache.get("f", "1".to_string()), Some(10));
+    }
+
+    #[test]
+    fn clears_one_function_or_all() {
+        let mut cache = MemoCache::new();
+        cache.insert("f", "1".to_string(), 1);
+        cache.insert("g", "1".to_string(), 2);
+        cache.clear(Some("f"));
+        assert_eq!(cache.get("f", "1".to_string()), None);
+        assert_eq!(cache.get("g", "1".to_string()), Some(2));
+        cache.clear(None);
+        assert_eq!(cache.stats(None), MemoStats { hits: 1, misses: 1, evictions: 0, entries: 0 });
+        cache.set_capacity(0);
+        cache.insert("f", "1".to_string(), 1);
+        assert_eq!(cache.get("f", "1".to_string()), None);
+    }
+}
@@ -0,0 +1,178 @@
+// The `net` extern backend: TCP clients and servers, shared by both kernels
+//
+//   extern("net:connect", address)    a connection to "host:port"
+//   extern("net:listen", address)     a listener bound to "host:port"
+//   extern("net:accept", listener)    the next connection to a listener (waits for one)
+//   extern("net:send", conn, text)    write all of text to the connection
+//   extern("net:recv", conn)          the text that has arrived (waits for some), at most
+//                                     64 KiB at a time; "" once the other end has closed
+//   extern("net:close", handle)       close a connection or stop a listener
+//
+// Connections and listeners are handles: opaque values the program passes back, written
+// as <net connection #2 127.0.0.1:8080> (the other end's address) or
+// <net listener #1 127.0.0.1:8080>. Programs cannot make one from a string. The open
+// sockets belong to the run that opened them (its Sockets, which the kernel keeps with the
+// rest of the run's state): one run cannot reach another's handles, and whatever a program
+// leaves open is closed when its run ends. Bytes that are not UTF-8 arrive as
+// U+FFFD. A failure is an error naming the selector ("net:connect: 127.0.0.1:9: ..."), coded
+// by its kind with the host's errno; a handle closed already gives code "closed".
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, PoisonError};
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "net:" in a selector
+pub const CAPABILITIES: &[&str] = &["accept", "close", "connect", "listen", "recv", "send"];
+
+/// Largest read of one recv
+const RECV_BYTES: usize = 64 * 1024;
+
+/// An argument as the backend sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arg<'a> {
+    Text(&'a str),
+    Handle(&'a str),
+    Other,
+}
+
+/// What a capability returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// A new handle, by the name the kernel wraps as an opaque value
+    Handle(String),
+    Text(String),
+    Null,
+}
+
+enum Socket {
+    Connection(TcpStream),
+    Listener(TcpListener),
+}
+
+/// The open sockets of a run by handle name, and the number of the last one; dropping it
+/// closes them
+#[derive(Default)]
+pub struct Sockets(Mutex<(u64, Vec<(String, Socket)>)>);
+
+impl std::fmt::Debug for Sockets {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Sockets({} open)", self.0.lock().unwrap_or_else(PoisonError::into_inner).1.len())
+    }
+}
+
+/// Run capability `name` of the backend on its arguments, with the run's `sockets`
+pub fn call(sockets: &Sockets, name: &str, args: &[Arg]) -> Result<Outcome, ExternError> {
+    let arity = match name {
+        "send" => 2,
+        "connect" | "listen" | "accept" | "recv" | "close" => 1,
+        _ => return Err(ExternError::new("unavailable", format!("net has no capability '{}'", name))),
+    };
+    if args.len() != arity {
+        return Err(ExternError::invalid_argument(format!("net:{} expects {} arguments, got {}", name, arity, args.len())));
+    }
+    let failed = |e: std::io::Error| ExternError::io(format!("net:{}: {}", name, e), &e);
+
+    match (name, args) {
+        ("connect", [Arg::Text(address)]) => {
+            let stream = TcpStream::connect(address).map_err(|e| ExternError::io(format!("net:connect: {}: {}", address, e), &e))?;
+            Ok(Outcome::Handle(sockets.open(Socket::Connection(stream), address)))
+        }
+        ("listen", [Arg::Text(address)]) => {
+            let listener = TcpListener::bind(address).map_err(|e| ExternError::io(format!("net:listen: {}: {}", address, e), &e))?;
+            let bound = listener.local_addr().map_err(failed)?.to_string();
+            Ok(Outcome::Handle(sockets.open(Socket::Listener(listener), &bound)))
+        }
+        ("connect" | "listen", _) => Err(ExternError::invalid_argument(format!("net:{} address must be a string \"host:port\"", name))),
+        (_, [Arg::Handle(handle), rest @ ..]) => {
+            // Clone the socket so a wait (accept, recv) does not hold the lock
+            let socket = {
+                let open = sockets.0.lock().unwrap_or_else(PoisonError::into_inner);
+                let (_, socket) = open.1.iter().find(|(key, _)| key == handle).ok_or_else(|| ExternError::new("closed", format!("net:{}: {} is closed", name, handle)))?;
+                match socket {
+                    Socket::Connection(stream) => Socket::Connection(stream.try_clone().map_err(failed)?),
+                    Socket::Listener(listener) => Socket::Listener(listener.try_clone().map_err(failed)?),
+                }
+            };
+            match (name, socket, rest) {
+                ("close", _, []) => {
+                    sockets.0.lock().unwrap_or_else(PoisonError::into_inner).1.retain(|(key, _)| key != handle);
+                    Ok(Outcome::Null)
+                }
+                ("accept", Socket::Listener(listener), []) => {
+                    let (stream, peer) = listener.accept().map_err(failed)?;
+                    Ok(Outcome::Handle(sockets.open(Socket::Connection(stream), &peer.to_string())))
+                }
+                ("send", Socket::Connection(mut stream), [Arg::Text(text)]) => {
+                    stream.write_all(text.as_bytes()).and_then(|_| stream.flush()).map_err(failed)?;
+                    Ok(Outcome::Null)
+                }
+                ("send", Socket::Connection(_), _) => Err(ExternError::invalid_argument("net:send text must be a string")),
+                ("recv", Socket::Connection(mut stream), []) => {
+                    let mut buffer = vec![0; RECV_BYTES];
+                    let count = stream.read(&mut buffer).map_err(failed)?;
+                    Ok(Outcome::Text(String::from_utf8_lossy(&buffer[..count]).into_owned()))
+                }
+                ("accept", _, _) => Err(ExternError::invalid_argument(format!("net:accept: {} is not a listener", handle))),
+                _ => Err(ExternError::invalid_argument(format!("net:{}: {} is not a connection", name, handle))),
+            }
+        }
+        _ => Err(ExternError::invalid_argument(format!("net:{} needs a connection or listener handle", name))),
+    }
+}
+
+impl Sockets {
+    /// Keep an open socket; returns its handle name
+    fn open(&self, socket: Socket, address: &str) -> String {
+        let mut open = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        open.0 += 1;
+        let kind = match socket {
+            Socket::Connection(_) => "connection",
+            Socket::Listener(_) => "listener",
+        };
+        let handle = format!("<net {} #{} {}>", kind, open.0, address);
+        open.1.push((handle.clone(), socket));
+        handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(outcome: Result<Outcome, ExternError>) -> String {
+        match outcome {
+            Ok(Outcome::Handle(handle)) => handle,
+            other => panic!("expected a handle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_client_and_a_server_talk_over_loopback() {
+        let sockets = Sockets::default();
+        let listener = handle(call(&sockets, "listen", &[Arg::Text("127.0.0.1:0")]));
+        let address = listener.trim_end_matches('>').rsplit(' ').next().unwrap().to_string();
+        let client = handle(call(&sockets, "connect", &[Arg::Text(&address)]));
+        let server = handle(call(&sockets, "accept", &[Arg::Handle(&listener)]));
+        assert!(server.starts_with("<net connection #"), "{}", server);
+
+        call(&sockets, "send", &[Arg::Handle(&client), Arg::Text("ping")]).unwrap();
+        assert_eq!(call(&sockets, "recv", &[Arg::Handle(&server)]), Ok(Outcome::Text("ping".to_string())));
+        call(&sockets, "send", &[Arg::Handle(&server), Arg::Text("pong")]).unwrap();
+        assert_eq!(call(&sockets, "recv", &[Arg::Handle(&client)]), Ok(Outcome::Text("pong".to_string())));
+
+        call(&sockets, "close", &[Arg::Handle(&server)]).unwrap();
+        assert_eq!(call(&sockets, "recv", &[Arg::Handle(&client)]), Ok(Outcome::Text(String::new())));
+        assert_eq!(call(&sockets, "recv", &[Arg::Handle(&server)]).unwrap_err().code, "closed");
+        assert!(call(&sockets, "recv", &[Arg::Handle(&listener)]).unwrap_err().message.ends_with("is not a connection"));
+        assert!(call(&sockets, "send", &[Arg::Text(&client), Arg::Text("x")]).unwrap_err().message.contains("handle"));
+        call(&sockets, "close", &[Arg::Handle(&client)]).unwrap();
+
+        // Another run's handles are not this one's; the listener closes with its run
+        let other = Sockets::default();
+        assert_eq!(call(&other, "accept", &[Arg::Handle(&listener)]).unwrap_err().code, "closed");
+        drop(sockets);
+        assert!(TcpStream::connect(&address).is_err());
+    }
+}
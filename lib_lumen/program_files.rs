@@ -0,0 +1,74 @@
+// Programs split across several source files
+// Included by both kernel binaries (like host_config.rs) so they accept the same inputs.
+//
+// The files run as one program in a shared environment: their sources are joined in
+// order, so functions and variables defined in an earlier file are visible in later ones.
+// A directory contributes its .lm files sorted by file name; each file starts at the
+// top level (a block left open at the end of one file is closed before the next).
+
+use std::fs;
+use std::path::Path;
+
+/// One source file of a program
+pub struct ProgramFile {
+    pub name: String,
+    pub text: String,
+}
+
+/// The program path at the start of `args` and any existing .lm files right after it
+/// (program arguments follow; `--` separates them when one would look like a file)
+pub fn leading_paths(args: &[String]) -> Vec<String> {
+    let more = args[1..]
+        .iter()
+        .take_while(|arg| arg.ends_with(".lm") && Path::new(arg.as_str()).is_file())
+        .cloned();
+    args[..1].iter().cloned().chain(more).collect()
+}
+
+/// Expand directories into their .lm files (sorted by name); other paths are kept as given
+pub fn collect(paths: &[String]) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !Path::new(path).is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let entries = fs::read_dir(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let mut found: Vec<String> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "lm"))
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        if found.is_empty() {
+            return Err(format!("No .lm files in directory {}", path));
+        }
+        found.sort();
+        files.extend(found);
+    }
+    Ok(files)
+}
+
+/// Read each file
+pub fn read(paths: &[String]) -> Result<Vec<ProgramFile>, String> {
+    paths
+        .iter()
+        .map(|path| {
+            fs::read_to_string(path)
+                .map(|text| ProgramFile { name: path.clone(), text })
+                .map_err(|e| format!("Failed to read {}: {}", path, e))
+        })
+        .collect()
+}
+
+/// Source text of the whole program: every file in order
+/// A newline is added between files where one does not end with it.
+pub fn join(files: &[ProgramFile]) -> String {
+    let mut source = String::new();
+    for file in files {
+        if !source.is_empty() && !source.ends_with('\n') {
+            source.push('\n');
+        }
+        source.push_str(&file.text);
+    }
+    source
+}
@@ -0,0 +1,121 @@
+// The `random` extern backend: random numbers, shared by both kernels
+//
+//   extern("random:int", low, high)   an integer from low to high, both included
+//   extern("random:float")            a REAL from 0 up to (not including) 1
+//
+// so a program can shuffle, sample or roll dice:
+//
+//   roll = extern("random:int", 1, 6)
+//
+// The numbers come from the host's entropy, fresh in every run (there is no seed), and are
+// not for cryptography. Compiled to wasm32-wasi the backend draws them from WASI's
+// random_get, so a WASM runtime supplies them; elsewhere from the keys the standard library
+// seeds its hash maps with, which come from the operating system. A kernel passes integer
+// arguments that fit an i64 as Int.
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "random:" in a selector
+pub const CAPABILITIES: &[&str] = &["float", "int"];
+
+/// Random bits in a random:float; the REAL is a multiple of 2^-FLOAT_BITS
+pub const FLOAT_BITS: u32 = 53;
+
+/// An argument as the backend sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arg {
+    Int(i64),
+    Other,
+}
+
+/// What a capability returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Int(i64),
+    /// The REAL numerator / 2^FLOAT_BITS
+    Fraction(u64),
+}
+
+/// Run capability `name` of the backend on its arguments
+pub fn call(name: &str, args: &[Arg]) -> Result<Outcome, ExternError> {
+    match (name, args) {
+        ("int", [Arg::Int(low), Arg::Int(high)]) if low <= high => {
+            let span = (*high as i128 - *low as i128) as u128 + 1;
+            Ok(Outcome::Int((*low as i128 + below(span)? as i128) as i64))
+        }
+        ("int", [Arg::Int(low), Arg::Int(high)]) => Err(ExternError::invalid_argument(format!("random:int: low {} is above high {}", low, high))),
+        ("int", [_, _]) => Err(ExternError::invalid_argument("random:int expects two integers that fit 64 bits")),
+        ("int", _) => Err(ExternError::invalid_argument(format!("random:int expects 2 arguments, got {}", args.len()))),
+        ("float", []) => Ok(Outcome::Fraction(next()? >> (64 - FLOAT_BITS))),
+        ("float", _) => Err(ExternError::invalid_argument(format!("random:float expects 0 arguments, got {}", args.len()))),
+        _ => Err(ExternError::new("unavailable", format!("random has no capability '{}'", name))),
+    }
+}
+
+/// A uniform number from 0 up to (not including) `span`, at most 2^64
+fn below(span: u128) -> Result<u64, String> {
+    if span > u64::MAX as u128 {
+        return next();
+    }
+    // Draws past the last whole multiple of span would favour small numbers
+    let span = span as u64;
+    let limit = u64::MAX - u64::MAX % span;
+    loop {
+        let n = next()?;
+        if n < limit {
+            return Ok(n % span);
+        }
+    }
+}
+
+/// 64 random bits from WASI
+#[cfg(all(target_os = "wasi", not(target_env = "p2")))]
+fn next() -> Result<u64, String> {
+    #[link(wasm_import_module = "wasi_snapshot_preview1")]
+    extern "C" {
+        fn random_get(buf: *mut u8, len: usize) -> i32;
+    }
+    let mut bytes = [0u8; 8];
+    match unsafe { random_get(bytes.as_mut_ptr(), bytes.len()) } {
+        0 => Ok(u64::from_le_bytes(bytes)),
+        errno => Err(format!("random: WASI random_get failed (errno {})", errno)),
+    }
+}
+
+/// 64 random bits: a count hashed with keys the operating system chose
+#[cfg(not(all(target_os = "wasi", not(target_env = "p2"))))]
+fn next() -> Result<u64, String> {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static DRAWS: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(DRAWS.fetch_add(1, Ordering::Relaxed));
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_stay_in_their_range() {
+        let mut seen = [false; 6];
+        for _ in 0..600 {
+            let Ok(Outcome::Int(roll)) = call("int", &[Arg::Int(1), Arg::Int(6)]) else { panic!("a roll is an integer") };
+            assert!((1..=6).contains(&roll), "{}", roll);
+            seen[roll as usize - 1] = true;
+        }
+        assert!(seen.iter().all(|&s| s), "{:?}", seen);
+        assert_eq!(call("int", &[Arg::Int(-3), Arg::Int(-3)]), Ok(Outcome::Int(-3)));
+        assert!(call("int", &[Arg::Int(i64::MIN), Arg::Int(i64::MAX)]).is_ok());
+
+        let Ok(Outcome::Fraction(n)) = call("float", &[]) else { panic!("a float is a fraction") };
+        assert!(n < 1 << FLOAT_BITS);
+        assert_ne!(call("float", &[]), call("float", &[]));
+        assert!(call("int", &[Arg::Int(2), Arg::Int(1)]).unwrap_err().message.contains("above"));
+        assert!(call("int", &[Arg::Other, Arg::Int(1)]).is_err());
+        assert!(call("float", &[Arg::Int(1)]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// Value rendering, shared by both kernels
+//
+// A kernel describes a value as a Node (the kernels' value types differ) and render writes
+// it in one of two styles, so arrays, maps, strings and numbers look the same whichever
+// kernel ran the program:
+//
+//   DISPLAY   print and array_to_string(): strings as they are, at every level, and
+//             nothing shortened
+//   REPR      repr(), debug_info and values in error messages: strings quoted and escaped
+//             at every level (map keys too), and huge values shortened:
+//
+//               a run of more than 40 digits        12345678901234567890...(1000 digits)...12345678901234567890
+//               a string of more than 80 chars      "the first 80 chars"...(1000 chars)
+//               more than 20 elements or entries    [1, 2, ..., 20, ...(80 more)]
+//               nesting deeper than 10              [...] or {...}
+//
+// Rationals are an atom like any number ("1/3"), so each of their digit runs is shortened
+// on its own. Bytes are written b"..." in both styles, printable ASCII as it is and any
+// other byte as \xNN; REPR cuts them at max_chars bytes: b"\x89PNG"...(1000 bytes).
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// A value as the renderer sees it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node<'a> {
+    /// A number, boolean, null or other value written as it is
+    Atom(Cow<'a, str>),
+    /// A string: quoted in REPR
+    Text(&'a str),
+    /// Binary data: always quoted
+    Bytes(&'a [u8]),
+    List(Vec<Node<'a>>),
+    Map(Vec<(&'a str, Node<'a>)>),
+}
+
+/// How render writes a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    /// Quote and escape strings (and map keys)
+    pub quote: bool,
+    /// Longest digit run, string, list or map, and deepest nesting written in full
+    pub max_digits: Option<usize>,
+    pub max_chars: Option<usize>,
+    pub max_items: Option<usize>,
+    pub max_depth: Option<usize>,
+}
+
+/// What print shows
+pub const DISPLAY: Style = Style { quote: false, max_digits: None, max_chars: None, max_items: None, max_depth: None };
+
+/// What repr(), debug_info and error messages show
+pub const REPR: Style = Style { quote: true, max_digits: Some(40), max_chars: Some(80), max_items: Some(20), max_depth: Some(10) };
+
+/// `node` written in `style`
+pub fn render(node: &Node, style: &Style) -> String {
+    let mut out = String::new();
+    write_node(&mut out, node, style, 0);
+    out
+}
+
+fn write_node(out: &mut String, node: &Node, style: &Style, depth: usize) {
+    let nested = matches!(node, Node::List(_) | Node::Map(_));
+    if nested && style.max_depth.is_some_and(|max| depth >= max) {
+        out.push_str(if matches!(node, Node::List(_)) { "[...]" } else { "{...}" });
+        return;
+    }
+    match node {
+        Node::Atom(text) => write_atom(out, text, style),
+        Node::Text(text) => write_text(out, text, style),
+        Node::Bytes(bytes) => write_bytes(out, bytes, style),
+        Node::List(items) => {
+            out.push('[');
+            write_items(out, items, style, |out, item| write_node(out, item, style, depth + 1));
+            out.push(']');
+        }
+        Node::Map(entries) => {
+            out.push('{');
+            write_items(out, entries, style, |out, (key, value)| {
+                write_text(out, key, style);
+                out.push_str(": ");
+                write_node(out, value, style, depth + 1);
+            });
+            out.push('}');
+        }
+    }
+}
+
+/// Items separated by ", ", the ones past max_items counted instead of written
+fn write_items<T>(out: &mut String, items: &[T], style: &Style, mut write: impl FnMut(&mut String, &T)) {
+    let shown = style.max_items.map_or(items.len(), |max| items.len().min(max));
+    for (i, item) in items[..shown].iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write(out, item);
+    }
+    if shown < items.len() {
+        let _ = write!(out, ", ...({} more)", items.len() - shown);
+    }
+}
+
+/// An atom, each digit run longer than max_digits shortened to its two ends and its length
+fn write_atom(out: &mut String, text: &str, style: &Style) {
+    let Some(max) = style.max_digits else {
+        out.push_str(text);
+        return;
+    };
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let digits = &rest[..end];
+        if digits.len() > max {
+            let half = max / 2;
+            let _ = write!(out, "{}...({} digits)...{}", &digits[..half], digits.len(), &digits[digits.len() - half..]);
+        } else {
+            out.push_str(digits);
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+}
+
+/// A string, quoted and escaped (and past max_chars, cut and counted) when the style quotes
+fn write_text(out: &mut String, text: &str, style: &Style) {
+    if !style.quote {
+        out.push_str(text);
+        return;
+    }
+    let count = text.chars().count();
+    let shown = style.max_chars.map_or(count, |max| count.min(max));
+    out.push('"');
+    for ch in text.chars().take(shown) {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            ch if ch.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    if shown < count {
+        let _ = write!(out, "...({} chars)", count);
+    }
+}
+
+/// Bytes as b"...", past max_chars cut and counted
+fn write_bytes(out: &mut String, bytes: &[u8], style: &Style) {
+    let shown = style.max_chars.map_or(bytes.len(), |max| bytes.len().min(max));
+    out.push_str("b\"");
+    for &byte in &bytes[..shown] {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            b'\r' => out.push_str("\\r"),
+            b' '..=b'~' => out.push(byte as char),
+            byte => {
+                let _ = write!(out, "\\x{:02x}", byte);
+            }
+        }
+    }
+    out.push('"');
+    if shown < bytes.len() {
+        let _ = write!(out, "...({} bytes)", bytes.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_and_shortens_only_in_repr() {
+        let digits = "7".repeat(100);
+        let node = Node::List(vec![
+            Node::Atom(Cow::Borrowed("-1/3")),
+            Node::Text("say \"hi\"\n"),
+            Node::Map(vec![("key", Node::Atom(Cow::Borrowed(&digits)))]),
+        ]);
+        assert_eq!(render(&node, &DISPLAY), format!("[-1/3, say \"hi\"\n, {{key: {}}}]", digits));
+        assert_eq!(
+            render(&node, &REPR),
+            "[-1/3, \"say \\\"hi\\\"\\n\", {\"key\": 77777777777777777777...(100 digits)...77777777777777777777}]"
+        );
+
+        let png = [0x89, b'P', b'N', b'G', b'\r', b'\n', b'"', 0];
+        assert_eq!(render(&Node::List(vec![Node::Bytes(&png)]), &DISPLAY), "[b\"\\x89PNG\\r\\n\\\"\\x00\"]");
+        assert_eq!(render(&Node::Bytes(&[b'a'; 100]), &REPR), format!("b\"{}\"...(100 bytes)", "a".repeat(80)));
+
+        let long = "é".repeat(100);
+        assert_eq!(render(&Node::Text(&long), &REPR), format!("\"{}\"...(100 chars)", "é".repeat(80)));
+        let many = Node::List((0..25).map(|i| Node::Atom(Cow::Owned(i.to_string()))).collect());
+        assert!(render(&many, &REPR).ends_with(", 18, 19, ...(5 more)]"));
+        assert_eq!(render(&many, &DISPLAY).matches(", ").count(), 24);
+
+        let mut deep = Node::List(Vec::new());
+        for _ in 0..12 {
+            deep = Node::List(vec![deep]);
+        }
+        assert_eq!(render(&deep, &REPR), format!("{}[...]{}", "[".repeat(10), "]".repeat(10)));
+    }
+}
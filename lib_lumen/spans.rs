@@ -0,0 +1,88 @@
+// Structured tracing through the `tracing` crate (cargo feature "tracing"), shared by both
+// kernels. Built with `--features tracing`, a run reports to whatever subscriber the host
+// installed, under target "lumen":
+//
+//   span  stage      kernel = "microcode", stage = "reduce"   INFO, around each pipeline
+//                                                             stage (ingest/lex, structure,
+//                                                             reduce/parse, execute, ...)
+//   event statement  line = 12, column = 5                    TRACE, as each statement of
+//                                                             the program starts
+//
+// Statement lines are the program's own, as in its error messages: a host that lexes the
+// program after a prelude says where it starts with program_starts_at, and the prelude's
+// statements are not reported. Without the feature the crate is not a dependency and
+// everything here compiles to nothing.
+
+use std::cell::Cell;
+
+thread_local! {
+    /// The line of the lexed text the program starts on (after any prelude)
+    static FIRST_LINE: Cell<usize> = const { Cell::new(1) };
+}
+
+/// The program run on this thread from now on starts on line `first_line` of the text
+/// the kernel lexes (1 without a prelude)
+pub fn program_starts_at(first_line: usize) {
+    FIRST_LINE.with(|first| first.set(first_line));
+}
+
+/// The span of one pipeline stage, entered until dropped
+#[must_use]
+pub struct Stage {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+/// Enter the span of `stage` of `kernel`
+#[inline]
+pub fn stage(kernel: &'static str, stage: &'static str) -> Stage {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (kernel, stage);
+    Stage {
+        #[cfg(feature = "tracing")]
+        _span: tracing::info_span!(target: "lumen", "stage", kernel, stage).entered(),
+    }
+}
+
+/// Whether a subscriber takes statement events, so statements need reporting at all
+#[cfg(feature = "tracing")]
+pub fn statements_observed() -> bool {
+    tracing::enabled!(target: "lumen", tracing::Level::TRACE)
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub fn statements_observed() -> bool {
+    false
+}
+
+/// The statement at `line`:`column` of the lexed text starts
+#[inline]
+pub fn statement(line: usize, column: usize) {
+    #[cfg(feature = "tracing")]
+    if let Some(line) = program_line(line) {
+        tracing::trace!(target: "lumen", line, column, "statement");
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (line, column);
+}
+
+/// Line `line` of the lexed text as a line of the program, or None in the prelude
+pub fn program_line(line: usize) -> Option<usize> {
+    let first_line = FIRST_LINE.with(Cell::get);
+    (line >= first_line).then(|| line + 1 - first_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_lines_from_the_start_of_the_program() {
+        assert_eq!(program_line(3), Some(3));
+        program_starts_at(10);
+        assert_eq!(program_line(9), None);
+        assert_eq!(program_line(12), Some(3));
+        program_starts_at(1);
+    }
+}
@@ -0,0 +1,78 @@
+// The `sys` extern backend: the host the program runs on, shared by both kernels
+//
+//   extern("sys:platform")    the operating system: "linux", "macos", "windows", "freebsd",
+//                             "wasi", ... (Rust's std::env::consts::OS)
+//   extern("sys:arch")        the CPU architecture: "x86_64", "aarch64", "wasm32", ...
+//   extern("sys:cpu_count")   how many threads can run at once (at least 1)
+//   extern("sys:cwd")         the working directory, which relative fs paths start from
+//   extern("sys:pid")         the process id
+//
+// so a script can adapt to its host instead of hard-coding it:
+//
+//   if extern("sys:platform") == "windows"
+//       separator = "\\"
+//
+// The answers describe the machine, so a sandboxed run reaches them only with --allow-sys.
+// Compiled to wasm32-wasi there is no process id: sys:pid fails with code "unsupported".
+
+use std::env;
+use std::thread;
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "sys:" in a selector
+pub const CAPABILITIES: &[&str] = &["arch", "cpu_count", "cwd", "pid", "platform"];
+
+/// What a capability returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Int(i64),
+    Text(String),
+}
+
+/// Run capability `name` of the backend (it takes no arguments)
+pub fn call(name: &str, args: usize) -> Result<Outcome, ExternError> {
+    if !CAPABILITIES.contains(&name) {
+        return Err(ExternError::new("unavailable", format!("sys has no capability '{}'", name)));
+    }
+    if args != 0 {
+        return Err(ExternError::invalid_argument(format!("sys:{} expects 0 arguments, got {}", name, args)));
+    }
+    Ok(match name {
+        "platform" => Outcome::Text(env::consts::OS.to_string()),
+        "arch" => Outcome::Text(env::consts::ARCH.to_string()),
+        "cpu_count" => Outcome::Int(thread::available_parallelism().map_or(1, |n| n.get() as i64)),
+        "cwd" => {
+            let cwd = env::current_dir().map_err(|e| ExternError::io(format!("sys:cwd: {}", e), &e))?;
+            Outcome::Text(cwd.to_string_lossy().into_owned())
+        }
+        _ => Outcome::Int(pid()?),
+    })
+}
+
+#[cfg(not(target_os = "wasi"))]
+fn pid() -> Result<i64, ExternError> {
+    Ok(i64::from(std::process::id()))
+}
+
+#[cfg(target_os = "wasi")]
+fn pid() -> Result<i64, ExternError> {
+    Err(ExternError::new("unsupported", "sys:pid: WASI has no process id"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_this_host() {
+        assert_eq!(call("platform", 0), Ok(Outcome::Text(env::consts::OS.to_string())));
+        assert_eq!(call("arch", 0), Ok(Outcome::Text(env::consts::ARCH.to_string())));
+        assert!(matches!(call("cpu_count", 0), Ok(Outcome::Int(n)) if n >= 1));
+        assert_eq!(call("pid", 0), Ok(Outcome::Int(i64::from(std::process::id()))));
+        let cwd = env::current_dir().unwrap().to_string_lossy().into_owned();
+        assert_eq!(call("cwd", 0), Ok(Outcome::Text(cwd)));
+        assert_eq!(call("pid", 1), Err(ExternError::invalid_argument("sys:pid expects 0 arguments, got 1")));
+        assert!(call("hostname", 0).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+// The `time` extern backend: the clock, shared by both kernels
+//
+//   extern("time:now")                      seconds since 1970-01-01 00:00:00 UTC (an integer)
+//   extern("time:monotonic_millis")         milliseconds on a clock that never goes back
+//                                           (0 at its first use), for measuring durations
+//   extern("time:format", seconds)          seconds since 1970 as "2024-03-09T14:05:00Z"
+//   extern("time:format", seconds, pattern) ... written by the pattern instead:
+//
+//     %Y year   %m month 01-12   %d day 01-31   %H hour 00-23   %M minute   %S second
+//     %a Mon-Sun   %b Jan-Dec   %j day of the year 001-366   %% a percent sign
+//
+// so a program can timestamp its output and time its own work:
+//
+//   start = extern("time:monotonic_millis")
+//   ...
+//   print(extern("time:format", extern("time:now"), "%H:%M:%S") . " took " . (extern("time:monotonic_millis") - start) . "ms")
+//
+// Times are UTC (there are no time zones); seconds before 1970 are negative. A kernel passes
+// integer arguments that fit an i64 as Int and strings as Text.
+
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use super::extern_error::ExternError;
+
+/// The capabilities of the backend, as they follow "time:" in a selector
+pub const CAPABILITIES: &[&str] = &["format", "monotonic_millis", "now"];
+
+/// The pattern of time:format without one
+pub const DEFAULT_PATTERN: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTH_NAMES: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// An argument as the backend sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arg<'a> {
+    Int(i64),
+    Text(&'a str),
+    Other,
+}
+
+/// What a capability returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Int(i64),
+    Text(String),
+}
+
+/// When the clock for monotonic_millis started (its first use)
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Run capability `name` of the backend on its arguments
+pub fn call(name: &str, args: &[Arg]) -> Result<Outcome, ExternError> {
+    match (name, args) {
+        ("now", []) => {
+            let seconds = match SystemTime::now().duration_since(UNIX_EPOCH) {
+                Ok(since) => since.as_secs() as i64,
+                Err(before) => -(before.duration().as_secs() as i64),
+            };
+            Ok(Outcome::Int(seconds))
+        }
+        ("monotonic_millis", []) => Ok(Outcome::Int(STARTED.get_or_init(Instant::now).elapsed().as_millis() as i64)),
+        ("format", [Arg::Int(seconds)]) => Ok(Outcome::Text(format(*seconds, DEFAULT_PATTERN)?)),
+        ("format", [Arg::Int(seconds), Arg::Text(pattern)]) => {
+            Ok(Outcome::Text(format(*seconds, pattern).map_err(ExternError::invalid_argument)?))
+        }
+        ("format", [_] | [_, _]) => Err(ExternError::invalid_argument("time:format expects integer seconds and a string pattern")),
+        ("format", _) => Err(ExternError::invalid_argument(format!("time:format expects 1 or 2 arguments, got {}", args.len()))),
+        ("now" | "monotonic_millis", _) => Err(ExternError::invalid_argument(format!("time:{} expects 0 arguments, got {}", name, args.len()))),
+        _ => Err(ExternError::new("unavailable", format!("time has no capability '{}'", name))),
+    }
+}
+
+/// `seconds` since 1970 (UTC) written by `pattern`
+pub fn format(seconds: i64, pattern: &str) -> Result<String, String> {
+    let days = seconds.div_euclid(86_400);
+    let of_day = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", year)),
+            Some('m') => out.push_str(&format!("{:02}", month)),
+            Some('d') => out.push_str(&format!("{:02}", day)),
+            Some('H') => out.push_str(&format!("{:02}", of_day / 3600)),
+            Some('M') => out.push_str(&format!("{:02}", of_day / 60 % 60)),
+            Some('S') => out.push_str(&format!("{:02}", of_day % 60)),
+            // 1970-01-01 was a Thursday
+            Some('a') => out.push_str(DAY_NAMES[(days + 3).rem_euclid(7) as usize]),
+            Some('b') => out.push_str(MONTH_NAMES[month as usize - 1]),
+            Some('j') => out.push_str(&format!("{:03}", days - days_from_civil(year, 1, 1) + 1)),
+            Some('%') => out.push('%'),
+            Some(other) => return Err(format!("time:format: unknown pattern code '%{}'", other)),
+            None => return Err("time:format: pattern ends with '%'".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+/// The (year, month, day) of a day number counted from 1970-01-01 (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The day number, counted from 1970-01-01, of a (year, month, day)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc_dates_and_runs_the_clocks() {
+        let text = |seconds, pattern| format(seconds, pattern).unwrap();
+        assert_eq!(text(0, DEFAULT_PATTERN), "1970-01-01T00:00:00Z");
+        assert_eq!(text(1_709_993_100, "%a %d %b %Y %H:%M:%S, day %j, 100%%"), "Sat 09 Mar 2024 14:05:00, day 069, 100%");
+        assert_eq!(text(951_782_400, "%Y-%m-%d %a"), "2000-02-29 Tue");
+        assert_eq!(text(-1, DEFAULT_PATTERN), "1969-12-31T23:59:59Z");
+        assert!(format(0, "%q").unwrap_err().contains("%q"));
+
+        let Ok(Outcome::Int(now)) = call("now", &[]) else { panic!("now is an integer") };
+        assert!(now > 1_700_000_000);
+        let Ok(Outcome::Int(first)) = call("monotonic_millis", &[]) else { panic!("millis are an integer") };
+        let Ok(Outcome::Int(second)) = call("monotonic_millis", &[]) else { panic!("millis are an integer") };
+        assert!(first >= 0 && second >= first);
+        assert_eq!(call("format", &[Arg::Int(0), Arg::Text("%Y")]), Ok(Outcome::Text("1970".to_string())));
+        assert!(call("format", &[Arg::Text("0")]).is_err());
+        assert!(call("now", &[Arg::Int(1)]).is_err());
+    }
+}
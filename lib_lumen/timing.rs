@@ -0,0 +1,105 @@
+// Stage timings for --timing, shared by both kernels
+// Included like control_flow.rs so both kernels report in the same formats on stderr:
+//
+//   --timing        [TIMING] Ingest:    1.2ms        one line per stage, then the total
+//                   [TIMING] tokens:    1520         and the size counts
+//   --timing json   {"kernel":"microcode","stages":{"ingest_us":1200,...},"total_us":...,
+//                    "counts":{"tokens":1520,...}}   on one line, durations in microseconds
+//
+// Each kernel names its own stages. Counts cover everything a stage processed, so for
+// Lumen programs they include the prelude.
+
+use std::time::{Duration, Instant};
+
+/// How --timing prints the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingFormat {
+    Text,
+    Json,
+}
+
+/// Durations of the stages run so far, and size counts recorded along the way
+pub struct Timing {
+    last: Instant,
+    pub stages: Vec<(&'static str, Duration)>,
+    pub counts: Vec<(&'static str, usize)>,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::start()
+    }
+}
+
+impl Timing {
+    /// Start timing the first stage
+    pub fn start() -> Self {
+        Timing { last: Instant::now(), stages: Vec::new(), counts: Vec::new() }
+    }
+
+    /// End the current stage; the next one starts now
+    pub fn lap(&mut self, stage: &'static str) {
+        let now = Instant::now();
+        self.stages.push((stage, now - self.last));
+        self.last = now;
+    }
+
+    /// Record a size, e.g. the number of tokens a stage produced
+    pub fn count(&mut self, name: &'static str, value: usize) {
+        self.counts.push((name, value));
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, time)| *time).sum()
+    }
+
+    /// The report for `kernel` in the given format, ending in a newline
+    pub fn render(&self, kernel: &str, format: TimingFormat) -> String {
+        match format {
+            TimingFormat::Text => {
+                let mut out = String::new();
+                for (stage, time) in &self.stages {
+                    out.push_str(&format!("[TIMING] {:<10} {:?}\n", format!("{}:", stage), time));
+                }
+                out.push_str(&format!("[TIMING] {:<10} {:?}\n", "Total:", self.total()));
+                for (name, value) in &self.counts {
+                    out.push_str(&format!("[TIMING] {:<10} {}\n", format!("{}:", name), value));
+                }
+                out
+            }
+            TimingFormat::Json => {
+                let stages: Vec<String> = self
+                    .stages
+                    .iter()
+                    .map(|(stage, time)| format!("\"{}_us\":{}", stage.to_lowercase(), time.as_micros()))
+                    .collect();
+                let counts: Vec<String> =
+                    self.counts.iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+                format!(
+                    "{{\"kernel\":\"{}\",\"stages\":{{{}}},\"total_us\":{},\"counts\":{{{}}}}}\n",
+                    kernel,
+                    stages.join(","),
+                    self.total().as_micros(),
+                    counts.join(",")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_report_lists_stages_and_counts() {
+        let mut timing = Timing::start();
+        timing.stages = vec![("Ingest", Duration::from_micros(150)), ("Execute", Duration::from_micros(50))];
+        timing.count("tokens", 42);
+        assert_eq!(
+            timing.render("microcode", TimingFormat::Json),
+            "{\"kernel\":\"microcode\",\"stages\":{\"ingest_us\":150,\"execute_us\":50},\"total_us\":200,\"counts\":{\"tokens\":42}}\n"
+        );
+        assert!(timing.render("microcode", TimingFormat::Text).contains("[TIMING] tokens:    42\n"));
+    }
+}
@@ -0,0 +1,30 @@
+// The subscriber of the kernel binaries for the spans and events of lib_lumen/spans.rs
+// (cargo feature "tracing"), shared by both. LUMEN_TRACE selects the level printed on stderr:
+//
+//   LUMEN_TRACE=info    each stage span as it closes, with its duration
+//   LUMEN_TRACE=trace   also each statement as it starts
+//
+// Unset (or without the feature) nothing is installed and runs are not observed.
+
+/// Print spans and events on stderr at the level LUMEN_TRACE names, if it is set
+#[cfg(feature = "tracing")]
+pub fn install_from_env() -> Result<(), String> {
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    let Ok(level) = std::env::var("LUMEN_TRACE") else {
+        return Ok(());
+    };
+    let level: tracing::Level = level.parse().map_err(|_| format!("LUMEN_TRACE={}: expected trace, debug, info, warn or error", level))?;
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+    Ok(())
+}
+
+/// Without the feature, LUMEN_TRACE is ignored
+#[cfg(not(feature = "tracing"))]
+pub fn install_from_env() -> Result<(), String> {
+    Ok(())
+}
@@ -0,0 +1,279 @@
+// Shared transcendental functions for REAL values: exp, ln, log, atan
+// Included by both kernels (like prelude.rs) so the series code lives in one place.
+//
+// All arithmetic is exact BigInt fixed-point: a value v is carried as v * 10^F
+// for some working number of fractional digits F. Each function evaluates its
+// series with extra guard digits, then rounds once (half away from zero) to the
+// requested number of significant digits. The error of the returned value is
+// below one unit in its last significant digit.
+//
+// Inputs and outputs are exact rationals (numerator, denominator); callers wrap
+// the result in their kernel's REAL value type. inf and nan are n/0 both ways (see
+// bigfloat.rs): ln(0) is -inf, the logarithm of a negative is nan, exp(inf) is inf,
+// exp(-inf) is 0 and atan(inf) is pi/2.
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+use super::bigfloat::{self, MAX_EXPONENT};
+
+/// Guard digits carried beyond the requested precision.
+/// Series truncation adds at most a few ulps per term, so 10 digits cover
+/// several thousand terms before the error can reach the rounding position.
+const GUARD_DIGITS: u32 = 10;
+
+/// Natural exponential e^x, rounded to `precision` significant digits
+pub fn exp(num: &BigInt, den: &BigInt, precision: usize) -> Result<(BigInt, BigInt), String> {
+    check_precision(precision)?;
+    if den.is_zero() {
+        // exp(-inf) = 0, and inf and nan are their own exponentials
+        return Ok(if num.is_negative() { (BigInt::zero(), BigInt::one()) } else { (num.clone(), BigInt::zero()) });
+    }
+    if num.is_zero() {
+        return Ok((BigInt::one(), BigInt::one()));
+    }
+    // e^x passes 10^MAX_EXPONENT a little before x = (MAX_EXPONENT + 1) * ln(10)
+    let limit = BigInt::from((MAX_EXPONENT + 1) * 2302585 / 1000000 + 1) * den.abs();
+    if num.abs() > limit {
+        // Overflow to inf, or underflow to 0 (see bigfloat.rs)
+        return Ok(match num.is_negative() {
+            true => (BigInt::zero(), BigInt::one()),
+            false => (BigInt::one(), BigInt::zero()),
+        });
+    }
+    Ok(to_significant(precision, |f| exp_fixed(num, den, f)))
+}
+
+/// Natural logarithm ln(x), rounded to `precision` significant digits
+/// (-inf for 0, nan below it)
+pub fn ln(num: &BigInt, den: &BigInt, precision: usize) -> Result<(BigInt, BigInt), String> {
+    check_precision(precision)?;
+    if den.is_zero() {
+        // ln(inf) = inf, and nan for -inf and nan
+        return Ok((num.signum().max(BigInt::zero()), BigInt::zero()));
+    }
+    if num.is_negative() != den.is_negative() {
+        return Ok((BigInt::zero(), BigInt::zero()));
+    }
+    if num.is_zero() {
+        return Ok((-BigInt::one(), BigInt::zero()));
+    }
+    if num == den {
+        return Ok((BigInt::zero(), BigInt::one()));
+    }
+    Ok(to_significant(precision, |f| ln_fixed(num, den, f)))
+}
+
+/// Logarithm of x in an arbitrary base: ln(x) / ln(base), rounded to `precision` significant digits
+pub fn log(
+    num: &BigInt,
+    den: &BigInt,
+    base_num: &BigInt,
+    base_den: &BigInt,
+    precision: usize,
+) -> Result<(BigInt, BigInt), String> {
+    check_precision(precision)?;
+    let finite_positive = |num: &BigInt, den: &BigInt| num.is_positive() && den.is_positive();
+    if !finite_positive(num, den) || !finite_positive(base_num, base_den) || base_num == base_den {
+        // ln(x) / ln(base) with inf and nan: base 1 divides by ln(1) = 0
+        let (ln_x, ln_base) = (ln(num, den, precision)?, ln(base_num, base_den, precision)?);
+        if let Some(quotient) = bigfloat::special_arithmetic("/", (&ln_x.0, &ln_x.1), (&ln_base.0, &ln_base.1)) {
+            return Ok(quotient);
+        }
+    }
+    if num == den {
+        return Ok((BigInt::zero(), BigInt::one()));
+    }
+    Ok(to_significant(precision, |f| {
+        // A base close to 1 has a tiny logarithm; widen the working precision
+        // by its leading zeros so the division does not magnify the error.
+        let probe = ln_fixed(base_num, base_den, f);
+        let leading_zeros = f.saturating_sub(digit_count(&probe));
+        let work = f + leading_zeros + 2;
+        let ln_x = ln_fixed(num, den, work);
+        let ln_base = ln_fixed(base_num, base_den, work);
+        ln_x * pow10(f) / ln_base
+    }))
+}
+
+/// Arctangent atan(x) in radians, rounded to `precision` significant digits
+pub fn atan(num: &BigInt, den: &BigInt, precision: usize) -> Result<(BigInt, BigInt), String> {
+    check_precision(precision)?;
+    if den.is_zero() && num.is_zero() {
+        return Ok((BigInt::zero(), BigInt::zero()));
+    }
+    if den.is_zero() {
+        // atan(inf) = pi/2
+        return Ok(to_significant(precision, |f| pi_fixed(f) / 2 * num));
+    }
+    if num.is_zero() {
+        return Ok((BigInt::zero(), BigInt::one()));
+    }
+    Ok(to_significant(precision, |f| {
+        if num.abs() <= den.abs() {
+            atan_fixed(num, den, f)
+        } else {
+            // |x| > 1: atan(x) = sign(x) * pi/2 - atan(1/x)
+            let half_pi: BigInt = pi_fixed(f) / 2;
+            let signed_half_pi = if num.is_negative() { -half_pi } else { half_pi };
+            signed_half_pi - atan_fixed(den, num, f)
+        }
+    }))
+}
+
+fn check_precision(precision: usize) -> Result<(), String> {
+    if precision == 0 {
+        return Err("Precision must be a positive integer".to_string());
+    }
+    Ok(())
+}
+
+fn pow10(digits: u32) -> BigInt {
+    BigInt::from(10).pow(digits)
+}
+
+/// Number of decimal digits in |v| (0 for zero)
+fn digit_count(v: &BigInt) -> u32 {
+    if v.is_zero() {
+        0
+    } else {
+        v.abs().to_string().len() as u32
+    }
+}
+
+/// Drive a fixed-point evaluator until it yields enough significant digits,
+/// then round the result once to `precision` significant digits.
+/// `eval(f)` must return the value scaled by 10^f.
+fn to_significant<F>(precision: usize, eval: F) -> (BigInt, BigInt)
+where
+    F: Fn(u32) -> BigInt,
+{
+    let wanted = precision as u32 + GUARD_DIGITS;
+    let mut frac_digits = wanted;
+    let scaled = loop {
+        let v = eval(frac_digits);
+        let digits = digit_count(&v);
+        if digits >= wanted {
+            break v;
+        }
+        // Small results need more fractional digits to show `wanted` significant ones
+        frac_digits += if digits == 0 { frac_digits } else { wanted - digits };
+    };
+
+    // Round half away from zero, dropping everything past `precision` digits
+    let drop = digit_count(&scaled) - precision as u32;
+    let divisor = pow10(drop);
+    let half = &divisor / 2;
+    let mut rounded: BigInt = (scaled.abs() + half) / &divisor;
+    if scaled.is_negative() {
+        rounded = -rounded;
+    }
+
+    // rounded * 10^drop / 10^frac_digits
+    if drop >= frac_digits {
+        (rounded * pow10(drop - frac_digits), BigInt::one())
+    } else {
+        (rounded, pow10(frac_digits - drop))
+    }
+}
+
+/// atan(a/b) * 10^f for |a/b| <= 1, using Euler's accelerated series:
+/// atan(x) = x/(1+x^2) * sum_n [ (2n)!! / (2n+1)!! * (x^2/(1+x^2))^n ]
+/// Each term shrinks by at least a factor of 2, so no argument reduction is needed.
+fn atan_fixed(a: &BigInt, b: &BigInt, f: u32) -> BigInt {
+    let a_sq = a * a;
+    let denom = &a_sq + b * b;
+    let mut term = pow10(f) * a * b / &denom;
+    let mut sum = term.clone();
+    let mut n: u64 = 1;
+    loop {
+        term = term * BigInt::from(2 * n) * &a_sq / (BigInt::from(2 * n + 1) * &denom);
+        if term.is_zero() {
+            break;
+        }
+        sum += &term;
+        n += 1;
+    }
+    sum
+}
+
+/// pi * 10^f via Machin's formula: pi = 16 atan(1/5) - 4 atan(1/239)
+fn pi_fixed(f: u32) -> BigInt {
+    let one = BigInt::one();
+    let work = f + 2;
+    let pi = BigInt::from(16) * atan_fixed(&one, &BigInt::from(5), work)
+        - BigInt::from(4) * atan_fixed(&one, &BigInt::from(239), work);
+    pi / 100
+}
+
+/// atanh(p/q) * 10^f for |p/q| < 1: sum_j (p/q)^(2j+1) / (2j+1)
+fn atanh_fixed(p: &BigInt, q: &BigInt, f: u32) -> BigInt {
+    let p_sq = p * p;
+    let q_sq = q * q;
+    let mut power = pow10(f) * p / q;
+    let mut sum = power.clone();
+    let mut j: u64 = 1;
+    loop {
+        power = power * &p_sq / &q_sq;
+        if power.is_zero() {
+            break;
+        }
+        sum += &power / BigInt::from(2 * j + 1);
+        j += 1;
+    }
+    sum
+}
+
+/// ln(a/b) * 10^f for a/b > 0
+/// Reduces x = 2^k * y with y in (1/2, 2), then ln(y) = 2 atanh((y-1)/(y+1))
+/// and ln(2) = 2 atanh(1/3).
+fn ln_fixed(a: &BigInt, b: &BigInt, f: u32) -> BigInt {
+    let (a, b) = (a.abs(), b.abs());
+    let k = a.bits() as i64 - b.bits() as i64;
+    let (yn, yd) = if k >= 0 {
+        (a, b << k as usize)
+    } else {
+        (a << (-k) as usize, b)
+    };
+
+    // k * ln(2) multiplies the ln(2) error by |k|; carry extra digits for it
+    let extra = digit_count(&BigInt::from(k)) + 1;
+    let work = f + extra;
+    let ln_y = atanh_fixed(&(&yn - &yd), &(&yn + &yd), work) * 2;
+    let ln_2 = atanh_fixed(&BigInt::one(), &BigInt::from(3), work) * 2;
+    (ln_y + ln_2 * k) / pow10(extra)
+}
+
+/// exp(a/b) * 10^f
+/// Halves the argument m times until it is below 2^-7, sums the Taylor series,
+/// then squares m times. Each squaring doubles the relative error, so the
+/// series runs with about m * log10(2) extra digits.
+fn exp_fixed(a: &BigInt, b: &BigInt, f: u32) -> BigInt {
+    let negative = a.is_negative() != b.is_negative();
+    let (a, b) = (a.abs(), b.abs());
+    let m = (a.bits() as i64 - b.bits() as i64 + 8).max(0) as usize;
+    let extra = (m as u32) * 3 / 10 + 2;
+    let work = f + extra;
+    let scale = pow10(work);
+
+    let y = &scale * &a / (&b << m);
+    let mut sum = scale.clone();
+    let mut term = scale.clone();
+    let mut k: u64 = 1;
+    loop {
+        term = term * &y / (&scale * BigInt::from(k));
+        if term.is_zero() {
+            break;
+        }
+        sum += &term;
+        k += 1;
+    }
+    for _ in 0..m {
+        sum = &sum * &sum / &scale;
+    }
+
+    if negative {
+        sum = &scale * &scale / sum;
+    }
+    sum / pow10(extra)
+}
@@ -0,0 +1,93 @@
+Numbers: integers of any size, exact rationals, reals, and the arithmetic operators.
+
+=== operators follow the usual precedence
+--- source
+print(1 + 2 * 3)
+print((1 + 2) * 3)
+print(2 ** 10)
+print(10 - 4 - 3)
+--- output
+7
+9
+1024
+3
+
+=== integers have no fixed size
+--- source
+print(2 ** 100)
+print(12345678901234567890 * 98765432109876543210)
+--- output
+1267650600228229401496703205376
+1219326311370217952237463801111263526900
+
+=== dividing integers gives an exact rational
+--- source
+print(1 / 3 + 1 / 6)
+print(6 / 3)
+--- output
+1/2
+2
+
+=== integer division truncates toward zero
+--- source
+print(7 // 2)
+print(-7 // 2)
+print(7 % 3)
+print(-7 % 3)
+--- output
+3
+-3
+1
+-1
+
+=== dividing by zero is an error
+--- source
+x = 1 / 0
+--- error
+division by zero
+
+=== reals divide by zero to inf and nan
+--- source
+inf = 1.0 / 0
+nan = 0.0 / 0
+print(inf)
+print(-1.5 / 0)
+print(nan)
+print(inf - inf)
+print(1 / inf)
+print(inf > 10 ** 100)
+print(nan == nan)
+print(nan != nan)
+print(ln(0))
+--- output
+inf
+-inf
+nan
+nan
+0
+true
+false
+true
+-inf
+
+=== format_number writes fixed decimal places in digit groups
+--- source
+print(format_number(1234567, 2, 3))
+print(format_number(-2 / 3, 4, 0))
+print(format_number(real(22, 15) / 7, 10, 5))
+--- output
+1 234 567.00
+-0.6667
+3.14285 71429
+
+=== comparisons
+--- source
+print(1 < 2)
+print(2 <= 2)
+print(3 != 3)
+print(1 / 2 == 2 / 4)
+--- output
+true
+true
+false
+true
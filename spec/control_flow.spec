@@ -0,0 +1,62 @@
+Conditionals, loops, break and continue, and exit.
+
+=== if and else
+--- source
+fn describe(n)
+    if n < 0
+        return "negative"
+    else
+        if n == 0
+            return "zero"
+        return "positive"
+print(describe(-5))
+print(describe(0))
+print(describe(5))
+--- output
+negative
+zero
+positive
+
+=== while with break and continue
+--- source
+i = 0
+total = 0
+while i < 10
+    i = i + 1
+    if i % 2 == 0
+        continue
+    if i > 7
+        break
+    total = total + i
+print(total)
+--- output
+16
+
+=== for over a range excludes its end
+--- source
+for i in 0..4
+    write(i)
+print("")
+--- output
+0123
+
+=== and, or and not short-circuit
+--- source
+fn loud(value)
+    print("evaluated")
+    return value
+print(false and loud(true))
+print(true or loud(false))
+print(not false)
+--- output
+false
+true
+true
+
+=== exit ends the program
+--- source
+print("before")
+exit(0)
+print("after")
+--- output
+before
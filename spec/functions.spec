@@ -0,0 +1,37 @@
+Functions: definition, recursion, and the scope of their variables.
+
+=== functions return values
+--- source
+fn square(x)
+    return x * x
+print(square(12))
+--- output
+144
+
+=== functions recurse
+--- source
+fn fib(n)
+    if n < 2
+        return n
+    return fib(n - 1) + fib(n - 2)
+print(fib(20))
+--- output
+6765
+
+=== assignments in a function stay in it
+--- source
+x = 1
+fn set()
+    x = 2
+    return x
+print(set())
+print(x)
+--- output
+2
+1
+
+=== calling an undefined function is an error
+--- source
+nothing_here(1)
+--- error
+nothing_here
@@ -0,0 +1,66 @@
+Strings: literals, concatenation, comparison and the string builtins.
+
+=== concatenation and length
+--- source
+s = "Hello" + ", " + "world"
+print(s)
+print(len(s))
+--- output
+Hello, world
+12
+
+=== strings compare by their characters
+--- source
+print("abc" < "abd")
+print("b" > "abc")
+print("x" == "x")
+--- output
+true
+true
+true
+
+=== write prints without a newline
+--- source
+write("a")
+write("b")
+print("c")
+--- output
+abc
+
+=== repr quotes strings and shortens huge values
+--- source
+a = ["x", 1/3, ["tab\t", true, null]]
+print(a)
+print(repr(a))
+print(repr(2 ** 200))
+s = "abcdefghij"
+s = s . s . s . s . s . s . s . s . s . s
+print(repr(s . "!"))
+--- output
+[x, 1/3, [tab	, true, null]]
+["x", 1/3, ["tab\t", true, null]]
+16069380442589902755...(61 digits)...02993782792835301376
+"abcdefghijabcdefghijabcdefghijabcdefghijabcdefghijabcdefghijabcdefghijabcdefghij"...(101 chars)
+
+=== bytes hold binary data that need not be UTF-8
+--- source
+png = bytes([137, 80, 78, 71, 13, 10])
+print(png)
+print(len(png) . " " . png[0] . " " . kind_to_string(kind(png)))
+print(bytes("hi") == bytes([104, 105]))
+print(decode_utf8(bytes([104, 105])))
+print([bytes("a\"b")])
+extern("io:write_bytes", bytes("raw\n"))
+--- output
+b"\x89PNG\r\n"
+6 137 BYTES
+true
+hi
+[b"a\"b"]
+raw
+
+=== decoding bytes that are not UTF-8 is an error
+--- source
+print(decode_utf8(bytes([104, 255])))
+--- error
+invalid UTF-8 at byte 1
@@ -0,0 +1,203 @@
+// Kernel backends: the kernels behind --kernel, driven in process through one trait
+//
+// Most subcommands run a kernel's binary (so a program's output, exit status and
+// diagnostics are the kernel's own). KernelBackend is the same choice of kernel as a
+// library call: run source text in a language on whichever kernel, without knowing
+// which one it is (`lumen-lang eval` is a front end over it). Stream and microcode are
+// implemented over their library crates (lumen_stream's Interpreter, microcode_2's
+// stages). The opaque kernel has no source in this tree, so it has no backend:
+// --kernel opaque only runs an opaque binary installed next to lumen-lang.
+//
+// Both backends run Lumen programs after the standard prelude, like their binaries, but
+// positions in errors count from the first line of the source; output (print, emit)
+// goes to standard output.
+
+use std::fmt;
+
+use lumen_stream::Interpreter;
+use microcode_2::kernel::{self, serial, ExecutionLimits, Passes, Timing};
+use microcode_2::languages;
+
+// Build-time packaging: lib_lumen/prelude.lm reduced by build.rs (PROGRAM, LINES)
+mod compiled_prelude {
+    include!(concat!(env!("OUT_DIR"), "/prelude.rs"));
+}
+
+/// Why a run stopped early, whichever kernel it ran on
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunError {
+    /// The source did not parse (or the language is unknown)
+    Syntax(String),
+    /// Execution stopped with an uncaught error
+    Runtime(String),
+    /// The program ran `exit status`
+    Exit(i32),
+    /// Execution went over one of its limits
+    LimitExceeded(String),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Syntax(e) | RunError::Runtime(e) | RunError::LimitExceeded(e) => write!(f, "{}", e),
+            RunError::Exit(status) => write!(f, "exit({})", status),
+        }
+    }
+}
+
+impl From<lumen_stream::RunError> for RunError {
+    fn from(error: lumen_stream::RunError) -> Self {
+        match error {
+            lumen_stream::RunError::Syntax(errors) => RunError::Syntax(lines(&errors)),
+            lumen_stream::RunError::Runtime(e) => RunError::Runtime(e.to_string()),
+            lumen_stream::RunError::Exit(status) => RunError::Exit(status),
+            lumen_stream::RunError::LimitExceeded(e) => RunError::LimitExceeded(e.to_string()),
+        }
+    }
+}
+
+impl From<kernel::RunError> for RunError {
+    fn from(error: kernel::RunError) -> Self {
+        match error {
+            kernel::RunError::Syntax(errors) => RunError::Syntax(lines(&errors)),
+            kernel::RunError::Runtime(e) => RunError::Runtime(e.to_string()),
+            kernel::RunError::Exit(status) => RunError::Exit(status),
+            kernel::RunError::LimitExceeded(e) => RunError::LimitExceeded(e.to_string()),
+        }
+    }
+}
+
+/// Each syntax error on a line of its own
+fn lines(errors: &[impl fmt::Display]) -> String {
+    errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+/// A kernel that runs programs in process
+pub trait KernelBackend {
+    /// Its --kernel name
+    fn name(&self) -> &'static str;
+
+    /// The --lang names of the languages it runs
+    fn languages(&self) -> Vec<&'static str>;
+
+    /// Run `source` as a whole `language` program with `args` (ARGS in Lumen), giving
+    /// the value the kernel reports for it, if any
+    fn run(&self, source: &str, language: &str, args: &[String]) -> Result<Option<String>, RunError>;
+
+    /// Whether it runs programs in `language`
+    fn supports(&self, language: &str) -> bool {
+        self.languages().contains(&language)
+    }
+}
+
+/// The stream kernel (lumen_stream)
+pub struct Stream;
+
+impl KernelBackend for Stream {
+    fn name(&self) -> &'static str {
+        "stream"
+    }
+
+    fn languages(&self) -> Vec<&'static str> {
+        vec!["lumen", "rust_core", "python_core"]
+    }
+
+    fn run(&self, source: &str, language: &str, args: &[String]) -> Result<Option<String>, RunError> {
+        let value = Interpreter::new().with_language(language).with_args(args).eval(source)?;
+        Ok(value.map(|value| value.to_string()))
+    }
+}
+
+/// The microcode kernel (microcode_2), with the languages of its registry
+pub struct Microcode;
+
+impl KernelBackend for Microcode {
+    fn name(&self) -> &'static str {
+        "microcode"
+    }
+
+    fn languages(&self) -> Vec<&'static str> {
+        languages::all().iter().map(|language| language.name).collect()
+    }
+
+    fn run(&self, source: &str, language: &str, args: &[String]) -> Result<Option<String>, RunError> {
+        let schema = microcode_schema(language)?;
+        let mut timing = Timing::start();
+        // User code starts after the prelude's text, as in the microcode binary, so the two
+        // never share positions; errors count them from the source's first line again
+        let (program, first_line) = if language == "lumen" {
+            let prelude = serial::load(compiled_prelude::PROGRAM, serial::Format::Cbor)
+                .map_err(|e| RunError::Syntax(format!("embedded prelude: {}", e)))?
+                .program;
+            let first_line = compiled_prelude::LINES + 2;
+            let program = kernel::reduce_with_prelude_timed(prelude, first_line, source, &schema, &mut timing, Passes::default())
+                .map_err(|e| rebase(e, first_line))?;
+            (program, first_line)
+        } else {
+            (kernel::reduce_program_timed(source, &schema, &mut timing, Passes::default())?, 1)
+        };
+        let value = kernel::run_reduced(&program, &schema, args, &[], &mut timing, ExecutionLimits::default())
+            .map_err(|e| rebase(e, first_line))?;
+        Ok(Some(value.to_string()))
+    }
+}
+
+/// `error` with the positions of user code (from `first_line` on) counted from the first
+/// line of the source, as the stream kernel counts them; the prelude's keep their own
+fn rebase(error: kernel::RunError, first_line: usize) -> RunError {
+    let rebase_line = |line: usize| if line >= first_line { line + 1 - first_line } else { line };
+    let rebase_located = |mut error: kernel::LocatedError| {
+        for (line, _) in &mut error.positions {
+            *line = rebase_line(*line);
+        }
+        error
+    };
+    match error {
+        kernel::RunError::Syntax(errors) => kernel::RunError::Syntax(errors.into_iter().map(rebase_located).collect()).into(),
+        kernel::RunError::Runtime(e) => kernel::RunError::Runtime(rebase_located(e)).into(),
+        kernel::RunError::LimitExceeded(e) => kernel::RunError::LimitExceeded(rebase_located(e)).into(),
+        kernel::RunError::Exit(status) => RunError::Exit(status),
+    }
+}
+
+fn microcode_schema(language: &str) -> Result<microcode_2::schema::LanguageSchema, RunError> {
+    languages::by_name(language)
+        .map(|language| language.get_schema())
+        .ok_or_else(|| RunError::Syntax(format!("Unknown language '{}'", language)))
+}
+
+/// Every kernel with a backend, in --kernel listing order
+pub fn all() -> [&'static dyn KernelBackend; 2] {
+    [&Stream, &Microcode]
+}
+
+/// The backend of a --kernel name (None for opaque, and for unknown names)
+pub fn by_name(name: &str) -> Option<&'static dyn KernelBackend> {
+    all().into_iter().find(|backend| backend.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_backend_runs_programs_the_same_way() {
+        for backend in all() {
+            let name = backend.name();
+            assert!(backend.supports("lumen") && backend.supports("python_core"), "{}", name);
+            assert!(backend.run("x = 1 + 2\n", "lumen", &[]).is_ok(), "{}", name);
+            assert!(matches!(backend.run("x = 1 + * 2\n", "lumen", &[]), Err(RunError::Syntax(_))), "{}", name);
+            assert!(matches!(backend.run("x = 1\n", "cobol", &[]), Err(RunError::Syntax(_))), "{}", name);
+            assert_eq!(backend.run("exit(3)\n", "lumen", &[]), Err(RunError::Exit(3)), "{}", name);
+            assert!(matches!(backend.run("x = 1 / 0\n", "lumen", &[]), Err(RunError::Runtime(_))), "{}", name);
+            // The prelude is loaded: assert_eq is one of its functions
+            assert!(backend.run("assert_eq(1 + 1, 2)\n", "lumen", &[]).is_ok(), "{}", name);
+            assert!(backend.run("assert_eq(1 + 1, 3)\n", "lumen", &[]).is_err(), "{}", name);
+            // Lines count from the source's first line
+            let error = backend.run("x = 1\ny = x / 0\n", "lumen", &[]).unwrap_err();
+            assert!(error.to_string().starts_with("at 2:"), "{}: {}", name, error);
+        }
+        assert_eq!(by_name("microcode").unwrap().name(), "microcode");
+        assert!(by_name("opaque").is_none());
+    }
+}
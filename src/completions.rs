@@ -0,0 +1,194 @@
+// Shell completion scripts for `lumen-lang completions <shell>`
+//
+//   bash   source <(lumen-lang completions bash)
+//   zsh    source <(lumen-lang completions zsh)     (after compinit)
+//   fish   lumen-lang completions fish > ~/.config/fish/completions/lumen-lang.fish
+//
+// Scripts complete subcommands, flags, --kernel and --lang values, and file names.
+// The --lang values come from the microcode kernel's language registry.
+
+use super::KERNELS;
+use microcode_2::languages::LANGUAGES;
+
+/// Subcommands with a short description
+const SUBCOMMANDS: [(&str, &str); 18] = [
+    ("run", "run a program"),
+    ("check", "syntax check only"),
+    ("tokens", "print the token streams"),
+    ("ast", "print the parse tree"),
+    ("lint", "static checks"),
+    ("repl", "interactive session"),
+    ("eval", "print the value of inline code"),
+    ("fmt", "format Lumen source"),
+    ("watch", "re-run on every change"),
+    ("bench", "compare the kernels"),
+    ("test", "run *_test.lm files"),
+    ("difftest", "compare the kernels on many programs"),
+    ("golden", "compare output with .expected files"),
+    ("spec", "run the language specification"),
+    ("doc", "generate API documentation"),
+    ("langs", "list the supported languages"),
+    ("completions", "print a shell completion script"),
+    ("help", "show usage"),
+];
+
+/// Flags with a short description; those taking a value end in '='
+const FLAGS: [(&str, &str); 36] = [
+    ("--kernel=", "kernel to run on"),
+    ("--lang=", "source language"),
+    ("--define=", "host constant NAME=value"),
+    ("--config=", "host constants from a TOML file"),
+    ("--lcov=", "write an lcov tracefile"),
+    ("--prelude=", "load this file instead of the prelude"),
+    ("--allow=", "disable a lint rule"),
+    ("--max-depth=", "limit nested function calls"),
+    ("--max-steps=", "limit loop iterations and calls"),
+    ("--max-time=", "limit running time in milliseconds"),
+    ("--max-heap=", "limit bytes held in variables"),
+    ("--allow-fs", "let extern reach files (--allow-fs=read for reading only)"),
+    ("--allow-net", "let extern reach the network"),
+    ("--deny-all", "let extern reach no backend unless allowed"),
+    ("--audit=", "log every extern call to a file as JSON lines"),
+    ("--extern-timeout=", "limit extern calls to milliseconds ([TARGET=]MS)"),
+    ("--emit-program=", "save the reduced program as JSON or CBOR"),
+    ("--plugin=", "load a language plugin library"),
+    ("--adapter=", "load an extern capability adapter library"),
+    ("--schema=", "define a language from a TOML or JSON file"),
+    ("--check", "syntax check only"),
+    ("--tokens", "print the token streams"),
+    ("--ast", "print the parse tree"),
+    ("--emit-instructions", "print the instruction tree"),
+    ("--lint", "static checks"),
+    ("--deny-warnings", "treat warnings as errors"),
+    ("--trace", "print statements as they run"),
+    ("--coverage", "statement and branch coverage"),
+    ("--intervals", "give reals error bounds and print certified digits"),
+    ("--timing", "report stage timings"),
+    ("--opt", "optimize before running (--opt=0..2 picks a level)"),
+    ("--load-program", "run a saved program"),
+    ("--cache", "reuse reduced programs between runs"),
+    ("--no-prelude", "skip the standard prelude"),
+    ("--bless", "write .expected files (golden)"),
+    ("-e=", "run inline code"),
+];
+
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+/// Completion script for a shell
+pub fn script(shell: &str) -> Result<String, String> {
+    let languages: Vec<&str> = LANGUAGES.iter().map(|language| language.name).collect();
+    let languages = languages.join(" ");
+    let kernels = KERNELS.join(" ");
+    let subcommands: Vec<&str> = SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+    let subcommands = subcommands.join(" ");
+    let flags: Vec<&str> = FLAGS.iter().map(|(flag, _)| flag.trim_end_matches('=')).collect();
+    let flags = flags.join(" ");
+    let shells = SHELLS.join(" ");
+
+    match shell {
+        "bash" => Ok(format!(
+            r#"# bash completion for lumen-lang
+_lumen_lang() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "$prev" in
+        --kernel) COMPREPLY=($(compgen -W "{kernels}" -- "$cur")); return ;;
+        --lang) COMPREPLY=($(compgen -W "{languages}" -- "$cur")); return ;;
+        completions) COMPREPLY=($(compgen -W "{shells}" -- "$cur")); return ;;
+        --define|--allow|-e) return ;;
+    esac
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "{flags}" -- "$cur"))
+    elif [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{subcommands}" -- "$cur") $(compgen -f -- "$cur"))
+    else
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}}
+complete -o filenames -F _lumen_lang lumen-lang
+"#
+        )),
+        "zsh" => {
+            let described: Vec<String> =
+                SUBCOMMANDS.iter().map(|(name, about)| format!("'{}:{}'", name, about)).collect();
+            let flag_specs: Vec<String> = FLAGS
+                .iter()
+                .map(|(flag, about)| match flag.strip_suffix('=') {
+                    Some("--kernel") => format!("'--kernel[{}]:kernel:({})'", about, kernels),
+                    Some("--lang") => format!("'--lang[{}]:language:({})'", about, languages),
+                    Some(name @ ("--config" | "--lcov" | "--prelude" | "--plugin" | "--adapter" | "--schema")) => format!("'{}[{}]:file:_files'", name, about),
+                    Some(name) => format!("'{}[{}]:value: '", name, about),
+                    None => format!("'{}[{}]'", flag, about),
+                })
+                .collect();
+            Ok(format!(
+                r#"#compdef lumen-lang
+# zsh completion for lumen-lang
+_lumen_lang() {{
+    local -a subcommands
+    subcommands=({described})
+    if [[ $words[CURRENT-1] == completions ]]; then
+        compadd {shells}
+        return
+    fi
+    if (( CURRENT == 2 )) && [[ $PREFIX != -* ]]; then
+        _describe 'subcommand' subcommands
+        _files
+        return
+    fi
+    _arguments -s {flags} '*:file:_files'
+}}
+compdef _lumen_lang lumen-lang
+"#,
+                described = described.join(" "),
+                flags = flag_specs.join(" \\\n        "),
+            ))
+        }
+        "fish" => {
+            let mut out = String::from("# fish completion for lumen-lang\n");
+            for (name, about) in SUBCOMMANDS {
+                out.push_str(&format!(
+                    "complete -c lumen-lang -n '__fish_use_subcommand' -a {} -d '{}'\n",
+                    name, about
+                ));
+            }
+            out.push_str(&format!(
+                "complete -c lumen-lang -n '__fish_seen_subcommand_from completions' -x -a '{}'\n",
+                shells
+            ));
+            for (flag, about) in FLAGS {
+                let (name, takes_value) = match flag.strip_suffix('=') {
+                    Some(name) => (name, true),
+                    None => (flag, false),
+                };
+                let option = match name.strip_prefix("--") {
+                    Some(long) => format!("-l {}", long),
+                    None => format!("-o {}", name.trim_start_matches('-')),
+                };
+                let values = match name {
+                    "--kernel" => format!(" -x -a '{}'", kernels),
+                    "--lang" => format!(" -x -a '{}'", languages),
+                    _ if takes_value => " -r".to_string(),
+                    _ => String::new(),
+                };
+                out.push_str(&format!("complete -c lumen-lang {}{} -d '{}'\n", option, values, about));
+            }
+            Ok(out)
+        }
+        _ => Err(format!("Unknown shell '{}'. Use one of: {}", shell, SHELLS.join(", "))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_offer_registered_languages() {
+        for shell in SHELLS {
+            let script = script(shell).unwrap();
+            assert!(script.contains("lumen rust_core python_core"), "{}", script);
+            assert!(script.contains("completions"), "{}", script);
+        }
+        assert!(script("tcsh").is_err());
+    }
+}
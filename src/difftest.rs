@@ -0,0 +1,235 @@
+// Differential testing for `lumen-lang difftest [file... | dir...]`
+//
+// The kernels implement one language each their own way, so their behaviour drifts
+// apart unless something compares them. difftest runs every program it finds (files of
+// a known language extension under the given directories, searched recursively, default
+// examples/) on every kernel whose backend supports the language (see backend.rs) and
+// whose binary is built, and checks that all of them print the same standard output and
+// exit with the same status. The first kernel is the reference; a disagreement shows the
+// first difference for each kernel that differs:
+//
+//   SAME examples/lumen/factorial.lm (stream, microcode)
+//   DIFF examples/python/demo.py
+//        microcode: output line 3: "5" vs "5.0" (stream)
+//
+//   2 programs: 1 same, 1 different (412ms)
+//
+// Standard error is not compared (diagnostics are worded per kernel), nor are programs
+// that only one kernel runs. A run that takes longer than TIMEOUT is stopped and counts
+// as a difference, so a kernel that hangs where another finishes is caught too.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use microcode_2::languages;
+
+use crate::backend;
+
+/// Longest a program may run on one kernel
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How one kernel's run of a program ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Exit status (None when stopped by a signal), standard output and standard error
+    Finished(Option<i32>, String, String),
+    TimedOut,
+}
+
+/// Compare the kernels on every program found under `paths`; Ok(true) when they all agreed
+/// `kernel_args` are passed to each kernel after the file (e.g. --define).
+pub fn run(paths: &[String], kernel_args: &[String], binary: impl Fn(&str) -> PathBuf) -> Result<bool, String> {
+    let files = discover(paths)?;
+    if files.is_empty() {
+        return Err(format!("No programs found in {}", paths.join(", ")));
+    }
+
+    let start = Instant::now();
+    let (mut same, mut different, mut skipped) = (0, 0, 0);
+    for file in &files {
+        let language = language_of(file).ok_or_else(|| format!("No language has the extension of {}", file.display()))?;
+        let kernels: Vec<(&str, PathBuf)> = backend::all()
+            .into_iter()
+            .filter(|backend| backend.supports(language))
+            .map(|backend| (backend.name(), binary(backend.name())))
+            .filter(|(_, binary)| binary.exists())
+            .collect();
+        if kernels.len() < 2 {
+            skipped += 1;
+            println!("SKIP {} (only {} runs {})", file.display(), kernels.len(), language);
+            continue;
+        }
+
+        let mut outcomes = Vec::new();
+        for (kernel, binary) in &kernels {
+            let mut command = Command::new(binary);
+            command.arg(file).args(kernel_args);
+            let outcome = run_with_timeout(command, TIMEOUT)
+                .map_err(|e| format!("Failed to execute {} kernel at {:?}: {}", kernel, binary, e))?;
+            outcomes.push((*kernel, outcome));
+        }
+
+        let (reference, reference_outcome) = &outcomes[0];
+        let differences: Vec<String> = outcomes[1..]
+            .iter()
+            .filter_map(|(kernel, outcome)| {
+                compare(reference_outcome, outcome).map(|difference| format!("{}: {} ({})", kernel, difference, reference))
+            })
+            .chain(outcomes.iter().filter(|(_, o)| *o == Outcome::TimedOut).map(|(kernel, _)| {
+                format!("{}: timed out after {:?}", kernel, TIMEOUT)
+            }))
+            .collect();
+        if differences.is_empty() {
+            same += 1;
+            let names: Vec<&str> = outcomes.iter().map(|(kernel, _)| *kernel).collect();
+            println!("SAME {} ({})", file.display(), names.join(", "));
+        } else {
+            different += 1;
+            println!("DIFF {}", file.display());
+            for difference in differences {
+                println!("     {}", difference);
+            }
+        }
+    }
+
+    println!();
+    let mut summary = format!(
+        "{} program{}: {} same, {} different",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        same,
+        different
+    );
+    if skipped > 0 {
+        summary.push_str(&format!(", {} skipped", skipped));
+    }
+    println!("{} ({:.0?})", summary, start.elapsed());
+    Ok(different == 0)
+}
+
+/// How `actual` differs from `expected`, if it does (timeouts are reported on their own)
+fn compare(expected: &Outcome, actual: &Outcome) -> Option<String> {
+    match (expected, actual) {
+        (Outcome::Finished(expected_status, expected_out, _), Outcome::Finished(actual_status, actual_out, _)) => {
+            if actual_status != expected_status {
+                Some(format!("exit status {} vs {}", status_name(*actual_status), status_name(*expected_status)))
+            } else {
+                crate::first_difference(expected_out, actual_out)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn status_name(status: Option<i32>) -> String {
+    status.map_or("signal".to_string(), |code| code.to_string())
+}
+
+/// Run `command` with no input, stopping it after `timeout`
+pub fn run_with_timeout(mut command: Command, timeout: Duration) -> io::Result<Outcome> {
+    let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // Read on other threads, so a program with a lot of output never blocks on a pipe
+    let stdout = read_all(child.stdout.take().expect("stdout is piped"));
+    let stderr = read_all(child.stderr.take().expect("stderr is piped"));
+
+    let status = wait(&mut child, timeout)?;
+    let stdout = stdout.join().expect("output reader panicked")?;
+    let stderr = stderr.join().expect("output reader panicked")?;
+    Ok(match status {
+        Some(status) => Outcome::Finished(status, stdout, stderr),
+        None => Outcome::TimedOut,
+    })
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<io::Result<String>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        pipe.read_to_end(&mut output)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    })
+}
+
+/// The exit status of `child` (Some(None) for a signal), or None once it was killed at `timeout`
+fn wait(child: &mut Child, timeout: Duration) -> io::Result<Option<Option<i32>>> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status.code()));
+        }
+        if start.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// The --lang name of a program file, from its extension
+fn language_of(file: &Path) -> Option<&'static str> {
+    let extension = file.extension()?.to_str()?;
+    languages::by_extension(extension).map(|language| language.name)
+}
+
+/// Programs named directly or found under the directories, in sorted order
+pub fn discover(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            search(path, &mut files).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        } else if path.is_file() {
+            files.push(path.to_path_buf());
+        } else {
+            return Err(format!("No such file or directory: {}", path.display()));
+        }
+    }
+    Ok(files)
+}
+
+fn search(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?.map(|entry| entry.map(|e| e.path())).collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                search(&path, files)?;
+            }
+        } else if language_of(&path).is_some() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_status_then_output() {
+        let finished = |status, out: &str| Outcome::Finished(Some(status), out.to_string(), String::new());
+        assert_eq!(compare(&finished(0, "1\n2\n"), &finished(0, "1\n2\n")), None);
+        assert_eq!(compare(&finished(0, "1\n"), &finished(1, "1\n")).unwrap(), "exit status 1 vs 0");
+        assert!(compare(&finished(0, "1\n2\n"), &finished(0, "1\n3\n")).unwrap().contains("output line 2"));
+        let signalled = Outcome::Finished(None, String::new(), String::new());
+        assert_eq!(compare(&signalled, &finished(0, "")).unwrap(), "exit status 0 vs signal");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_runs_at_the_timeout() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo hi; echo oops >&2; exit 3"]);
+        let finished = Outcome::Finished(Some(3), "hi\n".to_string(), "oops\n".to_string());
+        assert_eq!(run_with_timeout(command, TIMEOUT).unwrap(), finished);
+        let mut command = Command::new("sh");
+        command.args(["-c", "exec sleep 5"]);
+        assert_eq!(run_with_timeout(command, Duration::from_millis(50)).unwrap(), Outcome::TimedOut);
+    }
+}
@@ -0,0 +1,237 @@
+// API documentation for `lumen-lang doc`, from the comments and docstrings in .lm files
+//
+// Works on lines, like the formatter. For each file it collects:
+//
+//   file summary   the comment block at the top of the file, when a blank line follows it
+//   sections       `## Title` comment lines, which group the functions after them
+//   functions      each top-level `fn name(params)` with its docstring (a string literal
+//                  as the first line of the body) and the comment lines directly above it
+//
+// Lines made only of '#', '=' and '-' (banners) are dropped, as is a first summary line
+// that just repeats the file name. Files without functions are left out.
+
+/// Documentation of one source file
+pub struct FileDoc {
+    pub name: String,
+    pub summary: Vec<String>,
+    pub items: Vec<Item>,
+}
+
+pub enum Item {
+    Section(String),
+    Function { name: String, params: Vec<String>, doc: Option<String>, comments: Vec<String> },
+}
+
+/// Output format of the generated documentation
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+/// Extract the documentation of a file
+pub fn extract(name: &str, source: &str) -> FileDoc {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut summary = Vec::new();
+    let mut items = Vec::new();
+    // Comment lines since the last code or blank line
+    let mut pending: Vec<&str> = Vec::new();
+    let mut seen_code = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let text = line.trim();
+        if let Some(comment) = text.strip_prefix('#') {
+            if let Some(title) = comment.strip_prefix('#') {
+                if !title.trim().is_empty() && !is_banner(title) {
+                    items.push(Item::Section(title.trim().to_string()));
+                }
+            } else if !is_banner(comment) {
+                pending.push(comment.strip_prefix(' ').unwrap_or(comment).trim_end());
+            }
+            continue;
+        }
+        if text.is_empty() {
+            if !seen_code && summary.is_empty() && items.is_empty() {
+                summary = pending.iter().map(|s| s.to_string()).collect();
+            }
+            pending.clear();
+            continue;
+        }
+        seen_code = true;
+
+        if !line.starts_with(char::is_whitespace) {
+            if let Some((fn_name, params)) = signature(text) {
+                let body = lines[i + 1..].iter().find(|l| !l.trim().is_empty() && !l.trim().starts_with('#'));
+                let doc = body.filter(|l| l.starts_with(char::is_whitespace)).and_then(|l| docstring(l.trim()));
+                let comments = pending.iter().map(|s| s.to_string()).collect();
+                items.push(Item::Function { name: fn_name, params, doc, comments });
+            }
+        }
+        pending.clear();
+    }
+
+    // Drop a first summary line that only names the file, and sections with no functions
+    let base = name.rsplit('/').next().unwrap_or(name);
+    if summary.first().is_some_and(|first| first.trim() == base) {
+        summary.remove(0);
+    }
+    while summary.last().is_some_and(|line| line.is_empty()) {
+        summary.pop();
+    }
+    let mut kept: Vec<Item> = Vec::new();
+    for item in items {
+        if matches!(item, Item::Section(_)) && matches!(kept.last(), Some(Item::Section(_))) {
+            kept.pop();
+        }
+        kept.push(item);
+    }
+    if matches!(kept.last(), Some(Item::Section(_))) {
+        kept.pop();
+    }
+    FileDoc { name: name.to_string(), summary, items: kept }
+}
+
+/// Whether a documented file has any functions
+pub fn has_functions(file: &FileDoc) -> bool {
+    file.items.iter().any(|item| matches!(item, Item::Function { .. }))
+}
+
+/// Documentation for the files in the given format
+pub fn render(files: &[FileDoc], format: Format) -> String {
+    match format {
+        Format::Markdown => markdown(files),
+        Format::Html => html(files),
+    }
+}
+
+fn markdown(files: &[FileDoc]) -> String {
+    let mut out = String::from("# Lumen Library Reference\n");
+    for file in files {
+        out.push_str(&format!("\n## {}\n", file.name));
+        if !file.summary.is_empty() {
+            out.push_str(&format!("\n{}\n", file.summary.join("\n")));
+        }
+        for item in &file.items {
+            match item {
+                Item::Section(title) => out.push_str(&format!("\n### {}\n", title)),
+                Item::Function { name, params, doc, comments } => {
+                    out.push_str(&format!("\n#### `{}({})`\n", name, params.join(", ")));
+                    if let Some(doc) = doc {
+                        out.push_str(&format!("\n{}\n", doc));
+                    }
+                    if !comments.is_empty() {
+                        out.push_str(&format!("\n{}\n", comments.join("\n")));
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+fn html(files: &[FileDoc]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Lumen Library Reference</title>\n\
+         <style>body { font-family: sans-serif; max-width: 50em; margin: auto; } code { background: #f4f4f4; }</style>\n\
+         </head>\n<body>\n<h1>Lumen Library Reference</h1>\n",
+    );
+    let paragraph = |lines: &[String]| {
+        let lines: Vec<String> = lines.iter().map(|line| escape(line)).collect();
+        format!("<p>{}</p>\n", lines.join("<br>\n"))
+    };
+    for file in files {
+        out.push_str(&format!("<h2>{}</h2>\n", escape(&file.name)));
+        if !file.summary.is_empty() {
+            out.push_str(&paragraph(&file.summary));
+        }
+        for item in &file.items {
+            match item {
+                Item::Section(title) => out.push_str(&format!("<h3>{}</h3>\n", escape(title))),
+                Item::Function { name, params, doc, comments } => {
+                    out.push_str(&format!("<h4><code>{}({})</code></h4>\n", escape(name), escape(&params.join(", "))));
+                    if let Some(doc) = doc {
+                        out.push_str(&paragraph(std::slice::from_ref(doc)));
+                    }
+                    if !comments.is_empty() {
+                        out.push_str(&paragraph(comments));
+                    }
+                }
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Comment text made only of banner characters (an empty comment is a blank line, not a banner)
+fn is_banner(comment: &str) -> bool {
+    let comment = comment.trim();
+    !comment.is_empty() && comment.chars().all(|c| matches!(c, '#' | '=' | '-'))
+}
+
+/// Name and parameters of a `fn name(params)` line
+fn signature(line: &str) -> Option<(String, Vec<String>)> {
+    let rest = line.strip_prefix("fn ")?.trim_start();
+    let (name, rest) = rest.split_once('(')?;
+    let (params, _) = rest.split_once(')')?;
+    let params = params.split(',').map(str::trim).filter(|p| !p.is_empty()).map(str::to_string).collect();
+    Some((name.trim().to_string(), params))
+}
+
+/// Text of a line that is a single string literal
+fn docstring(line: &str) -> Option<String> {
+    let quote = line.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let inner = line.strip_prefix(quote)?.strip_suffix(quote)?;
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        if ch == quote {
+            // The literal ends before the line does
+            return None;
+        }
+        if ch != '\\' {
+            text.push(ch);
+            continue;
+        }
+        match chars.next()? {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            other => text.push(other),
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "# sample.lm\n# Helpers for samples\n\n## Arithmetic\n# Adds one\n# (to anything)\nfn inc(x)\n    \"Return x + 1.\"\n    x + 1\n\nfn twice( f ,x )\n    f(f(x))\n\n## Empty section\n";
+
+    #[test]
+    fn extracts_summary_sections_and_functions() {
+        let file = extract("lib/sample.lm", SOURCE);
+        assert_eq!(file.summary, ["Helpers for samples"]);
+        assert_eq!(file.items.len(), 3);
+        assert!(matches!(&file.items[0], Item::Section(title) if title == "Arithmetic"));
+        let Item::Function { name, params, doc, comments } = &file.items[1] else { panic!("expected a function") };
+        assert_eq!((name.as_str(), params.as_slice()), ("inc", ["x".to_string()].as_slice()));
+        assert_eq!(doc.as_deref(), Some("Return x + 1."));
+        assert_eq!(comments, &["Adds one", "(to anything)"]);
+        let Item::Function { params, doc, comments, .. } = &file.items[2] else { panic!("expected a function") };
+        assert_eq!(params, &["f", "x"]);
+        assert!(doc.is_none() && comments.is_empty());
+    }
+
+    #[test]
+    fn renders_markdown() {
+        let markdown = render(&[extract("sample.lm", SOURCE)], Format::Markdown);
+        assert!(markdown.starts_with("# Lumen Library Reference\n\n## sample.lm\n\nHelpers for samples\n\n### Arithmetic\n"));
+        assert!(markdown.contains("\n#### `inc(x)`\n\nReturn x + 1.\n\nAdds one\n(to anything)\n"), "{}", markdown);
+        assert!(markdown.ends_with("\n#### `twice(f, x)`\n"), "{}", markdown);
+    }
+}
@@ -0,0 +1,192 @@
+// Source formatter for `lumen-lang fmt`
+//
+// Works on lines, not tokens, so comments and blank lines survive unchanged in content:
+//
+//   indentation   4 spaces per block level (tabs count as 4 columns when reading)
+//   commas        no space before, one space after (outside strings and comments)
+//   whitespace    trailing whitespace removed, runs of blank lines collapsed to one,
+//                 no blank lines at the start or end, exactly one final newline
+//
+// Lines continuing a statement (open brackets or a trailing backslash) keep their
+// indentation relative to the statement's first line. A comment-only line takes the
+// level of the block whose indentation it matches, otherwise that of the next code line.
+// Formatting is idempotent: formatting formatted source leaves it unchanged.
+
+const INDENT: usize = 4;
+
+/// Formatted text of a Lumen source file
+pub fn format_source(source: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    // Indentation widths of the open blocks, as written in the input
+    let mut widths = vec![0usize];
+    // Comment-only lines waiting for the next code line: (blank line before, width, text)
+    let mut comments: Vec<(bool, usize, &str)> = Vec::new();
+    let mut blank = false;
+    // Columns added to continuation lines of the current statement
+    let mut shift = 0isize;
+    let mut depth = 0i32;
+    let mut continued = false;
+
+    for raw in source.lines() {
+        let line = raw.trim_end();
+        let text = line.trim_start();
+        let width = indent_width(line);
+
+        if depth > 0 || continued {
+            let width = if text.is_empty() { 0 } else { (width as isize + shift).max(0) as usize };
+            out.push(format!("{}{}", " ".repeat(width), space_commas(text)));
+            (depth, continued) = scan(text, depth);
+            continue;
+        }
+        if text.is_empty() {
+            blank = true;
+            continue;
+        }
+        if text.starts_with('#') {
+            comments.push((blank, width, text));
+            blank = false;
+            continue;
+        }
+
+        let before = widths.clone();
+        if width > *widths.last().unwrap_or(&0) {
+            widths.push(width);
+        } else {
+            while widths.len() > 1 && width < widths[widths.len() - 1] {
+                widths.pop();
+            }
+        }
+        let level = widths.len() - 1;
+        flush_comments(&mut out, &mut comments, &before, width, level);
+
+        if blank && !out.is_empty() {
+            out.push(String::new());
+        }
+        blank = false;
+        shift = (level * INDENT) as isize - width as isize;
+        out.push(format!("{}{}", " ".repeat(level * INDENT), space_commas(text)));
+        (depth, continued) = scan(text, 0);
+    }
+    flush_comments(&mut out, &mut comments, &widths, 0, 0);
+
+    if out.is_empty() {
+        return String::new();
+    }
+    out.join("\n") + "\n"
+}
+
+/// Emit waiting comment lines ahead of a code line at `width` that got block `level`
+/// `widths` is the block stack from before that line.
+fn flush_comments(out: &mut Vec<String>, comments: &mut Vec<(bool, usize, &str)>, widths: &[usize], width: usize, level: usize) {
+    for (blank, comment_width, text) in comments.drain(..) {
+        let comment_level = match widths.iter().position(|w| *w == comment_width) {
+            Some(matched) => matched,
+            None if comment_width >= width => level,
+            None => widths.iter().rposition(|w| *w <= comment_width).unwrap_or(0),
+        };
+        if blank && !out.is_empty() {
+            out.push(String::new());
+        }
+        out.push(format!("{}{}", " ".repeat(comment_level * INDENT), text));
+    }
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { INDENT } else { 1 })
+        .sum()
+}
+
+/// Bracket depth after a line, and whether it ends with a line-continuation backslash
+fn scan(code: &str, mut depth: i32) -> (i32, bool) {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut last = ' ';
+    for ch in code.chars() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '#' => break,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        if !ch.is_whitespace() {
+            last = ch;
+        }
+    }
+    (depth.max(0), last == '\\')
+}
+
+/// One space after each comma and none before, outside strings and comments
+fn space_commas(code: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut chars = code.char_indices().peekable();
+    let mut quote = None;
+    let mut escaped = false;
+    while let Some((i, ch)) = chars.next() {
+        if let Some(q) = quote {
+            out.push(ch);
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => {
+                quote = Some(ch);
+                out.push(ch);
+            }
+            '#' => {
+                out.push_str(&code[i..]);
+                break;
+            }
+            ',' => {
+                out.truncate(out.trim_end().len());
+                out.push(',');
+                while chars.peek().is_some_and(|(_, c)| *c == ' ' || *c == '\t') {
+                    chars.next();
+                }
+                if chars.peek().is_some_and(|(_, c)| !matches!(c, ')' | ']' | '}')) {
+                    out.push(' ');
+                }
+            }
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_indentation_and_spacing() {
+        let source = "\n\nfn add(a ,b)\n  # sum\n  return a+b   \n\n\n\nx = [1,2 ,  3]\nif x[0] > 0\n        print(add(1,2), \"a,b\")  # keep,this\n";
+        let expected = "fn add(a, b)\n    # sum\n    return a+b\n\nx = [1, 2, 3]\nif x[0] > 0\n    print(add(1, 2), \"a,b\")  # keep,this\n";
+        assert_eq!(format_source(source), expected);
+        assert_eq!(format_source(expected), expected);
+    }
+
+    #[test]
+    fn keeps_continuation_lines_relative() {
+        let source = "if true\n  total = [1,\n           2]\n  # after\nprint(total)\n";
+        let expected = "if true\n    total = [1,\n             2]\n    # after\nprint(total)\n";
+        assert_eq!(format_source(source), expected);
+    }
+}
@@ -0,0 +1,133 @@
+// Golden-file tests for `lumen-lang golden [--bless] [file... | dir...]`
+//
+// Each program found under the given directories (any file of a known language
+// extension, searched recursively, default tests/programs/) runs on the selected kernel,
+// and what it printed must match the .expected file next to it, byte for byte:
+//
+//   tests/programs/factorial.lm
+//   tests/programs/factorial.expected
+//
+// The expected text is the program's standard output, followed by a line
+// "[exit status N]" when it exits with a status other than 0, so a program that is meant
+// to fail is checked too. A mismatch shows the first line that differs:
+//
+//   PASS tests/programs/arithmetic.lm (31ms)
+//   FAIL tests/programs/strings.lm (29ms)
+//        output line 2: "HELLO" vs "Hello"
+//
+//   2 programs: 1 passed, 1 failed (60ms)
+//
+// --bless writes each program's output to its .expected file instead of comparing, for
+// new programs and intended changes; review the diff before committing it. A program
+// without a .expected file fails until it is blessed. Runs are stopped after
+// difftest::TIMEOUT.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+use crate::difftest::{self, Outcome};
+
+/// Run every program found under `paths` on the kernel at `binary`; Ok(true) when all matched
+/// With `bless`, their .expected files are written instead. `kernel_args` are passed to
+/// the kernel after each file (e.g. --define).
+pub fn run(binary: &Path, paths: &[String], kernel_args: &[String], bless: bool) -> Result<bool, String> {
+    let files = difftest::discover(paths)?;
+    if files.is_empty() {
+        return Err(format!("No programs found in {}", paths.join(", ")));
+    }
+
+    let start = Instant::now();
+    let mut failed = 0;
+    for file in &files {
+        let file_start = Instant::now();
+        let mut command = Command::new(binary);
+        command.arg(file).args(kernel_args);
+        let outcome = difftest::run_with_timeout(command, difftest::TIMEOUT)
+            .map_err(|e| format!("Failed to execute kernel at {:?}: {}", binary, e))?;
+        let elapsed = file_start.elapsed();
+        let expected_path = expected_file(file);
+
+        let Outcome::Finished(status, stdout, _) = outcome else {
+            failed += 1;
+            println!("FAIL {} (timed out after {:?})", file.display(), difftest::TIMEOUT);
+            continue;
+        };
+        let actual = recorded(status, stdout);
+        if bless {
+            fs::write(&expected_path, &actual)
+                .map_err(|e| format!("Failed to write {}: {}", expected_path.display(), e))?;
+            println!("BLESS {} ({:.0?})", expected_path.display(), elapsed);
+            continue;
+        }
+
+        let Ok(expected) = fs::read_to_string(&expected_path) else {
+            failed += 1;
+            println!("FAIL {} ({:.0?})", file.display(), elapsed);
+            println!("     no {} (run with --bless to create it)", expected_path.display());
+            continue;
+        };
+        match crate::first_difference(&expected, &actual) {
+            // first_difference compares lines, so a missing final newline would pass
+            None if expected == actual => println!("PASS {} ({:.0?})", file.display(), elapsed),
+            difference => {
+                failed += 1;
+                println!("FAIL {} ({:.0?})", file.display(), elapsed);
+                println!("     {}", difference.unwrap_or_else(|| "line endings differ".to_string()));
+            }
+        }
+    }
+
+    println!();
+    if bless {
+        println!(
+            "{} expected file{} written ({:.0?})",
+            files.len() - failed,
+            if files.len() - failed == 1 { "" } else { "s" },
+            start.elapsed()
+        );
+        return Ok(failed == 0);
+    }
+    println!(
+        "{} program{}: {} passed, {} failed ({:.0?})",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        files.len() - failed,
+        failed,
+        start.elapsed()
+    );
+    Ok(failed == 0)
+}
+
+/// The .expected file of a program
+fn expected_file(program: &Path) -> PathBuf {
+    program.with_extension("expected")
+}
+
+/// The text a run is compared as: its output and any failing exit status
+fn recorded(status: Option<i32>, mut stdout: String) -> String {
+    if status != Some(0) {
+        if !stdout.is_empty() && !stdout.ends_with('\n') {
+            stdout.push('\n');
+        }
+        match status {
+            Some(code) => stdout.push_str(&format!("[exit status {}]\n", code)),
+            None => stdout.push_str("[stopped by a signal]\n"),
+        }
+    }
+    stdout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_output_and_failing_exit_statuses() {
+        assert_eq!(expected_file(Path::new("tests/programs/pi.lm")), Path::new("tests/programs/pi.expected"));
+        assert_eq!(recorded(Some(0), "3.14\n".to_string()), "3.14\n");
+        assert_eq!(recorded(Some(1), "partial".to_string()), "partial\n[exit status 1]\n");
+        assert_eq!(recorded(None, String::new()), "[stopped by a signal]\n");
+    }
+}
@@ -0,0 +1,118 @@
+// Language listing for `lumen-lang langs [NAME]`
+//
+// Everything shown comes from the microcode kernel's language registry and each
+// language's schema: the --lang name and file extension, how blocks are delimited,
+// statement terminators, keywords, operators by precedence, and the features whose
+// keywords the schema defines (and extern calls, if it has an extern_syntax); then the
+// kernels that run it (see backend.rs).
+
+use crate::backend;
+use microcode_2::languages::{self, Language, LANGUAGES};
+use microcode_2::schema::{Associativity, LanguageSchema};
+
+/// Features shown when the schema has one of their keywords
+const FEATURES: [(&str, &[&str]); 9] = [
+    ("functions", &["fn", "def"]),
+    ("while loops", &["while"]),
+    ("for loops", &["for"]),
+    ("until loops", &["until"]),
+    ("break/continue", &["break", "continue"]),
+    ("return", &["return"]),
+    ("exit", &["exit"]),
+    ("let bindings", &["let"]),
+    ("mutable bindings", &["mut"]),
+];
+
+/// Listing of every registered language, or only the named one
+pub fn listing(name: Option<&str>) -> Result<String, String> {
+    let selected: Vec<&Language> = match name {
+        None => LANGUAGES.iter().collect(),
+        Some(name) => match languages::by_name(name) {
+            Some(language) => vec![language],
+            None => {
+                let names: Vec<&str> = LANGUAGES.iter().map(|language| language.name).collect();
+                return Err(format!("Unknown language '{}' (known languages: {})", name, names.join(", ")));
+            }
+        },
+    };
+    let entries: Vec<String> = selected.into_iter().map(describe).collect();
+    Ok(entries.join("\n"))
+}
+
+fn describe(language: &Language) -> String {
+    let schema = language.get_schema();
+    let mut keywords: Vec<&str> = schema.keywords.union(&schema.word_boundary_keywords).copied().collect();
+    keywords.sort_unstable();
+
+    let mut features: Vec<&str> = FEATURES
+        .iter()
+        .filter(|(_, needed)| needed.iter().any(|keyword| keywords.contains(keyword)))
+        .map(|(feature, _)| *feature)
+        .collect();
+    if schema.extern_syntax.is_some() {
+        features.push("extern calls");
+    }
+    let mut terminators: Vec<&str> =
+        schema.terminators.iter().map(|t| if *t == "\n" { "newline" } else { t }).collect();
+    terminators.sort_unstable_by_key(|t| (*t != "newline", *t));
+
+    let mut out = format!("{} (.{})\n", language.name, language.extension);
+    out.push_str(&format!("  blocks       {}\n", block_style(&schema)));
+    out.push_str(&format!("  statements   end at {}\n", terminators.join(" or ")));
+    out.push_str(&format!("  features     {}\n", features.join(", ")));
+    out.push_str(&format!("  keywords     {}\n", keywords.join(" ")));
+    out.push_str(&format!("  operators    {}\n", operators(&schema)));
+    let kernels: Vec<&str> =
+        backend::all().into_iter().filter(|backend| backend.supports(language.name)).map(|backend| backend.name()).collect();
+    out.push_str(&format!("  kernels      {}\n", kernels.join(", ")));
+    out
+}
+
+fn block_style(schema: &LanguageSchema) -> String {
+    if schema.block_open_marker == "{" {
+        return format!("braces {} {}", schema.block_open_marker, schema.block_close_marker);
+    }
+    let unit = if schema.indentation_char == '\t' { "tab" } else { "space" };
+    let mut style = format!("indentation ({} {}s per level)", schema.indentation_size, unit);
+    if !schema.block_open_marker.is_empty() {
+        style = format!("'{}' then {}", schema.block_open_marker, style);
+    }
+    style
+}
+
+/// Binary operators from loosest to tightest binding (right-associative ones marked),
+/// then the prefix operators
+fn operators(schema: &LanguageSchema) -> String {
+    let mut binary: Vec<(&String, f32, bool)> = schema
+        .binary_operators
+        .iter()
+        .map(|(op, info)| (op, info.precedence, matches!(info.associativity, Associativity::Right)))
+        .collect();
+    binary.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(b.0)));
+    let binary: Vec<String> = binary
+        .into_iter()
+        .map(|(op, _, right)| if right { format!("{} (right)", op) } else { op.clone() })
+        .collect();
+    let mut prefix: Vec<&String> = schema.unary_operators.keys().collect();
+    prefix.sort();
+    let prefix: Vec<&str> = prefix.into_iter().map(String::as_str).collect();
+    format!("{}; prefix {}", binary.join(" "), prefix.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_languages_from_their_schemas() {
+        let lumen = listing(Some("lumen")).unwrap();
+        assert!(lumen.starts_with("lumen (.lm)\n  blocks       indentation (4 spaces per level)\n"), "{}", lumen);
+        assert!(lumen.contains("until loops") && lumen.contains("extern calls"), "{}", lumen);
+        let rust = listing(Some("rust_core")).unwrap();
+        assert!(rust.contains("  blocks       braces { }\n"), "{}", rust);
+        assert!(rust.contains("extern calls"), "{}", rust);
+        assert!(rust.ends_with("  kernels      stream, microcode\n"), "{}", rust);
+        assert_eq!(listing(None).unwrap().matches(" (.").count(), LANGUAGES.len());
+        assert!(listing(Some("cobol")).is_err());
+    }
+}
@@ -1,134 +1,621 @@
 // Lumen-Lang Main Entry Point
 // Routes between opaque, stream and microcode kernels based on --kernel parameter
-// Usage: lumen-lang [--kernel opaque|stream|microcode] <file> [--lang <language>]
+// Usage: see USAGE below (`lumen-lang help`)
 // Default: microcode kernel
 
 use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
 use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod backend;
+mod completions;
+mod difftest;
+mod docgen;
+mod formatter;
+mod golden;
+mod langs;
+mod repl;
+mod spec;
+mod test_runner;
+
+// Programs split across several files, as the kernel binaries read them
+mod program_files {
+    include!("../lib_lumen/program_files.rs");
+}
+
+// Command-line reading, shared with the kernel binaries
+mod cli_args {
+    include!("../lib_lumen/cli_args.rs");
+}
+
+use cli_args::{Args, Arity, Flag};
+
+const USAGE: &str = "\
+Usage: lumen-lang [run] [--kernel KERNEL] <program> [options] [program_args...]
+       lumen-lang check|tokens|ast [--kernel KERNEL] <program> [options]
+       lumen-lang lint <program> [--lint=RULES] [--allow RULE]...
+       lumen-lang repl [--kernel KERNEL] [--lang LANGUAGE]
+       lumen-lang eval [--kernel KERNEL] [--lang LANGUAGE] <code> [program_args...]
+       lumen-lang fmt [--check] <file... | dir | ->
+       lumen-lang watch [--kernel KERNEL] <file... | dir> [options] [program_args...]
+       lumen-lang bench <program> [options] [program_args...]
+       lumen-lang test [--kernel KERNEL] [file... | dir...] [options]
+       lumen-lang difftest [file... | dir...] [options]
+       lumen-lang golden [--kernel KERNEL] [--bless] [file... | dir...] [options]
+       lumen-lang spec [file... | dir...]
+       lumen-lang doc [--html] [-o FILE] [file... | dir...]
+       lumen-lang langs [LANGUAGE]
+       lumen-lang completions bash|zsh|fish
+
+<program> is <file... | dir | - | -e code>; KERNEL is microcode (default), stream or opaque.
+Options are passed to the kernel: --lang LANGUAGE, --define NAME=value, --config FILE,
+and the kernel's own flags (see README). --watch and --bench-kernels are the same as
+watch and bench. With no arguments, a terminal starts repl and piped input is run.";
+
+const KERNELS: [&str; 3] = ["opaque", "stream", "microcode"];
+
+/// What the command line asks for
+enum Command {
+    /// Run a program (`run`, or no subcommand)
+    Run,
+    /// Parse without running: the kernel flag for check, tokens, ast or lint
+    Inspect(&'static str),
+    Repl,
+    /// Run inline code in process and print its value (see backend.rs)
+    Eval,
+    Fmt,
+    Watch,
+    Bench,
+    Test,
+    /// Run programs on every kernel and compare them (see difftest.rs)
+    DiffTest,
+    /// Compare programs' output with their .expected files (see golden.rs)
+    Golden,
+    /// Run the language specification's cases on every kernel (see spec.rs)
+    Spec,
+    Doc,
+    Langs,
+    Completions,
+    Help,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let (command, kernel, rest) = parse_command(&args[1..]);
 
-    // Parse --kernel parameter
-    let (kernel_type, remaining_args) = parse_kernel_arg(&args);
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+        }
+        Command::Run => run_kernel(&kernel, &rest),
+        Command::Inspect(flag) => {
+            if flag == "--lint" && kernel != "microcode" {
+                // The linter works on the instruction tree
+                eprintln!("Error: lint is only supported by the microcode kernel");
+                process::exit(1);
+            }
+            if rest.is_empty() {
+                usage_error();
+            }
+            run_kernel(&kernel, &with_flag(&rest, flag));
+        }
+        Command::Repl => {
+            if let Err(e) = repl::run(&kernel, &kernel_binary(&kernel), &rest) {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        }
+        Command::Eval => eval_code(&kernel, &rest),
+        Command::Fmt => format_files(&rest),
+        Command::Doc => generate_docs(&rest),
+        Command::Langs => {
+            let name = match rest.as_slice() {
+                [] => None,
+                [name] => Some(name.to_lowercase()),
+                _ => usage_error(),
+            };
+            match langs::listing(name.as_deref()) {
+                Ok(listing) => print!("{}", listing),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Completions => {
+            let [shell] = rest.as_slice() else { usage_error() };
+            match completions::script(shell) {
+                Ok(script) => print!("{}", script),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Watch => watch_and_run(&kernel, &rest),
+        Command::Bench => {
+            if rest.is_empty() {
+                usage_error();
+            }
+            bench_kernels(&rest);
+        }
+        Command::Test => {
+            // Test paths come first; the options after them go to the kernel
+            let split = rest.iter().position(|a| a.starts_with('-')).unwrap_or(rest.len());
+            let (paths, kernel_args) = rest.split_at(split);
+            let paths = if paths.is_empty() { vec![".".to_string()] } else { paths.to_vec() };
+            match test_runner::run(&kernel_binary(&kernel), &paths, kernel_args) {
+                Ok(passed) => process::exit(if passed { 0 } else { 1 }),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::DiffTest => {
+            // Program paths come first, as for test
+            let split = rest.iter().position(|a| a.starts_with('-')).unwrap_or(rest.len());
+            let (paths, kernel_args) = rest.split_at(split);
+            let paths = if paths.is_empty() { vec!["examples".to_string()] } else { paths.to_vec() };
+            match difftest::run(&paths, kernel_args, kernel_binary) {
+                Ok(agreed) => process::exit(if agreed { 0 } else { 1 }),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Golden => {
+            let bless = rest.iter().any(|a| a == "--bless");
+            let rest: Vec<String> = rest.into_iter().filter(|a| a != "--bless").collect();
+            // Program paths come first, as for test
+            let split = rest.iter().position(|a| a.starts_with('-')).unwrap_or(rest.len());
+            let (paths, kernel_args) = rest.split_at(split);
+            let paths = if paths.is_empty() { vec!["tests/programs".to_string()] } else { paths.to_vec() };
+            match golden::run(&kernel_binary(&kernel), &paths, kernel_args, bless) {
+                Ok(passed) => process::exit(if passed { 0 } else { 1 }),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Spec => {
+            let paths = if rest.is_empty() { vec!["spec".to_string()] } else { rest };
+            match spec::run(&paths, kernel_binary) {
+                Ok(passed) => process::exit(if passed { 0 } else { 1 }),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+}
 
-    // Default to microcode kernel
-    let kernel = if kernel_type.is_empty() {
-        "microcode"
-    } else {
-        &kernel_type
+/// Split the arguments into the subcommand, the kernel (--kernel right after the
+/// subcommand, or first for a plain run) and the arguments left for the command
+fn parse_command(args: &[String]) -> (Command, String, Vec<String>) {
+    let mut rest = Args::new(args);
+    let command = match rest.peek().map(subcommand) {
+        Some(Some(command)) => {
+            rest.next();
+            command
+        }
+        // Anything else starts a program to run
+        Some(None) => Command::Run,
+        // With no arguments, a terminal gets a session and piped input is run as the program
+        None if io::stdin().is_terminal() => Command::Repl,
+        None => Command::Run,
     };
 
-    // Route to appropriate kernel executable
-    match kernel {
-        "opaque" => run_opaque_kernel(&remaining_args),
-        "stream" => run_stream_kernel(&remaining_args),
-        "microcode" => run_microcode_kernel(&remaining_args),
-        _ => {
-            eprintln!("Error: Unknown kernel '{}'. Use 'opaque', 'stream', or 'microcode' (default).", kernel);
-            eprintln!("Usage: {} [--kernel opaque|stream|microcode] <file> [--lang <language>]", args[0]);
+    let kernel = match rest.value_of("--kernel") {
+        Ok(kernel) => kernel.map_or_else(|| "microcode".to_string(), str::to_lowercase),
+        Err(e) => {
+            eprintln!("Error: {}", e);
             process::exit(1);
         }
+    };
+    if !KERNELS.contains(&kernel.as_str()) {
+        eprintln!("Error: Unknown kernel '{}'. Use 'opaque', 'stream', or 'microcode' (default).", kernel);
+        process::exit(1);
     }
+    let rest = rest.rest();
+    if matches!(command, Command::Watch) && rest.is_empty() {
+        usage_error();
+    }
+    (command, kernel, rest.to_vec())
 }
 
-fn parse_kernel_arg(args: &[String]) -> (String, Vec<String>) {
-    if args.len() < 2 {
-        eprintln!("Usage: {} [--kernel opaque|stream|microcode] <file> [--lang <language>]", args.get(0).unwrap_or(&"lumen-lang".to_string()));
-        process::exit(1);
+/// The command a first argument names, if it is a subcommand
+fn subcommand(word: &str) -> Option<Command> {
+    Some(match word {
+        "run" => Command::Run,
+        "check" => Command::Inspect("--check"),
+        "tokens" => Command::Inspect("--tokens"),
+        "ast" => Command::Inspect("--ast"),
+        "lint" => Command::Inspect("--lint"),
+        "repl" => Command::Repl,
+        "eval" => Command::Eval,
+        "fmt" => Command::Fmt,
+        "watch" | "--watch" => Command::Watch,
+        "bench" | "--bench-kernels" => Command::Bench,
+        "test" => Command::Test,
+        "difftest" => Command::DiffTest,
+        "golden" => Command::Golden,
+        "spec" => Command::Spec,
+        "doc" => Command::Doc,
+        "langs" => Command::Langs,
+        "completions" => Command::Completions,
+        "help" | "--help" | "-h" => Command::Help,
+        _ => return None,
+    })
+}
+
+fn usage_error() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+/// Kernel arguments with an inspection flag placed right after the program
+/// (before any program arguments, which the kernel would not read as flags)
+fn with_flag(args: &[String], flag: &str) -> Vec<String> {
+    let mut rest = Args::new(args);
+    // A -e without code is passed on as it is, for the kernel to report
+    let _ = rest.input();
+    let program_len = args.len() - rest.rest().len();
+    let mut kernel_args = args[..program_len].to_vec();
+    kernel_args.push(flag.to_string());
+    kernel_args.extend_from_slice(&args[program_len..]);
+    kernel_args
+}
+
+/// Execute a kernel binary with the given arguments and exit with its status
+/// The kernel handles language detection and file processing.
+fn run_kernel(kernel: &str, args: &[String]) -> ! {
+    let binary_path = kernel_binary(kernel);
+    match process::Command::new(&binary_path).args(args).status() {
+        Ok(status) => process::exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("Error: Failed to execute {} kernel at {:?}: {}", kernel, binary_path, e);
+            eprintln!("Make sure to build with 'cargo build' first");
+            process::exit(1);
+        }
     }
+}
 
-    // Check if first argument is --kernel
-    if args.len() >= 3 && args[1] == "--kernel" {
-        let kernel = args[2].to_lowercase();
-        // Return the kernel and remaining args (excluding the binary name, --kernel, and kernel type)
-        let remaining: Vec<String> = args.iter().skip(3).cloned().collect();
-        (kernel, remaining)
-    } else {
-        // No --kernel specified, default to microcode
-        // Return empty kernel type and all args except the binary name
-        let remaining: Vec<String> = args.iter().skip(1).cloned().collect();
-        ("".to_string(), remaining)
+/// `eval`: run inline code on a kernel's backend and print the program's value, if it
+/// has one; errors are reported as the kernel binary would, with its exit status
+fn eval_code(kernel: &str, args: &[String]) -> ! {
+    let mut rest = Args::new(args);
+    let language = match rest.value_of("--lang") {
+        Ok(language) => language.map_or_else(|| "lumen".to_string(), str::to_lowercase),
+        Err(_) => usage_error(),
+    };
+    let [code, program_args @ ..] = rest.rest() else { usage_error() };
+    let Some(backend) = backend::by_name(kernel) else {
+        eprintln!("Error: eval is not supported by the {} kernel", kernel);
+        process::exit(1);
+    };
+    match backend.run(code, &language, program_args) {
+        Ok(value) => {
+            if let Some(value) = value {
+                println!("{}", value);
+            }
+            process::exit(0)
+        }
+        Err(backend::RunError::Exit(status)) => process::exit(status),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1)
+        }
     }
 }
 
-fn run_opaque_kernel(args: &[String]) {
-    // Execute the opaque kernel binary with the remaining arguments
-    // The opaque kernel will handle language detection and file processing
-    let mut binary_path = std::env::current_exe()
-        .expect("Failed to get current executable path")
-        .parent()
-        .expect("Failed to get parent directory")
-        .to_path_buf();
+/// `fmt`: rewrite Lumen files in place (or stdin to stdout); with --check, only list
+/// the files that would change and exit with 1 if there are any
+fn format_files(args: &[String]) -> ! {
+    let mut rest = Args::new(args);
+    let check = rest.next_if("--check");
+    let paths = rest.rest();
+    if paths.is_empty() {
+        usage_error();
+    }
 
-    let binary_name = if cfg!(windows) { "opaque.exe" } else { "opaque" };
-    binary_path.push(binary_name);
+    if paths == ["-"] {
+        let mut source = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut source) {
+            eprintln!("Error: Failed to read standard input: {}", e);
+            process::exit(1);
+        }
+        let formatted = formatter::format_source(&source);
+        if check {
+            process::exit(i32::from(formatted != source));
+        }
+        print!("{}", formatted);
+        process::exit(0);
+    }
 
-    let mut cmd = std::process::Command::new(&binary_path);
-    cmd.args(args);
+    let files = match program_files::collect(paths).and_then(|paths| program_files::read(&paths)) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut changed = false;
+    for file in files {
+        if !file.name.ends_with(".lm") {
+            eprintln!("Error: fmt formats Lumen (.lm) files only: {}", file.name);
+            process::exit(1);
+        }
+        let formatted = formatter::format_source(&file.text);
+        if formatted == file.text {
+            continue;
+        }
+        changed = true;
+        if check {
+            println!("{}", file.name);
+        } else if let Err(e) = fs::write(&file.name, formatted) {
+            eprintln!("Error: Failed to write {}: {}", file.name, e);
+            process::exit(1);
+        } else {
+            println!("formatted {}", file.name);
+        }
+    }
+    process::exit(i32::from(check && changed))
+}
 
-    match cmd.status() {
-        Ok(status) => {
-            process::exit(status.code().unwrap_or(1));
+/// `doc`: API documentation for the functions in .lm files (default lib_lumen), as
+/// Markdown or with --html as an HTML page, on stdout or into the -o file
+fn generate_docs(args: &[String]) -> ! {
+    let mut format = docgen::Format::Markdown;
+    let mut output = None;
+    let mut rest = Args::new(args);
+    let arity = |flag: &str| match flag {
+        "--html" => Some(Arity::Switch),
+        "-o" | "--output" => Some(Arity::Value),
+        _ => None,
+    };
+    while let Some(flag) = rest.next_flag(arity) {
+        match flag {
+            Ok(Flag { name: "--html", .. }) => format = docgen::Format::Html,
+            Ok(Flag { value, .. }) => output = value.map(str::to_string),
+            Err(_) => usage_error(),
         }
+    }
+    let mut paths = rest.rest().to_vec();
+    if paths.is_empty() {
+        paths.push("lib_lumen".to_string());
+    }
+
+    let files = match program_files::collect(&paths).and_then(|paths| program_files::read(&paths)) {
+        Ok(files) => files,
         Err(e) => {
-            eprintln!("Error: Failed to execute opaque kernel at {:?}: {}", binary_path, e);
-            eprintln!("Make sure to build with 'cargo build' first");
+            eprintln!("Error: {}", e);
             process::exit(1);
         }
+    };
+    let docs: Vec<docgen::FileDoc> = files
+        .iter()
+        .map(|file| docgen::extract(&file.name, &file.text))
+        .filter(docgen::has_functions)
+        .collect();
+    let text = docgen::render(&docs, format);
+    match output {
+        None => print!("{}", text),
+        Some(path) => {
+            if let Err(e) = fs::write(&path, text) {
+                eprintln!("Error: Failed to write {}: {}", path, e);
+                process::exit(1);
+            }
+            println!("documented {} files in {}", docs.len(), path);
+        }
     }
+    process::exit(0)
 }
 
-fn run_stream_kernel(args: &[String]) {
-    // Execute the stream kernel binary with the remaining arguments
-    // The stream kernel will handle language detection and file processing
+/// Path of a kernel executable, next to this binary
+fn kernel_binary(kernel: &str) -> PathBuf {
     let mut binary_path = std::env::current_exe()
         .expect("Failed to get current executable path")
         .parent()
         .expect("Failed to get parent directory")
         .to_path_buf();
 
-    let binary_name = if cfg!(windows) { "stream.exe" } else { "stream" };
+    let binary_name = if cfg!(windows) { format!("{}.exe", kernel) } else { kernel.to_string() };
     binary_path.push(binary_name);
+    binary_path
+}
 
-    let mut cmd = std::process::Command::new(&binary_path);
-    cmd.args(args);
+/// Run the program, then poll its source files and run it again after every change
+/// A save that leaves the contents unchanged does not trigger a re-run.
+/// Includes resolve from the embedded library, so the program files and the files the
+/// kernel flags name (--config, --prelude, --schema) are the inputs to watch.
+/// The microcode kernel runs with --cache, which keeps the last parse, so a re-run reduces
+/// only the statements from the one before the first changed line on, and none when only
+/// --config changed.
+fn watch_and_run(kernel: &str, args: &[String]) -> ! {
+    let paths = match args.first() {
+        Some(file) if !file.starts_with('-') => program_files::leading_paths(args),
+        _ => {
+            eprintln!("Error: --watch needs a source file or directory (not - or -e)");
+            process::exit(1);
+        }
+    };
+    let binary_path = kernel_binary(kernel);
+    let inputs = flag_files(args);
+    let args = if kernel == "microcode" { with_flag(args, "--cache") } else { args.to_vec() };
 
-    match cmd.status() {
-        Ok(status) => {
-            process::exit(status.code().unwrap_or(1));
+    let mut last_source: Option<String> = None;
+    let mut last_inputs: Vec<Option<Vec<u8>>> = Vec::new();
+    loop {
+        // A directory is listed again each time, so added and removed files count as changes
+        let files = program_files::collect(&paths).and_then(|paths| program_files::read(&paths));
+        let names = files.as_ref().map(|files| files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>().join(", "));
+        let source = files.as_ref().ok().map(|files| program_files::join(files));
+        // A missing one is an input too: the kernel reports it
+        let contents: Vec<Option<Vec<u8>>> = inputs.iter().map(|path| fs::read(path).ok()).collect();
+        // Missing or unchanged: keep waiting (editors may replace the file while saving)
+        if source.is_none() || (source == last_source && contents == last_inputs) {
+            thread::sleep(Duration::from_millis(250));
+            continue;
         }
-        Err(e) => {
-            eprintln!("Error: Failed to execute stream kernel at {:?}: {}", binary_path, e);
-            eprintln!("Make sure to build with 'cargo build' first");
-            process::exit(1);
+        let mut names = names.unwrap_or_default();
+        if last_source.is_some() {
+            let mut changed: Vec<&str> = inputs
+                .iter()
+                .zip(&contents)
+                .zip(&last_inputs)
+                .filter(|((_, new), old)| new != old)
+                .map(|((path, _), _)| path.as_str())
+                .collect();
+            if source != last_source {
+                changed.insert(0, &names);
+            }
+            eprintln!("[watch] {} changed, re-running", changed.join(", "));
+        }
+        last_source = source;
+        last_inputs = contents;
+        for input in &inputs {
+            names.push_str(&format!(", {}", input));
+        }
+
+        match process::Command::new(&binary_path).args(&args).status() {
+            Ok(status) => match status.code() {
+                Some(0) => eprintln!("[watch] finished; waiting for changes to {}", names),
+                Some(code) => eprintln!("[watch] exited with status {}; waiting for changes to {}", code, names),
+                None => eprintln!("[watch] stopped by a signal; waiting for changes to {}", names),
+            },
+            Err(e) => {
+                eprintln!("Error: Failed to execute {} kernel at {:?}: {}", kernel, binary_path, e);
+                eprintln!("Make sure to build with 'cargo build' first");
+                process::exit(1);
+            }
         }
     }
 }
 
-fn run_microcode_kernel(args: &[String]) {
-    // Execute the microcode kernel binary with the remaining arguments
-    // The microcode kernel will handle language detection and file processing
-    let mut binary_path = std::env::current_exe()
-        .expect("Failed to get current executable path")
-        .parent()
-        .expect("Failed to get parent directory")
-        .to_path_buf();
+/// The files that kernel flags before the program arguments name, which a run reads
+fn flag_files(args: &[String]) -> Vec<String> {
+    args.iter()
+        .take_while(|arg| *arg != "--")
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|pair| matches!(pair[0].as_str(), "--config" | "--prelude" | "--schema"))
+        .map(|pair| pair[1].clone())
+        .collect()
+}
 
-    let binary_name = if cfg!(windows) { "microcode.exe" } else { "microcode" };
-    binary_path.push(binary_name);
+/// Kernels compared by --bench-kernels, in report order (the first is the reference)
+const BENCH_KERNELS: [&str; 2] = ["stream", "microcode"];
 
-    let mut cmd = std::process::Command::new(&binary_path);
-    cmd.args(args);
+/// One kernel's run of the benchmarked program
+struct BenchRun {
+    kernel: &'static str,
+    status: Option<i32>,
+    stdout: String,
+    wall: Duration,
+    /// "[TIMING] ..." lines the kernel printed (stages, total, counts), without the prefix
+    stages: Vec<String>,
+}
 
-    match cmd.status() {
-        Ok(status) => {
-            process::exit(status.code().unwrap_or(1));
+/// Run the same program on each kernel binary that is present and report per-stage
+/// timings (the kernels' --timing report) and whether output and exit status agree
+/// Exits with 1 when the kernels disagree.
+fn bench_kernels(args: &[String]) -> ! {
+    let mut runs = Vec::new();
+    for kernel in BENCH_KERNELS {
+        let binary_path = kernel_binary(kernel);
+        if !binary_path.exists() {
+            println!("== {} ==\n  not built, skipped\n", kernel);
+            continue;
         }
-        Err(e) => {
-            eprintln!("Error: Failed to execute microcode kernel at {:?}: {}", binary_path, e);
-            eprintln!("Make sure to build with 'cargo build' first");
-            process::exit(1);
+        let start = Instant::now();
+        let output = match process::Command::new(&binary_path).args(with_flag(args, "--timing")).output() {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("Error: Failed to execute {} kernel at {:?}: {}", kernel, binary_path, e);
+                process::exit(1);
+            }
+        };
+        let wall = start.elapsed();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stages = stderr
+            .lines()
+            .filter_map(|line| line.strip_prefix("[TIMING] "))
+            .map(str::to_string)
+            .collect();
+        let run = BenchRun {
+            kernel,
+            status: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            wall,
+            stages,
+        };
+
+        println!("== {} ==", kernel);
+        match run.status {
+            Some(code) => println!("  exit status {}, wall time {:?}", code, run.wall),
+            None => println!("  stopped by a signal, wall time {:?}", run.wall),
+        }
+        if run.stages.is_empty() {
+            println!("  (no stage timings: the program stopped before the report)");
+        }
+        for stage in &run.stages {
+            println!("  {}", stage);
+        }
+        println!();
+        runs.push(run);
+    }
+
+    let Some((reference, others)) = runs.split_first() else {
+        eprintln!("Error: No kernel binaries found; build with 'cargo build' first");
+        process::exit(1);
+    };
+    println!("== results ==");
+    let mut agree = true;
+    for run in others {
+        let difference = if run.status != reference.status {
+            Some(format!("exit status {:?} vs {:?}", run.status, reference.status))
+        } else {
+            first_difference(&reference.stdout, &run.stdout)
+        };
+        match difference {
+            None => println!("  {}: same output and exit status as {}", run.kernel, reference.kernel),
+            Some(difference) => {
+                agree = false;
+                println!("  {}: differs from {} ({})", run.kernel, reference.kernel, difference);
+            }
+        }
+    }
+    if others.is_empty() {
+        println!("  only {} ran; nothing to compare", reference.kernel);
+    }
+    process::exit(if agree { 0 } else { 1 })
+}
+
+/// The first output line where two runs differ, if any
+fn first_difference(expected: &str, actual: &str) -> Option<String> {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (a, b) if a == b => continue,
+            (a, b) => {
+                return Some(format!(
+                    "output line {}: {:?} vs {:?}",
+                    line,
+                    b.unwrap_or("<end of output>"),
+                    a.unwrap_or("<end of output>")
+                ))
+            }
         }
     }
+    None
 }
@@ -0,0 +1,133 @@
+// Interactive session for `lumen-lang repl`
+//
+// The kernels run whole programs, so a session is replayed: each entry is appended to
+// the entries accepted so far and the result runs on the selected kernel as a fresh
+// program (`-e`). Output that earlier entries already produced is not shown again.
+// An entry that does not parse or stops with an error is reported and dropped, so the
+// session always holds a program that runs to the end. The microcode kernel runs with
+// --cache, which keeps the session's last parse, so a replay reduces only the new entry
+// and the statement before it (see src_microcode/kernel/incremental.rs).
+//
+// An entry continues on "..." lines until an empty line when its first line opens a
+// block (if, else, while, for, until, fn, or a line ending in ':' or '{'), and while
+// brackets are open.
+//
+// Commands: :quit (or end of input), :reset (forget all entries), :source (print them).
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const BLOCK_KEYWORDS: [&str; 6] = ["if", "else", "while", "for", "until", "fn"];
+
+/// Read entries from stdin until :quit or end of input
+/// `kernel_args` are passed to the kernel after the program (e.g. --lang).
+pub fn run(kernel: &str, binary: &Path, kernel_args: &[String]) -> io::Result<()> {
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Lumen REPL ({} kernel). Enter :quit or press Ctrl-D to leave.", kernel);
+    }
+
+    let mut session = String::new();
+    // Everything the session has printed so far
+    let mut shown = String::new();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        let Some(entry) = read_entry(&mut lines, interactive)? else {
+            return Ok(());
+        };
+        match entry.trim() {
+            "" => continue,
+            ":quit" | ":q" => return Ok(()),
+            ":reset" => {
+                session.clear();
+                shown.clear();
+                continue;
+            }
+            ":source" => {
+                print!("{}", session);
+                continue;
+            }
+            _ => {}
+        }
+
+        let program = format!("{}{}", session, entry);
+        let output = Command::new(binary)
+            .arg("-e")
+            .arg(&program)
+            .args((kernel == "microcode").then_some("--cache"))
+            .args(kernel_args)
+            .stdin(Stdio::null())
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        // Output depends only on the program, so a replay repeats the earlier output first
+        print!("{}", stdout.strip_prefix(shown.as_str()).unwrap_or(&stdout));
+        io::stdout().flush()?;
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        if output.status.success() {
+            session = program;
+            shown = stdout;
+        }
+    }
+}
+
+/// One entry, ending in a newline; None at end of input
+fn read_entry(lines: &mut impl Iterator<Item = io::Result<String>>, interactive: bool) -> io::Result<Option<String>> {
+    let mut entry = String::new();
+    loop {
+        if interactive {
+            print!("{}", if entry.is_empty() { "lumen> " } else { "...    " });
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            return Ok((!entry.is_empty()).then_some(entry));
+        };
+        entry.push_str(&line);
+        entry.push('\n');
+
+        let block = entry.lines().next().is_some_and(opens_block);
+        if line.trim().is_empty() || (!block && open_brackets(&entry) <= 0) {
+            return Ok(Some(entry));
+        }
+    }
+}
+
+fn opens_block(line: &str) -> bool {
+    let line = line.trim();
+    let first_word = line.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or("");
+    BLOCK_KEYWORDS.contains(&first_word) || line.ends_with(':') || line.ends_with('{')
+}
+
+/// Brackets opened and not yet closed, outside strings and comments
+fn open_brackets(source: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut comment = false;
+    for ch in source.chars() {
+        if ch == '\n' {
+            comment = false;
+        }
+        if comment {
+            continue;
+        }
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '#' => comment = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
@@ -0,0 +1,337 @@
+// Language specification suite for `lumen-lang spec [file... | dir...]`
+//
+// A .spec file (found under the given directories, searched recursively, default spec/)
+// states what programs do, as cases that every kernel must pass, so the suite is the
+// definition of the language's semantics rather than a description of one kernel. A case
+// is a title, optional settings, the source, and what it must print or the error it
+// must stop with:
+//
+//   === integer division truncates toward zero
+//   --- source
+//   print(7 // 2)
+//   print(-7 // 2)
+//   --- output
+//   3
+//   -3
+//
+//   === dividing by zero is an error
+//   kernels: stream, microcode
+//   --- source
+//   x = 1 / 0
+//   --- error
+//   division by zero
+//
+// `language:` is a --lang name (default lumen), and `kernels:` the kernels the case
+// applies to (default every kernel whose backend supports the language, see backend.rs).
+// An output case passes when the program exits with status 0 and its standard output is
+// exactly the listed lines; an error case when it exits with another status and its
+// standard error contains the listed text, ignoring case (kernels word messages
+// differently). Blank lines at the end of a section are not part of it, and lines
+// before the first case are comments. Each case runs once per kernel, on the kernel's
+// binary (skipped if it is not built):
+//
+//   PASS spec/arithmetic.spec: integer division truncates toward zero (stream, microcode)
+//   FAIL spec/strings.spec: slicing [stream]
+//        output line 1: "el" vs "ell"
+//
+//   2 cases: 1 passed, 1 failed (95ms)
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+use crate::backend;
+use crate::difftest::{self, Outcome};
+
+const SUFFIX: &str = ".spec";
+
+/// One case of a spec file
+#[derive(Debug, Clone, PartialEq)]
+struct Case {
+    title: String,
+    language: String,
+    /// Kernels named by `kernels:` (None for every one that supports the language)
+    kernels: Option<Vec<String>>,
+    source: String,
+    expected: Expected,
+}
+
+/// What a case must do
+#[derive(Debug, Clone, PartialEq)]
+enum Expected {
+    /// Exit with status 0, printing exactly this
+    Output(String),
+    /// Exit with another status, with this in the error message
+    Error(String),
+}
+
+/// Run every case of the spec files under `paths`; Ok(true) when all of them passed
+/// `binary` gives the path of a kernel's binary.
+pub fn run(paths: &[String], binary: impl Fn(&str) -> PathBuf) -> Result<bool, String> {
+    let files = discover(paths)?;
+    if files.is_empty() {
+        return Err(format!("No *{} files found in {}", SUFFIX, paths.join(", ")));
+    }
+
+    let start = Instant::now();
+    let (mut cases, mut failed) = (0, 0);
+    for file in &files {
+        let text = fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        for case in parse(&text).map_err(|e| format!("{}: {}", file.display(), e))? {
+            cases += 1;
+            let kernels = kernels_for(&case)?;
+            let mut ran = Vec::new();
+            let mut failures = Vec::new();
+            for kernel in kernels {
+                let binary = binary(kernel);
+                if !binary.exists() {
+                    continue;
+                }
+                let mut command = Command::new(&binary);
+                command.args(["-e", &case.source, "--lang", &case.language]);
+                let outcome = difftest::run_with_timeout(command, difftest::TIMEOUT)
+                    .map_err(|e| format!("Failed to execute {} kernel at {:?}: {}", kernel, binary, e))?;
+                ran.push(kernel);
+                if let Some(failure) = check(&case.expected, &outcome) {
+                    failures.push((kernel, failure));
+                }
+            }
+
+            let name = format!("{}: {}", file.display(), case.title);
+            if ran.is_empty() {
+                failed += 1;
+                println!("FAIL {} (no kernel for it is built)", name);
+            } else if failures.is_empty() {
+                println!("PASS {} ({})", name, ran.join(", "));
+            } else {
+                failed += 1;
+                for (kernel, failure) in failures {
+                    println!("FAIL {} [{}]", name, kernel);
+                    for line in failure.lines() {
+                        println!("     {}", line);
+                    }
+                }
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "{} case{}: {} passed, {} failed ({:.0?})",
+        cases,
+        if cases == 1 { "" } else { "s" },
+        cases - failed,
+        failed,
+        start.elapsed()
+    );
+    Ok(failed == 0)
+}
+
+/// The kernels a case runs on, in --kernel listing order
+fn kernels_for(case: &Case) -> Result<Vec<&'static str>, String> {
+    let supporting = backend::all().into_iter().filter(|backend| backend.supports(&case.language));
+    let Some(named) = &case.kernels else {
+        return Ok(supporting.map(|backend| backend.name()).collect());
+    };
+    let mut kernels = Vec::new();
+    for name in named {
+        let backend = backend::by_name(name).ok_or_else(|| format!("{}: unknown kernel '{}'", case.title, name))?;
+        if !backend.supports(&case.language) {
+            return Err(format!("{}: the {} kernel does not run {}", case.title, name, case.language));
+        }
+        kernels.push(backend.name());
+    }
+    Ok(kernels)
+}
+
+/// Why a run does not meet `expected`, if it does not
+fn check(expected: &Expected, outcome: &Outcome) -> Option<String> {
+    let Outcome::Finished(status, stdout, stderr) = outcome else {
+        return Some(format!("timed out after {:?}", difftest::TIMEOUT));
+    };
+    match expected {
+        Expected::Output(output) => {
+            if *status != Some(0) {
+                let mut failure = format!("expected output, but exited with status {:?}", status);
+                for line in stderr.lines().take(3) {
+                    failure.push_str(&format!("\n{}", line));
+                }
+                Some(failure)
+            } else if stdout != output {
+                Some(crate::first_difference(output, stdout).unwrap_or_else(|| "line endings differ".to_string()))
+            } else {
+                None
+            }
+        }
+        Expected::Error(message) => {
+            if *status == Some(0) {
+                Some(format!("expected an error containing {:?}, but the program finished", message))
+            } else if !stderr.to_lowercase().contains(&message.to_lowercase()) {
+                let first = stderr.lines().next().unwrap_or("");
+                Some(format!("expected an error containing {:?}, got: {}", message, first))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// The cases of a spec file's text
+fn parse(text: &str) -> Result<Vec<Case>, String> {
+    let mut cases = Vec::new();
+    let mut lines = text.lines().enumerate().peekable();
+    // Lines before the first case are comments
+    while lines.peek().is_some_and(|(_, line)| !line.starts_with("=== ")) {
+        lines.next();
+    }
+    while let Some((number, line)) = lines.next() {
+        let title = line.strip_prefix("=== ").unwrap_or_default().trim().to_string();
+        let at = |what: &str| format!("line {}: {}", number + 1, what);
+        let mut case = Case {
+            title,
+            language: "lumen".to_string(),
+            kernels: None,
+            source: String::new(),
+            expected: Expected::Output(String::new()),
+        };
+        let (mut source, mut expected) = (None, None);
+        let mut section: Option<(String, String)> = None;
+        while let Some((_, line)) = lines.next_if(|(_, line)| !line.starts_with("=== ")) {
+            if let Some(name) = line.strip_prefix("--- ") {
+                if let Some((name, body)) = section.take() {
+                    store(&name, body, &mut source, &mut expected).map_err(|e| at(&e))?;
+                }
+                section = Some((name.trim().to_string(), String::new()));
+            } else if let Some((_, body)) = &mut section {
+                body.push_str(line);
+                body.push('\n');
+            } else if let Some(language) = line.strip_prefix("language:") {
+                case.language = language.trim().to_lowercase();
+            } else if let Some(kernels) = line.strip_prefix("kernels:") {
+                case.kernels = Some(kernels.split(',').map(|k| k.trim().to_lowercase()).filter(|k| !k.is_empty()).collect());
+            } else if !line.trim().is_empty() {
+                return Err(at(&format!("unexpected line in case '{}': {}", case.title, line)));
+            }
+        }
+        if let Some((name, body)) = section.take() {
+            store(&name, body, &mut source, &mut expected).map_err(|e| at(&e))?;
+        }
+        if case.title.is_empty() {
+            return Err(at("a case needs a title after ==="));
+        }
+        case.source = source.ok_or_else(|| at(&format!("case '{}' has no --- source", case.title)))?;
+        case.expected = expected.ok_or_else(|| at(&format!("case '{}' has no --- output or --- error", case.title)))?;
+        cases.push(case);
+    }
+    Ok(cases)
+}
+
+/// Keep a finished section of a case, without its trailing blank lines
+fn store(name: &str, body: String, source: &mut Option<String>, expected: &mut Option<Expected>) -> Result<(), String> {
+    let body = format!("{}\n", body.trim_end_matches('\n'));
+    let body = if body == "\n" { String::new() } else { body };
+    match name {
+        "source" => *source = Some(body),
+        "output" if expected.is_none() => *expected = Some(Expected::Output(body)),
+        "error" if expected.is_none() => *expected = Some(Expected::Error(body.trim().to_string())),
+        "output" | "error" => return Err("a case has one --- output or --- error".to_string()),
+        other => return Err(format!("unknown section '--- {}' (source, output or error)", other)),
+    }
+    Ok(())
+}
+
+/// Spec files named directly or found under the directories, in sorted order
+fn discover(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            search(path, &mut files).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        } else if path.is_file() {
+            files.push(path.to_path_buf());
+        } else {
+            return Err(format!("No such file or directory: {}", path.display()));
+        }
+    }
+    Ok(files)
+}
+
+fn search(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?.map(|entry| entry.map(|e| e.path())).collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                search(&path, files)?;
+            }
+        } else if name.ends_with(SUFFIX) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "\
+Arithmetic cases
+
+=== addition
+--- source
+print(1 + 2)
+--- output
+3
+
+=== no output
+language: Python_Core
+kernels: microcode, stream
+--- source
+x = 1
+--- output
+
+=== dividing by zero
+--- source
+x = 1 / 0
+--- error
+Division by zero
+";
+
+    #[test]
+    fn parses_cases_with_their_settings() {
+        let cases = parse(SPEC).unwrap();
+        assert_eq!(cases.len(), 3);
+        assert_eq!(cases[0].title, "addition");
+        assert_eq!((cases[0].language.as_str(), &cases[0].kernels), ("lumen", &None));
+        assert_eq!(cases[0].source, "print(1 + 2)\n");
+        assert_eq!(cases[0].expected, Expected::Output("3\n".to_string()));
+        assert_eq!(cases[1].language, "python_core");
+        assert_eq!(cases[1].kernels, Some(vec!["microcode".to_string(), "stream".to_string()]));
+        assert_eq!(cases[1].expected, Expected::Output(String::new()));
+        assert_eq!(cases[2].expected, Expected::Error("Division by zero".to_string()));
+        assert_eq!(kernels_for(&cases[1]).unwrap(), ["microcode", "stream"]);
+
+        assert!(parse("=== x\n--- output\n1\n").unwrap_err().contains("no --- source"));
+        assert!(parse("=== x\n--- source\n--- stdout\n").unwrap_err().contains("unknown section"));
+        assert!(parse("=== x\nkernel: stream\n").unwrap_err().contains("unexpected line"));
+    }
+
+    #[test]
+    fn checks_output_exactly_and_errors_by_message() {
+        let finished = |status, stdout: &str, stderr: &str| Outcome::Finished(Some(status), stdout.to_string(), stderr.to_string());
+        let output = Expected::Output("3\n".to_string());
+        assert_eq!(check(&output, &finished(0, "3\n", "")), None);
+        assert!(check(&output, &finished(0, "4\n", "")).unwrap().contains("output line 1"));
+        assert!(check(&output, &finished(0, "3", "")).is_some());
+        assert!(check(&output, &finished(1, "3\n", "Error: oops")).unwrap().contains("Error: oops"));
+        let error = Expected::Error("division by zero".to_string());
+        assert_eq!(check(&error, &finished(1, "", "RuntimeError: Division by zero")), None);
+        assert!(check(&error, &finished(0, "", "")).is_some());
+        assert!(check(&error, &finished(1, "", "TypeError")).is_some());
+        assert!(check(&error, &Outcome::TimedOut).unwrap().starts_with("timed out"));
+    }
+}
@@ -0,0 +1,128 @@
+// Test runner for `lumen-lang test [file... | dir...]`
+//
+// Finds *_test.lm files under the given directories (searched recursively, default the
+// current directory) and runs each one as its own program on the selected kernel, so
+// test files share no state. A file passes when it runs to the end with exit status 0;
+// a failed assert() / assert_eq() (lib_lumen/assert.lm) or any other error fails it,
+// and its output is shown under the result line:
+//
+//   PASS lib_lumen/tests/string_test.lm (41ms)
+//   FAIL lib_lumen/tests/round_test.lm (38ms)
+//        LumenError: assertion failed: expected 3, got 2
+//
+//   2 test files: 1 passed, 1 failed (79ms)
+//
+// Files named on the command line run whatever their name. Hidden directories and
+// target/ are not searched.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+const SUFFIX: &str = "_test.lm";
+
+/// Run every test file found under `paths`; Ok(true) when all of them passed
+/// `kernel_args` are passed to the kernel after each file (e.g. --define).
+pub fn run(binary: &Path, paths: &[String], kernel_args: &[String]) -> Result<bool, String> {
+    let files = discover(paths)?;
+    if files.is_empty() {
+        return Err(format!("No *{} files found in {}", SUFFIX, paths.join(", ")));
+    }
+
+    let start = Instant::now();
+    let mut failed = 0;
+    for file in &files {
+        let file_start = Instant::now();
+        let output = Command::new(binary)
+            .arg(file)
+            .args(kernel_args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Failed to execute kernel at {:?}: {}", binary, e))?;
+        let elapsed = file_start.elapsed();
+
+        if output.status.success() {
+            println!("PASS {} ({:.0?})", file.display(), elapsed);
+            continue;
+        }
+        failed += 1;
+        println!("FAIL {} ({:.0?})", file.display(), elapsed);
+        let shown = [output.stdout, output.stderr].concat();
+        for line in String::from_utf8_lossy(&shown).lines() {
+            println!("     {}", line);
+        }
+        if output.status.code().is_none() {
+            println!("     (stopped by a signal)");
+        }
+    }
+
+    println!();
+    println!(
+        "{} test file{}: {} passed, {} failed ({:.0?})",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        files.len() - failed,
+        failed,
+        start.elapsed()
+    );
+    Ok(failed == 0)
+}
+
+/// Test files named directly or found under the directories, in sorted order
+pub fn discover(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            search(path, &mut files).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        } else if path.is_file() {
+            files.push(path.to_path_buf());
+        } else {
+            return Err(format!("No such file or directory: {}", path.display()));
+        }
+    }
+    Ok(files)
+}
+
+fn search(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?.map(|entry| entry.map(|e| e.path())).collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                search(&path, files)?;
+            }
+        } else if name.ends_with(SUFFIX) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovers_test_files_recursively_in_order() {
+        let root = std::env::temp_dir().join(format!("lumen_test_runner_{}", std::process::id()));
+        for dir in ["b", "a/nested", ".hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["b/two_test.lm", "a/nested/one_test.lm", "a/helper.lm", ".hidden/skip_test.lm", "zero_test.lm"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let found = discover(&[root.to_string_lossy().into_owned()]);
+        fs::remove_dir_all(&root).unwrap();
+        let names: Vec<String> = found
+            .unwrap()
+            .iter()
+            .map(|path| path.strip_prefix(&root).unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["a/nested/one_test.lm", "b/two_test.lm", "zero_test.lm"]);
+    }
+}
@@ -4,6 +4,7 @@
 // Key principle: tokens are MEANINGFUL units (not characters).
 // Strings are atomic, keywords are identified, operators are complete.
 
+use super::control_flow::LocatedError;
 use crate::schema::LanguageSchema;
 
 #[derive(Debug, Clone)]
@@ -64,61 +65,50 @@ fn strip_comments(source: &str) -> String {
 }
 
 /// Tokenize source using schema's multichar sequences
-pub fn lex(source: &str, schema: &LanguageSchema) -> Result<Vec<Token>, String> {
+pub fn lex(source: &str, schema: &LanguageSchema) -> Result<Vec<Token>, Vec<LocatedError>> {
+    lex_from(source, schema, 1)
+}
+
+/// Tokenize source whose first line is line `first_line` of a longer text
+/// (a program run after a prelude keeps the line numbers it would have after the prelude's text)
+pub fn lex_from(source: &str, schema: &LanguageSchema, first_line: usize) -> Result<Vec<Token>, Vec<LocatedError>> {
     let source = strip_comments(source);
     let mut tokens = Vec::new();
     let bytes = source.as_bytes();
     let mut pos = 0;
-    let mut line = 1;
+    let mut line = first_line;
     let mut col = 1;
 
     while pos < bytes.len() {
         let start_col = col;
-        let remaining = &source[pos..];
-
-        // Try multichar sequences first (sorted by length, longest first)
-        let mut matched = false;
-
-        for &seq in &schema.multichar_lexemes {
-            if remaining.starts_with(seq) {
-                // Check word boundary for keywords
-                let is_keyword = seq.chars().all(|c| c.is_alphabetic() || c == '_');
-                if is_keyword {
-                    let after_pos = pos + seq.len();
-                    if after_pos < bytes.len() {
-                        let next_byte = bytes[after_pos];
-                        let next_ch = next_byte as char;
-                        if next_ch.is_alphanumeric() || next_ch == '_' {
-                            continue;  // Word boundary violated, try next
-                        }
-                    }
-                }
 
-                // Matched! Add token.
-                tokens.push(Token {
-                    lexeme: seq.to_string(),
-                    span: (pos, pos + seq.len()),
-                    line,
-                    col: start_col,
-                });
-
-                // Update position
-                for ch in seq.chars() {
-                    if ch == '\n' {
-                        line += 1;
-                        col = 1;
-                    } else {
-                        col += 1;
-                    }
-                }
+        // Try multichar sequences first (longest first, walking the schema's trie)
+        let multichar = schema.multichar_trie().longest_match(&bytes[pos..], |seq, is_keyword| {
+            // Check word boundary for keywords
+            let next_ch = bytes.get(pos + seq.len()).map(|&byte| byte as char);
+            !is_keyword || !next_ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+        });
 
-                pos += seq.len();
-                matched = true;
-                break;
+        if let Some(seq) = multichar {
+            // Matched! Add token.
+            tokens.push(Token {
+                lexeme: seq.to_string(),
+                span: (pos, pos + seq.len()),
+                line,
+                col: start_col,
+            });
+
+            // Update position
+            for ch in seq.chars() {
+                if ch == '\n' {
+                    line += 1;
+                    col = 1;
+                } else {
+                    col += 1;
+                }
             }
-        }
 
-        if matched {
+            pos += seq.len();
             continue;
         }
 
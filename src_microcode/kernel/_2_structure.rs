@@ -10,20 +10,27 @@
 // 2. When indentation increases, insert {
 // 3. When indentation decreases, insert }
 // 4. Handle colons as block openers (for languages that use them, like PythonCore)
+//
+// Line continuation: inside ( ) or after a trailing backslash, the newline is
+// turned into a space before indentation is measured, so the next line joins
+// the current one and its indentation is not significant.
 
 use super::_1_ingest::Token;
+use super::control_flow::LocatedError;
 use crate::schema::LanguageSchema;
 
 /// Process indentation and insert block markers
 pub fn process_structure(
     tokens: Vec<Token>,
     schema: &LanguageSchema,
-) -> Result<Vec<Token>, String> {
+) -> Result<Vec<Token>, Vec<LocatedError>> {
     // For brace-based languages, skip processing
     if schema.block_open_marker == "{" {
         return Ok(tokens);
     }
 
+    let tokens = join_continued_lines(tokens);
+
     // First pass: track bracket depth globally to identify bracket ranges
     let mut bracket_depth_by_index = vec![0; tokens.len()];
     let mut bracket_depth = 0;
@@ -128,16 +135,89 @@ pub fn process_structure(
         }
     }
 
+    // A last line without a trailing newline (e.g. inline -e source) ends in the EOF
+    // marker: close the blocks before it, after terminating the line
+    let eof = if result.last().map(|t| t.lexeme.as_str()) == Some("EOF") {
+        result.pop()
+    } else {
+        None
+    };
+    // Synthetic tokens report the last source line (column 0: no particular position)
+    let last_line = result.last().map_or(0, |t| t.line);
+    if indent_stack.len() > 1 && result.last().map(|t| t.lexeme.as_str()) != Some("\n") {
+        result.push(Token {
+            lexeme: "\n".to_string(),
+            span: (0, 0),
+            line: last_line,
+            col: 0,
+        });
+    }
+
     // Close all remaining open indentation blocks
     while indent_stack.len() > 1 {
         indent_stack.pop();
         result.push(Token {
             lexeme: "}".to_string(),
             span: (0, 0),
-            line: 0,
+            line: last_line,
             col: 0,
         });
     }
+    result.extend(eof);
 
     Ok(result)
 }
+
+/// Join lines that continue onto the next one: newlines inside parentheses, and a
+/// trailing backslash together with its newline, become a single space.
+/// Parentheses and backslashes inside string literals are ignored.
+fn join_continued_lines(tokens: Vec<Token>) -> Vec<Token> {
+    let mut result: Vec<Token> = Vec::with_capacity(tokens.len());
+    let mut paren_depth = 0i32;
+    let mut in_string: Option<String> = None;
+    let mut escape_next = false;
+    // Index in `result` of a backslash that may be a continuation marker
+    let mut pending_backslash: Option<usize> = None;
+
+    for token in tokens {
+        let lexeme = token.lexeme.as_str();
+
+        if let Some(quote) = &in_string {
+            if escape_next {
+                escape_next = false;
+            } else if lexeme == "\\" {
+                escape_next = true;
+            } else if lexeme == quote {
+                in_string = None;
+            }
+            result.push(token);
+            continue;
+        }
+
+        match lexeme {
+            "\"" | "'" => in_string = Some(lexeme.to_string()),
+            "(" => paren_depth += 1,
+            ")" => paren_depth -= 1,
+            "\n" => {
+                if let Some(index) = pending_backslash.take() {
+                    result.remove(index);
+                } else if paren_depth <= 0 {
+                    result.push(token);
+                    continue;
+                }
+                result.push(Token { lexeme: " ".to_string(), ..token });
+                continue;
+            }
+            " " | "\t" => {
+                result.push(token);
+                continue;
+            }
+            _ => {}
+        }
+
+        pending_backslash = if lexeme == "\\" { Some(result.len()) } else { None };
+        result.push(token);
+    }
+
+    result
+}
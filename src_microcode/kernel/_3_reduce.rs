@@ -1,24 +1,53 @@
 // Stage 3: Reduce - Token stream → Instruction tree
 //
-// Parse tokens into Instruction tree using 7 primitives.
+// Parse tokens into Instruction tree using 7 primitives. Instructions are stored in
+// one Program arena as they are reduced; operands are stored before the instruction using them.
 // All semantics come from:
-// - Schema (operator precedence, statement patterns)
+// - Schema (operators and their precedence, statement keywords, terminators)
 // - Value types (what data exists)
 // - Environment (where data lives)
 //
-// Parser uses Pratt parsing for expressions + top-down for statements.
+// Parser uses precedence climbing over the schema's operator tables for expressions, and
+// top-down parsing for statements, whose keywords count only where the schema lists them.
 
 use super::eval::Value;
 use super::_1_ingest::Token;
-use super::primitives::Instruction;
-use crate::schema::LanguageSchema;
+use super::control_flow::LocatedError;
+use super::primitives::{InstrId, Instruction, Position, Program};
+use crate::schema::{Associativity, IntegerOverflow, LanguageSchema, UnaryPosition};
+
+/// The loosest operator an operand extends over (see Parser::parse_operators)
+#[derive(Debug, Clone, Copy)]
+enum Floor {
+    /// Every operator
+    Any,
+    /// Operators of this precedence or higher
+    AtLeast(f32),
+    /// Operators of higher precedence only
+    Above(f32),
+}
+
+impl Floor {
+    fn admits(self, precedence: f32) -> bool {
+        match self {
+            Floor::Any => true,
+            Floor::AtLeast(floor) => precedence >= floor,
+            Floor::Above(floor) => precedence > floor,
+        }
+    }
+}
 
 /// Parser: stateful token consumer
 struct Parser<'a> {
     tokens: &'a [Token],
     pos: usize,
-    #[allow(dead_code)]
+    /// Errors of statements that failed to parse and were skipped
+    errors: Vec<LocatedError>,
+    /// Instructions reduced so far
+    program: Program,
     schema: &'a LanguageSchema,
+    /// What peek returns past the last token
+    eof: Token,
 }
 
 impl<'a> Parser<'a> {
@@ -26,27 +55,36 @@ impl<'a> Parser<'a> {
         Parser {
             tokens,
             pos: 0,
+            errors: Vec::new(),
+            program: Program::default(),
             schema,
+            eof: Token { lexeme: "EOF".to_string(), span: (0, 0), line: 0, col: 0 },
         }
     }
 
-    fn peek(&self) -> Token {
-        self.tokens.get(self.pos).cloned().unwrap_or_else(|| Token {
-            lexeme: "EOF".to_string(),
-            span: (0, 0),
-            line: 0,
-            col: 0,
-        })
+    /// Store an instruction in the program
+    fn add(&mut self, instr: Instruction) -> InstrId {
+        self.program.add(instr)
+    }
+
+    fn peek(&self) -> &Token {
+        self.tokens.get(self.pos).unwrap_or(&self.eof)
     }
 
     fn advance(&mut self) -> Token {
-        let token = self.peek();
+        let token = self.peek().clone();
         if self.pos < self.tokens.len() {
             self.pos += 1;
         }
         token
     }
 
+    /// Where the next token starts
+    fn position(&self) -> Position {
+        let token = self.peek();
+        Position { line: token.line, col: token.col }
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().lexeme == "EOF"
     }
@@ -59,8 +97,9 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse a program (sequence of statements)
-    fn parse_program(&mut self) -> Result<Instruction, String> {
+    fn parse_program(&mut self) -> Result<InstrId, String> {
         let mut stmts = Vec::new();
+        let mut positions = Vec::new();
 
         while !self.is_at_end() {
             self.skip_whitespace();
@@ -68,23 +107,82 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            let stmt = self.parse_statement()?;
-            stmts.push(stmt);
+            let start = self.pos;
+            let position = self.position();
+            match self.parse_statement() {
+                Ok(stmt) => {
+                    stmts.push(stmt);
+                    positions.push(position);
+                }
+                Err(e) => self.recover(e, start),
+            }
             self.skip_whitespace();
 
-            // Skip optional semicolon after statement
-            if self.peek().lexeme == ";" {
+            // Skip an optional terminator after the statement
+            if self.schema.is_terminator(&self.peek().lexeme) {
                 self.advance();
                 self.skip_whitespace();
             }
         }
 
-        Ok(Instruction::sequence(stmts))
+        Ok(self.add(Instruction::sequence(stmts, positions)))
+    }
+
+    /// Record the error of a statement that failed to parse, and skip the rest of it
+    /// Starts over from `start`, where the statement began, and stops after a line end or
+    /// ';' outside of blocks (unless a block follows), after the statement's last block
+    /// (unless `else` follows), or before the '}' that closes the enclosing block.
+    fn recover(&mut self, error: String, start: usize) {
+        self.errors.push(self.locate(error));
+        self.pos = start;
+        let mut depth = 0usize;
+        while !self.is_at_end() {
+            if depth == 0 && self.peek().lexeme == "}" && self.pos > start {
+                break;
+            }
+            let lexeme = self.advance().lexeme;
+            match lexeme.as_str() {
+                "{" => depth += 1,
+                "}" => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 && self.next_lexeme() != "else" {
+                        break;
+                    }
+                }
+                end if depth == 0 && self.schema.is_terminator(end) && self.next_lexeme() != "{" => break,
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the statement ends here: at a terminator, the end of the block or the input
+    fn at_statement_end(&self) -> bool {
+        let lexeme = &self.peek().lexeme;
+        self.is_at_end() || lexeme == "\n" || lexeme == "}" || self.schema.is_terminator(lexeme)
+    }
+
+    /// The next token that is not whitespace
+    fn next_lexeme(&self) -> &str {
+        self.tokens[self.pos..]
+            .iter()
+            .map(|token| token.lexeme.as_str())
+            .find(|lexeme| !matches!(*lexeme, " " | "\t" | "\n"))
+            .unwrap_or("EOF")
+    }
+
+    /// An error with the position of the token where parsing stopped (line 0: past the end)
+    fn locate(&self, error: String) -> LocatedError {
+        let token = self.peek();
+        LocatedError::at(error, token.line, token.col)
     }
 
     /// Parse a statement
-    fn parse_statement(&mut self) -> Result<Instruction, String> {
+    /// A statement keyword the schema does not list is an ordinary name.
+    fn parse_statement(&mut self) -> Result<InstrId, String> {
         let keyword = &self.peek().lexeme.clone();
+        if !self.schema.is_keyword(keyword) {
+            return self.parse_assignment_or_expression();
+        }
 
         match keyword.as_str() {
             "let" => self.parse_let(),
@@ -93,13 +191,14 @@ impl<'a> Parser<'a> {
             "for" => self.parse_for(),
             "until" => self.parse_until(),
             "return" => self.parse_return(),
+            "exit" => self.parse_exit(),
             "break" => {
                 self.advance();
-                Ok(Instruction::break_stmt())
+                Ok(self.add(Instruction::break_stmt()))
             }
             "continue" => {
                 self.advance();
-                Ok(Instruction::continue_stmt())
+                Ok(self.add(Instruction::continue_stmt()))
             }
             "fn" => self.parse_function_def(),
             _ => self.parse_assignment_or_expression(),
@@ -107,7 +206,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse: let [mut] name [: type] = expr
-    fn parse_let(&mut self) -> Result<Instruction, String> {
+    fn parse_let(&mut self) -> Result<InstrId, String> {
         self.advance(); // consume 'let'
         self.skip_whitespace();
 
@@ -136,11 +235,11 @@ impl<'a> Parser<'a> {
         self.skip_whitespace();
 
         let value = self.parse_expression()?;
-        Ok(Instruction::assign(name, value))
+        Ok(self.add(Instruction::assign(name, value)))
     }
 
     /// Parse: if condition { block } [else { block }]
-    fn parse_if(&mut self) -> Result<Instruction, String> {
+    fn parse_if(&mut self) -> Result<InstrId, String> {
         self.advance(); // consume 'if'
         self.skip_whitespace();
 
@@ -158,11 +257,11 @@ impl<'a> Parser<'a> {
             None
         };
 
-        Ok(Instruction::branch(condition, then_block, else_block))
+        Ok(self.add(Instruction::branch(condition, then_block, else_block)))
     }
 
     /// Parse: while condition { block }
-    fn parse_while(&mut self) -> Result<Instruction, String> {
+    fn parse_while(&mut self) -> Result<InstrId, String> {
         self.advance(); // consume 'while'
         self.skip_whitespace();
 
@@ -171,11 +270,11 @@ impl<'a> Parser<'a> {
 
         let body = self.parse_block()?;
 
-        Ok(Instruction::loop_stmt(condition, body))
+        Ok(self.add(Instruction::loop_stmt(condition, body)))
     }
 
     /// Parse: for var in iterable { block }
-    fn parse_for(&mut self) -> Result<Instruction, String> {
+    fn parse_for(&mut self) -> Result<InstrId, String> {
         self.advance(); // consume 'for'
         self.skip_whitespace();
 
@@ -207,11 +306,11 @@ impl<'a> Parser<'a> {
         // Parse block
         let body = self.parse_block()?;
 
-        Ok(Instruction::for_loop(var, iterable, body))
+        Ok(self.add(Instruction::for_loop(var, iterable, body)))
     }
 
     /// Parse: until condition { block }
-    fn parse_until(&mut self) -> Result<Instruction, String> {
+    fn parse_until(&mut self) -> Result<InstrId, String> {
         self.advance(); // consume 'until'
         self.skip_whitespace();
 
@@ -220,24 +319,37 @@ impl<'a> Parser<'a> {
 
         let body = self.parse_block()?;
 
-        Ok(Instruction::until_loop(condition, body))
+        Ok(self.add(Instruction::until_loop(condition, body)))
     }
 
     /// Parse: return [expr]
-    fn parse_return(&mut self) -> Result<Instruction, String> {
+    fn parse_return(&mut self) -> Result<InstrId, String> {
         self.advance(); // consume 'return'
         self.skip_whitespace();
 
-        if self.peek().lexeme == "\n" || self.is_at_end() || self.peek().lexeme == "}" {
-            Ok(Instruction::return_stmt(None))
+        if self.at_statement_end() {
+            Ok(self.add(Instruction::return_stmt(None)))
         } else {
             let expr = self.parse_expression()?;
-            Ok(Instruction::return_stmt(Some(expr)))
+            Ok(self.add(Instruction::return_stmt(Some(expr))))
+        }
+    }
+
+    /// Parse: exit [status] (the exit builtin with zero or one argument)
+    fn parse_exit(&mut self) -> Result<InstrId, String> {
+        self.advance(); // consume 'exit'
+        self.skip_whitespace();
+
+        if self.at_statement_end() {
+            Ok(self.add(Instruction::invoke("exit".to_string(), Vec::new())))
+        } else {
+            let expr = self.parse_expression()?;
+            Ok(self.add(Instruction::invoke("exit".to_string(), vec![expr])))
         }
     }
 
     /// Parse: fn name(params) { block }
-    fn parse_function_def(&mut self) -> Result<Instruction, String> {
+    fn parse_function_def(&mut self) -> Result<InstrId, String> {
         self.advance(); // consume 'fn'
         self.skip_whitespace();
 
@@ -253,6 +365,9 @@ impl<'a> Parser<'a> {
         // Parse parameters
         let mut params = Vec::new();
         while self.peek().lexeme != ")" {
+            if self.is_at_end() {
+                return Err("Expected ')' after parameters".to_string());
+            }
             params.push(self.parse_identifier()?);
             self.skip_whitespace();
             if self.peek().lexeme == "," {
@@ -266,15 +381,30 @@ impl<'a> Parser<'a> {
 
         let body = self.parse_block()?;
 
-        Ok(Instruction::FunctionDef {
+        // A string literal as the first statement is the docstring, not executed code
+        let mut doc = None;
+        if let Instruction::Sequence(stmts, _) = &self.program[body] {
+            if let Some(Instruction::Literal(Value::String(text))) = stmts.first().map(|first| &self.program[*first]) {
+                doc = Some(text.clone());
+            }
+        }
+        if doc.is_some() {
+            if let Instruction::Sequence(stmts, positions) = &mut self.program[body] {
+                stmts.remove(0);
+                positions.remove(0);
+            }
+        }
+
+        Ok(self.add(Instruction::FunctionDef {
             name,
             params,
-            body: Box::new(body),
-        })
+            body,
+            doc,
+        }))
     }
 
     /// Parse a block: { statements }
-    fn parse_block(&mut self) -> Result<Instruction, String> {
+    fn parse_block(&mut self) -> Result<InstrId, String> {
         if self.peek().lexeme != "{" {
             return Err("Expected '{'".to_string());
         }
@@ -282,13 +412,21 @@ impl<'a> Parser<'a> {
         self.skip_whitespace();
 
         let mut stmts = Vec::new();
+        let mut positions = Vec::new();
         while self.peek().lexeme != "}" && !self.is_at_end() {
-            let stmt = self.parse_statement()?;
-            stmts.push(stmt);
+            let start = self.pos;
+            let position = self.position();
+            match self.parse_statement() {
+                Ok(stmt) => {
+                    stmts.push(stmt);
+                    positions.push(position);
+                }
+                Err(e) => self.recover(e, start),
+            }
             self.skip_whitespace();
 
             // Skip optional semicolon or newline after statement
-            if self.peek().lexeme == ";" || self.peek().lexeme == "\n" {
+            if self.schema.is_terminator(&self.peek().lexeme) {
                 self.advance();
                 self.skip_whitespace();
             }
@@ -299,11 +437,11 @@ impl<'a> Parser<'a> {
         }
         self.advance();
 
-        Ok(Instruction::sequence(stmts))
+        Ok(self.add(Instruction::sequence(stmts, positions)))
     }
 
     /// Parse assignment or expression statement
-    fn parse_assignment_or_expression(&mut self) -> Result<Instruction, String> {
+    fn parse_assignment_or_expression(&mut self) -> Result<InstrId, String> {
         let expr = self.parse_expression()?;
         self.skip_whitespace();
 
@@ -314,20 +452,20 @@ impl<'a> Parser<'a> {
 
             // Handle three cases:
             // 1. MEMOIZATION assignment (system control)
-            if let Instruction::Variable(name) = &expr {
+            if let Instruction::Variable(name) = &self.program[expr] {
                 if name == "MEMOIZATION" {
                     // Extract boolean value from either Variable or Literal
-                    match &value {
+                    match &self.program[value] {
                         Instruction::Variable(bool_str) => {
                             match bool_str.as_str() {
-                                "true" => return Ok(Instruction::SetMemoization { enabled: true }),
-                                "false" => return Ok(Instruction::SetMemoization { enabled: false }),
+                                "true" => return Ok(self.add(Instruction::SetMemoization { enabled: true })),
+                                "false" => return Ok(self.add(Instruction::SetMemoization { enabled: false })),
                                 _ => return Err(format!("MEMOIZATION must be set to 'true' or 'false', got: {}", bool_str)),
                             }
                         }
                         Instruction::Literal(val) => {
-                            if let crate::kernel::eval::Value::Bool(b) = val {
-                                return Ok(Instruction::SetMemoization { enabled: *b });
+                            if let crate::kernel::eval::Value::Bool(b) = *val {
+                                return Ok(self.add(Instruction::SetMemoization { enabled: b }));
                             }
                             return Err("MEMOIZATION must be set to a boolean literal (true or false)".to_string());
                         }
@@ -339,18 +477,19 @@ impl<'a> Parser<'a> {
             }
 
             // 2. Simple assignment: name = value
-            if let Instruction::Variable(name) = expr {
-                return Ok(Instruction::assign(name, value));
+            if let Instruction::Variable(name) = &self.program[expr] {
+                let name = name.clone();
+                return Ok(self.add(Instruction::assign(name, value)));
             }
 
             // 2. Indexed assignment: arr[i] = value
             // Check if expr is an Operate::Binary with "[]" operator
-            if let Instruction::Operate { kind: super::primitives::OperateKind::Binary(op), operands } = expr {
+            if let Instruction::Operate { kind: super::primitives::OperateKind::Binary(op), operands } = &self.program[expr] {
                 if op == "[]" && operands.len() == 2 {
                     // Extract array name from the left operand
-                    if let Instruction::Variable(name) = &operands[0] {
-                        let index = operands[1].clone();
-                        return Ok(Instruction::indexed_assign(name.clone(), index, value));
+                    if let Instruction::Variable(name) = &self.program[operands[0]] {
+                        let (name, index) = (name.clone(), operands[1]);
+                        return Ok(self.add(Instruction::indexed_assign(name, index, value)));
                     }
                 }
             }
@@ -361,177 +500,94 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    /// Parse expression (lowest precedence)
-    fn parse_expression(&mut self) -> Result<Instruction, String> {
-        self.parse_pipe()
-    }
-
-    /// Parse pipe operator (lowest precedence: 0.5)
-    fn parse_pipe(&mut self) -> Result<Instruction, String> {
-        let mut left = self.parse_logical_or()?;
-        self.skip_whitespace();
-
-        while self.peek().lexeme == "|>" {
-            self.advance();
-            self.skip_whitespace();
-            let right = self.parse_logical_or()?;
-            self.skip_whitespace();
-            left = Instruction::binary("|>".to_string(), left, right);
-        }
-
-        Ok(left)
+    /// Parse an expression: operands joined by the operators of the schema
+    fn parse_expression(&mut self) -> Result<InstrId, String> {
+        let left = self.parse_unary()?;
+        self.parse_operators(left, Floor::Any)
     }
 
-    /// Parse logical OR (lowest precedence after assignment)
-    fn parse_logical_or(&mut self) -> Result<Instruction, String> {
-        let mut left = self.parse_logical_and()?;
-        self.skip_whitespace();
-
+    /// Extend `left` with the binary and postfix operators that follow, while `floor`
+    /// admits their precedence (precedence climbing over the schema's operator tables)
+    /// A left-associative operator's right operand takes only tighter operators, a
+    /// right-associative one's its own as well. A lexeme that is both binary and postfix is
+    /// binary. `=` is left to parse_assignment_or_expression: assignment is a statement.
+    fn parse_operators(&mut self, mut left: InstrId, floor: Floor) -> Result<InstrId, String> {
+        let schema = self.schema;
         loop {
-            match self.peek().lexeme.as_str() {
-                "or" | "||" => {
-                    let op = self.peek().lexeme.clone();
-                    self.advance();
-                    self.skip_whitespace();
-                    let right = self.parse_logical_and()?;
-                    self.skip_whitespace();
-                    left = Instruction::binary(op, left, right);
+            self.skip_whitespace();
+            let op = self.peek().lexeme.clone();
+            if let Some(info) = schema.get_binary_operator(&op).filter(|_| op != "=") {
+                if !floor.admits(info.precedence) {
+                    break;
                 }
-                _ => break,
-            }
-        }
-
-        Ok(left)
-    }
-
-    /// Parse logical AND
-    fn parse_logical_and(&mut self) -> Result<Instruction, String> {
-        let mut left = self.parse_comparison()?;
-        self.skip_whitespace();
-
-        loop {
-            match self.peek().lexeme.as_str() {
-                "and" | "&&" => {
-                    let op = self.peek().lexeme.clone();
-                    self.advance();
-                    self.skip_whitespace();
-                    let right = self.parse_comparison()?;
-                    self.skip_whitespace();
-                    left = Instruction::binary(op, left, right);
+                let operand_floor = match info.associativity {
+                    Associativity::Left => Floor::Above(info.precedence),
+                    Associativity::Right => Floor::AtLeast(info.precedence),
+                };
+                self.advance();
+                self.skip_whitespace();
+                let right = self.parse_unary()?;
+                let right = self.parse_operators(right, operand_floor)?;
+                left = self.add(Instruction::binary(op, left, right));
+            } else if let Some(info) = schema.get_unary_operator(&op).filter(|info| info.position == UnaryPosition::Postfix) {
+                if !floor.admits(info.precedence) {
+                    break;
                 }
-                _ => break,
+                self.advance();
+                left = self.add(Instruction::unary(op, left));
+            } else {
+                break;
             }
         }
-
-        Ok(left)
-    }
-
-    /// Parse comparison operators
-    fn parse_comparison(&mut self) -> Result<Instruction, String> {
-        let mut left = self.parse_range()?;
-        self.skip_whitespace();
-
-        loop {
-            let op = match self.peek().lexeme.as_str() {
-                "==" | "!=" | "<" | ">" | "<=" | ">=" => self.peek().lexeme.clone(),
-                _ => break,
-            };
-            self.advance();
-            self.skip_whitespace();
-            let right = self.parse_range()?;
-            self.skip_whitespace();
-            left = Instruction::binary(op, left, right);
-        }
-
         Ok(left)
     }
 
-    /// Parse range operator (..)
-    fn parse_range(&mut self) -> Result<Instruction, String> {
-        let mut left = self.parse_additive()?;
-        self.skip_whitespace();
-
-        while self.peek().lexeme == ".." {
-            self.advance();
-            self.skip_whitespace();
-            let right = self.parse_additive()?;
-            self.skip_whitespace();
-            left = Instruction::binary("..".to_string(), left, right);
-        }
-
-        Ok(left)
-    }
+    /// Parse an operand: a prefix operator of the schema applied to its operand, or a primary
+    /// The operand of a prefix operator takes only the operators that bind tighter than it.
+    fn parse_unary(&mut self) -> Result<InstrId, String> {
+        let schema = self.schema;
+        let op = self.peek().lexeme.clone();
+        let Some(info) = schema.get_unary_operator(&op).filter(|info| info.position == UnaryPosition::Prefix) else {
+            let primary = self.parse_primary()?;
+            self.check_integer_literal(primary, false)?;
+            return Ok(primary);
+        };
 
-    /// Parse additive operators
-    fn parse_additive(&mut self) -> Result<Instruction, String> {
-        let mut left = self.parse_multiplicative()?;
+        self.advance();
         self.skip_whitespace();
-
-        loop {
-            let op = match self.peek().lexeme.as_str() {
-                "+" | "-" => self.peek().lexeme.clone(),
-                _ => break,
-            };
-            self.advance();
-            self.skip_whitespace();
-            let right = self.parse_multiplicative()?;
-            self.skip_whitespace();
-            left = Instruction::binary(op, left, right);
-        }
-
-        Ok(left)
+        let operand = if op == "-" && self.peek().lexeme.starts_with(|c: char| c.is_ascii_digit()) {
+            // As in Rust, -9223372036854775808 is i64::MIN though the literal alone is out of range
+            let literal = self.parse_primary()?;
+            self.check_integer_literal(literal, true)?;
+            literal
+        } else {
+            self.parse_unary()?
+        };
+        let operand = self.parse_operators(operand, Floor::Above(info.precedence))?;
+        Ok(self.add(Instruction::unary(op, operand)))
     }
 
-    /// Parse multiplicative operators
-    fn parse_multiplicative(&mut self) -> Result<Instruction, String> {
-        let mut left = self.parse_exponentiation()?;
-        self.skip_whitespace();
-
-        loop {
-            let op = match self.peek().lexeme.as_str() {
-                "*" | "/" | "%" | "//" | "." => self.peek().lexeme.clone(),
-                _ => break,
-            };
-            self.advance();
-            self.skip_whitespace();
-            let right = self.parse_exponentiation()?;
-            self.skip_whitespace();
-            left = Instruction::binary(op, left, right);
+    /// In a schema with 64-bit integers, reject an integer literal outside i64 (`negated`:
+    /// it follows a unary minus, so it may be one past i64::MAX)
+    fn check_integer_literal(&self, id: InstrId, negated: bool) -> Result<(), String> {
+        if self.schema.integer_overflow == IntegerOverflow::Unbounded {
+            return Ok(());
         }
-
-        Ok(left)
-    }
-
-    /// Parse exponentiation operator (right-associative)
-    fn parse_exponentiation(&mut self) -> Result<Instruction, String> {
-        let left = self.parse_unary()?;
-        self.skip_whitespace();
-
-        if self.peek().lexeme == "**" {
-            self.advance();
-            self.skip_whitespace();
-            let right = self.parse_exponentiation()?; // Right-associative!
-            return Ok(Instruction::binary("**".to_string(), left, right));
+        if let Instruction::Literal(Value::Number(n)) = &self.program[id] {
+            let limit = num_bigint::BigInt::from(i64::MAX) + u8::from(negated);
+            if n > &limit {
+                return Err(format!("literal out of range for i64: {}", n));
+            }
         }
-
-        Ok(left)
-    }
-
-    /// Parse unary operators
-    fn parse_unary(&mut self) -> Result<Instruction, String> {
-        let op = match self.peek().lexeme.as_str() {
-            "-" | "not" | "!" => self.peek().lexeme.clone(),
-            _ => return self.parse_primary(),
-        };
-
-        self.advance();
-        self.skip_whitespace();
-        let operand = self.parse_unary()?;
-        Ok(Instruction::unary(op, operand))
+        Ok(())
     }
 
     /// Parse primary expression
-    fn parse_primary(&mut self) -> Result<Instruction, String> {
+    fn parse_primary(&mut self) -> Result<InstrId, String> {
+        // The end-of-input token reads like a name; an expression cannot start there
+        if self.is_at_end() {
+            return Err("Unexpected end of input".to_string());
+        }
         let lexeme = &self.peek().lexeme.clone();
 
         // Numbers (integer or float or base-N)
@@ -544,10 +600,10 @@ impl<'a> Parser<'a> {
                 // Base-N literals with fractional part are Real
                 if denominator != num_bigint::BigInt::from(1) {
                     let precision = Self::calculate_precision(&num_str);
-                    return Ok(Instruction::literal(Value::Real { numerator, denominator, precision }));
+                    return Ok(self.add(Instruction::literal(Self::real(numerator, denominator, precision))));
                 } else {
                     // Base-N integer literal
-                    return Ok(Instruction::literal(Value::Number(numerator)));
+                    return Ok(self.add(Instruction::literal(Value::Number(numerator))));
                 }
             }
 
@@ -557,39 +613,39 @@ impl<'a> Parser<'a> {
                 // Float literals are Real values (not Rational)
                 // Precision is determined by significant figures
                 let precision = Self::calculate_precision(&num_str);
-                return Ok(Instruction::literal(Value::Real { numerator, denominator, precision }));
+                return Ok(self.add(Instruction::literal(Self::real(numerator, denominator, precision))));
             } else {
                 // Parse as integer
                 let num = num_str
                     .parse::<num_bigint::BigInt>()
                     .map_err(|_| format!("Invalid number: {}", num_str))?;
-                return Ok(Instruction::literal(Value::Number(num)));
+                return Ok(self.add(Instruction::literal(Value::Number(num))));
             }
         }
 
         // Strings - double-quoted
         if lexeme == "\"" {
             let string_val = self.consume_string('"')?;
-            return Ok(Instruction::literal(Value::String(string_val)));
+            return Ok(self.add(Instruction::literal(Value::String(string_val))));
         }
 
         // Strings - single-quoted
         if lexeme == "'" {
             let string_val = self.consume_string('\'')?;
-            return Ok(Instruction::literal(Value::String(string_val)));
+            return Ok(self.add(Instruction::literal(Value::String(string_val))));
         }
 
         // Booleans
         if lexeme == "true" || lexeme == "false" {
             let val = lexeme == "true";
             self.advance();
-            return Ok(Instruction::literal(Value::Bool(val)));
+            return Ok(self.add(Instruction::literal(Value::Bool(val))));
         }
 
         // Null
         if lexeme == "null" {
             self.advance();
-            return Ok(Instruction::literal(Value::Null));
+            return Ok(self.add(Instruction::literal(Value::Null)));
         }
 
         // Array literal
@@ -599,6 +655,9 @@ impl<'a> Parser<'a> {
             let mut elements = Vec::new();
 
             while self.peek().lexeme != "]" {
+                if self.is_at_end() {
+                    return Err("Expected ']'".to_string());
+                }
                 elements.push(self.parse_expression()?);
                 self.skip_whitespace();
                 if self.peek().lexeme == "," {
@@ -613,7 +672,7 @@ impl<'a> Parser<'a> {
             self.advance();
 
             // Return an instruction that constructs an array from the elements
-            return Ok(Instruction::construct_array(elements));
+            return Ok(self.add(Instruction::construct_array(elements)));
         }
 
         // Parenthesized expression
@@ -644,6 +703,9 @@ impl<'a> Parser<'a> {
 
                 let mut args = Vec::new();
                 while self.peek().lexeme != ")" {
+                    if self.is_at_end() {
+                        return Err("Expected ')' after arguments".to_string());
+                    }
                     args.push(self.parse_expression()?);
                     self.skip_whitespace();
                     if self.peek().lexeme == "," {
@@ -653,7 +715,7 @@ impl<'a> Parser<'a> {
                 }
 
                 self.advance(); // consume ')'
-                let mut expr = Instruction::invoke(name, args);
+                let mut expr = self.add(Instruction::invoke(name, args));
 
                 // Handle postfix array indexing on function call results: func()[i]
                 while self.peek().lexeme == "[" {
@@ -667,13 +729,13 @@ impl<'a> Parser<'a> {
                     self.advance(); // consume ']'
                     self.skip_whitespace();
 
-                    expr = Instruction::binary("[]".to_string(), expr, index_expr);
+                    expr = self.add(Instruction::binary("[]".to_string(), expr, index_expr));
                 }
 
                 return Ok(expr);
             }
 
-            let mut expr = Instruction::variable(name);
+            let mut expr = self.add(Instruction::variable(name));
             self.skip_whitespace();
 
             // Handle postfix array indexing: var[i]
@@ -688,7 +750,7 @@ impl<'a> Parser<'a> {
                 self.advance(); // consume ']'
                 self.skip_whitespace();
 
-                expr = Instruction::binary("[]".to_string(), expr, index_expr);
+                expr = self.add(Instruction::binary("[]".to_string(), expr, index_expr));
             }
 
             return Ok(expr);
@@ -713,7 +775,9 @@ impl<'a> Parser<'a> {
         let mut name = self.peek().lexeme.clone();
         self.advance();
 
-        loop {
+        // (the end-of-input token reads like a word, and follows a name on the last line
+        // of a source without a final newline)
+        while !self.is_at_end() {
             let next_lexeme = &self.peek().lexeme;
 
             // Check if next token is single-char alphanumeric/underscore
@@ -769,6 +833,12 @@ impl<'a> Parser<'a> {
         Ok(num_str)
     }
 
+    /// The REAL literal num/den, rounded to its precision (see lib_lumen/bigfloat.rs)
+    fn real(numerator: num_bigint::BigInt, denominator: num_bigint::BigInt, precision: usize) -> Value {
+        let (numerator, denominator) = super::bigfloat::round_real(&numerator, &denominator, precision);
+        Value::Real { numerator, denominator, precision }
+    }
+
     /// Calculate precision (significant figures) from a float literal string
     /// E.g., "1.5" -> 15, "3.14" -> 15, "0.05" -> 15 (minimum 15 significant figures)
     fn calculate_precision(s: &str) -> usize {
@@ -1017,8 +1087,51 @@ impl<'a> Parser<'a> {
     }
 }
 
-/// Parse tokens to instruction tree
-pub fn parse(tokens: Vec<Token>, schema: &LanguageSchema) -> Result<Instruction, String> {
+/// Parse tokens to a program, whose root is the top-level sequence
+/// Statements that do not parse are skipped so the rest can be checked; the error then
+/// lists every one of them.
+pub fn parse(tokens: Vec<Token>, schema: &LanguageSchema) -> Result<Program, Vec<LocatedError>> {
     let mut parser = Parser::new(&tokens, schema);
-    parser.parse_program()
+    match parser.parse_program() {
+        Ok(_) if !parser.errors.is_empty() => Err(parser.errors),
+        Ok(_) => Ok(parser.program),
+        Err(e) => {
+            parser.errors.push(parser.locate(e));
+            Err(parser.errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::{parse_program, LocatedError};
+    use crate::languages::lumen_schema;
+
+    #[test]
+    fn reports_every_statement_that_does_not_parse() {
+        let source = "x = 1 +* 2\nif x\n    y = )\nelse\n    y = 3\nz = 4\nw = ]\n";
+        let errors = parse_program(source, &lumen_schema::get_schema()).unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                LocatedError::at("Unexpected token: *", 1, 8),
+                LocatedError::at("Unexpected token: )", 3, 9),
+                LocatedError::at("Unexpected token: ]", 7, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_name_can_end_the_source() {
+        let schema = lumen_schema::get_schema();
+        assert!(parse_program("x = 1\ny = x", &schema).is_ok());
+        assert!(parse_program("x", &schema).is_ok());
+    }
+
+    #[test]
+    fn an_unfinished_expression_is_an_error() {
+        let schema = lumen_schema::get_schema();
+        assert!(parse_program("x = [1, 2", &schema).unwrap_err()[0].message.ends_with("Expected ']'"));
+        assert!(parse_program("x = ", &schema).unwrap_err()[0].message.contains("end of input"));
+    }
 }
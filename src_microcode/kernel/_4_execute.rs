@@ -2,14 +2,40 @@
 //
 // Apply the 7 primitives with clear, deterministic semantics.
 // No language-specific behavior here - just mechanics.
+//
+// Execution never recurses on the host stack. Work waits on an explicit stack of frames:
+// starting an instruction pushes a frame for what remains to be done with its operands,
+// then the operands themselves; when work finishes, its outcome (a value and how control
+// left it, or an error) is handed to the frame below. A user function call is a Call
+// frame over its body, so the depth of Lumen recursion and of nested expressions is
+// bounded by memory (and --max-depth), not by the native stack.
+
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::Arc;
 
-use super::primitives::{Instruction, TransferKind, OperateKind};
+use super::primitives::{InstrId, Instruction, OperateKind, Position, Program, TransferKind};
 use super::eval::{Value, KindValue};
 use super::env::Environment;
-use crate::schema::LanguageSchema;
+use super::arr_backend;
+use super::bigfloat;
+use super::crypto_backend;
+use super::extern_error::ExternError;
+use super::extern_policy;
+use super::extern_timeout::{self, Stopped};
+use super::env_backend;
+use super::fs_backend;
+use super::io_backend;
+use super::json_backend::{self, Json};
+use super::net_backend;
+use super::spans;
+use super::random_backend;
+use super::sys_backend;
+use super::time_backend;
+use super::transcendental;
+use super::control_flow::{at_function, at_loop, at_statement, check_exit_status, LoopStep, LumenError, Signal};
+use crate::schema::{IntegerOverflow, LanguageSchema};
 use num_bigint::BigInt;
 use num_traits::cast::ToPrimitive;
-use num_traits::Signed;
 use num_integer::gcd;
 
 /// Execution state
@@ -21,66 +47,152 @@ pub enum ControlFlow {
     Continue,
 }
 
-/// Execute instruction tree
+impl ControlFlow {
+    /// Map onto the shared control-flow model (lib_lumen/control_flow.rs)
+    fn signal(self) -> Signal {
+        match self {
+            ControlFlow::Normal => Signal::Normal,
+            ControlFlow::Return => Signal::Return,
+            ControlFlow::Break => Signal::Break,
+            ControlFlow::Continue => Signal::Continue,
+        }
+    }
+}
+
+/// How a piece of work finished: its value and control flow, or an error
+type Outcome = Result<(Value, ControlFlow), LumenError>;
+
+/// What starting or resuming a frame did
+enum Step {
+    /// The work finished; its outcome goes to the frame below
+    Done(Value, ControlFlow),
+    /// More work was pushed on the stack
+    Pushed,
+}
+
+/// Work waiting on the execution stack
+enum Frame<'a> {
+    /// Start executing an instruction
+    Eval(InstrId),
+    /// Statement `index` of a sequence is running
+    Sequence { instrs: &'a [InstrId], positions: &'a [Position], index: usize },
+    /// Leave the scope entered by a Scope instruction
+    PopScope,
+    /// The branch condition is running
+    Branch { then_instr: InstrId, else_instr: Option<InstrId> },
+    /// The value to bind is running (for the Assign instruction `instr`)
+    Assign { instr: InstrId, name: &'a str },
+    /// The value for push(arr, value) is running
+    Push(&'a str),
+    /// Argument `next - 1` is running; `values` holds the ones before it
+    Invoke { function: &'a str, args: &'a [InstrId], next: usize, values: Vec<Value> },
+    /// A user function body is running in its own scope
+    Call { function: &'a str, args: Vec<Value> },
+    /// The operand of a unary operator is running
+    Unary(&'a str),
+    /// The left operand of a binary operator is running
+    BinaryLeft { op: &'a str, right: InstrId },
+    /// The right operand is running
+    BinaryRight { op: &'a str, left: Value },
+    /// The left side of `|>` is running; it becomes the first argument of the call on the right
+    Pipe(InstrId),
+    /// The value of return/break/continue is running
+    Transfer(TransferKind),
+    /// The condition of a while loop is running
+    WhileCondition { condition: InstrId, body: InstrId },
+    /// The body of a while loop is running
+    WhileBody { condition: InstrId, body: InstrId },
+    /// The iterable of the for loop `instr` is running
+    ForIterable { instr: InstrId, var: &'a str, body: InstrId },
+    /// The body of a for loop is running with `var` = current
+    ForBody { instr: InstrId, var: &'a str, body: InstrId, current: BigInt, end: BigInt },
+    /// The body of an until loop is running
+    UntilBody { condition: InstrId, body: InstrId },
+    /// The condition of an until loop is running
+    UntilCondition { condition: InstrId, body: InstrId },
+    /// The index of an indexed assignment is running
+    IndexedIndex { name: &'a str, value: InstrId },
+    /// The value of an indexed assignment is running
+    IndexedValue { name: &'a str, index: Value },
+}
+
+/// Execute a program from its root
 pub fn execute(
-    instr: &Instruction,
+    program: &Program,
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<(Value, ControlFlow), LumenError> {
+    execute_from(program, program.root(), env, schema)
+}
+
+/// Execute the instruction tree rooted at `instr`
+fn execute_from(
+    program: &Program,
+    instr: InstrId,
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<(Value, ControlFlow), LumenError> {
+    let mut stack = vec![Frame::Eval(instr)];
+    let mut outcome: Outcome = Ok((Value::Null, ControlFlow::Normal));
+    while let Some(frame) = stack.pop() {
+        let step = match frame {
+            Frame::Eval(instr) => start(program, instr, &mut stack, env, schema),
+            frame => resume(program, frame, outcome, &mut stack, env, schema),
+        };
+        outcome = match step {
+            Ok(Step::Done(value, flow)) => Ok((value, flow)),
+            // The next frame popped is the pushed work, which does not look at the outcome
+            Ok(Step::Pushed) => Ok((Value::Null, ControlFlow::Normal)),
+            Err(e) => Err(e),
+        };
+    }
+    outcome
+}
+
+/// Push `then` and the instruction that runs before it
+fn push<'a>(stack: &mut Vec<Frame<'a>>, then: Frame<'a>, instr: InstrId) -> Result<Step, LumenError> {
+    stack.push(then);
+    stack.push(Frame::Eval(instr));
+    Ok(Step::Pushed)
+}
+
+/// Start executing an instruction
+fn start<'a>(
+    program: &'a Program,
+    instr: InstrId,
+    stack: &mut Vec<Frame<'a>>,
     env: &mut Environment,
-    _schema: &LanguageSchema,
-) -> Result<(Value, ControlFlow), String> {
-    match instr {
+    schema: &LanguageSchema,
+) -> Result<Step, LumenError> {
+    match &program[instr] {
         // 1. Sequence: execute in order, return last value
-        Instruction::Sequence(instrs) => {
-            let mut result = Value::Null;
-            for inst in instrs {
-                let (val, flow) = execute(inst, env, _schema)?;
-                result = val;
-                if flow != ControlFlow::Normal {
-                    return Ok((result, flow));
-                }
+        Instruction::Sequence(instrs, positions) => match instrs.first() {
+            Some(first) => {
+                spans::statement(positions[0].line, positions[0].col);
+                push(stack, Frame::Sequence { instrs, positions, index: 0 }, *first)
             }
-            Ok((result, ControlFlow::Normal))
-        }
+            None => Ok(Step::Done(Value::Null, ControlFlow::Normal)),
+        },
 
         // 2. Scope: push scope, execute, pop scope
         Instruction::Scope(inst) => {
-            env.push_scope();
-            let result = execute(inst, env, _schema);
-            env.pop_scope();
-            result
+            env.push_scope(env.layout(instr));
+            push(stack, Frame::PopScope, *inst)
         }
 
         // 3. Branch: if condition then else
-        Instruction::Branch {
-            condition,
-            then_instr,
-            else_instr,
-        } => {
-            let (cond_val, flow) = execute(condition, env, _schema)?;
-            if flow != ControlFlow::Normal {
-                return Ok((cond_val, flow));
-            }
-
-            if cond_val.to_bool() {
-                execute(then_instr, env, _schema)
-            } else if let Some(else_inst) = else_instr {
-                execute(else_inst, env, _schema)
-            } else {
-                Ok((Value::Null, ControlFlow::Normal))
-            }
+        Instruction::Branch { condition, then_instr, else_instr } => {
+            let frame = Frame::Branch { then_instr: *then_instr, else_instr: *else_instr };
+            push(stack, frame, *condition)
         }
 
         // 4. Assign: bind name in current scope
         Instruction::Assign { name, value } => {
-            // ARGS is a system-provided immutable semantic value
-            if name == "ARGS" {
-                return Err("Cannot reassign ARGS (system-provided immutable value)".to_string());
+            // ARGS, FEATURES and host constants are system-provided immutable semantic values
+            if env.is_protected(name) {
+                return Err(format!("Cannot reassign {} (system-provided immutable value)", name).into());
             }
-            let (val, flow) = execute(value, env, _schema)?;
-            if flow != ControlFlow::Normal {
-                return Ok((val.clone(), flow));
-            }
-            env.set(name.clone(), val.clone());
-            Ok((val, ControlFlow::Normal))
+            push(stack, Frame::Assign { instr, name }, *value)
         }
 
         // 5. Invoke: call external function
@@ -89,615 +201,62 @@ pub fn execute(
             // First argument should be a Variable (not evaluated), second is the value
             if function == "push" {
                 if args.len() != 2 {
-                    return Err(format!("push() expects 2 arguments, got {}", args.len()));
+                    return Err(format!("push() expects 2 arguments, got {}", args.len()).into());
                 }
 
                 // Extract array variable name from first argument
-                let arr_name = match &args[0] {
-                    Instruction::Variable(name) => name.clone(),
-                    _ => return Err("First argument to push() must be an array variable name".to_string()),
+                return match &program[args[0]] {
+                    // ARGS is a system-provided immutable array: it cannot grow either
+                    Instruction::Variable(name) if env.is_protected(name) => {
+                        Err(format!("Cannot reassign {} (system-provided immutable value)", name).into())
+                    }
+                    Instruction::Variable(name) => push(stack, Frame::Push(name), args[1]),
+                    _ => Err("First argument to push() must be an array variable name".into()),
                 };
-
-                // Evaluate the value to push
-                let (val, flow) = execute(&args[1], env, _schema)?;
-                if flow != ControlFlow::Normal {
-                    return Ok((val, flow));
-                }
-
-                // Push to array
-                env.push_to_array(&arr_name, val.clone())?;
-                return Ok((Value::Null, ControlFlow::Normal));
             }
+            invoke_next(program, function, args, 0, Vec::new(), stack, env, schema)
+        }
 
-            let mut arg_vals = Vec::new();
-            for arg in args {
-                let (val, flow) = execute(arg, env, _schema)?;
-                if flow != ControlFlow::Normal {
-                    return Ok((val, flow));
+        // 6. Operate: apply operator
+        Instruction::Operate { kind, operands } => match kind {
+            OperateKind::Unary(op) => {
+                if operands.len() != 1 {
+                    return Err("Unary operator requires 1 operand".into());
                 }
-                arg_vals.push(val);
+                push(stack, Frame::Unary(op), operands[0])
             }
-
-            // External function dispatch
-            match function.as_str() {
-                "emit" => {
-                    // emit(string) - kernel primitive for output
-                    // Accepts a string only, no implicit conversion
-                    if arg_vals.len() != 1 {
-                        return Err(format!("emit() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::String(s) => {
-                            print!("{}", s);
-                            Ok((Value::Null, ControlFlow::Normal))
-                        }
-                        _ => Err("emit() requires a string argument".to_string()),
-                    }
-                }
-                "real" => {
-                    // real(x, precision): convert to real with specified precision
-                    if arg_vals.len() != 2 {
-                        return Err(format!("real() expects 2 arguments, got {}", arg_vals.len()));
-                    }
-
-                    let precision = match &arg_vals[1] {
-                        Value::Number(n) => {
-                            n.to_u64()
-                                .ok_or_else(|| "Precision must be a positive integer".to_string())? as usize
-                        }
-                        _ => return Err("Precision argument must be an integer".to_string()),
-                    };
-
-                    match &arg_vals[0] {
-                        Value::Number(n) => {
-                            // Integer → Real
-                            Ok((Value::Real {
-                                numerator: n.clone(),
-                                denominator: BigInt::from(1),
-                                precision,
-                            }, ControlFlow::Normal))
-                        }
-                        Value::Rational { numerator, denominator } => {
-                            // Rational → Real
-                            Ok((Value::Real {
-                                numerator: numerator.clone(),
-                                denominator: denominator.clone(),
-                                precision,
-                            }, ControlFlow::Normal))
-                        }
-                        Value::Real { numerator, denominator, .. } => {
-                            // Real → Real (with new precision)
-                            Ok((Value::Real {
-                                numerator: numerator.clone(),
-                                denominator: denominator.clone(),
-                                precision,
-                            }, ControlFlow::Normal))
-                        }
-                        _ => Err("real() requires a number, rational, or real argument".to_string()),
-                    }
-                }
-                "int_to_string" => {
-                    // int_to_string(x): convert integer to string (mechanical primitive)
-                    // Assumes input is INTEGER. No type branching.
-                    if arg_vals.len() != 1 {
-                        return Err(format!("int_to_string() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Number(n) => Ok((Value::String(n.to_string()), ControlFlow::Normal)),
-                        _ => Err("int_to_string() requires an integer argument".to_string()),
-                    }
-                }
-                "real_to_string" => {
-                    // real_to_string(x): convert real to string (mechanical primitive)
-                    // Assumes input is REAL. No type branching.
-                    if arg_vals.len() != 1 {
-                        return Err(format!("real_to_string() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Real { numerator, denominator, precision } => {
-                            // Format real as decimal with precision
-                            let int_part = numerator / denominator;
-                            let remainder = numerator.clone() - (&int_part * denominator);
-                            if remainder == BigInt::from(0) {
-                                Ok((Value::String(int_part.to_string()), ControlFlow::Normal))
-                            } else {
-                                let mut decimal_str = String::new();
-                                let digit_count = int_part.to_string().len();
-                                let target_digits = *precision;
-                                let mut rem = remainder.abs();
-                                let mut frac_digits = if digit_count >= target_digits {
-                                    0
-                                } else {
-                                    target_digits - digit_count
-                                };
-                                let denom = denominator.clone();
-                                while frac_digits > 0 && rem > BigInt::from(0) {
-                                    rem = rem * BigInt::from(10);
-                                    let digit = &rem / &denom;
-                                    decimal_str.push_str(&digit.to_string());
-                                    rem = &rem - (&digit * &denom);
-                                    frac_digits -= 1;
-                                }
-                                Ok((Value::String(format!("{}.{}", int_part, decimal_str)), ControlFlow::Normal))
-                            }
-                        }
-                        _ => Err("real_to_string() requires a real argument".to_string()),
-                    }
-                }
-                "rational_to_string" => {
-                    // rational_to_string(x): convert rational to string (mechanical primitive)
-                    // Assumes input is RATIONAL. No type branching.
-                    if arg_vals.len() != 1 {
-                        return Err(format!("rational_to_string() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Rational { numerator, denominator } => {
-                            let string = if denominator == &BigInt::from(1) {
-                                numerator.to_string()
-                            } else {
-                                format!("{}/{}", numerator, denominator)
-                            };
-                            Ok((Value::String(string), ControlFlow::Normal))
-                        }
-                        _ => Err("rational_to_string() requires a rational argument".to_string()),
-                    }
-                }
-                "bool_to_string" => {
-                    // bool_to_string(x): convert boolean to string (mechanical primitive)
-                    // Assumes input is BOOLEAN. No type branching.
-                    if arg_vals.len() != 1 {
-                        return Err(format!("bool_to_string() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Bool(b) => {
-                            let string = if *b { "true" } else { "false" };
-                            Ok((Value::String(string.to_string()), ControlFlow::Normal))
-                        }
-                        _ => Err("bool_to_string() requires a boolean argument".to_string()),
-                    }
-                }
-                "array_to_string" => {
-                    // array_to_string(x): convert array to string (mechanical primitive)
-                    // Assumes input is ARRAY. No type branching.
-                    if arg_vals.len() != 1 {
-                        return Err(format!("array_to_string() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Array(elements) => {
-                            let elements_str = elements
-                                .iter()
-                                .map(|e| format!("{}", e))
-                                .collect::<Vec<_>>()
-                                .join(", ");
-                            Ok((Value::String(format!("[{}]", elements_str)), ControlFlow::Normal))
-                        }
-                        _ => Err("array_to_string() requires an array argument".to_string()),
-                    }
-                }
-                "null_to_string" => {
-                    // null_to_string(x): convert null to string (mechanical primitive)
-                    // Assumes input is NULL. No type branching.
-                    if arg_vals.len() != 1 {
-                        return Err(format!("null_to_string() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Null => Ok((Value::String("null".to_string()), ControlFlow::Normal)),
-                        _ => Err("null_to_string() requires a null argument".to_string()),
-                    }
-                }
-                "kind_to_string" => {
-                    // kind_to_string(x): convert kind meta-value to string (mechanical primitive)
-                    // Assumes input is KIND. No type branching.
-                    if arg_vals.len() != 1 {
-                        return Err(format!("kind_to_string() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Kind(k) => {
-                            let string = match k {
-                                KindValue::INTEGER => "INTEGER",
-                                KindValue::RATIONAL => "RATIONAL",
-                                KindValue::REAL => "REAL",
-                                KindValue::STRING => "STRING",
-                                KindValue::BOOLEAN => "BOOLEAN",
-                                KindValue::ARRAY => "ARRAY",
-                                KindValue::NULL => "NULL",
-                            };
-                            Ok((Value::String(string.to_string()), ControlFlow::Normal))
-                        }
-                        _ => Err("kind_to_string() requires a kind argument".to_string()),
-                    }
-                }
-                "len" => {
-                    // len(x): return length of string or array
-                    // For strings, counts UTF-8 characters (not bytes)
-                    if arg_vals.len() != 1 {
-                        return Err(format!("len() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::String(s) => {
-                            let len = s.chars().count();
-                            Ok((Value::Number(BigInt::from(len)), ControlFlow::Normal))
-                        }
-                        Value::Array(arr) => {
-                            let len = arr.len();
-                            Ok((Value::Number(BigInt::from(len)), ControlFlow::Normal))
-                        }
-                        _ => Err("len() requires a string or array argument".to_string()),
-                    }
-                }
-                "char_at" => {
-                    // char_at(string, index): return character at index
-                    // Characters are UTF-8 characters (not bytes)
-                    // Errors if index is out of bounds or negative (strict, truth-preserving semantics)
-                    if arg_vals.len() != 2 {
-                        return Err(format!("char_at() expects 2 arguments, got {}", arg_vals.len()));
-                    }
-                    match (&arg_vals[0], &arg_vals[1]) {
-                        (Value::String(s), Value::Number(idx)) => {
-                            // Convert index to usize
-                            match idx.to_usize() {
-                                Some(i) => {
-                                    // Get character at index
-                                    match s.chars().nth(i) {
-                                        Some(ch) => Ok((Value::String(ch.to_string()), ControlFlow::Normal)),
-                                        None => Err("char_at index out of bounds".to_string()), // Out of bounds
-                                    }
-                                }
-                                None => Err("char_at index out of bounds".to_string()), // Negative or too large
-                            }
-                        }
-                        (Value::String(_), _) => Err("char_at() second argument must be an integer".to_string()),
-                        _ => Err("char_at() first argument must be a string".to_string()),
-                    }
-                }
-                "ord" => {
-                    // ord(s): return decimal integer value of first character
-                    // Returns the UTF-8 code point of the first character
-                    if arg_vals.len() != 1 {
-                        return Err(format!("ord() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::String(s) => {
-                            // Check if string is empty
-                            if s.is_empty() {
-                                return Err("ord() requires a non-empty string".to_string());
-                            }
-                            // Get first character and convert to Unicode code point
-                            let first_char = s.chars().next().unwrap();
-                            let code_point = first_char as u32;
-                            Ok((Value::Number(BigInt::from(code_point)), ControlFlow::Normal))
-                        }
-                        _ => Err("ord() requires a string argument".to_string()),
-                    }
-                }
-                "chr" => {
-                    // chr(n): return single-character string for decimal integer
-                    // Returns a string containing the character for the given Unicode code point
-                    if arg_vals.len() != 1 {
-                        return Err(format!("chr() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Number(n) => {
-                            // Convert to u32 for char conversion
-                            let code_point = n.to_u32()
-                                .ok_or_else(|| "chr() argument must be a non-negative integer within valid Unicode range".to_string())?;
-                            // Convert to char (validates Unicode code point)
-                            let character = char::from_u32(code_point)
-                                .ok_or_else(|| format!("chr() argument {} is not a valid Unicode code point", code_point))?;
-                            Ok((Value::String(character.to_string()), ControlFlow::Normal))
-                        }
-                        _ => Err("chr() requires an integer argument".to_string()),
-                    }
-                }
-                "error" => {
-                    // error(message): abort execution with error message
-                    // Kernel primitive for unified error handling
-                    // No I/O is performed - the error is propagated via Result
-                    if arg_vals.len() != 1 {
-                        return Err(format!("error() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::String(s) => {
-                            // Return error to abort execution (no I/O)
-                            Err(s.clone())
-                        }
-                        _ => Err("error() argument must be a string".to_string()),
-                    }
-                }
-                "kind" => {
-                    // kind(x): return kind meta-value representing value category
-                    // Returns one of the predefined kind constants: INTEGER, RATIONAL, REAL, ARRAY, STRING, BOOLEAN, NULL
-                    if arg_vals.len() != 1 {
-                        return Err(format!("kind() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    let kind_val = match &arg_vals[0] {
-                        Value::Number(_) => KindValue::INTEGER,
-                        Value::Rational { .. } => KindValue::RATIONAL,
-                        Value::Real { .. } => KindValue::REAL,
-                        Value::Array(_) => KindValue::ARRAY,
-                        Value::String(_) => KindValue::STRING,
-                        Value::Bool(_) => KindValue::BOOLEAN,
-                        Value::Null => KindValue::NULL,
-                        Value::Kind(_) => KindValue::NULL, // KIND-of-KIND returns NULL as placeholder
-                        _ => return Err("kind(): unknown value type".to_string()),
-                    };
-                    Ok((Value::Kind(kind_val), ControlFlow::Normal))
-                }
-                "num" => {
-                    // num(x): extract numerator from rational
-                    // Valid only for RATIONAL values, returns numerator as INTEGER
-                    if arg_vals.len() != 1 {
-                        return Err(format!("num() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Rational { numerator, .. } => {
-                            Ok((Value::Number(numerator.clone()), ControlFlow::Normal))
-                        }
-                        _ => Err("num() requires a rational argument".to_string()),
-                    }
-                }
-                "den" => {
-                    // den(x): extract denominator from rational
-                    // Valid only for RATIONAL values, returns denominator as INTEGER
-                    if arg_vals.len() != 1 {
-                        return Err(format!("den() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Rational { denominator, .. } => {
-                            Ok((Value::Number(denominator.clone()), ControlFlow::Normal))
-                        }
-                        _ => Err("den() requires a rational argument".to_string()),
-                    }
-                }
-                "int" => {
-                    // int(x): extract integer part from real
-                    // Valid only for REAL values, returns integer part as INTEGER
-                    if arg_vals.len() != 1 {
-                        return Err(format!("int() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Real { numerator, denominator, .. } => {
-                            // Integer part: truncate toward zero (integer division)
-                            let int_part = numerator / denominator;
-                            Ok((Value::Number(int_part), ControlFlow::Normal))
-                        }
-                        _ => Err("int() requires a real argument".to_string()),
-                    }
-                }
-                "frac" => {
-                    // frac(x): extract fractional part from real
-                    // Valid only for REAL values, returns fractional part as REAL
-                    if arg_vals.len() != 1 {
-                        return Err(format!("frac() expects 1 argument, got {}", arg_vals.len()));
-                    }
-                    match &arg_vals[0] {
-                        Value::Real { numerator, denominator, precision } => {
-                            // Fractional part: x - int(x)
-                            // frac(x) = (numerator - (numerator / denominator) * denominator) / denominator
-                            let int_part = numerator / denominator;
-                            let frac_numerator = numerator - (&int_part * denominator);
-                            Ok((Value::Real {
-                                numerator: frac_numerator,
-                                denominator: denominator.clone(),
-                                precision: *precision,
-                            }, ControlFlow::Normal))
-                        }
-                        _ => Err("frac() requires a real argument".to_string()),
-                    }
-                }
-                "extern" => {
-                    // extern(function_name, arg1, arg2, ...)
-                    if arg_vals.is_empty() {
-                        return Err("extern requires at least one argument (function name)".to_string());
-                    }
-                    let func_name = match &arg_vals[0] {
-                        Value::String(s) => s.clone(),
-                        _ => return Err("First argument to extern must be a string (function name)".to_string()),
-                    };
-                    let extern_args = arg_vals[1..].to_vec();
-
-                    // Dispatch to the requested function
-                    match func_name.as_str() {
-                        "print_native" => {
-                            for val in &extern_args {
-                                println!("{}", val);
-                            }
-                            Ok((Value::Null, ControlFlow::Normal))
-                        }
-                        "value_type" => {
-                            // Return the type of the first argument
-                            if extern_args.is_empty() {
-                                return Err("value_type requires an argument".to_string());
-                            }
-                            let type_str = match &extern_args[0] {
-                                Value::Number(_) => "number",
-                                Value::Rational { .. } => "rational",
-                                Value::Real { .. } => "real",
-                                Value::String(_) => "string",
-                                Value::Bool(_) => "bool",
-                                Value::Null => "null",
-                                Value::Range { .. } => "range",
-                                Value::Array(_) => "array",
-                                Value::Function { .. } => "function",
-                                Value::Symbol(_) => "symbol",
-                                Value::Kind(_) => "kind",
-                            };
-                            Ok((Value::String(type_str.to_string()), ControlFlow::Normal))
-                        }
-                        "debug_info" => {
-                            // Print debug info about the value
-                            if extern_args.is_empty() {
-                                return Err("debug_info requires an argument".to_string());
-                            }
-                            println!("[DEBUG] {}", extern_args[0]);
-                            Ok((Value::Null, ControlFlow::Normal))
-                        }
-                        _ => Err(format!("Unknown external function: {}", func_name)),
-                    }
+            OperateKind::Binary(op) => {
+                if operands.len() != 2 {
+                    return Err("Binary operator requires 2 operands".into());
                 }
-                "__construct_array" => {
-                    // Construct an array from the evaluated arguments
-                    Ok((Value::Array(arg_vals), ControlFlow::Normal))
-                }
-                _ => {
-                    // Check if it's a user-defined function
-                    if let Ok(_func_val) = env.get(function) {
-                        // Look up the function metadata
-                        if let Some(metadata) = env.functions.get(function).cloned() {
-                            let params = metadata.params.clone();
-                            let body_instr = metadata.body.clone();
-
-                            // Check parameter count
-                            if params.len() != arg_vals.len() {
-                                return Err(format!(
-                                    "Function {} expects {} arguments, got {}",
-                                    function,
-                                    params.len(),
-                                    arg_vals.len()
-                                ));
-                            }
-
-                            // Check cache if MEMOIZATION is enabled
-                            // (get_cached returns None if MEMOIZATION = false)
-                            if let Some(cached_result) = env.get_cached(function, &arg_vals) {
-                                // Cache hit: return cached result without executing
-                                return Ok((cached_result, ControlFlow::Normal));
-                            }
-
-                            // Execute function (cache miss or MEMOIZATION disabled)
-                            env.push_scope();
-
-                            // Bind parameters
-                            for (param, arg) in params.iter().zip(arg_vals.iter()) {
-                                env.set(param.clone(), arg.clone());
-                            }
-
-                            // Execute function body
-                            let (result, flow) = execute(&body_instr, env, _schema)?;
-
-                            // Pop scope
-                            env.pop_scope();
-
-                            // Cache result if MEMOIZATION is enabled
-                            // (cache_result does nothing if MEMOIZATION = false)
-                            env.cache_result(function, &arg_vals, result.clone());
-
-                            // Handle return value
-                            match flow {
-                                ControlFlow::Return => Ok((result, ControlFlow::Normal)),
-                                ControlFlow::Normal => Ok((result, ControlFlow::Normal)),
-                                _ => Ok((result, flow)),
-                            }
-                        } else {
-                            Err(format!("Function body not found for: {}", function))
-                        }
-                    } else {
-                        Err(format!("Unknown function: {}", function))
-                    }
+                // Special handling for pipe operator
+                if op == "|>" {
+                    return push(stack, Frame::Pipe(operands[1]), operands[0]);
                 }
+                push(stack, Frame::BinaryLeft { op, right: operands[1] }, operands[0])
             }
-        }
-
-        // 6. Operate: apply operator
-        Instruction::Operate { kind, operands } => {
-            execute_operator(kind, operands, env, _schema)
-        }
+        },
 
         // 7. Transfer: control flow (return/break/continue)
-        Instruction::Transfer { kind, value } => {
-            let val = if let Some(v) = value {
-                let (v_val, flow) = execute(v, env, _schema)?;
-                if flow != ControlFlow::Normal {
-                    return Ok((v_val, flow));
-                }
-                v_val
-            } else {
-                Value::Null
-            };
-
-            let flow = match kind {
-                TransferKind::Return => ControlFlow::Return,
-                TransferKind::Break => ControlFlow::Break,
-                TransferKind::Continue => ControlFlow::Continue,
-            };
-
-            Ok((val, flow))
-        }
+        Instruction::Transfer { kind, value } => match value {
+            Some(value) => push(stack, Frame::Transfer(*kind), *value),
+            None => Ok(Step::Done(Value::Null, transfer_flow(*kind))),
+        },
 
         // Loop: while condition { body }
         Instruction::Loop { condition, body } => {
-            loop {
-                let (cond_val, flow) = execute(condition, env, _schema)?;
-                if flow != ControlFlow::Normal {
-                    return Ok((cond_val, flow));
-                }
-
-                if !cond_val.to_bool() {
-                    break;
-                }
-
-                let (result, flow) = execute(body, env, _schema)?;
-                match flow {
-                    ControlFlow::Normal => continue,
-                    ControlFlow::Break => return Ok((result, ControlFlow::Normal)),
-                    ControlFlow::Continue => continue,
-                    ControlFlow::Return => return Ok((result, ControlFlow::Return)),
-                }
-            }
-
-            Ok((Value::Null, ControlFlow::Normal))
+            push(stack, Frame::WhileCondition { condition: *condition, body: *body }, *condition)
         }
 
         // ForLoop: for var in iterable { body }
         Instruction::ForLoop { var, iterable, body } => {
-            let (range_val, flow) = execute(iterable, env, _schema)?;
-            if flow != ControlFlow::Normal {
-                return Ok((range_val, flow));
-            }
-
-            // Expect a range value
-            match range_val {
-                Value::Range { start, end } => {
-                    let mut current = start;
-                    while current < end {
-                        env.set(var.clone(), Value::Number(current.clone()));
-                        let (result, flow) = execute(body, env, _schema)?;
-                        match flow {
-                            ControlFlow::Normal => {},
-                            ControlFlow::Break => return Ok((result, ControlFlow::Normal)),
-                            ControlFlow::Continue => {},
-                            ControlFlow::Return => return Ok((result, ControlFlow::Return)),
-                        }
-                        current += BigInt::from(1);
-                    }
-                    Ok((Value::Null, ControlFlow::Normal))
-                }
-                _ => Err(format!("For loop requires a range, got {}", range_val)),
-            }
+            push(stack, Frame::ForIterable { instr, var, body: *body }, *iterable)
         }
 
         // UntilLoop: until condition { body } (do-until: execute body first, then check condition)
         Instruction::UntilLoop { condition, body } => {
-            loop {
-                let (result, flow) = execute(body, env, _schema)?;
-                match flow {
-                    ControlFlow::Normal => {},
-                    ControlFlow::Break => return Ok((result, ControlFlow::Normal)),
-                    ControlFlow::Continue => {},
-                    ControlFlow::Return => return Ok((result, ControlFlow::Return)),
-                }
-
-                let (cond_val, flow) = execute(condition, env, _schema)?;
-                if flow != ControlFlow::Normal {
-                    return Ok((cond_val, flow));
-                }
-
-                if cond_val.to_bool() {
-                    break;
-                }
-            }
-
-            Ok((Value::Null, ControlFlow::Normal))
+            env.step()?;
+            push(stack, Frame::UntilBody { condition: *condition, body: *body }, *body)
         }
 
         // Function definition: store in environment
@@ -705,9 +264,11 @@ pub fn execute(
             name,
             params,
             body,
+            doc,
         } => {
-            env.set(
-                name.clone(),
+            env.assign(
+                instr,
+                name,
                 Value::Function {
                     params: params.clone(),
                     body_ref: name.clone(),
@@ -717,700 +278,2041 @@ pub fn execute(
             use super::env::FunctionMetadata;
             let metadata = FunctionMetadata {
                 params: params.clone(),
-                body: body.as_ref().clone(),
+                body: *body,
+                doc: doc.clone(),
+                layout: env.layout(instr),
             };
             env.functions.insert(name.clone(), metadata);
 
-            Ok((Value::Null, ControlFlow::Normal))
+            Ok(Step::Done(Value::Null, ControlFlow::Normal))
         }
 
-        // Indexed assignment: arr[index] = value
+        // Indexed assignment: arr[index] = value (index first, then value)
         Instruction::IndexedAssign { name, index, value } => {
-            // Evaluate index
-            let (index_val, flow) = execute(index, env, _schema)?;
-            if flow != ControlFlow::Normal {
-                return Ok((index_val, flow));
-            }
-
-            // Evaluate value
-            let (val, flow) = execute(value, env, _schema)?;
-            if flow != ControlFlow::Normal {
-                return Ok((val, flow));
+            // ARGS is a system-provided immutable array: its elements cannot change either
+            if env.is_protected(name) {
+                return Err(format!("Cannot reassign {} (system-provided immutable value)", name).into());
             }
-
-            // Convert index to usize
-            let idx = match &index_val {
-                Value::Number(n) => {
-                    n.to_usize()
-                        .ok_or_else(|| "Array index out of bounds".to_string())?
-                }
-                _ => return Err("Array index must be a number".to_string()),
-            };
-
-            // Mutate the array
-            env.mutate_array(name, idx, val.clone())?;
-            Ok((val, ControlFlow::Normal))
+            push(stack, Frame::IndexedIndex { name, value: *value }, *index)
         }
 
         // Set MEMOIZATION flag (system control)
         Instruction::SetMemoization { enabled } => {
             env.set_memoization(*enabled);
-            Ok((Value::Null, ControlFlow::Normal))
+            Ok(Step::Done(Value::Null, ControlFlow::Normal))
         }
 
         // Literal: just return the value
-        Instruction::Literal(val) => Ok((val.clone(), ControlFlow::Normal)),
+        Instruction::Literal(val) => Ok(Step::Done(val.clone(), ControlFlow::Normal)),
 
         // Variable: look up in environment
         Instruction::Variable(name) => {
-            let val = env.get(name)?;
-            Ok((val, ControlFlow::Normal))
+            let val = env.lookup(instr, name)?;
+            Ok(Step::Done(val, ControlFlow::Normal))
         }
     }
 }
 
-/// Execute operator
-fn execute_operator(
-    kind: &OperateKind,
-    operands: &[Instruction],
+/// Hand the outcome of the work above `frame` to it
+/// An error or a return/break/continue passes through every frame that does not handle it.
+fn resume<'a>(
+    program: &'a Program,
+    frame: Frame<'a>,
+    outcome: Outcome,
+    stack: &mut Vec<Frame<'a>>,
     env: &mut Environment,
     schema: &LanguageSchema,
-) -> Result<(Value, ControlFlow), String> {
-    match kind {
-        OperateKind::Unary(op) => {
-            if operands.len() != 1 {
-                return Err("Unary operator requires 1 operand".to_string());
-            }
-            let (val, flow) = execute(&operands[0], env, schema)?;
-            if flow != ControlFlow::Normal {
-                return Ok((val, flow));
+) -> Result<Step, LumenError> {
+    // Frames that clean up see errors too
+    match frame {
+        Frame::Sequence { instrs, positions, index } => {
+            let (val, flow) = outcome.map_err(|e| error_at(e, &positions[index]))?;
+            if flow != ControlFlow::Normal || index + 1 == instrs.len() {
+                return Ok(Step::Done(val, flow));
             }
+            spans::statement(positions[index + 1].line, positions[index + 1].col);
+            return push(stack, Frame::Sequence { instrs, positions, index: index + 1 }, instrs[index + 1]);
+        }
+        Frame::PopScope => {
+            env.pop_scope();
+            let (val, flow) = outcome?;
+            return Ok(Step::Done(val, flow));
+        }
+        Frame::Call { function, args } => {
+            env.pop_scope();
+            env.usage.leave_call();
+            let (value, flow) = outcome?;
+            at_function(flow.signal())?;
+            // Cache result if MEMOIZATION is enabled
+            // (cache_result does nothing if MEMOIZATION = false)
+            env.cache_result(function, &args, value.clone());
+            return Ok(Step::Done(value, ControlFlow::Normal));
+        }
+        _ => {}
+    }
 
-            let result = match op.as_str() {
-                "-" => {
-                    match val {
-                        Value::Number(n) => Value::Number(-n),
-                        Value::Rational { numerator, denominator } => {
-                            Value::Rational { numerator: -numerator, denominator }
-                        }
-                        Value::Real { numerator, denominator, precision } => {
-                            Value::Real { numerator: -numerator, denominator, precision }
-                        }
-                        _ => return Err("Cannot negate non-numeric value".to_string()),
-                    }
-                }
-                "not" | "!" => Value::Bool(!val.to_bool()),
-                _ => return Err(format!("Unknown unary operator: {}", op)),
+    let (val, flow) = outcome?;
+    match frame {
+        // Loop bodies decide what break, continue and return mean
+        Frame::WhileBody { condition, body } => {
+            return match at_loop(flow.signal()) {
+                LoopStep::Next => push(stack, Frame::WhileCondition { condition, body }, condition),
+                LoopStep::Exit => Ok(Step::Done(val, ControlFlow::Normal)),
+                LoopStep::Propagate => Ok(Step::Done(val, flow)),
+            };
+        }
+        Frame::ForBody { instr, var, body, current, end } => {
+            return match at_loop(flow.signal()) {
+                LoopStep::Next => for_iteration(instr, var, body, current + 1, end, stack, env),
+                LoopStep::Exit => Ok(Step::Done(val, ControlFlow::Normal)),
+                LoopStep::Propagate => Ok(Step::Done(val, flow)),
             };
-
-            Ok((result, ControlFlow::Normal))
         }
+        Frame::UntilBody { condition, body } => {
+            return match at_loop(flow.signal()) {
+                LoopStep::Next => push(stack, Frame::UntilCondition { condition, body }, condition),
+                LoopStep::Exit => Ok(Step::Done(val, ControlFlow::Normal)),
+                LoopStep::Propagate => Ok(Step::Done(val, flow)),
+            };
+        }
+        _ => {}
+    }
 
-        OperateKind::Binary(op) => {
-            if operands.len() != 2 {
-                return Err("Binary operator requires 2 operands".to_string());
+    // Everything else works on a value
+    if flow != ControlFlow::Normal {
+        return Ok(Step::Done(val, flow));
+    }
+    match frame {
+        Frame::Branch { then_instr, else_instr } => {
+            if val.to_bool() {
+                stack.push(Frame::Eval(then_instr));
+                Ok(Step::Pushed)
+            } else if let Some(else_inst) = else_instr {
+                stack.push(Frame::Eval(else_inst));
+                Ok(Step::Pushed)
+            } else {
+                Ok(Step::Done(Value::Null, ControlFlow::Normal))
             }
-
-            // Special handling for pipe operator
-            if op == "|>" {
-                let (left_val, left_flow) = execute(&operands[0], env, schema)?;
-                if left_flow != ControlFlow::Normal {
-                    return Ok((left_val, left_flow));
+        }
+        Frame::Assign { instr, name } => {
+            env.assign(instr, name, val.clone());
+            Ok(Step::Done(val, ControlFlow::Normal))
+        }
+        Frame::Push(name) => {
+            env.push_to_array(name, val)?;
+            Ok(Step::Done(Value::Null, ControlFlow::Normal))
+        }
+        Frame::Invoke { function, args, next, mut values } => {
+            values.push(val);
+            invoke_next(program, function, args, next, values, stack, env, schema)
+        }
+        Frame::Unary(op) => {
+            let integer = matches!(val, Value::Number(_));
+            let result = apply_unary(op, val)?;
+            // `-` is the only unary operator with an integer result
+            let result = if integer { bounded(schema.integer_overflow, "negate", result)? } else { result };
+            Ok(Step::Done(result, ControlFlow::Normal))
+        }
+        Frame::BinaryLeft { op, right } => {
+            // Short-circuit evaluation for the logical operators the schema marks short_circuit
+            match op {
+                "and" | "&&" if !val.to_bool() && schema.is_short_circuit(op) => Ok(Step::Done(Value::Bool(false), ControlFlow::Normal)),
+                "or" | "||" if val.to_bool() && schema.is_short_circuit(op) => Ok(Step::Done(Value::Bool(true), ControlFlow::Normal)),
+                _ => push(stack, Frame::BinaryRight { op, left: val }, right),
+            }
+        }
+        Frame::BinaryRight { op, left } => {
+            let result = match (&left, &val) {
+                (Value::Number(l), Value::Number(r)) if schema.integer_overflow != IntegerOverflow::Unbounded => {
+                    bounded_binary(schema.integer_overflow, op, l, r)?
                 }
-
-                // Right operand should be a function call with the left value prepended as first arg
-                match &operands[1] {
-                    Instruction::Invoke { function, args } => {
-                        let mut new_args = vec![Instruction::Literal(left_val.clone())];
-                        new_args.extend(args.clone());
-                        let piped_invoke = Instruction::Invoke {
-                            function: function.clone(),
-                            args: new_args,
-                        };
-                        return execute(&piped_invoke, env, schema);
-                    }
-                    _ => {
-                        return Err("Pipe operator requires a function call on the right side".to_string());
+                _ => apply_binary(op, left, val)?,
+            };
+            Ok(Step::Done(result, ControlFlow::Normal))
+        }
+        Frame::Pipe(right) => {
+            // Right operand should be a function call with the left value prepended as first arg
+            match &program[right] {
+                Instruction::Invoke { function, args } if function == "push" => {
+                    // The piped value is not a variable name push() could append to
+                    if args.len() + 1 != 2 {
+                        return Err(format!("push() expects 2 arguments, got {}", args.len() + 1).into());
                     }
+                    Err("First argument to push() must be an array variable name".into())
                 }
+                Instruction::Invoke { function, args } => {
+                    invoke_next(program, function, args, 0, vec![val], stack, env, schema)
+                }
+                _ => Err("Pipe operator requires a function call on the right side".into()),
             }
-
-            let (left, left_flow) = execute(&operands[0], env, schema)?;
-            if left_flow != ControlFlow::Normal {
-                return Ok((left, left_flow));
+        }
+        Frame::Transfer(kind) => Ok(Step::Done(val, transfer_flow(kind))),
+        Frame::WhileCondition { condition, body } => {
+            if !val.to_bool() {
+                return Ok(Step::Done(Value::Null, ControlFlow::Normal));
             }
-
-            // Short-circuit evaluation for logical operators
-            match op.as_str() {
-                "and" | "&&" => {
-                    if !left.to_bool() {
-                        return Ok((Value::Bool(false), ControlFlow::Normal));
-                    }
-                }
-                "or" | "||" => {
-                    if left.to_bool() {
-                        return Ok((Value::Bool(true), ControlFlow::Normal));
-                    }
-                }
-                _ => {}
+            env.step()?;
+            push(stack, Frame::WhileBody { condition, body }, body)
+        }
+        Frame::ForIterable { instr, var, body } => {
+            // Expect a range value
+            match val {
+                Value::Range { start, end } => for_iteration(instr, var, body, start, end, stack, env),
+                _ => Err(format!("For loop requires a range, got {}", val.repr()).into()),
             }
-
-            let (right, right_flow) = execute(&operands[1], env, schema)?;
-            if right_flow != ControlFlow::Normal {
-                return Ok((right, right_flow));
+        }
+        Frame::UntilCondition { condition, body } => {
+            if val.to_bool() {
+                return Ok(Step::Done(Value::Null, ControlFlow::Normal));
             }
-
-            let result = match op.as_str() {
-                "." => {
-                    // Period operator: string concatenation with automatic coercion
-                    // Coerce both operands to strings using str()
-                    let left_str = format!("{}", left);
-                    let right_str = format!("{}", right);
-                    Value::String(format!("{}{}", left_str, right_str))
-                }
-                "+" => {
-                    if let (Value::String(_), _) | (_, Value::String(_)) = (&left, &right) {
-                        Value::String(format!("{}{}", left, right))
-                    } else {
-                        // Check if either operand is real or rational
-                        match (&left, &right) {
-                            // Real + Real = Real
-                            (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                             Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                                // (a/b) + (c/d) = (ad + bc) / bd, preserve left precision
-                                let num = l_num * r_denom + r_num * l_denom;
-                                let denom = l_denom * r_denom;
-                                reduce_real(num, denom, *l_prec)
-                            }
-                            // Real + Rational = Real
-                            (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                             Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                                let num = l_num * r_denom + r_num * l_denom;
-                                let denom = l_denom * r_denom;
-                                reduce_real(num, denom, *l_prec)
-                            }
-                            // Real + Number = Real
-                            (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                             Value::Number(r_num)) => {
-                                let num = l_num + r_num * l_denom;
-                                reduce_real(num, l_denom.clone(), *l_prec)
-                            }
-                            // Rational + Real = Real
-                            (Value::Rational { numerator: l_num, denominator: l_denom },
-                             Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                                let num = l_num * r_denom + r_num * l_denom;
-                                let denom = l_denom * r_denom;
-                                reduce_real(num, denom, *r_prec)
-                            }
-                            // Number + Real = Real
-                            (Value::Number(l_num),
-                             Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                                let num = l_num * r_denom + r_num;
-                                reduce_real(num, r_denom.clone(), *r_prec)
-                            }
-                            (Value::Rational { numerator: l_num, denominator: l_denom },
-                             Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                                // a/b + c/d = (ad + bc) / bd
-                                let num = l_num * r_denom + r_num * l_denom;
-                                let denom = l_denom * r_denom;
-                                reduce_rational(num, denom)
-                            }
-                            (Value::Rational { numerator: l_num, denominator: l_denom },
-                             Value::Number(r_num)) => {
-                                // a/b + c = (a + bc) / b
-                                let num = l_num + r_num * l_denom;
-                                reduce_rational(num, l_denom.clone())
-                            }
-                            (Value::Number(l_num),
-                             Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                                // a + c/d = (ad + c) / d
-                                let num = l_num * r_denom + r_num;
-                                reduce_rational(num, r_denom.clone())
-                            }
-                            _ => Value::Number(left.to_number()? + right.to_number()?)
-                        }
-                    }
+            env.step()?;
+            push(stack, Frame::UntilBody { condition, body }, body)
+        }
+        Frame::IndexedIndex { name, value } => push(stack, Frame::IndexedValue { name, index: val }, value),
+        Frame::IndexedValue { name, index } => {
+            // Convert index to usize
+            let idx = match &index {
+                Value::Number(n) => {
+                    n.to_usize()
+                        .ok_or_else(|| "Array index out of bounds".to_string())?
                 }
-                "-" => {
-                    match (&left, &right) {
-                        // Real - Real = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            // (a/b) - (c/d) = (ad - bc) / bd, preserve left precision
-                            let num = l_num * r_denom - r_num * l_denom;
-                            let denom = l_denom * r_denom;
-                            reduce_real(num, denom, *l_prec)
-                        }
-                        // Real - Rational = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // (a/b) - (c/d) = (ad - bc) / bd
-                            let num = l_num * r_denom - r_num * l_denom;
-                            let denom = l_denom * r_denom;
-                            reduce_real(num, denom, *l_prec)
-                        }
-                        // Real - Number = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Number(r_num)) => {
-                            // (a/b) - c = (a - bc) / b
-                            let num = l_num - r_num * l_denom;
-                            reduce_real(num, l_denom.clone(), *l_prec)
-                        }
-                        // Rational - Real = Real
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            // (a/b) - (c/d) = (ad - bc) / bd, preserve right precision
-                            let num = l_num * r_denom - r_num * l_denom;
-                            let denom = l_denom * r_denom;
-                            reduce_real(num, denom, *r_prec)
-                        }
-                        // Number - Real = Real
-                        (Value::Number(l_num),
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            // a - (c/d) = (ad - c) / d, preserve right precision
-                            let num = l_num * r_denom - r_num;
-                            reduce_real(num, r_denom.clone(), *r_prec)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a/b - c/d = (ad - bc) / bd
-                            let num = l_num * r_denom - r_num * l_denom;
-                            let denom = l_denom * r_denom;
-                            reduce_rational(num, denom)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Number(r_num)) => {
-                            // a/b - c = (a - bc) / b
-                            let num = l_num - r_num * l_denom;
-                            reduce_rational(num, l_denom.clone())
-                        }
-                        (Value::Number(l_num),
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a - c/d = (ad - c) / d
-                            let num = l_num * r_denom - r_num;
-                            reduce_rational(num, r_denom.clone())
+                _ => return Err("Array index must be a number".into()),
+            };
+
+            // Mutate the array
+            env.mutate_array(name, idx, val.clone())?;
+            Ok(Step::Done(val, ControlFlow::Normal))
+        }
+        Frame::Eval(_)
+        | Frame::Sequence { .. }
+        | Frame::PopScope
+        | Frame::Call { .. }
+        | Frame::WhileBody { .. }
+        | Frame::ForBody { .. }
+        | Frame::UntilBody { .. } => unreachable!("handled above"),
+    }
+}
+
+fn transfer_flow(kind: TransferKind) -> ControlFlow {
+    match kind {
+        TransferKind::Return => ControlFlow::Return,
+        TransferKind::Break => ControlFlow::Break,
+        TransferKind::Continue => ControlFlow::Continue,
+    }
+}
+
+/// Run the for-loop body with `var` = current, or finish once current reaches end
+fn for_iteration<'a>(
+    instr: InstrId,
+    var: &'a str,
+    body: InstrId,
+    current: BigInt,
+    end: BigInt,
+    stack: &mut Vec<Frame<'a>>,
+    env: &mut Environment,
+) -> Result<Step, LumenError> {
+    if current >= end {
+        return Ok(Step::Done(Value::Null, ControlFlow::Normal));
+    }
+    env.assign(instr, var, Value::Number(current.clone()));
+    env.step()?;
+    push(stack, Frame::ForBody { instr, var, body, current, end }, body)
+}
+
+/// Run argument `next` of a call, or make the call once every argument has a value
+#[allow(clippy::too_many_arguments)]
+fn invoke_next<'a>(
+    program: &'a Program,
+    function: &'a str,
+    args: &'a [InstrId],
+    next: usize,
+    mut values: Vec<Value>,
+    stack: &mut Vec<Frame<'a>>,
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Step, LumenError> {
+    if let Some(arg) = args.get(next) {
+        return push(stack, Frame::Invoke { function, args, next: next + 1, values }, *arg);
+    }
+
+    // External function dispatch
+    if let Some(value) = apply_builtin(program, function, &mut values, env, schema)? {
+        return Ok(Step::Done(value, ControlFlow::Normal));
+    }
+
+    // Check if it's a user-defined function (looked up by name first: searching the
+    // scopes costs as much as the call depth)
+    if !env.functions.contains_key(function) && env.get(function).is_err() {
+        return Err(format!("Unknown function: {}", function).into());
+    }
+    // Check cache if MEMOIZATION is enabled
+    // (get_cached returns None if MEMOIZATION = false)
+    if let Some(cached_result) = env.get_cached(function, &values) {
+        return Ok(Step::Done(cached_result, ControlFlow::Normal));
+    }
+
+    let metadata = env.functions.get(function)
+        .ok_or_else(|| format!("Function body not found for: {}", function))?;
+    if metadata.params.len() != values.len() {
+        return Err(format!(
+            "Function {} expects {} arguments, got {}",
+            function,
+            metadata.params.len(),
+            values.len()
+        ).into());
+    }
+    let params = metadata.params.clone();
+    let body = metadata.body;
+    let layout = metadata.layout.clone();
+
+    env.enter_call()?;
+    env.push_scope(layout);
+    for (param, arg) in params.into_iter().zip(values.iter()) {
+        env.set(param, arg.clone());
+    }
+    push(stack, Frame::Call { function, args: values }, body)
+}
+
+/// Builtins that reach host capabilities, there only in a language with an extern_syntax
+const EXTERN_BUILTINS: &[&str] = &[
+    "extern", "extern_try", "extern_async", "extern_await", "extern_available", "mock_extern", "push_mocks", "pop_mocks",
+    "cache_extern", "clear_extern_cache", "extern_timeout",
+];
+
+/// Apply a builtin function to its evaluated arguments
+/// None: `function` is not a builtin.
+fn apply_builtin(
+    program: &Program,
+    function: &str,
+    arg_vals: &mut Vec<Value>,
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Option<Value>, LumenError> {
+        // The language's extern call, under whatever name it has
+        let function = match schema.extern_syntax {
+            Some(name) if function == name => "extern",
+            None if EXTERN_BUILTINS.contains(&function) => return Ok(None),
+            _ => function,
+        };
+        match function {
+        "emit" => {
+            // emit(string) - kernel primitive for output
+            // Accepts a string only, no implicit conversion
+            if arg_vals.len() != 1 {
+                return Err(format!("emit() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::String(s) => {
+                    print!("{}", s);
+                    Ok(Some(Value::Null))
+                }
+                _ => Err("emit() requires a string argument".into()),
+            }
+        }
+        "real" => {
+            // real(x, precision): convert to real with specified precision
+            if arg_vals.len() != 2 {
+                return Err(format!("real() expects 2 arguments, got {}", arg_vals.len()).into());
+            }
+
+            let precision = match &arg_vals[1] {
+                Value::Number(n) => {
+                    n.to_u64()
+                        .ok_or_else(|| "Precision must be a positive integer".to_string())? as usize
+                }
+                _ => return Err("Precision argument must be an integer".into()),
+            };
+
+            match &arg_vals[0] {
+                // Integer → Real
+                Value::Number(n) => Ok(Some(reduce_real(n.clone(), BigInt::from(1), precision))),
+                // Rational → Real, or Real → Real with the new precision
+                Value::Rational { numerator, denominator } | Value::Real { numerator, denominator, .. } => {
+                    Ok(Some(reduce_real(numerator.clone(), denominator.clone(), precision)))
+                }
+                _ => Err("real() requires a number, rational, or real argument".into()),
+            }
+        }
+        "int_to_string" => {
+            // int_to_string(x): convert integer to string (mechanical primitive)
+            // Assumes input is INTEGER. No type branching.
+            if arg_vals.len() != 1 {
+                return Err(format!("int_to_string() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Number(n) => Ok(Some(Value::String(n.to_string()))),
+                _ => Err("int_to_string() requires an integer argument".into()),
+            }
+        }
+        "real_to_string" => {
+            // real_to_string(x): convert real to string (mechanical primitive)
+            // Assumes input is REAL. No type branching.
+            if arg_vals.len() != 1 {
+                return Err(format!("real_to_string() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                // Decimal notation, rounded to the precision (same as Display)
+                real @ Value::Real { .. } => Ok(Some(Value::String(real.to_string()))),
+                _ => Err("real_to_string() requires a real argument".into()),
+            }
+        }
+        "format_number" => {
+            // format_number(x, digits, grouping): x with exactly `digits` decimal places,
+            // its digits in space-separated groups of `grouping` (0 for none)
+            if arg_vals.len() != 3 {
+                return Err(format!("format_number() expects 3 arguments, got {}", arg_vals.len()).into());
+            }
+            let (num, den) = numeric_operand(&arg_vals[0], function)?;
+            let count = |value: &Value, what: &str| {
+                let count = if let Value::Number(n) = value { n.to_usize() } else { None };
+                count.ok_or_else(|| format!("format_number() {} must be a non-negative integer", what))
+            };
+            let text = bigfloat::fixed_string(&num, &den, count(&arg_vals[1], "digits")?, count(&arg_vals[2], "grouping")?);
+            Ok(Some(Value::String(text)))
+        }
+        "rational_to_string" => {
+            // rational_to_string(x): convert rational to string (mechanical primitive)
+            // Assumes input is RATIONAL. No type branching.
+            if arg_vals.len() != 1 {
+                return Err(format!("rational_to_string() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Rational { numerator, denominator } => {
+                    let string = if denominator == &BigInt::from(1) {
+                        numerator.to_string()
+                    } else {
+                        format!("{}/{}", numerator, denominator)
+                    };
+                    Ok(Some(Value::String(string)))
+                }
+                _ => Err("rational_to_string() requires a rational argument".into()),
+            }
+        }
+        "bool_to_string" => {
+            // bool_to_string(x): convert boolean to string (mechanical primitive)
+            // Assumes input is BOOLEAN. No type branching.
+            if arg_vals.len() != 1 {
+                return Err(format!("bool_to_string() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Bool(b) => {
+                    let string = if *b { "true" } else { "false" };
+                    Ok(Some(Value::String(string.to_string())))
+                }
+                _ => Err("bool_to_string() requires a boolean argument".into()),
+            }
+        }
+        "array_to_string" => {
+            // array_to_string(x): convert array to string (mechanical primitive)
+            // Assumes input is ARRAY. No type branching.
+            if arg_vals.len() != 1 {
+                return Err(format!("array_to_string() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                array @ Value::Array(_) => Ok(Some(Value::String(array.to_string()))),
+                _ => Err("array_to_string() requires an array argument".into()),
+            }
+        }
+        "map_to_string" | "keys" => Ok(Some(builtin_map(function, arg_vals)?)),
+        "bytes" | "bytes_to_string" | "decode_utf8" => Ok(Some(builtin_bytes(function, arg_vals)?)),
+        "null_to_string" => {
+            // null_to_string(x): convert null to string (mechanical primitive)
+            // Assumes input is NULL. No type branching.
+            if arg_vals.len() != 1 {
+                return Err(format!("null_to_string() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Null => Ok(Some(Value::String("null".to_string()))),
+                _ => Err("null_to_string() requires a null argument".into()),
+            }
+        }
+        "kind_to_string" => {
+            // kind_to_string(x): convert kind meta-value to string (mechanical primitive)
+            // Assumes input is KIND. No type branching.
+            if arg_vals.len() != 1 {
+                return Err(format!("kind_to_string() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Kind(k) => {
+                    let string = match k {
+                        KindValue::INTEGER => "INTEGER",
+                        KindValue::RATIONAL => "RATIONAL",
+                        KindValue::REAL => "REAL",
+                        KindValue::STRING => "STRING",
+                        KindValue::BOOLEAN => "BOOLEAN",
+                        KindValue::ARRAY => "ARRAY",
+                        KindValue::MAP => "MAP",
+                        KindValue::NULL => "NULL",
+                        KindValue::HANDLE => "HANDLE",
+                        KindValue::BYTES => "BYTES",
+                    };
+                    Ok(Some(Value::String(string.to_string())))
+                }
+                _ => Err("kind_to_string() requires a kind argument".into()),
+            }
+        }
+        "repr" => {
+            // repr(x): x as debug_info and error messages show it (see lib_lumen/repr.rs)
+            if arg_vals.len() != 1 {
+                return Err(format!("repr() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            Ok(Some(Value::String(arg_vals[0].repr())))
+        }
+        "len" => {
+            // len(x): return length of string, array, map, or bytes
+            // For strings, counts UTF-8 characters (not bytes)
+            if arg_vals.len() != 1 {
+                return Err(format!("len() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::String(s) => {
+                    let len = s.chars().count();
+                    Ok(Some(Value::Number(BigInt::from(len))))
+                }
+                Value::Array(arr) => {
+                    let len = arr.len();
+                    Ok(Some(Value::Number(BigInt::from(len))))
+                }
+                Value::Map(entries) => {
+                    Ok(Some(Value::Number(BigInt::from(entries.len()))))
+                }
+                Value::Bytes(bytes) => Ok(Some(Value::Number(BigInt::from(bytes.len())))),
+                _ => Err("len() requires a string, array, map, or bytes argument".into()),
+            }
+        }
+        "char_at" => {
+            // char_at(string, index): return character at index
+            // Characters are UTF-8 characters (not bytes)
+            // Errors if index is out of bounds or negative (strict, truth-preserving semantics)
+            if arg_vals.len() != 2 {
+                return Err(format!("char_at() expects 2 arguments, got {}", arg_vals.len()).into());
+            }
+            match (&arg_vals[0], &arg_vals[1]) {
+                (Value::String(s), Value::Number(idx)) => {
+                    // Convert index to usize
+                    match idx.to_usize() {
+                        Some(i) => {
+                            // Get character at index
+                            match s.chars().nth(i) {
+                                Some(ch) => Ok(Some(Value::String(ch.to_string()))),
+                                None => Err("char_at index out of bounds".into()), // Out of bounds
+                            }
                         }
-                        _ => Value::Number(left.to_number()? - right.to_number()?)
+                        None => Err("char_at index out of bounds".into()), // Negative or too large
                     }
                 }
-                "*" => {
-                    match (&left, &right) {
-                        // Real * Real = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            // (a/b) * (c/d) = (ac) / (bd), preserve left precision
-                            let num = l_num * r_num;
-                            let denom = l_denom * r_denom;
-                            reduce_real(num, denom, *l_prec)
-                        }
-                        // Real * Rational = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            let num = l_num * r_num;
-                            let denom = l_denom * r_denom;
-                            reduce_real(num, denom, *l_prec)
-                        }
-                        // Real * Number = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Number(r_num)) => {
-                            let num = l_num * r_num;
-                            reduce_real(num, l_denom.clone(), *l_prec)
-                        }
-                        // Rational * Real = Real
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            let num = l_num * r_num;
-                            let denom = l_denom * r_denom;
-                            reduce_real(num, denom, *r_prec)
-                        }
-                        // Number * Real = Real
-                        (Value::Number(l_num),
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            let num = l_num * r_num;
-                            reduce_real(num, r_denom.clone(), *r_prec)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a/b * c/d = (ac) / (bd)
-                            let num = l_num * r_num;
-                            let denom = l_denom * r_denom;
-                            reduce_rational(num, denom)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Number(r_num)) => {
-                            // a/b * c = (ac) / b
-                            let num = l_num * r_num;
-                            reduce_rational(num, l_denom.clone())
-                        }
-                        (Value::Number(l_num),
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a * c/d = (ac) / d
-                            let num = l_num * r_num;
-                            reduce_rational(num, r_denom.clone())
-                        }
-                        _ => Value::Number(left.to_number()? * right.to_number()?)
+                (Value::String(_), _) => Err("char_at() second argument must be an integer".into()),
+                _ => Err("char_at() first argument must be a string".into()),
+            }
+        }
+        "ord" => {
+            // ord(s): return decimal integer value of first character
+            // Returns the UTF-8 code point of the first character
+            if arg_vals.len() != 1 {
+                return Err(format!("ord() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::String(s) => {
+                    // Check if string is empty
+                    if s.is_empty() {
+                        return Err("ord() requires a non-empty string".into());
                     }
+                    // Get first character and convert to Unicode code point
+                    let first_char = s.chars().next().unwrap();
+                    let code_point = first_char as u32;
+                    Ok(Some(Value::Number(BigInt::from(code_point))))
                 }
-                "/" => {
-                    match (&left, &right) {
-                        // Real / Real = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            // (a/b) / (c/d) = (ad) / (bc), preserve left precision
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let num = l_num * r_denom;
-                            let denom = l_denom * r_num;
-                            reduce_real(num, denom, *l_prec)
-                        }
-                        // Real / Rational = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let num = l_num * r_denom;
-                            let denom = l_denom * r_num;
-                            reduce_real(num, denom, *l_prec)
-                        }
-                        // Real / Number = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
-                         Value::Number(r_num)) => {
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let denom = l_denom * r_num;
-                            reduce_real(l_num.clone(), denom, *l_prec)
-                        }
-                        // Rational / Real = Real
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let num = l_num * r_denom;
-                            let denom = l_denom * r_num;
-                            reduce_real(num, denom, *r_prec)
-                        }
-                        // Number / Real = Real
-                        (Value::Number(l_num),
-                         Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let num = l_num * r_denom;
-                            reduce_real(num, r_num.clone(), *r_prec)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a/b ÷ c/d = (ad) / (bc)
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let num = l_num * r_denom;
-                            let denom = l_denom * r_num;
-                            reduce_rational(num, denom)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Number(r_num)) => {
-                            // a/b ÷ c = a / (bc)
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let denom = l_denom * r_num;
-                            reduce_rational(l_num.clone(), denom)
-                        }
-                        (Value::Number(l_num),
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a ÷ c/d = (ad) / c
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let num = l_num * r_denom;
-                            reduce_rational(num, r_num.clone())
-                        }
-                        (Value::Number(l_num), Value::Number(r_num)) => {
-                            // a ÷ b = a/b (produces rational)
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            reduce_rational(l_num.clone(), r_num.clone())
-                        }
-                        _ => return Err("Division requires numeric operands".to_string())
+                _ => Err("ord() requires a string argument".into()),
+            }
+        }
+        "chr" => {
+            // chr(n): return single-character string for decimal integer
+            // Returns a string containing the character for the given Unicode code point
+            if arg_vals.len() != 1 {
+                return Err(format!("chr() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Number(n) => {
+                    // Convert to u32 for char conversion
+                    let code_point = n.to_u32()
+                        .ok_or_else(|| "chr() argument must be a non-negative integer within valid Unicode range".to_string())?;
+                    // Convert to char (validates Unicode code point)
+                    let character = char::from_u32(code_point)
+                        .ok_or_else(|| format!("chr() argument {} is not a valid Unicode code point", code_point))?;
+                    Ok(Some(Value::String(character.to_string())))
+                }
+                _ => Err("chr() requires an integer argument".into()),
+            }
+        }
+        "exit" => builtin_exit(arg_vals).map(Some),
+        "error" => {
+            // error(message): abort execution with error message
+            // Kernel primitive for unified error handling
+            // No I/O is performed - the error is propagated via Result
+            if arg_vals.len() != 1 {
+                return Err(format!("error() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::String(s) => {
+                    // Return error to abort execution (no I/O)
+                    Err(s.clone().into())
+                }
+                _ => Err("error() argument must be a string".into()),
+            }
+        }
+        "kind" => {
+            // kind(x): return kind meta-value representing value category
+            // Returns one of the predefined kind constants: INTEGER, RATIONAL, REAL, ARRAY, MAP, STRING, BOOLEAN, NULL, HANDLE, BYTES
+            if arg_vals.len() != 1 {
+                return Err(format!("kind() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            let kind_val = match &arg_vals[0] {
+                Value::Number(_) => KindValue::INTEGER,
+                Value::Rational { .. } => KindValue::RATIONAL,
+                Value::Real { .. } => KindValue::REAL,
+                Value::Array(_) => KindValue::ARRAY,
+                Value::Map(_) => KindValue::MAP,
+                Value::String(_) => KindValue::STRING,
+                Value::Bool(_) => KindValue::BOOLEAN,
+                Value::Null => KindValue::NULL,
+                Value::Symbol(_) => KindValue::HANDLE,
+                Value::Bytes(_) => KindValue::BYTES,
+                Value::Kind(_) => KindValue::NULL, // KIND-of-KIND returns NULL as placeholder
+                _ => return Err("kind(): unknown value type".into()),
+            };
+            Ok(Some(Value::Kind(kind_val)))
+        }
+        "num" => {
+            // num(x): extract numerator from rational
+            // Valid only for RATIONAL values, returns numerator as INTEGER
+            if arg_vals.len() != 1 {
+                return Err(format!("num() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Rational { numerator, .. } => {
+                    Ok(Some(Value::Number(numerator.clone())))
+                }
+                _ => Err("num() requires a rational argument".into()),
+            }
+        }
+        "den" => {
+            // den(x): extract denominator from rational
+            // Valid only for RATIONAL values, returns denominator as INTEGER
+            if arg_vals.len() != 1 {
+                return Err(format!("den() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Rational { denominator, .. } => {
+                    Ok(Some(Value::Number(denominator.clone())))
+                }
+                _ => Err("den() requires a rational argument".into()),
+            }
+        }
+        "int" => {
+            // int(x): extract integer part from real
+            // Valid only for REAL values, returns integer part as INTEGER
+            if arg_vals.len() != 1 {
+                return Err(format!("int() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Real { numerator, denominator, .. } => {
+                    // Integer part: truncate toward zero (integer division)
+                    let int_part = bigfloat::integer_part(numerator, denominator)?;
+                    Ok(Some(Value::Number(int_part)))
+                }
+                _ => Err("int() requires a real argument".into()),
+            }
+        }
+        "frac" => {
+            // frac(x): extract fractional part from real
+            // Valid only for REAL values, returns fractional part as REAL
+            if arg_vals.len() != 1 {
+                return Err(format!("frac() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Real { numerator, denominator, precision } => {
+                    // Fractional part: x - int(x)
+                    // frac(x) = (numerator - (numerator / denominator) * denominator) / denominator
+                    let int_part = bigfloat::integer_part(numerator, denominator)?;
+                    let frac_numerator = numerator - (&int_part * denominator);
+                    Ok(Some(Value::Real {
+                        numerator: frac_numerator,
+                        denominator: denominator.clone(),
+                        precision: *precision,
+                    }))
+                }
+                _ => Err("frac() requires a real argument".into()),
+            }
+        }
+        "exp" | "ln" | "atan" | "log" => {
+            // exp(x, p), ln(x, p), atan(x, p), log(x, base, p): transcendental functions
+            // Precision p defaults to 15 significant digits when omitted
+            Ok(Some(builtin_transcendental(function, arg_vals)?))
+        }
+        "extern" => {
+            // extern(function_name, arg1, arg2, ...)
+            Ok(Some(cached_extern(program, arg_vals, env, schema)?))
+        }
+        "doc" => {
+            // doc(f): docstring of a user-defined function, or null if it has none
+            if arg_vals.len() != 1 {
+                return Err(format!("doc() expects 1 argument, got {}", arg_vals.len()).into());
+            }
+            match &arg_vals[0] {
+                Value::Function { body_ref, .. } => {
+                    let doc = env.functions.get(body_ref).and_then(|f| f.doc.clone());
+                    Ok(Some(doc.map(Value::String).unwrap_or(Value::Null)))
+                }
+                _ => Err("doc() requires a function argument".into()),
+            }
+        }
+        "extern_async" => {
+            // extern_async(selector, arg1, ...): start an extern call, a HANDLE for extern_await
+            Ok(Some(builtin_extern_async(program, arg_vals, env, schema)?))
+        }
+        "extern_try" => {
+            // extern_try(selector, arg1, ...): the call's value or error as a MAP
+            Ok(Some(builtin_extern_try(program, arg_vals, env, schema)?))
+        }
+        "extern_await" => {
+            // extern_await(call): the value (or error) of an extern_async call
+            match arg_vals.as_slice() {
+                [Value::Symbol(handle)] => Ok(Some(env.await_extern_call(handle)?)),
+                _ => Err("extern_await() requires the HANDLE extern_async returned".into()),
+            }
+        }
+        "extern_available" => {
+            // extern_available(selector): true if extern(selector, ...) would resolve
+            Ok(Some(builtin_extern_available(arg_vals, env)?))
+        }
+        "mock_extern" | "push_mocks" | "pop_mocks" => {
+            // mock_extern(selector, f), push_mocks(), pop_mocks(): extern mocks for tests
+            Ok(Some(builtin_mock(function, arg_vals, env)?))
+        }
+        "cache_extern" | "clear_extern_cache" => {
+            // cache_extern(selector), clear_extern_cache([selector]): extern results kept for the run
+            Ok(Some(builtin_extern_cache(function, arg_vals, env)?))
+        }
+        "extern_timeout" => {
+            // extern_timeout(target, ms): how long the program's extern calls of target may take
+            Ok(Some(builtin_extern_timeout(arg_vals, env)?))
+        }
+        "memo_stats" | "memo_clear" | "memo_capacity" => {
+            // memo_stats([name]), memo_clear([name]), memo_capacity(n): the MEMOIZATION cache
+            Ok(Some(builtin_memo(function, arg_vals, env)?))
+        }
+        "__construct_array" => {
+            // Construct an array from the evaluated arguments
+            Ok(Some(Value::Array(std::mem::take(arg_vals))))
+        }
+        // Not a builtin: a user-defined function, which the caller runs
+        _ => Ok(None),
+    }
+}
+
+/// Apply a unary operator to its evaluated operand
+pub(super) fn apply_unary(op: &str, val: Value) -> Result<Value, String> {
+        let result = match op {
+        "-" => {
+            match val {
+                Value::Number(n) => Value::Number(-n),
+                Value::Rational { numerator, denominator } => {
+                    Value::Rational { numerator: -numerator, denominator }
+                }
+                Value::Real { numerator, denominator, precision } => {
+                    Value::Real { numerator: -numerator, denominator, precision }
+                }
+                _ => return Err("Cannot negate non-numeric value".to_string()),
+            }
+        }
+        "not" | "!" => Value::Bool(!val.to_bool()),
+        _ => return Err(format!("Unknown unary operator: {}", op)),
+    };
+
+    Ok(result)
+}
+
+/// An operator on two integers in a schema with 64-bit integers
+fn bounded_binary(overflow: IntegerOverflow, op: &str, left: &BigInt, right: &BigInt) -> Result<Value, String> {
+    // Powers far past 64 bits are not computed, only what they overflow to
+    if op == "**" && right > &BigInt::from(64) && (left > &BigInt::from(1) || left < &BigInt::from(-1)) {
+        let negative = left < &BigInt::from(0) && right % 2 != BigInt::from(0);
+        return match overflow {
+            IntegerOverflow::Wrapping => Ok(Value::Number(wrapped(&left.modpow(right, &(BigInt::from(1) << 64))))),
+            IntegerOverflow::Saturating => Ok(Value::Number(BigInt::from(if negative { i64::MIN } else { i64::MAX }))),
+            _ => Err(overflow_error(op)),
+        };
+    }
+    // Rust's integer `/` truncates toward zero (as `//` does), and `%` takes the dividend's sign
+    let zero = BigInt::from(0);
+    match op {
+        "/" if right == &zero => return Err("attempt to divide by zero".to_string()),
+        "%" if right == &zero => return Err("attempt to calculate the remainder with a divisor of zero".to_string()),
+        // i64::MIN % -1 overflows in Rust though the remainder, 0, fits
+        "%" if overflow == IntegerOverflow::Checked && left == &BigInt::from(i64::MIN) && right == &BigInt::from(-1) => {
+            return Err(overflow_error(op))
+        }
+        _ => {}
+    }
+    let op = if op == "/" { "//" } else { op };
+    let result = apply_binary(op, Value::Number(left.clone()), Value::Number(right.clone()))?;
+    bounded(overflow, op, result)
+}
+
+/// An integer result kept to i64 by the schema's rule (other values are left as they are)
+fn bounded(overflow: IntegerOverflow, op: &str, value: Value) -> Result<Value, String> {
+    let Value::Number(n) = &value else {
+        return Ok(value);
+    };
+    if n.to_i64().is_some() {
+        return Ok(value);
+    }
+    match overflow {
+        IntegerOverflow::Unbounded => Ok(value),
+        IntegerOverflow::Checked => Err(overflow_error(op)),
+        IntegerOverflow::Wrapping => Ok(Value::Number(wrapped(n))),
+        IntegerOverflow::Saturating => Ok(Value::Number(BigInt::from(if n < &BigInt::from(0) { i64::MIN } else { i64::MAX }))),
+    }
+}
+
+/// The low 64 bits of `n` as an i64 (two's complement, as Rust's wrapping operations)
+fn wrapped(n: &BigInt) -> BigInt {
+    let bytes = n.to_signed_bytes_le();
+    let fill = if n < &BigInt::from(0) { 0xff } else { 0 };
+    let mut low = [fill; 8];
+    let len = bytes.len().min(8);
+    low[..len].copy_from_slice(&bytes[..len]);
+    BigInt::from(i64::from_le_bytes(low))
+}
+
+/// Rust's message for an operator (or "negate") that overflowed
+fn overflow_error(op: &str) -> String {
+    let operation = match op {
+        "+" => "add",
+        "-" => "subtract",
+        "*" | "**" => "multiply",
+        "//" => "divide",
+        "%" => "calculate the remainder",
+        other => other,
+    };
+    format!("attempt to {} with overflow", operation)
+}
+
+/// Apply a binary operator to its evaluated operands
+pub(super) fn apply_binary(op: &str, left: Value, right: Value) -> Result<Value, String> {
+    if let Some(result) = special_real(op, &left, &right) {
+        return Ok(result);
+    }
+        let result = match op {
+        "." => {
+            // Period operator: string concatenation with automatic coercion
+            // Coerce both operands to strings using str()
+            let left_str = format!("{}", left);
+            let right_str = format!("{}", right);
+            Value::String(format!("{}{}", left_str, right_str))
+        }
+        "+" => {
+            if let (Value::String(_), _) | (_, Value::String(_)) = (&left, &right) {
+                Value::String(format!("{}{}", left, right))
+            } else {
+                // Check if either operand is real or rational
+                match (&left, &right) {
+                    // Real + Real = Real
+                    (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                     Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                        // (a/b) + (c/d) = (ad + bc) / bd, preserve left precision
+                        let num = l_num * r_denom + r_num * l_denom;
+                        let denom = l_denom * r_denom;
+                        reduce_real(num, denom, *l_prec)
+                    }
+                    // Real + Rational = Real
+                    (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                     Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                        let num = l_num * r_denom + r_num * l_denom;
+                        let denom = l_denom * r_denom;
+                        reduce_real(num, denom, *l_prec)
+                    }
+                    // Real + Number = Real
+                    (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                     Value::Number(r_num)) => {
+                        let num = l_num + r_num * l_denom;
+                        reduce_real(num, l_denom.clone(), *l_prec)
+                    }
+                    // Rational + Real = Real
+                    (Value::Rational { numerator: l_num, denominator: l_denom },
+                     Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                        let num = l_num * r_denom + r_num * l_denom;
+                        let denom = l_denom * r_denom;
+                        reduce_real(num, denom, *r_prec)
+                    }
+                    // Number + Real = Real
+                    (Value::Number(l_num),
+                     Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                        let num = l_num * r_denom + r_num;
+                        reduce_real(num, r_denom.clone(), *r_prec)
+                    }
+                    (Value::Rational { numerator: l_num, denominator: l_denom },
+                     Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                        // a/b + c/d = (ad + bc) / bd
+                        let num = l_num * r_denom + r_num * l_denom;
+                        let denom = l_denom * r_denom;
+                        reduce_rational(num, denom)
+                    }
+                    (Value::Rational { numerator: l_num, denominator: l_denom },
+                     Value::Number(r_num)) => {
+                        // a/b + c = (a + bc) / b
+                        let num = l_num + r_num * l_denom;
+                        reduce_rational(num, l_denom.clone())
                     }
+                    (Value::Number(l_num),
+                     Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                        // a + c/d = (ad + c) / d
+                        let num = l_num * r_denom + r_num;
+                        reduce_rational(num, r_denom.clone())
+                    }
+                    _ => Value::Number(left.to_number()? + right.to_number()?)
                 }
-                "%" => {
-                    // For modulo, extract integer parts from rationals
-                    let l_int = match &left {
-                        Value::Number(n) => n.clone(),
-                        Value::Rational { numerator, denominator } => numerator / denominator,
-                        _ => return Err("Modulo requires numeric operands".to_string()),
-                    };
-                    let r_int = match &right {
-                        Value::Number(n) => n.clone(),
-                        Value::Rational { numerator, denominator } => numerator / denominator,
-                        _ => return Err("Modulo requires numeric operands".to_string()),
-                    };
-                    if r_int == BigInt::from(0) {
-                        return Err("Modulo by zero".to_string());
+            }
+        }
+        "-" => {
+            match (&left, &right) {
+                // Real - Real = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    // (a/b) - (c/d) = (ad - bc) / bd, preserve left precision
+                    let num = l_num * r_denom - r_num * l_denom;
+                    let denom = l_denom * r_denom;
+                    reduce_real(num, denom, *l_prec)
+                }
+                // Real - Rational = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // (a/b) - (c/d) = (ad - bc) / bd
+                    let num = l_num * r_denom - r_num * l_denom;
+                    let denom = l_denom * r_denom;
+                    reduce_real(num, denom, *l_prec)
+                }
+                // Real - Number = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Number(r_num)) => {
+                    // (a/b) - c = (a - bc) / b
+                    let num = l_num - r_num * l_denom;
+                    reduce_real(num, l_denom.clone(), *l_prec)
+                }
+                // Rational - Real = Real
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    // (a/b) - (c/d) = (ad - bc) / bd, preserve right precision
+                    let num = l_num * r_denom - r_num * l_denom;
+                    let denom = l_denom * r_denom;
+                    reduce_real(num, denom, *r_prec)
+                }
+                // Number - Real = Real
+                (Value::Number(l_num),
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    // a - (c/d) = (ad - c) / d, preserve right precision
+                    let num = l_num * r_denom - r_num;
+                    reduce_real(num, r_denom.clone(), *r_prec)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a/b - c/d = (ad - bc) / bd
+                    let num = l_num * r_denom - r_num * l_denom;
+                    let denom = l_denom * r_denom;
+                    reduce_rational(num, denom)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Number(r_num)) => {
+                    // a/b - c = (a - bc) / b
+                    let num = l_num - r_num * l_denom;
+                    reduce_rational(num, l_denom.clone())
+                }
+                (Value::Number(l_num),
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a - c/d = (ad - c) / d
+                    let num = l_num * r_denom - r_num;
+                    reduce_rational(num, r_denom.clone())
+                }
+                _ => Value::Number(left.to_number()? - right.to_number()?)
+            }
+        }
+        "*" => {
+            match (&left, &right) {
+                // Real * Real = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    // (a/b) * (c/d) = (ac) / (bd), preserve left precision
+                    let num = l_num * r_num;
+                    let denom = l_denom * r_denom;
+                    reduce_real(num, denom, *l_prec)
+                }
+                // Real * Rational = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    let num = l_num * r_num;
+                    let denom = l_denom * r_denom;
+                    reduce_real(num, denom, *l_prec)
+                }
+                // Real * Number = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Number(r_num)) => {
+                    let num = l_num * r_num;
+                    reduce_real(num, l_denom.clone(), *l_prec)
+                }
+                // Rational * Real = Real
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    let num = l_num * r_num;
+                    let denom = l_denom * r_denom;
+                    reduce_real(num, denom, *r_prec)
+                }
+                // Number * Real = Real
+                (Value::Number(l_num),
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    let num = l_num * r_num;
+                    reduce_real(num, r_denom.clone(), *r_prec)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a/b * c/d = (ac) / (bd)
+                    let num = l_num * r_num;
+                    let denom = l_denom * r_denom;
+                    reduce_rational(num, denom)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Number(r_num)) => {
+                    // a/b * c = (ac) / b
+                    let num = l_num * r_num;
+                    reduce_rational(num, l_denom.clone())
+                }
+                (Value::Number(l_num),
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a * c/d = (ac) / d
+                    let num = l_num * r_num;
+                    reduce_rational(num, r_denom.clone())
+                }
+                _ => Value::Number(left.to_number()? * right.to_number()?)
+            }
+        }
+        "/" => {
+            match (&left, &right) {
+                // Real / Real = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    // (a/b) / (c/d) = (ad) / (bc), preserve left precision
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
                     }
-                    Value::Number(l_int % r_int)
-                }
-                "//" => {
-                    // Integer quotient: a // b returns quotient truncating toward zero
-                    // Identity: a == b * (a // b) + (a % b)
-                    match (&left, &right) {
-                        // Integer // Integer = Integer
-                        (Value::Number(l), Value::Number(r)) => {
-                            if *r == BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            Value::Number(l / r)  // Truncates toward zero in Rust
-                        }
-                        // Integer // Rational = Rational
-                        (Value::Number(l), Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            // l // (r_num/r_denom) = (l * r_denom) // r_num
-                            let quot = (l * r_denom) / r_num;
-                            reduce_rational(quot, BigInt::from(1))
-                        }
-                        // Rational // Integer = Rational
-                        (Value::Rational { numerator: l_num, denominator: l_denom }, Value::Number(r)) => {
-                            if *r == BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            // (l_num/l_denom) // r = l_num // (r * l_denom)
-                            let quot = l_num / (r * l_denom);
-                            reduce_rational(quot, BigInt::from(1))
-                        }
-                        // Rational // Rational = Rational
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            // (l_num/l_denom) // (r_num/r_denom) = (l_num * r_denom) // (r_num * l_denom)
-                            let quot = (l_num * r_denom) / (r_num * l_denom);
-                            reduce_rational(quot, BigInt::from(1))
-                        }
-                        // Real // ... = Real
-                        (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec }, _) => {
-                            let (r_num, r_denom) = match &right {
-                                Value::Number(n) => (n.clone(), BigInt::from(1)),
-                                Value::Rational { numerator: n, denominator: d } => (n.clone(), d.clone()),
-                                Value::Real { numerator: n, denominator: d, .. } => (n.clone(), d.clone()),
-                                _ => return Err("Integer quotient requires numeric operands".to_string()),
-                            };
-                            if r_num == BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let quot = (l_num * &r_denom) / (&r_num * l_denom);
-                            reduce_real(quot, BigInt::from(1), *l_prec)
-                        }
-                        // ... // Real = Real (symmetric)
-                        (_, Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
-                            if r_num == &BigInt::from(0) {
-                                return Err("Division by zero".to_string());
-                            }
-                            let (l_num, l_denom) = match &left {
-                                Value::Number(n) => (n.clone(), BigInt::from(1)),
-                                Value::Rational { numerator: n, denominator: d } => (n.clone(), d.clone()),
-                                _ => return Err("Integer quotient requires numeric operands".to_string()),
-                            };
-                            let quot = (&l_num * r_denom) / (&l_denom * r_num);
-                            reduce_real(quot, BigInt::from(1), *r_prec)
-                        }
-                        _ => return Err("Integer quotient requires numeric operands".to_string()),
+                    let num = l_num * r_denom;
+                    let denom = l_denom * r_num;
+                    reduce_real(num, denom, *l_prec)
+                }
+                // Real / Rational = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
                     }
+                    let num = l_num * r_denom;
+                    let denom = l_denom * r_num;
+                    reduce_real(num, denom, *l_prec)
                 }
-                "==" => Value::Bool(left == right),
-                "!=" => Value::Bool(left != right),
-                "<" => {
-                    match (&left, &right) {
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a/b < c/d ⟺ ad < bc
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross < right_cross)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Number(r_num)) => {
-                            // a/b < c ⟺ a < bc
-                            let left_cross = l_num;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross < &right_cross)
-                        }
-                        (Value::Number(l_num),
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a < c/d ⟺ ad < c
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num;
-                            Value::Bool(&left_cross < right_cross)
-                        }
-                        _ => Value::Bool(left.to_number()? < right.to_number()?)
+                // Real / Number = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec },
+                 Value::Number(r_num)) => {
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
                     }
+                    let denom = l_denom * r_num;
+                    reduce_real(l_num.clone(), denom, *l_prec)
                 }
-                ">" => {
-                    match (&left, &right) {
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a/b > c/d ⟺ ad > bc
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross > right_cross)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Number(r_num)) => {
-                            // a/b > c ⟺ a > bc
-                            let left_cross = l_num;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross > &right_cross)
-                        }
-                        (Value::Number(l_num),
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a > c/d ⟺ ad > c
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num;
-                            Value::Bool(&left_cross > right_cross)
-                        }
-                        _ => Value::Bool(left.to_number()? > right.to_number()?)
+                // Rational / Real = Real
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
                     }
+                    let num = l_num * r_denom;
+                    let denom = l_denom * r_num;
+                    reduce_real(num, denom, *r_prec)
                 }
-                "<=" => {
-                    match (&left, &right) {
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a/b <= c/d ⟺ ad <= bc
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross <= right_cross)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Number(r_num)) => {
-                            // a/b <= c ⟺ a <= bc
-                            let left_cross = l_num;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross <= &right_cross)
-                        }
-                        (Value::Number(l_num),
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a <= c/d ⟺ ad <= c
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num;
-                            Value::Bool(&left_cross <= right_cross)
-                        }
-                        _ => Value::Bool(left.to_number()? <= right.to_number()?)
+                // Number / Real = Real
+                (Value::Number(l_num),
+                 Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
                     }
+                    let num = l_num * r_denom;
+                    reduce_real(num, r_num.clone(), *r_prec)
                 }
-                ">=" => {
-                    match (&left, &right) {
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a/b >= c/d ⟺ ad >= bc
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross >= right_cross)
-                        }
-                        (Value::Rational { numerator: l_num, denominator: l_denom },
-                         Value::Number(r_num)) => {
-                            // a/b >= c ⟺ a >= bc
-                            let left_cross = l_num;
-                            let right_cross = r_num * l_denom;
-                            Value::Bool(left_cross >= &right_cross)
-                        }
-                        (Value::Number(l_num),
-                         Value::Rational { numerator: r_num, denominator: r_denom }) => {
-                            // a >= c/d ⟺ ad >= c
-                            let left_cross = l_num * r_denom;
-                            let right_cross = r_num;
-                            Value::Bool(&left_cross >= right_cross)
-                        }
-                        _ => Value::Bool(left.to_number()? >= right.to_number()?)
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a/b ÷ c/d = (ad) / (bc)
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
                     }
+                    let num = l_num * r_denom;
+                    let denom = l_denom * r_num;
+                    reduce_rational(num, denom)
                 }
-                "**" => {
-                    // Extract base as rational (supports Number, Rational, and Real)
-                    let (base_num, base_denom, is_real, precision) = match left {
-                        Value::Number(n) => (n.clone(), BigInt::from(1), false, 0),
-                        Value::Rational { numerator, denominator } => {
-                            (numerator.clone(), denominator.clone(), false, 0)
-                        }
-                        Value::Real { numerator, denominator, precision } => {
-                            (numerator.clone(), denominator.clone(), true, precision)
-                        }
-                        _ => return Err("Left operand must be a number".to_string()),
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Number(r_num)) => {
+                    // a/b ÷ c = a / (bc)
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    let denom = l_denom * r_num;
+                    reduce_rational(l_num.clone(), denom)
+                }
+                (Value::Number(l_num),
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a ÷ c/d = (ad) / c
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    let num = l_num * r_denom;
+                    reduce_rational(num, r_num.clone())
+                }
+                (Value::Number(l_num), Value::Number(r_num)) => {
+                    // a ÷ b = a/b (produces rational)
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    reduce_rational(l_num.clone(), r_num.clone())
+                }
+                _ => return Err("Division requires numeric operands".to_string())
+            }
+        }
+        "%" => {
+            // For modulo, extract integer parts from rationals
+            let l_int = match &left {
+                Value::Number(n) => n.clone(),
+                Value::Rational { numerator, denominator } => numerator / denominator,
+                _ => return Err("Modulo requires numeric operands".to_string()),
+            };
+            let r_int = match &right {
+                Value::Number(n) => n.clone(),
+                Value::Rational { numerator, denominator } => numerator / denominator,
+                _ => return Err("Modulo requires numeric operands".to_string()),
+            };
+            if r_int == BigInt::from(0) {
+                return Err("Modulo by zero".to_string());
+            }
+            Value::Number(l_int % r_int)
+        }
+        "//" => {
+            // Integer quotient: a // b returns quotient truncating toward zero
+            // Identity: a == b * (a // b) + (a % b)
+            match (&left, &right) {
+                // Integer // Integer = Integer
+                (Value::Number(l), Value::Number(r)) => {
+                    if *r == BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    Value::Number(l / r)  // Truncates toward zero in Rust
+                }
+                // Integer // Rational = Rational
+                (Value::Number(l), Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    // l // (r_num/r_denom) = (l * r_denom) // r_num
+                    let quot = (l * r_denom) / r_num;
+                    reduce_rational(quot, BigInt::from(1))
+                }
+                // Rational // Integer = Rational
+                (Value::Rational { numerator: l_num, denominator: l_denom }, Value::Number(r)) => {
+                    if *r == BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    // (l_num/l_denom) // r = l_num // (r * l_denom)
+                    let quot = l_num / (r * l_denom);
+                    reduce_rational(quot, BigInt::from(1))
+                }
+                // Rational // Rational = Rational
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    // (l_num/l_denom) // (r_num/r_denom) = (l_num * r_denom) // (r_num * l_denom)
+                    let quot = (l_num * r_denom) / (r_num * l_denom);
+                    reduce_rational(quot, BigInt::from(1))
+                }
+                // Real // ... = Real
+                (Value::Real { numerator: l_num, denominator: l_denom, precision: l_prec }, _) => {
+                    let (r_num, r_denom) = match &right {
+                        Value::Number(n) => (n.clone(), BigInt::from(1)),
+                        Value::Rational { numerator: n, denominator: d } => (n.clone(), d.clone()),
+                        Value::Real { numerator: n, denominator: d, .. } => (n.clone(), d.clone()),
+                        _ => return Err("Integer quotient requires numeric operands".to_string()),
                     };
-
-                    // Extract exponent as integer (truncate Rational/Real to integer)
-                    let exp_int = match &right {
-                        Value::Number(n) => n.clone(),
-                        Value::Rational { numerator, denominator } => numerator / denominator,
-                        Value::Real { numerator, denominator, .. } => numerator / denominator,
-                        _ => return Err("Right operand must be a number".to_string()),
+                    if r_num == BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    let quot = (l_num * &r_denom) / (&r_num * l_denom);
+                    reduce_real(quot, BigInt::from(1), *l_prec)
+                }
+                // ... // Real = Real (symmetric)
+                (_, Value::Real { numerator: r_num, denominator: r_denom, precision: r_prec }) => {
+                    if r_num == &BigInt::from(0) {
+                        return Err("Division by zero".to_string());
+                    }
+                    let (l_num, l_denom) = match &left {
+                        Value::Number(n) => (n.clone(), BigInt::from(1)),
+                        Value::Rational { numerator: n, denominator: d } => (n.clone(), d.clone()),
+                        _ => return Err("Integer quotient requires numeric operands".to_string()),
                     };
+                    let quot = (&l_num * r_denom) / (&l_denom * r_num);
+                    reduce_real(quot, BigInt::from(1), *r_prec)
+                }
+                _ => return Err("Integer quotient requires numeric operands".to_string()),
+            }
+        }
+        "==" => Value::Bool(left == right),
+        "!=" => Value::Bool(left != right),
+        "in" => Value::Bool(value_contains(&right, &left)?),
+        "<" => {
+            match (&left, &right) {
+                // Strings order lexicographically by Unicode code point
+                (Value::String(l_str), Value::String(r_str)) => Value::Bool(l_str < r_str),
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a/b < c/d ⟺ ad < bc
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross < right_cross)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Number(r_num)) => {
+                    // a/b < c ⟺ a < bc
+                    let left_cross = l_num;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross < &right_cross)
+                }
+                (Value::Number(l_num),
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a < c/d ⟺ ad < c
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num;
+                    Value::Bool(&left_cross < right_cross)
+                }
+                _ => Value::Bool(left.to_number()? < right.to_number()?)
+            }
+        }
+        ">" => {
+            match (&left, &right) {
+                (Value::String(l_str), Value::String(r_str)) => Value::Bool(l_str > r_str),
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a/b > c/d ⟺ ad > bc
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross > right_cross)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Number(r_num)) => {
+                    // a/b > c ⟺ a > bc
+                    let left_cross = l_num;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross > &right_cross)
+                }
+                (Value::Number(l_num),
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a > c/d ⟺ ad > c
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num;
+                    Value::Bool(&left_cross > right_cross)
+                }
+                _ => Value::Bool(left.to_number()? > right.to_number()?)
+            }
+        }
+        "<=" => {
+            match (&left, &right) {
+                (Value::String(l_str), Value::String(r_str)) => Value::Bool(l_str <= r_str),
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a/b <= c/d ⟺ ad <= bc
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross <= right_cross)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Number(r_num)) => {
+                    // a/b <= c ⟺ a <= bc
+                    let left_cross = l_num;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross <= &right_cross)
+                }
+                (Value::Number(l_num),
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a <= c/d ⟺ ad <= c
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num;
+                    Value::Bool(&left_cross <= right_cross)
+                }
+                _ => Value::Bool(left.to_number()? <= right.to_number()?)
+            }
+        }
+        ">=" => {
+            match (&left, &right) {
+                (Value::String(l_str), Value::String(r_str)) => Value::Bool(l_str >= r_str),
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a/b >= c/d ⟺ ad >= bc
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross >= right_cross)
+                }
+                (Value::Rational { numerator: l_num, denominator: l_denom },
+                 Value::Number(r_num)) => {
+                    // a/b >= c ⟺ a >= bc
+                    let left_cross = l_num;
+                    let right_cross = r_num * l_denom;
+                    Value::Bool(left_cross >= &right_cross)
+                }
+                (Value::Number(l_num),
+                 Value::Rational { numerator: r_num, denominator: r_denom }) => {
+                    // a >= c/d ⟺ ad >= c
+                    let left_cross = l_num * r_denom;
+                    let right_cross = r_num;
+                    Value::Bool(&left_cross >= right_cross)
+                }
+                _ => Value::Bool(left.to_number()? >= right.to_number()?)
+            }
+        }
+        "**" => {
+            // Extract base as rational (supports Number, Rational, and Real)
+            let (base_num, base_denom, is_real, precision) = match left {
+                Value::Number(n) => (n.clone(), BigInt::from(1), false, 0),
+                Value::Rational { numerator, denominator } => {
+                    (numerator.clone(), denominator.clone(), false, 0)
+                }
+                Value::Real { numerator, denominator, precision } => {
+                    (numerator.clone(), denominator.clone(), true, precision)
+                }
+                _ => return Err("Left operand must be a number".to_string()),
+            };
 
-                    // Convert exponent to u32 for pow operation
-                    let exp_u32 = exp_int.to_u32()
-                        .ok_or_else(|| "Exponent too large".to_string())?;
+            // Extract exponent as integer (truncate Rational/Real to integer)
+            let exp_int = match &right {
+                Value::Number(n) => n.clone(),
+                Value::Rational { numerator, denominator } => numerator / denominator,
+                Value::Real { numerator, denominator, .. } => bigfloat::integer_part(numerator, denominator)?,
+                _ => return Err("Right operand must be a number".to_string()),
+            };
 
-                    // Compute base^exp for rational: (a/b)^n = a^n / b^n
-                    let result_num = base_num.pow(exp_u32);
-                    let result_denom = base_denom.pow(exp_u32);
+            // Convert exponent to u32 for pow operation
+            let exp_u32 = exp_int.to_u32()
+                .ok_or_else(|| "Exponent too large".to_string())?;
 
-                    // Return appropriate type based on input
-                    if is_real {
-                        Value::Real {
-                            numerator: result_num,
-                            denominator: result_denom,
-                            precision,
-                        }
-                    } else if result_denom == BigInt::from(1) {
-                        Value::Number(result_num)
-                    } else {
-                        Value::Rational {
-                            numerator: result_num,
-                            denominator: result_denom,
-                        }
-                    }
+            // inf and nan bases, and powers past the REAL range (see lib_lumen/bigfloat.rs)
+            if let (true, Some((num, den))) = (is_real, bigfloat::special_power((&base_num, &base_denom), exp_u32)) {
+                return Ok(reduce_real(num, den, precision));
+            }
+
+            // Compute base^exp for rational: (a/b)^n = a^n / b^n
+            let result_num = base_num.pow(exp_u32);
+            let result_denom = base_denom.pow(exp_u32);
+
+            // Return appropriate type based on input
+            if is_real {
+                reduce_real(result_num, result_denom, precision)
+            } else if result_denom == BigInt::from(1) {
+                Value::Number(result_num)
+            } else {
+                Value::Rational {
+                    numerator: result_num,
+                    denominator: result_denom,
                 }
-                ".." => Value::Range {
-                    start: left.to_number()?,
-                    end: right.to_number()?,
-                },
-                "and" | "&&" => Value::Bool(left.to_bool() && right.to_bool()),
-                "or" | "||" => Value::Bool(left.to_bool() || right.to_bool()),
-                "[]" => {
-                    // Array indexing: left is array, right is index
-                    let arr = match left {
-                        Value::Array(ref elements) => elements,
-                        _ => return Err("Cannot index non-array value".to_string()),
-                    };
+            }
+        }
+        ".." => Value::Range {
+            start: left.to_number()?,
+            end: right.to_number()?,
+        },
+        "and" | "&&" => Value::Bool(left.to_bool() && right.to_bool()),
+        "or" | "||" => Value::Bool(left.to_bool() || right.to_bool()),
+        "[]" => {
+            // Map lookup: left is map, right is a string key
+            if let Value::Map(_) = left {
+                let key = match &right {
+                    Value::String(k) => k,
+                    _ => return Err("Map key must be a string".to_string()),
+                };
+                return match left.map_get(key) {
+                    Some(v) => Ok(v.clone()),
+                    None => Err(format!("Map has no key '{}'", key)),
+                };
+            }
 
-                    // Convert index to usize
-                    let idx = match &right {
-                        Value::Number(n) => {
-                            n.to_usize()
-                                .ok_or_else(|| "Array index out of bounds".to_string())?
-                        }
-                        _ => return Err("Array index must be a number".to_string()),
-                    };
+            // Convert index to usize
+            let index = |right: &Value| match right {
+                Value::Number(n) => {
+                    n.to_usize()
+                        .ok_or_else(|| "Array index out of bounds".to_string())
+                }
+                _ => Err("Array index must be a number".to_string()),
+            };
 
-                    // Bounds check
-                    if idx >= arr.len() {
-                        return Err(format!("Array index {} out of bounds (length: {})", idx, arr.len()));
-                    }
+            // A byte, as an INTEGER from 0 to 255
+            if let Value::Bytes(bytes) = &left {
+                let idx = index(&right)?;
+                return match bytes.get(idx) {
+                    Some(&byte) => Ok(Value::Number(BigInt::from(byte))),
+                    None => Err(format!("Bytes index {} out of bounds (length: {})", idx, bytes.len())),
+                };
+            }
+
+            // Array indexing: left is array, right is index
+            let arr = match left {
+                Value::Array(ref elements) => elements,
+                _ => return Err("Cannot index non-array value".to_string()),
+            };
+            let idx = index(&right)?;
+
+            // Bounds check
+            if idx >= arr.len() {
+                return Err(format!("Array index {} out of bounds (length: {})", idx, arr.len()));
+            }
+
+            arr[idx].clone()
+        }
+        _ => return Err(format!("Unknown binary operator: {}", op)),
+    };
+
+    Ok(result)
+}
+
+/// Membership test for `item in container`
+/// Arrays match an equal element, strings a substring, maps a key, ranges an integer in [start, end).
+fn value_contains(container: &Value, item: &Value) -> Result<bool, String> {
+    match container {
+        Value::Array(elements) => Ok(elements.iter().any(|e| e == item)),
+        Value::String(haystack) => match item {
+            Value::String(needle) => Ok(haystack.contains(needle.as_str())),
+            _ => Err("'in' on a string requires a string on the left".to_string()),
+        },
+        Value::Map(_) => match item {
+            Value::String(key) => Ok(container.map_get(key).is_some()),
+            _ => Err("'in' on a map requires a string key on the left".to_string()),
+        },
+        Value::Range { start, end } => match item {
+            Value::Number(n) => Ok(start <= n && n < end),
+            _ => Ok(false),
+        },
+        _ => Err("'in' requires an array, string, map, or range on the right".to_string()),
+    }
+}
+
+/// exit([status]): end the program with a process exit status (default 0)
+/// Unwinds like error(); the binary turns the request into the exit status.
+#[inline(never)]
+fn builtin_exit(args: &[Value]) -> Result<Value, LumenError> {
+    let status = match args {
+        [] => 0,
+        [Value::Number(n)] => check_exit_status(n.to_i64())?,
+        _ => check_exit_status(None)?,
+    };
+    Err(LumenError::Exit(status))
+}
+
+/// extern(function_name, arg1, arg2, ...): dispatch to a host capability or a registered mock
+fn builtin_extern(
+    program: &Program,
+    arg_vals: &[Value],
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Value, ExternError> {
+    if arg_vals.is_empty() {
+        return Err(ExternError::invalid_argument("extern requires at least one argument (function name)"));
+    }
+    let func_name = match &arg_vals[0] {
+        Value::String(s) => s.clone(),
+        _ => return Err(ExternError::invalid_argument("First argument to extern must be a string (function name)")),
+    };
+    let extern_args = arg_vals[1..].to_vec();
+
+    // A mock registered by a test shadows the real capability;
+    // its handler receives the arguments as a single array
+    if let Some(handler) = env.resolve_mock(&func_name) {
+        return Ok(call_user_function(program, &handler, vec![Value::Array(extern_args)], env, schema)?);
+    }
+
+    // --audit: every call that reaches (or is denied) a capability is logged
+    let Some(audit) = env.usage.limits().audit.clone() else {
+        return call_capability(&func_name, &extern_args, program, env, schema);
+    };
+    let (started, clock) = (SystemTime::now(), Instant::now());
+    let result = call_capability(&func_name, &extern_args, program, env, schema);
+    let args: Vec<String> = extern_args.iter().map(Value::repr).collect();
+    let failure = result.as_ref().err().map(|e| (e.code.as_str(), e.message.as_str()));
+    audit.record(&func_name, &args, started, clock.elapsed(), failure);
+    result
+}
+
+/// builtin_extern, served from the run's cache when cache_extern marked the selector and it
+/// was called with the same arguments before (see lib_lumen/extern_cache.rs). A mock
+/// shadows the cache as it shadows the capability.
+fn cached_extern(
+    program: &Program,
+    arg_vals: &[Value],
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Value, ExternError> {
+    let selector = match arg_vals.first() {
+        Some(Value::String(s)) if env.extern_cache.is_cacheable(s) && env.resolve_mock(s).is_none() => s.clone(),
+        _ => return builtin_extern(program, arg_vals, env, schema),
+    };
+    if let Some(value) = env.cached_extern(&selector, &arg_vals[1..]) {
+        return Ok(value);
+    }
+    let value = builtin_extern(program, arg_vals, env, schema)?;
+    env.cache_extern_result(&selector, &arg_vals[1..], value.clone());
+    Ok(value)
+}
+
+/// Run host capability `func_name`, unless the run's policy denies it
+/// A capability given one of the program's functions calls it back in `env`; any other with
+/// a timeout runs on a thread of its own (see lib_lumen/extern_timeout.rs).
+fn call_capability(
+    func_name: &str,
+    extern_args: &[Value],
+    program: &Program,
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Value, ExternError> {
+    // A capability the run's policy (--allow-BACKEND, --deny-all) denies is an error naming the flag
+    if let Some((backend, capability)) = func_name.split_once(':') {
+        if !env.usage.limits().externs.allows(Some(backend), capability) && super::EXTERN_CAPABILITIES.contains(&func_name) {
+            return Err(ExternError::new("denied", extern_policy::denied(backend, capability)));
+        }
+    }
+
+    // The program's functions run here, which that thread cannot reach
+    if func_name == "arr:sort_by" {
+        return arr_sort_by(extern_args, program, env, schema);
+    }
+    let (backend, capability) = match func_name.split_once(':') {
+        Some((backend, capability)) => (Some(backend), capability),
+        None => (None, func_name),
+    };
+    match env.usage.limits().extern_timeouts.with(&env.extern_timeouts, backend, capability) {
+        Some(timeout) => {
+            let (name, args, sockets) = (func_name.to_string(), extern_args.to_vec(), Arc::clone(&env.sockets));
+            extern_timeout::run(timeout, move || host_capability(&name, &args, &sockets))
+                .unwrap_or_else(|stopped| Err(stopped_error(func_name, timeout, stopped)))
+        }
+        None => host_capability(func_name, extern_args, &env.sockets),
+    }
+}
+
+/// The error of a timed call that gave no result
+fn stopped_error(selector: &str, timeout: Duration, stopped: Stopped) -> ExternError {
+    match stopped {
+        Stopped::TimedOut => ExternError::timed_out(extern_timeout::timed_out(selector, timeout)),
+        Stopped::Failed(why) => ExternError::new("failed", format!("extern \"{}\": {}", selector, why)),
+    }
+}
 
-                    arr[idx].clone()
+/// Run host capability `func_name`, which takes none of the program's functions; net:*
+/// calls use the run's `sockets`
+fn host_capability(func_name: &str, extern_args: &[Value], sockets: &net_backend::Sockets) -> Result<Value, ExternError> {
+    match func_name {
+        "print_native" => {
+            for val in extern_args {
+                println!("{}", val);
+            }
+            Ok(Value::Null)
+        }
+        "value_type" => {
+            // Return the type of the first argument
+            if extern_args.is_empty() {
+                return Err(ExternError::invalid_argument("value_type requires an argument"));
+            }
+            let type_str = match &extern_args[0] {
+                Value::Number(_) => "number",
+                Value::Rational { .. } => "rational",
+                Value::Real { .. } => "real",
+                Value::String(_) => "string",
+                Value::Bytes(_) => "bytes",
+                Value::Bool(_) => "bool",
+                Value::Null => "null",
+                Value::Range { .. } => "range",
+                Value::Array(_) => "array",
+                Value::Map(_) => "map",
+                Value::Function { .. } => "function",
+                Value::Symbol(_) => "symbol",
+                Value::Kind(_) => "kind",
+            };
+            Ok(Value::String(type_str.to_string()))
+        }
+        "debug_info" => {
+            // Print debug info about the value
+            if extern_args.is_empty() {
+                return Err(ExternError::invalid_argument("debug_info requires an argument"));
+            }
+            println!("[DEBUG] {}", extern_args[0].repr());
+            Ok(Value::Null)
+        }
+        name if name.starts_with("fs:") => {
+            let texts: Vec<Option<&str>> = extern_args.iter().map(|arg| match arg {
+                Value::String(s) => Some(s.as_str()),
+                _ => None,
+            }).collect();
+            Ok(match fs_backend::call(&name[3..], &texts)? {
+                fs_backend::Outcome::Text(text) => Value::String(text),
+                fs_backend::Outcome::Bool(b) => Value::Bool(b),
+                fs_backend::Outcome::Names(names) => Value::Array(names.into_iter().map(Value::String).collect()),
+                fs_backend::Outcome::Null => Value::Null,
+            })
+        }
+        name if name.starts_with("crypto:") => {
+            let args: Vec<crypto_backend::Arg> = extern_args.iter().map(|arg| match arg {
+                Value::String(s) => crypto_backend::Arg::Data(s.as_bytes()),
+                Value::Bytes(bytes) => crypto_backend::Arg::Data(bytes),
+                _ => crypto_backend::Arg::Other,
+            }).collect();
+            Ok(Value::String(crypto_backend::call(&name[7..], &args)?))
+        }
+        name if name.starts_with("io:") => {
+            let args: Vec<io_backend::Arg> = extern_args.iter().map(|arg| match arg {
+                Value::Bytes(bytes) => io_backend::Arg::Bytes(bytes),
+                _ => io_backend::Arg::Other,
+            }).collect();
+            Ok(match io_backend::call(&name[3..], &args)? {
+                io_backend::Outcome::Text(text) => Value::String(text),
+                io_backend::Outcome::Bytes(bytes) => Value::Bytes(bytes),
+                io_backend::Outcome::Null => Value::Null,
+            })
+        }
+        name if name.starts_with("net:") => {
+            // Connections and listeners are symbols, which a program cannot spell
+            let args: Vec<net_backend::Arg> = extern_args.iter().map(|arg| match arg {
+                Value::String(s) => net_backend::Arg::Text(s.as_str()),
+                Value::Symbol(s) => net_backend::Arg::Handle(s.as_str()),
+                _ => net_backend::Arg::Other,
+            }).collect();
+            Ok(match net_backend::call(sockets, &name[4..], &args)? {
+                net_backend::Outcome::Handle(handle) => Value::Symbol(handle),
+                net_backend::Outcome::Text(text) => Value::String(text),
+                net_backend::Outcome::Null => Value::Null,
+            })
+        }
+        "json:parse" => match extern_args {
+            [Value::String(text)] => Ok(from_json(json_backend::parse(text)?)),
+            _ => Err(ExternError::invalid_argument("json:parse expects one string")),
+        },
+        "json:stringify" => {
+            let indent = match extern_args.get(1) {
+                None => None,
+                Some(Value::Number(n)) => Some(n.to_usize().ok_or_else(|| {
+                    ExternError::invalid_argument("json:stringify indent must be a small non-negative integer")
+                })?),
+                Some(_) => return Err(ExternError::invalid_argument("json:stringify indent must be an integer")),
+            };
+            match extern_args.first() {
+                Some(value) if extern_args.len() <= 2 => Ok(Value::String(json_backend::stringify(&to_json(value)?, indent)?)),
+                _ => Err(ExternError::invalid_argument(format!("json:stringify expects 1 or 2 arguments, got {}", extern_args.len()))),
+            }
+        }
+        name if name.starts_with("env:") => {
+            let args: Vec<Option<&str>> = extern_args.iter().map(|arg| match arg {
+                Value::String(text) => Some(text.as_str()),
+                _ => None,
+            }).collect();
+            Ok(match env_backend::call(&name[4..], &args)? {
+                env_backend::Outcome::Text(text) => Value::String(text),
+                env_backend::Outcome::Null => Value::Null,
+                env_backend::Outcome::Vars(vars) => {
+                    Value::Map(vars.into_iter().map(|(name, value)| (name, Value::String(value))).collect())
                 }
-                _ => return Err(format!("Unknown binary operator: {}", op)),
+            })
+        }
+        name if name.starts_with("sys:") => {
+            Ok(match sys_backend::call(&name[4..], extern_args.len())? {
+                sys_backend::Outcome::Int(n) => Value::Number(BigInt::from(n)),
+                sys_backend::Outcome::Text(text) => Value::String(text),
+            })
+        }
+        name if name.starts_with("random:") => {
+            let args: Vec<random_backend::Arg> = extern_args.iter().map(|arg| match arg {
+                Value::Number(n) => i64::try_from(n).map_or(random_backend::Arg::Other, random_backend::Arg::Int),
+                _ => random_backend::Arg::Other,
+            }).collect();
+            Ok(match random_backend::call(&name[7..], &args)? {
+                random_backend::Outcome::Int(n) => Value::Number(BigInt::from(n)),
+                random_backend::Outcome::Fraction(n) => {
+                    let denominator = BigInt::from(1u64 << random_backend::FLOAT_BITS);
+                    let (numerator, denominator) = bigfloat::round_real(&BigInt::from(n), &denominator, 15);
+                    Value::Real { numerator, denominator, precision: 15 }
+                }
+            })
+        }
+        name if name.starts_with("time:") => {
+            let args: Vec<time_backend::Arg> = extern_args.iter().map(|arg| match arg {
+                Value::String(s) => time_backend::Arg::Text(s.as_str()),
+                Value::Number(n) => i64::try_from(n).map_or(time_backend::Arg::Other, time_backend::Arg::Int),
+                _ => time_backend::Arg::Other,
+            }).collect();
+            Ok(match time_backend::call(&name[5..], &args)? {
+                time_backend::Outcome::Int(n) => Value::Number(BigInt::from(n)),
+                time_backend::Outcome::Text(text) => Value::String(text),
+            })
+        }
+        _ => Err(ExternError::new("unavailable", format!("Unknown external function: {}", func_name))),
+    }
+}
+
+/// arr:sort_by(items, compare): `items` sorted by the program's function compare(a, b), which
+/// runs here while the call goes on (see lib_lumen/arr_backend.rs)
+fn arr_sort_by(extern_args: &[Value], program: &Program, env: &mut Environment, schema: &LanguageSchema) -> Result<Value, ExternError> {
+    let (items, compare) = match extern_args {
+        [Value::Array(items), Value::Function { body_ref, .. }] => (items.clone(), body_ref),
+        _ => return Err(arr_backend::expects("sort_by")),
+    };
+    let sorted = arr_backend::sort_by(items, &mut |a: &Value, b: &Value| {
+        match call_user_function(program, compare, vec![a.clone(), b.clone()], env, schema)? {
+            Value::Number(n) => Ok(n.cmp(&BigInt::from(0))),
+            other => Err(arr_backend::not_an_order(&other.repr())),
+        }
+    })?;
+    Ok(Value::Array(sorted))
+}
+
+/// A parsed JSON value as a Lumen value (numbers as their literals would be)
+fn from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(text) => match json_backend::number(&text) {
+            json_backend::Number::Int(n) => Value::Number(n),
+            json_backend::Number::Real { numerator, denominator, precision } => {
+                let (numerator, denominator) = bigfloat::round_real(&numerator, &denominator, precision);
+                Value::Real { numerator, denominator, precision }
+            }
+        },
+        Json::Text(text) => Value::String(text),
+        Json::List(items) => Value::Array(items.into_iter().map(from_json).collect()),
+        Json::Map(entries) => Value::Map(entries.into_iter().map(|(key, value)| (key, from_json(value))).collect()),
+    }
+}
+
+/// A Lumen value as JSON; numbers go as they print (the backend refuses a RATIONAL, inf, nan)
+fn to_json(value: &Value) -> Result<Json, String> {
+    Ok(match value {
+        Value::Null => Json::Null,
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Number(_) | Value::Rational { .. } | Value::Real { .. } => Json::Number(value.to_string()),
+        Value::String(s) => Json::Text(s.clone()),
+        Value::Array(items) => Json::List(items.iter().map(to_json).collect::<Result<_, _>>()?),
+        Value::Map(entries) => Json::Map(
+            entries.iter().map(|(key, value)| Ok((key.clone(), to_json(value)?))).collect::<Result<_, String>>()?,
+        ),
+        other => return Err(format!("json:stringify: {} has no JSON form", other.repr())),
+    })
+}
+
+/// extern_async(selector, arg1, ...): this kernel has no executor, so the call runs now and
+/// its result waits for extern_await. As in the stream kernel, an unknown or denied selector
+/// (or an error in a mock) stops the program here; a capability's own error comes at the await.
+fn builtin_extern_async(
+    program: &Program,
+    arg_vals: &[Value],
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Value, String> {
+    let selector = match arg_vals.first() {
+        Some(Value::String(s)) => s.clone(),
+        _ => return Err("extern_async() requires a string selector".to_string()),
+    };
+    if arg_vals[1..].iter().any(|arg| matches!(arg, Value::Function { .. })) {
+        return Err("extern_async() cannot pass a function: its call runs alongside the program".to_string());
+    }
+    let capability = env.resolve_mock(&selector).is_none()
+        && super::EXTERN_CAPABILITIES.contains(&selector.as_str())
+        && env.usage.limits().externs.allows_selector(&selector);
+    let result = builtin_extern(program, arg_vals, env, schema).map_err(String::from);
+    let result = if capability { result } else { Ok(result?) };
+    Ok(Value::Symbol(env.finish_extern_call(&selector, result)))
+}
+
+/// extern_try(selector, arg1, ...): {"ok": true, "value": v}, or {"ok": false, "error": e} with
+/// the ExternError as a MAP (lib_lumen/extern_error.rs). A mock runs as for extern, and an error
+/// in it stops the program, as in the stream kernel. So does an `exit` in a function the
+/// capability calls back.
+fn builtin_extern_try(
+    program: &Program,
+    arg_vals: &[Value],
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Value, LumenError> {
+    let selector = match arg_vals.first() {
+        Some(Value::String(s)) => s.clone(),
+        _ => return Err("extern_try() requires a string selector".into()),
+    };
+    let result = match env.resolve_mock(&selector) {
+        Some(handler) => Ok(call_user_function(program, &handler, vec![Value::Array(arg_vals[1..].to_vec())], env, schema)?),
+        None => cached_extern(program, arg_vals, env, schema),
+    };
+    // A function the capability called back ended the program; that is no failed call
+    if let Err(ExternError { exit: Some(status), .. }) = &result {
+        return Err(LumenError::Exit(*status));
+    }
+    let (ok, key, value) = match result {
+        Ok(value) => (true, "value", value),
+        Err(error) => {
+            let errno = error.errno.map_or(Value::Null, |errno| Value::Number(BigInt::from(errno)));
+            let fields = vec![
+                ("code".to_string(), Value::String(error.code)),
+                ("message".to_string(), Value::String(error.message)),
+                ("retriable".to_string(), Value::Bool(error.retriable)),
+                ("errno".to_string(), errno),
+            ];
+            (false, "error", Value::Map(fields))
+        }
+    };
+    Ok(Value::Map(vec![("ok".to_string(), Value::Bool(ok)), (key.to_string(), value)]))
+}
+
+/// extern_available(selector): whether extern() would resolve the selector
+/// (an active mock or one of the allowed capabilities handled by builtin_extern). Never calls it.
+#[inline(never)]
+fn builtin_extern_available(args: &[Value], env: &Environment) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("extern_available() expects 1 argument, got {}", args.len()));
+    }
+    let selector = match &args[0] {
+        Value::String(s) => s,
+        _ => return Err("extern_available() requires a string selector".to_string()),
+    };
+    let available = env.resolve_mock(selector).is_some()
+        || (super::EXTERN_CAPABILITIES.contains(&selector.as_str()) && env.usage.limits().externs.allows_selector(selector));
+    Ok(Value::Bool(available))
+}
+
+/// Extern mock builtins: mock_extern(selector, f) registers `f` in the current mock layer,
+/// push_mocks() starts a layer and pop_mocks() discards it.
+/// Selectors are matched exactly, as extern() itself matches them.
+#[inline(never)]
+fn builtin_mock(function: &str, args: &[Value], env: &mut Environment) -> Result<Value, String> {
+    match function {
+        "mock_extern" => {
+            if args.len() != 2 {
+                return Err(format!("mock_extern() expects 2 arguments, got {}", args.len()));
+            }
+            let selector = match &args[0] {
+                Value::String(s) => s,
+                _ => return Err("mock_extern() selector must be a string".to_string()),
             };
+            match &args[1] {
+                Value::Function { body_ref, .. } => env.mock_extern(selector, body_ref),
+                _ => return Err("mock_extern() requires a function argument".to_string()),
+            }
+        }
+        "push_mocks" => env.push_mock_layer(),
+        _ => {
+            if !env.pop_mock_layer() {
+                return Err("pop_mocks() called with no active mock layer".to_string());
+            }
+        }
+    }
+    Ok(Value::Null)
+}
+
+/// Extern cache builtins (see lib_lumen/extern_cache.rs): cache_extern(selector) keeps the
+/// results of the selector's calls for the run, clear_extern_cache([selector]) drops them.
+#[inline(never)]
+fn builtin_extern_cache(function: &str, args: &[Value], env: &mut Environment) -> Result<Value, String> {
+    match (function, args) {
+        ("cache_extern", [Value::String(selector)]) => env.extern_cache.mark(selector),
+        ("clear_extern_cache", []) => env.extern_cache.clear(None),
+        ("clear_extern_cache", [Value::String(selector)]) => env.extern_cache.clear(Some(selector)),
+        ("cache_extern", [_]) | ("clear_extern_cache", [_]) => return Err(format!("{}() requires a string selector", function)),
+        ("cache_extern", _) => return Err(format!("cache_extern() expects 1 argument, got {}", args.len())),
+        _ => return Err(format!("clear_extern_cache() expects 0 or 1 arguments, got {}", args.len())),
+    }
+    Ok(Value::Null)
+}
+
+/// extern_timeout(target, ms): from now on the program's extern calls of `target` (*, a
+/// backend or a capability) stop waiting after `ms` milliseconds; null takes the timeout
+/// away. The host's timeouts still apply (see lib_lumen/extern_timeout.rs).
+#[inline(never)]
+fn builtin_extern_timeout(args: &[Value], env: &mut Environment) -> Result<Value, String> {
+    let (target, millis) = match args {
+        [Value::String(target), millis] => (target, millis),
+        [_, _] => return Err("extern_timeout() requires a string target".to_string()),
+        _ => return Err(format!("extern_timeout() expects 2 arguments, got {}", args.len())),
+    };
+    let timeout = match millis {
+        Value::Null => None,
+        Value::Number(ms) if ms.to_i64().is_some_and(|ms| ms > 0) => ms.to_u64().map(Duration::from_millis),
+        _ => return Err("extern_timeout() requires a positive integer of milliseconds, or null".to_string()),
+    };
+    env.extern_timeouts.set(target, timeout)?;
+    Ok(Value::Null)
+}
+
+/// Memoization builtins (see lib_lumen/memo_cache.rs): memo_stats([name]) gives the cache's
+/// counts as a map, memo_clear([name]) drops cached results, memo_capacity(n) bounds the cache.
+#[inline(never)]
+fn builtin_memo(function: &str, args: &[Value], env: &mut Environment) -> Result<Value, String> {
+    let name = match args {
+        [] if function != "memo_capacity" => None,
+        [Value::String(name)] if function != "memo_capacity" => Some(name.as_str()),
+        [_] if function != "memo_capacity" => return Err(format!("{}() function name must be a string", function)),
+        [Value::Number(n)] => {
+            let capacity = n.to_usize().ok_or_else(|| "memo_capacity() requires a non-negative integer".to_string())?;
+            env.call_cache.set_capacity(capacity);
+            return Ok(Value::Null);
+        }
+        [_] => return Err("memo_capacity() requires a non-negative integer".to_string()),
+        _ if function == "memo_capacity" => return Err(format!("memo_capacity() expects 1 argument, got {}", args.len())),
+        _ => return Err(format!("{}() expects 0 or 1 arguments, got {}", function, args.len())),
+    };
+    if function == "memo_clear" {
+        env.call_cache.clear(name);
+        return Ok(Value::Null);
+    }
+    let stats = env.call_cache.stats(name);
+    let count = |n: u64| Value::Number(BigInt::from(n));
+    Ok(Value::Map(vec![
+        ("hits".to_string(), count(stats.hits)),
+        ("misses".to_string(), count(stats.misses)),
+        ("evictions".to_string(), count(stats.evictions)),
+        ("entries".to_string(), count(stats.entries as u64)),
+        ("capacity".to_string(), count(env.call_cache.capacity() as u64)),
+    ]))
+}
+
+/// Call a user-defined function by name with already-evaluated arguments
+/// Used by callers that hold a function name rather than an Invoke (e.g. extern mocks).
+#[inline(never)]
+fn call_user_function(
+    program: &Program,
+    name: &str,
+    args: Vec<Value>,
+    env: &mut Environment,
+    schema: &LanguageSchema,
+) -> Result<Value, LumenError> {
+    let metadata = env.functions.get(name).cloned()
+        .ok_or_else(|| format!("Function body not found for: {}", name))?;
+    if metadata.params.len() != args.len() {
+        return Err(format!(
+            "Function {} expects {} arguments, got {}",
+            name,
+            metadata.params.len(),
+            args.len()
+        ).into());
+    }
+
+    env.enter_call()?;
+    env.push_scope(metadata.layout.clone());
+    for (param, arg) in metadata.params.iter().zip(args) {
+        env.set(param.clone(), arg);
+    }
+    let result = execute_from(program, metadata.body, env, schema);
+    env.pop_scope();
+    env.usage.leave_call();
+
+    let (value, flow) = result?;
+    at_function(flow.signal())?;
+    Ok(value)
+}
+
+/// Mark a runtime error with the position of the statement it left
+fn error_at(error: LumenError, position: &Position) -> LumenError {
+    at_statement(error, position.line, position.col)
+}
+
+/// Map builtins: map_to_string(m) renders a map, keys(m) lists its keys in insertion order
+fn builtin_map(function: &str, args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("{}() expects 1 argument, got {}", function, args.len()));
+    }
+    let entries = match &args[0] {
+        Value::Map(entries) => entries,
+        _ => return Err(format!("{}() requires a map argument", function)),
+    };
+    match function {
+        "map_to_string" => Ok(Value::String(format!("{}", args[0]))),
+        _ => Ok(Value::Array(entries.iter().map(|(k, _)| Value::String(k.clone())).collect())),
+    }
+}
+
+/// bytes(x): BYTES from a string (its UTF-8), an array of integers 0 to 255, or bytes;
+/// bytes_to_string(b): b as print writes it; decode_utf8(b): the string whose UTF-8 b is
+fn builtin_bytes(function: &str, arg_vals: &[Value]) -> Result<Value, String> {
+    if arg_vals.len() != 1 {
+        return Err(format!("{}() expects 1 argument, got {}", function, arg_vals.len()));
+    }
+    match (function, &arg_vals[0]) {
+        ("bytes", Value::String(s)) => Ok(Value::Bytes(s.clone().into_bytes())),
+        ("bytes", bytes @ Value::Bytes(_)) => Ok(bytes.clone()),
+        ("bytes", Value::Array(elements)) => {
+            let byte = |(i, element): (usize, &Value)| match element {
+                Value::Number(n) => n.to_u8(),
+                _ => None,
+            }
+            .ok_or_else(|| format!("bytes(): element {} is not an integer from 0 to 255", i));
+            Ok(Value::Bytes(elements.iter().enumerate().map(byte).collect::<Result<_, _>>()?))
+        }
+        ("bytes", _) => Err("bytes() requires a string, bytes, or an array of integers from 0 to 255".to_string()),
+        ("bytes_to_string", bytes @ Value::Bytes(_)) => Ok(Value::String(bytes.to_string())),
+        ("decode_utf8", Value::Bytes(bytes)) => match std::str::from_utf8(bytes) {
+            Ok(text) => Ok(Value::String(text.to_string())),
+            Err(e) => Err(format!("decode_utf8(): invalid UTF-8 at byte {}", e.valid_up_to())),
+        },
+        _ => Err(format!("{}() requires a bytes argument", function)),
+    }
+}
 
-            Ok((result, ControlFlow::Normal))
+/// Transcendental builtins: exp/ln/atan take (x[, precision]), log takes (x, base[, precision])
+fn builtin_transcendental(function: &str, args: &[Value]) -> Result<Value, String> {
+    let (min_args, max_args) = if function == "log" { (2, 3) } else { (1, 2) };
+    if args.len() < min_args || args.len() > max_args {
+        return Err(format!(
+            "{}() expects {} or {} arguments, got {}",
+            function, min_args, max_args, args.len()
+        ));
+    }
+    let precision = match args.get(max_args - 1) {
+        Some(p) => precision_arg(p)?,
+        None => 15,
+    };
+
+    let (num, den) = numeric_operand(&args[0], function)?;
+    let (res_num, res_den) = match function {
+        "exp" => transcendental::exp(&num, &den, precision)?,
+        "ln" => transcendental::ln(&num, &den, precision)?,
+        "atan" => transcendental::atan(&num, &den, precision)?,
+        _ => {
+            let (base_num, base_den) = numeric_operand(&args[1], function)?;
+            transcendental::log(&num, &den, &base_num, &base_den, precision)?
         }
+    };
+    Ok(reduce_real(res_num, res_den, precision))
+}
+
+/// Extract a precision argument (positive integer number of significant digits)
+fn precision_arg(value: &Value) -> Result<usize, String> {
+    match value {
+        Value::Number(n) => n
+            .to_usize()
+            .filter(|p| *p > 0)
+            .ok_or_else(|| "Precision must be a positive integer".to_string()),
+        _ => Err("Precision argument must be an integer".to_string()),
+    }
+}
+
+/// Extract the exact (numerator, denominator) of an integer, rational, or real argument
+fn numeric_operand(value: &Value, function: &str) -> Result<(BigInt, BigInt), String> {
+    match value {
+        Value::Number(n) => Ok((n.clone(), BigInt::from(1))),
+        Value::Rational { numerator, denominator } => Ok((numerator.clone(), denominator.clone())),
+        Value::Real { numerator, denominator, .. } => Ok((numerator.clone(), denominator.clone())),
+        _ => Err(format!("{}() requires a number, rational, or real argument", function)),
     }
 }
 
@@ -1444,32 +2346,34 @@ fn reduce_rational(numerator: BigInt, denominator: BigInt) -> Value {
     }
 }
 
-/// Reduce a real to canonical form (like reduce_rational) but preserve precision
+/// Round a real to its precision: a decimal float in canonical rational form, or inf or
+/// nan for a zero denominator (see lib_lumen/bigfloat.rs)
 fn reduce_real(numerator: BigInt, denominator: BigInt, precision: usize) -> Value {
-    // Handle zero numerator
-    if numerator == BigInt::from(0) {
-        return Value::Real {
-            numerator: BigInt::from(0),
-            denominator: BigInt::from(1),
-            precision,
-        };
-    }
+    let (numerator, denominator) = bigfloat::round_real(&numerator, &denominator, precision);
+    Value::Real { numerator, denominator, precision }
+}
 
-    // Ensure denominator is always positive (move sign to numerator)
-    let (num, denom) = if denominator < BigInt::from(0) {
-        (-numerator, -denominator)
-    } else {
-        (numerator, denominator)
+/// `left op right` when a REAL operand is inf or nan, or a REAL is divided by zero (see
+/// lib_lumen/bigfloat.rs): + - * / give inf or nan, and comparisons treat nan as unordered.
+/// None for every other case, which the operator's own rules handle (% // ** and int()
+/// reject inf and nan as having no integer part).
+fn special_real(op: &str, left: &Value, right: &Value) -> Option<Value> {
+    let exact = |value: &Value| match value {
+        Value::Number(n) => Some((n.clone(), BigInt::from(1))),
+        Value::Rational { numerator, denominator } => Some((numerator.clone(), denominator.clone())),
+        Value::Real { numerator, denominator, .. } => Some((numerator.clone(), denominator.clone())),
+        _ => None,
     };
-
-    // Reduce by GCD
-    let g = gcd(num.clone(), denom.clone());
-    let reduced_num = &num / &g;
-    let reduced_denom = &denom / &g;
-
-    Value::Real {
-        numerator: reduced_num,
-        denominator: reduced_denom,
-        precision,
+    let precision = match (left, right) {
+        (Value::Real { precision, .. }, _) | (_, Value::Real { precision, .. }) => *precision,
+        _ => return None,
+    };
+    let (a, b) = (exact(left)?, exact(right)?);
+    if let Some((num, den)) = bigfloat::special_arithmetic(op, (&a.0, &a.1), (&b.0, &b.1)) {
+        return Some(reduce_real(num, den, precision));
+    }
+    if a.1 != BigInt::from(0) && b.1 != BigInt::from(0) {
+        return None;
     }
+    bigfloat::comparison(op, (&a.0, &a.1), (&b.0, &b.1)).map(Value::Bool)
 }
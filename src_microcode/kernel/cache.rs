@@ -0,0 +1,172 @@
+// On-disk cache of reduced programs (--cache)
+//
+// A run with --cache looks for the program's instructions under a key derived from its
+// text, the prelude it runs after, its language (with the bytes of the --schema file or
+// --plugin library that defines it, if one does) and the --opt passes. A hit skips
+// stages 1-3; a miss reduces the program as usual and stores it for the next run.
+//
+// A miss also looks up the last parse of the same input (the same files, or -e) in the
+// same language after the same prelude, whatever its text was, and reduces only what
+// changed since (see incremental.rs): the REPL and watch run the kernel again for each
+// entry or save, and the text differs from the last only at the end or around the edit.
+//
+// Entries are saved programs (see serial.rs) in CBOR, one file per key:
+//
+//   $LUMEN_CACHE_DIR/<sha-256 of the key>.cbor
+//   $LUMEN_CACHE_DIR/<sha-256 of the input's key>.last   its text, then its parse
+//
+// LUMEN_CACHE_DIR defaults to $XDG_CACHE_HOME/lumen, then ~/.cache/lumen. The key covers
+// the saved-program layout version, the crate version and the build id (a digest of the
+// kernel's source files and the compiler, from build.rs), so entries written by another
+// kernel build, or by a rebuild with a changed reducer, are never read back. The cache is best effort: an entry that cannot be
+// read is treated as a miss, and one that cannot be written is skipped. Deleting the
+// directory clears it.
+
+use super::optimize::Passes;
+use super::primitives::Program;
+use super::serial::{self, Format, SavedProgram};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+/// A cache directory (created on the first store)
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Self {
+        Cache { dir }
+    }
+
+    /// The directory from LUMEN_CACHE_DIR, XDG_CACHE_HOME or HOME
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+        let dir = var("LUMEN_CACHE_DIR")
+            .or_else(|| var("XDG_CACHE_HOME").map(|dir| dir.join("lumen")))
+            .or_else(|| var("HOME").map(|dir| dir.join(".cache").join("lumen")))
+            .ok_or("no cache directory: set LUMEN_CACHE_DIR")?;
+        Ok(Cache::new(dir))
+    }
+
+    /// The cache key of a program's text (hex SHA-256)
+    /// `prelude` identifies what the program is linked after (empty for none), and
+    /// `definition` the language (the file defining it; empty for a built-in language).
+    pub fn key(prelude: &[u8], definition: &[u8], source: &str, language: &str, passes: Passes) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!(
+            "lumen-microcode {} build {} layout {}\n{}\n{:?}\n",
+            env!("CARGO_PKG_VERSION"),
+            env!("LUMEN_BUILD_ID"),
+            serial::VERSION,
+            language,
+            passes
+        ));
+        hasher.update(Sha256::digest(prelude));
+        hasher.update(Sha256::digest(definition));
+        hasher.update(source);
+        hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// The key of the last parse of `input` (its file names, or "-e"), whatever its text
+    /// The parse is the program's own, before linking and --opt passes.
+    pub fn last_parse_key(prelude: &[u8], definition: &[u8], input: &str, language: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!(
+            "lumen-microcode last parse {} build {} layout {}\n{}\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            env!("LUMEN_BUILD_ID"),
+            serial::VERSION,
+            language,
+            input
+        ));
+        hasher.update(Sha256::digest(prelude));
+        hasher.update(Sha256::digest(definition));
+        hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.cbor", key))
+    }
+
+    /// The program stored under `key`, if there is a readable entry for `language`
+    pub fn load(&self, key: &str, language: &str) -> Option<Program> {
+        let bytes = fs::read(self.path(key)).ok()?;
+        let saved = serial::load(&bytes, Format::Cbor).ok()?;
+        (saved.language == language).then_some(saved.program)
+    }
+
+    /// Store a program under `key`
+    pub fn store(&self, key: &str, language: &str, program: &Program) -> Result<(), String> {
+        let bytes = serial::save(&SavedProgram::new(language, program.clone()), Format::Cbor)?;
+        self.write(self.path(key), key, bytes)
+    }
+
+    /// The text and program of the last parse stored under `key`, if it is for `language`
+    pub fn load_last_parse(&self, key: &str, language: &str) -> Option<(String, Program)> {
+        let bytes = fs::read(self.dir.join(format!("{}.last", key))).ok()?;
+        let (length, rest) = bytes.split_first_chunk::<8>()?;
+        let (source, program) = rest.split_at_checked(usize::try_from(u64::from_le_bytes(*length)).ok()?)?;
+        let saved = serial::load(program, Format::Cbor).ok()?;
+        if saved.language != language {
+            return None;
+        }
+        Some((String::from_utf8(source.to_vec()).ok()?, saved.program))
+    }
+
+    /// Store `program` as the last parse under `key`, with the `source` it is the parse of
+    pub fn store_last_parse(&self, key: &str, language: &str, source: &str, program: &Program) -> Result<(), String> {
+        let mut bytes = (source.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(source.as_bytes());
+        bytes.extend(serial::save(&SavedProgram::new(language, program.clone()), Format::Cbor)?);
+        self.write(self.dir.join(format!("{}.last", key)), key, bytes)
+    }
+
+    /// Write an entry to a temporary file first, so a concurrent run never reads half of it
+    fn write(&self, path: PathBuf, key: &str, bytes: Vec<u8>) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("{}: {}", self.dir.display(), e))?;
+        let temporary = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        fs::write(&temporary, bytes)
+            .and_then(|_| fs::rename(&temporary, path))
+            .map_err(|e| format!("{}: {}", temporary.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::disasm::disassemble;
+    use crate::kernel::parse_program;
+    use crate::languages::lumen_schema;
+
+    #[test]
+    fn stores_and_finds_programs_by_source_language_and_passes() {
+        let dir = std::env::temp_dir().join(format!("lumen-cache-test-{}", std::process::id()));
+        let cache = Cache::new(dir.clone());
+        let source = "x = 1 + 2\n";
+        let key = Cache::key(b"", b"", source, "lumen", Passes::default());
+        assert_ne!(key, Cache::key(b"", b"", source, "lumen", Passes::all()));
+        assert_ne!(key, Cache::key(b"", b"", source, "python_core", Passes::default()));
+        assert_ne!(key, Cache::key(b"", b"", "x = 1 + 3\n", "lumen", Passes::default()));
+        assert_ne!(key, Cache::key(b"y = 0\n", b"", source, "lumen", Passes::default()));
+        assert_ne!(key, Cache::key(b"", b"name = \"lumen\"", source, "lumen", Passes::default()));
+        assert!(cache.load(&key, "lumen").is_none());
+
+        let program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+        cache.store(&key, "lumen", &program).unwrap();
+        let cached = cache.load(&key, "lumen").unwrap();
+        assert_eq!(disassemble(&cached), disassemble(&program));
+        assert!(cache.load(&key, "python_core").is_none());
+
+        // The last parse of an input is kept whatever its text, with the text
+        let last = Cache::last_parse_key(b"", b"", "-e", "lumen");
+        assert_ne!(last, Cache::last_parse_key(b"", b"", "main.lm", "lumen"));
+        assert!(cache.load_last_parse(&last, "lumen").is_none());
+        cache.store_last_parse(&last, "lumen", source, &program).unwrap();
+        let (text, parsed) = cache.load_last_parse(&last, "lumen").unwrap();
+        assert_eq!((text.as_str(), disassemble(&parsed)), (source, disassemble(&program)));
+        assert!(cache.load_last_parse(&last, "python_core").is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,179 @@
+// Readable listing of the instruction tree for --emit-instructions
+//
+// Prints the output of stage 3 (reduce) one instruction per line, with its operands
+// indented under it. Instructions with different kinds of operands label them:
+//
+//   [3] Branch (no else)
+//     condition:
+//       [6] Operate binary '<'
+//         Variable total
+//         Literal Number 3
+//     then:
+//       ...
+//
+// The bracketed number is the primitive (1 Sequence .. 7 Transfer, see primitives.rs);
+// literals, variables, loops, function definitions and the other structural nodes have
+// none. A closing line counts how often each primitive occurs.
+
+use super::eval::Value;
+use super::primitives::{InstrId, Instruction, OperateKind, Program, TransferKind};
+use std::fmt::Write;
+
+/// Primitive names in primitive-number order
+const PRIMITIVES: [&str; 7] = ["Sequence", "Scope", "Branch", "Assign", "Invoke", "Operate", "Transfer"];
+
+/// Listing of a whole program
+pub fn disassemble(program: &Program) -> String {
+    let mut out = String::new();
+    let mut counts = [0usize; 7];
+    emit(program, program.root(), 0, &mut out, &mut counts);
+    let totals: Vec<String> = PRIMITIVES
+        .iter()
+        .zip(counts)
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect();
+    let _ = writeln!(out, "; primitives: {}", totals.join(", "));
+    out
+}
+
+fn emit(program: &Program, id: InstrId, depth: usize, out: &mut String, counts: &mut [usize; 7]) {
+    let instr = &program[id];
+    let (primitive, text) = describe(instr);
+    if let Some(number) = primitive {
+        counts[number - 1] += 1;
+    }
+    let tag = primitive.map(|n| format!("[{}] ", n)).unwrap_or_default();
+    let _ = writeln!(out, "{}{}{}", "  ".repeat(depth), tag, text);
+
+    let children = operands(instr);
+    let labelled = children.len() > 1 && children.iter().any(|(label, _)| !label.is_empty());
+    for (label, group) in children {
+        let mut child_depth = depth + 1;
+        if labelled {
+            let _ = writeln!(out, "{}{}:", "  ".repeat(depth + 1), label);
+            child_depth += 1;
+        }
+        for child in group {
+            emit(program, child, child_depth, out, counts);
+        }
+    }
+}
+
+/// Primitive number (if the node is one of the 7) and the node's own line of text
+fn describe(instr: &Instruction) -> (Option<usize>, String) {
+    match instr {
+        Instruction::Sequence(items, _) => (Some(1), format!("Sequence ({})", plural(items.len(), "item"))),
+        Instruction::Scope(_) => (Some(2), "Scope".to_string()),
+        Instruction::Branch { else_instr, .. } => {
+            let text = if else_instr.is_some() { "Branch" } else { "Branch (no else)" };
+            (Some(3), text.to_string())
+        }
+        Instruction::Assign { name, .. } => (Some(4), format!("Assign {}", name)),
+        Instruction::Invoke { function, args } => {
+            (Some(5), format!("Invoke {} ({})", function, plural(args.len(), "arg")))
+        }
+        Instruction::Operate { kind, .. } => {
+            let text = match kind {
+                OperateKind::Unary(op) => format!("Operate unary '{}'", op),
+                OperateKind::Binary(op) => format!("Operate binary '{}'", op),
+            };
+            (Some(6), text)
+        }
+        Instruction::Transfer { kind, value } => {
+            let text = match (kind, value) {
+                (TransferKind::Return, Some(_)) => "Transfer return",
+                (TransferKind::Return, None) => "Transfer return (no value)",
+                (TransferKind::Break, _) => "Transfer break",
+                (TransferKind::Continue, _) => "Transfer continue",
+            };
+            (Some(7), text.to_string())
+        }
+        Instruction::Literal(value) => (None, format!("Literal {}", literal(value))),
+        Instruction::Variable(name) => (None, format!("Variable {}", name)),
+        Instruction::Loop { .. } => (None, "Loop while".to_string()),
+        Instruction::UntilLoop { .. } => (None, "Loop until (body runs first)".to_string()),
+        Instruction::ForLoop { var, .. } => (None, format!("ForLoop {}", var)),
+        Instruction::FunctionDef { name, params, doc, .. } => {
+            let mut text = format!("FunctionDef {}({})", name, params.join(", "));
+            if let Some(doc) = doc {
+                let _ = write!(text, " doc {:?}", doc);
+            }
+            (None, text)
+        }
+        Instruction::IndexedAssign { name, .. } => (None, format!("IndexedAssign {}[]", name)),
+        Instruction::SetMemoization { enabled } => (None, format!("SetMemoization {}", enabled)),
+    }
+}
+
+/// Child instructions grouped under the label they are listed with
+fn operands(instr: &Instruction) -> Vec<(&'static str, Vec<InstrId>)> {
+    match instr {
+        Instruction::Sequence(items, _) => vec![("", items.clone())],
+        Instruction::Scope(inner) => vec![("", vec![*inner])],
+        Instruction::Branch { condition, then_instr, else_instr } => {
+            let mut groups = vec![("condition", vec![*condition]), ("then", vec![*then_instr])];
+            if let Some(else_instr) = else_instr {
+                groups.push(("else", vec![*else_instr]));
+            }
+            groups
+        }
+        Instruction::Assign { value, .. } => vec![("", vec![*value])],
+        Instruction::Invoke { args, .. } => vec![("", args.clone())],
+        Instruction::Operate { operands, .. } => vec![("", operands.clone())],
+        Instruction::Transfer { value, .. } => value.iter().map(|v| ("", vec![*v])).collect(),
+        Instruction::Loop { condition, body } | Instruction::UntilLoop { condition, body } => {
+            vec![("condition", vec![*condition]), ("body", vec![*body])]
+        }
+        Instruction::ForLoop { iterable, body, .. } => vec![("in", vec![*iterable]), ("body", vec![*body])],
+        Instruction::FunctionDef { body, .. } => vec![("", vec![*body])],
+        Instruction::IndexedAssign { index, value, .. } => vec![("index", vec![*index]), ("value", vec![*value])],
+        Instruction::Literal(_) | Instruction::Variable(_) | Instruction::SetMemoization { .. } => Vec::new(),
+    }
+}
+
+/// Value kind and its operand details (exact fractions for rationals and reals)
+fn literal(value: &Value) -> String {
+    match value {
+        Value::Number(n) => format!("Number {}", n),
+        Value::Rational { numerator, denominator } => format!("Rational {}/{}", numerator, denominator),
+        Value::Real { numerator, denominator, precision } => {
+            format!("Real {} (= {}/{}, precision {})", value, numerator, denominator, precision)
+        }
+        Value::String(s) => format!("String {:?}", s),
+        Value::Bool(b) => format!("Bool {}", b),
+        Value::Null => "Null".to_string(),
+        Value::Kind(_) => format!("Kind {}", value),
+        Value::Symbol(s) => format!("Symbol {}", s),
+        other => format!("{:?}", other),
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::parse_program;
+    use crate::languages::lumen_schema;
+
+    #[test]
+    fn lists_tree_with_primitive_numbers() {
+        let source = "total = 0\nif total < 3\n    total = total + 1\n";
+        let program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+        let listing = disassemble(&program);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[0], "[1] Sequence (2 items)");
+        assert_eq!(lines[1], "  [4] Assign total");
+        assert_eq!(lines[2], "    Literal Number 0");
+        assert_eq!(lines[3], "  [3] Branch (no else)");
+        assert_eq!(lines[4], "    condition:");
+        assert_eq!(lines[5], "      [6] Operate binary '<'");
+        assert!(listing.ends_with("; primitives: Sequence 2, Scope 0, Branch 1, Assign 2, Invoke 0, Operate 2, Transfer 0\n"), "{}", listing);
+    }
+}
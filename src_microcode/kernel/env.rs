@@ -2,24 +2,82 @@
 //
 // Minimal, explicit scope stack.
 // No special semantics - just name lookup.
+//
+// A scope keeps the names its layout lists (see resolve.rs) in slots, and any other
+// bindings by name. Resolved reads and assignments index a slot; everything else
+// searches the scopes by name, slots included, so both see the same bindings.
 
 use crate::kernel::eval::Value;
-use crate::kernel::primitives::Instruction;
-use std::collections::HashMap;
+use crate::kernel::extern_cache::ExternCache;
+use crate::kernel::extern_timeout::ExternTimeouts;
+use crate::kernel::limits::Usage;
+use crate::kernel::memo_cache::MemoCache;
+use crate::kernel::net_backend::Sockets;
+use crate::kernel::primitives::{InstrId, Program};
+use crate::kernel::resolve::{self, Layout, Resolution};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Metadata about a function
 #[derive(Clone, Debug)]
 pub struct FunctionMetadata {
     pub params: Vec<String>,
-    pub body: Instruction,
+    pub body: InstrId,
+    pub doc: Option<String>,
+    /// Slots of the scope its calls run in
+    pub layout: Layout,
 }
 
-/// Cache key: (function_name, argument_hashes)
-/// Using hashes of arguments for stable key generation
-type CacheKey = (String, String);
-
 /// A single scope frame
-type Scope = HashMap<String, Value>;
+#[derive(Debug)]
+struct Scope {
+    layout: Layout,
+    /// Values of the layout's names (None: not bound yet)
+    slots: Vec<Option<Value>>,
+    /// Bindings of names outside the layout (e.g. those the host binds before the run)
+    names: HashMap<String, Value>,
+}
+
+impl Scope {
+    fn new(layout: Layout) -> Self {
+        Scope { slots: vec![None; layout.len()], layout, names: HashMap::new() }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.layout.iter().position(|bound| bound == name)
+    }
+
+    fn get(&self, name: &str) -> Option<&Value> {
+        match self.position(name) {
+            Some(index) => self.slots[index].as_ref(),
+            None => self.names.get(name),
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match self.position(name) {
+            Some(index) => self.slots[index].as_mut(),
+            None => self.names.get_mut(name),
+        }
+    }
+
+    fn set(&mut self, name: String, value: Value) {
+        match self.position(&name) {
+            Some(index) => self.slots[index] = Some(value),
+            None => {
+                self.names.insert(name, value);
+            }
+        }
+    }
+
+    /// The value in a resolved slot, if `name` is the one the slot was laid out for
+    fn slot(&self, index: usize, name: &str) -> Option<&Value> {
+        match self.layout.get(index) {
+            Some(bound) if bound == name => self.slots[index].as_ref(),
+            _ => None,
+        }
+    }
+}
 
 /// Environment: stack of scopes
 /// Top of stack is current scope.
@@ -28,11 +86,30 @@ pub struct Environment {
     /// Store function metadata (params, body)
     pub functions: HashMap<String, FunctionMetadata>,
     /// Call cache: (function_name, argument_values_repr) -> result
-    /// Only populated when MEMOIZATION is enabled
-    call_cache: HashMap<CacheKey, Value>,
+    /// Only populated when MEMOIZATION is enabled (see lib_lumen/memo_cache.rs)
+    pub call_cache: MemoCache<Value>,
     /// MEMOIZATION state stack (dynamically scoped)
     /// Allows dynamic scoping with proper nesting
     memoization_stack: Vec<bool>,
+    /// Extern mock layers: selector -> handler function name
+    /// The topmost layer that knows a selector shadows the layers below and the real capability.
+    extern_mocks: Vec<HashMap<String, String>>,
+    /// Results of the selectors marked with cache_extern (see lib_lumen/extern_cache.rs)
+    pub extern_cache: ExternCache<Value>,
+    /// Timeouts the program set with extern_timeout (the host's are in its limits)
+    pub extern_timeouts: ExternTimeouts,
+    /// Results of extern_async calls no extern_await has taken yet, by handle name,
+    /// and how many calls were started
+    extern_calls: (u64, HashMap<String, Result<Value, String>>),
+    /// The connections and listeners the program opened with net:*, closed when the run ends
+    pub sockets: Arc<Sockets>,
+    /// Names bound by the system or host (ARGS, FEATURES, --define constants)
+    /// User code may read them but never rebind them.
+    protected: HashSet<String>,
+    /// Call depth and steps so far, checked against --max-depth / --max-steps
+    pub usage: Usage,
+    /// Slots of the running program's names
+    resolution: Resolution,
 }
 
 impl Environment {
@@ -40,10 +117,57 @@ impl Environment {
     /// MEMOIZATION is disabled by default (MEMOIZATION = false)
     pub fn new() -> Self {
         Environment {
-            scopes: vec![HashMap::new()],
+            scopes: vec![Scope::new(Layout::from([]))],
             functions: HashMap::new(),
-            call_cache: HashMap::new(),
+            call_cache: MemoCache::new(),
             memoization_stack: vec![false], // Default: MEMOIZATION = false
+            extern_mocks: Vec::new(),
+            extern_cache: ExternCache::new(),
+            extern_timeouts: ExternTimeouts::default(),
+            extern_calls: (0, HashMap::new()),
+            sockets: Arc::default(),
+            protected: HashSet::new(),
+            usage: Usage::default(),
+            resolution: Resolution::default(),
+        }
+    }
+
+    /// Resolve the names of the program about to run and lay out the global scope
+    /// Bindings made so far (by the host) are kept. Returns how many names were resolved.
+    pub fn resolve(&mut self, program: &Program) -> usize {
+        self.resolution = resolve::resolve(program);
+        let old = std::mem::replace(&mut self.scopes[0], Scope::new(self.resolution.global.clone()));
+        let bindings = old.layout.iter().cloned().zip(old.slots).filter_map(|(name, value)| Some((name, value?)));
+        for (name, value) in bindings.chain(old.names) {
+            self.scopes[0].set(name, value);
+        }
+        self.resolution.resolved()
+    }
+
+    /// The slots of the scope a FunctionDef's calls or a Scope instruction run in
+    pub fn layout(&self, owner: InstrId) -> Layout {
+        self.resolution.layout(owner)
+    }
+
+    /// Read the name of a Variable instruction: from its resolved slot, or by name
+    pub fn lookup(&self, instr: InstrId, name: &str) -> Result<Value, String> {
+        if let Some(slot) = self.resolution.slot(instr) {
+            let scope = self.scopes.len().checked_sub(slot.depth + 1).and_then(|at| self.scopes.get(at));
+            if let Some(value) = scope.and_then(|scope| scope.slot(slot.index, name)) {
+                return Ok(value.clone());
+            }
+        }
+        self.get(name)
+    }
+
+    /// Bind the name of an Assign or ForLoop instruction in the current scope
+    pub fn assign(&mut self, instr: InstrId, name: &str, value: Value) {
+        let scope = self.scopes.last_mut().expect("the global scope is never popped");
+        match self.resolution.slot(instr) {
+            Some(slot) if slot.depth == 0 && scope.layout.get(slot.index).is_some_and(|bound| bound == name) => {
+                scope.slots[slot.index] = Some(value);
+            }
+            _ => scope.set(name.to_string(), value),
         }
     }
 
@@ -61,12 +185,11 @@ impl Environment {
     }
 
     /// Get cached result for a function call (if MEMOIZATION enabled and cached)
-    pub fn get_cached(&self, func_name: &str, args: &[Value]) -> Option<Value> {
+    pub fn get_cached(&mut self, func_name: &str, args: &[Value]) -> Option<Value> {
         if !self.memoization_enabled() {
             return None;
         }
-        let cache_key = (func_name.to_string(), Self::args_to_key(args));
-        self.call_cache.get(&cache_key).cloned()
+        self.call_cache.get(func_name, Self::args_to_key(args))
     }
 
     /// Cache a function result (only if MEMOIZATION is enabled)
@@ -74,8 +197,23 @@ impl Environment {
         if !self.memoization_enabled() {
             return;
         }
-        let cache_key = (func_name.to_string(), Self::args_to_key(args));
-        self.call_cache.insert(cache_key, result);
+        self.call_cache.insert(func_name, Self::args_to_key(args), result);
+    }
+
+    /// The result an earlier call of `selector` with these arguments gave, if it is cacheable
+    pub fn cached_extern(&self, selector: &str, args: &[Value]) -> Option<Value> {
+        if !self.extern_cache.is_cacheable(selector) {
+            return None;
+        }
+        self.extern_cache.get(selector, &Self::args_to_key(args))
+    }
+
+    /// Keep the result of an extern call (only if its selector is cacheable)
+    pub fn cache_extern_result(&mut self, selector: &str, args: &[Value], result: Value) {
+        if !self.extern_cache.is_cacheable(selector) {
+            return;
+        }
+        self.extern_cache.insert(selector, Self::args_to_key(args), result);
     }
 
     /// Generate a stable cache key from argument values
@@ -88,9 +226,64 @@ impl Environment {
             .join("|")
     }
 
-    /// Push new scope (inherits memoization state)
-    pub fn push_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+    /// Bind a read-only name in the global scope
+    pub fn set_protected(&mut self, name: String, value: Value) {
+        self.protected.insert(name.clone());
+        self.set(name, value);
+    }
+
+    /// Check whether a name is read-only for user code
+    pub fn is_protected(&self, name: &str) -> bool {
+        self.protected.contains(name)
+    }
+
+    /// Start a new extern mock layer
+    pub fn push_mock_layer(&mut self) {
+        self.extern_mocks.push(HashMap::new());
+    }
+
+    /// Discard the topmost extern mock layer (false if there was none)
+    pub fn pop_mock_layer(&mut self) -> bool {
+        self.extern_mocks.pop().is_some()
+    }
+
+    /// Register a mock handler in the topmost layer (a layer is created if none is active)
+    pub fn mock_extern(&mut self, selector: &str, handler: &str) {
+        if self.extern_mocks.is_empty() {
+            self.push_mock_layer();
+        }
+        if let Some(layer) = self.extern_mocks.last_mut() {
+            layer.insert(selector.to_string(), handler.to_string());
+        }
+    }
+
+    /// Find the mock handler for a selector, searching from the top layer down
+    pub fn resolve_mock(&self, selector: &str) -> Option<String> {
+        self.extern_mocks
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get(selector).cloned())
+    }
+
+    /// Keep the result of an extern_async call; returns its handle name
+    pub fn finish_extern_call(&mut self, selector: &str, result: Result<Value, String>) -> String {
+        self.extern_calls.0 += 1;
+        let handle = format!("<extern_async #{} {}>", self.extern_calls.0, selector);
+        self.extern_calls.1.insert(handle.clone(), result);
+        handle
+    }
+
+    /// Take the result of the extern_async call `handle` names (each is taken once)
+    pub fn await_extern_call(&mut self, handle: &str) -> Result<Value, String> {
+        self.extern_calls
+            .1
+            .remove(handle)
+            .ok_or_else(|| format!("extern_await(): {} is not a pending call (each is awaited once)", handle))?
+    }
+
+    /// Push new scope with slots for `layout` (inherits memoization state)
+    pub fn push_scope(&mut self, layout: Layout) {
+        self.scopes.push(Scope::new(layout));
         // Inherit current memoization state
         let current_memo = self.memoization_enabled();
         self.memoization_stack.push(current_memo);
@@ -109,7 +302,7 @@ impl Environment {
     /// Set binding in current scope
     pub fn set(&mut self, name: String, value: Value) {
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, value);
+            scope.set(name, value);
         }
     }
 
@@ -124,9 +317,38 @@ impl Environment {
         Err(format!("Undefined variable: {}", name))
     }
 
+    /// Count a loop iteration against the limits
+    pub fn step(&mut self) -> Result<(), String> {
+        self.usage.step()?;
+        self.check_heap()
+    }
+
+    /// Count a call against the limits; pair with usage.leave_call when it returns
+    pub fn enter_call(&mut self) -> Result<(), String> {
+        self.usage.enter_call()?;
+        self.check_heap()
+    }
+
+    /// Check the values held in variables against --max-heap, when it is time to
+    fn check_heap(&self) -> Result<(), String> {
+        if !self.usage.heap_check_due() {
+            return Ok(());
+        }
+        let bytes = self
+            .scopes
+            .iter()
+            .flat_map(|scope| {
+                let slots = scope.layout.iter().zip(&scope.slots).filter_map(|(name, value)| Some((name, value.as_ref()?)));
+                slots.chain(&scope.names)
+            })
+            .map(|(name, value)| name.len() + value.heap_bytes())
+            .sum();
+        self.usage.check_heap(bytes)
+    }
+
     /// Check if name exists in any scope
     pub fn exists(&self, name: &str) -> bool {
-        self.scopes.iter().rev().any(|scope| scope.contains_key(name))
+        self.scopes.iter().rev().any(|scope| scope.get(name).is_some())
     }
 
     /// Mutate array element at index (search from innermost scope upward)
@@ -3,13 +3,17 @@
 // Language-agnostic representation of all possible values.
 // No language-specific behavior here.
 
+use std::borrow::Cow;
 use std::fmt;
 use num_bigint::BigInt;
-use num_traits::Signed;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 
-/// Kind meta-value enum - the 7 possible runtime type descriptors
+use super::repr::{self, Node};
+
+/// Kind meta-value enum - the 10 possible runtime type descriptors
 /// These form a closed set defined by the kernel
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum KindValue {
     INTEGER,
     RATIONAL,
@@ -17,12 +21,15 @@ pub enum KindValue {
     STRING,
     BOOLEAN,
     ARRAY,
+    MAP,
     NULL,
+    HANDLE,
+    BYTES,
 }
 
 /// Runtime value
 /// These are the only things that exist at runtime.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Value {
     Number(BigInt),
     Rational {
@@ -35,6 +42,7 @@ pub enum Value {
         precision: usize, // Number of significant digits
     },
     String(String),
+    Bytes(Vec<u8>),  // Binary data (io:read_bytes, bytes()); kind BYTES
     Bool(bool),
     Null,
     Range {
@@ -42,12 +50,13 @@ pub enum Value {
         end: BigInt,
     },
     Array(Vec<Value>),
+    Map(Vec<(String, Value)>),  // String-keyed entries in insertion order
     Function {
         params: Vec<String>,
         // Body is stored as-is, execution happens in the execute layer
         body_ref: String,  // reference to function registry, not the body itself
     },
-    Symbol(String),  // Symbolic constant (deprecated, use Kind instead)
+    Symbol(String),  // Opaque handle from an extern backend (net connections); kind HANDLE
     Kind(KindValue),  // Kernel-level type descriptor meta-value
 }
 
@@ -66,37 +75,8 @@ impl fmt::Display for Value {
                 }
             }
             Value::Real { numerator, denominator, precision } => {
-                // For real, display the decimal representation with significant digit precision
-                let int_part = numerator / denominator;
-                let remainder = numerator.clone() - (&int_part * denominator);
-                if remainder == BigInt::from(0) {
-                    write!(f, "{}", int_part)
-                } else {
-                    // Compute decimal places needed for precision
-                    let mut decimal_str = String::new();
-                    let digit_count = int_part.to_string().len();
-                    let target_digits = *precision;
-                    let mut rem = remainder.abs();
-
-                    // If int part has fewer digits than target, include fractional digits
-                    let mut frac_digits = if digit_count >= target_digits {
-                        0
-                    } else {
-                        target_digits - digit_count
-                    };
-
-                    // Compute fractional part
-                    let denom = denominator.clone();
-                    while frac_digits > 0 && rem > BigInt::from(0) {
-                        rem = rem * BigInt::from(10);
-                        let digit = &rem / &denom;
-                        decimal_str.push_str(&digit.to_string());
-                        rem = &rem - (&digit * &denom);
-                        frac_digits -= 1;
-                    }
-
-                    write!(f, "{}.{}", int_part, decimal_str)
-                }
+                // Decimal notation rounded to the precision, or inf or nan (see lib_lumen/bigfloat.rs)
+                write!(f, "{}", super::bigfloat::decimal_string(numerator, denominator, *precision))
             }
             Value::String(s) => write!(f, "{}", s),
             Value::Bool(b) => write!(f, "{}", if *b { "true" } else { "false" }),
@@ -104,16 +84,7 @@ impl fmt::Display for Value {
             Value::Range { start, end } => {
                 write!(f, "{}..{}", start, end)
             }
-            Value::Array(elements) => {
-                write!(f, "[")?;
-                for (i, elem) in elements.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{}", elem)?;
-                }
-                write!(f, "]")
-            }
+            Value::Bytes(_) | Value::Array(_) | Value::Map(_) => f.write_str(&repr::render(&self.node(), &repr::DISPLAY)),
             Value::Function { params, body_ref: _ } => {
                 write!(f, "<function({})>", params.join(", "))
             }
@@ -126,7 +97,10 @@ impl fmt::Display for Value {
                     KindValue::STRING => "STRING",
                     KindValue::BOOLEAN => "BOOLEAN",
                     KindValue::ARRAY => "ARRAY",
+                    KindValue::MAP => "MAP",
                     KindValue::NULL => "NULL",
+                    KindValue::HANDLE => "HANDLE",
+                    KindValue::BYTES => "BYTES",
                 };
                 write!(f, "{}", name)
             }
@@ -143,25 +117,27 @@ impl PartialEq for Value {
                 a_num * b_denom == b_num * a_denom
             }
             (Value::Real { numerator: a_num, denominator: a_denom, .. }, Value::Real { numerator: b_num, denominator: b_denom, .. }) => {
-                // Compare exact rational values
-                a_num * b_denom == b_num * a_denom
+                // Compare exact rational values (inf and nan as lib_lumen/bigfloat.rs says: nan equals nothing)
+                super::bigfloat::comparison("==", (a_num, a_denom), (b_num, b_denom)) == Some(true)
             }
             // Allow comparison between real and rational/number
             (Value::Real { numerator: r_num, denominator: r_denom, .. }, Value::Rational { numerator: q_num, denominator: q_denom }) |
             (Value::Rational { numerator: q_num, denominator: q_denom }, Value::Real { numerator: r_num, denominator: r_denom, .. }) => {
-                r_num * q_denom == q_num * r_denom
+                r_num * q_denom == q_num * r_denom && !r_denom.is_zero()
             }
             (Value::Real { numerator: r_num, denominator: r_denom, .. }, Value::Number(n)) |
             (Value::Number(n), Value::Real { numerator: r_num, denominator: r_denom, .. }) => {
                 r_num == n && r_denom == &BigInt::from(1)
             }
             (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
             (Value::Bool(a), Value::Bool(b)) => a == b,
             (Value::Null, Value::Null) => true,
             (Value::Range { start: a_start, end: a_end }, Value::Range { start: b_start, end: b_end }) => {
                 a_start == b_start && a_end == b_end
             }
             (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
             (Value::Symbol(a), Value::Symbol(b)) => a == b,
             (Value::Kind(a), Value::Kind(b)) => a == b,
             _ => false,
@@ -170,6 +146,49 @@ impl PartialEq for Value {
 }
 
 impl Value {
+    /// The value as lib_lumen/repr.rs renders it
+    pub fn node(&self) -> Node<'_> {
+        match self {
+            Value::String(s) => Node::Text(s),
+            Value::Bytes(bytes) => Node::Bytes(bytes),
+            Value::Array(elements) => Node::List(elements.iter().map(Value::node).collect()),
+            Value::Map(entries) => Node::Map(entries.iter().map(|(key, value)| (key.as_str(), value.node())).collect()),
+            other => Node::Atom(Cow::Owned(other.to_string())),
+        }
+    }
+
+    /// What repr() returns: strings quoted, huge values shortened
+    pub fn repr(&self) -> String {
+        repr::render(&self.node(), &repr::REPR)
+    }
+
+    /// Rough size in bytes of the value and everything it owns (for --max-heap)
+    pub fn heap_bytes(&self) -> usize {
+        let digits = |n: &BigInt| (n.bits() / 8) as usize;
+        std::mem::size_of::<Value>()
+            + match self {
+                Value::Number(n) => digits(n),
+                Value::Rational { numerator, denominator } | Value::Real { numerator, denominator, .. } => {
+                    digits(numerator) + digits(denominator)
+                }
+                Value::Range { start, end } => digits(start) + digits(end),
+                Value::String(s) | Value::Symbol(s) => s.len(),
+                Value::Bytes(bytes) => bytes.len(),
+                Value::Array(items) => items.iter().map(Value::heap_bytes).sum(),
+                Value::Map(entries) => entries.iter().map(|(key, value)| key.len() + value.heap_bytes()).sum(),
+                Value::Function { params, body_ref } => params.iter().map(String::len).sum::<usize>() + body_ref.len(),
+                Value::Bool(_) | Value::Null | Value::Kind(_) => 0,
+            }
+    }
+
+    /// Look up a key in a map value
+    pub fn map_get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
     /// Coerce to boolean (language-agnostic rules)
     pub fn to_bool(&self) -> bool {
         match self {
@@ -179,8 +198,10 @@ impl Value {
             Value::Rational { numerator, .. } => numerator != &BigInt::from(0),
             Value::Real { numerator, .. } => numerator != &BigInt::from(0),
             Value::String(s) => !s.is_empty(),
+            Value::Bytes(bytes) => !bytes.is_empty(),
             Value::Range { .. } => true,
             Value::Array(_) => true,
+            Value::Map(_) => true,
             Value::Function { .. } => true,
             Value::Symbol(_) => true,
             Value::Kind(_) => true,
@@ -193,16 +214,18 @@ impl Value {
             Value::Number(n) => Ok(n.clone()),
             Value::Rational { .. } => Err("Cannot coerce rational to integer".to_string()),
             Value::Real { numerator, denominator, .. } => {
-                // Truncate toward zero: integer division
-                Ok(numerator / denominator)
+                // Truncate toward zero: integer division (inf and nan have no integer part)
+                super::bigfloat::integer_part(numerator, denominator)
             }
             Value::Bool(true) => Ok(BigInt::from(1)),
             Value::Bool(false) => Ok(BigInt::from(0)),
             Value::Null => Ok(BigInt::from(0)),
             Value::String(s) => s.parse::<BigInt>()
                 .map_err(|_| format!("Cannot coerce '{}' to number", s)),
+            Value::Bytes(_) => Err("Cannot coerce bytes to number".to_string()),
             Value::Range { .. } => Err("Cannot coerce range to number".to_string()),
             Value::Array(_) => Err("Cannot coerce array to number".to_string()),
+            Value::Map(_) => Err("Cannot coerce map to number".to_string()),
             Value::Function { .. } => Err("Cannot coerce function to number".to_string()),
             Value::Symbol(_) => Err("Cannot coerce symbol to number".to_string()),
             Value::Kind(_) => Err("Cannot coerce kind meta-value to number".to_string()),
@@ -0,0 +1,225 @@
+// Incremental reparsing: stages 1-3 again for only the part of a buffer that changed
+//
+// An editor or REPL parses the same buffer over and over, and an edit usually leaves
+// everything above it alone. IncrementalParser keeps the text and the program it last
+// parsed; given the new text, it keeps the top-level statements that start on a line
+// before the first changed line, except the last of them (the edit may continue it,
+// e.g. an `else` added after an `if`), and reduces only the rest of the text, from the
+// first line of the earliest statement it does not keep. The two parts are joined with
+// Program::followed_by, so the result is the program parse_program gives for the whole
+// text, positions included.
+//
+// A statement is only a place to cut when it starts a line (column 1) and no earlier
+// statement shares that line, so the text after the cut is a whole number of top-level
+// statements. A buffer that does not parse leaves the parser as it was, and the next
+// text is compared with the last one that did.
+//
+// A Lumen program runs after the prelude, so its lines are numbered from the line after
+// the prelude's (see reduce_with_prelude_timed); a parser made with starting_at numbers
+// them the same way. The microcode binary keeps the last text and program of each input in
+// its --cache and resumes a parser from them, so the REPL and watch, which run it again
+// for every entry or save, reduce only what changed.
+
+use super::control_flow::LocatedError;
+use super::primitives::{Instruction, Program};
+use super::{ingest, reduce, structure};
+use crate::schema::LanguageSchema;
+
+/// A buffer's last parsed text and program, for reparsing its next version
+/// One parser is for one language: use a new one when the schema changes.
+#[derive(Debug, Clone)]
+pub struct IncrementalParser {
+    source: String,
+    program: Option<Program>,
+    reused: usize,
+    /// The line the text starts on
+    first_line: usize,
+}
+
+impl Default for IncrementalParser {
+    fn default() -> Self {
+        Self::starting_at(1)
+    }
+}
+
+impl IncrementalParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A parser for text that starts on line `first_line` (after a prelude)
+    pub fn starting_at(first_line: usize) -> Self {
+        IncrementalParser { source: String::new(), program: None, reused: 0, first_line }
+    }
+
+    /// A parser whose last parse gave `program` for `source` (e.g. in an earlier run)
+    /// `program` must be the one parse_program gives for `source`, numbered from `first_line`.
+    pub fn resume(first_line: usize, source: String, program: Program) -> Self {
+        IncrementalParser { source, program: Some(program), reused: 0, first_line }
+    }
+
+    /// The program of `source`, reusing what is unchanged since the last parse
+    pub fn parse(&mut self, source: &str, schema: &LanguageSchema) -> Result<&Program, Vec<LocatedError>> {
+        let (statements, offset, line) = self.reusable(source);
+        let rest = &source[offset..];
+        let tokens = ingest::lex_from(rest, schema, line)?;
+        let tokens = structure::process_structure(tokens, schema)?;
+        let reduced = reduce::parse(tokens, schema)?;
+
+        let program = match (&self.program, statements) {
+            (Some(program), 1..) => first_statements(program, statements).followed_by(&reduced),
+            _ => reduced,
+        };
+        self.source = source.to_string();
+        self.reused = statements;
+        Ok(self.program.insert(program))
+    }
+
+    /// The program of the last text that parsed, if any
+    pub fn program(&self) -> Option<&Program> {
+        self.program.as_ref()
+    }
+
+    /// The last text that parsed
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Top-level statements the last parse kept from the one before it
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+
+    /// How many top-level statements of the last program `source` still has, and the
+    /// byte offset and line where the text after them starts
+    fn reusable(&self, source: &str) -> (usize, usize, usize) {
+        let first_line = self.first_line;
+        let Some(program) = &self.program else {
+            return (0, 0, first_line);
+        };
+        let Instruction::Sequence(_, positions) = &program[program.root()] else {
+            return (0, 0, first_line);
+        };
+
+        let mut unchanged = self.source.bytes().zip(source.bytes()).take_while(|(old, new)| old == new).count();
+        while !source.is_char_boundary(unchanged) {
+            unchanged -= 1;
+        }
+        let changed_line = first_line + source[..unchanged].matches('\n').count();
+
+        // The last statement that starts a line before the change, reparsed with the rest
+        let Some(cut) = (0..positions.len()).rev().find(|&index| {
+            let position = positions[index];
+            position.line < changed_line
+                && position.col == 1
+                && (index == 0 || positions[index - 1].line < position.line)
+        }) else {
+            return (0, 0, first_line);
+        };
+        let line = positions[cut].line;
+        let offset = match line - first_line {
+            0 => 0,
+            lines => source.match_indices('\n').nth(lines - 1).map_or(0, |(index, _)| index + 1),
+        };
+        (cut, offset, line)
+    }
+}
+
+/// A program of the first `count` top-level statements of `program`
+fn first_statements(program: &Program, count: usize) -> Program {
+    let Instruction::Sequence(items, positions) = &program[program.root()] else {
+        unreachable!("a reduced program has a sequence at the root");
+    };
+    let sequence = Instruction::sequence(items[..count].to_vec(), positions[..count].to_vec());
+    let mut program = program.clone();
+    let root = program.add(sequence);
+    program.extract(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::parse_program;
+    use crate::languages;
+
+    /// The reachable instructions of `program` (reduce leaves a few unused ones behind
+    /// when it backtracks, and a reparse does not repeat them)
+    fn tree(program: &Program) -> String {
+        format!("{:?}", program.extract(program.root()))
+    }
+
+    #[test]
+    fn reparses_from_the_statement_before_the_change() {
+        let schema = languages::lumen_schema::get_schema();
+        let mut parser = IncrementalParser::new();
+        let steps = [
+            "fn double(n)\n    return n * 2\nx = 1\nif x > 0\n    print(x)\n",
+            // Appended: the `if` may be continued, so only the first two are kept
+            "fn double(n)\n    return n * 2\nx = 1\nif x > 0\n    print(x)\nelse\n    print(0)\n",
+            "fn double(n)\n    return n * 2\nx = 1\nif x > 0\n    print(x)\nelse\n    print(0)\ny = double(x)\n",
+            // Changed inside the first statement: nothing is kept
+            "fn double(n)\n    return n + n\nx = 1\nif x > 0\n    print(x)\nelse\n    print(0)\ny = double(x)\n",
+            // Statements sharing a line are kept or reparsed together
+            "fn double(n)\n    return n + n\nx = 1; z = 2\n",
+            "fn double(n)\n    return n + n\nx = 1; z = 2\nw = 3\n",
+        ];
+        let reused = [0, 2, 2, 0, 0, 1];
+        for (source, reused) in steps.into_iter().zip(reused) {
+            let program = tree(parser.parse(source, &schema).unwrap());
+            assert_eq!(program, tree(&parse_program(source, &schema).unwrap()), "{}", source);
+            assert_eq!(parser.reused(), reused, "{}", source);
+        }
+
+        // A text that does not parse leaves the last program in place
+        assert!(parser.parse("fn double(n)\n    return n + n\nx = (1\n", &schema).is_err());
+        assert_eq!(parser.reused(), 1);
+        assert!(parser.program().is_some());
+    }
+
+    #[test]
+    fn numbers_lines_after_a_prelude_and_resumes_a_stored_parse() {
+        let schema = languages::lumen_schema::get_schema();
+        let first = "x = 1\nif x > 0\n    print(x)\n";
+        let second = "x = 1\nif x > 0\n    print(x)\ny = x\n";
+        let whole = |source: &str| {
+            let program = super::super::reduce_timed(source, 40, &schema, &mut super::super::Timing::start()).unwrap();
+            tree(&program)
+        };
+
+        let mut parser = IncrementalParser::starting_at(40);
+        assert_eq!(tree(parser.parse(first, &schema).unwrap()), whole(first));
+        let stored = (parser.source().to_string(), parser.program().unwrap().clone());
+
+        // As the next run would, from what this one stored
+        let mut parser = IncrementalParser::resume(40, stored.0, stored.1);
+        assert_eq!(tree(parser.parse(second, &schema).unwrap()), whole(second));
+        assert_eq!(parser.reused(), 1);
+        assert!(parser.parse("x = 1\nif x > 0\n    print(x))\n", &schema).unwrap_err()[0].positions[0].0 == 42);
+    }
+
+    #[test]
+    fn gives_the_whole_parse_for_every_edit_of_the_examples() {
+        for language in languages::all() {
+            let schema = language.get_schema();
+            let mut parser = IncrementalParser::new();
+            let dir = format!("{}/examples/{}", env!("CARGO_MANIFEST_DIR"), language.name);
+            let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+            for entry in entries {
+                let Ok(source) = std::fs::read_to_string(entry.unwrap().path()) else { continue };
+                let Ok(whole) = parse_program(&source, &schema) else { continue };
+                // Type the file in a line at a time; every prefix that parses must match
+                let mut typed = String::new();
+                for line in source.split_inclusive('\n') {
+                    typed.push_str(line);
+                    let expected = parse_program(&typed, &schema).map(|program| tree(&program));
+                    let actual = parser.parse(&typed, &schema).map(tree);
+                    assert_eq!(actual.is_ok(), expected.is_ok(), "{}", typed);
+                    if let (Ok(actual), Ok(expected)) = (actual, expected) {
+                        assert_eq!(actual, expected, "{}", typed);
+                    }
+                }
+                assert_eq!(tree(parser.program().unwrap()), tree(&whole));
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+// Static linter over the instruction tree
+//
+// Runs on the output of stage 3 (reduce) and never executes anything.
+// Rules can be enabled or disabled individually:
+//
+//   unused-variable     a variable or parameter that is assigned but never read
+//   unreachable-code    statements after return/break/continue in the same block
+//   shadowed-name       a function parameter or local with the same name as a global
+//                       (assignment inside a function always binds a new local)
+//   constant-condition  an if/while/until condition built only from literals
+//                       (`while true` is allowed as the idiomatic endless loop)
+//
+// Names starting with '_' are never reported as unused.
+
+use super::eval::Value;
+use super::primitives::{InstrId, Instruction, Program, TransferKind};
+use std::collections::HashSet;
+use std::fmt;
+
+/// A lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    UnusedVariable,
+    UnreachableCode,
+    ShadowedName,
+    ConstantCondition,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 4] = [
+        Rule::UnusedVariable,
+        Rule::UnreachableCode,
+        Rule::ShadowedName,
+        Rule::ConstantCondition,
+    ];
+
+    /// Name used on the command line and in reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::ShadowedName => "shadowed-name",
+            Rule::ConstantCondition => "constant-condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+/// One lint finding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.rule.name(), self.message)
+    }
+}
+
+/// Names bound and read in one scope (the program top level or one function body)
+#[derive(Default)]
+struct Names {
+    /// Bound names in first-binding order (parameters come first in functions)
+    bound: Vec<String>,
+    read: HashSet<String>,
+}
+
+impl Names {
+    fn bind(&mut self, name: &str) {
+        if !self.bound.iter().any(|n| n == name) {
+            self.bound.push(name.to_string());
+        }
+    }
+}
+
+/// A function body collected during the walk
+struct Function {
+    name: String,
+    params: Vec<String>,
+    names: Names,
+}
+
+/// Lint a program with the given rules enabled
+pub fn lint(program: &Program, rules: &[Rule]) -> Vec<Finding> {
+    let mut top = Names::default();
+    let mut functions = Vec::new();
+    let mut all_reads = HashSet::new();
+    collect(program, program.root(), &mut top, &mut functions, &mut all_reads);
+
+    let mut findings = Vec::new();
+    let mut report = |rule: Rule, message: String| {
+        if rules.contains(&rule) {
+            findings.push(Finding { rule, message });
+        }
+    };
+
+    // Globals may be read from any function, so they count as used if read anywhere
+    for name in &top.bound {
+        if !name.starts_with('_') && !all_reads.contains(name) {
+            report(Rule::UnusedVariable, format!("variable '{}' is assigned but never used", name));
+        }
+    }
+
+    for function in &functions {
+        for name in &function.names.bound {
+            let kind = if function.params.contains(name) { "parameter" } else { "variable" };
+            if !name.starts_with('_') && !function.names.read.contains(name) {
+                report(
+                    Rule::UnusedVariable,
+                    format!("{} '{}' is never used in function '{}'", kind, name, function.name),
+                );
+            }
+            if top.bound.contains(name) {
+                report(
+                    Rule::ShadowedName,
+                    format!("{} '{}' in function '{}' shadows a global variable", kind, name, function.name),
+                );
+            }
+        }
+    }
+
+    check_flow(program, program.root(), "at top level", &mut report);
+    findings
+}
+
+/// Record bound and read names for the current scope; function bodies get their own scope
+fn collect(
+    program: &Program,
+    id: InstrId,
+    names: &mut Names,
+    functions: &mut Vec<Function>,
+    all_reads: &mut HashSet<String>,
+) {
+    let instr = &program[id];
+    match instr {
+        Instruction::Variable(name) => {
+            names.read.insert(name.clone());
+            all_reads.insert(name.clone());
+        }
+        Instruction::Assign { name, value } => {
+            collect(program, *value, names, functions, all_reads);
+            names.bind(name);
+        }
+        Instruction::IndexedAssign { name, index, value } => {
+            // Mutating an element uses the array binding
+            names.read.insert(name.clone());
+            all_reads.insert(name.clone());
+            collect(program, *index, names, functions, all_reads);
+            collect(program, *value, names, functions, all_reads);
+        }
+        Instruction::ForLoop { var, iterable, body } => {
+            collect(program, *iterable, names, functions, all_reads);
+            names.bind(var);
+            collect(program, *body, names, functions, all_reads);
+        }
+        Instruction::FunctionDef { name, params, body, .. } => {
+            let mut inner = Names::default();
+            for param in params {
+                inner.bind(param);
+            }
+            collect(program, *body, &mut inner, functions, all_reads);
+            functions.push(Function { name: name.clone(), params: params.clone(), names: inner });
+        }
+        _ => {
+            for child in instr.children() {
+                collect(program, child, names, functions, all_reads);
+            }
+        }
+    }
+}
+
+/// Report unreachable statements and constant conditions
+fn check_flow(program: &Program, id: InstrId, context: &str, report: &mut impl FnMut(Rule, String)) {
+    let instr = &program[id];
+    match instr {
+        Instruction::Sequence(items, _) => {
+            if let Some(pos) = items.iter().position(|item| terminator(program, *item).is_some()) {
+                if pos + 1 < items.len() {
+                    let kind = terminator(program, items[pos]).map(transfer_name).unwrap_or("return");
+                    report(Rule::UnreachableCode, format!("unreachable code after {} {}", kind, context));
+                }
+            }
+        }
+        Instruction::Branch { condition, .. } if is_constant(program, *condition) => {
+            report(Rule::ConstantCondition, format!("if condition is always the same {}", context));
+        }
+        Instruction::Loop { condition, .. } => {
+            let endless = matches!(&program[*condition], Instruction::Literal(Value::Bool(true)));
+            if is_constant(program, *condition) && !endless {
+                report(Rule::ConstantCondition, format!("while condition is always the same {}", context));
+            }
+        }
+        Instruction::UntilLoop { condition, .. } if is_constant(program, *condition) => {
+            report(Rule::ConstantCondition, format!("until condition is always the same {}", context));
+        }
+        Instruction::FunctionDef { name, body, .. } => {
+            return check_flow(program, *body, &format!("in function '{}'", name), report);
+        }
+        _ => {}
+    }
+    for child in instr.children() {
+        check_flow(program, child, context, report);
+    }
+}
+
+/// The transfer an instruction always ends with, if every path through it ends in one
+fn terminator(program: &Program, id: InstrId) -> Option<TransferKind> {
+    match &program[id] {
+        Instruction::Transfer { kind, .. } => Some(*kind),
+        Instruction::Sequence(items, _) => items.iter().find_map(|item| terminator(program, *item)),
+        Instruction::Scope(inner) => terminator(program, *inner),
+        Instruction::Branch { then_instr, else_instr: Some(else_instr), .. } => {
+            terminator(program, *then_instr).and(terminator(program, *else_instr))
+        }
+        _ => None,
+    }
+}
+
+fn transfer_name(kind: TransferKind) -> &'static str {
+    match kind {
+        TransferKind::Return => "return",
+        TransferKind::Break => "break",
+        TransferKind::Continue => "continue",
+    }
+}
+
+/// A condition built only from literals and operators
+fn is_constant(program: &Program, id: InstrId) -> bool {
+    match &program[id] {
+        Instruction::Literal(_) => true,
+        Instruction::Operate { operands, .. } => operands.iter().all(|operand| is_constant(program, *operand)),
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::parse_program;
+    use crate::languages::lumen_schema;
+
+    fn lint_source(source: &str, rules: &[Rule]) -> Vec<String> {
+        let program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+        lint(&program, rules).iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn reports_each_rule() {
+        let source = "\
+total = 5
+fn f(total, unused)
+    let local = 1
+    return total
+    print(local)
+if 1 < 2
+    print(f(1, 2))
+while true
+    break
+";
+        let findings = lint_source(source, &Rule::ALL);
+        assert_eq!(
+            findings,
+            vec![
+                "warning[shadowed-name]: parameter 'total' in function 'f' shadows a global variable",
+                "warning[unused-variable]: parameter 'unused' is never used in function 'f'",
+                "warning[unreachable-code]: unreachable code after return in function 'f'",
+                "warning[constant-condition]: if condition is always the same at top level",
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_silent() {
+        let source = "x = 1\nif true\n    print(2)\n";
+        assert_eq!(lint_source(source, &[Rule::ConstantCondition]).len(), 1);
+        assert_eq!(lint_source(source, &[Rule::UnusedVariable]).len(), 1);
+        assert!(lint_source(source, &[Rule::ShadowedName, Rule::UnreachableCode]).is_empty());
+    }
+}
@@ -10,6 +10,162 @@ pub mod primitives;
 pub mod eval;
 pub mod env;
 
+// Transcendental functions for REAL values (exp, ln, log, atan)
+// The series code is shared with the stream kernel.
+pub mod transcendental {
+    include!("../../lib_lumen/transcendental.rs");
+}
+
+// Correctly rounded decimal floating point for REAL values
+// Shared with the stream kernel so both round every REAL result the same way.
+pub mod bigfloat {
+    include!("../../lib_lumen/bigfloat.rs");
+}
+
+// How values are written by print, repr(), debug_info and error messages
+// Shared with the stream kernel so both render arrays, maps and strings the same way.
+pub mod repr {
+    include!("../../lib_lumen/repr.rs");
+}
+
+// The fs extern backend (fs:read, fs:write, ...)
+// Shared with the stream kernel so programs touch files the same way on both.
+pub mod fs_backend {
+    include!("../../lib_lumen/fs_backend.rs");
+}
+
+// The io extern backend (io:read_line, io:read_all, io:read_bytes, io:write_bytes): stdin
+// and stdout for filter-style programs
+pub mod io_backend {
+    include!("../../lib_lumen/io_backend.rs");
+}
+
+// The net extern backend (net:connect, net:listen, ...): TCP clients and servers
+pub mod net_backend {
+    include!("../../lib_lumen/net_backend.rs");
+}
+
+// The random extern backend (random:int, random:float): numbers from the host's entropy
+pub mod random_backend {
+    include!("../../lib_lumen/random_backend.rs");
+}
+
+// The sys extern backend (sys:platform, sys:arch, sys:cpu_count, sys:cwd, sys:pid): the host
+pub mod sys_backend {
+    include!("../../lib_lumen/sys_backend.rs");
+}
+
+// The env extern backend (env:get, env:vars): the environment variables of the process
+pub mod env_backend {
+    include!("../../lib_lumen/env_backend.rs");
+}
+
+// The time extern backend (time:now, time:monotonic_millis, time:format): the clock
+pub mod time_backend {
+    include!("../../lib_lumen/time_backend.rs");
+}
+
+// The arr extern backend (arr:sort_by): array operations that call the program's functions back
+pub mod arr_backend {
+    include!("../../lib_lumen/arr_backend.rs");
+}
+
+// The crypto extern backend (crypto:sha256, crypto:md5, crypto:blake3): hashes of data
+pub mod crypto_backend {
+    include!("../../lib_lumen/crypto_backend.rs");
+}
+
+// The json extern backend (json:parse, json:stringify): JSON text to and from values
+pub mod json_backend {
+    include!("../../lib_lumen/json_backend.rs");
+}
+
+// Break/continue/return rules for loops and function calls
+// Shared with the stream kernel so both handle nested control flow identically.
+pub mod control_flow {
+    include!("../../lib_lumen/control_flow.rs");
+}
+
+// Stage durations and sizes for --timing
+// Shared with the stream kernel so both report in the same formats.
+pub mod timing {
+    include!("../../lib_lumen/timing.rs");
+}
+
+// Stage spans and statement events through the tracing crate (cargo feature "tracing")
+// Shared with the stream kernel so both report the same spans.
+pub mod spans {
+    include!("../../lib_lumen/spans.rs");
+}
+
+// Maximal-munch matching of multi-character lexemes (stage 1)
+// Shared with the stream kernel so both lexers match the same way.
+pub mod lexeme_trie {
+    include!("../../lib_lumen/lexeme_trie.rs");
+}
+
+// The error of a failed extern call (code, message, retriable, errno), for extern_try
+pub mod extern_error {
+    include!("../../lib_lumen/extern_error.rs");
+}
+
+// Extern capabilities a run may reach (--allow-BACKEND, --deny-all), part of its limits
+pub mod extern_policy {
+    include!("../../lib_lumen/extern_policy.rs");
+}
+
+// The log of extern calls (--audit), part of the limits
+pub mod extern_audit {
+    include!("../../lib_lumen/extern_audit.rs");
+}
+
+// How long extern calls may take (--extern-timeout, extern_timeout), part of the limits
+pub mod extern_timeout {
+    include!("../../lib_lumen/extern_timeout.rs");
+}
+
+// Results of the selectors a program marked with cache_extern
+// Shared with the stream kernel so both keep and drop the same results.
+pub mod extern_cache {
+    include!("../../lib_lumen/extern_cache.rs");
+}
+
+// Call depth and step limits for --max-depth / --max-steps
+// Shared with the stream kernel so both count steps the same way.
+pub mod limits {
+    include!("../../lib_lumen/limits.rs");
+}
+
+// Bounded LRU cache of function results for MEMOIZATION, with its statistics
+// Shared with the stream kernel so both evict and count the same way.
+pub mod memo_cache {
+    include!("../../lib_lumen/memo_cache.rs");
+}
+
+// Static analysis over the reduced instruction tree (not a pipeline stage)
+pub mod lint;
+
+// Readable listing of the reduced instruction tree (--emit-instructions)
+pub mod disasm;
+
+// Optional rewrites of the reduced instruction tree before execution (--opt)
+pub mod optimize;
+
+// Reduced programs saved as JSON or CBOR (--emit-program / --load-program)
+pub mod serial;
+
+// Reduced programs cached on disk by source hash (--cache)
+pub mod cache;
+
+// Prelude definitions a program does not use, dropped when it is linked
+pub mod shake;
+
+// Reparsing only the changed end of a buffer (for editors and the REPL)
+pub mod incremental;
+
+// Slots for variable names, assigned before execution
+pub mod resolve;
+
 // 4-stage pipeline modules (in execution order)
 pub mod _1_ingest;
 pub mod _2_structure;
@@ -23,44 +179,254 @@ use _1_ingest as ingest;
 use _2_structure as structure;
 use _3_reduce as reduce;
 use num_bigint::BigInt;
+use std::fmt;
 
-pub use primitives::Instruction;
+pub use primitives::{Instruction, Program};
+pub use _1_ingest::Token;
 pub use eval::Value;
+pub use control_flow::LocatedError;
+pub use limits::ExecutionLimits;
+pub use optimize::Passes;
+pub use incremental::IncrementalParser;
+pub use timing::{Timing, TimingFormat};
+
+/// Extern capabilities handled by the execute stage (see builtin_extern in _4_execute.rs)
+const EXTERN_CAPABILITIES: &[&str] = &[
+    "arr:sort_by", "crypto:blake3", "crypto:md5", "crypto:sha256", "debug_info", "env:get", "env:vars", "fs:append", "fs:exists", "fs:list_dir", "fs:open", "fs:read", "fs:remove", "fs:write",
+    "io:read_all", "io:read_bytes", "io:read_line", "io:write_bytes", "json:parse", "json:stringify",
+    "net:accept", "net:close", "net:connect", "net:listen", "net:recv", "net:send", "print_native",
+    "random:float", "random:int", "sys:arch", "sys:cpu_count", "sys:cwd", "sys:pid", "sys:platform", "time:format", "time:monotonic_millis", "time:now", "value_type",
+];
+
+/// Feature matrix exposed to scripts as FEATURES
+/// Library code checks these to degrade gracefully when a subsystem is missing.
+/// Only the extern capabilities `policy` allows are listed.
+fn features(policy: &extern_policy::ExternPolicy) -> Value {
+    let capabilities = EXTERN_CAPABILITIES
+        .iter()
+        .filter(|c| policy.allows_selector(c))
+        .map(|c| Value::String(c.to_string()))
+        .collect();
+    Value::Map(vec![
+        ("kernel".to_string(), Value::String("microcode".to_string())),
+        ("real_backend".to_string(), Value::String("bigfloat".to_string())),
+        ("extern".to_string(), Value::Bool(true)),
+        ("extern_capabilities".to_string(), Value::Array(capabilities)),
+        ("memoization".to_string(), Value::Bool(true)),
+    ])
+}
+
+/// Token streams of a program: after ingest, and after structure (stages 1-2)
+pub fn tokenize(source: &str, schema: &LanguageSchema) -> Result<(Vec<Token>, Vec<Token>), Vec<LocatedError>> {
+    let raw = ingest::lex(source, schema)?;
+    let structured = structure::process_structure(raw.clone(), schema)?;
+    Ok((raw, structured))
+}
+
+/// Parse a program into its instructions without executing it (stages 1-3)
+pub fn parse_program(source: &str, schema: &LanguageSchema) -> Result<Program, Vec<LocatedError>> {
+    let tokens = ingest::lex(source, schema)?;
+    let tokens = structure::process_structure(tokens, schema)?;
+    reduce::parse(tokens, schema)
+}
 
 /// Run a program through the microcode kernel
 /// program_args: command-line arguments passed to the program
 pub fn run(source: &str, schema: &LanguageSchema, program_args: &[String]) -> Result<Value, String> {
-    let start = std::time::Instant::now();
+    run_with_constants(source, schema, program_args, &[])
+}
+
+/// Run a program with host-injected constants bound in the initial environment
+/// constants: (name, value) pairs; like ARGS they are read-only for the program.
+/// A name that collides with a system-provided binding is an error.
+pub fn run_with_constants(
+    source: &str,
+    schema: &LanguageSchema,
+    program_args: &[String],
+    constants: &[(String, Value)],
+) -> Result<Value, String> {
+    run_program(source, schema, program_args, constants).map_err(|e| e.to_string())
+}
+
+/// Why a run stopped early
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunError {
+    /// The source did not lex, structure or parse (stages 1-3), at each of these
+    Syntax(Vec<control_flow::LocatedError>),
+    /// Execution stopped with an uncaught error
+    Runtime(control_flow::LocatedError),
+    /// The program ran `exit status`
+    Exit(i32),
+    /// Execution went over one of its ExecutionLimits
+    LimitExceeded(control_flow::LocatedError),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Syntax(errors) => {
+                let lines: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            RunError::Runtime(e) | RunError::LimitExceeded(e) => write!(f, "{}", e),
+            RunError::Exit(status) => write!(f, "{}", control_flow::LumenError::Exit(*status)),
+        }
+    }
+}
+
+/// Run a program like run_with_constants, telling syntax errors, runtime errors and
+/// `exit` apart so a host can choose its own exit status for each
+pub fn run_program(
+    source: &str,
+    schema: &LanguageSchema,
+    program_args: &[String],
+    constants: &[(String, Value)],
+) -> Result<Value, RunError> {
+    run_program_timed(source, schema, program_args, constants, &mut Timing::start(), ExecutionLimits::default(), Passes::default())
+}
 
+/// Run a program like run_program within `limits`, recording each stage's duration and
+/// the token and instruction counts in `timing` (stages that ran before an error are kept)
+/// `passes` are run over the instructions before they execute.
+pub fn run_program_timed(
+    source: &str,
+    schema: &LanguageSchema,
+    program_args: &[String],
+    constants: &[(String, Value)],
+    timing: &mut Timing,
+    limits: ExecutionLimits,
+    passes: Passes,
+) -> Result<Value, RunError> {
+    let program = reduce_program_timed(source, schema, timing, passes)?;
+    run_reduced(&program, schema, program_args, constants, timing, limits)
+}
+
+/// Stages 1-3 and the selected passes, timed like run_program_timed
+pub fn reduce_program_timed(
+    source: &str,
+    schema: &LanguageSchema,
+    timing: &mut Timing,
+    passes: Passes,
+) -> Result<Program, RunError> {
+    let mut program = reduce_timed(source, 1, schema, timing)?;
+    optimize_timed(&mut program, passes, timing);
+    Ok(program)
+}
+
+/// Stages 1-3 for a program run after an already reduced prelude, then the selected
+/// passes over both, timed like run_program_timed
+/// `first_line` is the line the program's text would start on after the prelude's text,
+/// so positions and syntax errors read as if the two texts had been reduced together.
+/// Only the prelude definitions the program uses are linked (see shake.rs).
+pub fn reduce_with_prelude_timed(
+    prelude: Program,
+    first_line: usize,
+    source: &str,
+    schema: &LanguageSchema,
+    timing: &mut Timing,
+    passes: Passes,
+) -> Result<Program, RunError> {
+    let program = reduce_timed(source, first_line, schema, timing)?;
+    let mut program = link_timed(prelude, &program, timing);
+    optimize_timed(&mut program, passes, timing);
+    Ok(program)
+}
+
+/// Stages 1-3 through `parser`, which reduces only what changed since the text it parsed
+/// last, then linking after `prelude` (if there is one) and the selected passes, timed like
+/// run_program_timed
+/// With a prelude, `parser` must start on the line reduce_with_prelude_timed would give.
+pub fn reparse_timed(
+    parser: &mut IncrementalParser,
+    prelude: Option<Program>,
+    source: &str,
+    schema: &LanguageSchema,
+    timing: &mut Timing,
+    passes: Passes,
+) -> Result<Program, RunError> {
+    let stage = spans::stage("microcode", "reparse");
+    let program = parser.parse(source, schema).map_err(RunError::Syntax)?.clone();
+    drop(stage);
+    timing.lap("Reparse");
+    timing.count("reused_statements", parser.reused());
+    timing.count("instructions", program.len());
+    let mut program = match prelude {
+        Some(prelude) => link_timed(prelude, &program, timing),
+        None => program,
+    };
+    optimize_timed(&mut program, passes, timing);
+    Ok(program)
+}
+
+fn reduce_timed(source: &str, first_line: usize, schema: &LanguageSchema, timing: &mut Timing) -> Result<Program, RunError> {
     // Stage 1: Ingest - source → tokens
-    let t1 = std::time::Instant::now();
-    let tokens = ingest::lex(source, schema)?;
-    let ingest_time = t1.elapsed();
+    let stage = spans::stage("microcode", "ingest");
+    let tokens = ingest::lex_from(source, schema, first_line).map_err(RunError::Syntax)?;
+    drop(stage);
+    timing.lap("Ingest");
+    timing.count("tokens", tokens.len());
 
     // Stage 2: Structure - tokens → structured tokens
-    let t2 = std::time::Instant::now();
-    let tokens = structure::process_structure(tokens, schema)?;
-    let structure_time = t2.elapsed();
+    let stage = spans::stage("microcode", "structure");
+    let tokens = structure::process_structure(tokens, schema).map_err(RunError::Syntax)?;
+    drop(stage);
+    timing.lap("Structure");
+    timing.count("structured_tokens", tokens.len());
 
     // Stage 3: Reduce - tokens → instructions
-    let t3 = std::time::Instant::now();
-    let instr = reduce::parse(tokens, schema)?;
-    let reduce_time = t3.elapsed();
+    let stage = spans::stage("microcode", "reduce");
+    let program = reduce::parse(tokens, schema).map_err(RunError::Syntax)?;
+    drop(stage);
+    timing.lap("Reduce");
+    timing.count("instructions", program.len());
+    Ok(program)
+}
 
+/// The program linked after the prelude definitions it uses (see shake.rs)
+fn link_timed(prelude: Program, program: &Program, timing: &mut Timing) -> Program {
+    let stage = spans::stage("microcode", "link");
+    let (prelude, dropped) = shake::shake(prelude, program);
+    let program = prelude.followed_by(program);
+    drop(stage);
+    timing.lap("Link");
+    timing.count("dropped_definitions", dropped);
+    timing.count("linked_instructions", program.len());
+    program
+}
+
+fn optimize_timed(program: &mut Program, passes: Passes, timing: &mut Timing) {
+    // Optional passes (--opt) - instructions → instructions
+    if passes.any() {
+        let stage = spans::stage("microcode", "optimize");
+        let rewritten = optimize::optimize(program, passes);
+        drop(stage);
+        timing.lap("Optimize");
+        timing.count("rewritten", rewritten);
+    }
+}
+
+/// Run an already reduced program (stage 4 only), e.g. one loaded with serial::load
+/// `schema` must be the one of the language the program was reduced from.
+pub fn run_reduced(
+    program: &Program,
+    schema: &LanguageSchema,
+    program_args: &[String],
+    constants: &[(String, Value)],
+    timing: &mut Timing,
+    limits: ExecutionLimits,
+) -> Result<Value, RunError> {
     // Stage 4: Execute - instructions → values
-    let t4 = std::time::Instant::now();
     let mut env = Environment::new();
+    env.usage = limits::Usage::new(limits);
 
-    // Bind ARGS: system-provided semantic value containing all program arguments as a single string
-    // ARGS is immutable and read-only (cannot be reassigned by user code)
-    let args_str = if program_args.is_empty() {
-        String::new()
-    } else {
-        program_args.join(" ")
-    };
-    env.set("ARGS".to_string(), Value::String(args_str));
+    // Bind ARGS: an array with a string per program argument, as the shell passed it
+    // (an argument keeps its spaces), and ARGC: how many there are
+    // Both are immutable and read-only (cannot be reassigned by user code)
+    let args = program_args.iter().map(|arg| Value::String(arg.clone())).collect();
+    env.set_protected("ARGS".to_string(), Value::Array(args));
+    env.set_protected("ARGC".to_string(), Value::Number(BigInt::from(program_args.len())));
 
-    // Bind kind meta-value constants: INTEGER, RATIONAL, REAL, STRING, BOOLEAN, ARRAY, NULL
+    // Bind kind meta-value constants: INTEGER, RATIONAL, REAL, STRING, BOOLEAN, ARRAY, MAP, NULL, HANDLE, BYTES
     // These are predefined kernel-level type descriptors that match kind() return values
     env.set("INTEGER".to_string(), Value::Kind(eval::KindValue::INTEGER));
     env.set("RATIONAL".to_string(), Value::Kind(eval::KindValue::RATIONAL));
@@ -68,24 +434,200 @@ pub fn run(source: &str, schema: &LanguageSchema, program_args: &[String]) -> Re
     env.set("STRING".to_string(), Value::Kind(eval::KindValue::STRING));
     env.set("BOOLEAN".to_string(), Value::Kind(eval::KindValue::BOOLEAN));
     env.set("ARRAY".to_string(), Value::Kind(eval::KindValue::ARRAY));
+    env.set("MAP".to_string(), Value::Kind(eval::KindValue::MAP));
     env.set("NULL".to_string(), Value::Kind(eval::KindValue::NULL));
+    env.set("HANDLE".to_string(), Value::Kind(eval::KindValue::HANDLE));
+    env.set("BYTES".to_string(), Value::Kind(eval::KindValue::BYTES));
 
     // Bind kernel constant: REAL_DEFAULT_PRECISION
     env.set("REAL_DEFAULT_PRECISION".to_string(), Value::Number(BigInt::from(15)));
 
-    let (result, _flow) = execute(&instr, &mut env, schema)?;
-    let execute_time = t4.elapsed();
-
-    let total_time = start.elapsed();
+    // Bind FEATURES: read-only map of the optional subsystems this kernel provides
+    env.set_protected("FEATURES".to_string(), features(&env.usage.limits().externs));
 
-    // Only print timing if environment variable is set (for debugging)
-    if std::env::var("LUMEN_TIMING").is_ok() {
-        eprintln!("[TIMING] Ingest:    {:?}", ingest_time);
-        eprintln!("[TIMING] Structure: {:?}", structure_time);
-        eprintln!("[TIMING] Reduce:    {:?}", reduce_time);
-        eprintln!("[TIMING] Execute:   {:?}", execute_time);
-        eprintln!("[TIMING] Total:     {:?}", total_time);
+    // Bind host constants (--define / --config, or an embedding host)
+    for (name, value) in constants {
+        if env.exists(name) {
+            return Err(RunError::Runtime(control_flow::LocatedError::new(format!("Host constant '{}' collides with a system-provided name", name))));
+        }
+        env.set_protected(name.clone(), value.clone());
     }
 
+    // Resolve names to slots (the bindings above are kept)
+    let stage = spans::stage("microcode", "resolve");
+    let resolved = env.resolve(program);
+    drop(stage);
+    timing.lap("Resolve");
+    timing.count("resolved_names", resolved);
+
+    let stage = spans::stage("microcode", "execute");
+    let executed = execute(program, &mut env, schema);
+    drop(stage);
+    timing.lap("Execute");
+    let (result, _flow) = executed.map_err(|e| match e {
+        control_flow::LumenError::Exit(status) => RunError::Exit(status),
+        control_flow::LumenError::Raised(e) if limits::limit_exceeded(&e.message) => RunError::LimitExceeded(e),
+        control_flow::LumenError::Raised(e) => RunError::Runtime(e),
+    })?;
+
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::lumen_schema;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn programs_and_runtime_state_can_cross_threads() {
+        assert_send_sync::<Program>();
+        assert_send_sync::<Value>();
+        assert_send_sync::<Environment>();
+        assert_send_sync::<LanguageSchema>();
+        assert_send_sync::<RunError>();
+    }
+
+    #[test]
+    fn one_reduced_program_runs_on_several_threads_at_once() {
+        let schema = Arc::new(lumen_schema::get_schema());
+        let source = "fn fib(n)\n    if n < 2\n        return n\n    return fib(n - 1) + fib(n - 2)\nfib(N)\n";
+        let program = Arc::new(parse_program(source, &schema).unwrap());
+        let runs: Vec<_> = (10..14)
+            .map(|n| {
+                let (program, schema) = (Arc::clone(&program), Arc::clone(&schema));
+                thread::spawn(move || {
+                    let constants = [("N".to_string(), Value::Number(BigInt::from(n)))];
+                    run_reduced(&program, &schema, &[], &constants, &mut Timing::start(), ExecutionLimits::default())
+                })
+            })
+            .collect();
+        let results: Vec<Value> = runs.into_iter().map(|run| run.join().unwrap().unwrap()).collect();
+        let expected: Vec<Value> = [55, 89, 144, 233].into_iter().map(|n| Value::Number(BigInt::from(n))).collect();
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn runaway_programs_stop_with_a_limit_error() {
+        let schema = lumen_schema::get_schema();
+        let run = |limits: ExecutionLimits, source: &str| {
+            run_program_timed(source, &schema, &[], &[], &mut Timing::start(), limits, Passes::default())
+        };
+        let forever = "while true\n    x = 1\n";
+        let time = ExecutionLimits { max_time: Some(std::time::Duration::from_millis(10)), ..Default::default() };
+        assert!(matches!(run(time, forever), Err(RunError::LimitExceeded(e)) if e.message.contains("--max-time")));
+        let heap = ExecutionLimits { max_heap: Some(100_000), ..Default::default() };
+        let growing = "s = \"\"\nwhile true\n    s = s + \"xxxxxxxxxx\"\n";
+        assert!(matches!(run(heap.clone(), growing), Err(RunError::LimitExceeded(e)) if e.message.contains("--max-heap")));
+        assert!(matches!(run(heap, "x = 1 / 0\n"), Err(RunError::Runtime(_))));
+    }
+
+    #[test]
+    fn only_exit_ends_a_run_with_a_status() {
+        let schema = lumen_schema::get_schema();
+        assert_eq!(run_program("exit(3)\n", &schema, &[], &[]), Err(RunError::Exit(3)));
+        let error = run_program("error(\"exit requested with status 7\")\n", &schema, &[], &[]).unwrap_err();
+        assert!(matches!(&error, RunError::Runtime(e) if e.message.contains("exit requested with status 7")), "{:?}", error);
+    }
+
+    #[test]
+    fn integers_overflow_by_the_schema_rule() {
+        use crate::languages::rust_core_schema;
+        use crate::schema::IntegerOverflow;
+        let mut schema = rust_core_schema::get_schema();
+        let run = |schema: &LanguageSchema, passes: Passes, source: &str| {
+            run_program_timed(source, schema, &[], &[], &mut Timing::start(), ExecutionLimits::default(), passes)
+                .map(|value| value.to_string())
+        };
+        let sources = ["9223372036854775807 + 1", "-9223372036854775807 - 3", "3 ** 41", "(-3) ** 1001", "2 ** 62 * 2 - 1"];
+        let expected = [
+            (IntegerOverflow::Unbounded, ["9223372036854775808", "-9223372036854775810", "36472996377170786403", "", "9223372036854775807"]),
+            (IntegerOverflow::Wrapping, ["-9223372036854775808", "9223372036854775806", "-420491770248316829", "-163179016665764195", "9223372036854775807"]),
+            (IntegerOverflow::Saturating, ["9223372036854775807", "-9223372036854775808", "9223372036854775807", "-9223372036854775808", "9223372036854775806"]),
+        ];
+        for (overflow, values) in expected {
+            schema.integer_overflow = overflow;
+            for (source, value) in sources.into_iter().zip(values).filter(|(_, value)| !value.is_empty()) {
+                // Folding leaves results outside i64 to the rule
+                for passes in [Passes::default(), Passes::all()] {
+                    assert_eq!(run(&schema, passes, source).unwrap(), value, "{:?} {}", overflow, source);
+                }
+            }
+        }
+
+        // rust_core's own rule is Rust's debug build: an error
+        let schema = rust_core_schema::get_schema();
+        let error = run(&schema, Passes::all(), "let x = 9223372036854775807;\nx + 1").unwrap_err();
+        assert!(error.to_string().contains("attempt to add with overflow"), "{}", error);
+        let error = run(&schema, Passes::default(), "-((-2) ** 63)").unwrap_err();
+        assert!(error.to_string().contains("attempt to negate with overflow"), "{}", error);
+        assert_eq!(run(&schema, Passes::default(), "(-2) ** 63").unwrap(), "-9223372036854775808");
+
+        // Integer `/` truncates as Rust's does, folded or not; literals must fit in i64
+        for passes in [Passes::default(), Passes::all()] {
+            assert_eq!(run(&schema, passes, "7 / 2").unwrap(), "3");
+            assert_eq!(run(&schema, passes, "-7 / 2").unwrap(), "-3");
+            assert_eq!(run(&schema, passes, "-7 % 2").unwrap(), "-1");
+            assert_eq!(run(&schema, passes, "-9223372036854775808").unwrap(), "-9223372036854775808");
+            let error = run(&schema, passes, "7 / 0").unwrap_err();
+            assert!(error.to_string().contains("attempt to divide by zero"), "{}", error);
+            let error = run(&schema, passes, "-9223372036854775808 % -1").unwrap_err();
+            assert!(error.to_string().contains("attempt to calculate the remainder with overflow"), "{}", error);
+        }
+        let error = run(&schema, Passes::default(), "9223372036854775808").unwrap_err();
+        assert!(matches!(&error, RunError::Syntax(e) if e[0].message.contains("literal out of range for i64")), "{}", error);
+        assert!(run(&schema, Passes::default(), "-9223372036854775809").is_err());
+        let mut wrapping = rust_core_schema::get_schema();
+        wrapping.integer_overflow = IntegerOverflow::Wrapping;
+        assert_eq!(run(&wrapping, Passes::default(), "-9223372036854775808 / -1").unwrap(), "-9223372036854775808");
+
+        // Lumen's integers are unbounded, and its `/` gives a RATIONAL
+        let lumen = crate::languages::lumen_schema::get_schema();
+        assert_eq!(run(&lumen, Passes::all(), "7 / 2").unwrap(), "7/2");
+        assert_eq!(run(&lumen, Passes::default(), "9223372036854775808").unwrap(), "9223372036854775808");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn runs_report_stage_spans_and_statement_events() {
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Output {
+            fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(bytes)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let schema = lumen_schema::get_schema();
+        tracing::subscriber::with_default(subscriber, || {
+            run_program_timed("x = 1\nif x > 0\n    x = 2\n", &schema, &[], &[], &mut Timing::start(), ExecutionLimits::default(), Passes::default())
+        })
+        .unwrap();
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        for stage in ["ingest", "structure", "reduce", "resolve", "execute"] {
+            assert!(output.contains(&format!("stage{{kernel=\"microcode\" stage=\"{}\"}}: lumen: close", stage)), "{}", output);
+        }
+        let statements: Vec<&str> = output.lines().filter(|line| line.contains("lumen: statement ")).collect();
+        assert_eq!(statements.len(), 3, "{}", output);
+        assert!(statements[2].contains("statement line=3 column=5"), "{}", output);
+    }
+}
@@ -0,0 +1,494 @@
+// Optional passes over the instruction tree (--opt)
+//
+// Run between stage 3 (reduce) and stage 4 (execute), whatever language the program was
+// reduced from. Passes rewrite instructions in place in the program arena; what a program
+// prints and returns does not change.
+//
+//   fold-constants   an operator applied only to literals becomes the literal result
+//                    (arithmetic, comparisons, logic and string concatenation)
+//   dead-code        statements after return/break/continue in the same block are dropped;
+//                    an `if` on a literal condition becomes the arm that would run, and
+//                    `while` on a false literal or an `if` with nothing to run becomes null
+//   peephole         local rewrites of one instruction and its operands, by level:
+//                    1  negate-literal   `-` on a number literal becomes the negative literal
+//                       double-not       `not not c` as an if/while/until condition becomes c
+//                    2  inverted-branch  `if not c` with an else swaps its arms and tests c
+//                       redundant-load   a statement that only reads a variable an earlier
+//                                        statement of the same block assigned is dropped
+//                                        (unless it is the block's value)
+//   common-subexpressions
+//                    an operation on variables and literals that a statement computes more
+//                    than once (e.g. the same power) is computed once: its first occurrence
+//                    assigns the value to a hidden variable (__cse0, __cse1, ...) that the
+//                    others read instead
+//
+// --opt=1 runs folding, dead-code and the level 1 peepholes; --opt=2 (and --opt) adds
+// the level 2 ones and common subexpressions. Peepholes run first, then folding, then
+// dead-code, which runs after folding so `if 1 > 2` counts as a literal condition, then
+// common subexpressions.
+//
+// Common subexpressions never look into calls (a function may run anything), and only
+// reuse a value whose first occurrence always runs before the others: one on the right
+// of `and` or `or` may be skipped, so it is only ever a later occurrence. The hidden
+// variables are bindings like any other, so they count towards --max-heap.
+//
+// Folding uses the execute stage's own operator functions, so a folded value is exactly
+// the one the operator would have produced at run time. An operation that fails (such as
+// a division by zero) is left in place to fail at run time with its statement position,
+// and so is an integer result outside i64, which depends on the schema's integer_overflow,
+// as do `/` and `%` on integers (a RATIONAL, or Rust's truncating division, and its
+// overflow of i64::MIN % -1).
+
+use super::_4_execute::{apply_binary, apply_unary};
+use super::eval::Value;
+use super::primitives::{InstrId, Instruction, OperateKind, Program};
+use num_traits::cast::ToPrimitive;
+
+/// Highest --opt level
+pub const MAX_LEVEL: u8 = 2;
+
+/// Which passes to run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Passes {
+    pub fold_constants: bool,
+    pub eliminate_dead_code: bool,
+    /// Peephole patterns up to this level (0 for none, see PEEPHOLES)
+    pub peephole: u8,
+    pub common_subexpressions: bool,
+}
+
+impl Passes {
+    /// Every pass (--opt)
+    pub fn all() -> Self {
+        Self::level(MAX_LEVEL)
+    }
+
+    /// The passes of --opt=`level` (none for 0; levels above MAX_LEVEL run them all)
+    pub fn level(level: u8) -> Self {
+        match level {
+            0 => Passes::default(),
+            _ => Passes {
+                fold_constants: true,
+                eliminate_dead_code: true,
+                peephole: level.min(MAX_LEVEL),
+                common_subexpressions: level >= 2,
+            },
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.fold_constants || self.eliminate_dead_code || self.peephole > 0 || self.common_subexpressions
+    }
+}
+
+/// Binary operators folded when both operands are literals
+/// (ranges, indexing and `|>` build or look up values and are left to the execute stage)
+const FOLDED_BINARY: &[&str] = &[
+    "+", "-", "*", "/", "%", "//", "**", ".", "==", "!=", "<", ">", "<=", ">=", "and", "or", "&&", "||",
+];
+
+/// Run the selected passes; returns how many instructions were rewritten
+pub fn optimize(program: &mut Program, passes: Passes) -> usize {
+    let mut rewritten = 0;
+    if passes.peephole > 0 {
+        rewritten += peephole(program, passes.peephole);
+    }
+    if passes.fold_constants {
+        rewritten += fold_constants(program);
+    }
+    if passes.eliminate_dead_code {
+        rewritten += eliminate_dead_code(program);
+    }
+    if passes.common_subexpressions {
+        rewritten += eliminate_common_subexpressions(program);
+    }
+    rewritten
+}
+
+/// Replace operators on literals with their result; returns how many were folded
+/// Operands are stored before the instructions using them, so one pass in storage order
+/// folds nested operations from the inside out.
+pub fn fold_constants(program: &mut Program) -> usize {
+    let mut folded = 0;
+    for id in program.ids() {
+        if let Some(value) = fold(program, id) {
+            program[id] = Instruction::literal(value);
+            folded += 1;
+        }
+    }
+    folded
+}
+
+/// The value of an operator instruction whose operands are all literals
+fn fold(program: &Program, id: InstrId) -> Option<Value> {
+    let Instruction::Operate { kind, operands } = &program[id] else {
+        return None;
+    };
+    let literal = |operand: &InstrId| match &program[*operand] {
+        Instruction::Literal(value) => Some(value.clone()),
+        _ => None,
+    };
+    let value = match (kind, operands.as_slice()) {
+        (OperateKind::Unary(op), [operand]) => apply_unary(op, literal(operand)?).ok(),
+        (OperateKind::Binary(op), [left, right]) if FOLDED_BINARY.contains(&op.as_str()) => {
+            match (literal(left)?, literal(right)?) {
+                // On integers they are Rust's in a schema with 64-bit integers
+                (Value::Number(_), Value::Number(_)) if op == "/" || op == "%" => None,
+                (left, right) => apply_binary(op, left, right).ok(),
+            }
+        }
+        _ => None,
+    };
+    value.filter(fits_every_schema)
+}
+
+/// Whether a folded value is the same in every schema (see integer_overflow in schema.rs)
+fn fits_every_schema(value: &Value) -> bool {
+    !matches!(value, Value::Number(n) if n.to_i64().is_none())
+}
+
+/// Drop statements and branches that can never run; returns how many instructions changed
+/// Like folding, one pass in storage order: a block's statements are simplified before
+/// the block itself.
+pub fn eliminate_dead_code(program: &mut Program) -> usize {
+    let mut changed = 0;
+    for id in program.ids() {
+        if let Some(instr) = live_code(program, id) {
+            program[id] = instr;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// What an instruction with dead parts reduces to
+fn live_code(program: &Program, id: InstrId) -> Option<Instruction> {
+    let literal = |instr: InstrId| match &program[instr] {
+        Instruction::Literal(value) => Some(value.to_bool()),
+        _ => None,
+    };
+    match &program[id] {
+        Instruction::Branch { condition, then_instr, else_instr } => match (literal(*condition)?, else_instr) {
+            (true, _) => Some(program[*then_instr].clone()),
+            (false, Some(else_instr)) => Some(program[*else_instr].clone()),
+            (false, None) => Some(Instruction::literal(Value::Null)),
+        },
+        Instruction::Loop { condition, .. } if literal(*condition) == Some(false) => {
+            Some(Instruction::literal(Value::Null))
+        }
+        Instruction::Sequence(items, positions) => {
+            // Nothing after a transfer runs; a literal statement does nothing unless it is
+            // the block's value (the last statement)
+            let end = items
+                .iter()
+                .position(|item| matches!(program[*item], Instruction::Transfer { .. }))
+                .map_or(items.len(), |transfer| transfer + 1);
+            let live: Vec<usize> = (0..end)
+                .filter(|&i| i + 1 == end || !matches!(program[items[i]], Instruction::Literal(_)))
+                .collect();
+            if live.len() == items.len() {
+                return None;
+            }
+            Some(Instruction::sequence(
+                live.iter().map(|&i| items[i]).collect(),
+                live.iter().map(|&i| positions[i]).collect(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// A local rewrite: what an instruction becomes, if the pattern applies to it
+type Peephole = fn(&Program, InstrId) -> Option<Instruction>;
+
+/// Peephole patterns by name, with the level they run from
+const PEEPHOLES: &[(&str, u8, Peephole)] = &[
+    ("negate-literal", 1, negate_literal),
+    ("double-not", 1, double_not),
+    ("inverted-branch", 2, inverted_branch),
+    ("redundant-load", 2, redundant_load),
+];
+
+/// Apply the peephole patterns up to `level`; returns how many rewrites were made
+/// In storage order like the other passes, so an instruction sees its operands already
+/// rewritten, and each pattern sees what the ones before it made of the instruction.
+pub fn peephole(program: &mut Program, level: u8) -> usize {
+    let patterns: Vec<Peephole> = PEEPHOLES
+        .iter()
+        .filter(|(_, from, _)| *from <= level)
+        .map(|(_, _, pattern)| *pattern)
+        .collect();
+    let mut rewritten = 0;
+    for id in program.ids() {
+        for pattern in &patterns {
+            if let Some(instr) = pattern(program, id) {
+                program[id] = instr;
+                rewritten += 1;
+            }
+        }
+    }
+    rewritten
+}
+
+/// The operand of a `not` (or `!`) instruction
+fn negated(program: &Program, id: InstrId) -> Option<InstrId> {
+    match &program[id] {
+        Instruction::Operate { kind: OperateKind::Unary(op), operands } if op == "not" || op == "!" => {
+            operands.first().copied()
+        }
+        _ => None,
+    }
+}
+
+/// `-` applied to a number literal: the negative literal
+fn negate_literal(program: &Program, id: InstrId) -> Option<Instruction> {
+    let Instruction::Operate { kind: OperateKind::Unary(op), operands } = &program[id] else {
+        return None;
+    };
+    match (op.as_str(), operands.as_slice()) {
+        ("-", [operand]) => match &program[*operand] {
+            Instruction::Literal(value @ (Value::Number(_) | Value::Rational { .. } | Value::Real { .. })) => {
+                apply_unary(op, value.clone()).ok().filter(fits_every_schema).map(Instruction::literal)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A condition negated twice: only its truth is used, and `not` gives that of its operand
+fn double_not(program: &Program, id: InstrId) -> Option<Instruction> {
+    let plain = |condition: InstrId| negated(program, condition).and_then(|inner| negated(program, inner));
+    match &program[id] {
+        Instruction::Branch { condition, then_instr, else_instr } => {
+            Some(Instruction::branch(plain(*condition)?, *then_instr, *else_instr))
+        }
+        Instruction::Loop { condition, body } => Some(Instruction::loop_stmt(plain(*condition)?, *body)),
+        Instruction::UntilLoop { condition, body } => Some(Instruction::until_loop(plain(*condition)?, *body)),
+        _ => None,
+    }
+}
+
+/// `if not c: a else: b` becomes `if c: b else: a`, one operation less each time it runs
+fn inverted_branch(program: &Program, id: InstrId) -> Option<Instruction> {
+    let Instruction::Branch { condition, then_instr, else_instr: Some(else_instr) } = &program[id] else {
+        return None;
+    };
+    Some(Instruction::branch(negated(program, *condition)?, *else_instr, Some(*then_instr)))
+}
+
+/// A block without the statements that only read a variable it already assigned
+/// Reading a bound name cannot fail and the value is unused, unless it is the last statement.
+fn redundant_load(program: &Program, id: InstrId) -> Option<Instruction> {
+    let Instruction::Sequence(items, positions) = &program[id] else {
+        return None;
+    };
+    let mut assigned: Vec<&str> = Vec::new();
+    let mut live = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        match &program[*item] {
+            Instruction::Variable(name) if i + 1 < items.len() && assigned.contains(&name.as_str()) => continue,
+            Instruction::Assign { name, .. } => assigned.push(name),
+            _ => {}
+        }
+        live.push(i);
+    }
+    if live.len() == items.len() {
+        return None;
+    }
+    Some(Instruction::sequence(
+        live.iter().map(|&i| items[i]).collect(),
+        live.iter().map(|&i| positions[i]).collect(),
+    ))
+}
+
+/// Prefix of the hidden variables holding common subexpressions
+const CSE_PREFIX: &str = "__cse";
+
+/// Compute each pure operation repeated within a statement once; returns how many
+/// repeats now read the first occurrence's value
+pub fn eliminate_common_subexpressions(program: &mut Program) -> usize {
+    // A statement's expressions: the pure operands of everything else (statements and calls)
+    let roots: Vec<InstrId> = program
+        .ids()
+        .filter(|&id| !is_pure(&program[id]))
+        .flat_map(|id| program[id].children())
+        .filter(|&operand| is_pure(&program[operand]))
+        .collect();
+    let program_root = program.root();
+    let mut replaced = 0;
+    let mut temporaries = 0;
+    for root in roots {
+        while let Some((first, repeats)) = repeated_subexpression(program, root) {
+            let name = format!("{}{}", CSE_PREFIX, temporaries);
+            temporaries += 1;
+            let value = program.add(program[first].clone());
+            program[first] = Instruction::assign(name.clone(), value);
+            for &repeat in &repeats {
+                program[repeat] = Instruction::variable(name.clone());
+            }
+            replaced += repeats.len();
+        }
+    }
+    if replaced > 0 {
+        // The moved first occurrences are stored after the instructions using them
+        *program = program.relaid(program_root);
+    }
+    replaced
+}
+
+/// Whether an instruction only computes a value from its operands
+/// (calls, including `|>`, may do anything)
+fn is_pure(instr: &Instruction) -> bool {
+    match instr {
+        Instruction::Literal(_) | Instruction::Variable(_) => true,
+        Instruction::Operate { kind: OperateKind::Binary(op), .. } => op != "|>",
+        Instruction::Operate { kind: OperateKind::Unary(_), .. } => true,
+        _ => false,
+    }
+}
+
+/// The largest operation under `root` computed more than once, as its first occurrence
+/// and the repeats
+fn repeated_subexpression(program: &Program, root: InstrId) -> Option<(InstrId, Vec<InstrId>)> {
+    // (structure, size, whether it always runs, id) of every pure operation, in evaluation order
+    let mut operations: Vec<(String, usize, bool, InstrId)> = Vec::new();
+    let mut pending = vec![(root, true)];
+    while let Some((id, always)) = pending.pop() {
+        match &program[id] {
+            Instruction::Operate { kind, operands } if is_pure(&program[id]) => {
+                if let Some(size) = pure_size(program, id) {
+                    operations.push((format!("{:?}", program.tree(id)), size, always, id));
+                }
+                let short_circuit = matches!(kind, OperateKind::Binary(op) if matches!(op.as_str(), "and" | "&&" | "or" | "||"));
+                pending.extend(operands.iter().enumerate().rev().map(|(i, &operand)| (operand, always && !(short_circuit && i == 1))));
+            }
+            // An earlier common subexpression: its value is still computed here
+            Instruction::Assign { name, value } if name.starts_with(CSE_PREFIX) => pending.push((*value, always)),
+            _ => {}
+        }
+    }
+
+    let mut best: Option<(usize, InstrId, Vec<InstrId>)> = None;
+    for (index, (structure, size, always, first)) in operations.iter().enumerate() {
+        let seen_before = operations[..index].iter().any(|(earlier, ..)| earlier == structure);
+        if seen_before || !always || best.as_ref().is_some_and(|(best_size, ..)| best_size >= size) {
+            continue;
+        }
+        let repeats: Vec<InstrId> = operations[index + 1..]
+            .iter()
+            .filter(|(later, ..)| later == structure)
+            .map(|(.., id)| *id)
+            .collect();
+        if !repeats.is_empty() {
+            best = Some((*size, *first, repeats));
+        }
+    }
+    best.map(|(_, first, repeats)| (first, repeats))
+}
+
+/// Instructions in the tree at `id`, if all of them are pure
+fn pure_size(program: &Program, id: InstrId) -> Option<usize> {
+    if !is_pure(&program[id]) {
+        return None;
+    }
+    program[id].children().into_iter().try_fold(1, |size, operand| Some(size + pure_size(program, operand)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::disasm::disassemble;
+    use crate::kernel::parse_program;
+    use crate::languages::lumen_schema;
+
+    fn folded_listing(source: &str) -> (usize, String) {
+        let mut program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+        let folded = fold_constants(&mut program);
+        (folded, disassemble(&program))
+    }
+
+    #[test]
+    fn folds_nested_arithmetic_comparisons_and_concatenation() {
+        let (folded, listing) = folded_listing("x = (2 + 3) * 4\ny = 1 < 2\nz = \"a\" . 7\n");
+        assert_eq!(folded, 4);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[1..7], [
+            "  [4] Assign x",
+            "    Literal Number 20",
+            "  [4] Assign y",
+            "    Literal Bool true",
+            "  [4] Assign z",
+            "    Literal String \"a7\"",
+        ]);
+    }
+
+    #[test]
+    fn drops_code_after_transfers_and_branches_that_never_run() {
+        let source = "fn f(x)\n    return x\n    print(x)\nif 1 > 2\n    print(1)\nif false\n    print(2)\nelse\n    print(3)\nwhile false\n    print(4)\n";
+        let mut program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+        let passes = optimize(&mut program, Passes::all());
+        assert!(passes > 0);
+        let listing = disassemble(&program);
+        assert!(!listing.contains("[3] Branch"), "{}", listing);
+        assert!(!listing.contains("Loop while"), "{}", listing);
+        let prints: Vec<&str> = listing.lines().filter(|line| line.contains("Invoke print")).collect();
+        assert_eq!(prints.len(), 1, "{}", listing);
+        assert!(listing.contains("Literal Number 3"), "{}", listing);
+    }
+
+    #[test]
+    fn peepholes_run_from_their_level() {
+        let source = "x = -5\nif not not x\n    print(x)\nif not x\n    print(1)\nelse\n    print(2)\nx\nprint(x)\n";
+        let listing = |level| {
+            let mut program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+            let rewritten = peephole(&mut program, level);
+            (rewritten, disassemble(&program))
+        };
+        let (rewritten, listing_1) = listing(1);
+        assert_eq!(rewritten, 2, "{}", listing_1);
+        assert!(listing_1.contains("Literal Number -5"), "{}", listing_1);
+        assert_eq!(listing_1.matches("unary 'not'").count(), 1, "{}", listing_1);
+        assert_eq!(listing_1.matches("Variable x").count(), 5, "{}", listing_1);
+
+        let (rewritten, listing_2) = listing(2);
+        assert_eq!(rewritten, 4, "{}", listing_2);
+        assert!(!listing_2.contains("unary 'not'"), "{}", listing_2);
+        // The arms are swapped: print(2) now runs when x is true
+        assert!(listing_2.find("Literal Number 2").unwrap() < listing_2.find("Literal Number 1").unwrap(), "{}", listing_2);
+        assert_eq!(listing_2.matches("Variable x").count(), 4, "{}", listing_2);
+        assert_eq!(Passes::level(1).peephole, 1);
+        assert_eq!(Passes::level(9), Passes::all());
+        assert!(!Passes::level(0).any());
+    }
+
+    #[test]
+    fn computes_repeated_operations_of_a_statement_once() {
+        let schema = lumen_schema::get_schema();
+        let source = "a = 3\nx = a ** 40 * (a ** 40 + 1)\ny = a ** 40\nz = a > 1 and a * 2 > 5 and a * 2 < 7\nx - y + z\n";
+        let mut program = parse_program(source, &schema).unwrap();
+        assert_eq!(eliminate_common_subexpressions(&mut program), 1);
+        program.check().unwrap();
+        let listing = disassemble(&program);
+        // Only within a statement, and never first on the right of `and`
+        assert_eq!(listing.matches("'**'").count(), 2, "{}", listing);
+        assert_eq!(listing.matches("Assign __cse0").count(), 1, "{}", listing);
+        assert_eq!(listing.matches("Variable __cse0").count(), 1, "{}", listing);
+        assert_eq!(listing.matches("'*'").count(), 3, "{}", listing);
+
+        let run = |passes| {
+            let mut timing = crate::kernel::Timing::start();
+            let program = crate::kernel::reduce_program_timed(source, &schema, &mut timing, passes).unwrap();
+            crate::kernel::run_reduced(&program, &schema, &[], &[], &mut timing, Default::default()).unwrap()
+        };
+        assert_eq!(run(Passes::level(2)), run(Passes::default()));
+    }
+
+    #[test]
+    fn leaves_variables_and_failing_operations_alone() {
+        let (folded, listing) = folded_listing("x = 1\ny = x + 2\nz = 1 / 0\n");
+        assert_eq!(folded, 0);
+        assert!(listing.contains("[6] Operate binary '+'"), "{}", listing);
+        assert!(listing.contains("[6] Operate binary '/'"), "{}", listing);
+    }
+}
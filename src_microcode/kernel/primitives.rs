@@ -12,6 +12,8 @@
 // 6. Operate - dispatch unary/binary operator
 // 7. Transfer - control flow (return/break/continue)
 //
+// Instructions live in a Program arena and refer to their operands by InstrId.
+//
 // Each primitive is stateless data. Semantics come from:
 // - Instruction structure (the "what")
 // - Schema tables (the "how")
@@ -19,9 +21,11 @@
 // - Environment (the "in what context")
 
 use crate::kernel::eval::Value;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Control transfer kinds (for Transfer primitive)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransferKind {
     Return,
     Break,
@@ -29,53 +33,62 @@ pub enum TransferKind {
 }
 
 /// Operator kinds (for Operate primitive)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OperateKind {
     Unary(String),   // operator name
     Binary(String),  // operator name
 }
 
+/// Where a statement starts in the parsed text (line and column count from 1)
+/// Runtime errors leaving the statement are marked with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
 /// Instruction: One node in the semantic normal form.
 /// Each instruction is one of 7 primitives, nothing more.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Instruction {
-    // 1. Sequence: execute Vec<Instruction> in order, return last value
-    Sequence(Vec<Instruction>),
+    // 1. Sequence: execute Vec<InstrId> in order, return last value
+    //    (with the source position of each statement)
+    Sequence(Vec<InstrId>, Vec<Position>),
 
     // 2. Scope: push scope, execute instruction, pop scope
-    Scope(Box<Instruction>),
+    Scope(InstrId),
 
     // 3. Branch: if cond then_instr else else_instr
     Branch {
-        condition: Box<Instruction>,
-        then_instr: Box<Instruction>,
-        else_instr: Option<Box<Instruction>>,
+        condition: InstrId,
+        then_instr: InstrId,
+        else_instr: Option<InstrId>,
     },
 
     // 4. Assign: name = value_instr
     Assign {
         name: String,
-        value: Box<Instruction>,
+        value: InstrId,
     },
 
     // 5. Invoke: call external function
     //    All actual semantics come from the schema and external registry
     Invoke {
         function: String,  // fully qualified function name
-        args: Vec<Instruction>,
+        args: Vec<InstrId>,
     },
 
     // 6. Operate: apply operator to operands
     //    Operator semantics defined in schema
     Operate {
         kind: OperateKind,
-        operands: Vec<Instruction>,
+        operands: Vec<InstrId>,
     },
 
     // 7. Transfer: control flow signal
     Transfer {
         kind: TransferKind,
-        value: Option<Box<Instruction>>,
+        value: Option<InstrId>,
     },
 
     // Literals: not a "primitive" but necessary
@@ -87,21 +100,21 @@ pub enum Instruction {
 
     // Loop: while condition { body }
     Loop {
-        condition: Box<Instruction>,
-        body: Box<Instruction>,
+        condition: InstrId,
+        body: InstrId,
     },
 
     // ForLoop: for var in iterable { body }
     ForLoop {
         var: String,
-        iterable: Box<Instruction>,
-        body: Box<Instruction>,
+        iterable: InstrId,
+        body: InstrId,
     },
 
     // UntilLoop: until condition { body } (do-until: execute body first, then check condition)
     UntilLoop {
-        condition: Box<Instruction>,
-        body: Box<Instruction>,
+        condition: InstrId,
+        body: InstrId,
     },
 
     // Function definition: store in registry
@@ -109,14 +122,15 @@ pub enum Instruction {
     FunctionDef {
         name: String,
         params: Vec<String>,
-        body: Box<Instruction>,
+        body: InstrId,
+        doc: Option<String>,
     },
 
     // Indexed assignment: arr[index] = value
     IndexedAssign {
         name: String,
-        index: Box<Instruction>,
-        value: Box<Instruction>,
+        index: InstrId,
+        value: InstrId,
     },
 
     // Set MEMOIZATION flag (system control)
@@ -126,9 +140,9 @@ pub enum Instruction {
 }
 
 impl Instruction {
-    /// Helper: sequence of instructions
-    pub fn sequence(instrs: Vec<Instruction>) -> Self {
-        Instruction::Sequence(instrs)
+    /// Helper: sequence of statements starting at `positions`
+    pub fn sequence(instrs: Vec<InstrId>, positions: Vec<Position>) -> Self {
+        Instruction::Sequence(instrs, positions)
     }
 
     /// Helper: literal value
@@ -142,15 +156,15 @@ impl Instruction {
     }
 
     /// Helper: assignment
-    pub fn assign(name: String, value: Instruction) -> Self {
+    pub fn assign(name: String, value: InstrId) -> Self {
         Instruction::Assign {
             name,
-            value: Box::new(value),
+            value,
         }
     }
 
     /// Helper: binary operation
-    pub fn binary(op: String, left: Instruction, right: Instruction) -> Self {
+    pub fn binary(op: String, left: InstrId, right: InstrId) -> Self {
         Instruction::Operate {
             kind: OperateKind::Binary(op),
             operands: vec![left, right],
@@ -158,7 +172,7 @@ impl Instruction {
     }
 
     /// Helper: unary operation
-    pub fn unary(op: String, operand: Instruction) -> Self {
+    pub fn unary(op: String, operand: InstrId) -> Self {
         Instruction::Operate {
             kind: OperateKind::Unary(op),
             operands: vec![operand],
@@ -166,28 +180,28 @@ impl Instruction {
     }
 
     /// Helper: function call
-    pub fn invoke(function: String, args: Vec<Instruction>) -> Self {
+    pub fn invoke(function: String, args: Vec<InstrId>) -> Self {
         Instruction::Invoke { function, args }
     }
 
     /// Helper: if-then-else
     pub fn branch(
-        condition: Instruction,
-        then_instr: Instruction,
-        else_instr: Option<Instruction>,
+        condition: InstrId,
+        then_instr: InstrId,
+        else_instr: Option<InstrId>,
     ) -> Self {
         Instruction::Branch {
-            condition: Box::new(condition),
-            then_instr: Box::new(then_instr),
-            else_instr: else_instr.map(Box::new),
+            condition,
+            then_instr,
+            else_instr,
         }
     }
 
     /// Helper: return statement
-    pub fn return_stmt(value: Option<Instruction>) -> Self {
+    pub fn return_stmt(value: Option<InstrId>) -> Self {
         Instruction::Transfer {
             kind: TransferKind::Return,
-            value: value.map(Box::new),
+            value,
         }
     }
 
@@ -208,37 +222,37 @@ impl Instruction {
     }
 
     /// Helper: loop
-    pub fn loop_stmt(condition: Instruction, body: Instruction) -> Self {
+    pub fn loop_stmt(condition: InstrId, body: InstrId) -> Self {
         Instruction::Loop {
-            condition: Box::new(condition),
-            body: Box::new(body),
+            condition,
+            body,
         }
     }
 
     /// Helper: for loop
-    pub fn for_loop(var: String, iterable: Instruction, body: Instruction) -> Self {
+    pub fn for_loop(var: String, iterable: InstrId, body: InstrId) -> Self {
         Instruction::ForLoop {
             var,
-            iterable: Box::new(iterable),
-            body: Box::new(body),
+            iterable,
+            body,
         }
     }
 
     /// Helper: until loop
-    pub fn until_loop(condition: Instruction, body: Instruction) -> Self {
+    pub fn until_loop(condition: InstrId, body: InstrId) -> Self {
         Instruction::UntilLoop {
-            condition: Box::new(condition),
-            body: Box::new(body),
+            condition,
+            body,
         }
     }
 
     /// Helper: scope push
-    pub fn scope(instr: Instruction) -> Self {
-        Instruction::Scope(Box::new(instr))
+    pub fn scope(instr: InstrId) -> Self {
+        Instruction::Scope(instr)
     }
 
     /// Helper: construct array from elements
-    pub fn construct_array(elements: Vec<Instruction>) -> Self {
+    pub fn construct_array(elements: Vec<InstrId>) -> Self {
         Instruction::Invoke {
             function: "__construct_array".to_string(),
             args: elements,
@@ -246,11 +260,308 @@ impl Instruction {
     }
 
     /// Helper: indexed assignment arr[index] = value
-    pub fn indexed_assign(name: String, index: Instruction, value: Instruction) -> Self {
+    pub fn indexed_assign(name: String, index: InstrId, value: InstrId) -> Self {
         Instruction::IndexedAssign {
             name,
-            index: Box::new(index),
-            value: Box::new(value),
+            index,
+            value,
+        }
+    }
+
+    /// Direct child instructions (function bodies included)
+    pub fn children(&self) -> Vec<InstrId> {
+        match self {
+            Instruction::Sequence(items, _) => items.clone(),
+            Instruction::Scope(inner) => vec![*inner],
+            Instruction::Branch { condition, then_instr, else_instr } => {
+                let mut kids = vec![*condition, *then_instr];
+                kids.extend(*else_instr);
+                kids
+            }
+            Instruction::Assign { value, .. } => vec![*value],
+            Instruction::Invoke { args, .. } => args.clone(),
+            Instruction::Operate { operands, .. } => operands.clone(),
+            Instruction::Transfer { value, .. } => value.iter().copied().collect(),
+            Instruction::Loop { condition, body } | Instruction::UntilLoop { condition, body } => {
+                vec![*condition, *body]
+            }
+            Instruction::ForLoop { iterable, body, .. } => vec![*iterable, *body],
+            Instruction::FunctionDef { body, .. } => vec![*body],
+            Instruction::IndexedAssign { index, value, .. } => vec![*index, *value],
+            Instruction::Literal(_) | Instruction::Variable(_) | Instruction::SetMemoization { .. } => Vec::new(),
+        }
+    }
+
+    /// Direct child instructions, for renumbering
+    fn children_mut(&mut self) -> Vec<&mut InstrId> {
+        match self {
+            Instruction::Sequence(items, _) => items.iter_mut().collect(),
+            Instruction::Scope(inner) => vec![inner],
+            Instruction::Branch { condition, then_instr, else_instr } => {
+                let mut kids = vec![condition, then_instr];
+                kids.extend(else_instr.as_mut());
+                kids
+            }
+            Instruction::Assign { value, .. } => vec![value],
+            Instruction::Invoke { args, .. } => args.iter_mut().collect(),
+            Instruction::Operate { operands, .. } => operands.iter_mut().collect(),
+            Instruction::Transfer { value, .. } => value.iter_mut().collect(),
+            Instruction::Loop { condition, body } | Instruction::UntilLoop { condition, body } => {
+                vec![condition, body]
+            }
+            Instruction::ForLoop { iterable, body, .. } => vec![iterable, body],
+            Instruction::FunctionDef { body, .. } => vec![body],
+            Instruction::IndexedAssign { index, value, .. } => vec![index, value],
+            Instruction::Literal(_) | Instruction::Variable(_) | Instruction::SetMemoization { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Index of an instruction in its program's arena
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InstrId(u32);
+
+impl InstrId {
+    /// Position in the program's arena, for tables kept alongside it
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A reduced program: all of its instructions in one arena, operands referring to
+/// their instructions by index
+/// Reduce allocates one Vec instead of a box per node, and cloning a program copies it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Program {
+    instrs: Vec<Instruction>,
+}
+
+impl Program {
+    /// Store an instruction whose operands are already stored
+    pub fn add(&mut self, instr: Instruction) -> InstrId {
+        let id = InstrId(self.instrs.len() as u32);
+        self.instrs.push(instr);
+        id
+    }
+
+    /// The top-level instruction (stored last, after everything it refers to)
+    pub fn root(&self) -> InstrId {
+        InstrId(self.instrs.len().saturating_sub(1) as u32)
+    }
+
+    /// Number of instructions
+    pub fn len(&self) -> usize {
+        self.instrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instrs.is_empty()
+    }
+
+    /// Every instruction id in storage order (operands before the instructions using them)
+    pub fn ids(&self) -> impl Iterator<Item = InstrId> {
+        (0..self.instrs.len() as u32).map(InstrId)
+    }
+
+    /// Check that every operand is stored before the instruction using it and that each
+    /// sequence has a position per statement (always true of a reduced program; a program
+    /// read from a file may not be one)
+    pub fn check(&self) -> Result<(), String> {
+        for id in self.ids() {
+            let instr = &self[id];
+            if let Some(operand) = instr.children().into_iter().find(|operand| operand.0 >= id.0) {
+                return Err(format!("instruction {} refers to instruction {} stored after it", id.0, operand.0));
+            }
+            if let Instruction::Sequence(items, positions) = instr {
+                if items.len() != positions.len() {
+                    return Err(format!("sequence {} has {} statements but {} positions", id.0, items.len(), positions.len()));
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// One program running this program's top-level statements, then `next`'s
+    /// Both must be reduced programs (a sequence at the root). `next`'s instructions are
+    /// stored after this program's, so the result is the program reduced from the two
+    /// texts one after the other.
+    pub fn followed_by(mut self, next: &Program) -> Program {
+        let (Some(Instruction::Sequence(mut items, mut positions)), Some((Instruction::Sequence(next_items, next_positions), next_instrs))) =
+            (self.instrs.pop(), next.instrs.split_last())
+        else {
+            panic!("followed_by: both programs need a sequence at the root");
+        };
+        let offset = self.instrs.len() as u32;
+        for instr in next_instrs {
+            let mut instr = instr.clone();
+            for operand in instr.children_mut() {
+                operand.0 += offset;
+            }
+            self.instrs.push(instr);
+        }
+        items.extend(next_items.iter().map(|item| InstrId(item.0 + offset)));
+        positions.extend(next_positions);
+        self.add(Instruction::sequence(items, positions));
+        self
+    }
+
+    /// A program of the instructions reachable from `root`, each stored after its operands
+    /// For passes that add instructions as operands of ones already stored (which makes
+    /// the last one stored no longer the root); the result is in evaluation order.
+    pub fn relaid(&self, root: InstrId) -> Program {
+        let mut moved: Vec<Option<InstrId>> = vec![None; self.instrs.len()];
+        let mut program = Program::default();
+        let mut pending = vec![(root, false)];
+        while let Some((id, operands_stored)) = pending.pop() {
+            if moved[id.0 as usize].is_some() {
+                continue;
+            }
+            if !operands_stored {
+                pending.push((id, true));
+                pending.extend(self[id].children().into_iter().rev().map(|operand| (operand, false)));
+                continue;
+            }
+            let mut instr = self[id].clone();
+            for operand in instr.children_mut() {
+                *operand = moved[operand.0 as usize].expect("operands are stored first");
+            }
+            moved[id.0 as usize] = Some(program.add(instr));
+        }
+        program
+    }
+
+    /// A program of the instructions reachable from `root` alone, `root` last
+    /// Kept instructions stay in storage order, so operands still come before their users.
+    pub fn extract(&self, root: InstrId) -> Program {
+        let mut reachable = vec![false; self.instrs.len()];
+        let mut pending = vec![root];
+        while let Some(id) = pending.pop() {
+            if !std::mem::replace(&mut reachable[id.0 as usize], true) {
+                pending.extend(self[id].children());
+            }
+        }
+        let mut renumbered = vec![InstrId(0); self.instrs.len()];
+        let mut program = Program::default();
+        for id in self.ids().filter(|id| reachable[id.0 as usize]) {
+            let mut instr = self[id].clone();
+            for operand in instr.children_mut() {
+                *operand = renumbered[operand.0 as usize];
+            }
+            renumbered[id.0 as usize] = program.add(instr);
+        }
+        program
+    }
+
+    /// The instruction tree rooted at `id`, formatted with {:#?} as nested instructions
+    pub fn tree(&self, id: InstrId) -> Tree<'_> {
+        Tree { program: self, id }
+    }
+}
+
+impl std::ops::Index<InstrId> for Program {
+    type Output = Instruction;
+
+    fn index(&self, id: InstrId) -> &Instruction {
+        &self.instrs[id.0 as usize]
+    }
+}
+
+impl std::ops::IndexMut<InstrId> for Program {
+    fn index_mut(&mut self, id: InstrId) -> &mut Instruction {
+        &mut self.instrs[id.0 as usize]
+    }
+}
+
+/// An instruction with its operands resolved, for printing (see Program::tree)
+pub struct Tree<'a> {
+    program: &'a Program,
+    id: InstrId,
+}
+
+impl fmt::Debug for Tree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tree = |id: InstrId| self.program.tree(id);
+        let trees = |ids: &[InstrId]| ids.iter().map(|id| self.program.tree(*id)).collect::<Vec<_>>();
+        match &self.program[self.id] {
+            Instruction::Sequence(items, positions) => {
+                f.debug_tuple("Sequence").field(&trees(items)).field(positions).finish()
+            }
+            Instruction::Scope(inner) => f.debug_tuple("Scope").field(&tree(*inner)).finish(),
+            Instruction::Branch { condition, then_instr, else_instr } => f
+                .debug_struct("Branch")
+                .field("condition", &tree(*condition))
+                .field("then_instr", &tree(*then_instr))
+                .field("else_instr", &else_instr.map(tree))
+                .finish(),
+            Instruction::Assign { name, value } => {
+                f.debug_struct("Assign").field("name", name).field("value", &tree(*value)).finish()
+            }
+            Instruction::Invoke { function, args } => {
+                f.debug_struct("Invoke").field("function", function).field("args", &trees(args)).finish()
+            }
+            Instruction::Operate { kind, operands } => {
+                f.debug_struct("Operate").field("kind", kind).field("operands", &trees(operands)).finish()
+            }
+            Instruction::Transfer { kind, value } => {
+                f.debug_struct("Transfer").field("kind", kind).field("value", &value.map(tree)).finish()
+            }
+            Instruction::Loop { condition, body } => f
+                .debug_struct("Loop")
+                .field("condition", &tree(*condition))
+                .field("body", &tree(*body))
+                .finish(),
+            Instruction::ForLoop { var, iterable, body } => f
+                .debug_struct("ForLoop")
+                .field("var", var)
+                .field("iterable", &tree(*iterable))
+                .field("body", &tree(*body))
+                .finish(),
+            Instruction::UntilLoop { condition, body } => f
+                .debug_struct("UntilLoop")
+                .field("condition", &tree(*condition))
+                .field("body", &tree(*body))
+                .finish(),
+            Instruction::FunctionDef { name, params, body, doc } => f
+                .debug_struct("FunctionDef")
+                .field("name", name)
+                .field("params", params)
+                .field("body", &tree(*body))
+                .field("doc", doc)
+                .finish(),
+            Instruction::IndexedAssign { name, index, value } => f
+                .debug_struct("IndexedAssign")
+                .field("name", name)
+                .field("index", &tree(*index))
+                .field("value", &tree(*value))
+                .finish(),
+            // No operands: the same as the instruction's own Debug
+            other => other.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::disasm::disassemble;
+    use crate::kernel::{parse_program, reduce_with_prelude_timed, Passes, Timing};
+    use crate::languages::lumen_schema;
+
+    #[test]
+    fn linking_after_a_prelude_matches_reducing_the_joined_text() {
+        let schema = lumen_schema::get_schema();
+        let prelude = "fn twice(x)\n    return x * 2\nlimit = 10\n";
+        let source = "y = twice(limit)\nif y > 5\n    emit(y)\n";
+        let joined = parse_program(&format!("{}\n{}", prelude, source), &schema).unwrap();
+        let linked = reduce_with_prelude_timed(
+            parse_program(prelude, &schema).unwrap(),
+            prelude.matches('\n').count() + 2,
+            source,
+            &schema,
+            &mut Timing::start(),
+            Passes::default(),
+        )
+        .unwrap();
+        assert_eq!(disassemble(&linked), disassemble(&joined));
+        linked.check().unwrap();
     }
 }
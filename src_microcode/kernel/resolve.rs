@@ -0,0 +1,174 @@
+// Variable resolution: slots for names instead of hash lookups
+//
+// Run once before execution. Every scope the execute stage enters (the global scope, a
+// function call, a Scope instruction) gets a layout: the names bound directly in it
+// (parameters first, then assignment, for-loop and function names), one slot each. A
+// variable read or assignment is resolved to a (depth, slot) pair: the slot of the
+// name in the layout of the innermost scope that can bind it, `depth` scopes out.
+//
+// Lumen scoping is dynamic - a function sees its callers' variables - so resolution
+// stops at the scope of the function being defined: a name no layout up to there binds
+// is left to be looked up by name at run time (usually a global or a function name),
+// and so is a resolved name whose slot is still empty when it is read (e.g. assigned in
+// a branch that did not run). What a program does is unchanged; only lookups of names
+// bound in the running function (or at the top level) get cheaper.
+
+use super::primitives::{InstrId, Instruction, Program};
+use std::sync::Arc;
+
+/// Where a resolved name lives: slot `index` of the scope `depth` scopes out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+/// Names with a slot in a scope, in slot order
+pub type Layout = Arc<[String]>;
+
+/// Slots and layouts of a program
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    /// Slot of each Variable, Assign, ForLoop and FunctionDef name, by instruction
+    slots: Vec<Option<Slot>>,
+    /// Scope layout of each FunctionDef (its calls) and Scope instruction, by instruction
+    layouts: Vec<Option<Layout>>,
+    /// Layout of the global scope (the program's top level)
+    pub global: Layout,
+}
+
+impl Resolution {
+    /// The slot resolved for an instruction's name, if any
+    pub fn slot(&self, id: InstrId) -> Option<Slot> {
+        self.slots.get(id.index()).copied().flatten()
+    }
+
+    /// The layout of the scope a FunctionDef's calls or a Scope instruction run in
+    pub fn layout(&self, id: InstrId) -> Layout {
+        self.layouts.get(id.index()).cloned().flatten().unwrap_or_else(|| Arc::from([]))
+    }
+
+    /// How many names were resolved to slots
+    pub fn resolved(&self) -> usize {
+        self.slots.iter().flatten().count()
+    }
+}
+
+/// A scope being laid out
+struct Frame {
+    names: Vec<String>,
+    /// The enclosing scope, unless this is the global scope or a function's
+    parent: Option<usize>,
+}
+
+impl Frame {
+    fn bind(&mut self, name: &str) {
+        if !self.names.iter().any(|bound| bound == name) {
+            self.names.push(name.to_string());
+        }
+    }
+}
+
+/// Lay out every scope of `program` and resolve its names
+pub fn resolve(program: &Program) -> Resolution {
+    let mut frames = vec![Frame { names: Vec::new(), parent: None }];
+    let mut frame_of = vec![None; program.len()];
+    let mut owners = Vec::new();
+
+    // Layouts: which names each scope binds (a name may be read before it is assigned,
+    // so every layout is complete before any name is resolved)
+    let mut pending = vec![(program.root(), 0)];
+    while let Some((id, frame)) = pending.pop() {
+        frame_of[id.index()] = Some(frame);
+        let inner = match &program[id] {
+            Instruction::FunctionDef { name, params, .. } => {
+                frames[frame].bind(name);
+                let mut function = Frame { names: Vec::new(), parent: None };
+                params.iter().for_each(|param| function.bind(param));
+                Some(function)
+            }
+            Instruction::Scope(_) => Some(Frame { names: Vec::new(), parent: Some(frame) }),
+            Instruction::Assign { name, .. } | Instruction::ForLoop { var: name, .. } => {
+                frames[frame].bind(name);
+                None
+            }
+            _ => None,
+        };
+        let children_frame = match inner {
+            Some(inner) => {
+                frames.push(inner);
+                owners.push((id, frames.len() - 1));
+                frames.len() - 1
+            }
+            None => frame,
+        };
+        // Children in source order, so names get slots in the order they are first bound
+        pending.extend(program[id].children().into_iter().rev().map(|child| (child, children_frame)));
+    }
+
+    // Names: the innermost layout binding each one, up to the enclosing function
+    let find = |mut frame: usize, name: &str| {
+        let mut depth = 0;
+        loop {
+            if let Some(index) = frames[frame].names.iter().position(|bound| bound == name) {
+                return Some(Slot { depth, index });
+            }
+            frame = frames[frame].parent?;
+            depth += 1;
+        }
+    };
+    let mut slots = vec![None; program.len()];
+    // (only instructions the root reaches run)
+    for id in program.ids() {
+        let Some(frame) = frame_of[id.index()] else {
+            continue;
+        };
+        let name = match &program[id] {
+            Instruction::Variable(name)
+            | Instruction::Assign { name, .. }
+            | Instruction::ForLoop { var: name, .. }
+            | Instruction::FunctionDef { name, .. } => name,
+            _ => continue,
+        };
+        slots[id.index()] = find(frame, name);
+    }
+
+    let layout = |frame: &Frame| Layout::from(frame.names.clone());
+    let mut layouts = vec![None; program.len()];
+    for (owner, frame) in owners {
+        layouts[owner.index()] = Some(layout(&frames[frame]));
+    }
+    Resolution { slots, layouts, global: layout(&frames[0]) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::parse_program;
+    use crate::languages::lumen_schema;
+
+    #[test]
+    fn resolves_locals_and_leaves_caller_and_global_names_to_lookup() {
+        let source = "limit = 3\nfn count(n)\n    total = 0\n    for i in 0..n\n        total = total + i + limit\n    return total\nx = count(limit)\n";
+        let program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+        let program = program.extract(program.root());
+        let resolution = resolve(&program);
+        assert_eq!(&resolution.global[..], ["limit", "count", "x"]);
+
+        let slot_of = |wanted: &str| -> Vec<Option<Slot>> {
+            program
+                .ids()
+                .filter(|id| matches!(&program[*id], Instruction::Variable(name) if name == wanted))
+                .map(|id| resolution.slot(id))
+                .collect()
+        };
+        // In count: n, total and i are its own; limit is the caller's (dynamic) or global
+        assert_eq!(slot_of("n"), [Some(Slot { depth: 0, index: 0 })]);
+        assert_eq!(slot_of("total"), [Some(Slot { depth: 0, index: 1 }); 2]);
+        assert_eq!(slot_of("i"), [Some(Slot { depth: 0, index: 2 })]);
+        assert_eq!(slot_of("limit"), [None, Some(Slot { depth: 0, index: 0 })]);
+
+        let count = program.ids().find(|id| matches!(&program[*id], Instruction::FunctionDef { .. })).unwrap();
+        assert_eq!(&resolution.layout(count)[..], ["n", "total", "i"]);
+    }
+}
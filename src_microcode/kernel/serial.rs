@@ -0,0 +1,106 @@
+// Saved programs: the reduced instruction tree as JSON or CBOR
+// (--emit-program / --load-program)
+//
+// A saved program is the output of stage 3 (after any --opt passes) with the name of the
+// language it was reduced from, whose schema the execute stage needs:
+//
+//   {"version":1,"language":"lumen","program":{"instrs":[{"Literal":{"Number":[1,[5]]}},...]}}
+//
+// CBOR holds the same document in binary. Loading checks the version and that every
+// operand refers to an instruction stored before it, so a damaged or stale file is an
+// error instead of a crash in the execute stage.
+
+use super::primitives::Program;
+use serde::{Deserialize, Serialize};
+
+/// Version of the saved-program layout; bumped whenever Instruction or Value change shape
+pub const VERSION: u32 = 1;
+
+/// How a saved program is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Cbor,
+}
+
+impl Format {
+    /// CBOR for a `.cbor` file, JSON otherwise
+    pub fn for_path(path: &str) -> Format {
+        if path.ends_with(".cbor") {
+            Format::Cbor
+        } else {
+            Format::Json
+        }
+    }
+}
+
+/// A reduced program and the language it was reduced from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedProgram {
+    pub version: u32,
+    pub language: String,
+    pub program: Program,
+}
+
+impl SavedProgram {
+    pub fn new(language: &str, program: Program) -> Self {
+        SavedProgram { version: VERSION, language: language.to_string(), program }
+    }
+}
+
+/// Encode a saved program
+pub fn save(saved: &SavedProgram, format: Format) -> Result<Vec<u8>, String> {
+    match format {
+        Format::Json => serde_json::to_vec(saved).map_err(|e| e.to_string()),
+        Format::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(saved, &mut bytes).map_err(|e| e.to_string())?;
+            Ok(bytes)
+        }
+    }
+}
+
+/// Decode and check a saved program
+pub fn load(bytes: &[u8], format: Format) -> Result<SavedProgram, String> {
+    let saved: SavedProgram = match format {
+        Format::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string())?,
+        Format::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string())?,
+    };
+    if saved.version != VERSION {
+        return Err(format!(
+            "saved program has layout version {}, this kernel reads version {}",
+            saved.version, VERSION
+        ));
+    }
+    saved.program.check()?;
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::disasm::disassemble;
+    use crate::kernel::parse_program;
+    use crate::languages::lumen_schema;
+
+    #[test]
+    fn round_trips_through_json_and_cbor() {
+        let source = "x = 3/4\nif x < 1\n    emit(\"small\" . x)\n";
+        let program = parse_program(source, &lumen_schema::get_schema()).unwrap();
+        let saved = SavedProgram::new("lumen", program);
+        for format in [Format::Json, Format::Cbor] {
+            let loaded = load(&save(&saved, format).unwrap(), format).unwrap();
+            assert_eq!(loaded.language, "lumen");
+            assert_eq!(disassemble(&loaded.program), disassemble(&saved.program));
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions_and_forward_references() {
+        let json = r#"{"version":1,"language":"lumen","program":{"instrs":[{"Scope":1},{"Variable":"x"}]}}"#;
+        let error = load(json.as_bytes(), Format::Json).unwrap_err();
+        assert_eq!(error, "instruction 0 refers to instruction 1 stored after it");
+        let json = json.replace("\"version\":1", "\"version\":0");
+        assert!(load(json.as_bytes(), Format::Json).unwrap_err().contains("version 0"));
+    }
+}
@@ -0,0 +1,111 @@
+// Prelude tree shaking: link only the prelude definitions a program uses
+//
+// Run when a program is linked after its prelude (see reduce_with_prelude_timed). A
+// top-level `fn` or assignment in the prelude is kept if its name is used by the
+// program, or by another kept definition; other top-level prelude statements always
+// run and count as users. A name is used where it appears as
+//
+//   a call or variable      print(x), f |> g, mock_extern("clock.now", fake_now)
+//   an assignment target    alphabet = ..., total[0] = ...
+//
+// Functions are only ever called through one of these names, so dropping the rest of
+// the prelude changes nothing but the work done to define it. The kept statements run
+// in their original order.
+
+use super::primitives::{InstrId, Instruction, Program};
+use std::collections::{HashMap, HashSet};
+
+/// The part of `prelude` that `program` uses, and how many top-level statements were dropped
+pub fn shake(prelude: Program, program: &Program) -> (Program, usize) {
+    let root = prelude.root();
+    let Instruction::Sequence(items, positions) = &prelude[root] else {
+        return (prelude, 0);
+    };
+
+    // Definitions by name (a name may be defined more than once; the last one wins at run time)
+    let mut definitions: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut keep = vec![false; items.len()];
+    let mut wanted: Vec<&str> = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        match &prelude[*item] {
+            Instruction::FunctionDef { name, .. } | Instruction::Assign { name, .. } => {
+                definitions.entry(name.as_str()).or_default().push(index);
+            }
+            _ => {
+                keep[index] = true;
+                names(&prelude, *item, &mut wanted);
+            }
+        }
+    }
+    for id in program.ids() {
+        name(&program[id], &mut wanted);
+    }
+
+    let mut seen = HashSet::new();
+    while let Some(used) = wanted.pop() {
+        if !seen.insert(used) {
+            continue;
+        }
+        for &index in definitions.get(used).into_iter().flatten() {
+            if !std::mem::replace(&mut keep[index], true) {
+                names(&prelude, items[index], &mut wanted);
+            }
+        }
+    }
+
+    let dropped = keep.iter().filter(|kept| !**kept).count();
+    if dropped == 0 {
+        return (prelude, 0);
+    }
+    let kept: Vec<usize> = (0..items.len()).filter(|&index| keep[index]).collect();
+    let sequence = Instruction::sequence(
+        kept.iter().map(|&index| items[index]).collect(),
+        kept.iter().map(|&index| positions[index]).collect(),
+    );
+    let mut prelude = prelude;
+    let root = prelude.add(sequence);
+    (prelude.extract(root), dropped)
+}
+
+/// Every name used in the tree rooted at `id`
+fn names<'a>(program: &'a Program, id: InstrId, found: &mut Vec<&'a str>) {
+    let mut pending = vec![id];
+    while let Some(id) = pending.pop() {
+        name(&program[id], found);
+        pending.extend(program[id].children());
+    }
+}
+
+/// The name one instruction uses, if any
+fn name<'a>(instr: &'a Instruction, found: &mut Vec<&'a str>) {
+    match instr {
+        Instruction::Invoke { function: name, .. }
+        | Instruction::Variable(name)
+        | Instruction::Assign { name, .. }
+        | Instruction::IndexedAssign { name, .. } => found.push(name),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::disasm::disassemble;
+    use crate::kernel::parse_program;
+    use crate::languages::lumen_schema;
+
+    #[test]
+    fn keeps_used_definitions_and_what_they_use() {
+        let schema = lumen_schema::get_schema();
+        let prelude = "digits = \"0123456789\"\nfn digit(n)\n    return digits[n]\nfn show(n)\n    return digit(n)\nfn unused()\n    return 0\nfn fake_now(args)\n    return 1\nemit(\"ready\\n\")\n";
+        let program = parse_program("print(show(3))\nmock_extern(\"clock.now\", fake_now)\n", &schema).unwrap();
+        let (shaken, dropped) = shake(parse_program(prelude, &schema).unwrap(), &program);
+        assert_eq!(dropped, 1);
+        shaken.check().unwrap();
+        let listing = disassemble(&shaken);
+        for kept in ["Assign digits", "FunctionDef digit", "FunctionDef show", "FunctionDef fake_now", "Invoke emit"] {
+            assert!(listing.contains(kept), "{} missing from\n{}", kept, listing);
+        }
+        assert!(!listing.contains("unused"), "{}", listing);
+    }
+}
@@ -17,7 +17,7 @@ pub fn get_schema() -> LanguageSchema {
         "==", "!=", "<=", ">=", "**", "->", "|>", "..", "//",
 
         // Keywords
-        "let", "mut", "if", "else", "while", "for", "until", "in", "break", "continue", "return", "fn",
+        "let", "mut", "if", "else", "while", "for", "until", "in", "break", "continue", "return", "exit", "fn",
         "and", "or", "not", "print", "true", "false", "null", "extern", "type",
 
         // Single-char operators
@@ -26,13 +26,15 @@ pub fn get_schema() -> LanguageSchema {
     ];
 
     // Keywords requiring word boundaries
-    schema.word_boundary_keywords = vec![
-        "let", "mut", "if", "else", "while", "for", "until", "in", "break", "continue", "return", "fn",
+    schema.word_boundary_keywords = [
+        "let", "mut", "if", "else", "while", "for", "until", "in", "break", "continue", "return", "exit", "fn",
         "and", "or", "not", "print", "true", "false", "null", "extern", "type",
-    ];
+    ]
+    .into_iter()
+    .collect();
 
     // Statement terminators
-    schema.terminators = vec!["\n", ";"];
+    schema.terminators = ["\n", ";"].into_iter().collect();
 
     // Binary operators with precedence and associativity (from lumen.yaml lines 147-256)
     // Precedence: higher number = tighter binding
@@ -57,8 +59,8 @@ pub fn get_schema() -> LanguageSchema {
         short_circuit: true,
     });
 
-    // Comparison operators
-    for op in &["==", "!=", "<", ">", "<=", ">="] {
+    // Comparison and membership operators
+    for op in &["==", "!=", "<", ">", "<=", ">=", "in"] {
         schema.binary_operators.insert(op.to_string(), OperatorInfo {
             precedence: 4.0,
             associativity: Associativity::Left,
@@ -66,6 +68,13 @@ pub fn get_schema() -> LanguageSchema {
         });
     }
 
+    // Range operator
+    schema.binary_operators.insert("..".to_string(), OperatorInfo {
+        precedence: 4.5,
+        associativity: Associativity::Left,
+        short_circuit: false,
+    });
+
     // Additive operators
     for op in &["+", "-"] {
         schema.binary_operators.insert(op.to_string(), OperatorInfo {
@@ -102,10 +111,10 @@ pub fn get_schema() -> LanguageSchema {
     });
 
     // Keywords
-    schema.keywords = vec![
-        "let", "mut", "if", "else", "while", "for", "break", "continue", "return", "fn",
+    schema.keywords = [
+        "let", "mut", "if", "else", "while", "for", "until", "break", "continue", "return", "exit", "fn",
         "and", "or", "not", "print", "true", "false", "null", "extern", "type",
-    ].into_iter().map(|s| s.to_string()).collect();
+    ].into_iter().collect();
 
     // Indentation settings (from lumen.yaml lines 124-141)
     schema.indentation_size = 4;
@@ -113,5 +122,8 @@ pub fn get_schema() -> LanguageSchema {
     schema.block_open_marker = "".to_string();  // No marker; indentation alone introduces blocks
     schema.block_close_marker = "DEDENT".to_string();
 
+    // Host capabilities: extern("fs:read", path)
+    schema.extern_syntax = Some("extern");
+
     schema
 }
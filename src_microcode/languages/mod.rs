@@ -2,11 +2,99 @@
 //
 // Each language is defined entirely as data in its schema.
 // The kernel interprets code according to the schema - no language-specific logic in kernel.
+//
+// The built-in languages are listed in LANGUAGES; a host can add more while it runs
+// (the microcode binary registers those of --plugin libraries, see plugin.rs, and of
+// --schema files, see schema_file.rs).
 
 pub mod lumen;
 pub mod rust_core;
 pub mod python_core;
+pub mod schema_file;
 
 pub use lumen::schema as lumen_schema;
 pub use rust_core::schema as rust_core_schema;
 pub use python_core::schema as python_core_schema;
+
+use std::sync::RwLock;
+
+use crate::schema::LanguageSchema;
+
+/// A registered frontend: its --lang name, source file extension and schema
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    pub extension: &'static str,
+    pub schema: Schema,
+}
+
+/// Where a language's schema comes from
+#[derive(Debug)]
+pub enum Schema {
+    /// Built by Rust code (the built-in languages and plugins)
+    Compiled(fn() -> LanguageSchema),
+    /// Read from a schema file
+    Loaded(Box<LanguageSchema>),
+}
+
+impl Language {
+    /// The language's schema
+    pub fn get_schema(&self) -> LanguageSchema {
+        match &self.schema {
+            Schema::Compiled(build) => build(),
+            Schema::Loaded(schema) => LanguageSchema::clone(schema),
+        }
+    }
+}
+
+/// Every language the kernel runs, in listing order
+pub static LANGUAGES: [Language; 3] = [
+    Language { name: "lumen", extension: "lm", schema: Schema::Compiled(lumen_schema::get_schema) },
+    Language { name: "rust_core", extension: "rs", schema: Schema::Compiled(rust_core_schema::get_schema) },
+    Language { name: "python_core", extension: "py", schema: Schema::Compiled(python_core_schema::get_schema) },
+];
+
+/// Languages registered at run time, after the built-in ones
+static REGISTERED: RwLock<Vec<&'static Language>> = RwLock::new(Vec::new());
+
+/// Add a language, looked up after the built-in ones; its name must be new
+pub fn register(language: &'static Language) -> Result<(), String> {
+    if by_name(language.name).is_some() {
+        return Err(format!("Language '{}' is already defined", language.name));
+    }
+    REGISTERED.write().unwrap_or_else(|e| e.into_inner()).push(language);
+    Ok(())
+}
+
+/// Every language: the built-in ones, then those registered at run time
+pub fn all() -> Vec<&'static Language> {
+    let registered = REGISTERED.read().unwrap_or_else(|e| e.into_inner());
+    LANGUAGES.iter().chain(registered.iter().copied()).collect()
+}
+
+/// Look up a language by its --lang name
+pub fn by_name(name: &str) -> Option<&'static Language> {
+    all().into_iter().find(|language| language.name == name)
+}
+
+/// Look up a language by source file extension (without the dot)
+pub fn by_extension(extension: &str) -> Option<&'static Language> {
+    all().into_iter().find(|language| language.extension == extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_languages_are_found_after_the_built_in_ones() {
+        static TOY: Language = Language { name: "toy_registered", extension: "toy", schema: Schema::Compiled(lumen_schema::get_schema) };
+        static SHADOW: Language = Language { name: "lumen", extension: "lm2", schema: Schema::Compiled(lumen_schema::get_schema) };
+        register(&TOY).unwrap();
+        assert!(std::ptr::eq(by_name("toy_registered").unwrap(), &TOY));
+        assert_eq!(by_extension("toy").unwrap().name, "toy_registered");
+        assert!(register(&TOY).unwrap_err().contains("already defined"));
+        assert!(register(&SHADOW).is_err());
+        assert!(std::ptr::eq(by_name("lumen").unwrap(), &LANGUAGES[0]));
+    }
+}
@@ -14,7 +14,7 @@ pub fn get_schema() -> LanguageSchema {
 
         // Keywords
         "def", "if", "elif", "else", "while", "for", "break", "continue", "return",
-        "and", "or", "not", "print", "True", "False", "None", "in", "pass",
+        "and", "or", "not", "print", "True", "False", "None", "in", "pass", "extern",
         "true", "false", "none",  // Also support lowercase variants
 
         // Single-char operators
@@ -23,14 +23,16 @@ pub fn get_schema() -> LanguageSchema {
     ];
 
     // Keywords requiring word boundaries
-    schema.word_boundary_keywords = vec![
+    schema.word_boundary_keywords = [
         "def", "if", "elif", "else", "while", "for", "break", "continue", "return",
-        "and", "or", "not", "print", "True", "False", "None", "in", "pass",
+        "and", "or", "not", "print", "True", "False", "None", "in", "pass", "extern",
         "true", "false", "none",  // Also support lowercase variants
-    ];
+    ]
+    .into_iter()
+    .collect();
 
     // Statement terminators
-    schema.terminators = vec!["\n", ";"];
+    schema.terminators = ["\n", ";"].into_iter().collect();
 
     // Binary operators (PythonCore-like precedence)
     schema.binary_operators.insert("=".to_string(), OperatorInfo {
@@ -89,10 +91,10 @@ pub fn get_schema() -> LanguageSchema {
         position: UnaryPosition::Prefix,
     });
 
-    schema.keywords = vec![
+    schema.keywords = [
         "def", "if", "elif", "else", "while", "for", "break", "continue", "return",
-        "and", "or", "not", "print", "True", "False", "None", "in", "pass",
-    ].into_iter().map(|s| s.to_string()).collect();
+        "and", "or", "not", "print", "True", "False", "None", "in", "pass", "extern",
+    ].into_iter().collect();
 
     // PythonCore-like indentation settings
     schema.indentation_size = 4;
@@ -100,5 +102,8 @@ pub fn get_schema() -> LanguageSchema {
     schema.block_open_marker = ":".to_string();
     schema.block_close_marker = "DEDENT".to_string();
 
+    // Host capabilities, with Lumen's selectors: extern("fs:read", path)
+    schema.extern_syntax = Some("extern");
+
     schema
 }
@@ -1,4 +1,4 @@
-use crate::schema::{LanguageSchema, OperatorInfo, UnaryOperatorInfo, Associativity, UnaryPosition};
+use crate::schema::{LanguageSchema, OperatorInfo, UnaryOperatorInfo, Associativity, UnaryPosition, IntegerOverflow};
 
 pub fn get_schema() -> LanguageSchema {
     let mut schema = LanguageSchema::new();
@@ -7,17 +7,19 @@ pub fn get_schema() -> LanguageSchema {
     schema.multichar_lexemes = vec![
         "==", "!=", "<=", ">=", "**", "->", "&&", "||",
         "let", "mut", "if", "else", "while", "for", "break", "continue", "return", "fn",
-        "and", "or", "not", "print", "true", "false", "none",
+        "and", "or", "not", "print", "true", "false", "none", "extern",
         ":", "=", "+", "-", "*", "/", "%", "<", ">", "!", "&", "|", "^", "~",
         "(", ")", "{", "}", "[", "]", ",", ".", ";",
     ];
 
-    schema.word_boundary_keywords = vec![
+    schema.word_boundary_keywords = [
         "let", "mut", "if", "else", "while", "for", "break", "continue", "return", "fn",
-        "and", "or", "not", "print", "true", "false", "none",
-    ];
+        "and", "or", "not", "print", "true", "false", "none", "extern",
+    ]
+    .into_iter()
+    .collect();
 
-    schema.terminators = vec!["\n", ";"];
+    schema.terminators = ["\n", ";"].into_iter().collect();
 
     // Binary operators (similar to Lumen but no pipe operator)
     schema.binary_operators.insert("=".to_string(), OperatorInfo {
@@ -91,15 +93,21 @@ pub fn get_schema() -> LanguageSchema {
         position: UnaryPosition::Prefix,
     });
 
-    schema.keywords = vec![
+    schema.keywords = [
         "let", "mut", "if", "else", "while", "for", "break", "continue", "return", "fn",
-        "and", "or", "not", "print", "true", "false", "none",
-    ].into_iter().map(|s| s.to_string()).collect();
+        "and", "or", "not", "print", "true", "false", "none", "extern",
+    ].into_iter().collect();
 
     // Mini-RustCore uses braces, not indentation
     schema.indentation_size = 0;
     schema.block_open_marker = "{".to_string();
     schema.block_close_marker = "}".to_string();
 
+    // i64 integers: overflow is an error, as in a debug build
+    schema.integer_overflow = IntegerOverflow::Checked;
+
+    // Host capabilities, with Lumen's selectors: extern("fs:read", path)
+    schema.extern_syntax = Some("extern");
+
     schema
 }
@@ -0,0 +1,343 @@
+// Schema files: a language defined by a TOML or JSON file read at run time (--schema)
+//
+// A schema is only data, so a language needs no Rust code at all: a file names it and
+// lists the tables a LanguageSchema holds (see schema.rs). In TOML:
+//
+//   name = "mini"                  # --lang name
+//   extension = "mini"             # source file extension (without the dot)
+//   keywords = ["if", "else", "while", "fn", "return", "and", "or", "not"]
+//   terminators = ["\n"]
+//   block_open_marker = ":"        # ":" and indentation, or "{" for braces
+//   integer_overflow = "checked"   # i64 integers; or "wrapping", "saturating" ("unbounded")
+//   extern_syntax = "extern"       # host capabilities: extern("fs:read", path) (none)
+//
+//   [binary_operators]
+//   "=" = { precedence = 1, associativity = "right" }
+//   "or" = { precedence = 2, short_circuit = true }
+//   "+" = { precedence = 5 }
+//
+//   [unary_operators]
+//   "-" = { precedence = 7 }      # position = "prefix" (or "postfix")
+//
+// The reducer parses expressions by these tables alone: precedence (higher binds tighter),
+// associativity, and prefix or postfix position, for any lexeme; what an operator computes
+// is the kernel's, so one it does not know parses but fails when run. short_circuit
+// applies to and, or, && and ||. A statement form (if, while, fn, ...) is parsed only if its
+// word is among the keywords, and only the terminators separate statements on a line.
+//
+// A .json file holds the same keys in one object. Omitted settings take
+// LanguageSchema::new()'s values, except that word_boundary_keywords defaults to the
+// keywords, and multichar_lexemes to the keywords plus every operator and terminator
+// longer than one character. Unknown keys are an error, so a misspelt one is not ignored.
+//
+// The schema's strings live as long as the process (the kernel's tables hold &'static str);
+// a file is read once per run.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::Value as Json;
+
+use super::{Language, Schema};
+use crate::schema::{Associativity, IntegerOverflow, LanguageSchema, OperatorInfo, UnaryOperatorInfo, UnaryPosition};
+
+/// The keys of a schema file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaFile {
+    name: String,
+    extension: String,
+    #[serde(default)]
+    keywords: Vec<String>,
+    word_boundary_keywords: Option<Vec<String>>,
+    multichar_lexemes: Option<Vec<String>>,
+    #[serde(default)]
+    terminators: Vec<String>,
+    #[serde(default)]
+    binary_operators: HashMap<String, BinaryOperator>,
+    #[serde(default)]
+    unary_operators: HashMap<String, UnaryOperator>,
+    indentation_size: Option<usize>,
+    indentation_char: Option<char>,
+    block_open_marker: Option<String>,
+    block_close_marker: Option<String>,
+    #[serde(default)]
+    integer_overflow: OverflowName,
+    extern_syntax: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BinaryOperator {
+    precedence: f32,
+    #[serde(default)]
+    associativity: AssociativityName,
+    #[serde(default)]
+    short_circuit: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UnaryOperator {
+    precedence: f32,
+    #[serde(default)]
+    position: PositionName,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AssociativityName {
+    #[default]
+    Left,
+    Right,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PositionName {
+    #[default]
+    Prefix,
+    Postfix,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OverflowName {
+    #[default]
+    Unbounded,
+    Checked,
+    Wrapping,
+    Saturating,
+}
+
+/// Read the schema file at `path` (.toml or .json) and register its language
+pub fn load(path: &str) -> Result<&'static Language, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
+    let language = parse(&text, extension).map_err(|e| format!("{}: {}", path, e))?;
+    let language: &'static Language = Box::leak(Box::new(language));
+    super::register(language).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(language)
+}
+
+/// The language defined by the text of a schema file in `format` ("toml" or "json")
+pub fn parse(text: &str, format: &str) -> Result<Language, String> {
+    let json = match format {
+        "toml" => {
+            let document: toml_edit::DocumentMut = text.parse().map_err(|e| format!("invalid TOML: {}", e))?;
+            toml_table(document.as_table())
+        }
+        "json" => serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))?,
+        _ => return Err("schema files must be .toml or .json".to_string()),
+    };
+    let file: SchemaFile = serde_json::from_value(json).map_err(|e| e.to_string())?;
+    to_language(file)
+}
+
+fn to_language(file: SchemaFile) -> Result<Language, String> {
+    let leak = |text: String| -> &'static str { Box::leak(text.into_boxed_str()) };
+    let leak_all = |texts: Vec<String>| -> Vec<&'static str> { texts.into_iter().map(leak).collect() };
+    if file.name.is_empty() || !file.name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(format!("name '{}' must be letters, digits and '_'", file.name));
+    }
+
+    let mut schema = LanguageSchema::new();
+    for (op, info) in file.binary_operators {
+        if info.short_circuit && !matches!(op.as_str(), "and" | "or" | "&&" | "||") {
+            return Err(format!("binary operator '{}': only and, or, && and || can short_circuit", op));
+        }
+        let associativity = match info.associativity {
+            AssociativityName::Left => Associativity::Left,
+            AssociativityName::Right => Associativity::Right,
+        };
+        schema.binary_operators.insert(op, OperatorInfo { precedence: info.precedence, associativity, short_circuit: info.short_circuit });
+    }
+    for (op, info) in file.unary_operators {
+        let position = match info.position {
+            PositionName::Prefix => UnaryPosition::Prefix,
+            PositionName::Postfix => UnaryPosition::Postfix,
+        };
+        schema.unary_operators.insert(op, UnaryOperatorInfo { precedence: info.precedence, position });
+    }
+
+    let keywords = leak_all(file.keywords);
+    schema.keywords = keywords.iter().copied().collect();
+    schema.word_boundary_keywords = match file.word_boundary_keywords {
+        Some(words) => leak_all(words).into_iter().collect(),
+        None => schema.keywords.clone(),
+    };
+    schema.terminators = leak_all(file.terminators).into_iter().collect();
+    schema.multichar_lexemes = match file.multichar_lexemes {
+        Some(lexemes) => leak_all(lexemes),
+        None => {
+            let symbols = schema.binary_operators.keys().chain(schema.unary_operators.keys());
+            let mut seen: HashSet<&str> = keywords.iter().copied().collect();
+            let mut lexemes = keywords.clone();
+            for symbol in symbols.map(String::as_str).chain(schema.terminators.iter().copied()) {
+                if symbol.len() > 1 && seen.insert(symbol) {
+                    lexemes.push(leak(symbol.to_string()));
+                }
+            }
+            lexemes
+        }
+    };
+    if let Some(size) = file.indentation_size {
+        if size == 0 {
+            return Err("indentation_size must be at least 1".to_string());
+        }
+        schema.indentation_size = size;
+    }
+    if let Some(ch) = file.indentation_char {
+        schema.indentation_char = ch;
+    }
+    if let Some(marker) = file.block_open_marker {
+        schema.block_open_marker = marker;
+    }
+    if let Some(marker) = file.block_close_marker {
+        schema.block_close_marker = marker;
+    }
+    schema.integer_overflow = match file.integer_overflow {
+        OverflowName::Unbounded => IntegerOverflow::Unbounded,
+        OverflowName::Checked => IntegerOverflow::Checked,
+        OverflowName::Wrapping => IntegerOverflow::Wrapping,
+        OverflowName::Saturating => IntegerOverflow::Saturating,
+    };
+    schema.extern_syntax = file.extern_syntax.map(leak);
+
+    Ok(Language { name: leak(file.name), extension: leak(file.extension), schema: Schema::Loaded(Box::new(schema)) })
+}
+
+/// A TOML table as the JSON object serde reads the schema from
+fn toml_table(table: &toml_edit::Table) -> Json {
+    Json::Object(table.iter().map(|(key, item)| (key.to_string(), toml_item(item))).collect())
+}
+
+fn toml_item(item: &toml_edit::Item) -> Json {
+    match item {
+        toml_edit::Item::Value(value) => toml_value(value),
+        toml_edit::Item::Table(table) => toml_table(table),
+        toml_edit::Item::ArrayOfTables(tables) => Json::Array(tables.iter().map(toml_table).collect()),
+        toml_edit::Item::None => Json::Null,
+    }
+}
+
+fn toml_value(value: &toml_edit::Value) -> Json {
+    use toml_edit::Value;
+    match value {
+        Value::String(s) => Json::from(s.value().as_str()),
+        Value::Integer(i) => Json::from(*i.value()),
+        Value::Float(f) => Json::from(*f.value()),
+        Value::Boolean(b) => Json::from(*b.value()),
+        Value::Datetime(d) => Json::from(d.value().to_string()),
+        Value::Array(array) => Json::Array(array.iter().map(toml_value).collect()),
+        Value::InlineTable(table) => Json::Object(table.iter().map(|(key, value)| (key.to_string(), toml_value(value))).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::run;
+    use crate::languages::lumen_schema::get_schema as lumen_schema;
+
+    const MINI: &str = r#"
+name = "mini"
+extension = "mini"
+keywords = ["if", "else", "while", "and", "not"]
+terminators = ["\n"]
+
+[binary_operators]
+"=" = { precedence = 1, associativity = "right" }
+"and" = { precedence = 2, short_circuit = true }
+"==" = { precedence = 4 }
+"<" = { precedence = 4 }
+"+" = { precedence = 5 }
+"-" = { precedence = 5 }
+"/" = { precedence = 6 }
+
+[unary_operators]
+"not" = { precedence = 3 }
+"#;
+
+    #[test]
+    fn reads_a_toml_schema_with_defaults() {
+        let language = parse(MINI, "toml").unwrap();
+        assert_eq!((language.name, language.extension), ("mini", "mini"));
+        let schema = language.get_schema();
+        assert!(schema.is_word_boundary_keyword("while"));
+        assert!(schema.multichar_lexemes.contains(&"=="));
+        assert!(!schema.multichar_lexemes.contains(&"+"));
+        assert_eq!(schema.get_binary_operator("=").unwrap().associativity, Associativity::Right);
+        assert!(schema.is_short_circuit("and"));
+        assert_eq!(schema.get_unary_operator("not").unwrap().position, UnaryPosition::Prefix);
+        assert_eq!(schema.block_open_marker, ":");
+
+        // The loop runs until x is 4, so only the last line divides by zero
+        let counting = "x = 1\nwhile x < 4 and x == x\n    x = x + 1\ny = 1 / (x - 4)\n";
+        let error = run(counting, &schema, &[]).unwrap_err();
+        assert!(error.to_lowercase().contains("division by zero"), "{}", error);
+
+        // No extern_syntax: no host capabilities
+        let error = run("t = extern(\"value_type\", 1)\n", &schema, &[]).unwrap_err();
+        assert!(error.contains("Unknown function: extern"), "{}", error);
+    }
+
+    #[test]
+    fn parses_by_the_tables_of_the_file() {
+        let language = |operators: &str| {
+            let text = format!("name = \"p\"\nextension = \"p\"\nkeywords = [\"and\", \"false\"]\nterminators = [\"\\n\"]\n[binary_operators]\n{}", operators);
+            parse(&text, "toml").unwrap().get_schema()
+        };
+        let value = |source: &str, schema: &LanguageSchema| run(source, schema, &[]).map(|value| value.to_string());
+
+        // Precedence and associativity decide the parse
+        let usual = language("\"+\" = { precedence = 5 }\n\"*\" = { precedence = 6 }\n\"-\" = { precedence = 5 }\n");
+        let swapped = language("\"+\" = { precedence = 6 }\n\"*\" = { precedence = 5 }\n\"-\" = { precedence = 5, associativity = \"right\" }\n");
+        assert_eq!(value("2 * 3 + 4\n", &usual).unwrap(), "10");
+        assert_eq!(value("2 * 3 + 4\n", &swapped).unwrap(), "14");
+        assert_eq!(value("10 - 4 - 3\n", &usual).unwrap(), "3");
+        assert_eq!(value("10 - 4 - 3\n", &swapped).unwrap(), "9");
+
+        // A new operator parses (and fails when run: the kernel gives it no meaning)
+        let diamond = language("\"<>\" = { precedence = 4 }\n\"+\" = { precedence = 5 }\n");
+        assert!(crate::kernel::parse_program("x = 1 <> 2 + 3\n", &diamond).is_ok());
+        assert!(value("1 <> 2 + 3\n", &diamond).unwrap_err().ends_with("Unknown binary operator: <>"));
+        assert!(crate::kernel::parse_program("x = 1 <> 2\n", &usual).is_err());
+
+        // A prefix operator's operand takes only tighter operators
+        let loose_not = language("\"==\" = { precedence = 4 }\n[unary_operators]\n\"not\" = { precedence = 3 }\n");
+        let tight_not = language("\"==\" = { precedence = 4 }\n[unary_operators]\n\"not\" = { precedence = 7 }\n");
+        assert_eq!(value("not 1 == 2\n", &loose_not).unwrap(), "true");
+        assert_eq!(value("not 1 == 2\n", &tight_not).unwrap(), "false");
+
+        // short_circuit decides whether the right operand runs
+        assert_eq!(value("false and 1 / 0\n", &language("\"and\" = { precedence = 3, short_circuit = true }\n\"/\" = { precedence = 6 }\n")).unwrap(), "false");
+        let error = value("false and 1 / 0\n", &language("\"and\" = { precedence = 3 }\n\"/\" = { precedence = 6 }\n")).unwrap_err();
+        assert!(error.to_lowercase().contains("division by zero"), "{}", error);
+
+        // Unlisted statement keywords are names, and only the terminators separate statements
+        assert_eq!(value("while = 3\nwhile\n", &usual).unwrap(), "3");
+        assert!(value("x = 1; x\n", &usual).is_err());
+        assert_eq!(value("x = 1; x\n", &lumen_schema()).unwrap(), "1");
+    }
+
+    #[test]
+    fn reads_json_and_rejects_bad_files() {
+        let json = r#"{"name": "j", "extension": "j", "binary_operators": {"+": {"precedence": 5}}}"#;
+        assert!(parse(json, "json").unwrap().get_schema().get_binary_operator("+").is_some());
+        let wrapping = "name = \"w\"\nextension = \"w\"\ninteger_overflow = \"wrapping\"\n";
+        assert_eq!(parse(wrapping, "toml").unwrap().get_schema().integer_overflow, IntegerOverflow::Wrapping);
+        assert_eq!(parse(MINI, "toml").unwrap().get_schema().integer_overflow, IntegerOverflow::Unbounded);
+        let host = "name = \"h\"\nextension = \"h\"\nextern_syntax = \"extern\"\n";
+        assert_eq!(parse(host, "toml").unwrap().get_schema().extern_syntax, Some("extern"));
+        let error = parse("name = \"m\"\nextension = \"m\"\nkeyword = []\n", "toml").unwrap_err();
+        assert!(error.contains("unknown field `keyword`"), "{}", error);
+        assert!(parse("name = \"m\"\n", "toml").unwrap_err().contains("extension"));
+        assert!(parse("name = ", "toml").unwrap_err().starts_with("invalid TOML"));
+        assert!(parse("{}", "yaml").is_err());
+        let error = parse("name = \"m\"\nextension = \"m\"\n[binary_operators]\n\"+\" = { precedence = 5, short_circuit = true }\n", "toml").unwrap_err();
+        assert!(error.contains("only and, or, && and || can short_circuit"), "{}", error);
+    }
+}
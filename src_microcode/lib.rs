@@ -11,10 +11,16 @@
 // 2. Structure: tokens → structured tokens (indentation/blocks)
 // 3. Reduce: tokens → instructions (Pratt parsing)
 // 4. Execute: instructions → values (faithful evaluation)
+//
+// Every run has its own environment and the kernel keeps no global state, so values,
+// reduced programs and schemas are Send + Sync: a host can reduce a program once and
+// run it on several threads at once.
 
 pub mod schema;
 pub mod kernel;
 pub mod languages;
+pub mod plugin;
+pub mod fuzzing;
 
-pub use kernel::run;
+pub use kernel::{parse_program, run, run_program, run_with_constants, tokenize, RunError};
 pub use kernel::Value;
@@ -1,188 +1,671 @@
 // Microcode Kernel v2 - Main Entry Point
 // Handles language detection and routing for the new microcode kernel
-// Usage: microcode_2 <file> [--lang <language>]
+// Usage: microcode_2 <file... | dir | - | -e code> [--lang <language>] [--check | --tokens | --ast | --emit-instructions | --lint[=RULES] [--allow RULE]...] [--timing [json]] [--opt[=LEVEL]]
 
 use std::env;
 use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::Path;
 use std::process;
 
 // Import the microcode_2 library
-use microcode_2::kernel::run;
-use microcode_2::languages::{lumen_schema, rust_core_schema, python_core_schema};
+use microcode_2::kernel::{parse_program, reduce_program_timed, reduce_with_prelude_timed, reparse_timed, run_reduced, IncrementalParser, tokenize, ExecutionLimits, Passes, Program, RunError, Timing, TimingFormat, Token, Value};
+use microcode_2::kernel::cache::Cache;
+use microcode_2::kernel::extern_audit::ExternAudit;
+use microcode_2::kernel::extern_policy::ExternPolicy;
+use microcode_2::kernel::limits;
+use microcode_2::kernel::spans;
+use microcode_2::kernel::disasm::disassemble;
+use microcode_2::kernel::lint::{lint, Rule};
+use microcode_2::kernel::optimize::{self, optimize};
+use microcode_2::kernel::serial::{self, SavedProgram};
+use microcode_2::languages::{self, schema_file};
+use microcode_2::plugin;
+use microcode_2::schema::LanguageSchema;
 
 // Build-time packaging: embedded .lm file list from lib_lumen/prelude.rs
 mod embedded_files {
     include!("../lib_lumen/prelude.rs");
 }
 
+// `include "path"` expansion against the embedded files, shared with the other kernel binary
+mod includes {
+    include!("../lib_lumen/includes.rs");
+}
+use includes::process_includes;
+
+// Build-time packaging: lib_lumen/prelude.lm reduced by build.rs (PROGRAM, LINES)
+mod compiled_prelude {
+    include!(concat!(env!("OUT_DIR"), "/prelude.rs"));
+}
+
+// Host constants from --define / --config, shared with the other kernel binary
+mod host_config {
+    include!("../lib_lumen/host_config.rs");
+}
+
+use host_config::ConfigValue;
+
+// Error messages with source excerpts, shared with the other kernel binary
+mod diagnostics {
+    include!("../lib_lumen/diagnostics.rs");
+}
+
+use diagnostics::Sources;
+
+// Programs split across several files, shared with the other kernel binary
+mod program_files {
+    include!("../lib_lumen/program_files.rs");
+}
+
+use program_files::ProgramFile;
+
+// Command-line reading, shared with the other kernel binary and lumen-lang
+mod cli_args {
+    include!("../lib_lumen/cli_args.rs");
+}
+
+use cli_args::{Args, Arity, Flag, Input};
+
+// The LUMEN_TRACE subscriber (cargo feature "tracing"), shared with the other kernel binary
+mod trace_output {
+    include!("../lib_lumen/trace_output.rs");
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
+    if let Err(e) = trace_output::install_from_env() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
 
-    // Parse arguments: [binary] <file> [--lang <language>] [program_args...]
-    let (filepath, language, program_args) = parse_args(&args);
+    // Parse arguments: [binary] <file... | dir | - | -e code> [--lang <language>] [--check | --tokens | --ast | --emit-instructions | --lint[=RULES] [--allow RULE]...] [--timing [json]] [--opt[=LEVEL]] [--cache] [--emit-program FILE | --load-program] [--no-prelude | --prelude file.lm] [--max-depth N] [--max-steps N] [--max-time MS] [--max-heap BYTES] [--allow-BACKEND[=CAPS]]... [--deny-all] [--audit FILE] [--extern-timeout [TARGET=]MS]... [--define NAME=value]... [--config file.toml] [--plugin lib.so]... [--schema file.toml] [program_args...]
+    run(parse_args(&args));
+}
 
-    // Read source file
-    let source = match fs::read_to_string(&filepath) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("Error: Failed to read {}: {}", filepath, e);
-            process::exit(1);
-        }
-    };
+fn run(options: Options) {
+    let Options {
+        input,
+        language,
+        constants,
+        program_args,
+        inspect,
+        lint_rules,
+        timing,
+        prelude,
+        limits,
+        passes,
+        emit_program,
+        load_program,
+        cache,
+        definitions,
+    } = options;
+    if !constants.is_empty() && language != "lumen" {
+        eprintln!("Error: --define and --config are only supported for lumen programs");
+        process::exit(1);
+    }
+    if !matches!(prelude, Prelude::Standard) && language != "lumen" {
+        eprintln!("Error: --no-prelude and --prelude are only supported for lumen programs");
+        process::exit(1);
+    }
 
-    // Route to appropriate language
-    match language.as_str() {
-        "lumen" => {
-            let schema = lumen_schema::get_schema();
-            // Load bootstrap file (prelude.lm) before user code
-            // The kernel has no semantic knowledge of what this file does or contains
-            let bootstrap_source = include_str!("../lib_lumen/prelude.lm");
-
-            // Process include directives in bootstrap file
-            let expanded_bootstrap = match process_includes(bootstrap_source) {
-                Ok(expanded) => expanded,
-                Err(e) => {
-                    eprintln!("Include error: {}", e);
-                    process::exit(1);
-                }
-            };
+    // --load-program: the input is a program saved with --emit-program; only stage 4 runs
+    if load_program {
+        run_saved(input, &program_args, constants, timing, limits);
+        return;
+    }
 
-            let full_source = format!("{}\n{}", expanded_bootstrap, source);
-            if let Err(e) = run(&full_source, &schema, &program_args) {
-                eprintln!("LumenError: {}", e);
+    // Read the source files (joined in order) or standard input, or take the inline -e source as is
+    let files = match input {
+        Input::Inline(code) => vec![ProgramFile { name: "-e".to_string(), text: code }],
+        Input::Stdin => {
+            let mut code = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut code) {
+                eprintln!("Error: Failed to read standard input: {}", e);
                 process::exit(1);
             }
+            vec![ProgramFile { name: "-".to_string(), text: code }]
         }
-        "rust_core" => {
-            let schema = rust_core_schema::get_schema();
-            if let Err(e) = run(&source, &schema, &program_args) {
-                eprintln!("RustCoreError: {}", e);
+        Input::Files(paths) => match program_files::collect(&paths).and_then(|paths| program_files::read(&paths)) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("Error: {}", e);
                 process::exit(1);
             }
-        }
-        "python_core" => {
-            let schema = python_core_schema::get_schema();
-            if let Err(e) = run(&source, &schema, &program_args) {
-                eprintln!("PythonCoreError: {}", e);
-                process::exit(1);
+        },
+    };
+    let source = program_files::join(&files);
+    let sources = |first_line| Sources {
+        files: files.iter().map(|f| (f.name.as_str(), f.text.as_str())).collect(),
+        first_line,
+    };
+
+    // --check / --tokens / --ast / --emit-instructions / --lint: parse the user program (prelude excluded) and stop
+    if let Some(inspect) = inspect {
+        inspect_program(&source, &sources(1), &language, inspect, &lint_rules, passes);
+        return;
+    }
+
+    // Route to appropriate language (built in, or registered by a --plugin library)
+    let Some(schema) = languages::by_name(&language).map(|l| l.get_schema()) else {
+        eprintln!("Error: Unknown language '{}'", language);
+        process::exit(1);
+    };
+    let prelude = if language == "lumen" { prelude } else { Prelude::Off };
+
+    // The bootstrap program (prelude.lm, or the --prelude file) runs before user code
+    // The kernel has no semantic knowledge of what this program does or contains
+    let bootstrap = find_prelude(&prelude);
+    let first_line = bootstrap.as_ref().map_or(1, |bootstrap| bootstrap.first_line);
+    spans::program_starts_at(first_line);
+    let prelude_key = bootstrap.as_ref().map_or(&[][..], |bootstrap| bootstrap.key());
+    let reduce_prelude = |stages: &mut Timing| {
+        bootstrap.as_ref().map(|bootstrap| {
+            let prelude = bootstrap.reduce(&schema, &sources(first_line));
+            stages.lap("Prelude");
+            stages.count("prelude_instructions", prelude.len());
+            prelude
+        })
+    };
+    let reduce = |stages: &mut Timing| match reduce_prelude(stages) {
+        Some(prelude) => reduce_with_prelude_timed(prelude, first_line, &source, &schema, stages, passes),
+        None => reduce_program_timed(&source, &schema, stages, passes),
+    };
+
+    let mut stages = Timing::start();
+    // --emit-program: save the program as it would run (prelude included) instead of running it
+    if let Some(path) = emit_program {
+        emit_program_file(reduce(&mut stages), &language, &path, &sources(first_line));
+        return;
+    }
+
+    let constants = to_values(constants);
+    let program = if cache {
+        // --cache: reuse the instructions of an earlier run of the same text, or store them
+        let cache = Cache::from_env().unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        });
+        // A --plugin or --schema language is keyed by the file that defines it: the same
+        // name may stand for another definition in the next run
+        let definition = definitions.iter().find(|(name, _)| *name == language).map_or_else(Vec::new, |(_, path)| {
+            fs::read(path).unwrap_or_else(|_| path.clone().into_bytes())
+        });
+        let key = Cache::key(prelude_key, &definition, &source, &language, passes);
+        match cache.load(&key, &language) {
+            Some(program) => {
+                stages.lap("Cache");
+                stages.count("instructions", program.len());
+                Ok(program)
+            }
+            None => {
+                // The last parse of the same input leaves only what changed to reduce
+                let input = files.iter().map(|file| file.name.as_str()).collect::<Vec<_>>().join("\n");
+                let last_key = Cache::last_parse_key(prelude_key, &definition, &input, &language);
+                let mut parser = match cache.load_last_parse(&last_key, &language) {
+                    Some((text, parsed)) => IncrementalParser::resume(first_line, text, parsed),
+                    None => IncrementalParser::starting_at(first_line),
+                };
+                let prelude = reduce_prelude(&mut stages);
+                reparse_timed(&mut parser, prelude, &source, &schema, &mut stages, passes).inspect(|program| {
+                    // Best effort: the run goes on without cache entries
+                    let _ = cache.store(&key, &language, program);
+                    if let Some(parsed) = parser.program() {
+                        let _ = cache.store_last_parse(&last_key, &language, &source, parsed);
+                    }
+                })
             }
         }
+    } else {
+        reduce(&mut stages)
+    };
+    let result = program.and_then(|program| run_reduced(&program, &schema, &program_args, &constants, &mut stages, limits));
+
+    // --timing: reported even when the program stopped early (stages that ran are listed)
+    if let Some(format) = timing {
+        eprint!("{}", stages.render("microcode", format));
+    }
+    if let Err(e) = result {
+        exit_with(error_kind(&language), e, &sources(first_line));
+    }
+}
+
+/// Header for the errors of a language's programs
+fn error_kind(language: &str) -> &'static str {
+    match language {
+        "rust_core" => "RustCoreError",
+        "python_core" => "PythonCoreError",
+        _ => "LumenError",
+    }
+}
+
+/// Write a reduced program to `path` (- for stdout) as JSON, or as CBOR for a .cbor file
+fn emit_program_file(program: Result<Program, RunError>, language: &str, path: &str, sources: &Sources) {
+    let program = match program {
+        Ok(program) => program,
+        Err(e) => exit_with("ParseError", e, sources),
+    };
+    let written = serial::save(&SavedProgram::new(language, program), serial::Format::for_path(path)).and_then(|bytes| {
+        if path == "-" {
+            io::stdout().write_all(&bytes).map_err(|e| e.to_string())
+        } else {
+            fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path, e))
+        }
+    });
+    if let Err(e) = written {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Run a program saved with --emit-program: it carries its language and, for Lumen, the
+/// prelude it was saved with
+/// Runtime errors are reported with the positions of the source the program was reduced
+/// from (for Lumen, counting the prelude lines) and no excerpt.
+fn run_saved(input: Input, program_args: &[String], constants: Vec<(String, ConfigValue)>, timing: Option<TimingFormat>, limits: ExecutionLimits) {
+    let path = match input {
+        Input::Files(paths) if paths.len() == 1 => paths[0].clone(),
         _ => {
-            eprintln!("Error: Unknown language '{}'", language);
+            eprintln!("Error: --load-program needs one saved program file");
+            process::exit(1);
+        }
+    };
+    let saved = fs::read(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))
+        .and_then(|bytes| serial::load(&bytes, serial::Format::for_path(&path)).map_err(|e| format!("{}: {}", path, e)));
+    let saved = match saved {
+        Ok(saved) => saved,
+        Err(e) => {
+            eprintln!("Error: {}", e);
             process::exit(1);
         }
+    };
+    let Some(schema) = languages::by_name(&saved.language).map(|l| l.get_schema()) else {
+        eprintln!("Error: Unknown language '{}' in {}", saved.language, path);
+        process::exit(1);
+    };
+    if !constants.is_empty() && saved.language != "lumen" {
+        eprintln!("Error: --define and --config are only supported for lumen programs");
+        process::exit(1);
     }
+
+    let mut stages = Timing::start();
+    let result = run_reduced(&saved.program, &schema, program_args, &to_values(constants), &mut stages, limits);
+    if let Some(format) = timing {
+        eprint!("{}", stages.render("microcode", format));
+    }
+    match result {
+        Ok(_) => {}
+        // No source to show an excerpt from, so the error's position stays in the message
+        Err(RunError::Runtime(e) | RunError::LimitExceeded(e)) => {
+            eprintln!("{}: {}", error_kind(&saved.language), e);
+            process::exit(diagnostics::EXIT_RUNTIME_ERROR);
+        }
+        Err(e) => exit_with(error_kind(&saved.language), e, &Sources { files: Vec::new(), first_line: 1 }),
+    }
+}
+
+/// Report a failed run and exit with the status for its kind of failure
+fn exit_with(kind: &str, error: RunError, sources: &Sources) -> ! {
+    let status = match error {
+        RunError::Exit(status) => {
+            let _ = io::stdout().flush();
+            status
+        }
+        RunError::Syntax(errors) => {
+            for e in &errors {
+                diagnostics::report_at(kind, &e.message, &e.positions, sources);
+            }
+            diagnostics::EXIT_SYNTAX_ERROR
+        }
+        RunError::Runtime(e) | RunError::LimitExceeded(e) => {
+            diagnostics::report_at(kind, &e.message, &e.positions, sources);
+            diagnostics::EXIT_RUNTIME_ERROR
+        }
+    };
+    process::exit(status)
 }
 
-fn parse_args(args: &[String]) -> (String, String, Vec<String>) {
-    if args.len() < 2 {
-        eprintln!(
-            "Usage: {} <file> [--lang <language>] [program_args...]",
-            args.get(0).unwrap_or(&"microcode_2".to_string())
-        );
+/// Check or lint a program (stages 1-3), or print its tokens (stages 1-2) or instruction tree
+/// The printed tree is the one that would run, so it has been through `passes`; lint
+/// checks the program as written.
+fn inspect_program(source: &str, sources: &Sources, language: &str, inspect: Inspect, lint_rules: &[Rule], passes: Passes) {
+    let Some(schema) = languages::by_name(language).map(|l| l.get_schema()) else {
+        eprintln!("Error: Unknown language '{}'", language);
         process::exit(1);
+    };
+    let optimized = || {
+        parse_program(source, &schema).map(|mut program| {
+            optimize(&mut program, passes);
+            program
+        })
+    };
+    let result = match inspect {
+        Inspect::Check => parse_program(source, &schema).map(|_| ()),
+        Inspect::Tokens => tokenize(source, &schema).map(|(raw, structured)| {
+            print_tokens("raw tokens", &raw);
+            print_tokens("after structure", &structured);
+        }),
+        Inspect::Ast => optimized().map(|program| println!("{:#?}", program.tree(program.root()))),
+        Inspect::Instructions => optimized().map(|program| print!("{}", disassemble(&program))),
+        Inspect::Lint => parse_program(source, &schema).map(|program| {
+            let findings = lint(&program, lint_rules);
+            for finding in &findings {
+                println!("{}", finding);
+            }
+            if !findings.is_empty() {
+                process::exit(1);
+            }
+        }),
+    };
+    if let Err(errors) = result {
+        exit_with("ParseError", RunError::Syntax(errors), sources);
     }
+}
 
-    let filepath = args[1].clone();
-    let mut language = String::new();
-    let mut program_args = Vec::new();
+/// One token per line: line:col and the quoted lexeme (block markers show as { and })
+fn print_tokens(title: &str, tokens: &[Token]) {
+    println!("== {} ({}) ==", title, tokens.len());
+    for token in tokens {
+        println!("{:>4}:{:<4}{:?}", token.line, token.col, token.lexeme);
+    }
+}
+
+/// Convert host constants into microcode values
+fn to_values(constants: Vec<(String, ConfigValue)>) -> Vec<(String, Value)> {
+    constants
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                ConfigValue::Integer(n) => Value::Number(n),
+                ConfigValue::Real { numerator, denominator, precision } => {
+                    Value::Real { numerator, denominator, precision }
+                }
+                ConfigValue::Bool(b) => Value::Bool(b),
+                ConfigValue::String(s) => Value::String(s),
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+/// Parsed command line
+struct Options {
+    input: Input,
+    language: String,
+    constants: Vec<(String, ConfigValue)>,
+    program_args: Vec<String>,
+    /// --check / --tokens / --ast / --emit-instructions / --lint: stop after parsing instead of running the program
+    inspect: Option<Inspect>,
+    /// Rules for --lint: all, or the --lint=RULE,... list, minus any --allow RULE
+    lint_rules: Vec<Rule>,
+    /// --timing / --timing json: report stage durations and sizes on stderr
+    timing: Option<TimingFormat>,
+    /// Library code loaded before a Lumen program (--no-prelude / --prelude FILE)
+    prelude: Prelude,
+    /// --max-depth / --max-steps / --max-time / --max-heap: stop runaway programs with an error;
+    /// --allow-BACKEND / --deny-all: the extern capabilities they may reach; --extern-timeout:
+    /// how long their extern calls may take
+    limits: ExecutionLimits,
+    /// --opt[=LEVEL]: passes run over the instructions before execution
+    passes: Passes,
+    /// --emit-program FILE: save the reduced program (JSON, or CBOR for .cbor) and stop
+    emit_program: Option<String>,
+    /// --load-program: the input file is a saved program to run
+    load_program: bool,
+    /// --cache: take the reduced program from the cache directory, or store it there
+    cache: bool,
+    /// (language, file) of each language a --plugin library or --schema file defines
+    definitions: Vec<(String, String)>,
+}
+
+/// What a Lumen program runs after
+enum Prelude {
+    /// lib_lumen/prelude.lm, embedded in the binary
+    Standard,
+    /// --prelude FILE: read from disk; its includes still resolve in the embedded library
+    File(String),
+    /// --no-prelude: only kernel builtins (emit, len, ...) are defined, not print or write
+    Off,
+}
+
+/// What to do with a parsed program instead of running it
+#[derive(Clone, Copy, PartialEq)]
+enum Inspect {
+    /// Report syntax errors only (nonzero exit status on error, no output on success)
+    Check,
+    /// Raw token stream, then the token stream after structure processing
+    Tokens,
+    /// Parsed program
+    Ast,
+    /// Instruction tree as an indented listing with primitive numbers
+    Instructions,
+    /// Lint findings (nonzero exit status if there are any)
+    Lint,
+}
 
-    // Parse --lang flag
-    let mut consumed_until = 2;
-    if args.len() > 2 && args[2] == "--lang" {
-        if args.len() < 4 {
-            eprintln!("Error: --lang requires an argument");
+fn parse_args(args: &[String]) -> Options {
+    let mut rest = Args::new(&args[1..]);
+    let input = match rest.input() {
+        Ok(Some(input)) => input,
+        // No arguments but piped input: run the program from stdin
+        Ok(None) if !io::stdin().is_terminal() => Input::Stdin,
+        Ok(None) => {
+            eprintln!(
+                "Usage: {} <file... | dir | - | -e code> [--lang <language>] [--check | --tokens | --ast | --emit-instructions | --lint[=RULES] [--allow RULE]...] [--timing [json]] [--opt[=LEVEL]] [--cache] [--emit-program FILE | --load-program] [--no-prelude | --prelude file.lm] [--max-depth N] [--max-steps N] [--max-time MS] [--max-heap BYTES] [--allow-BACKEND[=CAPS]]... [--deny-all] [--audit FILE] [--extern-timeout [TARGET=]MS]... [--define NAME=value]... [--config file.toml] [--plugin lib.so]... [--schema file.toml] [program_args...]",
+                args.get(0).unwrap_or(&"microcode_2".to_string())
+            );
             process::exit(1);
         }
-        language = args[3].to_lowercase();
-        consumed_until = 4;
+        Err(e) => exit_with_error(e),
+    };
+    let mut language = String::new();
+    let mut schema_language = None;
+
+    let mut constants = Vec::new();
+    let mut inspect = None;
+    let mut lint_rules = Rule::ALL.to_vec();
+    let mut allowed = Vec::new();
+    let mut timing = None;
+    let mut prelude = Prelude::Standard;
+    let mut limits = ExecutionLimits::default();
+    let mut passes = Passes::default();
+    let mut emit_program = None;
+    let mut load_program = false;
+    let mut cache = false;
+    let mut definitions = Vec::new();
+
+    // Flags in any order, before program arguments (see flag_arity; -- ends the flags)
+    while let Some(flag) = rest.next_flag(flag_arity) {
+        let Flag { name: flag, value } = flag.unwrap_or_else(|e| exit_with_error(e));
+        let value = value.unwrap_or_default();
+        match flag {
+            "--check" => inspect = Some(Inspect::Check),
+            "--tokens" => inspect = Some(Inspect::Tokens),
+            "--ast" => inspect = Some(Inspect::Ast),
+            "--emit-instructions" => inspect = Some(Inspect::Instructions),
+            "--lint" => inspect = Some(Inspect::Lint),
+            "--allow" => allowed.push(parse_rule(value)),
+            // `--timing json` also selects JSON (json is never a program file)
+            "--timing" if rest.next_if("json") => timing = Some(TimingFormat::Json),
+            "--timing" => timing = Some(TimingFormat::Text),
+            "--timing=json" => timing = Some(TimingFormat::Json),
+            // The instruction tree carries no source locations to report
+            "--trace" | "--trace=vars" | "--coverage" | "--lcov" |
+            // Capability adapters register with the stream kernel's CapabilityRegistry
+            "--adapter" |
+            // REAL values here carry no error bounds
+            "--intervals" => exit_with_error(format!("{} is only supported by the stream kernel (use --kernel stream)", flag)),
+            // This kernel checks programs statically with --lint instead
+            "--deny-warnings" => exit_with_error(format!("{} is only supported by the stream kernel (use --lint here)", flag)),
+            "--opt" => passes = Passes::all(),
+            "--load-program" => load_program = true,
+            "--cache" => cache = true,
+            "--emit-program" => emit_program = Some(value.to_string()),
+            "--no-prelude" => prelude = Prelude::Off,
+            "--prelude" => prelude = Prelude::File(value.to_string()),
+            "--lang" => language = value.to_lowercase(),
+            // Loaded now, so that its language's extension is known to auto-detection below
+            "--plugin" => {
+                let defined = or_exit(plugin::load(value));
+                definitions.push((defined.name.to_string(), value.to_string()));
+            }
+            // The language a schema file defines is the program's, unless --lang names another
+            "--schema" => {
+                let defined = or_exit(schema_file::load(value));
+                schema_language = Some(defined.name.to_string());
+                definitions.push((defined.name.to_string(), value.to_string()));
+            }
+            "--max-depth" => limits.max_depth = Some(or_exit(limits::parse_limit(flag, value)) as usize),
+            "--max-steps" => limits.max_steps = Some(or_exit(limits::parse_limit(flag, value))),
+            "--max-time" => limits.max_time = Some(std::time::Duration::from_millis(or_exit(limits::parse_limit(flag, value)))),
+            "--max-heap" => limits.max_heap = Some(or_exit(limits::parse_limit(flag, value)) as usize),
+            "--audit" => limits.audit = Some(or_exit(ExternAudit::open(value))),
+            "--extern-timeout" => or_exit(limits.extern_timeouts.apply_flag(value)),
+            "--define" => or_exit(host_config::parse_define(value).and_then(|c| host_config::merge(&mut constants, vec![c]))),
+            "--config" => or_exit(
+                fs::read_to_string(value)
+                    .map_err(|e| format!("Failed to read {}: {}", value, e))
+                    .and_then(|source| host_config::parse_config(&source))
+                    .map_err(|e| format!("{}: {}", value, e))
+                    .and_then(|c| host_config::merge(&mut constants, c)),
+            ),
+            _ => {
+                if let Some(list) = flag.strip_prefix("--lint=") {
+                    lint_rules = list.split(',').map(parse_rule).collect();
+                    inspect = Some(Inspect::Lint);
+                } else if let Some(level) = flag.strip_prefix("--opt=") {
+                    match level.parse::<u8>() {
+                        Ok(level) if level <= optimize::MAX_LEVEL => passes = Passes::level(level),
+                        _ => exit_with_error(format!("--opt level must be 0 to {}, got '{}'", optimize::MAX_LEVEL, level)),
+                    }
+                } else {
+                    // --allow-BACKEND[=CAPS] and --deny-all, the other flags flag_arity knows
+                    or_exit(limits.externs.apply_flag(flag).unwrap_or(Ok(())));
+                }
+            }
+        }
     }
 
-    // Auto-detect language if not specified
+    // Auto-detect language if not specified (inline source defaults to lumen)
     if language.is_empty() {
-        language = detect_language_from_extension(&filepath)
-            .unwrap_or_else(|| "lumen".to_string());
+        language = schema_language.unwrap_or_default();
+    }
+    if language.is_empty() {
+        language = match &input {
+            Input::Files(paths) => detect_language_from_extension(&paths[0]),
+            Input::Stdin | Input::Inline(_) => None,
+        }
+        .unwrap_or_else(|| "lumen".to_string());
     }
 
     // Remaining arguments are program arguments
-    if args.len() > consumed_until {
-        program_args = args[consumed_until..].to_vec();
-    }
+    let program_args = rest.rest().to_vec();
+
+    lint_rules.retain(|rule| !allowed.contains(rule));
 
-    (filepath, language, program_args)
+    Options {
+        input,
+        language,
+        constants,
+        program_args,
+        inspect,
+        lint_rules,
+        timing,
+        prelude,
+        limits,
+        passes,
+        emit_program,
+        load_program,
+        cache,
+        definitions,
+    }
 }
 
-fn detect_language_from_extension(filepath: &str) -> Option<String> {
-    let path = Path::new(filepath);
-    let extension = path.extension()?.to_str()?;
-
-    let language = match extension {
-        "lm" => "lumen",
-        "rs" => "rust_core",
-        "py" => "python_core",
-        _ => return None,
-    };
+/// The flags this kernel reads; any other argument starts the program arguments
+fn flag_arity(flag: &str) -> Option<Arity> {
+    match flag {
+        "--lang" | "--allow" | "--prelude" | "--max-depth" | "--max-steps" | "--max-time" | "--max-heap" | "--audit"
+        | "--extern-timeout" | "--emit-program" | "--define" | "--config" | "--plugin" | "--schema" => Some(Arity::Value),
+        "--check" | "--tokens" | "--ast" | "--emit-instructions" | "--lint" | "--timing" | "--timing=json" | "--opt"
+        | "--load-program" | "--cache" | "--no-prelude" => Some(Arity::Switch),
+        // Stream kernel flags, rejected
+        "--trace" | "--trace=vars" | "--coverage" | "--lcov" | "--adapter" | "--intervals" | "--deny-warnings" => {
+            Some(Arity::Switch)
+        }
+        _ if flag.starts_with("--lint=") || flag.starts_with("--opt=") || ExternPolicy::is_flag(flag) => Some(Arity::Switch),
+        _ => None,
+    }
+}
 
-    Some(language.to_string())
+/// The value of a flag, or exit with its error
+fn or_exit<T>(parsed: Result<T, String>) -> T {
+    parsed.unwrap_or_else(|e| exit_with_error(e))
 }
 
-/// Generic embedded file lookup: queries the embedded virtual filesystem by path
-/// The kernel performs a simple path-based lookup with no semantic knowledge
-fn get_embedded_file(path: &str) -> Option<&'static str> {
-    embedded_files::EMBEDDED_FILES
-        .iter()
-        .find(|(p, _)| *p == path)
-        .map(|(_, contents)| *contents)
+fn exit_with_error(message: String) -> ! {
+    eprintln!("Error: {}", message);
+    process::exit(1)
 }
 
-/// Process include directives in source code
-/// Recursively expands `include "path"` directives by inlining embedded file contents
-fn process_includes(source: &str) -> Result<String, String> {
-    let mut result = String::new();
-    let mut processed_files = std::collections::HashSet::new();
+/// Look up a lint rule by name, exiting with the list of known rules if there is none
+fn parse_rule(name: &str) -> Rule {
+    Rule::from_name(name.trim()).unwrap_or_else(|| {
+        let known: Vec<&str> = Rule::ALL.iter().map(|rule| rule.name()).collect();
+        eprintln!("Error: Unknown lint rule '{}' (known rules: {})", name, known.join(", "));
+        process::exit(1);
+    })
+}
 
-    fn process_recursive(
-        source: &str,
-        processed_files: &mut std::collections::HashSet<String>,
-        result: &mut String,
-    ) -> Result<(), String> {
-        for line in source.lines() {
-            let trimmed = line.trim();
+fn detect_language_from_extension(filepath: &str) -> Option<String> {
+    let extension = Path::new(filepath).extension()?.to_str()?;
+    languages::by_extension(extension).map(|language| language.name.to_string())
+}
 
-            // Check if line is an include directive
-            if trimmed.starts_with("include ") {
-                // Extract the file path from: include "path"
-                let rest = trimmed.strip_prefix("include ").unwrap().trim();
+/// The prelude a Lumen program runs after
+struct Bootstrap {
+    /// The expanded text of a --prelude file (None for the standard prelude, reduced by build.rs)
+    text: Option<String>,
+    /// The line user code starts on (after the prelude text and one blank line)
+    first_line: usize,
+}
 
-                if !rest.starts_with('"') || !rest.ends_with('"') {
-                    return Err(format!("Invalid include syntax: {}", line));
-                }
+impl Bootstrap {
+    /// What the prelude is reduced from, for cache keys
+    fn key(&self) -> &[u8] {
+        self.text.as_ref().map_or(compiled_prelude::PROGRAM, |text| text.as_bytes())
+    }
 
-                let path = &rest[1..rest.len()-1];
+    /// The prelude's instructions: decoded from the binary, or reduced from a --prelude file
+    fn reduce(&self, schema: &LanguageSchema, sources: &Sources) -> Program {
+        let Some(text) = &self.text else {
+            return serial::load(compiled_prelude::PROGRAM, serial::Format::Cbor)
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: embedded prelude: {}", e);
+                    process::exit(1);
+                })
+                .program;
+        };
+        parse_program(text, schema).unwrap_or_else(|e| exit_with(error_kind("lumen"), RunError::Syntax(e), sources))
+    }
+}
 
-                // Prevent circular includes
-                if processed_files.contains(path) {
-                    continue; // Skip already processed files
-                }
-                processed_files.insert(path.to_string());
-
-                // Retrieve from embedded virtual filesystem
-                let file_contents = get_embedded_file(path)
-                    .ok_or_else(|| format!("File not found in embedded filesystem: {}", path))?;
-
-                // Recursively process the included file
-                process_recursive(file_contents, processed_files, result)?;
-                result.push('\n');
-            } else {
-                // Regular line - keep it
-                result.push_str(line);
-                result.push('\n');
-            }
+/// The prelude to run before a Lumen program (None for --no-prelude), with a --prelude
+/// file read and its includes expanded
+fn find_prelude(prelude: &Prelude) -> Option<Bootstrap> {
+    match prelude {
+        Prelude::Standard => Some(Bootstrap { text: None, first_line: compiled_prelude::LINES + 2 }),
+        Prelude::File(path) => {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read prelude {}: {}", path, e))
+                .and_then(|source| process_includes(&source))
+                .unwrap_or_else(|e| {
+                    eprintln!("Include error: {}", e);
+                    process::exit(1);
+                });
+            let first_line = text.matches('\n').count() + 2;
+            Some(Bootstrap { text: Some(text), first_line })
         }
-        Ok(())
+        Prelude::Off => None,
     }
-
-    process_recursive(source, &mut processed_files, &mut result)?;
-    Ok(result)
 }
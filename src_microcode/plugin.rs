@@ -0,0 +1,125 @@
+// Language plugins: frontends compiled as separate dynamic libraries (--plugin)
+//
+// A plugin is a cdylib crate that depends on this one and defines a language the way the
+// built-in ones do, as a schema function (see languages/lumen/schema.rs):
+//
+//   [lib]
+//   crate-type = ["cdylib"]
+//
+//   [dependencies]
+//   lumen-lang = { path = "../lumen-lang" }
+//
+//   pub fn get_schema() -> microcode_2::schema::LanguageSchema { ... }
+//   microcode_2::language_plugin!("toy", "toy", get_schema);
+//
+// `microcode prog.toy --plugin target/release/libtoy.so` loads it: its language is
+// registered (see languages/mod.rs), so --lang toy and the .toy extension select it,
+// and the kernel runs its programs like any other language's.
+//
+// The schema crosses the library boundary as a Rust value, so a plugin must be built
+// against the same version of this crate with the same compiler. Each side carries
+// PLUGIN_ABI, and a plugin whose string differs is refused before anything else in it
+// is called. Loaded libraries stay loaded until the process exits: the registered
+// language points into them. Plugins are native code and run with the kernel's
+// permissions; only load ones you trust. Loading needs dlopen (Unix).
+
+use crate::languages::{self, Language};
+
+/// Crate version and compiler a plugin was built with; must match the loading kernel's
+pub const PLUGIN_ABI: &str = concat!("microcode_2 ", env!("CARGO_PKG_VERSION"), " (", env!("LUMEN_RUSTC"), ")\0");
+
+/// Export a language from a plugin crate: its --lang name, file extension and schema function
+#[macro_export]
+macro_rules! language_plugin {
+    ($name:expr, $extension:expr, $schema:path) => {
+        static LUMEN_PLUGIN_LANGUAGE: $crate::languages::Language =
+            $crate::languages::Language {
+                name: $name,
+                extension: $extension,
+                schema: $crate::languages::Schema::Compiled($schema),
+            };
+
+        #[no_mangle]
+        pub extern "C" fn lumen_plugin_abi() -> *const ::std::os::raw::c_char {
+            $crate::plugin::PLUGIN_ABI.as_ptr().cast()
+        }
+
+        #[no_mangle]
+        pub fn lumen_plugin_language() -> &'static $crate::languages::Language {
+            &LUMEN_PLUGIN_LANGUAGE
+        }
+    };
+}
+
+/// Load the plugin at `path` and register its language
+pub fn load(path: &str) -> Result<&'static Language, String> {
+    let language = open(path).map_err(|e| format!("Failed to load plugin {}: {}", path, e))?;
+    languages::register(language).map_err(|e| format!("Plugin {}: {}", path, e))?;
+    Ok(language)
+}
+
+#[cfg(unix)]
+fn open(path: &str) -> Result<&'static Language, String> {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+
+    // An absolute path, so dlopen never searches the library path instead
+    let path = std::fs::canonicalize(path).map_err(|e| e.to_string())?;
+    let path = CString::new(path.to_string_lossy().into_owned()).map_err(|e| e.to_string())?;
+    let last_error = || {
+        let error = unsafe { libc::dlerror() };
+        if error.is_null() {
+            "unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned()
+        }
+    };
+
+    // Never closed (see the header)
+    let library = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if library.is_null() {
+        return Err(last_error());
+    }
+    let symbol = |name: &CStr| {
+        let symbol = unsafe { libc::dlsym(library, name.as_ptr()) };
+        if symbol.is_null() {
+            Err(format!("not a Lumen language plugin (no {})", name.to_string_lossy()))
+        } else {
+            Ok(symbol)
+        }
+    };
+
+    // The ABI string first: it is the only call that is safe whatever built the library
+    let abi = symbol(c"lumen_plugin_abi")?;
+    let abi: extern "C" fn() -> *const c_char = unsafe { std::mem::transmute(abi) };
+    let abi = unsafe { CStr::from_ptr(abi()) }.to_string_lossy().into_owned();
+    let expected = PLUGIN_ABI.trim_end_matches('\0');
+    if abi != expected {
+        return Err(format!("built for {}, but this kernel is {}", abi, expected));
+    }
+    let language = symbol(c"lumen_plugin_language")?;
+    let language: fn() -> &'static Language = unsafe { std::mem::transmute(language) };
+    Ok(language())
+}
+
+#[cfg(not(unix))]
+fn open(_path: &str) -> Result<&'static Language, String> {
+    Err("plugins are only supported on Unix".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_missing_files_and_non_plugins() {
+        let error = load("no/such/plugin.so").unwrap_err();
+        assert!(error.starts_with("Failed to load plugin no/such/plugin.so: "), "{}", error);
+
+        let path = std::env::temp_dir().join(format!("lumen-not-a-plugin-{}.so", std::process::id()));
+        std::fs::write(&path, "not a library").unwrap();
+        let error = load(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.starts_with("Failed to load plugin"), "{}", error);
+    }
+}
@@ -1,9 +1,13 @@
 // Language schema: declarative syntax and semantics
 //
-// LanguageSchema contains ONLY data loaded from YAML specifications.
+// LanguageSchema contains ONLY data: built by a language's schema.rs, or read from a
+// TOML/JSON schema file (languages/schema_file.rs).
 // All interpretation is done by the kernel stages.
 
 use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use crate::kernel::lexeme_trie::LexemeTrie;
 
 #[derive(Debug, Clone)]
 pub struct OperatorInfo {
@@ -30,6 +34,20 @@ pub enum UnaryPosition {
     Postfix,
 }
 
+/// What integer arithmetic does with a result outside the 64-bit signed range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerOverflow {
+    /// Integers have no range (Lumen's)
+    #[default]
+    Unbounded,
+    /// The operation is an error, as in a Rust debug build
+    Checked,
+    /// The result wraps around modulo 2^64, as with i64::wrapping_add
+    Wrapping,
+    /// The result is clamped to i64::MIN..=i64::MAX, as with i64::saturating_add
+    Saturating,
+}
+
 /// Comprehensive language schema loaded from YAML
 #[derive(Debug, Clone)]
 pub struct LanguageSchema {
@@ -37,10 +55,10 @@ pub struct LanguageSchema {
     pub multichar_lexemes: Vec<&'static str>,
 
     /// Keywords that require word boundaries
-    pub word_boundary_keywords: Vec<&'static str>,
+    pub word_boundary_keywords: HashSet<&'static str>,
 
     /// Statement terminators (e.g., ";", "\n")
-    pub terminators: Vec<&'static str>,
+    pub terminators: HashSet<&'static str>,
 
     /// Binary operators with precedence and associativity
     pub binary_operators: HashMap<String, OperatorInfo>,
@@ -49,7 +67,7 @@ pub struct LanguageSchema {
     pub unary_operators: HashMap<String, UnaryOperatorInfo>,
 
     /// All keywords in the language
-    pub keywords: Vec<String>,
+    pub keywords: HashSet<&'static str>,
 
     /// Indentation settings
     pub indentation_size: usize,
@@ -58,6 +76,20 @@ pub struct LanguageSchema {
     /// Block structure markers (e.g., ":" for Lumen)
     pub block_open_marker: String,
     pub block_close_marker: String,
+
+    /// Integer results outside i64: unbounded, or an error, wrapped or clamped; with i64
+    /// integers `/` truncates and literals must fit
+    pub integer_overflow: IntegerOverflow,
+
+    /// The call that reaches host capabilities (`extern` in Lumen, python_core and rust_core):
+    /// extern(selector, ...) and the rest of its family (extern_try, extern_async,
+    /// extern_await, extern_available, mock_extern, push_mocks, pop_mocks, cache_extern,
+    /// clear_extern_cache, extern_timeout); None: the language has none of them, and calling
+    /// one is an unknown function
+    pub extern_syntax: Option<&'static str>,
+
+    /// multichar_lexemes as a trie for the lexer, built on first use
+    multichar_trie: OnceLock<LexemeTrie>,
 }
 
 impl LanguageSchema {
@@ -65,26 +97,43 @@ impl LanguageSchema {
     pub fn new() -> Self {
         LanguageSchema {
             multichar_lexemes: Vec::new(),
-            word_boundary_keywords: Vec::new(),
-            terminators: Vec::new(),
+            word_boundary_keywords: HashSet::new(),
+            terminators: HashSet::new(),
             binary_operators: HashMap::new(),
             unary_operators: HashMap::new(),
-            keywords: Vec::new(),
+            keywords: HashSet::new(),
             indentation_size: 4,
             indentation_char: ' ',
             block_open_marker: ":".to_string(),
             block_close_marker: "DEDENT".to_string(),
+            integer_overflow: IntegerOverflow::Unbounded,
+            extern_syntax: None,
+            multichar_trie: OnceLock::new(),
         }
     }
 
+    /// The multi-character lexemes as a trie, the keyword-like ones (letters and '_' only)
+    /// flagged as words (set multichar_lexemes before the first lex)
+    pub fn multichar_trie(&self) -> &LexemeTrie {
+        self.multichar_trie.get_or_init(|| {
+            let is_word = |lexeme: &str| lexeme.chars().all(|c| c.is_alphabetic() || c == '_');
+            LexemeTrie::new(self.multichar_lexemes.iter().map(|&lexeme| (lexeme, is_word(lexeme))))
+        })
+    }
+
     /// Check if a word is a keyword that requires word boundaries
     pub fn is_word_boundary_keyword(&self, word: &str) -> bool {
-        self.word_boundary_keywords.contains(&word)
+        self.word_boundary_keywords.contains(word)
+    }
+
+    /// Check if a word is a keyword of the language
+    pub fn is_keyword(&self, word: &str) -> bool {
+        self.keywords.contains(word)
     }
 
     /// Check if a token is a terminator
     pub fn is_terminator(&self, lexeme: &str) -> bool {
-        self.terminators.contains(&lexeme)
+        self.terminators.contains(lexeme)
     }
 
     /// Get binary operator info
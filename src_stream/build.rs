@@ -0,0 +1,19 @@
+// Build script for the stream kernel library
+//
+// Sets LUMEN_RUSTC (the compiler's version line), part of the adapter ABI string (see
+// languages/lumen/extern_system/adapter.rs): an adapter only loads if it was built by the
+// same compiler.
+
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = std::process::Command::new(rustc)
+        .arg("--version")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=LUMEN_RUSTC={}", version);
+}
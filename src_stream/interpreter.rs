@@ -0,0 +1,658 @@
+// Embedding: run a program from Rust and get its value back
+//
+//   use lumen_stream::Interpreter;
+//
+//   let value = Interpreter::new().eval("fn square(x)\n    return x * x\nsquare(7)\n")?;
+//   assert_eq!(value.unwrap().to_string(), "49");
+//
+// Each eval runs its source as a whole program, the way the stream binary runs a file:
+// Lumen programs run after the standard prelude (unless without_prelude), with ARGS,
+// ARGC, FEATURES and the kind constants bound. Output (print, emit) goes to standard output.
+// The value is that of the last top-level expression statement, or of a top-level
+// return; rust_core and python_core statements have no value, so their programs give None.
+//
+// The prelude and the source are parsed separately, so line numbers in errors count
+// from the first line of the source. Nothing a program defines outlives its eval:
+// interpreters share no state, so several can run in one process, on any threads.
+//
+// with_limits bounds a Lumen run (see lib_lumen/limits.rs), so an untrusted program can
+// be run knowing it stops: going over a limit is RunError::LimitExceeded, never a hang.
+// with_intervals gives REALs error bounds, as --intervals does (see
+// languages/lumen/interval.rs).
+//
+// with_capability gives Lumen programs a capability of the host's, next to the builtin
+// ones (fs, net, json, ...):
+//
+//   struct Greet;
+//   impl ExternCapability for Greet {
+//       fn name(&self) -> &'static str { "greet" }
+//       fn call(&self, args: Vec<Value>) -> ExternResult { ... }
+//   }
+//   Interpreter::new().with_capability(Some("host"), Greet).eval("extern(\"host:greet\", \"Ada\")\n")?;
+//
+// It is registered in every run the interpreter starts, is listed in
+// FEATURES["extern_capabilities"], can be mocked and is subject to the run's extern policy
+// (with_limits) like any other; one with the backend and name of a builtin replaces it.
+// with_adapter does the same for the capabilities of an adapter library loaded with
+// adapter::load (see languages/lumen/extern_system/adapter.rs).
+// with_executor runs the program's extern_async calls on the host's executor instead of a
+// thread each (see languages/lumen/extern_system/pending.rs).
+//
+// The capabilities belong to the interpreter: each run (in any of the languages) gets a
+// registry of its own with them, so two interpreters in one process can offer different
+// sets and their calls share no lock. without_builtin_capabilities leaves out the builtin
+// ones, for a host that wants its programs to reach only what it registers:
+//
+//   let sandboxed = Interpreter::new().without_builtin_capabilities().with_capability(Some("host"), Greet);
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::includes::process_includes;
+use crate::kernel::ast::Program;
+use crate::kernel::control_flow::{LocatedError, LumenError};
+use crate::kernel::eval;
+use crate::kernel::lexer::lex;
+use crate::kernel::parser::Parser;
+use crate::kernel::runtime::Value;
+use crate::languages::lumen::extern_system::adapter::Adapter;
+use crate::languages::lumen::extern_system::pending::ExternExecutor;
+use crate::languages::lumen::extern_system::capabilities;
+use crate::languages::lumen::extern_system::registry::{CapabilityRegistry, ExternCapability};
+use crate::languages::lumen::state::State;
+use crate::languages::{lumen, python_core, rust_core};
+use crate::limits::{self, ExecutionLimits};
+use crate::spans;
+
+/// Why a run stopped early
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunError {
+    /// The source did not lex, structure or parse (or the language is unknown): every
+    /// error found
+    Syntax(Vec<LocatedError>),
+    /// Execution stopped with an uncaught error
+    Runtime(LocatedError),
+    /// The program ran `exit status`
+    Exit(i32),
+    /// Execution went over one of its ExecutionLimits
+    LimitExceeded(LocatedError),
+}
+
+impl RunError {
+    fn from_runtime(error: LumenError) -> Self {
+        match error {
+            LumenError::Exit(status) => RunError::Exit(status),
+            LumenError::Raised(error) if limits::limit_exceeded(&error.message) => RunError::LimitExceeded(error),
+            LumenError::Raised(error) => RunError::Runtime(error),
+        }
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Syntax(errors) => {
+                let lines: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            RunError::Runtime(e) | RunError::LimitExceeded(e) => write!(f, "{}", e),
+            RunError::Exit(status) => write!(f, "{}", LumenError::Exit(*status)),
+        }
+    }
+}
+
+/// Runs source text in one of the stream kernel's languages
+#[derive(Clone)]
+pub struct Interpreter {
+    language: String,
+    prelude: bool,
+    args: Vec<String>,
+    limits: ExecutionLimits,
+    intervals: bool,
+    builtin_capabilities: bool,
+    adapters: Vec<Adapter>,
+    capabilities: Vec<HostCapability>,
+    executor: Option<Arc<dyn ExternExecutor>>,
+}
+
+/// A capability the host gave the interpreter, and its backend
+#[derive(Clone)]
+struct HostCapability {
+    backend: Option<String>,
+    capability: Arc<dyn ExternCapability>,
+}
+
+impl fmt::Debug for HostCapability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.backend {
+            Some(backend) => write!(f, "{}:{}", backend, self.capability.name()),
+            None => write!(f, "{}", self.capability.name()),
+        }
+    }
+}
+
+impl fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("language", &self.language)
+            .field("prelude", &self.prelude)
+            .field("args", &self.args)
+            .field("limits", &self.limits)
+            .field("intervals", &self.intervals)
+            .field("builtin_capabilities", &self.builtin_capabilities)
+            .field("adapters", &self.adapters)
+            .field("capabilities", &self.capabilities)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// A Lumen interpreter that runs programs after the standard prelude
+    pub fn new() -> Self {
+        Interpreter {
+            language: "lumen".to_string(),
+            prelude: true,
+            args: Vec::new(),
+            limits: ExecutionLimits::default(),
+            intervals: false,
+            builtin_capabilities: true,
+            adapters: Vec::new(),
+            capabilities: Vec::new(),
+            executor: None,
+        }
+    }
+
+    /// Run programs in `language`: lumen, rust_core or python_core (checked by eval)
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = language.to_lowercase();
+        self
+    }
+
+    /// Run Lumen programs without the prelude: only kernel builtins (emit, len, ...) are defined
+    pub fn without_prelude(mut self) -> Self {
+        self.prelude = false;
+        self
+    }
+
+    /// Program arguments, bound to ARGS (an array of them) and ARGC in Lumen programs
+    pub fn with_args(mut self, args: &[String]) -> Self {
+        self.args = args.to_vec();
+        self
+    }
+
+    /// Stop Lumen programs that go over `limits` (other languages run unlimited, but their
+    /// extern calls follow its policy and audit)
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Give Lumen REALs error bounds, printed as their certified digits
+    pub fn with_intervals(mut self) -> Self {
+        self.intervals = true;
+        self
+    }
+
+    /// Leave the builtin capabilities (fs, net, json, print_native, ...) out of every run:
+    /// programs reach only those of with_capability and with_adapter
+    pub fn without_builtin_capabilities(mut self) -> Self {
+        self.builtin_capabilities = false;
+        self
+    }
+
+    /// Make `capability` reachable from Lumen programs as extern("backend:name") (or
+    /// extern("name") without a backend)
+    pub fn with_capability(mut self, backend: Option<&str>, capability: impl ExternCapability + 'static) -> Self {
+        self.capabilities.push(HostCapability { backend: backend.map(str::to_string), capability: Arc::new(capability) });
+        self
+    }
+
+    /// Make the capabilities of a loaded adapter library reachable from Lumen programs
+    pub fn with_adapter(mut self, adapter: Adapter) -> Self {
+        self.adapters.push(adapter);
+        self
+    }
+
+    /// Run the extern_async calls of Lumen programs on `executor`
+    pub fn with_executor(mut self, executor: impl ExternExecutor + 'static) -> Self {
+        self.executor = Some(Arc::new(executor));
+        self
+    }
+
+    /// Run `source` as a program and return its value
+    /// Errors carry the message (and position) the kernel reports.
+    pub fn eval(&self, source: &str) -> Result<Option<Value>, RunError> {
+        let program = match self.language.as_str() {
+            "lumen" => return self.eval_lumen(source),
+            "rust_core" => {
+                let mut registry = rust_core::registry::Registry::new();
+                rust_core::register_all(&mut registry);
+                parse_rust_core(source, &registry)
+            }
+            "python_core" => {
+                let mut registry = python_core::registry::Registry::new();
+                python_core::register_all(&mut registry);
+                parse_python_core(source, &registry)
+            }
+            other => Err(vec![LocatedError::new(format!("Unknown language '{}'", other))]),
+        };
+        let program = program.map_err(RunError::Syntax)?;
+        let _stage = spans::stage("stream", "execute");
+        eval::eval_value(&program, |env| {
+            // Where their extern calls look (see languages/lumen/extern_system/bridge.rs)
+            *env.language_state(CapabilityRegistry::new) = self.registry();
+            Ok(())
+        })
+        .map_err(RunError::from_runtime)
+    }
+
+    /// Lex, structure and parse `source` without running it (the prelude is not parsed)
+    pub fn check(&self, source: &str) -> Result<(), RunError> {
+        let parsed = match self.language.as_str() {
+            "lumen" => {
+                let mut registry = lumen::registry::Registry::new();
+                lumen::dispatcher::register_all(&mut registry);
+                parse_lumen(source, &registry)
+            }
+            "rust_core" => {
+                let mut registry = rust_core::registry::Registry::new();
+                rust_core::register_all(&mut registry);
+                parse_rust_core(source, &registry)
+            }
+            "python_core" => {
+                let mut registry = python_core::registry::Registry::new();
+                python_core::register_all(&mut registry);
+                parse_python_core(source, &registry)
+            }
+            other => Err(vec![LocatedError::new(format!("Unknown language '{}'", other))]),
+        };
+        parsed.map(|_| ()).map_err(RunError::Syntax)
+    }
+
+    fn eval_lumen(&self, source: &str) -> Result<Option<Value>, RunError> {
+        let mut registry = lumen::registry::Registry::new();
+        lumen::dispatcher::register_all(&mut registry);
+
+        let mut statements = Vec::new();
+        if self.prelude {
+            let prelude = process_includes(include_str!("../lib_lumen/prelude.lm"))
+                .map_err(|e| RunError::Syntax(vec![LocatedError::new(format!("Include error: {}", e))]))?;
+            // Its statements report no statement events (see lib_lumen/spans.rs)
+            spans::program_starts_at(usize::MAX);
+            statements = parse_lumen(&prelude, &registry)
+                .map_err(|errors| {
+                    RunError::Syntax(errors.iter().map(|e| LocatedError::new(format!("prelude: {}", e))).collect())
+                })?
+                .statements;
+        }
+        spans::program_starts_at(1);
+        statements.extend(parse_lumen(source, &registry).map_err(RunError::Syntax)?.statements);
+
+        // The run owns what the program registers (see languages/lumen/state.rs)
+        let functions = registry.take_functions();
+        let _stage = spans::stage("stream", "execute");
+        eval::eval_value(&Program { statements }, |env| {
+            State::install(env, functions, self.limits.clone());
+            State::of(env).intervals = self.intervals;
+            if let Some(executor) = &self.executor {
+                State::of(env).executor = executor.clone();
+            }
+            State::of(env).capabilities = self.registry();
+            lumen::system::bind(env, &self.args);
+            Ok(())
+        })
+        .map_err(RunError::from_runtime)
+    }
+
+    /// The capability registry of a new run: the builtin capabilities (unless left out),
+    /// then the adapters' and the host's, under the extern policy, audit and timeouts of the limits
+    fn registry(&self) -> CapabilityRegistry {
+        let mut registry = CapabilityRegistry::new();
+        if self.builtin_capabilities {
+            capabilities::register_builtins(&mut registry);
+        }
+        for adapter in &self.adapters {
+            adapter.register(&mut registry);
+        }
+        for host in &self.capabilities {
+            registry.register(host.backend.as_deref(), Box::new(host.capability.clone()));
+        }
+        registry.set_policy(self.limits.externs.clone());
+        registry.set_audit(self.limits.audit.clone());
+        registry.set_timeouts(self.limits.extern_timeouts.clone());
+        registry
+    }
+}
+
+/// A source that did not lex or structure has that one error
+fn syntax(error: LumenError) -> Vec<LocatedError> {
+    vec![error.into_located()]
+}
+
+fn parse_lumen(source: &str, registry: &lumen::registry::Registry) -> Result<Program, Vec<LocatedError>> {
+    use lumen::structure::structural;
+    let stage = spans::stage("stream", "lex");
+    let tokens = lex(source, &registry.tokens).map_err(syntax)?;
+    drop(stage);
+    let stage = spans::stage("stream", "structure");
+    let tokens = structural::process_indentation(source, tokens).map_err(syntax)?;
+    drop(stage);
+    let _stage = spans::stage("stream", "parse");
+    let mut parser = Parser::new_with_tokens(tokens, &registry.tokens).map_err(syntax)?;
+    structural::parse_program(&mut parser, registry)
+}
+
+fn parse_rust_core(source: &str, registry: &rust_core::registry::Registry) -> Result<Program, Vec<LocatedError>> {
+    use rust_core::structure::structural;
+    let stage = spans::stage("stream", "lex");
+    let tokens = lex(source, &registry.tokens).map_err(syntax)?;
+    drop(stage);
+    let stage = spans::stage("stream", "structure");
+    let tokens = structural::process_tokens(tokens).map_err(syntax)?;
+    drop(stage);
+    let _stage = spans::stage("stream", "parse");
+    let mut parser = Parser::new_with_tokens(tokens, &registry.tokens).map_err(syntax)?;
+    structural::parse_program(&mut parser, registry)
+}
+
+fn parse_python_core(source: &str, registry: &python_core::registry::Registry) -> Result<Program, Vec<LocatedError>> {
+    use python_core::structure::structural;
+    let stage = spans::stage("stream", "lex");
+    let tokens = lex(source, &registry.tokens).map_err(syntax)?;
+    drop(stage);
+    let stage = spans::stage("stream", "structure");
+    let tokens = structural::process_indentation(source, tokens).map_err(syntax)?;
+    drop(stage);
+    let _stage = spans::stage("stream", "parse");
+    let mut parser = Parser::new_with_tokens(tokens, &registry.tokens).map_err(syntax)?;
+    structural::parse_program(&mut parser, registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::lumen::extern_system::extern_error::ExternError;
+    use crate::languages::lumen::extern_system::registry::ExternResult;
+
+    #[test]
+    fn evaluates_to_the_last_expression_statement() {
+        let value = Interpreter::new().eval("fn square(x)\n    return x * x\nsquare(7)\n").unwrap();
+        assert_eq!(value.unwrap().to_string(), "49");
+        // Functions of an earlier program are gone
+        let error = Interpreter::new().without_prelude().eval("square(7)\n").unwrap_err();
+        assert!(matches!(&error, RunError::Runtime(e) if e.message.contains("square")), "{}", error);
+        assert!(Interpreter::new().eval("x = 1\n").unwrap().is_none());
+    }
+
+    #[test]
+    fn binds_args_and_reports_source_lines() {
+        let args = ["a".to_string(), "b c".to_string()];
+        let value = Interpreter::new().with_args(&args).eval("ARGS[1]\n").unwrap();
+        assert_eq!(value.unwrap().to_string(), "b c");
+        let value = Interpreter::new().with_args(&args).eval("ARGC\n").unwrap();
+        assert_eq!(value.unwrap().to_string(), "2");
+        let error = Interpreter::new().eval("x = 1\ny = 2 + * 3\n").unwrap_err();
+        assert!(matches!(&error, RunError::Syntax(e) if e[0].positions == [(2, 9)]), "{}", error);
+        // An error raised inside a handler is at the token the parser stopped on
+        let error = Interpreter::new().eval("fn f(a)\n    return a\nf(1 = 2)\n").unwrap_err();
+        let RunError::Syntax(errors) = &error else { panic!("{}", error) };
+        assert_eq!(errors, &[LocatedError::at("Expected ',' or ')' after argument", 3, 5)]);
+        assert_eq!(Interpreter::new().eval("exit(3)\n"), Err(RunError::Exit(3)));
+    }
+
+    #[test]
+    fn a_function_value_runs_in_the_scopes_it_was_taken_in() {
+        let source = "fn by_value()\n    calls = []\n    fn order(a, b)\n        push(calls, a)\n        return a - b\n    return order\n\
+                      extern(\"arr:sort_by\", [3, 1, 2], by_value())\n";
+        let value = Interpreter::new().eval(source).unwrap();
+        assert_eq!(value.unwrap().to_string(), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn an_error_naming_an_exit_stays_an_error() {
+        let error = Interpreter::new().eval("error(\"exit requested with status 7\")\n").unwrap_err();
+        assert!(matches!(&error, RunError::Runtime(e) if e.message.contains("exit requested with status 7")), "{:?}", error);
+    }
+
+    #[test]
+    fn programs_do_not_share_mocks_or_functions() {
+        let mocking = "fn fake(args)\n    return 1\nmock_extern(\"test:cap\", fake)\nextern_available(\"test:cap\")\n";
+        let checking = "extern_available(\"test:cap\")\n";
+        let run = |source: &str| Interpreter::new().eval(source).unwrap().unwrap().to_string();
+        assert_eq!(run(mocking), "true");
+        assert_eq!(run(checking), "false");
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| std::thread::spawn(move || run(if i % 2 == 0 { mocking } else { checking })))
+            .collect();
+        let results: Vec<String> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        assert_eq!(results, ["true", "false", "true", "false"]);
+    }
+
+    #[test]
+    fn checks_programs_without_running_them() {
+        assert_eq!(Interpreter::new().check("exit(3)\n"), Ok(()));
+        assert!(matches!(Interpreter::new().check("x = 1 + * 2\n"), Err(RunError::Syntax(_))));
+        assert!(Interpreter::new().with_language("python_core").check("x = 1\n").is_ok());
+    }
+
+    #[test]
+    fn runs_other_languages_and_rejects_unknown_ones() {
+        let value = Interpreter::new().with_language("rust_core").eval("let x = 1;\n").unwrap();
+        assert!(value.is_none());
+        assert!(Interpreter::new().with_language("cobol").eval("").is_err());
+
+        // Both reach Lumen's extern capabilities
+        let python = Interpreter::new().with_language("python_core");
+        assert!(python.eval("n = extern(\"value_type\", 1) + 1\nextern(\"json:stringify\", n)\n").is_ok());
+        let error = Interpreter::new().with_language("rust_core").eval("let x = extern(\"fs:nothing\");\n").unwrap_err();
+        assert!(error.to_string().contains("No implementation found for capability 'nothing'"), "{}", error);
+    }
+
+    #[test]
+    fn stops_runaway_programs_at_their_limits() {
+        let run = |limits: ExecutionLimits, source: &str| Interpreter::new().with_limits(limits).eval(source);
+        let forever = "while true\n    x = 1\n";
+        let steps = ExecutionLimits { max_steps: Some(1000), ..Default::default() };
+        assert!(matches!(run(steps, forever), Err(RunError::LimitExceeded(e)) if e.message.contains("--max-steps")));
+        let time = ExecutionLimits { max_time: Some(std::time::Duration::from_millis(10)), ..Default::default() };
+        assert!(matches!(run(time, forever), Err(RunError::LimitExceeded(e)) if e.message.contains("--max-time")));
+        let heap = ExecutionLimits { max_heap: Some(100_000), ..Default::default() };
+        let growing = "s = \"\"\nwhile true\n    s = s + \"xxxxxxxxxx\"\n";
+        assert!(matches!(run(heap, growing), Err(RunError::LimitExceeded(e)) if e.message.contains("--max-heap")));
+        let depth = ExecutionLimits { max_depth: Some(50), ..Default::default() };
+        assert!(matches!(run(depth, "fn f(n)\n    return f(n + 1)\nf(0)\n"), Err(RunError::LimitExceeded(_))));
+    }
+
+    #[test]
+    fn reaches_only_the_externs_its_policy_allows() {
+        let mut limits = ExecutionLimits::default();
+        limits.externs.apply_flag("--allow-json").unwrap().unwrap();
+        let run = |source: &str| Interpreter::new().with_limits(limits.clone()).eval(source);
+        let parsed = run("extern(\"json:parse\", \"[1, 2]\")\n").unwrap().unwrap();
+        assert_eq!(parsed.as_display_string(), "[1, 2]");
+        let available = run("extern_available(\"time:now\")\n").unwrap().unwrap();
+        assert_eq!(available.as_display_string(), "false");
+        let denied = run("extern(\"time:now\")\n").unwrap_err();
+        assert!(format!("{:?}", denied).contains("--allow-time"), "{:?}", denied);
+    }
+
+    #[test]
+    fn logs_its_extern_calls_to_the_audit() {
+        let log = std::env::temp_dir().join(format!("lumen-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+        let mut limits = ExecutionLimits::default();
+        limits.externs.apply_flag("--allow-json").unwrap().unwrap();
+        limits.audit = Some(crate::ExternAudit::open(log.to_str().unwrap()).unwrap());
+        let source = "extern(\"json:parse\", \"[1]\")\nextern_try(\"time:now\")\nawait_me = extern_async(\"json:stringify\", 2)\nextern_await(await_me)\n";
+        Interpreter::new().with_limits(limits).eval(source).unwrap();
+        let lines: Vec<String> = std::fs::read_to_string(&log).unwrap().lines().map(str::to_string).collect();
+        let _ = std::fs::remove_file(&log);
+        assert_eq!(lines.len(), 3, "{:?}", lines);
+        assert!(lines[0].contains(r#""selector":"json:parse","args":["\"[1]\""]"#) && lines[0].ends_with(r#""ok":true}"#), "{}", lines[0]);
+        assert!(lines[1].contains(r#""ok":false,"code":"denied""#), "{}", lines[1]);
+        assert!(lines[2].contains(r#""selector":"json:stringify","args":["2"]"#), "{}", lines[2]);
+    }
+
+    struct Greet;
+
+    impl ExternCapability for Greet {
+        fn name(&self) -> &'static str {
+            "greet"
+        }
+
+        fn call(&self, args: Vec<Value>) -> ExternResult {
+            if args.is_empty() {
+                return Err(ExternError { retriable: true, ..ExternError::new("no_name", "host:greet needs a name") });
+            }
+            let names: Vec<String> = args.iter().map(|a| a.as_display_string()).collect();
+            Ok(Box::new(lumen::values::LumenString::new(format!("hello {}", names.join(" ")))))
+        }
+    }
+
+    #[test]
+    fn runs_capabilities_the_host_registers() {
+        let interpreter = Interpreter::new().with_capability(Some("host"), Greet);
+        let value = interpreter.eval("extern(\"host:greet\", \"Ada\")\n").unwrap().unwrap();
+        assert_eq!(value.as_display_string(), "hello Ada");
+        let listed = interpreter.eval("FEATURES[\"extern_capabilities\"]\n").unwrap().unwrap();
+        assert!(listed.as_display_string().contains("host:greet"), "{}", listed.as_display_string());
+        assert!(Interpreter::new().eval("extern(\"host:greet\", \"Ada\")\n").is_err());
+        let caught = interpreter.eval("extern_try(\"host:greet\")[\"error\"]\n").unwrap().unwrap();
+        assert_eq!(
+            caught.as_display_string(),
+            "{code: no_name, message: host:greet needs a name, retriable: true, errno: null}"
+        );
+
+        let mut limits = ExecutionLimits::default();
+        limits.externs.apply_flag("--deny-all").unwrap().unwrap();
+        let denied = interpreter.with_limits(limits).eval("extern(\"host:greet\")\n").unwrap_err();
+        assert!(format!("{:?}", denied).contains("--allow-host"), "{:?}", denied);
+    }
+
+    #[test]
+    fn gives_each_interpreter_its_own_capabilities() {
+        let greeting = "extern(\"host:greet\", \"Ada\")\n";
+        let host = Interpreter::new().with_capability(Some("host"), Greet);
+        let sandboxed = Interpreter::new().without_builtin_capabilities().with_capability(Some("host"), Greet);
+        assert_eq!(host.eval(greeting).unwrap().unwrap().as_display_string(), "hello Ada");
+        assert_eq!(sandboxed.eval(greeting).unwrap().unwrap().as_display_string(), "hello Ada");
+        let listed = sandboxed.eval("FEATURES[\"extern_capabilities\"]\n").unwrap().unwrap();
+        assert_eq!(listed.as_display_string(), "[host:greet]");
+        assert!(host.eval("extern(\"json:parse\", \"[]\")\n").is_ok());
+        assert!(sandboxed.eval("extern(\"json:parse\", \"[]\")\n").is_err());
+
+        // The other languages reach the same set
+        let python = sandboxed.clone().with_language("python_core");
+        assert!(python.eval("x = extern(\"host:greet\", \"Ada\")\n").is_ok());
+        assert!(python.eval("x = extern(\"value_type\", 1)\n").is_err());
+    }
+
+    /// Answers after a second
+    struct Slow;
+
+    impl ExternCapability for Slow {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        fn call(&self, _args: Vec<Value>) -> ExternResult {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            Ok(Box::new(lumen::values::LumenNull))
+        }
+    }
+
+    #[test]
+    fn stops_waiting_for_calls_at_their_timeout() {
+        let mut limits = ExecutionLimits::default();
+        limits.extern_timeouts.apply_flag("host:slow=20").unwrap();
+        let interpreter = Interpreter::new().with_capability(Some("host"), Slow).with_capability(Some("host"), Greet).with_limits(limits);
+        let error = interpreter.eval("extern_try(\"host:slow\")[\"error\"]\n").unwrap().unwrap();
+        assert_eq!(
+            error.as_display_string(),
+            "{code: timed_out, message: extern \"host:slow\" timed out after 20 ms, retriable: true, errno: null}"
+        );
+        let greeting = interpreter.eval("extern(\"host:greet\", \"Ada\")\n").unwrap().unwrap();
+        assert_eq!(greeting.as_display_string(), "hello Ada");
+
+        // The program can shorten the host's timeouts, not lengthen them
+        let shortened = Interpreter::new().with_capability(Some("host"), Slow);
+        let source = "extern_timeout(\"host\", 10)\nextern(\"host:slow\")\n";
+        assert!(matches!(shortened.eval(source), Err(RunError::Runtime(e)) if e.message.contains("timed out after 10 ms")));
+        let source = "extern_timeout(\"*\", 60000)\nextern_await(extern_async(\"host:slow\"))\n";
+        assert!(matches!(interpreter.eval(source), Err(RunError::Runtime(e)) if e.message.contains("timed out after 20 ms")));
+    }
+
+    /// Runs each task to the end as soon as it is spawned, counting them
+    struct Inline(Arc<std::sync::atomic::AtomicUsize>);
+
+    impl ExternExecutor for Inline {
+        fn spawn(&self, task: crate::languages::lumen::extern_system::pending::Task) -> std::io::Result<()> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            crate::languages::lumen::extern_system::pending::block_on(task);
+            Ok(())
+        }
+    }
+
+    /// Has no thread to run a task on, as on wasm32-wasip1
+    struct NoThreads;
+
+    impl ExternExecutor for NoThreads {
+        fn spawn(&self, _task: crate::languages::lumen::extern_system::pending::Task) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::Unsupported.into())
+        }
+    }
+
+    /// Answers from its future, which waits once before it is ready
+    struct Later;
+
+    impl ExternCapability for Later {
+        fn name(&self) -> &'static str {
+            "later"
+        }
+
+        fn call(&self, _args: Vec<Value>) -> ExternResult {
+            Err("host:later is only called with extern_async".into())
+        }
+
+        fn call_async(self: Arc<Self>, args: Vec<Value>) -> crate::languages::lumen::extern_system::pending::ExternFuture {
+            let mut waited = false;
+            let mut args = Some(args);
+            Box::pin(std::future::poll_fn(move |context| {
+                if !waited {
+                    waited = true;
+                    context.waker().wake_by_ref();
+                    return std::task::Poll::Pending;
+                }
+                let names: Vec<String> = args.take().unwrap().iter().map(|a| a.as_display_string()).collect();
+                std::task::Poll::Ready(Ok(Box::new(lumen::values::LumenString::new(format!("later {}", names.join(" ")))) as Value))
+            }))
+        }
+    }
+
+    #[test]
+    fn runs_async_extern_calls_on_an_executor() {
+        let source = "a = extern_async(\"host:later\", 1)\nb = extern_async(\"host:greet\", \"Ada\")\n[extern_await(b), extern_await(a)]\n";
+        let run = |interpreter: Interpreter| interpreter.with_capability(Some("host"), Later).with_capability(Some("host"), Greet).eval(source);
+        assert_eq!(run(Interpreter::new()).unwrap().unwrap().as_display_string(), "[hello Ada, later 1]");
+        let spawned = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let inline = Interpreter::new().with_executor(Inline(spawned.clone()));
+        assert_eq!(run(inline).unwrap().unwrap().as_display_string(), "[hello Ada, later 1]");
+        assert_eq!(spawned.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let error = Interpreter::new().eval("extern_await(extern_async(\"json:parse\", \"[\"))\n").unwrap_err();
+        assert!(matches!(&error, RunError::Runtime(e) if e.message.contains("json:parse")), "{}", error);
+        assert!(Interpreter::new().eval("extern_async(\"no:such\")\n").is_err());
+
+        // A call the executor cannot start fails at its await, not at extern_async
+        let source = "h = extern_async(\"time:now\")\nstarted = true\nextern_await(h)\n";
+        let error = Interpreter::new().with_executor(NoThreads).eval(source).unwrap_err();
+        assert!(matches!(&error, RunError::Runtime(e) if e.message.contains("extern_async(\"time:now\"): cannot start the call")), "{}", error);
+    }
+}
@@ -4,8 +4,15 @@
 // No feature enums. No syntax knowledge.
 
 use std::fmt;
+use crate::kernel::control_flow::LumenError;
 use crate::kernel::runtime::{Env, Value};
 
+/// A parsed program
+/// Nodes, like runtime values, are Send + Sync: a host can parse on one thread and run
+/// on another, or run programs on several threads at once.
+/// Nodes are boxed by the language module that defines them, not kept in an arena with
+/// typed indices as the microcode kernel's instructions are: the kernel knows only these
+/// traits, and a node evaluates through `&self` without a program to look children up in.
 pub struct Program {
     pub statements: Vec<Box<dyn StmtNode>>,
 }
@@ -25,10 +32,181 @@ pub enum Control {
     Return(Value),    // Explicit return statement
 }
 
-pub trait ExprNode: fmt::Debug {
-    fn eval(&self, env: &mut Env) -> Result<Value, String>;
+pub trait ExprNode: fmt::Debug + Send + Sync {
+    fn eval(&self, env: &mut Env) -> Result<Value, LumenError>;
+
+    /// Evaluate on the kernel's work stack (see kernel::eval::run)
+    /// Nodes with sub-expressions return them as steps instead of evaluating them
+    /// recursively; leaves (and languages that don't care about depth) keep this default.
+    fn step<'a>(&'a self, env: &mut Env) -> Result<Step<'a>, LumenError> {
+        Ok(Step::value(self.eval(env)?))
+    }
+}
+
+pub trait StmtNode: fmt::Debug + Send + Sync {
+    fn exec(&self, env: &mut Env) -> Result<Control, LumenError>;
+
+    /// Execute on the kernel's work stack (see ExprNode::step)
+    fn step<'a>(&'a self, env: &mut Env) -> Result<Step<'a>, LumenError> {
+        Ok(Step::control(self.exec(env)?))
+    }
+}
+
+/// What a node finished with
+#[derive(Debug)]
+pub enum Outcome {
+    Value(Value),
+    Control(Control),
+}
+
+impl Outcome {
+    pub fn into_value(self) -> Value {
+        match self {
+            Outcome::Value(value) => value,
+            Outcome::Control(_) => unreachable!("an expression finishes with a value"),
+        }
+    }
+
+    pub fn into_control(self) -> Control {
+        match self {
+            Outcome::Control(control) => control,
+            Outcome::Value(_) => unreachable!("a statement finishes with a control"),
+        }
+    }
+}
+
+/// A node to run on the work stack
+#[derive(Clone, Copy)]
+pub enum Node<'a> {
+    Expr(&'a dyn ExprNode),
+    Stmt(&'a dyn StmtNode),
+}
+
+impl<'a> Node<'a> {
+    pub fn step(self, env: &mut Env) -> Result<Step<'a>, LumenError> {
+        match self {
+            Node::Expr(expr) => expr.step(env),
+            Node::Stmt(stmt) => stmt.step(env),
+        }
+    }
+}
+
+/// Work waiting on the stack for what runs above it; it sees errors too, so it can
+/// clean up or mark them
+pub type Then<'a> = Box<dyn FnOnce(Result<Outcome, LumenError>, &mut Env) -> Result<Step<'a>, LumenError> + 'a>;
+
+/// What running a node did: finished, or left work for the kernel's loop
+pub enum Step<'a> {
+    Done(Outcome),
+    /// Run the node, then hand its outcome to `then`
+    Run(Node<'a>, Then<'a>),
+    /// Carry on with the step, then hand its outcome to `then`
+    After(Box<Step<'a>>, Then<'a>),
+}
+
+impl<'a> Step<'a> {
+    pub fn value(value: Value) -> Self {
+        Step::Done(Outcome::Value(value))
+    }
+
+    pub fn control(control: Control) -> Self {
+        Step::Done(Outcome::Control(control))
+    }
+
+    /// Evaluate `expr`, then continue with its value (an error skips `then`)
+    pub fn eval<F>(expr: &'a dyn ExprNode, then: F) -> Self
+    where
+        F: FnOnce(Value, &mut Env) -> Result<Step<'a>, LumenError> + 'a,
+    {
+        Step::Run(Node::Expr(expr), Box::new(move |outcome, env| then(outcome?.into_value(), env)))
+    }
+
+    /// Execute `stmt`, then continue with its control (an error skips `then`)
+    pub fn exec<F>(stmt: &'a dyn StmtNode, then: F) -> Self
+    where
+        F: FnOnce(Control, &mut Env) -> Result<Step<'a>, LumenError> + 'a,
+    {
+        Step::Run(Node::Stmt(stmt), Box::new(move |outcome, env| then(outcome?.into_control(), env)))
+    }
+
+    /// Evaluate `exprs` left to right, then continue with their values
+    pub fn eval_all<F>(exprs: &'a [Box<dyn ExprNode>], then: F) -> Self
+    where
+        F: FnOnce(Vec<Value>, &mut Env) -> Result<Step<'a>, LumenError> + 'a,
+    {
+        if exprs.is_empty() {
+            return Step::control(Control::None).then(move |_, env| then(Vec::new(), env));
+        }
+        eval_from(exprs, Vec::with_capacity(exprs.len()), Box::new(then))
+    }
+
+    /// Continue with this step, then hand its outcome (or error) to `then`
+    pub fn then<F>(self, then: F) -> Self
+    where
+        F: FnOnce(Result<Outcome, LumenError>, &mut Env) -> Result<Step<'a>, LumenError> + 'a,
+    {
+        Step::After(Box::new(self), Box::new(then))
+    }
+}
+
+type ThenValues<'a> = Box<dyn FnOnce(Vec<Value>, &mut Env) -> Result<Step<'a>, LumenError> + 'a>;
+
+fn eval_from<'a>(exprs: &'a [Box<dyn ExprNode>], values: Vec<Value>, then: ThenValues<'a>) -> Step<'a> {
+    Step::eval(exprs[values.len()].as_ref(), move |value, env| {
+        let mut values = values;
+        values.push(value);
+        if values.len() == exprs.len() {
+            then(values, env)
+        } else {
+            Ok(eval_from(exprs, values, then))
+        }
+    })
 }
 
-pub trait StmtNode: fmt::Debug {
-    fn exec(&self, env: &mut Env) -> Result<Control, String>;
+/// A statement with the position of its first token in the parsed text
+/// Runtime errors leaving it get its position (see kernel::control_flow::at_statement).
+#[derive(Debug)]
+pub struct LocatedStmt {
+    inner: Box<dyn StmtNode>,
+    line: usize,
+    col: usize,
+}
+
+impl LocatedStmt {
+    pub fn wrap(inner: Box<dyn StmtNode>, line: usize, col: usize) -> Box<dyn StmtNode> {
+        Box::new(LocatedStmt { inner, line, col })
+    }
+}
+
+impl StmtNode for LocatedStmt {
+    fn exec(&self, env: &mut Env) -> Result<Control, LumenError> {
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> Result<Step<'a>, LumenError> {
+        let (line, col) = (self.line, self.col);
+        Ok(Step::Run(
+            Node::Stmt(self.inner.as_ref()),
+            Box::new(move |outcome, _env| {
+                outcome
+                    .map(Step::Done)
+                    .map_err(|e| crate::kernel::control_flow::at_statement(e, line, col))
+            }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn programs_and_runtime_state_can_cross_threads() {
+        assert_send_sync::<Program>();
+        assert_send_sync::<Env>();
+        assert_send_sync::<Value>();
+        assert_send_sync::<Control>();
+    }
 }
@@ -2,37 +2,93 @@
 //
 // Core execution loop.
 // No language semantics live here.
+//
+// Nodes run on an explicit work stack rather than the host stack: a node's `step` either
+// finishes or names the next node to run together with the work that waits for it
+// (see kernel::ast::Step). The loop in `run` pushes the waiting work, runs the next node,
+// and hands each outcome (or error) down to the work waiting for it. Nesting of
+// expressions and of user function calls is then bounded by memory, not by the native
+// stack. A node that still evaluates its children through eval/exec starts a nested run.
 
-use crate::kernel::ast::{Control, Program};
+use crate::kernel::ast::{Control, ExprNode, Outcome, Program, Step, StmtNode, Then};
+use crate::kernel::control_flow::LumenError;
 use crate::kernel::runtime::env::Env;
+use crate::kernel::runtime::Value;
 
 /// Execute a program.
 /// The environment includes a memoization cache that is always present.
 /// Only functions explicitly marked as memoizable use the cache (matching microcode kernel design).
 /// init_fn: callback to initialize the environment with language-specific system values (like ARGS)
-pub fn eval<F>(program: &Program, init_fn: F) -> Result<(), String>
+pub fn eval<F>(program: &Program, init_fn: F) -> Result<(), LumenError>
+where
+    F: FnOnce(&mut Env) -> Result<(), LumenError>,
+{
+    eval_value(program, init_fn).map(|_| ())
+}
+
+/// Execute a program like eval and return its value: that of the last top-level
+/// expression statement that ran, or of a top-level return (None if neither ran)
+pub fn eval_value<F>(program: &Program, init_fn: F) -> Result<Option<Value>, LumenError>
 where
-    F: FnOnce(&mut Env) -> Result<(), String>,
+    F: FnOnce(&mut Env) -> Result<(), LumenError>,
 {
     let mut env = Env::new();
 
     // Initialize system values (ARGS, etc.) via language-specific callback
     init_fn(&mut env)?;
 
+    let mut value = None;
     for stmt in &program.statements {
         match stmt.exec(&mut env)? {
             Control::None => {}
-            Control::ExprValue(_) => {
-                // Expression statement value - ignore at top level and continue
+            Control::ExprValue(val) => {
+                // Expression statement value - kept as the program's value so far
+                value = Some(val);
             }
             Control::Break => break,
             Control::Continue => continue,
-            Control::Return(_) => {
+            Control::Return(val) => {
                 // Explicit return at top level - stop execution
-                break;
+                return Ok(Some(val));
             }
         }
     }
 
-    Ok(())
+    Ok(value)
+}
+
+/// Run a step and all the work it leads to
+pub fn run<'a>(first: Result<Step<'a>, LumenError>, env: &mut Env) -> Result<Outcome, LumenError> {
+    let mut waiting: Vec<Then<'a>> = Vec::new();
+    let mut step = first;
+    loop {
+        let finished = match step {
+            Ok(Step::Run(node, then)) => {
+                waiting.push(then);
+                step = node.step(env);
+                continue;
+            }
+            Ok(Step::After(next, then)) => {
+                waiting.push(then);
+                step = Ok(*next);
+                continue;
+            }
+            Ok(Step::Done(outcome)) => Ok(outcome),
+            Err(e) => Err(e),
+        };
+        match waiting.pop() {
+            Some(then) => step = then(finished, env),
+            None => return finished,
+        }
+    }
+}
+
+/// Evaluate an expression on the work stack
+pub fn run_expr(expr: &dyn ExprNode, env: &mut Env) -> Result<Value, LumenError> {
+    run(expr.step(env), env).map(Outcome::into_value)
+}
+
+/// Execute a statement on the work stack
+pub fn run_stmt(stmt: &dyn StmtNode, env: &mut Env) -> Result<Control, LumenError> {
+    run(stmt.step(env), env).map(Outcome::into_control)
 }
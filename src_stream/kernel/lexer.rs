@@ -20,9 +20,13 @@
 // - line/col are DIAGNOSTIC-ONLY (derived metadata for error messages only)
 //
 // ARCHITECTURE:
-// - Token: { lexeme: String, span: Span } - opaque, no semantic categories
+// - Token: { lexeme: Lexeme, span: Span } - opaque, no semantic categories; the lexeme
+//   borrows its text (a registered sequence or an ASCII character), so lexing a source
+//   allocates almost nothing per token
 // - SpannedToken: adds line/col for diagnostic formatting
-// - Lexer: pure maximal-munch with language-provided sequences + single-char fallback
+// - Lexer: pure maximal-munch with language-provided sequences + single-char fallback,
+//   reading its input in chunks (lex_reader), so a source never has to be in memory
+//   as a whole: only a chunk and the tokens not yet consumed are
 // - No character-class checks (no is_digit, is_alpha, is_whitespace)
 // - No special-case handling (no string literals, numbers, identifiers, keywords)
 // - No assumptions about human language conventions
@@ -32,7 +36,10 @@
 // The lexer tokenizes this WITHOUT KNOWING what it means.
 // All meaning is defined by the language module via registry and parser.
 
-use crate::kernel::registry::{LumenResult, TokenRegistry};
+use std::borrow::Cow;
+use std::io::{ErrorKind, Read};
+
+use crate::kernel::registry::{LocatedError, LumenError, LumenResult, TokenRegistry};
 
 /// Explicit byte span: (start, end) offsets in source code
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -47,15 +54,19 @@ impl Span {
     }
 }
 
+/// Text of a token: borrowed from the language's registered sequences or from ASCII for
+/// almost every token, owned only for other characters and tokens built by languages
+pub type Lexeme = Cow<'static, str>;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
-    pub lexeme: String,
+    pub lexeme: Lexeme,
     pub span: Span,
 }
 
 impl Token {
-    pub fn new(lexeme: String, span: Span) -> Self {
-        Self { lexeme, span }
+    pub fn new(lexeme: impl Into<Lexeme>, span: Span) -> Self {
+        Self { lexeme: lexeme.into(), span }
     }
 }
 
@@ -72,53 +83,63 @@ impl SpannedToken {
     }
 }
 
-/// Strip single-line comments from source.
+/// Bytes read from the input at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Strips single-line comments from source, one chunk at a time.
 /// Comments start with # and continue until end of line.
 /// Preserves newlines for correct line counting.
 /// Respects string boundaries: # inside strings is not a comment.
-fn strip_comments(source: &str) -> String {
-    let mut result = String::with_capacity(source.len());
-    let mut chars = source.chars().peekable();
-    let mut in_string = false;
-    let mut string_char = ' ';
-    let mut escape_next = false;
-
-    while let Some(ch) = chars.next() {
-        // Handle escape sequences in strings
-        if escape_next {
-            result.push(ch);
-            escape_next = false;
-            continue;
-        }
-
-        if ch == '\\' && in_string {
-            result.push(ch);
-            escape_next = true;
-            continue;
-        }
+/// Quotes, backslashes, # and newlines are ASCII, so the bytes of other characters
+/// pass through untouched; the state carries over from one chunk to the next.
+#[derive(Default)]
+struct CommentStripper {
+    in_string: bool,
+    string_char: u8,
+    escape_next: bool,
+    in_comment: bool,
+}
 
-        // Track string state (both single and double quotes)
-        if !in_string && (ch == '"' || ch == '\'') {
-            in_string = true;
-            string_char = ch;
-            result.push(ch);
-        } else if in_string && ch == string_char {
-            in_string = false;
-            result.push(ch);
-        } else if !in_string && ch == '#' {
-            // Skip comment until newline (but preserve the newline)
-            while let Some(c) = chars.next() {
-                if c == '\n' {
-                    result.push('\n');
-                    break;
+impl CommentStripper {
+    fn strip(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        for &byte in chunk {
+            if self.in_comment {
+                // Skip comment until newline (but preserve the newline)
+                if byte == b'\n' {
+                    out.push(byte);
+                    self.in_comment = false;
                 }
+                continue;
+            }
+
+            // Handle escape sequences in strings
+            if self.escape_next {
+                out.push(byte);
+                self.escape_next = false;
+                continue;
+            }
+
+            if byte == b'\\' && self.in_string {
+                out.push(byte);
+                self.escape_next = true;
+                continue;
+            }
+
+            // Track string state (both single and double quotes)
+            if !self.in_string && (byte == b'"' || byte == b'\'') {
+                self.in_string = true;
+                self.string_char = byte;
+                out.push(byte);
+            } else if self.in_string && byte == self.string_char {
+                self.in_string = false;
+                out.push(byte);
+            } else if !self.in_string && byte == b'#' {
+                self.in_comment = true;
+            } else {
+                out.push(byte);
             }
-        } else {
-            result.push(ch);
         }
     }
-
-    result
 }
 
 /// Tokenize source code using pure maximal-munch segmentation.
@@ -128,9 +149,9 @@ fn strip_comments(source: &str) -> String {
 /// Algorithm:
 ///   1. Strip comments from source (# to end of line, respecting strings)
 ///   2. At each byte position, try to match the longest language-supplied multi-char sequence
-///   3. If no multi-char match, emit a single byte as a token (including all whitespace)
-///   4. Track line/col for every byte (required for error reporting)
-///   5. Never reject any input - all bytes are valid
+///   3. If no multi-char match, emit a single character as a token (including all whitespace)
+///   4. Track line/col for every character (required for error reporting)
+///   5. Never reject valid UTF-8 - all characters are valid
 ///
 /// This lexer makes NO assumptions about:
 ///   - What constitutes whitespace or if it's meaningful
@@ -140,83 +161,242 @@ fn strip_comments(source: &str) -> String {
 ///
 /// All such interpretation is delegated entirely to language modules.
 pub fn lex(source: &str, token_reg: &TokenRegistry) -> LumenResult<Vec<SpannedToken>> {
-    let source = strip_comments(source);
-    let mut out = Vec::new();
-    let bytes = source.as_bytes();
-    let mut byte_pos = 0usize;
-    let mut line_no = 1usize;
-    let mut col_in_line = 1usize;
+    lex_reader(source.as_bytes(), token_reg).collect()
+}
 
-    while byte_pos < bytes.len() {
-        let start_col = col_in_line;
+/// Tokenize the source `reader` yields, like lex, reading it in chunks as tokens are taken.
+/// Spans are byte offsets into the source with comments stripped, as for lex. Read errors
+/// and invalid UTF-8 end the stream with an error.
+pub fn lex_reader<R: Read>(reader: R, token_reg: &TokenRegistry) -> Lexer<'_, R> {
+    // Bytes that must be buffered past a position to decide its token: the longest
+    // sequence, the byte after it (word boundary), and at least one whole character
+    let lookahead = token_reg.multichar_trie().longest().max(4) + 1;
+    Lexer {
+        reader,
+        token_reg,
+        stripper: CommentStripper::default(),
+        buffer: Vec::new(),
+        pos: 0,
+        offset: 0,
+        previous: None,
+        line_no: 1,
+        col_in_line: 1,
+        lookahead,
+        eof: false,
+    }
+}
 
-        // Try maximal-munch: match longest language-provided sequence first
-        let remaining = &source[byte_pos..];
-        let mut matched = false;
+/// Tokens of a source read in chunks (see lex_reader)
+pub struct Lexer<'a, R> {
+    reader: R,
+    token_reg: &'a TokenRegistry,
+    stripper: CommentStripper,
+    /// Stripped source not yet tokenized starts at buffer[pos]
+    buffer: Vec<u8>,
+    pos: usize,
+    /// Byte offset of buffer[pos] in the stripped source
+    offset: usize,
+    /// The byte before buffer[pos], for word boundaries
+    previous: Option<u8>,
+    line_no: usize,
+    col_in_line: usize,
+    lookahead: usize,
+    eof: bool,
+}
 
-        // Try multi-char sequences in descending length order (pre-sorted by registry)
-        for &multichar in token_reg.multichar_lexemes() {
-            if !remaining.starts_with(multichar) {
-                continue;
+impl<R: Read> Lexer<'_, R> {
+    /// Read until `lookahead` bytes are buffered past pos, or the input ends
+    fn fill(&mut self) -> LumenResult<()> {
+        if self.eof || self.buffer.len() - self.pos >= self.lookahead {
+            return Ok(());
+        }
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        let mut chunk = vec![0; CHUNK_SIZE];
+        while !self.eof && self.buffer.len() - self.pos < self.lookahead {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(read) => self.stripper.strip(&chunk[..read], &mut self.buffer),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    let message = format!("Cannot read source: {}", e);
+                    return Err(LumenError::Raised(LocatedError::at(message, self.line_no, self.col_in_line)));
+                }
             }
+        }
+        Ok(())
+    }
 
+    /// The next token, or None at the end of the source
+    fn next_token(&mut self) -> LumenResult<Option<SpannedToken>> {
+        self.fill()?;
+        let remaining = &self.buffer[self.pos..];
+        if remaining.is_empty() {
+            return Ok(None);
+        }
+
+        // Try maximal-munch: match longest language-provided sequence first
+        // (walking the registry's trie of multi-char sequences)
+        let previous = self.previous;
+        let multichar = self.token_reg.multichar_trie().longest_match(remaining, |multichar, word| {
             // Respect word boundaries for keyword-like tokens to avoid breaking identifiers.
-            if token_reg.requires_word_boundary(multichar) {
-                let end = byte_pos + multichar.len();
-                let left_ok = byte_pos == 0 || !is_identifier_byte(bytes[byte_pos - 1]);
-                let right_ok = end >= bytes.len() || !is_identifier_byte(bytes[end]);
+            !word
+                || (!previous.is_some_and(is_identifier_byte)
+                    && !remaining.get(multichar.len()).copied().is_some_and(is_identifier_byte))
+        });
 
-                if !(left_ok && right_ok) {
-                    continue;
-                }
+        // No multi-char match: emit a single character as token
+        // Kernel does not reject any character - even whitespace, control chars, etc.
+        // Languages interpret all characters according to their conventions.
+        let lexeme: Lexeme = match multichar {
+            Some(multichar) => Cow::Borrowed(multichar),
+            None if remaining[0].is_ascii() => Cow::Borrowed(ascii_lexeme(remaining[0])),
+            None => match std::str::from_utf8(&remaining[..remaining.len().min(4)]) {
+                Ok(text) => text.chars().next().map(String::from),
+                Err(e) if e.valid_up_to() > 0 => std::str::from_utf8(&remaining[..e.valid_up_to()])
+                    .ok()
+                    .and_then(|text| text.chars().next())
+                    .map(String::from),
+                Err(_) => None,
             }
+            .map(Cow::Owned)
+            .ok_or_else(|| LumenError::Raised(LocatedError::at("Invalid UTF-8", self.line_no, self.col_in_line)))?,
+        };
 
-            // Found a multi-char match. Emit it and update position tracking.
-            let lexeme = multichar.to_string();
-            let span = Span::new(byte_pos, byte_pos + multichar.len());
-            out.push(SpannedToken::new(Token::new(lexeme, span), line_no, start_col));
-
-            // Update line/col for the matched sequence
-            for byte in multichar.as_bytes() {
-                if *byte == b'\n' {
-                    line_no += 1;
-                    col_in_line = 1;
-                } else {
-                    col_in_line += 1;
-                }
-            }
+        let span = Span::new(self.offset, self.offset + lexeme.len());
+        let token = SpannedToken::new(Token::new(lexeme, span), self.line_no, self.col_in_line);
 
-            byte_pos += multichar.len();
-            matched = true;
-            break;
+        // Update line/col for the matched sequence
+        for ch in token.tok.lexeme.chars() {
+            if ch == '\n' {
+                self.line_no += 1;
+                self.col_in_line = 1;
+            } else {
+                self.col_in_line += 1;
+            }
         }
+        self.pos += span.end - span.start;
+        self.offset = span.end;
+        self.previous = token.tok.lexeme.as_bytes().last().copied();
+        Ok(Some(token))
+    }
+}
 
-        if matched {
-            continue;
-        }
+impl<R: Read> Iterator for Lexer<'_, R> {
+    type Item = LumenResult<SpannedToken>;
 
-        // No multi-char match: emit single byte as token
-        // Kernel does not reject any byte - even whitespace, control chars, etc.
-        // Languages interpret all bytes according to their conventions.
-        let byte = bytes[byte_pos];
-        let lexeme = (byte as char).to_string();
-        let span = Span::new(byte_pos, byte_pos + 1);
-        out.push(SpannedToken::new(Token::new(lexeme, span), line_no, start_col));
-
-        // Update line/col for the single byte
-        if byte == b'\n' {
-            line_no += 1;
-            col_in_line = 1;
-        } else {
-            col_in_line += 1;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_token() {
+            Ok(token) => token.map(Ok),
+            Err(e) => {
+                // Nothing follows an error
+                self.buffer.clear();
+                self.pos = 0;
+                self.eof = true;
+                Some(Err(e))
+            }
         }
+    }
+}
 
-        byte_pos += 1;
+/// Every ASCII character, so a single-character token can borrow its text
+const ASCII_BYTES: [u8; 128] = {
+    let mut bytes = [0u8; 128];
+    let mut byte = 0;
+    while byte < 128 {
+        bytes[byte] = byte as u8;
+        byte += 1;
     }
+    bytes
+};
+const ASCII: &str = match std::str::from_utf8(&ASCII_BYTES) {
+    Ok(text) => text,
+    Err(_) => panic!("ASCII is UTF-8"),
+};
+
+fn ascii_lexeme(byte: u8) -> &'static str {
+    &ASCII[byte as usize..byte as usize + 1]
+}
 
-    Ok(out)
+/// Walks tokens in source order a line at a time, for structure passes that go through
+/// a source line by line (skipping some lines) without rescanning every token per line
+pub struct LineCursor<'a> {
+    tokens: &'a [SpannedToken],
+    next: usize,
+}
+
+impl<'a> LineCursor<'a> {
+    pub fn new(tokens: &'a [SpannedToken]) -> Self {
+        Self { tokens, next: 0 }
+    }
+
+    /// The tokens on line `line_no`; lines are asked for in increasing order
+    pub fn line(&mut self, line_no: usize) -> &'a [SpannedToken] {
+        while self.tokens.get(self.next).is_some_and(|token| token.line < line_no) {
+            self.next += 1;
+        }
+        let start = self.next;
+        while self.tokens.get(self.next).is_some_and(|token| token.line == line_no) {
+            self.next += 1;
+        }
+        &self.tokens[start..self.next]
+    }
 }
 
 fn is_identifier_byte(b: u8) -> bool {
-    b.is_ascii_alphanumeric() || b == b'_' 
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::registry::TokenDefinition;
+
+    /// A reader that yields one byte per read, so every token straddles chunk boundaries
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    fn registry() -> TokenRegistry {
+        let mut registry = TokenRegistry::new();
+        registry.set_token_definitions(vec![TokenDefinition::keyword("if"), TokenDefinition::recognize("==")]);
+        registry
+    }
+
+    #[test]
+    fn lexes_a_reader_like_a_string() {
+        let registry = registry();
+        let source = "if x == \"#é\" # note\niffy == 1\n";
+        let whole = lex(source, &registry).unwrap();
+        let read: Vec<SpannedToken> = lex_reader(Trickle(source.as_bytes()), &registry).collect::<LumenResult<_>>().unwrap();
+        let lexemes = |tokens: &[SpannedToken]| tokens.iter().map(|t| (t.tok.lexeme.clone(), t.tok.span, t.line, t.col)).collect::<Vec<_>>();
+        assert_eq!(lexemes(&whole), lexemes(&read));
+
+        let texts: Vec<&str> = whole.iter().map(|t| t.tok.lexeme.as_ref()).collect();
+        assert_eq!(texts[..5], ["if", " ", "x", " ", "=="]);
+        // A string keeps its #, a comment is dropped, a character is one token
+        assert!(texts.contains(&"é"));
+        assert!(!texts.contains(&"n"));
+        // No keyword inside an identifier
+        assert_eq!(whole.iter().find(|t| t.line == 2).unwrap().tok.lexeme, "i");
+        // Only the non-ASCII character owns its text
+        let owned: Vec<&Token> = whole.iter().map(|t| &t.tok).filter(|t| matches!(t.lexeme, Cow::Owned(_))).collect();
+        assert_eq!(owned.len(), 1);
+        assert_eq!(owned[0].lexeme, "é");
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error() {
+        let error = lex_reader(&b"x = \xff\n"[..], &registry()).find_map(Result::err).unwrap();
+        assert_eq!(error, LumenError::Raised(LocatedError::at("Invalid UTF-8", 1, 5)));
+    }
 }
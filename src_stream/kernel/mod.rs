@@ -11,4 +11,25 @@ pub mod parser;
 pub mod registry;
 pub mod runtime;
 
+// Maximal-munch matching of multi-character lexemes, shared with the microcode kernel
+pub mod lexeme_trie {
+    include!("../../lib_lumen/lexeme_trie.rs");
+}
 
+// break/continue/return rules and error positions, shared with the microcode kernel
+pub mod control_flow {
+    include!("../../lib_lumen/control_flow.rs");
+}
+
+
+
+// How values are written by print, repr(), debug_info and error messages, shared with
+// the microcode kernel
+pub mod repr {
+    include!("../../lib_lumen/repr.rs");
+}
+
+// Bounded LRU cache of MEMOIZATION results and its statistics, shared with the microcode kernel
+pub mod memo_cache {
+    include!("../../lib_lumen/memo_cache.rs");
+}
@@ -12,11 +12,13 @@
 // Token skipping (whitespace, comments) is handled by language-specific extension traits.
 
 use crate::kernel::lexer::{SpannedToken, Token};
-use crate::kernel::registry::{LumenResult, TokenRegistry};
+use crate::kernel::registry::{LocatedError, LumenError, LumenResult, TokenRegistry};
 
 pub struct Parser<'a> {
     pub toks: Vec<SpannedToken>,
     pub i: usize,
+    /// Errors of statements that failed to parse and were skipped (see recover)
+    pub errors: Vec<LocatedError>,
     _token_registry: std::marker::PhantomData<&'a TokenRegistry>,
 }
 
@@ -27,6 +29,7 @@ impl<'a> Parser<'a> {
         Ok(Self {
             toks,
             i: 0,
+            errors: Vec::new(),
             _token_registry: std::marker::PhantomData,
         })
     }
@@ -34,12 +37,12 @@ impl<'a> Parser<'a> {
     /// Get diagnostic line/column position of current token.
     /// DIAGNOSTIC ONLY - derived from source; not used by parsing logic.
     pub fn position(&self) -> (usize, usize) {
-        let t = self.toks.get(self.i).unwrap();
+        let t = self.current();
         (t.line, t.col)
     }
 
     pub fn peek(&self) -> &Token {
-        &self.toks[self.i].tok
+        &self.current().tok
     }
 
     pub fn peek_n(&self, n: usize) -> Option<&Token> {
@@ -47,9 +50,73 @@ impl<'a> Parser<'a> {
     }
 
     pub fn advance(&mut self) -> Token {
-        let t = self.toks[self.i].tok.clone();
+        let t = self.current().tok.clone();
         self.i += 1;
         t
     }
+
+    /// Record the error of a statement that failed to parse, and skip the rest of it
+    /// An error a handler raised without a position is placed at the token where parsing
+    /// stopped. Parsing starts over from `start`, where the statement began, and stops
+    /// after the statement's end, so the next statement can be parsed and its errors
+    /// reported too.
+    pub fn recover(&mut self, error: LumenError, start: usize, syntax: &StatementSyntax) {
+        let mut error = error.into_located();
+        if error.positions.is_empty() {
+            let (line, col) = self.position();
+            error = LocatedError::at(error.message, line, col);
+        }
+        self.errors.push(error);
+        self.i = start;
+        let mut depth = 0usize;
+        loop {
+            let lexeme = self.peek().lexeme.clone();
+            if lexeme == syntax.end || (depth == 0 && lexeme == syntax.block_close && self.i > start) {
+                // The end of the input, or the end of the block the statement is in
+                break;
+            }
+            self.i += 1;
+            let next = self.next_lexeme(syntax.skip);
+            if lexeme == syntax.block_open {
+                depth += 1;
+            } else if lexeme == syntax.block_close {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && !syntax.continuations.contains(&next) {
+                    break;
+                }
+            } else if depth == 0 && syntax.terminators.contains(&lexeme.as_ref()) && next != syntax.block_open {
+                break;
+            }
+        }
+    }
+
+    /// Lexeme of the next token that is not in `skip`
+    fn next_lexeme(&self, skip: &[&str]) -> &str {
+        self.toks[self.i.min(self.toks.len())..]
+            .iter()
+            .map(|t| t.tok.lexeme.as_ref())
+            .find(|lexeme| !skip.contains(lexeme))
+            .unwrap_or("")
+    }
+
+    /// Token at the cursor; a cursor moved past the end stays on the last token (EOF)
+    fn current(&self) -> &SpannedToken {
+        &self.toks[self.i.min(self.toks.len() - 1)]
+    }
 }
 
+
+/// Where statements end, for skipping one that failed to parse
+pub struct StatementSyntax {
+    /// Tokens that open and close a block
+    pub block_open: &'static str,
+    pub block_close: &'static str,
+    /// Tokens that end a statement outside of blocks
+    pub terminators: &'static [&'static str],
+    /// Keywords that continue a statement after its block (e.g. else)
+    pub continuations: &'static [&'static str],
+    /// Tokens looked past to find what follows a block or terminator (e.g. whitespace)
+    pub skip: &'static [&'static str],
+    /// The end-of-input token
+    pub end: &'static str,
+}
@@ -15,16 +15,19 @@
 // - Languages define their own Precedence types
 // - Languages manage all dispatch and handler logic
 
+use crate::kernel::lexeme_trie::LexemeTrie;
 use crate::kernel::parser::Parser;
 
-pub type LumenResult<T> = Result<T, String>;
+pub type LumenResult<T> = Result<T, LumenError>;
 
-/// Format a parse error with diagnostic position information.
+pub use crate::kernel::control_flow::{LocatedError, LumenError};
+
+/// A parse error at the current token.
 /// DIAGNOSTIC FUNCTION: Uses line/col (derived from source) only for human-readable error messages.
 /// line/col are NOT used by parsing logic - all core logic uses Span.
-pub fn err_at(parser: &Parser, msg: &str) -> String {
+pub fn err_at(parser: &Parser, msg: &str) -> LumenError {
     let (line, col) = parser.position();
-    format!("ParseError at {line}:{col}: {msg}")
+    LumenError::Raised(LocatedError::at(msg, line, col))
 }
 
 // --------------------
@@ -76,6 +79,8 @@ pub struct TokenRegistry {
     // Cached: Multi-character lexeme sequences for maximal-munch segmentation
     // Stored in descending length order for proper maximal-munch
     multichar_lexemes: Vec<&'static str>,
+    // Cached: The same lexemes in a trie, flagged if they require word boundaries
+    multichar_trie: LexemeTrie,
     // Cached: Tokens that should be skipped during parsing
     skip_tokens: Vec<&'static str>,
     // Cached: Tokens that require word boundaries (keywords that shouldn't match inside identifiers)
@@ -87,6 +92,7 @@ impl TokenRegistry {
         Self {
             token_defs: Vec::new(),
             multichar_lexemes: Vec::new(),
+            multichar_trie: LexemeTrie::default(),
             skip_tokens: Vec::new(),
             word_boundary_lexemes: Vec::new(),
         }
@@ -106,6 +112,12 @@ impl TokenRegistry {
         &self.multichar_lexemes
     }
 
+    /// Get the multi-character lexemes as a trie, each flagged if it requires word boundaries.
+    /// Used by the lexer for maximal-munch segmentation.
+    pub fn multichar_trie(&self) -> &LexemeTrie {
+        &self.multichar_trie
+    }
+
 
     /// Check if the lexeme requires surrounding word boundaries.
     /// Used by the lexer to avoid splitting identifiers that contain keywords.
@@ -139,6 +151,7 @@ impl TokenRegistry {
         // Sort by descending length for proper maximal-munch
         multichar.sort_by(|a, b| b.len().cmp(&a.len()));
 
+        self.multichar_trie = LexemeTrie::new(multichar.iter().map(|&lexeme| (lexeme, word_boundary.contains(&lexeme))));
         self.multichar_lexemes = multichar;
         self.skip_tokens = skip;
         self.word_boundary_lexemes = word_boundary;
@@ -3,8 +3,11 @@
 // Runtime environment: variable bindings and lexical scopes.
 // This file is core infrastructure and must remain stable.
 
+use std::any::Any;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
 
+use crate::kernel::memo_cache::MemoCache;
 use crate::kernel::runtime::Value;
 
 // ============================================================================
@@ -20,12 +23,45 @@ use crate::kernel::runtime::Value;
 // - NOT readable, passable, or storable as data
 //
 // Cache key: (function_name, argument_fingerprint)
+// The cache is bounded and evicts least recently used results (see lib_lumen/memo_cache.rs).
 
-type MemoKey = (String, String);
+// ============================================================================
+// SCOPES
+// ============================================================================
+//
+// A scope is shared, not owned: a function value keeps the scopes it was taken in
+// (Captured), and a call of it runs in those same scopes. What the call changes there,
+// e.g. an array it pushes to, is seen by everything else holding them, and the scopes
+// live on after the function that created them returns. Taking a function value copies
+// no bindings. Scopes are behind Arc and Mutex so Env stays Send + Sync.
+
+type Scope = Arc<Mutex<HashMap<String, Value>>>;
+
+fn new_scope() -> Scope {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+fn lock(scope: &Scope) -> MutexGuard<'_, HashMap<String, Value>> {
+    // A binding is written whole, so a scope is usable after a panic elsewhere
+    scope.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
 
+/// The scopes visible where a function value was taken, outermost first
+/// Cloning shares the scopes.
 #[derive(Debug, Clone)]
+pub struct Captured(Vec<Scope>);
+
+impl PartialEq for Captured {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+#[derive(Debug)]
 pub struct Env {
-    scopes: Vec<HashMap<String, Value>>,
+    scopes: Vec<Scope>,
+    // Scopes of the code that entered captured scopes, innermost last (see enter_captured)
+    suspended: Vec<Vec<Scope>>,
 
     // --- MEMOIZATION STATE ---
     // Stack of memoization enabled/disabled states
@@ -35,7 +71,12 @@ pub struct Env {
     // --- MEMOIZATION CACHE ---
     // Function call result cache
     // Only populated when memoization_enabled() is true
-    memoization_cache: HashMap<MemoKey, Value>,
+    memoization_cache: MemoCache<Value>,
+
+    // --- LANGUAGE STATE ---
+    // What the running language keeps for this run (e.g. its function table)
+    // The kernel only carries it, so separate runs never share it.
+    language_state: Option<Box<dyn Any + Send + Sync>>,
 }
 
 impl Env {
@@ -43,12 +84,34 @@ impl Env {
     /// Memoization is disabled by default (MEMOIZATION = false).
     pub fn new() -> Self {
         Self {
-            scopes: vec![HashMap::new()],
+            scopes: vec![new_scope()],
+            suspended: Vec::new(),
             memoization_stack: vec![false],  // Default: MEMOIZATION = false
-            memoization_cache: HashMap::new(),
+            memoization_cache: MemoCache::new(),
+            language_state: None,
         }
     }
 
+    /// The running language's state for this run, created with `init` on first use
+    /// A language keeps one type of state; asking for another type replaces it.
+    pub fn language_state<T: Any + Send + Sync>(&mut self, init: impl FnOnce() -> T) -> &mut T {
+        if !self.language_state.as_ref().is_some_and(|state| state.is::<T>()) {
+            self.language_state = Some(Box::new(init()));
+        }
+        self.language_state
+            .as_mut()
+            .and_then(|state| state.downcast_mut::<T>())
+            .expect("language state was just set")
+    }
+
+    /// Estimated bytes held by the values bound in every scope (for --max-heap)
+    pub fn heap_bytes(&self) -> usize {
+        self.scopes
+            .iter()
+            .map(|scope| lock(scope).iter().map(|(name, value)| name.len() + value.heap_bytes()).sum::<usize>())
+            .sum()
+    }
+
     /// Check if memoization is currently enabled.
     pub fn memoization_enabled(&self) -> bool {
         self.memoization_stack.last().copied().unwrap_or(false)
@@ -79,15 +142,13 @@ impl Env {
 
     /// Enter a new lexical scope.
     /// Also preserves and manages memoization state for dynamic scoping.
-    #[allow(dead_code)]
     pub fn push_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+        self.scopes.push(new_scope());
         self.push_memoization_state();
     }
 
     /// Exit the current lexical scope.
     /// Also restores memoization state when exiting.
-    #[allow(dead_code)]
     pub fn pop_scope(&mut self) {
         if self.scopes.len() == 1 {
             // Global scope must always exist.
@@ -97,18 +158,30 @@ impl Env {
         self.pop_memoization_state();
     }
 
-    /// Push a scope with RAII guard that guarantees cleanup.
-    /// The guard automatically pops the scope when dropped.
-    pub fn push_scope_guarded(&mut self) -> ScopeGuard {
-        self.push_scope();
-        ScopeGuard { env: self as *mut Env }
+    /// The scopes visible here, shared for a function value to run in later
+    pub fn capture(&self) -> Captured {
+        Captured(self.scopes.clone())
+    }
+
+    /// Run in `captured` instead of the current scopes, until leave_captured
+    /// Used to call a function value: the call's own scope goes on top of them.
+    pub fn enter_captured(&mut self, captured: &Captured) {
+        let current = std::mem::replace(&mut self.scopes, captured.0.clone());
+        self.suspended.push(current);
+    }
+
+    /// Return to the scopes that were current before enter_captured
+    pub fn leave_captured(&mut self) {
+        if let Some(scopes) = self.suspended.pop() {
+            self.scopes = scopes;
+        }
     }
 
     /// Define a new variable in the current scope.
     /// This shadows any outer binding with the same name.
     pub fn define(&mut self, name: String, value: Value) {
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, value);
+        if let Some(scope) = self.scopes.last() {
+            lock(scope).insert(name, value);
         }
     }
 
@@ -118,8 +191,8 @@ impl Env {
     /// This matches the Microcode kernel's scoping behavior.
     pub fn assign(&mut self, name: &str, value: Value) -> Result<(), String> {
         // Always set in current scope, don't search parent scopes
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name.to_string(), value);
+        if let Some(scope) = self.scopes.last() {
+            lock(scope).insert(name.to_string(), value);
         }
         Ok(())
     }
@@ -128,34 +201,49 @@ impl Env {
     /// Prefer assign() or define() in client code.
     #[allow(dead_code)]
     pub fn set(&mut self, name: String, value: Value) {
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, value);
+        if let Some(scope) = self.scopes.last() {
+            lock(scope).insert(name, value);
         }
     }
 
     /// Retrieve a variable value.
     pub fn get(&self, name: &str) -> Result<Value, String> {
         for scope in self.scopes.iter().rev() {
-            if let Some(v) = scope.get(name) {
+            if let Some(v) = lock(scope).get(name) {
                 return Ok(v.clone());
             }
         }
         Err(format!("Undefined variable '{}'", name))
     }
 
+    /// All visible bindings; an inner binding hides an outer one with the same name.
+    /// Used by diagnostics (--trace=vars).
+    pub fn visible_bindings(&self) -> Vec<(String, Value)> {
+        let mut seen = std::collections::HashSet::new();
+        let mut bindings = Vec::new();
+        for scope in self.scopes.iter().rev() {
+            for (name, value) in lock(scope).iter() {
+                if seen.insert(name.clone()) {
+                    bindings.push((name.clone(), value.clone()));
+                }
+            }
+        }
+        bindings
+    }
+
     // --- MEMOIZATION CACHE METHODS ---
     // Cache operations are gated by memoization_enabled() state.
 
     /// Check if a result is cached for this function call.
     /// Returns Some(value) only if memoization is enabled AND result is cached.
     /// Only computes fingerprint if memoization is enabled (performance optimization).
-    pub fn get_cached(&self, func_name: &str, args: &[Value]) -> Option<Value> {
+    /// Counts it as a hit or a miss (see memo_stats).
+    pub fn get_cached(&mut self, func_name: &str, args: &[Value]) -> Option<Value> {
         if !self.memoization_enabled() {
             return None;
         }
         let arg_fingerprint = Self::fingerprint_args(args);
-        let key = (func_name.to_string(), arg_fingerprint);
-        self.memoization_cache.get(&key).cloned()
+        self.memoization_cache.get(func_name, arg_fingerprint)
     }
 
     /// Cache the result of a function call.
@@ -166,8 +254,13 @@ impl Env {
             return;
         }
         let arg_fingerprint = Self::fingerprint_args(args);
-        let key = (func_name.to_string(), arg_fingerprint);
-        self.memoization_cache.insert(key, result);
+        self.memoization_cache.insert(func_name, arg_fingerprint, result);
+    }
+
+    /// The memoization cache itself, for its statistics and controls
+    /// (memo_stats, memo_clear and memo_capacity).
+    pub fn memoization_cache(&mut self) -> &mut MemoCache<Value> {
+        &mut self.memoization_cache
     }
 
     /// Generate a stable fingerprint from argument values.
@@ -183,8 +276,8 @@ impl Env {
     /// Searches for the array in any scope and mutates it in place.
     pub fn mutate_array(&mut self, name: &str, index: usize, value: Value) -> Result<(), String> {
         // Find and mutate in reverse scope order (innermost first)
-        for scope in self.scopes.iter_mut().rev() {
-            if let Some(arr_val) = scope.get_mut(name) {
+        for scope in self.scopes.iter().rev() {
+            if let Some(arr_val) = lock(scope).get_mut(name) {
                 // Downcast to LumenArray and mutate
                 // We need to access the raw pointer to mutate
                 // Since Value is Box<dyn RuntimeValue>, we can't directly downcast and mutate
@@ -208,8 +301,8 @@ impl Env {
     /// Searches for the array in any scope and appends to it in place.
     pub fn push_array(&mut self, name: &str, value: Value) -> Result<(), String> {
         // Find and mutate in reverse scope order (innermost first)
-        for scope in self.scopes.iter_mut().rev() {
-            if let Some(arr_val) = scope.get_mut(name) {
+        for scope in self.scopes.iter().rev() {
+            if let Some(arr_val) = lock(scope).get_mut(name) {
                 // Downcast to LumenArray and push
                 use std::any::Any;
                 if let Some(arr) = arr_val.as_any_mut().downcast_mut::<crate::languages::lumen::values::LumenArray>() {
@@ -229,24 +322,32 @@ impl Default for Env {
     }
 }
 
-// ============================================================================
-// RAII SCOPE GUARD
-// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::lumen::values::{LumenArray, LumenNumber};
 
-/// RAII guard that guarantees scope cleanup even on early returns.
-/// Automatically pops the scope when dropped (goes out of scope).
-pub struct ScopeGuard {
-    env: *mut Env,
-}
+    #[test]
+    fn captured_scopes_are_shared_after_they_are_left() {
+        let mut env = Env::new();
+        // A function's scope binds `seen`, and a function value is taken there
+        env.push_scope();
+        env.define("seen".to_string(), Box::new(LumenArray::empty()));
+        let captured = env.capture();
+        env.pop_scope();
+        assert!(env.get("seen").is_err());
 
-impl Drop for ScopeGuard {
-    fn drop(&mut self) {
-        // SAFETY: This pointer is valid because:
-        // 1. It comes from &mut Env which is guaranteed valid
-        // 2. The guard's lifetime is tied to the function that created it
-        // 3. Rust's borrow checker ensures env outlives the guard
-        unsafe {
-            (*self.env).pop_scope();
+        // Each call of the function value pushes to the `seen` it closes over
+        for n in 1..=2 {
+            env.enter_captured(&captured);
+            env.push_scope();
+            env.push_array("seen", Box::new(LumenNumber::new(n))).unwrap();
+            env.pop_scope();
+            env.leave_captured();
         }
+        assert!(env.get("seen").is_err());
+        env.enter_captured(&captured);
+        assert_eq!(env.get("seen").unwrap().as_display_string(), "[1, 2]");
+        env.leave_captured();
     }
 }
@@ -3,5 +3,5 @@
 pub mod env;
 pub mod value;
 
-pub use env::Env;
+pub use env::{Captured, Env};
 pub use value::{Value, RuntimeValue};
@@ -11,9 +11,12 @@
 // - Invoke language-defined operations on values
 // But the kernel never interprets what a value represents.
 
+use std::borrow::Cow;
 use std::fmt;
 use std::any::Any;
 
+use crate::kernel::repr::{self, Node};
+
 /// Trait for language-specific runtime values.
 /// Languages implement this for their concrete value types.
 /// The kernel treats all values opaquely via this trait.
@@ -39,6 +42,18 @@ pub trait RuntimeValue: Send + Sync {
     /// Support for mutable type-safe downcasting (for mutation).
     /// Returns a mutable reference for use with Any::downcast_mut.
     fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// This value as the renderer sees it (see lib_lumen/repr.rs).
+    /// Strings, arrays and maps should say so; anything else is written as it displays.
+    fn repr_node(&self) -> Node<'_> {
+        Node::Atom(Cow::Owned(self.as_display_string()))
+    }
+
+    /// Estimated bytes this value holds, for --max-heap.
+    /// Values that own heap data (text, digits, elements) should count it.
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
 }
 
 impl Clone for Box<dyn RuntimeValue> {
@@ -60,6 +75,13 @@ impl fmt::Debug for dyn RuntimeValue {
     }
 }
 
+impl dyn RuntimeValue {
+    /// What repr() returns: strings quoted, huge values shortened
+    pub fn repr(&self) -> String {
+        repr::render(&self.repr_node(), &repr::REPR)
+    }
+}
+
 impl fmt::Display for dyn RuntimeValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.as_display_string())
@@ -0,0 +1,116 @@
+// Break/continue/return handling for Lumen loops and function calls
+// The rules themselves are shared with the microcode kernel (lib_lumen/control_flow.rs,
+// included as crate::kernel::control_flow); this module maps the stream kernel's Control
+// onto them. Loop iterations and function calls are also where the execution limits
+// (--max-steps, --max-depth, --max-time, --max-heap) are checked (lib_lumen/limits.rs).
+
+use crate::kernel::ast::{Control, Step, StmtNode};
+use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::prelude::*;
+use crate::languages::lumen::state::State;
+use crate::languages::lumen::trace;
+
+pub use crate::kernel::control_flow::{at_function, at_loop, check_exit_status, LoopStep, Signal};
+
+/// Map a statement result onto the shared model
+pub fn signal_of(control: &Control) -> Signal {
+    match control {
+        Control::None | Control::ExprValue(_) => Signal::Normal,
+        Control::Break => Signal::Break,
+        Control::Continue => Signal::Continue,
+        Control::Return(_) => Signal::Return,
+    }
+}
+
+/// Continues a loop once one iteration of its body finished: with None to keep looping,
+/// or the Control the loop statement itself finishes with
+pub type AfterIteration<'a> = Box<dyn FnOnce(Option<Control>, &mut Env) -> LumenResult<Step<'a>> + 'a>;
+
+/// Run one iteration of a loop body on the work stack
+pub fn loop_body<'a>(body: &'a [Box<dyn StmtNode>], env: &mut Env, then: AfterIteration<'a>) -> LumenResult<Step<'a>> {
+    let depth = trace::LoopBody::enter(env);
+    State::of(env).usage.step()?;
+    check_heap(env)?;
+    loop_statements(body, 0, depth, then, env)
+}
+
+fn loop_statements<'a>(
+    body: &'a [Box<dyn StmtNode>],
+    index: usize,
+    depth: trace::LoopBody,
+    then: AfterIteration<'a>,
+    env: &mut Env,
+) -> LumenResult<Step<'a>> {
+    let Some(stmt) = body.get(index) else {
+        drop(depth);
+        return then(None, env);
+    };
+    Ok(Step::exec(stmt.as_ref(), move |control, env| {
+        let signal = signal_of(&control);
+        if signal == Signal::Normal {
+            return loop_statements(body, index + 1, depth, then, env);
+        }
+        drop(depth);
+        match at_loop(signal) {
+            LoopStep::Next => then(None, env),
+            LoopStep::Exit => then(Some(Control::None), env),
+            LoopStep::Propagate => then(Some(control), env),
+        }
+    }))
+}
+
+/// Run a function body on the work stack; the step finishes with the call's value
+/// The value is the explicit return value, else the last expression statement, else null.
+pub fn function_body<'a>(body: &'a [Box<dyn StmtNode>], env: &mut Env) -> LumenResult<Step<'a>> {
+    State::of(env).usage.enter_call()?;
+    check_heap(env)?;
+    let null = Box::new(crate::languages::lumen::values::LumenNull) as Value;
+    Ok(function_statements(body, 0, null).then(|outcome, env| {
+        State::of(env).usage.leave_call();
+        outcome.map(Step::Done)
+    }))
+}
+
+/// Measure the variables against --max-heap when a check is due
+fn check_heap(env: &mut Env) -> LumenResult<()> {
+    if State::of(env).usage.heap_check_due() {
+        let bytes = env.heap_bytes();
+        State::of(env).usage.check_heap(bytes)?;
+    }
+    Ok(())
+}
+
+fn function_statements<'a>(body: &'a [Box<dyn StmtNode>], index: usize, result: Value) -> Step<'a> {
+    let Some(stmt) = body.get(index) else {
+        return Step::value(result);
+    };
+    Step::exec(stmt.as_ref(), move |control, _env| {
+        at_function(signal_of(&control))?;
+        Ok(match control {
+            Control::ExprValue(val) => function_statements(body, index + 1, val),
+            Control::Return(val) => Step::value(val),
+            _ => function_statements(body, index + 1, result),
+        })
+    })
+}
+
+/// Run an if/else block on the work stack
+/// The block finishes with the first break, continue or return, else with the value of
+/// its last expression statement.
+pub fn block<'a>(body: &'a [Box<dyn StmtNode>]) -> Step<'a> {
+    block_statements(body, 0, Control::None)
+}
+
+fn block_statements<'a>(body: &'a [Box<dyn StmtNode>], index: usize, result: Control) -> Step<'a> {
+    let Some(stmt) = body.get(index) else {
+        return Step::control(result);
+    };
+    Step::exec(stmt.as_ref(), move |control, _env| {
+        Ok(match control {
+            Control::None => block_statements(body, index + 1, result),
+            Control::ExprValue(val) => block_statements(body, index + 1, Control::ExprValue(val)),
+            // Break/Continue/Return control flow
+            other => Step::control(other),
+        })
+    })
+}
@@ -0,0 +1,203 @@
+// Statement and branch coverage for --coverage / --lcov
+//
+// When coverage is enabled on the Registry before parsing, every statement of the user
+// program is wrapped in a CountedStmt that counts how often it runs, and each `if`
+// records which arm it took. Counts go to the run's State::coverage, shared with the
+// binary that reports them. Prelude statements are never counted.
+//
+// Reports:
+//   listing  the source with an execution count per statement line
+//            (##### marks a statement that never ran) and a summary line
+//   lcov     DA/BRDA records in lcov tracefile format, for genhtml and CI tools
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::kernel::ast::{Control, Step, StmtNode};
+use crate::kernel::runtime::Env;
+use crate::languages::lumen::prelude::*;
+use crate::languages::lumen::registry::Registry;
+use crate::languages::lumen::state::State;
+
+/// Execution counts for one program, keyed by user source line
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Lines before this belong to the prelude
+    first_line: usize,
+    /// Statement line -> times executed
+    lines: BTreeMap<usize, u64>,
+    /// `if` line -> [then taken, else taken]
+    branches: BTreeMap<usize, [u64; 2]>,
+}
+
+/// Where a program's counts go: its Registry while it is parsed, its State while it runs,
+/// and the binary reading them once it stopped
+pub type CoverageSink = Arc<Mutex<Coverage>>;
+
+fn lock(sink: &CoverageSink) -> MutexGuard<'_, Coverage> {
+    sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Start recording for statements parsed from now on with `registry`
+/// `first_line` is the line where the user program starts in the text handed to the lexer.
+/// The run counts into the returned sink once it is its State::coverage.
+pub fn enable(registry: &mut Registry, first_line: usize) -> CoverageSink {
+    let sink = Arc::new(Mutex::new(Coverage { first_line, ..Coverage::default() }));
+    registry.set_coverage(Arc::clone(&sink));
+    sink
+}
+
+/// Wrap a parsed statement so its executions are counted, or return it unchanged
+pub fn wrap(registry: &Registry, stmt: Box<dyn StmtNode>, line: usize) -> Box<dyn StmtNode> {
+    let user_line = registry.coverage().and_then(|sink| {
+        let mut coverage = lock(sink);
+        let line = coverage.user_line(line)?;
+        coverage.lines.entry(line).or_insert(0);
+        Some(line)
+    });
+    match user_line {
+        Some(line) => Box::new(CountedStmt { inner: stmt, line }),
+        None => stmt,
+    }
+}
+
+/// Register an `if` at `line`; returns the line to pass to `record_branch`, if recording
+pub fn branch_site(registry: &Registry, line: usize) -> Option<usize> {
+    let mut coverage = lock(registry.coverage()?);
+    let line = coverage.user_line(line)?;
+    coverage.branches.entry(line).or_insert([0, 0]);
+    Some(line)
+}
+
+/// Record which arm of the `if` at `line` ran
+pub fn record_branch(env: &mut Env, line: usize, then_taken: bool) {
+    if let Some(sink) = &State::of(env).coverage {
+        if let Some(counts) = lock(sink).branches.get_mut(&line) {
+            counts[if then_taken { 0 } else { 1 }] += 1;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CountedStmt {
+    inner: Box<dyn StmtNode>,
+    line: usize,
+}
+
+impl StmtNode for CountedStmt {
+    fn exec(&self, env: &mut Env) -> LumenResult<Control> {
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        if let Some(sink) = &State::of(env).coverage {
+            if let Some(count) = lock(sink).lines.get_mut(&self.line) {
+                *count += 1;
+            }
+        }
+        self.inner.step(env)
+    }
+}
+
+impl Coverage {
+    fn user_line(&self, line: usize) -> Option<usize> {
+        (line >= self.first_line).then(|| line - self.first_line + 1)
+    }
+
+    /// One-line totals: statements and branch arms that ran at least once
+    pub fn summary(&self) -> String {
+        let hit_lines = self.lines.values().filter(|&&n| n > 0).count();
+        let arms = self.branches.len() * 2;
+        let hit_arms = self.branches.values().flatten().filter(|&&n| n > 0).count();
+        format!(
+            "coverage: {}/{} statements ({}), {}/{} branches ({})",
+            hit_lines,
+            self.lines.len(),
+            percent(hit_lines, self.lines.len()),
+            hit_arms,
+            arms,
+            percent(hit_arms, arms)
+        )
+    }
+
+    /// The source annotated with per-line execution counts
+    pub fn listing(&self, source: &str) -> String {
+        let mut out = String::new();
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let count = match self.lines.get(&line) {
+                Some(0) => "#####".to_string(),
+                Some(n) => n.to_string(),
+                None => String::new(),
+            };
+            let _ = write!(out, "{:>7} {:>4} | {}", count, line, text);
+            if let Some([then_taken, else_taken]) = self.branches.get(&line) {
+                let _ = write!(out, "    [then {}, else {}]", then_taken, else_taken);
+            }
+            out.push('\n');
+        }
+        out.push_str(&self.summary());
+        out.push('\n');
+        out
+    }
+
+    /// lcov tracefile for one source file
+    pub fn lcov(&self, source_name: &str) -> String {
+        let mut out = format!("TN:\nSF:{}\n", source_name);
+        for (line, counts) in &self.branches {
+            let reached = self.lines.get(line).is_some_and(|&n| n > 0);
+            for (arm, count) in counts.iter().enumerate() {
+                let taken = if reached { count.to_string() } else { "-".to_string() };
+                let _ = writeln!(out, "BRDA:{},0,{},{}", line, arm, taken);
+            }
+        }
+        let hit_arms = self.branches.values().flatten().filter(|&&n| n > 0).count();
+        let _ = writeln!(out, "BRF:{}\nBRH:{}", self.branches.len() * 2, hit_arms);
+        for (line, count) in &self.lines {
+            let _ = writeln!(out, "DA:{},{}", line, count);
+        }
+        let hit_lines = self.lines.values().filter(|&&n| n > 0).count();
+        let _ = writeln!(out, "LF:{}\nLH:{}\nend_of_record", self.lines.len(), hit_lines);
+        out
+    }
+}
+
+fn percent(hit: usize, total: usize) -> String {
+    if total == 0 {
+        return "n/a".to_string();
+    }
+    format!("{:.1}%", hit as f64 * 100.0 / total as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Coverage {
+        Coverage {
+            first_line: 1,
+            lines: BTreeMap::from([(1, 1), (2, 1), (3, 0)]),
+            branches: BTreeMap::from([(2, [0, 1])]),
+        }
+    }
+
+    #[test]
+    fn listing_marks_unexecuted_statements() {
+        let listing = sample().listing("x = 1\nif x < 0\n    print(x)\n");
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[1], "      1    2 | if x < 0    [then 0, else 1]");
+        assert_eq!(lines[2], "  #####    3 |     print(x)");
+        assert_eq!(lines[3], "coverage: 2/3 statements (66.7%), 1/2 branches (50.0%)");
+    }
+
+    #[test]
+    fn lcov_records_lines_and_branches() {
+        let lcov = sample().lcov("prog.lm");
+        assert_eq!(
+            lcov,
+            "TN:\nSF:prog.lm\nBRDA:2,0,0,0\nBRDA:2,0,1,1\nBRF:2\nBRH:1\n\
+             DA:1,1\nDA:2,1\nDA:3,0\nLF:3\nLH:2\nend_of_record\n"
+        );
+    }
+}
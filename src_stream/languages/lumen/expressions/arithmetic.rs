@@ -4,14 +4,16 @@ use crate::languages::lumen::prelude::*;
 // + - * / % // ** and unary minus
 // Supports integers, rationals, and real values (exact rational arithmetic + real precision)
 
-use crate::kernel::ast::ExprNode;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::registry::LumenResult;
 use crate::languages::lumen::registry::{ExprInfix, ExprPrefix, Precedence, Registry};
 use crate::kernel::runtime::{Env, Value};
-use crate::languages::lumen::numeric;
-use crate::languages::lumen::values::{LumenNumber, LumenRational, LumenReal, as_number, as_rational, as_real};
+use crate::languages::lumen::inline_cache::OperandCache;
+use crate::languages::lumen::interval::{self, Radius};
+use crate::languages::lumen::numeric::{self, bigfloat};
+use crate::languages::lumen::values::{LumenNumber, LumenRational, LumenReal, as_exact, as_number, as_rational, as_real};
 use num_bigint::BigInt;
 use num_traits::ToPrimitive;
 
@@ -22,11 +24,20 @@ struct UnaryMinusExpr {
 
 impl ExprNode for UnaryMinusExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        let val = self.expr.eval(env)?;
+        crate::kernel::eval::run_expr(self, env)
+    }
 
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.expr.as_ref(), move |val, _env| Ok(Step::value(self.apply(val)?))))
+    }
+}
+
+impl UnaryMinusExpr {
+    /// Negate the evaluated operand
+    fn apply(&self, val: Value) -> LumenResult<Value> {
         // Handle real negation
         if let Ok(real) = as_real(val.as_ref()) {
-            return Ok(Box::new(LumenReal::new(-real.numerator.clone(), real.denominator.clone(), real.precision)));
+            return Ok(Box::new(LumenReal::with_error(-real.numerator.clone(), real.denominator.clone(), real.precision, real.radius.clone())));
         }
 
         // Handle rational negation
@@ -36,8 +47,7 @@ impl ExprNode for UnaryMinusExpr {
 
         // Handle integer negation
         let num = as_number(val.as_ref())?;
-        let result = numeric::negate(&num.value)?;
-        Ok(Box::new(LumenNumber::new(result)))
+        Ok(Box::new(LumenNumber::new(num.value.neg())))
     }
 }
 
@@ -48,6 +58,10 @@ impl ExprPrefix for UnaryMinusPrefix {
         parser.peek().lexeme == "-"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["-"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance(); // '-'
         parser.skip_tokens();
@@ -56,17 +70,58 @@ impl ExprPrefix for UnaryMinusPrefix {
     }
 }
 
-#[derive(Debug)]
 struct ArithmeticExpr {
     left: Box<dyn ExprNode>,
     op: String,
     right: Box<dyn ExprNode>,
+    /// Whether this site's operands have all been integers so far
+    operands: OperandCache,
+}
+
+impl std::fmt::Debug for ArithmeticExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArithmeticExpr")
+            .field("left", &self.left)
+            .field("op", &self.op)
+            .field("right", &self.right)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ExprNode for ArithmeticExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        let l = self.left.eval(env)?;
-        let r = self.right.eval(env)?;
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.left.as_ref(), move |l, _env| {
+            Ok(Step::eval(self.right.as_ref(), move |r, _env| Ok(Step::value(self.apply(l, r)?))))
+        }))
+    }
+}
+
+impl ArithmeticExpr {
+    /// Apply the operator to the evaluated operands
+    fn apply(&self, l: Value, r: Value) -> LumenResult<Value> {
+        // Integers at a site that has only seen integers: no other representation to try
+        if self.operands.integers(|| as_number(l.as_ref()).is_ok() && as_number(r.as_ref()).is_ok()) {
+            let (a, b) = (&as_number(l.as_ref())?.value, &as_number(r.as_ref())?.value);
+            let result = match self.op.as_str() {
+                "+" => a.add(b),
+                "-" => a.sub(b),
+                "*" => a.mul(b),
+                "%" if b.is_zero() => return Err("Modulo by zero".into()),
+                "%" => a.rem(b),
+                "//" if b.is_zero() => return Err("Division by zero".into()),
+                "//" => a.div(b),
+                "**" => {
+                    let exponent = b.to_i64().and_then(|e| u32::try_from(e).ok());
+                    a.pow(exponent.ok_or_else(|| "Exponent too large".to_string())?)
+                }
+                _ => return Err("Invalid arithmetic operator".into()),
+            };
+            return Ok(Box::new(LumenNumber::new(result)));
+        }
 
         // Special handling for . operator: string concatenation with coercion
         if self.op == "." {
@@ -102,6 +157,19 @@ impl ExprNode for ArithmeticExpr {
             (None, false)
         };
         let result_is_real = left_is_real || right_is_real;
+        // Under --intervals, the operands' error bounds (see interval.rs)
+        let left_radius = as_real(l.as_ref()).ok().and_then(|real| real.radius.clone());
+        let right_radius = as_real(r.as_ref()).ok().and_then(|real| real.radius.clone());
+        let tracked = left_radius.is_some() || right_radius.is_some();
+        let zero = Radius::zero();
+
+        // + - * / with inf or nan, or a REAL divided by zero (see lib_lumen/bigfloat.rs)
+        if let (true, Some(a), Some(b)) = (result_is_real, as_exact(l.as_ref()), as_exact(r.as_ref())) {
+            if let Some((num, den)) = bigfloat::special_arithmetic(&self.op, (&a.0, &a.1), (&b.0, &b.1)) {
+                let precision = left_real_prec.or(right_real_prec).unwrap_or(15);
+                return Ok(Box::new(LumenReal::new(num, den, precision)));
+            }
+        }
 
         // Fast path for modulo and integer quotient (integer-only operations)
         // For Real values, extract the integer part and perform the operation
@@ -109,9 +177,9 @@ impl ExprNode for ArithmeticExpr {
         if self.op == "%" || self.op == "//" {
             // Extract integers directly by reference, then clone only if needed
             let result = if let Ok(real) = as_real(l.as_ref()) {
-                let left_int = &real.numerator / &real.denominator;
+                let left_int = real.integer_part()?;
                 if let Ok(real2) = as_real(r.as_ref()) {
-                    let right_int = &real2.numerator / &real2.denominator;
+                    let right_int = real2.integer_part()?;
                     if self.op == "//" {
                         if right_int == BigInt::from(0) {
                             return Err("Division by zero".into());
@@ -122,12 +190,12 @@ impl ExprNode for ArithmeticExpr {
                     }
                 } else if let Ok(num) = as_number(r.as_ref()) {
                     if self.op == "//" {
-                        if num.value == BigInt::from(0) {
+                        if num.value.is_zero() {
                             return Err("Division by zero".into());
                         }
-                        &left_int / &num.value
+                        &left_int / &*num.value.big()
                     } else {
-                        numeric::modulo(&left_int, &num.value)?
+                        numeric::modulo(&left_int, &num.value.big())?
                     }
                 } else if let Ok(rat) = as_rational(r.as_ref()) {
                     if self.op == "//" {
@@ -142,9 +210,11 @@ impl ExprNode for ArithmeticExpr {
                     return Err("Right operand must be a number".into());
                 }
             } else if let Ok(num) = as_number(l.as_ref()) {
-                let left_ref = &num.value;
+                let left_big = num.value.big();
+                let left_ref = &*left_big;
                 if let Ok(num2) = as_number(r.as_ref()) {
-                    let right_ref = &num2.value;
+                    let right_big = num2.value.big();
+                    let right_ref = &*right_big;
                     if self.op == "%" {
                         numeric::modulo(left_ref, right_ref)?
                     } else {
@@ -169,7 +239,8 @@ impl ExprNode for ArithmeticExpr {
                 // For modulo/quotient with rationals, extract integer part first (numerator / denominator)
                 let left_int = &rat.numerator / &rat.denominator;
                 if let Ok(num) = as_number(r.as_ref()) {
-                    let right_ref = &num.value;
+                    let right_big = num.value.big();
+                    let right_ref = &*right_big;
                     if self.op == "%" {
                         numeric::modulo(&left_int, right_ref)?
                     } else {
@@ -201,7 +272,8 @@ impl ExprNode for ArithmeticExpr {
 
             // If result involves Real, return as LumenReal; otherwise as LumenNumber
             if result_is_real {
-                return Ok(Box::new(LumenReal::new(result, BigInt::from(1), result_precision)));
+                let error = tracked.then(Radius::zero);
+                return Ok(Box::new(LumenReal::with_error(result, BigInt::from(1), result_precision, error)));
             } else {
                 return Ok(Box::new(LumenNumber::new(result)));
             }
@@ -209,24 +281,17 @@ impl ExprNode for ArithmeticExpr {
 
         // Handle exponentiation separately (base can be any numeric type, exponent must be integer)
         if self.op == "**" {
-            // Extract base as rational (supports integer, rational, and real)
-            let base_num = if let Ok(real) = as_real(l.as_ref()) {
-                LumenRational::new(real.numerator.clone(), real.denominator.clone())
-            } else if let Ok(rat) = as_rational(l.as_ref()) {
-                rat.clone()
-            } else if let Ok(num) = as_number(l.as_ref()) {
-                LumenRational::new(num.value.clone(), BigInt::from(1))
-            } else {
-                return Err("Left operand must be a number".into());
-            };
+            // Extract base as (numerator, denominator) (supports integer, rational, and real)
+            let (base_numerator, base_denominator) = as_exact(l.as_ref())
+                .ok_or_else(|| "Left operand must be a number".to_string())?;
 
             // Extract exponent as integer (truncate rational/real to integer)
             let exp_int = if let Ok(num) = as_number(r.as_ref()) {
-                num.value.clone()
+                num.value.big().into_owned()
             } else if let Ok(rat) = as_rational(r.as_ref()) {
                 &rat.numerator / &rat.denominator
             } else if let Ok(real) = as_real(r.as_ref()) {
-                &real.numerator / &real.denominator
+                real.integer_part()?
             } else {
                 return Err("Right operand must be a number".into());
             };
@@ -234,18 +299,26 @@ impl ExprNode for ArithmeticExpr {
             // Convert exponent to u32 for pow operation
             let exp_u32 = exp_int.to_u32()
                 .ok_or_else(|| "Exponent too large".to_string())?;
+            // Determine result precision for real operations
+            let result_precision = left_real_prec.or(right_real_prec).unwrap_or(15);
+
+            // inf and nan bases, and powers past the REAL range (see lib_lumen/bigfloat.rs)
+            if let (true, Some((num, den))) = (result_is_real, bigfloat::special_power((&base_numerator, &base_denominator), exp_u32)) {
+                return Ok(Box::new(LumenReal::new(num, den, result_precision)));
+            }
+            let base_num = LumenRational::new(base_numerator, base_denominator);
+            let error = tracked.then(|| {
+                interval::power((&base_num.numerator, &base_num.denominator), left_radius.as_ref().unwrap_or(&zero), exp_u32)
+            });
 
             // Compute base^exp for rational: (a/b)^n = a^n / b^n
             let result_num = base_num.numerator.pow(exp_u32);
             let result_denom = base_num.denominator.pow(exp_u32);
             let result_rational = LumenRational::new(result_num, result_denom);
 
-            // Determine result precision for real operations
-            let result_precision = left_real_prec.or(right_real_prec).unwrap_or(15);
-
             // If result involves Real, return as LumenReal; otherwise check if rational or integer
             if result_is_real {
-                return Ok(Box::new(LumenReal::new(result_rational.numerator, result_rational.denominator, result_precision)));
+                return Ok(Box::new(LumenReal::with_error(result_rational.numerator, result_rational.denominator, result_precision, error)));
             } else if result_rational.denominator == BigInt::from(1) {
                 // Result is an integer
                 return Ok(Box::new(LumenNumber::new(result_rational.numerator)));
@@ -261,7 +334,7 @@ impl ExprNode for ArithmeticExpr {
         } else if let Ok(rat) = as_rational(l.as_ref()) {
             (rat.clone(), true)
         } else if let Ok(num) = as_number(l.as_ref()) {
-            let rat = LumenRational::new(num.value.clone(), BigInt::from(1));
+            let rat = LumenRational::new(num.value.big().into_owned(), BigInt::from(1));
             (rat, false)
         } else {
             return Err("Left operand must be a number".into());
@@ -272,7 +345,7 @@ impl ExprNode for ArithmeticExpr {
         } else if let Ok(rat) = as_rational(r.as_ref()) {
             (rat.clone(), true)
         } else if let Ok(num) = as_number(r.as_ref()) {
-            let rat = LumenRational::new(num.value.clone(), BigInt::from(1));
+            let rat = LumenRational::new(num.value.big().into_owned(), BigInt::from(1));
             (rat, false)
         } else {
             return Err("Right operand must be a number".into());
@@ -283,6 +356,20 @@ impl ExprNode for ArithmeticExpr {
         // Check if either operand is rational (when not real)
         let result_is_rational = !result_is_real && (left_is_rat || right_is_rat);
 
+        if self.op == "/" && right_num.numerator == BigInt::from(0) {
+            return Err("Division by zero".into());
+        }
+        let error = match tracked {
+            true => Some(interval::propagate(
+                &self.op,
+                (&left_num.numerator, &left_num.denominator),
+                left_radius.as_ref().unwrap_or(&zero),
+                (&right_num.numerator, &right_num.denominator),
+                right_radius.as_ref().unwrap_or(&zero),
+            )?),
+            false => None,
+        };
+
         let result = match self.op.as_str() {
             "+" => {
                 // a/b + c/d = (ad + bc) / bd
@@ -304,9 +391,6 @@ impl ExprNode for ArithmeticExpr {
             }
             "/" => {
                 // a/b ÷ c/d = (ad) / (bc)
-                if right_num.numerator == BigInt::from(0) {
-                    return Err("Division by zero".into());
-                }
                 let num = left_num.numerator * &right_num.denominator;
                 let denom = left_num.denominator * right_num.numerator;
                 LumenRational::new(num, denom)
@@ -316,7 +400,7 @@ impl ExprNode for ArithmeticExpr {
 
         // If result involves Real, return as LumenReal
         if result_is_real {
-            Ok(Box::new(LumenReal::new(result.numerator, result.denominator, result_precision)))
+            Ok(Box::new(LumenReal::with_error(result.numerator, result.denominator, result_precision, error)))
         }
         // If result is an integer (denominator = 1), return as LumenNumber
         else if result.is_integer() {
@@ -345,6 +429,10 @@ impl ExprInfix for ArithmeticInfix {
         parser.peek().lexeme == self.op
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![&self.op]
+    }
+
     fn precedence(&self) -> Precedence {
         self.prec
     }
@@ -353,7 +441,12 @@ impl ExprInfix for ArithmeticInfix {
         parser.advance(); // consume operator
         parser.skip_tokens();
         let right = parser.parse_expr_prec(registry, self.precedence() + 1)?;
-        Ok(Box::new(ArithmeticExpr { left, op: self.op.clone(), right }))
+        // / gives rationals and . strings, so only the others have an integer path
+        let operands = match self.op.as_str() {
+            "/" | "." => OperandCache::general(),
+            _ => OperandCache::new(),
+        };
+        Ok(Box::new(ArithmeticExpr { left, op: self.op.clone(), right, operands }))
     }
 }
 
@@ -1,12 +1,13 @@
 use crate::languages::lumen::prelude::*;
-// Array indexing expression: arr[i]
+// Array indexing expression: arr[i] (also a map's m["key"] and the byte b[i] of bytes)
 
-use crate::kernel::ast::ExprNode;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::languages::lumen::structure::structural::LBRACKET;
 use crate::kernel::runtime::{Env, Value};
-use crate::languages::lumen::values::as_array;
+use crate::languages::lumen::values::{as_array, Integer, LumenBytes, LumenMap, LumenNumber, LumenString};
+use num_traits::ToPrimitive;
 
 #[derive(Debug)]
 pub struct ArrayIndex {
@@ -16,39 +17,61 @@ pub struct ArrayIndex {
 
 impl ExprNode for ArrayIndex {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        let array_val = self.array_expr.eval(env)?;
-        let index_val = self.index_expr.eval(env)?;
+        crate::kernel::eval::run_expr(self, env)
+    }
 
-        // Get the array
-        let arr = as_array(array_val.as_ref())?;
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.array_expr.as_ref(), move |array_val, _env| {
+            Ok(Step::eval(self.index_expr.as_ref(), move |index_val, _env| Ok(Step::value(self.apply(array_val, index_val)?))))
+        }))
+    }
+}
 
-        // Get the index as an integer
-        let index_bigint = crate::languages::lumen::values::as_number(index_val.as_ref())?;
-        let (sign, digits) = index_bigint.value.to_u32_digits();
+impl ArrayIndex {
+    /// Index the evaluated array (or map, or bytes)
+    fn apply(&self, array_val: Value, index_val: Value) -> LumenResult<Value> {
+        // Map lookup: the index is a string key
+        if let Some(map) = array_val.as_any().downcast_ref::<LumenMap>() {
+            let key = index_val.as_any()
+                .downcast_ref::<LumenString>()
+                .ok_or_else(|| "Map key must be a string".to_string())?;
+            return map.get(&key.value)
+                .map(|v| v.clone_boxed())
+                .ok_or_else(|| format!("Map has no key '{}'", key.value).into());
+        }
 
-        // Check for negative index
-        use num_bigint::Sign;
-        if sign == Sign::Minus {
-            return Err("Array index cannot be negative".to_string());
+        // A byte, as an INTEGER from 0 to 255
+        if let Some(bytes) = array_val.as_any().downcast_ref::<LumenBytes>() {
+            let idx = position(&index_val)?;
+            return match bytes.value.get(idx) {
+                Some(&byte) => Ok(Box::new(LumenNumber::new(i64::from(byte)))),
+                None => Err(format!("Bytes index {} out of bounds (length: {})", idx, bytes.value.len()).into()),
+            };
         }
 
-        // Get the index value (0 if digits is empty, otherwise digits[0])
-        let idx = if digits.is_empty() {
-            0usize
-        } else if digits.len() == 1 {
-            digits[0] as usize
-        } else {
-            return Err("Array index out of bounds".to_string());
-        };
+        // Get the array
+        let arr = as_array(array_val.as_ref())?;
+        let idx = position(&index_val)?;
 
         if idx >= arr.elements.len() {
-            return Err(format!("Array index out of bounds"));
+            return Err(format!("Array index out of bounds").into());
         }
 
         Ok(arr.elements[idx].clone_boxed())
     }
 }
 
+/// The index as a position, which must be a non-negative integer
+fn position(index_val: &Value) -> LumenResult<usize> {
+    let index = crate::languages::lumen::values::as_number(index_val.as_ref())?;
+
+    // Check for negative index
+    if index.value < Integer::Small(0) {
+        return Err("Array index cannot be negative".into());
+    }
+    index.value.to_usize().ok_or_else(|| "Array index out of bounds".into())
+}
+
 pub struct ArrayIndexInfix;
 
 impl ExprInfix for ArrayIndexInfix {
@@ -56,6 +79,10 @@ impl ExprInfix for ArrayIndexInfix {
         parser.peek().lexeme == LBRACKET
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![LBRACKET]
+    }
+
     fn parse(
         &self,
         parser: &mut Parser,
@@ -1,7 +1,7 @@
 use crate::languages::lumen::prelude::*;
 // Array literals: [ ... ]
 
-use crate::kernel::ast::ExprNode;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::languages::lumen::structure::structural::{LBRACKET, RBRACKET};
@@ -15,11 +15,11 @@ pub struct ArrayLiteral {
 
 impl ExprNode for ArrayLiteral {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        let mut values = Vec::new();
-        for elem in &self.elements {
-            values.push(elem.eval(env)?);
-        }
-        Ok(Box::new(LumenArray::new(values)))
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval_all(&self.elements, |values, _env| Ok(Step::value(Box::new(LumenArray::new(values))))))
     }
 }
 
@@ -30,6 +30,10 @@ impl ExprPrefix for ArrayLiteralPrefix {
         parser.peek().lexeme == LBRACKET
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![LBRACKET]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance(); // consume '['
         parser.skip_tokens();
@@ -56,7 +60,7 @@ impl ExprPrefix for ArrayLiteralPrefix {
                 return Err(format!(
                     "Expected ',' or ']' in array literal, got '{}'",
                     parser.peek().lexeme
-                ));
+                ).into());
             }
         }
 
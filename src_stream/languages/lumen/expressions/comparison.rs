@@ -1,26 +1,80 @@
 use crate::languages::lumen::prelude::*;
 // Comparison operators: == != < > <= >=
+// Membership operator: x in container
 
-use crate::kernel::ast::ExprNode;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::registry::LumenResult;
 use crate::kernel::runtime::{Env, Value};
 use crate::languages::lumen::registry::{ExprInfix, Precedence, Registry};
-use crate::languages::lumen::numeric;
-use crate::languages::lumen::values::{as_number, as_string, as_rational, as_real, LumenBool, LumenRational};
+use crate::languages::lumen::inline_cache::OperandCache;
+use crate::languages::lumen::numeric::bigfloat;
+use crate::languages::lumen::values::{as_exact, as_number, as_string, as_rational, as_real, Integer, LumenBool, LumenNull, LumenRational};
+use crate::languages::lumen::warnings;
 
-#[derive(Debug)]
 struct ComparisonExpr {
     left: Box<dyn ExprNode>,
     op: String,
     right: Box<dyn ExprNode>,
+    /// Position of the operator, while warnings are collected
+    warning_site: Option<(usize, usize)>,
+    /// Whether this site's operands have all been integers so far
+    operands: OperandCache,
+}
+
+impl std::fmt::Debug for ComparisonExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComparisonExpr")
+            .field("left", &self.left)
+            .field("op", &self.op)
+            .field("right", &self.right)
+            .field("warning_site", &self.warning_site)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ExprNode for ComparisonExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        let l = self.left.eval(env)?;
-        let r = self.right.eval(env)?;
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.left.as_ref(), move |l, _env| {
+            Ok(Step::eval(self.right.as_ref(), move |r, env| Ok(Step::value(self.apply(l, r, env)?))))
+        }))
+    }
+}
+
+impl ComparisonExpr {
+    /// Compare two integers
+    fn apply_integers(&self, a: &Integer, b: &Integer) -> LumenResult<Value> {
+        let result = match self.op.as_str() {
+            "==" => a == b,
+            "!=" => a != b,
+            "<" => a < b,
+            ">" => a > b,
+            "<=" => a <= b,
+            ">=" => a >= b,
+            _ => return Err("Invalid comparison operator".into()),
+        };
+        Ok(Box::new(LumenBool::new(result)))
+    }
+
+    /// Compare the evaluated operands
+    fn apply(&self, l: Value, r: Value, env: &mut Env) -> LumenResult<Value> {
+        // Integers at a site that has only seen integers: no rational to build first
+        if self.operands.integers(|| as_number(l.as_ref()).is_ok() && as_number(r.as_ref()).is_ok()) {
+            return self.apply_integers(&as_number(l.as_ref())?.value, &as_number(r.as_ref())?.value);
+        }
+
+        // inf and nan (see lib_lumen/bigfloat.rs): nan is unordered, even with itself
+        let special = |v: &Value| as_real(v.as_ref()).is_ok_and(|real| real.is_special());
+        if let (true, Some(a), Some(b)) = (special(&l) || special(&r), as_exact(l.as_ref()), as_exact(r.as_ref())) {
+            let result = bigfloat::comparison(&self.op, (&a.0, &a.1), (&b.0, &b.1))
+                .ok_or_else(|| "Invalid comparison operator".to_string())?;
+            return Ok(Box::new(LumenBool::new(result)));
+        }
 
         // Check if either operand is Real and convert to Rational-like for comparison
         let (l_rat_opt, r_rat_opt) = (
@@ -71,7 +125,7 @@ impl ExprNode for ComparisonExpr {
         // Try rational/real vs integer (convert integer to rational first)
         let left_rat_maybe = l_rat_opt.clone();
         if let (Some(left_rat), Ok(right_num)) = (left_rat_maybe, as_number(r.as_ref())) {
-            let right_rat = LumenRational::new(right_num.value.clone(), num_bigint::BigInt::from(1));
+            let right_rat = LumenRational::new(right_num.value.big().into_owned(), num_bigint::BigInt::from(1));
             let result = match self.op.as_str() {
                 "==" => (&left_rat as &dyn crate::kernel::runtime::RuntimeValue).eq_value(&right_rat as &dyn crate::kernel::runtime::RuntimeValue).unwrap_or(false),
                 "!=" => !(&left_rat as &dyn crate::kernel::runtime::RuntimeValue).eq_value(&right_rat as &dyn crate::kernel::runtime::RuntimeValue).unwrap_or(false),
@@ -103,7 +157,7 @@ impl ExprNode for ComparisonExpr {
         // Try integer vs rational/real (convert integer to rational first)
         let right_rat_maybe = r_rat_opt.clone();
         if let (Ok(left_num), Some(right_rat)) = (as_number(l.as_ref()), right_rat_maybe) {
-            let left_rat = LumenRational::new(left_num.value.clone(), num_bigint::BigInt::from(1));
+            let left_rat = LumenRational::new(left_num.value.big().into_owned(), num_bigint::BigInt::from(1));
             let result = match self.op.as_str() {
                 "==" => (&left_rat as &dyn crate::kernel::runtime::RuntimeValue).eq_value(&right_rat as &dyn crate::kernel::runtime::RuntimeValue).unwrap_or(false),
                 "!=" => !(&left_rat as &dyn crate::kernel::runtime::RuntimeValue).eq_value(&right_rat as &dyn crate::kernel::runtime::RuntimeValue).unwrap_or(false),
@@ -134,28 +188,31 @@ impl ExprNode for ComparisonExpr {
 
         // Try numeric (integer-only) comparison
         if let (Ok(left_num), Ok(right_num)) = (as_number(l.as_ref()), as_number(r.as_ref())) {
-            let result = match self.op.as_str() {
-                "==" => left_num.value == right_num.value,
-                "!=" => left_num.value != right_num.value,
-                "<" => numeric::compare_lt(&left_num.value, &right_num.value)?,
-                ">" => numeric::compare_gt(&left_num.value, &right_num.value)?,
-                "<=" => numeric::compare_le(&left_num.value, &right_num.value)?,
-                ">=" => numeric::compare_ge(&left_num.value, &right_num.value)?,
-                _ => return Err("Invalid comparison operator".into()),
-            };
-            return Ok(Box::new(LumenBool::new(result)));
+            return self.apply_integers(&left_num.value, &right_num.value);
         }
 
-        // Try string comparison
+        // Try string comparison (ordering is lexicographic by Unicode code point)
         if let (Ok(left_str), Ok(right_str)) = (as_string(l.as_ref()), as_string(r.as_ref())) {
             let result = match self.op.as_str() {
                 "==" => left_str.value == right_str.value,
                 "!=" => left_str.value != right_str.value,
-                _ => return Err("String comparison only supports == and !=".into()),
+                "<" => left_str.value < right_str.value,
+                ">" => left_str.value > right_str.value,
+                "<=" => left_str.value <= right_str.value,
+                ">=" => left_str.value >= right_str.value,
+                _ => return Err("Invalid comparison operator".into()),
             };
             return Ok(Box::new(LumenBool::new(result)));
         }
 
+        // Values of kinds that are never equal (null is compared with anything on purpose)
+        if let (Some(site), Err(_)) = (self.warning_site, l.eq_value(r.as_ref())) {
+            let is_null = |v: &Value| v.as_any().downcast_ref::<LumenNull>().is_some();
+            if !is_null(&l) && !is_null(&r) && matches!(self.op.as_str(), "==" | "!=") {
+                warnings::incompatible_comparison(env, site, &l, &self.op, &r)?;
+            }
+        }
+
         // Handle equality comparisons for remaining types
         match self.op.as_str() {
             "==" => {
@@ -175,6 +232,89 @@ impl ExprNode for ComparisonExpr {
     }
 }
 
+/// Membership test: `item in container`
+/// - ARRAY: some element equals item
+/// - STRING: item is a substring
+/// - MAP: item is a key
+/// - range: item is an integer within [start, end)
+#[derive(Debug)]
+struct MembershipExpr {
+    item: Box<dyn ExprNode>,
+    container: Box<dyn ExprNode>,
+}
+
+impl ExprNode for MembershipExpr {
+    fn eval(&self, env: &mut Env) -> LumenResult<Value> {
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.item.as_ref(), move |item, _env| {
+            Ok(Step::eval(self.container.as_ref(), move |container, _env| Ok(Step::value(self.apply(item, container)?))))
+        }))
+    }
+}
+
+impl MembershipExpr {
+    /// Look for the evaluated item in the evaluated container
+    fn apply(&self, item: Value, container: Value) -> LumenResult<Value> {
+        use crate::languages::lumen::values::{LumenArray, LumenMap};
+        use crate::languages::lumen::expressions::range_expr::LumenRange;
+
+        let found = if let Some(arr) = container.as_any().downcast_ref::<LumenArray>() {
+            arr.elements.iter().any(|e| e.eq_value(item.as_ref()).unwrap_or(false))
+        } else if let Ok(haystack) = as_string(container.as_ref()) {
+            let needle = as_string(item.as_ref())
+                .map_err(|_| "'in' on a string requires a string on the left".to_string())?;
+            haystack.value.contains(needle.value.as_str())
+        } else if let Some(map) = container.as_any().downcast_ref::<LumenMap>() {
+            let key = as_string(item.as_ref())
+                .map_err(|_| "'in' on a map requires a string key on the left".to_string())?;
+            map.get(&key.value).is_some()
+        } else if let Some(range) = container.as_any().downcast_ref::<LumenRange>() {
+            match as_number(item.as_ref()) {
+                Ok(n) => {
+                    let n = n.value.big();
+                    range.start <= *n && *n < range.end
+                }
+                Err(_) => false,
+            }
+        } else {
+            return Err("'in' requires an array, string, map, or range on the right".into());
+        };
+
+        Ok(Box::new(LumenBool::new(found)))
+    }
+}
+
+pub struct MembershipInfix;
+
+impl ExprInfix for MembershipInfix {
+    fn matches(&self, parser: &Parser) -> bool {
+        parser.peek().lexeme == "in"
+    }
+
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["in"]
+    }
+
+    fn precedence(&self) -> Precedence {
+        Precedence::Comparison
+    }
+
+    fn parse(
+        &self,
+        parser: &mut Parser,
+        left: Box<dyn ExprNode>,
+        registry: &super::super::registry::Registry,
+    ) -> LumenResult<Box<dyn ExprNode>> {
+        parser.advance(); // consume 'in'
+        parser.skip_tokens();
+        let container = parser.parse_expr_prec(registry, self.precedence() + 1)?;
+        Ok(Box::new(MembershipExpr { item: left, container }))
+    }
+}
+
 pub struct ComparisonInfix {
     op: String,
 }
@@ -190,6 +330,10 @@ impl ExprInfix for ComparisonInfix {
         parser.peek().lexeme == self.op
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![&self.op]
+    }
+
     fn precedence(&self) -> Precedence {
         Precedence::Comparison
     }
@@ -200,10 +344,12 @@ impl ExprInfix for ComparisonInfix {
         left: Box<dyn ExprNode>,
         registry: &super::super::registry::Registry,
     ) -> LumenResult<Box<dyn ExprNode>> {
+        let (line, col) = parser.position();
         parser.advance(); // consume operator
         parser.skip_tokens();
         let right = parser.parse_expr_prec(registry, self.precedence() + 1)?;
-        Ok(Box::new(ComparisonExpr { left, op: self.op.clone(), right }))
+        let warning_site = warnings::comparison_site(registry, line, col);
+        Ok(Box::new(ComparisonExpr { left, op: self.op.clone(), right, warning_site, operands: OperandCache::new() }))
     }
 }
 
@@ -230,4 +376,5 @@ pub fn register(reg: &mut Registry) {
     reg.register_infix(Box::new(ComparisonInfix::new(">")));
     reg.register_infix(Box::new(ComparisonInfix::new("<=")));
     reg.register_infix(Box::new(ComparisonInfix::new(">=")));
+    reg.register_infix(Box::new(MembershipInfix));
 }
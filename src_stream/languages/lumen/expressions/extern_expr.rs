@@ -6,12 +6,15 @@ use crate::languages::lumen::prelude::*;
 // Extern marks the boundary where Lumen's semantic guarantees stop.
 // It is deliberately uncomfortable, making the impurity explicit.
 
-use crate::kernel::ast::ExprNode;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::{Env, Value};
 use crate::languages::lumen::structure::structural::{LPAREN, RPAREN};
 use crate::languages::lumen::extern_system;
+use crate::languages::lumen::state::State;
+use crate::languages::lumen::statements::functions;
+use crate::languages::lumen::values::LumenArray;
 
 #[derive(Debug)]
 struct ExternExpr {
@@ -21,14 +24,22 @@ struct ExternExpr {
 
 impl ExprNode for ExternExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
         // Evaluate all arguments
-        let mut eval_args = Vec::new();
-        for arg in &self.args {
-            eval_args.push(arg.eval(env)?);
-        }
+        Ok(Step::eval_all(&self.args, move |eval_args, env| {
+            // A mock registered by a test shadows the real capability;
+            // its handler receives the arguments as a single array
+            if let Some(handler) = extern_system::resolve_mock(&State::of(env).capabilities, &self.selector)? {
+                let args_array: Value = Box::new(LumenArray::new(eval_args));
+                return functions::call_function(&handler, vec![args_array], env);
+            }
 
-        // Call the extern function
-        extern_system::call_extern(&self.selector, eval_args)
+            // Call the extern function (or take its result from the cache_extern cache)
+            Ok(Step::value(extern_system::call_cached(env, &self.selector, eval_args)?))
+        }))
     }
 }
 
@@ -95,7 +106,7 @@ impl ExprPrefix for ExternPrefix {
         }
 
         // Consume opening quote
-        let mut selector_lexeme = parser.advance().lexeme;
+        let mut selector_lexeme = parser.advance().lexeme.into_owned();
 
         // Since the kernel lexer is agnostic, assemble the full string from individual characters
         loop {
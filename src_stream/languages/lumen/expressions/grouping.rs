@@ -13,6 +13,10 @@ impl ExprPrefix for GroupingPrefix {
         parser.peek().lexeme == LPAREN
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![LPAREN]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance(); // consume '('
         parser.skip_tokens();
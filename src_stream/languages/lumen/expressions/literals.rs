@@ -6,7 +6,9 @@ use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::{Env, Value};
 use crate::languages::lumen::values::{LumenNumber, LumenBool, LumenString, LumenNull, LumenRational, LumenReal};
+use crate::languages::lumen::interval::Radius;
 use crate::languages::lumen::numeric;
+use crate::languages::lumen::state::State;
 use num_bigint::BigInt;
 
 #[derive(Debug)]
@@ -15,7 +17,7 @@ pub struct NumberLiteral {
 }
 
 impl ExprNode for NumberLiteral {
-    fn eval(&self, _env: &mut Env) -> LumenResult<Value> {
+    fn eval(&self, env: &mut Env) -> LumenResult<Value> {
         let (numerator, denominator) = numeric::parse_number_rational(&self.value)?;
 
         // If denominator is 1, it's an integer - use Number
@@ -25,7 +27,9 @@ impl ExprNode for NumberLiteral {
             // Float literal with decimal places - create Real value
             // Precision is determined by significant figures in the literal
             let precision = calculate_precision(&self.value);
-            Ok(Box::new(LumenReal::new(numerator, denominator, precision)))
+            // Under --intervals the literal is exact, up to what rounding to its precision loses
+            let error = State::of(env).intervals.then(Radius::zero);
+            Ok(Box::new(LumenReal::with_error(numerator, denominator, precision, error)))
         }
     }
 }
@@ -56,7 +60,7 @@ impl ExprPrefix for NumberLiteralPrefix {
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         // Consume the first digit
-        let mut value = parser.advance().lexeme;
+        let mut value = parser.advance().lexeme.into_owned();
 
         // Since the kernel lexer is fully agnostic, it emits each digit as a separate token.
         // We need to consume consecutive digit tokens to build the full number.
@@ -189,7 +193,7 @@ impl ExprPrefix for BoolLiteralPrefix {
             Ok(Box::new(BoolLiteral { value: false }))
         } else {
             // Not a boolean literal, this is an error
-            Err(format!("Expected 'true' or 'false', got '{}'", matched_keyword))
+            Err(format!("Expected 'true' or 'false', got '{}'", matched_keyword).into())
         }
     }
 }
@@ -204,18 +208,71 @@ struct StringLiteral {
 
 impl ExprNode for StringLiteral {
     fn eval(&self, _env: &mut Env) -> LumenResult<Value> {
+        Ok(Box::new(LumenString::new(self.unescaped())))
+    }
+}
+
+impl StringLiteral {
+    /// Literal text with quotes removed and escapes processed
+    fn unescaped(&self) -> String {
         // Remove quotes from the tokenized string: "hello" -> hello or 'hello' -> hello
         let content = &self.value[1..self.value.len() - 1];
 
-        let unescaped = if self.is_single_quoted {
+        if self.is_single_quoted {
             // Single-quoted strings: only process \' and \\ escapes
             process_single_quote_escapes(content)
         } else {
             // Double-quoted strings: process all standard escapes
             process_double_quote_escapes(content)
-        };
-        Ok(Box::new(LumenString::new(unescaped)))
+        }
+    }
+}
+
+/// Consume a string literal at the parser position and return its value.
+/// Lets statements read a literal at parse time (e.g. function docstrings).
+pub fn parse_string_literal_value(parser: &mut Parser) -> LumenResult<String> {
+    let is_single_quoted = parser.peek().lexeme == "'";
+    let quote = if is_single_quoted { "'" } else { "\"" };
+    let value = scan_quoted(parser, quote)?;
+    Ok(StringLiteral { value, is_single_quoted }.unescaped())
+}
+
+/// Consume a quoted string token sequence and return it with its quotes.
+fn scan_quoted(parser: &mut Parser, quote: &str) -> LumenResult<String> {
+    // Consume opening quote
+    let mut value = parser.advance().lexeme.into_owned();
+
+    // Since the kernel lexer is agnostic, it emits each character separately.
+    // Assemble the full string by consuming characters until closing quote (unescaped).
+    loop {
+        let ch = parser.peek().lexeme.clone();
+
+        // Check for backslash (escape character)
+        if ch == "\\" {
+            value.push_str(&parser.advance().lexeme);
+            // Consume the next character as escaped
+            if parser.i < parser.toks.len() {
+                value.push_str(&parser.advance().lexeme);
+            }
+            continue;
+        }
+
+        // Check for closing quote
+        if ch == quote {
+            value.push_str(&parser.advance().lexeme);
+            break;
+        }
+
+        // Add character to string (including whitespace, newlines, etc.)
+        value.push_str(&parser.advance().lexeme);
+
+        // Protect against unterminated strings
+        if parser.i >= parser.toks.len() {
+            return Err("Unterminated string literal".into());
+        }
     }
+
+    Ok(value)
 }
 
 /// Process escape sequences in single-quoted strings: \' and \\
@@ -296,40 +353,12 @@ impl ExprPrefix for StringLiteralPrefix {
         parser.peek().lexeme == "\""
     }
 
-    fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
-        // Consume opening quote
-        let mut value = parser.advance().lexeme;
-
-        // Since the kernel lexer is agnostic, it emits each character separately.
-        // Assemble the full string by consuming characters until closing quote (unescaped).
-        loop {
-            let ch = parser.peek().lexeme.clone();
-
-            // Check for backslash (escape character)
-            if ch == "\\" {
-                value.push_str(&parser.advance().lexeme);
-                // Consume the next character as escaped
-                if parser.i < parser.toks.len() {
-                    value.push_str(&parser.advance().lexeme);
-                }
-                continue;
-            }
-
-            // Check for closing quote
-            if ch == "\"" {
-                value.push_str(&parser.advance().lexeme);
-                break;
-            }
-
-            // Add character to string (including whitespace, newlines, etc.)
-            value.push_str(&parser.advance().lexeme);
-
-            // Protect against unterminated strings
-            if parser.i >= parser.toks.len() {
-                return Err("Unterminated string literal".into());
-            }
-        }
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["\""]
+    }
 
+    fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
+        let value = scan_quoted(parser, "\"")?;
         Ok(Box::new(StringLiteral { value, is_single_quoted: false }))
     }
 }
@@ -344,40 +373,12 @@ impl ExprPrefix for SingleQuotedStringPrefix {
         parser.peek().lexeme == "'"
     }
 
-    fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
-        // Consume opening quote
-        let mut value = parser.advance().lexeme;
-
-        // Since the kernel lexer is agnostic, it emits each character separately.
-        // Assemble the full string by consuming characters until closing quote (unescaped).
-        loop {
-            let ch = parser.peek().lexeme.clone();
-
-            // Check for backslash (escape character)
-            if ch == "\\" {
-                value.push_str(&parser.advance().lexeme);
-                // Consume the next character as escaped
-                if parser.i < parser.toks.len() {
-                    value.push_str(&parser.advance().lexeme);
-                }
-                continue;
-            }
-
-            // Check for closing quote
-            if ch == "'" {
-                value.push_str(&parser.advance().lexeme);
-                break;
-            }
-
-            // Add character to string (including whitespace, newlines, etc.)
-            value.push_str(&parser.advance().lexeme);
-
-            // Protect against unterminated strings
-            if parser.i >= parser.toks.len() {
-                return Err("Unterminated string literal".into());
-            }
-        }
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["'"]
+    }
 
+    fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
+        let value = scan_quoted(parser, "'")?;
         Ok(Box::new(StringLiteral { value, is_single_quoted: true }))
     }
 }
@@ -400,6 +401,10 @@ impl ExprPrefix for NoneLiteralPrefix {
         parser.peek().lexeme == "null"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["null"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance(); // consume 'null'
         Ok(Box::new(NoneLiteral))
@@ -1,7 +1,7 @@
 use crate::languages::lumen::prelude::*;
 // Logical operators: and / or / not
 
-use crate::kernel::ast::ExprNode;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::{Env, Value};
@@ -16,31 +16,34 @@ struct LogicExpr {
 
 impl ExprNode for LogicExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
         // Implement short-circuit evaluation
-        let l = self.left.eval(env)?;
-        let left_bool = as_bool(l.as_ref())?;
-
-        match self.op.as_str() {
-            "and" => {
-                // Short-circuit: if left is false, don't evaluate right
-                if !left_bool.value {
-                    return Ok(Box::new(LumenBool::new(false)));
+        Ok(Step::eval(self.left.as_ref(), move |l, _env| {
+            let left_bool = as_bool(l.as_ref())?;
+
+            match self.op.as_str() {
+                "and" => {
+                    // Short-circuit: if left is false, don't evaluate right
+                    if !left_bool.value {
+                        return Ok(Step::value(Box::new(LumenBool::new(false))));
+                    }
                 }
-                let r = self.right.eval(env)?;
-                let right_bool = as_bool(r.as_ref())?;
-                Ok(Box::new(LumenBool::new(right_bool.value)))
-            }
-            "or" => {
-                // Short-circuit: if left is true, don't evaluate right
-                if left_bool.value {
-                    return Ok(Box::new(LumenBool::new(true)));
+                "or" => {
+                    // Short-circuit: if left is true, don't evaluate right
+                    if left_bool.value {
+                        return Ok(Step::value(Box::new(LumenBool::new(true))));
+                    }
                 }
-                let r = self.right.eval(env)?;
-                let right_bool = as_bool(r.as_ref())?;
-                Ok(Box::new(LumenBool::new(right_bool.value)))
+                _ => return Err(format!("Invalid logical operator: {}", self.op).into()),
             }
-            _ => Err(format!("Invalid logical operator: {}", self.op)),
-        }
+            Ok(Step::eval(self.right.as_ref(), |r, _env| {
+                let right_bool = as_bool(r.as_ref())?;
+                Ok(Step::value(Box::new(LumenBool::new(right_bool.value))))
+            }))
+        }))
     }
 }
 
@@ -134,7 +137,17 @@ struct NotExpr {
 
 impl ExprNode for NotExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        let val = self.expr.eval(env)?;
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.expr.as_ref(), move |val, _env| Ok(Step::value(self.apply(val)?))))
+    }
+}
+
+impl NotExpr {
+    /// Negate the evaluated operand
+    fn apply(&self, val: Value) -> LumenResult<Value> {
         let b = as_bool(val.as_ref())?;
         Ok(Box::new(LumenBool::new(!b.value)))
     }
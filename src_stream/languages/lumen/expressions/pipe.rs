@@ -1,10 +1,8 @@
 // Pipe operator expression: expr |> func(args)
 // Passes the left value as the first argument to the right function
 
-use std::rc::Rc;
-use std::cell::RefCell;
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::{ExprNode, StmtNode};
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::languages::lumen::registry::{ExprInfix, Precedence, Registry};
@@ -21,92 +19,51 @@ struct PipeExpr {
 
 impl ExprNode for PipeExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        // Evaluate the left side
-        let left_value = self.left.eval(env)?;
-
-        // Get function definition
-        let (params, body) = functions::get_function(&self.func_name)
-            .ok_or_else(|| format!("Undefined function '{}'", self.func_name))?;
-
-        // Evaluate other arguments
-        let mut arg_values = vec![left_value];
-        for arg in &self.args {
-            arg_values.push(arg.eval(env)?);
-        }
-
-        // Check argument count
-        if arg_values.len() != params.len() {
-            return Err(format!(
-                "Function '{}' expects {} arguments, got {}",
-                self.func_name,
-                params.len(),
-                arg_values.len()
-            ));
-        }
-
-        // ================================================================
-        // MEMOIZATION: Gated by execution context (MEMOIZATION = true/false)
-        // ================================================================
-        // Cache operations are gated by env.memoization_enabled().
-        // If MEMOIZATION = false (default): no cache lookup/storage
-        // If MEMOIZATION = true: check cache before execution, store after
-        //
-        // Performance: fingerprint only computed when memoization enabled
-        if let Some(cached_result) = env.get_cached(&self.func_name, &arg_values) {
-            return Ok(cached_result);
-        }
-
-        let result = self.execute_function(&params, &body, &arg_values, env)?;
-        env.cache_result(&self.func_name, &arg_values, result.clone());
-        Ok(result)
+        crate::kernel::eval::run_expr(self, env)
     }
-}
-
-impl PipeExpr {
-    /// Execute function body and return result.
-    fn execute_function(
-        &self,
-        params: &[String],
-        body: &Rc<RefCell<Vec<Box<dyn StmtNode>>>>,
-        arg_values: &[Value],
-        env: &mut Env,
-    ) -> LumenResult<Value> {
-        // Create new scope for function
-        env.push_scope();
-
-        // Bind parameters to arguments
-        for (param, arg_val) in params.iter().zip(arg_values) {
-            env.define(param.clone(), arg_val.clone());
-        }
 
-        // Execute function body
-        let mut result = Box::new(crate::languages::lumen::values::LumenNull) as Value;
-        {
-            let body_ref = body.borrow();
-            for stmt in body_ref.iter() {
-                let ctl = stmt.exec(env)?;
-                match ctl {
-                    crate::kernel::ast::Control::ExprValue(val) => {
-                        // Expression statement value - keep as result but continue
-                        result = val;
-                    }
-                    crate::kernel::ast::Control::Return(val) => {
-                        // Explicit return - set result and break
-                        result = val;
-                        break;
-                    }
-                    crate::kernel::ast::Control::Break | crate::kernel::ast::Control::Continue => {
-                        return Err("break/continue outside of loop".into());
-                    }
-                    crate::kernel::ast::Control::None => {}
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        // Evaluate the left side
+        Ok(Step::eval(self.left.as_ref(), move |left_value, env| {
+            // Get function definition
+            let (params, body) = functions::get_function(env, &self.func_name)
+                .ok_or_else(|| format!("Undefined function '{}'", self.func_name))?;
+
+            // Evaluate other arguments
+            Ok(Step::eval_all(&self.args, move |args, env| {
+                let mut arg_values = vec![left_value];
+                arg_values.extend(args);
+
+                // Check argument count
+                if arg_values.len() != params.len() {
+                    return Err(format!(
+                        "Function '{}' expects {} arguments, got {}",
+                        self.func_name,
+                        params.len(),
+                        arg_values.len()
+                    ).into());
                 }
-            }
-        }
 
-        // Exit function scope
-        env.pop_scope();
+                // ================================================================
+                // MEMOIZATION: Gated by execution context (MEMOIZATION = true/false)
+                // ================================================================
+                // Cache operations are gated by env.memoization_enabled().
+                // If MEMOIZATION = false (default): no cache lookup/storage
+                // If MEMOIZATION = true: check cache before execution, store after
+                //
+                // Performance: fingerprint only computed when memoization enabled
+                if let Some(cached_result) = env.get_cached(&self.func_name, &arg_values) {
+                    return Ok(Step::value(cached_result));
+                }
 
-        Ok(result)
+                let call = functions::call(&params, body, arg_values.clone(), env)?;
+                Ok(call.then(move |outcome, env| {
+                    let result = outcome?.into_value();
+                    env.cache_result(&self.func_name, &arg_values, result.clone());
+                    Ok(Step::value(result))
+                }))
+            }))
+        }))
     }
 }
 
@@ -117,6 +74,10 @@ impl ExprInfix for PipeInfix {
         parser.peek().lexeme == "|>"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["|>"]
+    }
+
     fn precedence(&self) -> Precedence {
         Precedence::Pipe
     }
@@ -128,7 +89,7 @@ impl ExprInfix for PipeInfix {
         // Parse function name
         let mut func_name = String::new();
         if parser.peek().lexeme.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_') {
-            func_name = parser.advance().lexeme;
+            func_name = parser.advance().lexeme.into_owned();
             parser.skip_tokens();
 
             // Handle multi-character identifiers
@@ -6,7 +6,7 @@
 // Returns a special value type that carries range metadata
 
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::ExprNode;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::kernel::runtime::{Env, Value, RuntimeValue};
 use crate::languages::lumen::patterns::PatternSet;
@@ -65,13 +65,23 @@ impl RuntimeValue for LumenRange {
 
 impl ExprNode for RangeExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        let start_val = self.start.eval(env)?;
-        let end_val = self.end.eval(env)?;
+        crate::kernel::eval::run_expr(self, env)
+    }
 
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.start.as_ref(), move |start_val, _env| {
+            Ok(Step::eval(self.end.as_ref(), move |end_val, _env| Ok(Step::value(self.apply(start_val, end_val)?))))
+        }))
+    }
+}
+
+impl RangeExpr {
+    /// Build the range from its evaluated bounds
+    fn apply(&self, start_val: Value, end_val: Value) -> LumenResult<Value> {
         let start_num = as_number(start_val.as_ref())?;
         let end_num = as_number(end_val.as_ref())?;
 
-        Ok(Box::new(LumenRange::new(start_num.value.clone(), end_num.value.clone())))
+        Ok(Box::new(LumenRange::new(start_num.value.big().into_owned(), end_num.value.big().into_owned())))
     }
 }
 
@@ -82,6 +92,10 @@ impl ExprInfix for RangeExprHandler {
         parser.peek().lexeme == ".."
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![".."]
+    }
+
     fn precedence(&self) -> Precedence {
         Precedence::Range
     }
@@ -106,7 +120,7 @@ impl ExprInfix for RangeExprHandler {
 pub fn as_range(val: &dyn RuntimeValue) -> LumenResult<&LumenRange> {
     val.as_any()
         .downcast_ref::<LumenRange>()
-        .ok_or_else(|| "Expected range value".to_string())
+        .ok_or_else(|| "Expected range value".into())
 }
 
 // --------------------
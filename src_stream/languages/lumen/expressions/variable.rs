@@ -3,14 +3,19 @@ use crate::languages::lumen::prelude::*;
 //
 // Variable reference expression: `x` or function call: `func(args)`
 
-use std::rc::Rc;
-use std::cell::RefCell;
 use num_bigint::BigInt;
-use crate::kernel::ast::{ExprNode, StmtNode};
+use num_traits::Zero;
+use crate::kernel::ast::{ExprNode, Step};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::extern_system::registry::CapabilityRegistry;
+use crate::languages::lumen::inline_cache::CallCache;
+use crate::languages::lumen::interval::{self, Radius};
+use crate::languages::lumen::state::State;
 use crate::languages::lumen::statements::functions;
+use crate::languages::lumen::values::LumenFunction;
+use crate::languages::lumen::warnings;
 use crate::languages::lumen::structure::structural::{LPAREN, RPAREN};
 
 #[derive(Debug)]
@@ -20,125 +25,55 @@ struct VarExpr {
 
 impl ExprNode for VarExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        env.get(&self.name)
+        // A name that is no variable may be a function's: the function as a value
+        env.get(&self.name).or_else(|e| match functions::get_function(env, &self.name) {
+            Some((params, _)) => {
+                let function = LumenFunction::new(self.name.clone(), params).closing_over(env.capture());
+                Ok(Box::new(function) as Value)
+            }
+            None => Err(e.into()),
+        })
     }
 }
 
-#[derive(Debug)]
 struct FunctionCallExpr {
     func_name: String,
     args: Vec<Box<dyn ExprNode>>,
+    /// A built-in primitive (see is_builtin), decided once when parsed
+    builtin: bool,
+    /// The user-defined function this site called last
+    cache: CallCache,
+}
+
+impl std::fmt::Debug for FunctionCallExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionCallExpr")
+            .field("func_name", &self.func_name)
+            .field("args", &self.args)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ExprNode for FunctionCallExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
+        crate::kernel::eval::run_expr(self, env)
+    }
+
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
         // First, check if this is a built-in primitive function
-        if self.args.len() == 1 {
-            match self.func_name.as_str() {
-                "emit" => {
-                    // emit(string) - kernel primitive for I/O
-                    return builtin_emit(&self.args[0].eval(env)?);
-                }
-                "real" => {
-                    // real(x): convert to real with default precision 15
-                    return builtin_real(&self.args[0].eval(env)?, 15);
-                }
-                "len" => {
-                    // len(x): return length of string or array
-                    return builtin_len(&self.args[0].eval(env)?);
-                }
-                "ord" => {
-                    // ord(s): return decimal integer value of first character
-                    return builtin_ord(&self.args[0].eval(env)?);
-                }
-                "chr" => {
-                    // chr(n): return single-character string for decimal integer
-                    return builtin_chr(&self.args[0].eval(env)?);
-                }
-                "error" => {
-                    // error(message): abort execution with error message
-                    return builtin_error(&self.args[0].eval(env)?);
-                }
-                "kind" => {
-                    // kind(x): return symbolic constant representing value category
-                    return builtin_kind(&self.args[0].eval(env)?);
+        if self.builtin {
+            return Ok(Step::eval_all(&self.args, move |arg_values, env| {
+                match self.func_name.as_str() {
+                    "extern_async" => return start_extern_async(arg_values, env),
+                    "extern_try" => return try_extern(arg_values, env),
+                    _ => {}
                 }
-                "num" => {
-                    // num(x): return numerator of rational (errors on non-rational)
-                    return builtin_num(&self.args[0].eval(env)?);
-                }
-                "den" => {
-                    // den(x): return denominator of rational (errors on non-rational)
-                    return builtin_den(&self.args[0].eval(env)?);
-                }
-                "int" => {
-                    // int(x): return integer part of real (errors on non-real)
-                    return builtin_int_part(&self.args[0].eval(env)?);
-                }
-                "frac" => {
-                    // frac(x): return fractional part of real (errors on non-real)
-                    return builtin_frac(&self.args[0].eval(env)?);
-                }
-                "int_to_string" => {
-                    // int_to_string(x): convert integer to string (mechanical primitive)
-                    return builtin_int_to_string(&self.args[0].eval(env)?);
-                }
-                "real_to_string" => {
-                    // real_to_string(x): convert real to string (mechanical primitive)
-                    return builtin_real_to_string(&self.args[0].eval(env)?);
-                }
-                "rational_to_string" => {
-                    // rational_to_string(x): convert rational to string (mechanical primitive)
-                    return builtin_rational_to_string(&self.args[0].eval(env)?);
-                }
-                "bool_to_string" => {
-                    // bool_to_string(x): convert boolean to string (mechanical primitive)
-                    return builtin_bool_to_string(&self.args[0].eval(env)?);
-                }
-                "array_to_string" => {
-                    // array_to_string(x): convert array to string (mechanical primitive)
-                    return builtin_array_to_string(&self.args[0].eval(env)?);
-                }
-                "null_to_string" => {
-                    // null_to_string(x): convert null to string (mechanical primitive)
-                    return builtin_null_to_string(&self.args[0].eval(env)?);
-                }
-                "kind_to_string" => {
-                    // kind_to_string(x): convert kind meta-value to string (mechanical primitive)
-                    return builtin_kind_to_string(&self.args[0].eval(env)?);
-                }
-                _ => {}
-            }
-        } else if self.args.len() == 2 {
-            match self.func_name.as_str() {
-                "real" => {
-                    // real(x, y): convert to real with precision y
-                    use crate::languages::lumen::values::LumenNumber;
-                    use num_traits::ToPrimitive;
-                    let x_val = self.args[0].eval(env)?;
-                    let y_val = self.args[1].eval(env)?;
-                    // Extract precision from y_val
-                    let precision = match y_val.as_any().downcast_ref::<LumenNumber>() {
-                        Some(num) => {
-                            num.value.to_u64()
-                                .ok_or_else(|| "Precision must be a positive integer".to_string())? as usize
-                        }
-                        None => return Err("Precision argument must be an integer".to_string()),
-                    };
-                    return builtin_real(&x_val, precision);
-                }
-                "char_at" => {
-                    // char_at(string, index): return character at index
-                    let str_val = self.args[0].eval(env)?;
-                    let idx_val = self.args[1].eval(env)?;
-                    return builtin_char_at(&str_val, &idx_val);
-                }
-                _ => {}
-            }
+                Ok(Step::value(apply_builtin(&self.func_name, &arg_values, env)?))
+            }));
         }
 
-        // Get user-defined function definition
-        let (params, body) = functions::get_function(&self.func_name)
+        // Get user-defined function definition (looked up once per run at this site)
+        let (params, body) = self.cache.resolve(env, &self.func_name)
             .ok_or_else(|| format!("Undefined function '{}'", self.func_name))?;
 
         // Check argument count
@@ -148,90 +83,207 @@ impl ExprNode for FunctionCallExpr {
                 self.func_name,
                 params.len(),
                 self.args.len()
-            ));
+            ).into());
         }
 
         // Evaluate arguments
-        let mut arg_values = Vec::new();
-        for arg in &self.args {
-            arg_values.push(arg.eval(env)?);
-        }
+        Ok(Step::eval_all(&self.args, move |arg_values, env| {
+            // ================================================================
+            // MEMOIZATION: Gated by execution context (MEMOIZATION = true/false)
+            // ================================================================
+            // Cache operations are gated by env.memoization_enabled().
+            // If MEMOIZATION = false (default): no cache lookup/storage
+            // If MEMOIZATION = true: check cache before execution, store after
+            //
+            // The memoization state is dynamically scoped:
+            // - Set by MEMOIZATION = true/false statements
+            // - Inherited by function calls
+            // - Automatically restored on scope exit
+            //
+            // Performance: fingerprint only computed when memoization enabled
+            if let Some(cached_result) = env.get_cached(&self.func_name, &arg_values) {
+                // Cache hit: return cached result without executing function
+                return Ok(Step::value(cached_result));
+            }
 
-        // ================================================================
-        // MEMOIZATION: Gated by execution context (MEMOIZATION = true/false)
-        // ================================================================
-        // Cache operations are gated by env.memoization_enabled().
-        // If MEMOIZATION = false (default): no cache lookup/storage
-        // If MEMOIZATION = true: check cache before execution, store after
-        //
-        // The memoization state is dynamically scoped:
-        // - Set by MEMOIZATION = true/false statements
-        // - Inherited by function calls
-        // - Automatically restored on scope exit
-        //
-        // Performance: fingerprint only computed when memoization enabled
-        if let Some(cached_result) = env.get_cached(&self.func_name, &arg_values) {
-            // Cache hit: return cached result without executing function
-            return Ok(cached_result);
-        }
+            // Execute function in its own scope (left on every exit: return, break, error)
+            let call = functions::call(&params, body, arg_values.clone(), env)?;
 
-        // Execute function (cache lookup may have returned early)
-        let result = self.execute_function(&params, &body, &arg_values, env)?;
+            // Cache result if memoization is enabled
+            Ok(call.then(move |outcome, env| {
+                let result = outcome?.into_value();
+                env.cache_result(&self.func_name, &arg_values, result.clone());
+                Ok(Step::value(result))
+            }))
+        }))
+    }
+}
 
-        // Cache result if memoization is enabled
-        env.cache_result(&self.func_name, &arg_values, result.clone());
+/// Whether `name` called with `arity` arguments is a built-in primitive function
+/// Any other call goes to a user-defined function.
+fn is_builtin(name: &str, arity: usize) -> bool {
+    if name == "extern_async" || name == "extern_try" {
+        return arity >= 1;
+    }
+    match arity {
+        0 => matches!(name, "push_mocks" | "pop_mocks" | "clear_extern_cache" | "memo_stats" | "memo_clear"),
+        1 => matches!(
+            name,
+            "emit" | "real" | "len" | "ord" | "chr" | "error" | "kind" | "num" | "den" | "int" | "frac"
+                | "int_to_string" | "real_to_string" | "rational_to_string" | "bool_to_string"
+                | "array_to_string" | "map_to_string" | "keys" | "null_to_string" | "kind_to_string"
+                | "extern_available" | "extern_await" | "cache_extern" | "clear_extern_cache" | "exp" | "ln" | "atan" | "memo_stats" | "memo_clear" | "memo_capacity"
+                | "repr" | "bytes" | "bytes_to_string" | "decode_utf8"
+        ),
+        2 => matches!(name, "real" | "char_at" | "exp" | "ln" | "atan" | "log" | "extern_timeout"),
+        3 => matches!(name, "log" | "format_number"),
+        _ => false,
+    }
+}
 
-        Ok(result)
+/// Apply a built-in primitive function (see is_builtin) to its evaluated arguments
+fn apply_builtin(name: &str, args: &[Value], env: &mut Env) -> LumenResult<Value> {
+    match (name, args) {
+        // emit(string) - kernel primitive for I/O
+        ("emit", [x]) => builtin_emit(x),
+        // real(x): convert to real with default precision 15
+        ("real", [x]) => builtin_real(x, 15, State::of(env).intervals),
+        // len(x): return length of string or array
+        ("len", [x]) => builtin_len(x),
+        // ord(s): return decimal integer value of first character
+        ("ord", [x]) => builtin_ord(x),
+        // chr(n): return single-character string for decimal integer
+        ("chr", [x]) => builtin_chr(x),
+        // error(message): abort execution with error message
+        ("error", [x]) => builtin_error(x),
+        // kind(x): return symbolic constant representing value category
+        ("kind", [x]) => builtin_kind(x),
+        // num(x): return numerator of rational (errors on non-rational)
+        ("num", [x]) => builtin_num(x),
+        // den(x): return denominator of rational (errors on non-rational)
+        ("den", [x]) => builtin_den(x),
+        // int(x): return integer part of real (errors on non-real)
+        ("int", [x]) => builtin_int_part(x),
+        // frac(x): return fractional part of real (errors on non-real)
+        ("frac", [x]) => builtin_frac(x),
+        // int_to_string(x): convert integer to string (mechanical primitive)
+        ("int_to_string", [x]) => builtin_int_to_string(x),
+        // real_to_string(x): convert real to string (mechanical primitive)
+        ("real_to_string", [x]) => builtin_real_to_string(x),
+        // rational_to_string(x): convert rational to string (mechanical primitive)
+        ("rational_to_string", [x]) => builtin_rational_to_string(x),
+        // bool_to_string(x): convert boolean to string (mechanical primitive)
+        ("bool_to_string", [x]) => builtin_bool_to_string(x),
+        // array_to_string(x): convert array to string (mechanical primitive)
+        ("array_to_string", [x]) => builtin_array_to_string(x),
+        // map_to_string(x): convert map to string (mechanical primitive)
+        ("map_to_string", [x]) => builtin_map_to_string(x),
+        // keys(m): array of a map's keys in insertion order
+        ("keys", [x]) => builtin_keys(x),
+        // bytes(x): BYTES from a string (its UTF-8), an array of integers 0 to 255, or bytes
+        ("bytes", [x]) => builtin_bytes(x),
+        // bytes_to_string(x): convert bytes to string (mechanical primitive)
+        ("bytes_to_string", [x]) => builtin_bytes_to_string(x),
+        // decode_utf8(b): the string whose UTF-8 the bytes are
+        ("decode_utf8", [x]) => builtin_decode_utf8(x),
+        // null_to_string(x): convert null to string (mechanical primitive)
+        ("null_to_string", [x]) => builtin_null_to_string(x),
+        // kind_to_string(x): convert kind meta-value to string (mechanical primitive)
+        ("kind_to_string", [x]) => builtin_kind_to_string(x),
+        // repr(x): x as debug_info and error messages show it (see lib_lumen/repr.rs)
+        ("repr", [x]) => Ok(Box::new(crate::languages::lumen::values::LumenString::new(x.repr()))),
+        // extern_available(selector): true if extern(selector, ...) would resolve
+        ("extern_available", [x]) => builtin_extern_available(x, &State::of(env).capabilities),
+        // extern_await(call): wait for an extern_async call and give its value
+        ("extern_await", [x]) => builtin_extern_await(x, env),
+        // exp(x), ln(x), atan(x): transcendental functions at default precision 15
+        ("exp", [x]) | ("ln", [x]) | ("atan", [x]) => builtin_transcendental(name, x, 15, State::of(env).intervals),
+        // real(x, y): convert to real with precision y
+        ("real", [x_val, y_val]) => {
+            use crate::languages::lumen::values::LumenNumber;
+            use num_traits::ToPrimitive;
+            // Extract precision from y_val
+            let precision = match y_val.as_any().downcast_ref::<LumenNumber>() {
+                Some(num) => {
+                    num.value.to_u64()
+                        .ok_or_else(|| "Precision must be a positive integer".to_string())? as usize
+                }
+                None => return Err("Precision argument must be an integer".into()),
+            };
+            builtin_real(x_val, precision, State::of(env).intervals)
+        }
+        // char_at(string, index): return character at index
+        ("char_at", [str_val, idx_val]) => builtin_char_at(str_val, idx_val),
+        // exp(x, p), ln(x, p), atan(x, p): transcendental functions with precision p
+        ("exp", [x_val, p]) | ("ln", [x_val, p]) | ("atan", [x_val, p]) => {
+            builtin_transcendental(name, x_val, precision_arg(p)?, State::of(env).intervals)
+        }
+        // log(x, base): logarithm in an arbitrary base at default precision 15
+        ("log", [x_val, base_val]) => builtin_log(x_val, base_val, 15, State::of(env).intervals),
+        // log(x, base, p): logarithm in an arbitrary base with precision p
+        ("log", [x_val, base_val, p]) => builtin_log(x_val, base_val, precision_arg(p)?, State::of(env).intervals),
+        // format_number(x, digits, grouping): x with fixed decimal places, digits grouped
+        ("format_number", [x, digits, grouping]) => builtin_format_number(x, digits, grouping),
+        // push_mocks(): start a new layer of extern mocks
+        ("push_mocks", []) => {
+            State::of(env).capabilities.push_mock_layer();
+            Ok(Box::new(crate::languages::lumen::values::LumenNull))
+        }
+        // pop_mocks(): discard the most recent layer of extern mocks
+        ("pop_mocks", []) => {
+            crate::languages::lumen::extern_system::pop_mock_layer(&mut State::of(env).capabilities)?;
+            Ok(Box::new(crate::languages::lumen::values::LumenNull))
+        }
+        // cache_extern(selector), clear_extern_cache([selector]): extern results kept for the run
+        ("cache_extern", _) | ("clear_extern_cache", _) => builtin_extern_cache(name, args.first(), env),
+        // extern_timeout(target, ms): how long the program's extern calls of target may take
+        ("extern_timeout", [target, millis]) => builtin_extern_timeout(target, millis, env),
+        // memo_stats([name]), memo_clear([name]), memo_capacity(n): the MEMOIZATION cache
+        ("memo_stats", _) | ("memo_clear", _) | ("memo_capacity", _) => builtin_memo(name, args.first(), env),
+        _ => unreachable!("{}() with {} arguments is not a builtin", name, args.len()),
     }
 }
 
-impl FunctionCallExpr {
-    /// Execute function body and return result.
-    /// This is factored out to be shared between cached and non-cached paths.
-    fn execute_function(
-        &self,
-        params: &[String],
-        body: &Rc<RefCell<Vec<Box<dyn StmtNode>>>>,
-        arg_values: &[Value],
-        env: &mut Env,
-    ) -> LumenResult<Value> {
-        // Create new scope for function with RAII guard
-        // The guard automatically pops the scope on ANY exit (return, break, error)
-        let _scope_guard = env.push_scope_guarded();
+/// doc(f): docstring of a user-defined function, or null if it has none
+/// Functions are not first-class values in this kernel, so `f` is resolved by name at runtime.
+#[derive(Debug)]
+struct DocExpr {
+    func_name: String,
+}
 
-        // Bind parameters to arguments
-        for (param, arg_val) in params.iter().zip(arg_values) {
-            env.define(param.clone(), arg_val.clone());
-        }
+impl ExprNode for DocExpr {
+    fn eval(&self, env: &mut Env) -> LumenResult<Value> {
+        use crate::languages::lumen::values::{LumenNull, LumenString};
 
-        // Execute function body
-        let mut result = Box::new(crate::languages::lumen::values::LumenNull) as Value;
-        {
-            let body_ref = body.borrow();
-            for stmt in body_ref.iter() {
-                let ctl = stmt.exec(env)?;
-                match ctl {
-                    crate::kernel::ast::Control::ExprValue(val) => {
-                        // Expression statement value - keep as result but continue
-                        result = val;
-                    }
-                    crate::kernel::ast::Control::Return(val) => {
-                        // Explicit return - set result and break
-                        result = val;
-                        break;
-                        // _scope_guard drops here, automatically calling env.pop_scope()
-                    }
-                    crate::kernel::ast::Control::Break | crate::kernel::ast::Control::Continue => {
-                        return Err("break/continue outside of loop".into());
-                        // _scope_guard drops here, automatically calling env.pop_scope()
-                    }
-                    crate::kernel::ast::Control::None => {}
-                }
-            }
+        match functions::get_doc(env, &self.func_name) {
+            Some(Some(doc)) => Ok(Box::new(LumenString::new(doc))),
+            Some(None) => Ok(Box::new(LumenNull)),
+            None => Err(format!("doc() requires a function, '{}' is not defined", self.func_name).into()),
         }
+    }
+}
+
+/// mock_extern(selector, f): shadow an extern capability with user function `f`
+/// `f` receives the extern arguments as one array; the mock lives in the current mock layer.
+#[derive(Debug)]
+struct MockExternExpr {
+    selector: Box<dyn ExprNode>,
+    handler: String,
+}
 
-        // _scope_guard drops here on normal exit, automatically calling env.pop_scope()
-        Ok(result)
+impl ExprNode for MockExternExpr {
+    fn eval(&self, env: &mut Env) -> LumenResult<Value> {
+        use crate::languages::lumen::values::{LumenNull, LumenString};
+
+        let selector_val = self.selector.eval(env)?;
+        let selector = selector_val.as_any()
+            .downcast_ref::<LumenString>()
+            .ok_or_else(|| "mock_extern() selector must be a string".to_string())?;
+        if functions::get_function(env, &self.handler).is_none() {
+            return Err(format!("mock_extern() requires a function, '{}' is not defined", self.handler).into());
+        }
+        crate::languages::lumen::extern_system::mock_extern(&mut State::of(env).capabilities, &selector.value, &self.handler)?;
+        Ok(Box::new(LumenNull))
     }
 }
 
@@ -246,14 +298,15 @@ impl ExprPrefix for VariablePrefix {
         let lex = &parser.peek().lexeme;
         let is_identifier = lex.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_');
         // Exclude statement keywords but allow builtin functions like emit, int, str
-        let is_statement_keyword = matches!(lex.as_str(),
+        let is_statement_keyword = matches!(lex.as_ref(),
             "if" | "else" | "while" | "break" | "continue" | "fn" | "let" | "mut" | "return");
         is_identifier && !is_statement_keyword
     }
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         // Consume the first character of the identifier
-        let mut name = parser.advance().lexeme;
+        let line = parser.position().0;
+        let mut name = parser.advance().lexeme.into_owned();
 
         // Since the kernel lexer is agnostic, multi-character identifiers are split into single chars
         // Continue consuming identifier characters
@@ -278,6 +331,14 @@ impl ExprPrefix for VariablePrefix {
             parser.advance(); // consume '('
             parser.skip_tokens();
 
+            // doc(f) names a function rather than evaluating an argument
+            if name == "doc" {
+                return parse_doc_target(parser);
+            }
+            if name == "mock_extern" {
+                return parse_mock_extern(parser, registry);
+            }
+
             let mut args = Vec::new();
 
             // Parse arguments
@@ -299,15 +360,62 @@ impl ExprPrefix for VariablePrefix {
             }
 
             return Ok(Box::new(FunctionCallExpr {
+                builtin: is_builtin(&name, args.len()),
                 func_name: name,
                 args,
+                cache: CallCache::default(),
             }));
         }
 
+        warnings::read(registry, &name, line);
         Ok(Box::new(VarExpr { name }))
     }
 }
 
+/// Parse the function name inside doc(...), after the opening '('
+fn parse_doc_target(parser: &mut Parser) -> LumenResult<Box<dyn ExprNode>> {
+    let func_name = parse_function_name(parser);
+    if func_name.is_empty() || parser.advance().lexeme != RPAREN {
+        return Err("doc() expects a single function name".into());
+    }
+    Ok(Box::new(DocExpr { func_name }))
+}
+
+/// Parse mock_extern(selector, f) after the opening '('
+/// The handler is resolved by name, like doc(f).
+fn parse_mock_extern(parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
+    let selector = parser.parse_expr(registry)?;
+    parser.skip_tokens();
+    if parser.advance().lexeme != "," {
+        return Err("mock_extern() expects a selector and a function name".into());
+    }
+    parser.skip_tokens();
+
+    let handler = parse_function_name(parser);
+    if handler.is_empty() || parser.advance().lexeme != RPAREN {
+        return Err("mock_extern() expects a selector and a function name".into());
+    }
+    Ok(Box::new(MockExternExpr { selector, handler }))
+}
+
+/// Consume an identifier naming a function, followed by any trailing whitespace
+fn parse_function_name(parser: &mut Parser) -> String {
+    let mut func_name = String::new();
+    loop {
+        let lex = &parser.peek().lexeme;
+        let is_ident_char = lex.len() == 1 && {
+            let ch = lex.as_bytes()[0];
+            ch.is_ascii_alphanumeric() || ch == b'_'
+        };
+        if !is_ident_char {
+            break;
+        }
+        func_name.push_str(&parser.advance().lexeme);
+    }
+    parser.skip_tokens();
+    func_name
+}
+
 // ============================================================================
 // BUILT-IN CONVERSION FUNCTIONS
 // ============================================================================
@@ -317,38 +425,44 @@ impl ExprPrefix for VariablePrefix {
 /// - Rational → real (stored as exact rational with precision hint for display)
 /// - Real → real (unchanged, or with new precision)
 /// Precision is in significant digits (default 15)
-fn builtin_real(value: &Value, precision: usize) -> LumenResult<Value> {
+fn builtin_real(value: &Value, precision: usize, intervals: bool) -> LumenResult<Value> {
     use crate::languages::lumen::values::{LumenNumber, LumenRational, LumenReal};
     use num_bigint::BigInt;
 
+    // Under --intervals the result carries an error bound: the input's, plus the rounding
+    let exact = intervals.then(Radius::zero);
+
     // If it's a Real, return with new precision
     if let Some(real) = value.as_any().downcast_ref::<LumenReal>() {
-        return Ok(Box::new(LumenReal::new(
+        return Ok(Box::new(LumenReal::with_error(
             real.numerator.clone(),
             real.denominator.clone(),
             precision,
+            real.radius.clone().or(exact),
         )));
     }
 
     // If it's a Rational, convert to Real with precision
     if let Some(rational) = value.as_any().downcast_ref::<LumenRational>() {
-        return Ok(Box::new(LumenReal::new(
+        return Ok(Box::new(LumenReal::with_error(
             rational.numerator.clone(),
             rational.denominator.clone(),
             precision,
+            exact,
         )));
     }
 
     // If it's a Number (integer), convert to Real
     if let Some(number) = value.as_any().downcast_ref::<LumenNumber>() {
-        return Ok(Box::new(LumenReal::new(
-            number.value.clone(),
+        return Ok(Box::new(LumenReal::with_error(
+            number.value.big().into_owned(),
             BigInt::from(1),
             precision,
+            exact,
         )));
     }
 
-    Err("real() requires a number, rational, or real argument".to_string())
+    Err("real() requires a number, rational, or real argument".into())
 }
 
 /// Built-in function: int_to_string(x) - Convert integer to string (mechanical primitive)
@@ -375,6 +489,25 @@ fn builtin_real_to_string(value: &Value) -> LumenResult<Value> {
     Ok(Box::new(LumenString::new(real.as_decimal_string())))
 }
 
+/// Built-in function: format_number(x, digits, grouping) - x with exactly `digits` decimal
+/// places, rounded half away from zero, its digits in space-separated groups of `grouping`
+/// counted from the point (0 for none). Accepts any numeric kind (see lib_lumen/bigfloat.rs).
+fn builtin_format_number(value: &Value, digits: &Value, grouping: &Value) -> LumenResult<Value> {
+    use crate::languages::lumen::numeric::bigfloat;
+    use crate::languages::lumen::values::{LumenNumber, LumenString};
+    use num_traits::ToPrimitive;
+
+    let (num, den) = numeric_operand(value, "format_number")?;
+    let count = |value: &Value, what: &str| {
+        value.as_any()
+            .downcast_ref::<LumenNumber>()
+            .and_then(|number| number.value.to_usize())
+            .ok_or_else(|| format!("format_number() {} must be a non-negative integer", what))
+    };
+    let text = bigfloat::fixed_string(&num, &den, count(digits, "digits")?, count(grouping, "grouping")?);
+    Ok(Box::new(LumenString::new(text)))
+}
+
 /// Built-in function: rational_to_string(x) - Convert rational to string (mechanical primitive)
 /// Assumes input is a RATIONAL. No type branching. No semantic decisions.
 fn builtin_rational_to_string(value: &Value) -> LumenResult<Value> {
@@ -410,18 +543,179 @@ fn builtin_bool_to_string(value: &Value) -> LumenResult<Value> {
 /// Assumes input is an ARRAY. No type branching. No semantic decisions.
 fn builtin_array_to_string(value: &Value) -> LumenResult<Value> {
     use crate::languages::lumen::values::{LumenString, LumenArray};
+    use crate::kernel::runtime::RuntimeValue;
 
     let array_val = value.as_any()
         .downcast_ref::<LumenArray>()
         .ok_or_else(|| "array_to_string() requires an array argument".to_string())?;
 
-    let elements_str = array_val.elements
+    Ok(Box::new(LumenString::new(array_val.as_display_string())))
+}
+
+/// Built-in function: map_to_string(x) - Convert map to string (mechanical primitive)
+/// Assumes input is MAP. No type branching. No semantic decisions.
+fn builtin_map_to_string(value: &Value) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenString, LumenMap};
+    use crate::kernel::runtime::RuntimeValue;
+
+    let map_val = value.as_any()
+        .downcast_ref::<LumenMap>()
+        .ok_or_else(|| "map_to_string() requires a map argument".to_string())?;
+
+    Ok(Box::new(LumenString::new(map_val.as_display_string())))
+}
+
+/// Built-in function: keys(m) - Return a map's keys as an array of strings
+/// Keys come back in insertion order.
+fn builtin_keys(value: &Value) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenString, LumenArray, LumenMap};
+
+    let map_val = value.as_any()
+        .downcast_ref::<LumenMap>()
+        .ok_or_else(|| "keys() requires a map argument".to_string())?;
+
+    let keys = map_val.entries
         .iter()
-        .map(|e| e.as_display_string())
-        .collect::<Vec<_>>()
-        .join(", ");
+        .map(|(k, _)| Box::new(LumenString::new(k.clone())) as Value)
+        .collect();
+
+    Ok(Box::new(LumenArray::new(keys)))
+}
+
+/// Built-in function: bytes(x) - Binary data from a string, an array or bytes
+/// A string gives its UTF-8 encoding; an array must hold integers from 0 to 255.
+fn builtin_bytes(value: &Value) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenArray, LumenBytes, LumenNumber, LumenString};
+    use num_traits::ToPrimitive;
 
-    Ok(Box::new(LumenString::new(format!("[{}]", elements_str))))
+    if let Some(string_val) = value.as_any().downcast_ref::<LumenString>() {
+        return Ok(Box::new(LumenBytes::new(string_val.value.clone().into_bytes())));
+    }
+    if value.as_any().downcast_ref::<LumenBytes>().is_some() {
+        return Ok(value.clone());
+    }
+    let array_val = value.as_any()
+        .downcast_ref::<LumenArray>()
+        .ok_or_else(|| "bytes() requires a string, bytes, or an array of integers from 0 to 255".to_string())?;
+    let mut bytes = Vec::with_capacity(array_val.elements.len());
+    for (i, element) in array_val.elements.iter().enumerate() {
+        let byte = element.as_any()
+            .downcast_ref::<LumenNumber>()
+            .and_then(|number| number.value.to_u8())
+            .ok_or_else(|| format!("bytes(): element {} is not an integer from 0 to 255", i))?;
+        bytes.push(byte);
+    }
+    Ok(Box::new(LumenBytes::new(bytes)))
+}
+
+/// Built-in function: bytes_to_string(x) - Convert bytes to string (mechanical primitive)
+/// Assumes input is BYTES. Gives b"..." as print writes it, not the decoded text.
+fn builtin_bytes_to_string(value: &Value) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenBytes, LumenString};
+    use crate::kernel::runtime::RuntimeValue;
+
+    let bytes_val = value.as_any()
+        .downcast_ref::<LumenBytes>()
+        .ok_or_else(|| "bytes_to_string() requires a bytes argument".to_string())?;
+
+    Ok(Box::new(LumenString::new(bytes_val.as_display_string())))
+}
+
+/// Built-in function: decode_utf8(b) - The string the bytes encode in UTF-8
+/// Errors, naming the first bad byte, if they are not valid UTF-8.
+fn builtin_decode_utf8(value: &Value) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenBytes, LumenString};
+
+    let bytes_val = value.as_any()
+        .downcast_ref::<LumenBytes>()
+        .ok_or_else(|| "decode_utf8() requires a bytes argument".to_string())?;
+
+    match std::str::from_utf8(&bytes_val.value) {
+        Ok(text) => Ok(Box::new(LumenString::new(text.to_string()))),
+        Err(e) => Err(format!("decode_utf8(): invalid UTF-8 at byte {}", e.valid_up_to()).into()),
+    }
+}
+
+/// Built-in functions: cache_extern(selector), clear_extern_cache([selector])
+/// cache_extern keeps the results of a selector's calls for the run, clear_extern_cache drops
+/// them (see lib_lumen/extern_cache.rs).
+fn builtin_extern_cache(func_name: &str, arg: Option<&Value>, env: &mut Env) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenNull, LumenString};
+
+    let selector = match arg {
+        Some(value) => Some(
+            value.as_any()
+                .downcast_ref::<LumenString>()
+                .map(|selector| selector.value.as_str())
+                .ok_or_else(|| format!("{}() requires a string selector", func_name))?,
+        ),
+        None => None,
+    };
+    let cache = &mut State::of(env).extern_cache;
+    match selector {
+        Some(selector) if func_name == "cache_extern" => cache.mark(selector),
+        _ => cache.clear(selector),
+    }
+    Ok(Box::new(LumenNull))
+}
+
+/// Built-in function: extern_timeout(target, ms)
+/// From now on the program's extern calls of `target` (*, a backend or a capability) stop
+/// waiting after `ms` milliseconds; null takes the timeout away. The host's timeouts still
+/// apply (see lib_lumen/extern_timeout.rs).
+fn builtin_extern_timeout(target: &Value, millis: &Value, env: &mut Env) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{Integer, LumenNull, LumenNumber, LumenString};
+
+    let target = target
+        .as_any()
+        .downcast_ref::<LumenString>()
+        .ok_or_else(|| "extern_timeout() requires a string target".to_string())?;
+    let timeout = match millis.as_any().downcast_ref::<LumenNumber>() {
+        _ if millis.as_any().is::<LumenNull>() => None,
+        Some(LumenNumber { value: Integer::Small(ms) }) if *ms > 0 => Some(std::time::Duration::from_millis(*ms as u64)),
+        _ => return Err("extern_timeout() requires a positive integer of milliseconds, or null".into()),
+    };
+    State::of(env).capabilities.program_timeouts().set(&target.value, timeout)?;
+    Ok(Box::new(LumenNull))
+}
+
+/// Built-in functions: memo_stats([name]), memo_clear([name]), memo_capacity(n)
+/// Statistics and controls of the MEMOIZATION cache (see lib_lumen/memo_cache.rs).
+fn builtin_memo(func_name: &str, arg: Option<&Value>, env: &mut Env) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenMap, LumenNull, LumenNumber, LumenString};
+    use num_traits::ToPrimitive;
+
+    let cache = env.memoization_cache();
+    if func_name == "memo_capacity" {
+        let capacity = arg
+            .and_then(|value| value.as_any().downcast_ref::<LumenNumber>())
+            .and_then(|num| num.value.to_usize())
+            .ok_or_else(|| "memo_capacity() requires a non-negative integer".to_string())?;
+        cache.set_capacity(capacity);
+        return Ok(Box::new(LumenNull));
+    }
+    let function = match arg {
+        Some(value) => Some(
+            value.as_any()
+                .downcast_ref::<LumenString>()
+                .map(|name| name.value.as_str())
+                .ok_or_else(|| format!("{}() function name must be a string", func_name))?,
+        ),
+        None => None,
+    };
+    if func_name == "memo_clear" {
+        cache.clear(function);
+        return Ok(Box::new(LumenNull));
+    }
+    let stats = cache.stats(function);
+    let count = |n: u64| Box::new(LumenNumber::new(BigInt::from(n))) as Value;
+    Ok(Box::new(LumenMap::new(vec![
+        ("hits".to_string(), count(stats.hits)),
+        ("misses".to_string(), count(stats.misses)),
+        ("evictions".to_string(), count(stats.evictions)),
+        ("entries".to_string(), count(stats.entries as u64)),
+        ("capacity".to_string(), count(cache.capacity() as u64)),
+    ])))
 }
 
 /// Built-in function: null_to_string(x) - Convert null to string (mechanical primitive)
@@ -452,17 +746,31 @@ fn builtin_kind_to_string(value: &Value) -> LumenResult<Value> {
         KindValue::STRING => "STRING",
         KindValue::BOOLEAN => "BOOLEAN",
         KindValue::ARRAY => "ARRAY",
+        KindValue::MAP => "MAP",
         KindValue::NULL => "NULL",
+        KindValue::HANDLE => "HANDLE",
+        KindValue::BYTES => "BYTES",
     };
 
     Ok(Box::new(LumenString::new(string.to_string())))
 }
 
-/// Built-in function: len(x) - Return length of string or array
-/// Returns the number of characters in a string or elements in an array.
-/// For strings, counts UTF-8 characters (not bytes).
+/// Name of the kind of a value, as kind_to_string(kind(x)) returns it ("value" for other values)
+pub fn kind_name(value: &Value) -> String {
+    use crate::languages::lumen::values::as_string;
+
+    builtin_kind(value)
+        .and_then(|kind| builtin_kind_to_string(&kind))
+        .ok()
+        .and_then(|name| as_string(name.as_ref()).ok().map(|s| s.value.clone()))
+        .unwrap_or_else(|| "value".to_string())
+}
+
+/// Built-in function: len(x) - Return length of string, array, map, or bytes
+/// Returns the number of characters in a string, elements in an array, entries in a map,
+/// or bytes in bytes. For strings, counts UTF-8 characters (not bytes).
 fn builtin_len(value: &Value) -> LumenResult<Value> {
-    use crate::languages::lumen::values::{LumenString, LumenNumber, LumenArray};
+    use crate::languages::lumen::values::{LumenString, LumenNumber, LumenArray, LumenMap, LumenBytes};
     use num_bigint::BigInt;
 
     // Check if it's a string
@@ -477,7 +785,18 @@ fn builtin_len(value: &Value) -> LumenResult<Value> {
         return Ok(Box::new(LumenNumber::new(BigInt::from(len))));
     }
 
-    Err("len() requires a string or array argument".to_string())
+    // Check if it's a map
+    if let Some(map_val) = value.as_any().downcast_ref::<LumenMap>() {
+        let len = map_val.entries.len();
+        return Ok(Box::new(LumenNumber::new(BigInt::from(len))));
+    }
+
+    // Check if it's bytes
+    if let Some(bytes_val) = value.as_any().downcast_ref::<LumenBytes>() {
+        return Ok(Box::new(LumenNumber::new(BigInt::from(bytes_val.value.len()))));
+    }
+
+    Err("len() requires a string, array, map, or bytes argument".into())
 }
 
 /// Built-in function: char_at(string, index) - Return character at index
@@ -503,14 +822,14 @@ fn builtin_char_at(string_val: &Value, index_val: &Value) -> LumenResult<Value>
         Some(i) => i,
         None => {
             // Negative or too large index
-            return Err("char_at index out of bounds".to_string());
+            return Err("char_at index out of bounds".into());
         }
     };
 
     // Get character at index
     match string.value.chars().nth(index) {
         Some(ch) => Ok(Box::new(LumenString::new(ch.to_string()))),
-        None => Err("char_at index out of bounds".to_string()), // Out of bounds
+        None => Err("char_at index out of bounds".into()), // Out of bounds
     }
 }
 
@@ -528,7 +847,7 @@ fn builtin_ord(value: &Value) -> LumenResult<Value> {
 
     // Check if string is empty
     if string_val.value.is_empty() {
-        return Err("ord() requires a non-empty string".to_string());
+        return Err("ord() requires a non-empty string".into());
     }
 
     // Get first character and convert to Unicode code point (u32)
@@ -576,7 +895,7 @@ fn builtin_error(msg_val: &Value) -> LumenResult<Value> {
         .ok_or_else(|| "error() argument must be a string".to_string())?;
 
     // Return error to abort execution (no I/O)
-    Err(msg.value.clone())
+    Err(msg.value.clone().into())
 }
 
 /// Built-in function: emit(string) - Kernel primitive for I/O
@@ -598,13 +917,120 @@ fn builtin_emit(value: &Value) -> LumenResult<Value> {
     Ok(Box::new(crate::languages::lumen::values::LumenNull))
 }
 
+/// Built-in function: extern_available(selector) - Check whether a capability can be called
+/// Consults the capability registry, including active mocks. Never calls the capability.
+fn builtin_extern_available(value: &Value, registry: &CapabilityRegistry) -> LumenResult<Value> {
+    use crate::languages::lumen::values::{LumenString, LumenBool};
+
+    let selector = value.as_any()
+        .downcast_ref::<LumenString>()
+        .ok_or_else(|| "extern_available() requires a string selector".to_string())?;
+
+    let available = crate::languages::lumen::extern_system::is_available(registry, &selector.value)?;
+    Ok(Box::new(LumenBool::new(available)))
+}
+
+/// Built-in function: extern_async(selector, args...) - Start an extern call without waiting
+/// Returns a HANDLE at once (see extern_system/pending.rs); a mock runs now, so its call is ready.
+fn start_extern_async<'a>(mut args: Vec<Value>, env: &mut Env) -> LumenResult<Step<'a>> {
+    use crate::languages::lumen::extern_system;
+    use crate::languages::lumen::values::{LumenArray, LumenString, LumenSymbol};
+
+    let selector = args[0]
+        .as_any()
+        .downcast_ref::<LumenString>()
+        .map(|s| s.value.clone())
+        .ok_or_else(|| "extern_async() requires a string selector".to_string())?;
+    let extern_args = args.split_off(1);
+
+    if let Some(handler) = extern_system::resolve_mock(&State::of(env).capabilities, &selector)? {
+        let call = functions::call_function(&handler, vec![Box::new(LumenArray::new(extern_args))], env)?;
+        return Ok(call.then(move |outcome, env| {
+            // An error (or exit) in the mock stops the program here, as extern's would
+            let value = outcome?.into_value();
+            let handle = State::of(env).pending.ready(&selector, Ok(value));
+            Ok(Step::value(Box::new(LumenSymbol::new(handle))))
+        }));
+    }
+    if extern_args.iter().any(|arg| arg.as_any().is::<LumenFunction>()) {
+        return Err("extern_async() cannot pass a function: its call runs alongside the program".into());
+    }
+    let state = State::of(env);
+    let (future, timeout) = extern_system::start_extern(&state.capabilities, &selector, extern_args)?;
+    let handle = state.pending.start(&selector, future, &*state.executor.clone(), timeout);
+    Ok(Step::value(Box::new(LumenSymbol::new(handle))))
+}
+
+/// Built-in function: extern_try(selector, args...) - Call an extern, catching its error
+/// Gives {"ok": true, "value": v}, or {"ok": false, "error": e} with e the ExternError as a MAP
+/// (see lib_lumen/extern_error.rs). A mock runs as for extern, and its errors still stop the
+/// program, as does an `exit` in a function the capability calls back.
+fn try_extern<'a>(mut args: Vec<Value>, env: &mut Env) -> LumenResult<Step<'a>> {
+    use crate::languages::lumen::extern_system;
+    use crate::languages::lumen::values::{LumenArray, LumenString};
+
+    let selector = args[0]
+        .as_any()
+        .downcast_ref::<LumenString>()
+        .map(|s| s.value.clone())
+        .ok_or_else(|| "extern_try() requires a string selector".to_string())?;
+    let extern_args = args.split_off(1);
+
+    if let Some(handler) = extern_system::resolve_mock(&State::of(env).capabilities, &selector)? {
+        let call = functions::call_function(&handler, vec![Box::new(LumenArray::new(extern_args))], env)?;
+        return Ok(call.then(move |outcome, _| Ok(Step::value(extern_outcome(Ok(outcome?.into_value()))))));
+    }
+    let result = extern_system::call_cached(env, &selector, extern_args);
+    // A function the capability called back ended the program; that is no failed call
+    if let Err(error) = &result {
+        if let Some(status) = error.exit {
+            return Err(LumenError::Exit(status));
+        }
+    }
+    Ok(Step::value(extern_outcome(result)))
+}
+
+/// The MAP extern_try gives for the result of a call
+fn extern_outcome(result: crate::languages::lumen::extern_system::registry::ExternResult) -> Value {
+    use crate::languages::lumen::values::{LumenBool, LumenMap, LumenNull, LumenNumber, LumenString};
+
+    let (ok, key, value): (bool, &str, Value) = match result {
+        Ok(value) => (true, "value", value),
+        Err(error) => {
+            let errno: Value = match error.errno {
+                Some(errno) => Box::new(LumenNumber::new(num_bigint::BigInt::from(errno))),
+                None => Box::new(LumenNull),
+            };
+            let fields: Vec<(String, Value)> = vec![
+                ("code".to_string(), Box::new(LumenString::new(error.code))),
+                ("message".to_string(), Box::new(LumenString::new(error.message))),
+                ("retriable".to_string(), Box::new(LumenBool::new(error.retriable))),
+                ("errno".to_string(), errno),
+            ];
+            (false, "error", Box::new(LumenMap::new(fields)))
+        }
+    };
+    Box::new(LumenMap::new(vec![("ok".to_string(), Box::new(LumenBool::new(ok))), (key.to_string(), value)]))
+}
+
+/// Built-in function: extern_await(call) - Wait for an extern_async call (the await point)
+fn builtin_extern_await(value: &Value, env: &mut Env) -> LumenResult<Value> {
+    use crate::languages::lumen::values::LumenSymbol;
+
+    let handle = value
+        .as_any()
+        .downcast_ref::<LumenSymbol>()
+        .ok_or_else(|| "extern_await() requires the HANDLE extern_async returned".to_string())?;
+    Ok(State::of(env).pending.wait(&handle.name)?)
+}
+
 /// Built-in function: kind(x) - Return kind meta-value representing value category
-/// Returns one of the predefined kind constants: INTEGER, RATIONAL, REAL, ARRAY, STRING, BOOLEAN, NULL
+/// Returns one of the predefined kind constants: INTEGER, RATIONAL, REAL, ARRAY, MAP, STRING, BOOLEAN, NULL, HANDLE, BYTES
 /// This is a pure introspection function with no side effects.
 fn builtin_kind(value: &Value) -> LumenResult<Value> {
     use crate::languages::lumen::values::{
-        LumenNumber, LumenRational, LumenReal, LumenArray,
-        LumenString, LumenBool, LumenNull, LumenKind, KindValue
+        LumenNumber, LumenRational, LumenReal, LumenArray, LumenMap,
+        LumenString, LumenBool, LumenNull, LumenKind, LumenSymbol, LumenBytes, KindValue
     };
 
     // Check value type and return appropriate kind meta-value
@@ -624,6 +1050,10 @@ fn builtin_kind(value: &Value) -> LumenResult<Value> {
         return Ok(Box::new(LumenKind::new(KindValue::ARRAY)));
     }
 
+    if value.as_any().downcast_ref::<LumenMap>().is_some() {
+        return Ok(Box::new(LumenKind::new(KindValue::MAP)));
+    }
+
     if value.as_any().downcast_ref::<LumenString>().is_some() {
         return Ok(Box::new(LumenKind::new(KindValue::STRING)));
     }
@@ -636,6 +1066,14 @@ fn builtin_kind(value: &Value) -> LumenResult<Value> {
         return Ok(Box::new(LumenKind::new(KindValue::NULL)));
     }
 
+    if value.as_any().downcast_ref::<LumenSymbol>().is_some() {
+        return Ok(Box::new(LumenKind::new(KindValue::HANDLE)));
+    }
+
+    if value.as_any().downcast_ref::<LumenBytes>().is_some() {
+        return Ok(Box::new(LumenKind::new(KindValue::BYTES)));
+    }
+
     if value.as_any().downcast_ref::<LumenKind>().is_some() {
         // KIND is a meta-value representing types - return a special KIND marker
         // This allows kind(INTEGER) to work, returning a kind-of-kind meta-value
@@ -643,7 +1081,7 @@ fn builtin_kind(value: &Value) -> LumenResult<Value> {
     }
 
     // Unknown value type
-    Err("kind(): unknown value type".to_string())
+    Err("kind(): unknown value type".into())
 }
 
 /// Built-in function: num(x) - Extract numerator from rational
@@ -657,7 +1095,7 @@ fn builtin_num(value: &Value) -> LumenResult<Value> {
         return Ok(Box::new(LumenNumber::new(rational.numerator.clone())));
     }
 
-    Err("num() requires a rational argument".to_string())
+    Err("num() requires a rational argument".into())
 }
 
 /// Built-in function: den(x) - Extract denominator from rational
@@ -671,7 +1109,7 @@ fn builtin_den(value: &Value) -> LumenResult<Value> {
         return Ok(Box::new(LumenNumber::new(rational.denominator.clone())));
     }
 
-    Err("den() requires a rational argument".to_string())
+    Err("den() requires a rational argument".into())
 }
 
 /// Built-in function: int(x) - Extract integer part from real
@@ -684,11 +1122,11 @@ fn builtin_int_part(value: &Value) -> LumenResult<Value> {
     // Check if it's a Real
     if let Some(real) = value.as_any().downcast_ref::<LumenReal>() {
         // Integer part: truncate toward zero (integer division)
-        let int_part = &real.numerator / &real.denominator;
+        let int_part = real.integer_part()?;
         return Ok(Box::new(LumenNumber::new(int_part)));
     }
 
-    Err("int() requires a real argument".to_string())
+    Err("int() requires a real argument".into())
 }
 
 /// Built-in function: frac(x) - Extract fractional part from real
@@ -706,18 +1144,110 @@ fn builtin_frac(value: &Value) -> LumenResult<Value> {
         // int(x) = numerator / denominator (integer division)
         // frac(x) = x - int(x) = numerator/denominator - (numerator / denominator)
         //         = (numerator - (numerator / denominator) * denominator) / denominator
-        let int_part = &real.numerator / &real.denominator;
+        let int_part = real.integer_part()?;
         let frac_numerator = &real.numerator - (&int_part * &real.denominator);
 
         // Return as REAL with same precision, preserving exact structure
-        return Ok(Box::new(LumenReal::new(
+        return Ok(Box::new(LumenReal::with_error(
             frac_numerator,
             real.denominator.clone(),
             real.precision,
+            real.radius.clone(),
         )));
     }
 
-    Err("frac() requires a real argument".to_string())
+    Err("frac() requires a real argument".into())
+}
+
+// ============================================================================
+// BUILT-IN TRANSCENDENTAL FUNCTIONS
+// ============================================================================
+
+/// Extract a precision argument (positive integer number of significant digits)
+fn precision_arg(value: &Value) -> LumenResult<usize> {
+    use crate::languages::lumen::values::LumenNumber;
+    use num_traits::ToPrimitive;
+
+    match value.as_any().downcast_ref::<LumenNumber>() {
+        Some(num) => num.value.to_usize()
+            .filter(|p| *p > 0)
+            .ok_or_else(|| "Precision must be a positive integer".into()),
+        None => Err("Precision argument must be an integer".into()),
+    }
+}
+
+/// Extract the exact (numerator, denominator) of an integer, rational, or real argument
+fn numeric_operand(value: &Value, func_name: &str) -> LumenResult<(BigInt, BigInt)> {
+    use crate::languages::lumen::values::{LumenNumber, LumenRational, LumenReal};
+
+    if let Some(real) = value.as_any().downcast_ref::<LumenReal>() {
+        return Ok((real.numerator.clone(), real.denominator.clone()));
+    }
+    if let Some(rational) = value.as_any().downcast_ref::<LumenRational>() {
+        return Ok((rational.numerator.clone(), rational.denominator.clone()));
+    }
+    if let Some(number) = value.as_any().downcast_ref::<LumenNumber>() {
+        return Ok((number.value.big().into_owned(), BigInt::from(1)));
+    }
+    Err(format!("{}() requires a number, rational, or real argument", func_name).into())
+}
+
+/// The error bound of a REAL argument under --intervals (other arguments are exact)
+fn operand_radius(value: &Value, intervals: bool) -> Option<Radius> {
+    use crate::languages::lumen::values::LumenReal;
+
+    match value.as_any().downcast_ref::<LumenReal>() {
+        Some(LumenReal { radius: Some(radius), .. }) => Some(radius.clone()),
+        _ => intervals.then(Radius::zero),
+    }
+}
+
+/// Built-in functions: exp(x, precision), ln(x, precision), atan(x, precision)
+/// Accept any numeric kind and return a REAL rounded to `precision` significant digits.
+/// The error is below one unit in the last digit (see lib_lumen/transcendental.rs).
+fn builtin_transcendental(func_name: &str, value: &Value, precision: usize, intervals: bool) -> LumenResult<Value> {
+    use crate::languages::lumen::numeric::transcendental;
+    use crate::languages::lumen::values::LumenReal;
+
+    let (num, den) = numeric_operand(value, func_name)?;
+    let (res_num, res_den) = match func_name {
+        "exp" => transcendental::exp(&num, &den, precision)?,
+        "ln" => transcendental::ln(&num, &den, precision)?,
+        "atan" => transcendental::atan(&num, &den, precision)?,
+        _ => return Err(format!("Unknown transcendental function '{}'", func_name).into()),
+    };
+    // inf and nan carry no error bound
+    let error = match operand_radius(value, intervals) {
+        Some(radius) if !den.is_zero() && !res_den.is_zero() => Some(interval::function_error(func_name, (&num, &den), &radius, (&res_num, &res_den), precision)?),
+        _ => None,
+    };
+    Ok(Box::new(LumenReal::with_error(res_num, res_den, precision, error)))
+}
+
+/// Built-in function: log(x, base, precision) - Logarithm of x in an arbitrary base
+/// Returns a REAL rounded to `precision` significant digits.
+fn builtin_log(value: &Value, base: &Value, precision: usize, intervals: bool) -> LumenResult<Value> {
+    use crate::languages::lumen::numeric::transcendental;
+    use crate::languages::lumen::values::LumenReal;
+
+    let (num, den) = numeric_operand(value, "log")?;
+    let (base_num, base_den) = numeric_operand(base, "log")?;
+    let (res_num, res_den) = transcendental::log(&num, &den, &base_num, &base_den, precision)?;
+    let special = den.is_zero() || base_den.is_zero() || res_den.is_zero();
+    let error = match (operand_radius(value, intervals), operand_radius(base, intervals)) {
+        (None, None) => None,
+        _ if special => None,
+        (x_radius, base_radius) => {
+            // ln(x) / ln(base), with the logarithms bounded a few digits past the precision
+            let guard = precision + 5;
+            let (x_radius, base_radius) = (x_radius.unwrap_or_else(Radius::zero), base_radius.unwrap_or_else(Radius::zero));
+            let (ln_x, ln_base) = (transcendental::ln(&num, &den, guard)?, transcendental::ln(&base_num, &base_den, guard)?);
+            let ln_x_error = interval::function_error("ln", (&num, &den), &x_radius, (&ln_x.0, &ln_x.1), guard)?;
+            let ln_base_error = interval::function_error("ln", (&base_num, &base_den), &base_radius, (&ln_base.0, &ln_base.1), guard)?;
+            Some(interval::quotient_error((&res_num, &res_den), (&ln_x.0, &ln_x.1), &ln_x_error, (&ln_base.0, &ln_base.1), &ln_base_error)?)
+        }
+    };
+    Ok(Box::new(LumenReal::with_error(res_num, res_den, precision, error)))
 }
 
 // --------------------
@@ -0,0 +1,277 @@
+// src_lumen/extern_system/adapter.rs
+//
+// Capability adapters: extern backends compiled as separate dynamic libraries (--adapter)
+//
+// An adapter is a cdylib crate that depends on this one and registers its capabilities
+// from an entry point:
+//
+//   [lib]
+//   crate-type = ["cdylib"]
+//
+//   [dependencies]
+//   lumen_stream = { path = "../lumen-lang/src_stream" }
+//
+//   use lumen_stream::languages::lumen::extern_system::adapter::{AdapterCapability, AdapterRegistry, Json};
+//   use lumen_stream::ExternError;
+//
+//   struct Greet;
+//   impl AdapterCapability for Greet {
+//       fn name(&self) -> &'static str { "greet" }
+//       fn call(&self, args: Vec<Json>) -> Result<Json, ExternError> { ... }
+//   }
+//   pub fn register(registry: &mut AdapterRegistry) {
+//       registry.register(Some("host"), Box::new(Greet));
+//   }
+//   lumen_stream::extern_adapter!(register);
+//
+// `stream prog.lm --adapter target/release/libgreet_adapter.so` loads it at startup and
+// calls the entry point once; every run then registers the capabilities in its
+// CapabilityRegistry after the builtin ones (so an adapter may replace one), where they
+// are listed in FEATURES, mockable and subject to --allow-* like any other. The kernel
+// itself names no adapter: the binary stays host-agnostic.
+//
+// Values do not cross the boundary as Lumen values (the two sides are separate builds of
+// this crate, whose types do not downcast into each other) but as JSON-shaped data, the
+// Json of json_backend.rs: null, booleans, numbers, strings, arrays and maps. Passing a
+// HANDLE or a RATIONAL to an adapter is an error.
+//
+// The entry point is `extern "C"` and takes the registry as an untyped pointer, but
+// capabilities and Json are Rust values, so an adapter must be built against the same
+// version of this crate with the same compiler. Each side carries ADAPTER_ABI, and an
+// adapter whose string differs is refused before anything else in it is called. Loaded
+// libraries stay loaded until the process exits: the registered capabilities point into
+// them. Adapters are native code and run with the kernel's permissions (the --allow-*
+// policy limits only what Lumen code reaches); only load ones you trust. Loading needs
+// dlopen (Unix).
+
+use std::ffi::c_void;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::kernel::runtime::Value;
+use super::marshal::{from_json, to_json};
+use super::extern_error::ExternError;
+use super::registry::{CapabilityRegistry, ExternCapability, ExternResult, Signature};
+use super::selector::Version;
+
+pub use super::json_backend::Json;
+
+/// Crate version and compiler an adapter was built with; must match the loading kernel's
+pub const ADAPTER_ABI: &str = concat!("lumen_stream ", env!("CARGO_PKG_VERSION"), " (", env!("LUMEN_RUSTC"), ")\0");
+
+/// Export an adapter from a cdylib crate: its fn(&mut AdapterRegistry) registering capabilities
+#[macro_export]
+macro_rules! extern_adapter {
+    ($register:path) => {
+        #[no_mangle]
+        pub extern "C" fn lumen_adapter_abi() -> *const ::std::os::raw::c_char {
+            $crate::languages::lumen::extern_system::adapter::ADAPTER_ABI.as_ptr().cast()
+        }
+
+        /// # Safety
+        /// `registry` is an AdapterRegistry, passed by the loading kernel
+        #[no_mangle]
+        pub unsafe extern "C" fn lumen_adapter_register(registry: *mut ::std::ffi::c_void) {
+            let registry = &mut *registry.cast::<$crate::languages::lumen::extern_system::adapter::AdapterRegistry>();
+            $register(registry);
+        }
+    };
+}
+
+/// A capability an adapter provides, taking and returning JSON-shaped data
+pub trait AdapterCapability: Send + Sync {
+    /// Name of the capability, as it follows "backend:" in a selector
+    fn name(&self) -> &'static str;
+
+    /// Call the capability with the given arguments
+    fn call(&self, args: Vec<Json>) -> Result<Json, ExternError>;
+
+    /// The arguments it takes, checked before `call` (see ExternCapability::signature)
+    fn signature(&self) -> Option<Signature> {
+        None
+    }
+
+    /// Its version, for "backend@2:name" selectors (see ExternCapability::version)
+    fn version(&self) -> Version {
+        Version::new(1, 0)
+    }
+}
+
+/// The capabilities an adapter's entry point registered, with their backends
+#[derive(Default)]
+pub struct AdapterRegistry {
+    capabilities: Vec<(Option<String>, Arc<dyn AdapterCapability>)>,
+}
+
+impl AdapterRegistry {
+    /// Register a capability with an optional backend, as CapabilityRegistry::register does
+    pub fn register(&mut self, backend: Option<&str>, capability: Box<dyn AdapterCapability>) {
+        self.capabilities.push((backend.map(str::to_string), Arc::from(capability)));
+    }
+}
+
+/// A loaded adapter library and the capabilities it registered
+#[derive(Clone)]
+pub struct Adapter {
+    /// The path it was loaded from
+    pub path: String,
+    capabilities: Vec<(Option<String>, Arc<dyn AdapterCapability>)>,
+}
+
+impl fmt::Debug for Adapter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Adapter({}: {})", self.path, self.selectors().join(", "))
+    }
+}
+
+impl Adapter {
+    /// Register the adapter's capabilities in the registry of a run
+    pub fn register(&self, registry: &mut CapabilityRegistry) {
+        for (selector, (backend, capability)) in self.selectors().into_iter().zip(&self.capabilities) {
+            registry.register(backend.as_deref(), Box::new(Adapted { selector, capability: capability.clone() }));
+        }
+    }
+
+    /// Its capabilities as selector strings ("cap" or "backend:cap")
+    pub fn selectors(&self) -> Vec<String> {
+        let selector = |(backend, capability): &(Option<String>, Arc<dyn AdapterCapability>)| match backend {
+            Some(backend) => format!("{}:{}", backend, capability.name()),
+            None => capability.name().to_string(),
+        };
+        self.capabilities.iter().map(selector).collect()
+    }
+}
+
+/// An adapter's capability as the registry of a run holds it
+struct Adapted {
+    selector: String,
+    capability: Arc<dyn AdapterCapability>,
+}
+
+impl ExternCapability for Adapted {
+    fn name(&self) -> &'static str {
+        self.capability.name()
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        self.capability.signature()
+    }
+
+    fn version(&self) -> Version {
+        self.capability.version()
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let args = args
+            .iter()
+            .map(|arg| to_json(arg).map_err(|_| ExternError::invalid_argument(format!("{}: {} cannot be passed to an adapter", self.selector, arg.repr()))))
+            .collect::<Result<_, _>>()?;
+        Ok(from_json(self.capability.call(args)?))
+    }
+}
+
+/// Load the adapter at `path` and collect the capabilities it registers
+pub fn load(path: &str) -> Result<Adapter, String> {
+    let entry = open(path).map_err(|e| format!("Failed to load adapter {}: {}", path, e))?;
+    let mut registry = AdapterRegistry::default();
+    let pointer: *mut AdapterRegistry = &mut registry;
+    // The ABI check in open() makes the registry the type the adapter expects
+    unsafe { entry(pointer.cast()) };
+    Ok(Adapter { path: path.to_string(), capabilities: registry.capabilities })
+}
+
+#[cfg(unix)]
+fn open(path: &str) -> Result<unsafe extern "C" fn(*mut c_void), String> {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+
+    // An absolute path, so dlopen never searches the library path instead
+    let path = std::fs::canonicalize(path).map_err(|e| e.to_string())?;
+    let path = CString::new(path.to_string_lossy().into_owned()).map_err(|e| e.to_string())?;
+    let last_error = || {
+        let error = unsafe { libc::dlerror() };
+        if error.is_null() {
+            "unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned()
+        }
+    };
+
+    // Never closed (see the header)
+    let library = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if library.is_null() {
+        return Err(last_error());
+    }
+    let symbol = |name: &CStr| {
+        let symbol = unsafe { libc::dlsym(library, name.as_ptr()) };
+        if symbol.is_null() {
+            Err(format!("not a Lumen capability adapter (no {})", name.to_string_lossy()))
+        } else {
+            Ok(symbol)
+        }
+    };
+
+    // The ABI string first: it is the only call that is safe whatever built the library
+    let abi = symbol(c"lumen_adapter_abi")?;
+    let abi: extern "C" fn() -> *const c_char = unsafe { std::mem::transmute(abi) };
+    let abi = unsafe { CStr::from_ptr(abi()) }.to_string_lossy().into_owned();
+    let expected = ADAPTER_ABI.trim_end_matches('\0');
+    if abi != expected {
+        return Err(format!("built for {}, but this kernel is {}", abi, expected));
+    }
+    let entry = symbol(c"lumen_adapter_register")?;
+    Ok(unsafe { std::mem::transmute::<*mut c_void, unsafe extern "C" fn(*mut c_void)>(entry) })
+}
+
+#[cfg(not(unix))]
+fn open(_path: &str) -> Result<unsafe extern "C" fn(*mut c_void), String> {
+    Err("adapters are only supported on Unix".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_missing_files_and_non_adapters() {
+        let error = load("no/such/adapter.so").unwrap_err();
+        assert!(error.starts_with("Failed to load adapter no/such/adapter.so: "), "{}", error);
+
+        let path = std::env::temp_dir().join(format!("lumen-not-an-adapter-{}.so", std::process::id()));
+        std::fs::write(&path, "not a library").unwrap();
+        let error = load(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.starts_with("Failed to load adapter"), "{}", error);
+    }
+
+    struct Count;
+
+    impl AdapterCapability for Count {
+        fn name(&self) -> &'static str {
+            "count"
+        }
+
+        fn call(&self, args: Vec<Json>) -> Result<Json, ExternError> {
+            match args.as_slice() {
+                [Json::List(items)] => Ok(Json::Number(items.len().to_string())),
+                _ => Err(ExternError::invalid_argument("host:count expects an array")),
+            }
+        }
+    }
+
+    #[test]
+    fn adapted_capabilities_take_and_give_json_shaped_values() {
+        let mut registered = AdapterRegistry::default();
+        registered.register(Some("host"), Box::new(Count));
+        let adapter = Adapter { path: "libhost.so".to_string(), capabilities: registered.capabilities };
+        assert_eq!(format!("{:?}", adapter), "Adapter(libhost.so: host:count)");
+
+        let mut registry = CapabilityRegistry::new();
+        adapter.register(&mut registry);
+        let count = registry.resolve(&super::super::selector::parse_selector("host:count").unwrap()[0]).unwrap();
+        let array = from_json(Json::List(vec![Json::Null, Json::Text("x".to_string())]));
+        assert_eq!(count.call(vec![array]).unwrap().as_display_string(), "2");
+        let handle: Value = Box::new(crate::languages::lumen::values::LumenSymbol::new("<net connection #1>".to_string()));
+        assert!(count.call(vec![handle]).unwrap_err().message.contains("cannot be passed to an adapter"));
+    }
+}
@@ -0,0 +1,129 @@
+// src_lumen/extern_system/bridge.rs
+//
+// extern for the other stream frontends (python_core, rust_core)
+//
+//   x = extern("json:parse", "[1, 2]")      # python_core
+//   let now = extern("time:now");           // rust_core
+//   extern("print_native", x)               # a statement: the value is dropped
+//
+// The call takes Lumen's selectors (selector.rs) and reaches the same capabilities, through
+// a CapabilityRegistry the run keeps in its environment (Env::language_state): the one an
+// Interpreter installs, or the builtin capabilities.
+// Those frontends have no strings, so the selector and any text argument are string
+// literals written in the call itself. Their numbers and booleans cross as Lumen's (an
+// integer as a NUMBER, a decimal as the exact RATIONAL) and come back as their own; any
+// other result (a STRING, ARRAY, MAP, ...) stays a Lumen value, which the program can
+// print or hand to another extern call.
+
+use crate::kernel::ast::ExprNode;
+use crate::kernel::parser::Parser;
+use crate::kernel::registry::{err_at, LumenResult};
+use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::numeric::parse_number_rational;
+use crate::languages::lumen::values::{as_bool, as_number, LumenBool, LumenNumber, LumenRational, LumenString};
+use super::capabilities;
+use super::registry::CapabilityRegistry;
+
+/// An argument of an extern call, as written
+#[derive(Debug)]
+pub enum Arg {
+    /// A string literal
+    Text(String),
+    /// Any other expression of the frontend
+    Expr(Box<dyn ExprNode>),
+}
+
+/// What an extern call gave, in the terms of the calling frontend
+pub enum Returned {
+    /// An integer, as its digits
+    Number(String),
+    Bool(bool),
+    /// Any other value, left as Lumen's
+    Lumen(Value),
+}
+
+/// Parse `extern("selector", arg, ...)`: the selector and the arguments; `expr` parses an
+/// expression of the frontend and `skip` its whitespace
+pub fn parse_call(
+    parser: &mut Parser,
+    mut expr: impl FnMut(&mut Parser) -> LumenResult<Box<dyn ExprNode>>,
+    skip: impl Fn(&mut Parser),
+) -> LumenResult<(String, Vec<Arg>)> {
+    parser.advance(); // consume 'extern'
+    skip(parser);
+    if parser.advance().lexeme != "(" {
+        return Err(err_at(parser, "Expected '(' after extern"));
+    }
+    skip(parser);
+    if parser.peek().lexeme != "\"" {
+        return Err(err_at(parser, "extern needs a selector string, as in extern(\"fs:read\", path)"));
+    }
+    let selector = parse_string(parser)?;
+    let mut args = Vec::new();
+    loop {
+        skip(parser);
+        match parser.advance().lexeme.as_ref() {
+            ")" => return Ok((selector, args)),
+            "," => {}
+            _ => return Err(err_at(parser, "Expected ',' or ')' in extern call")),
+        }
+        skip(parser);
+        if parser.peek().lexeme == "\"" {
+            args.push(Arg::Text(parse_string(parser)?));
+        } else {
+            args.push(Arg::Expr(expr(parser)?));
+        }
+    }
+}
+
+/// A string literal: every lexeme from the opening quote to the closing one
+fn parse_string(parser: &mut Parser) -> LumenResult<String> {
+    parser.advance(); // consume '"'
+    let mut text = String::new();
+    loop {
+        let token = parser.advance();
+        match token.lexeme.as_ref() {
+            "\"" => return Ok(text),
+            "\n" | "NEWLINE" | "EOF" => return Err(err_at(parser, "Unterminated string in extern call")),
+            lexeme => text.push_str(lexeme),
+        }
+    }
+}
+
+/// The frontend's number, given as its text, as a Lumen NUMBER or RATIONAL
+pub fn number(text: &str) -> LumenResult<Value> {
+    let (numerator, denominator) = parse_number_rational(text)?;
+    if denominator == 1.into() {
+        Ok(Box::new(LumenNumber::new(numerator)))
+    } else {
+        Ok(Box::new(LumenRational::new(numerator, denominator)))
+    }
+}
+
+/// The frontend's boolean as a Lumen BOOLEAN
+pub fn boolean(value: bool) -> Value {
+    Box::new(LumenBool::new(value))
+}
+
+/// A string literal argument as a Lumen STRING
+pub fn text(value: &str) -> Value {
+    Box::new(LumenString::new(value.to_string()))
+}
+
+/// Make the call through the run's registry (without one, the builtin capabilities, created
+/// by the first call)
+pub fn call(env: &mut Env, selector: &str, args: Vec<Value>) -> LumenResult<Returned> {
+    let registry = env.language_state(|| {
+        let mut registry = CapabilityRegistry::new();
+        capabilities::register_builtins(&mut registry);
+        registry
+    });
+    let value = super::call_extern(registry, selector, args)?;
+    if let Ok(number) = as_number(value.as_ref()) {
+        return Ok(Returned::Number(number.value.to_string()));
+    }
+    if let Ok(boolean) = as_bool(value.as_ref()) {
+        return Ok(Returned::Bool(boolean.value));
+    }
+    Ok(Returned::Lumen(value))
+}
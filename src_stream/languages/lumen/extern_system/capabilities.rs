@@ -3,10 +3,23 @@
 // Built-in capability implementations.
 // These are the boundary between Lumen and the host system.
 
-use crate::kernel::registry::LumenResult;
+use std::sync::Arc;
+
 use crate::kernel::runtime::Value;
-use super::registry::ExternCapability;
-use crate::languages::lumen::values::{LumenNumber, as_number, as_string, as_bool};
+use super::arr_backend;
+use super::crypto_backend;
+use super::env_backend;
+use super::fs_backend::{self, Outcome};
+use super::extern_error::ExternError;
+use super::io_backend;
+use super::json_backend;
+use super::marshal::{self, from_json, to_json};
+use super::net_backend::{self, Arg};
+use super::random_backend;
+use super::sys_backend;
+use super::time_backend;
+use super::registry::{ExternCapability, ExternResult, Host, NoHost, Signature};
+use crate::languages::lumen::values::{Integer, KindValue, LumenArray, LumenBool, LumenBytes, LumenFunction, LumenNull, LumenNumber, LumenReal, LumenString, LumenSymbol, as_number, as_string, as_bool};
 
 /// print_native capability
 /// Takes a single Value and prints it to stdout.
@@ -18,14 +31,11 @@ impl ExternCapability for PrintNative {
         "print_native"
     }
 
-    fn call(&self, args: Vec<Value>) -> LumenResult<Value> {
-        if args.len() != 1 {
-            return Err(format!(
-                "print_native expects 1 argument, got {}",
-                args.len()
-            ));
-        }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new(&[None]))
+    }
 
+    fn call(&self, args: Vec<Value>) -> ExternResult {
         // Print to stdout (impure operation)
         println!("{}", args[0]);
 
@@ -44,16 +54,13 @@ impl ExternCapability for DebugInfo {
         "debug_info"
     }
 
-    fn call(&self, args: Vec<Value>) -> LumenResult<Value> {
-        if args.len() != 1 {
-            return Err(format!(
-                "debug_info expects 1 argument, got {}",
-                args.len()
-            ));
-        }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new(&[None]))
+    }
 
+    fn call(&self, args: Vec<Value>) -> ExternResult {
         // Print debug representation
-        eprintln!("[DEBUG] {}", args[0]);
+        eprintln!("[DEBUG] {}", args[0].repr());
 
         // Return the original value
         Ok(args[0].clone())
@@ -69,14 +76,11 @@ impl ExternCapability for ValueType {
         "value_type"
     }
 
-    fn call(&self, args: Vec<Value>) -> LumenResult<Value> {
-        if args.len() != 1 {
-            return Err(format!(
-                "value_type expects 1 argument, got {}",
-                args.len()
-            ));
-        }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new(&[None]))
+    }
 
+    fn call(&self, args: Vec<Value>) -> ExternResult {
         let type_code = if as_number(args[0].as_ref()).is_ok() {
             0   // 0 = number
         } else if as_bool(args[0].as_ref()).is_ok() {
@@ -84,13 +88,313 @@ impl ExternCapability for ValueType {
         } else if as_string(args[0].as_ref()).is_ok() {
             2   // 2 = string
         } else {
-            return Err("Unknown value type".to_string());
+            return Err("Unknown value type".into());
         };
 
         Ok(Box::new(LumenNumber::new(num_bigint::BigInt::from(type_code))))
     }
 }
 
+/// fs backend capability (fs:read, fs:write, ...; see lib_lumen/fs_backend.rs)
+/// Takes string arguments and touches real files.
+pub struct Fs(&'static str);
+
+impl ExternCapability for Fs {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        let path = Some(KindValue::STRING);
+        Some(match self.0 {
+            "open" | "write" | "append" => Signature::new(&[path, Some(KindValue::STRING)]),
+            _ => Signature::new(&[path]),
+        })
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let texts: Vec<Option<&str>> = args.iter().map(|arg| as_string(arg.as_ref()).ok().map(|s| s.value.as_str())).collect();
+        Ok(match fs_backend::call(self.0, &texts)? {
+            Outcome::Text(text) => Box::new(LumenString::new(text)),
+            Outcome::Bool(b) => Box::new(LumenBool::new(b)),
+            Outcome::Names(names) => {
+                Box::new(LumenArray::new(names.into_iter().map(|name| Box::new(LumenString::new(name)) as Value).collect()))
+            }
+            Outcome::Null => Box::new(LumenNull),
+        })
+    }
+}
+
+/// io backend capability (io:read_line, io:read_all, io:read_bytes, io:write_bytes; see
+/// lib_lumen/io_backend.rs)
+/// Reads stdin, or writes BYTES to stdout.
+pub struct Io(&'static str);
+
+impl ExternCapability for Io {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(match self.0 {
+            "write_bytes" => Signature::new(&[Some(KindValue::BYTES)]),
+            _ => Signature::new(&[]),
+        })
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let args: Vec<io_backend::Arg> = args.iter().map(|arg| match arg.as_any().downcast_ref::<LumenBytes>() {
+            Some(bytes) => io_backend::Arg::Bytes(&bytes.value),
+            None => io_backend::Arg::Other,
+        }).collect();
+        Ok(match io_backend::call(self.0, &args)? {
+            io_backend::Outcome::Text(text) => Box::new(LumenString::new(text)),
+            io_backend::Outcome::Bytes(bytes) => Box::new(LumenBytes::new(bytes)),
+            io_backend::Outcome::Null => Box::new(LumenNull),
+        })
+    }
+}
+
+/// net backend capability (net:connect, net:send, ...; see lib_lumen/net_backend.rs)
+/// Connections and listeners travel through Lumen as symbols the program cannot spell; they
+/// live in the registry's socket table, so they close when the run's registry is dropped.
+pub struct Net(&'static str, Arc<net_backend::Sockets>);
+
+impl ExternCapability for Net {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        let connection = Some(KindValue::HANDLE);
+        Some(match self.0 {
+            "connect" | "listen" => Signature::new(&[Some(KindValue::STRING)]),
+            "send" => Signature::new(&[connection, Some(KindValue::STRING)]),
+            _ => Signature::new(&[connection]),
+        })
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let args: Vec<Arg> = args.iter().map(|arg| {
+            if let Some(handle) = arg.as_any().downcast_ref::<LumenSymbol>() {
+                Arg::Handle(handle.name.as_str())
+            } else {
+                as_string(arg.as_ref()).map_or(Arg::Other, |s| Arg::Text(s.value.as_str()))
+            }
+        }).collect();
+        Ok(match net_backend::call(&self.1, self.0, &args)? {
+            net_backend::Outcome::Handle(handle) => Box::new(LumenSymbol::new(handle)),
+            net_backend::Outcome::Text(text) => Box::new(LumenString::new(text)),
+            net_backend::Outcome::Null => Box::new(LumenNull),
+        })
+    }
+}
+
+/// time backend capability (time:now, time:monotonic_millis, time:format; see lib_lumen/time_backend.rs)
+/// Reads the clock; seconds and milliseconds are integers.
+pub struct Time(&'static str);
+
+impl ExternCapability for Time {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(match self.0 {
+            "format" => Signature::new(&[Some(KindValue::INTEGER)]).optional(&[Some(KindValue::STRING)]),
+            _ => Signature::new(&[]),
+        })
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let args: Vec<time_backend::Arg> = args.iter().map(|arg| {
+            if let Ok(s) = as_string(arg.as_ref()) {
+                time_backend::Arg::Text(s.value.as_str())
+            } else if let Ok(LumenNumber { value: Integer::Small(n) }) = as_number(arg.as_ref()) {
+                time_backend::Arg::Int(*n)
+            } else {
+                time_backend::Arg::Other
+            }
+        }).collect();
+        Ok(match time_backend::call(self.0, &args)? {
+            time_backend::Outcome::Int(n) => Box::new(LumenNumber::new(num_bigint::BigInt::from(n))),
+            time_backend::Outcome::Text(text) => Box::new(LumenString::new(text)),
+        })
+    }
+}
+
+/// sys backend capability (sys:platform, sys:arch, sys:cpu_count, sys:cwd, sys:pid; see
+/// lib_lumen/sys_backend.rs)
+/// Takes no arguments and describes the host.
+pub struct Sys(&'static str);
+
+impl ExternCapability for Sys {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new(&[]))
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        Ok(match sys_backend::call(self.0, args.len())? {
+            sys_backend::Outcome::Int(n) => marshal::value(n),
+            sys_backend::Outcome::Text(text) => marshal::value(text),
+        })
+    }
+}
+
+/// env backend capability (env:get, env:vars; see lib_lumen/env_backend.rs)
+/// Reads the environment variables of the process.
+pub struct EnvVars(&'static str);
+
+impl ExternCapability for EnvVars {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(match self.0 {
+            "get" => Signature::new(&[Some(KindValue::STRING)]),
+            _ => Signature::new(&[]),
+        })
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let args: Vec<Option<&str>> = args.iter()
+            .map(|arg| arg.as_any().downcast_ref::<LumenString>().map(|s| s.value.as_str()))
+            .collect();
+        Ok(match env_backend::call(self.0, &args)? {
+            env_backend::Outcome::Text(text) => marshal::value(text),
+            env_backend::Outcome::Null => marshal::value(()),
+            env_backend::Outcome::Vars(vars) => marshal::value(vars.into_iter().collect::<std::collections::BTreeMap<_, _>>()),
+        })
+    }
+}
+
+/// random backend capability (random:int, random:float; see lib_lumen/random_backend.rs)
+/// Draws integers in a range and REALs from 0 to 1 from the host's entropy.
+pub struct Random(&'static str);
+
+impl ExternCapability for Random {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(match self.0 {
+            "int" => Signature::new(&[Some(KindValue::INTEGER), Some(KindValue::INTEGER)]),
+            _ => Signature::new(&[]),
+        })
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let args: Vec<random_backend::Arg> = args.iter().map(|arg| match as_number(arg.as_ref()) {
+            Ok(LumenNumber { value: Integer::Small(n) }) => random_backend::Arg::Int(*n),
+            _ => random_backend::Arg::Other,
+        }).collect();
+        Ok(match random_backend::call(self.0, &args)? {
+            random_backend::Outcome::Int(n) => Box::new(LumenNumber::new(num_bigint::BigInt::from(n))),
+            random_backend::Outcome::Fraction(n) => {
+                let denominator = num_bigint::BigInt::from(1u64 << random_backend::FLOAT_BITS);
+                Box::new(LumenReal::new(num_bigint::BigInt::from(n), denominator, 15))
+            }
+        })
+    }
+}
+
+/// json backend capability (json:parse, json:stringify; see lib_lumen/json_backend.rs)
+/// Turns JSON text into arrays, maps, strings and numbers, and back.
+pub struct Json(&'static str);
+
+impl ExternCapability for Json {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(match self.0 {
+            "parse" => Signature::new(&[Some(KindValue::STRING)]),
+            _ => Signature::new(&[None]).optional(&[Some(KindValue::INTEGER)]),
+        })
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        if self.0 == "parse" {
+            let text = as_string(args[0].as_ref())?;
+            return Ok(from_json(json_backend::parse(&text.value)?));
+        }
+        let indent = match args.get(1).map(|arg| as_number(arg.as_ref())) {
+            None => None,
+            Some(Ok(LumenNumber { value: Integer::Small(n) })) if *n >= 0 => Some(*n as usize),
+            Some(_) => return Err(ExternError::invalid_argument("json:stringify indent must be a small non-negative integer")),
+        };
+        Ok(Box::new(LumenString::new(json_backend::stringify(&to_json(&args[0])?, indent)?)))
+    }
+}
+
+/// arr backend capability (arr:sort_by; see lib_lumen/arr_backend.rs)
+/// Sorts an ARRAY by a function of the program, which it calls back through the host.
+pub struct Arr(&'static str);
+
+impl ExternCapability for Arr {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        // The array and the function, which call_with checks
+        Some(Signature::new(&[Some(KindValue::ARRAY), None]))
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        self.call_with(args, &mut NoHost)
+    }
+
+    fn call_with(&self, args: Vec<Value>, host: &mut dyn Host) -> ExternResult {
+        let (Ok(items), Ok(function)) = (marshal::arg::<Vec<Value>>(&args, 0), marshal::arg::<Value>(&args, 1)) else {
+            return Err(arr_backend::expects(self.0));
+        };
+        if !function.as_any().is::<LumenFunction>() {
+            return Err(arr_backend::expects(self.0));
+        }
+        let sorted = arr_backend::sort_by(items, &mut |a: &Value, b: &Value| {
+            let order = host.call_back(&function, vec![a.clone(), b.clone()])?;
+            as_number(order.as_ref())
+                .map(|n| n.value.cmp(&Integer::from(0)))
+                .map_err(|_| arr_backend::not_an_order(&order.repr()))
+        })?;
+        Ok(marshal::value(sorted))
+    }
+}
+
+/// crypto backend capability (crypto:sha256, crypto:md5, crypto:blake3; see lib_lumen/crypto_backend.rs)
+/// Hashes a STRING (its UTF-8) or BYTES to a hex STRING.
+pub struct Crypto(&'static str);
+
+impl ExternCapability for Crypto {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        // A STRING or BYTES, which the backend checks
+        Some(Signature::new(&[None]))
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        let args: Vec<crypto_backend::Arg> = args.iter().map(|arg| {
+            if let Some(bytes) = arg.as_any().downcast_ref::<LumenBytes>() {
+                crypto_backend::Arg::Data(&bytes.value)
+            } else {
+                as_string(arg.as_ref()).map_or(crypto_backend::Arg::Other, |s| crypto_backend::Arg::Data(s.value.as_bytes()))
+            }
+        }).collect();
+        Ok(Box::new(LumenString::new(crypto_backend::call(self.0, &args)?)))
+    }
+}
+
 /// Create and register all built-in capabilities
 pub fn register_builtins(
     registry: &mut super::registry::CapabilityRegistry,
@@ -98,4 +402,35 @@ pub fn register_builtins(
     registry.register(None, Box::new(PrintNative));
     registry.register(None, Box::new(DebugInfo));
     registry.register(None, Box::new(ValueType));
+    for &name in arr_backend::CAPABILITIES {
+        registry.register(Some("arr"), Box::new(Arr(name)));
+    }
+    for &name in crypto_backend::CAPABILITIES {
+        registry.register(Some("crypto"), Box::new(Crypto(name)));
+    }
+    for &name in env_backend::CAPABILITIES {
+        registry.register(Some("env"), Box::new(EnvVars(name)));
+    }
+    for &name in fs_backend::CAPABILITIES {
+        registry.register(Some("fs"), Box::new(Fs(name)));
+    }
+    for &name in io_backend::CAPABILITIES {
+        registry.register(Some("io"), Box::new(Io(name)));
+    }
+    for &name in json_backend::CAPABILITIES {
+        registry.register(Some("json"), Box::new(Json(name)));
+    }
+    let sockets = Arc::new(net_backend::Sockets::default());
+    for &name in net_backend::CAPABILITIES {
+        registry.register(Some("net"), Box::new(Net(name, Arc::clone(&sockets))));
+    }
+    for &name in random_backend::CAPABILITIES {
+        registry.register(Some("random"), Box::new(Random(name)));
+    }
+    for &name in sys_backend::CAPABILITIES {
+        registry.register(Some("sys"), Box::new(Sys(name)));
+    }
+    for &name in time_backend::CAPABILITIES {
+        registry.register(Some("time"), Box::new(Time(name)));
+    }
 }
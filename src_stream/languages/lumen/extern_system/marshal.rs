@@ -0,0 +1,340 @@
+// src_lumen/extern_system/marshal.rs
+//
+// Conversions between Lumen values and the Rust data a capability works on.
+//
+// A capability receives its arguments as opaque Values. Instead of downcasting each one
+// by hand, it can take them as Rust types and give its result back the same way:
+//
+//   let path: String = marshal::arg(&args, 0)?;
+//   let lines: Vec<String> = marshal::arg(&args, 1)?;
+//   let limit: Option<usize> = marshal::arg(&args, 2)?;   // NULL or left out: None
+//   ...
+//   Ok(marshal::value(counts))                            // a BTreeMap<String, i64>: a MAP
+//
+//   Lumen               Rust
+//   BOOLEAN             bool
+//   INTEGER             i64, usize (when it fits), BigInt
+//   STRING              String
+//   BYTES               Bytes
+//   ARRAY               Vec<T>
+//   MAP                 BTreeMap<String, T> (the MAP keeps its entries in key order)
+//   NULL                None of an Option<T>
+//   JSON-shaped value   Json: NULL, BOOLEAN, a number, STRING, and ARRAY or MAP of those
+//   any value           Value, as it is
+//
+// An argument of the wrong kind is an invalid_argument ExternError naming what was
+// expected ("argument 2 must be ARRAY of STRING, got [1, 2]"). The microcode kernel's
+// capabilities match on its Value enum directly and need no layer of their own.
+
+use std::collections::BTreeMap;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::kernel::runtime::Value;
+use crate::languages::lumen::numeric::bigfloat;
+use crate::languages::lumen::values::{Integer, LumenArray, LumenBool, LumenBytes, LumenMap, LumenNull, LumenNumber, LumenRational, LumenReal, LumenString};
+use super::extern_error::ExternError;
+use super::json_backend::{self, Json};
+
+/// A Rust type a capability can take a Lumen value as
+pub trait FromValue: Sized {
+    /// What the value must be, as an error names it ("STRING", "ARRAY of INTEGER")
+    fn expected() -> String;
+
+    /// The value as this type, or None when it is not one
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+/// A Rust type a capability can give back as a Lumen value
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+/// The contents of a BYTES value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+/// Argument `index` (from 0) of a call, as a T; an argument past the end is NULL, so an
+/// Option argument may be left out
+pub fn arg<T: FromValue>(args: &[Value], index: usize) -> Result<T, ExternError> {
+    let null: Value = Box::new(LumenNull);
+    let value = args.get(index).unwrap_or(&null);
+    T::from_value(value).ok_or_else(|| {
+        ExternError::invalid_argument(format!("argument {} must be {}, got {}", index + 1, T::expected(), value.repr()))
+    })
+}
+
+/// Rust data as the Lumen value a capability returns
+pub fn value(data: impl IntoValue) -> Value {
+    data.into_value()
+}
+
+impl FromValue for Value {
+    fn expected() -> String {
+        "any value".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Box::new(LumenNull)
+    }
+}
+
+impl FromValue for bool {
+    fn expected() -> String {
+        "BOOLEAN".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_any().downcast_ref::<LumenBool>().map(|b| b.value)
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Box::new(LumenBool::new(self))
+    }
+}
+
+impl FromValue for BigInt {
+    fn expected() -> String {
+        "INTEGER".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_any().downcast_ref::<LumenNumber>().map(|n| n.value.big().into_owned())
+    }
+}
+
+impl IntoValue for BigInt {
+    fn into_value(self) -> Value {
+        Box::new(LumenNumber::new(self))
+    }
+}
+
+impl FromValue for i64 {
+    fn expected() -> String {
+        "INTEGER (64-bit)".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.as_any().downcast_ref::<LumenNumber>()?.value {
+            Integer::Small(n) => Some(n),
+            Integer::Big(_) => None,
+        }
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Box::new(LumenNumber::new(self))
+    }
+}
+
+impl FromValue for usize {
+    fn expected() -> String {
+        "non-negative INTEGER".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        i64::from_value(value)?.to_usize()
+    }
+}
+
+impl IntoValue for usize {
+    fn into_value(self) -> Value {
+        Box::new(LumenNumber::new(BigInt::from(self)))
+    }
+}
+
+impl FromValue for String {
+    fn expected() -> String {
+        "STRING".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_any().downcast_ref::<LumenString>().map(|s| s.value.clone())
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Box::new(LumenString::new(self))
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Box::new(LumenString::new(self.to_string()))
+    }
+}
+
+impl FromValue for Bytes {
+    fn expected() -> String {
+        "BYTES".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_any().downcast_ref::<LumenBytes>().map(|bytes| Bytes(bytes.value.clone()))
+    }
+}
+
+impl IntoValue for Bytes {
+    fn into_value(self) -> Value {
+        Box::new(LumenBytes::new(self.0))
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn expected() -> String {
+        format!("ARRAY of {}", T::expected())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let array = value.as_any().downcast_ref::<LumenArray>()?;
+        array.elements.iter().map(T::from_value).collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Box::new(LumenArray::new(self.into_iter().map(IntoValue::into_value).collect()))
+    }
+}
+
+impl<T: FromValue> FromValue for BTreeMap<String, T> {
+    fn expected() -> String {
+        format!("MAP of {}", T::expected())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let map = value.as_any().downcast_ref::<LumenMap>()?;
+        map.entries.iter().map(|(key, value)| Some((key.clone(), T::from_value(value)?))).collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for BTreeMap<String, T> {
+    fn into_value(self) -> Value {
+        Box::new(LumenMap::new(self.into_iter().map(|(key, value)| (key, value.into_value())).collect()))
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn expected() -> String {
+        format!("{} or NULL", T::expected())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        if value.as_any().is::<LumenNull>() {
+            Some(None)
+        } else {
+            T::from_value(value).map(Some)
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(data) => data.into_value(),
+            None => Box::new(LumenNull),
+        }
+    }
+}
+
+impl FromValue for Json {
+    fn expected() -> String {
+        "a JSON-shaped value".to_string()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        to_json(value).ok()
+    }
+}
+
+impl IntoValue for Json {
+    fn into_value(self) -> Value {
+        from_json(self)
+    }
+}
+
+/// A parsed JSON value as a Lumen value; numbers become INTEGERs or REALs as they are written
+pub fn from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Box::new(LumenNull),
+        Json::Bool(b) => Box::new(LumenBool::new(b)),
+        Json::Number(text) => match json_backend::number(&text) {
+            json_backend::Number::Int(n) => Box::new(LumenNumber::new(n)),
+            json_backend::Number::Real { numerator, denominator, precision } => {
+                Box::new(LumenReal::new(numerator, denominator, precision))
+            }
+        },
+        Json::Text(text) => Box::new(LumenString::new(text)),
+        Json::List(items) => Box::new(LumenArray::new(items.into_iter().map(from_json).collect())),
+        Json::Map(entries) => {
+            Box::new(LumenMap::new(entries.into_iter().map(|(key, value)| (key, from_json(value))).collect()))
+        }
+    }
+}
+
+/// A Lumen value as JSON; numbers go as they print (the backend refuses a RATIONAL, inf, nan)
+pub fn to_json(value: &Value) -> Result<Json, String> {
+    let any = value.as_any();
+    if any.is::<LumenNull>() {
+        Ok(Json::Null)
+    } else if let Some(b) = any.downcast_ref::<LumenBool>() {
+        Ok(Json::Bool(b.value))
+    } else if let Some(real) = any.downcast_ref::<LumenReal>() {
+        // Without the error bound that --intervals writes
+        Ok(Json::Number(bigfloat::decimal_string(&real.numerator, &real.denominator, real.precision)))
+    } else if any.is::<LumenNumber>() || any.is::<LumenRational>() {
+        Ok(Json::Number(value.as_display_string()))
+    } else if let Some(s) = any.downcast_ref::<LumenString>() {
+        Ok(Json::Text(s.value.clone()))
+    } else if let Some(array) = any.downcast_ref::<LumenArray>() {
+        Ok(Json::List(array.elements.iter().map(to_json).collect::<Result<_, _>>()?))
+    } else if let Some(map) = any.downcast_ref::<LumenMap>() {
+        let entries = map.entries.iter().map(|(key, value)| Ok((key.clone(), to_json(value)?)));
+        Ok(Json::Map(entries.collect::<Result<_, String>>()?))
+    } else {
+        Err(format!("json:stringify: {} has no JSON form", value.repr()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_and_gives_structured_values() {
+        let words = value(vec!["b".to_string(), "a".to_string()]);
+        let counts = value(BTreeMap::from([("b".to_string(), 2i64), ("a".to_string(), 1)]));
+        let args = vec![words, counts, value(Bytes(vec![1, 2]))];
+
+        assert_eq!(arg::<Vec<String>>(&args, 0), Ok(vec!["b".to_string(), "a".to_string()]));
+        let counts: BTreeMap<String, i64> = arg(&args, 1).unwrap();
+        assert_eq!(counts.into_iter().collect::<Vec<_>>(), vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+        assert_eq!(arg::<Bytes>(&args, 2), Ok(Bytes(vec![1, 2])));
+        assert_eq!(arg::<Option<usize>>(&args, 3), Ok(None));
+        assert_eq!(arg::<Value>(&args, 1).unwrap().repr(), args[1].repr());
+
+        assert_eq!(
+            arg::<Vec<i64>>(&args, 0),
+            Err(ExternError::invalid_argument(format!("argument 1 must be ARRAY of INTEGER (64-bit), got {}", args[0].repr())))
+        );
+        assert!(arg::<String>(&args, 3).is_err());
+        assert_eq!(value(Some(true)).repr(), value(true).repr());
+    }
+}
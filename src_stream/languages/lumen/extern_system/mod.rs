@@ -64,11 +64,21 @@
 //   - Design extern to be host-agnostic (no hardcoded backends)
 //   - Result: Selector grammar, registry, and capability trait are host-neutral
 //
-// Phase 4 (PENDING): External adapters
+// Phase 4 (IN PROGRESS): External adapters
 //   - Host environments provide ExternCapability implementations
 //   - Register capabilities via CapabilityRegistry
 //   - Requires no changes to Lumen, kernel, or selector semantics
-//   - Example: A Rust adapter might implement "fs:open", "net:connect", etc.
+//   - The fs backend (fs_backend.rs) is the first: open, read, write, append, exists,
+//     remove and list_dir; the io backend (io_backend.rs) reads stdin, as text or as
+//     BYTES, and writes BYTES to stdout; the net backend
+//     (net_backend.rs) is TCP: connect, listen, accept, send, recv and close; the time
+//     backend (time_backend.rs) reads the clock; the json backend (json_backend.rs)
+//     parses and writes JSON; the random backend (random_backend.rs) draws numbers;
+//     the crypto backend (crypto_backend.rs) hashes strings and bytes; the sys backend
+//     (sys_backend.rs) describes the host; the env backend (env_backend.rs) reads
+//     environment variables; the arr backend (arr_backend.rs) sorts an
+//     array by a function of the program, which it calls back (Host, registry.rs)
+//   - Adapters compiled apart from the kernel load at startup (--adapter, adapter.rs)
 //
 // =============================================================================
 // ADDING NEW CAPABILITIES
@@ -77,62 +87,351 @@
 // To extend extern without modifying Lumen:
 //
 // 1. Create a struct implementing ExternCapability trait (registry.rs)
-// 2. Implement the ExternCapability::call() method
-// 3. Register via CapabilityRegistry::register(backend, capability)
+// 2. Implement the ExternCapability::call() method, and signature() to have
+//    call_extern check the arguments' number and kinds before the call; marshal.rs
+//    takes the arguments as Rust data (marshal::arg) and gives the result back
+//    (marshal::value)
+// 3. Register via CapabilityRegistry::register(backend, capability), or, from an
+//    application embedding the kernel, Interpreter::with_capability(backend, capability)
 // 4. Invoke from Lumen: extern("backend:capability", args...)
 //
+// Library code can test for a capability and pick its own fallback:
+//   if extern_available("fs:read") ... else ...
+//
+// A failure can be caught as a value (an ExternError, see lib_lumen/extern_error.rs):
+//   result = extern_try("fs:read", path)
+//   if result["ok"] ... else if result["error"]["retriable"] ...
+//
+// Long calls can run while the program carries on (see pending.rs):
+//   call = extern_async("fs:read", path)
+//   ...
+//   text = extern_await(call)
+//
+// A capability can take the program's functions and call them back (see Host in registry.rs):
+//   sorted = extern("arr:sort_by", items, compare)   # compare(a, b) runs during the call
+//
+// Tests can shadow capabilities without a host adapter:
+//   push_mocks()
+//   mock_extern("fs:read", fake_read)   # fake_read(args) receives the args array
+//   ...
+//   pop_mocks()
+//
+// Calls whose answer does not change during a run can be served from a cache
+// (see lib_lumen/extern_cache.rs):
+//   cache_extern("env:get")
+//   ...
+//   clear_extern_cache("env:get")     # after the program changed what it reads
+//
+// A stuck capability need not hang the program: calls can give up after a timeout
+// (see lib_lumen/extern_timeout.rs), set by the host (--extern-timeout) or the program:
+//   extern_timeout("net", 500)        # net calls fail with "timed_out" after 500 ms
+//
 // The kernel and language remain unchanged.
 // The selector string travels opaquely through the evaluation pipeline.
-// Host adapters are responsible for their own validation, error handling,
-// and argument interpretation.
+// Host adapters are responsible for their own validation (beyond what a
+// signature declares), error handling, and argument interpretation.
 //
 // =============================================================================
 
+pub mod adapter;
+pub mod bridge;
 pub mod capabilities;
+pub mod marshal;
+pub mod pending;
 pub mod registry;
 pub mod selector;
 
-use registry::CapabilityRegistry;
-use std::sync::{Mutex, OnceLock};
-use crate::kernel::runtime::Value;
+// The error of a failed extern call (code, message, retriable, errno), shared with the microcode kernel
+pub mod extern_error {
+    include!("../../../../lib_lumen/extern_error.rs");
+}
+
+// Results of the selectors a program marked with cache_extern, shared with the microcode kernel
+pub mod extern_cache {
+    include!("../../../../lib_lumen/extern_cache.rs");
+}
+
+// The arr backend (arr:sort_by), which calls back into the program, shared with the microcode kernel
+pub mod arr_backend {
+    include!("../../../../lib_lumen/arr_backend.rs");
+}
+
+// The crypto backend (crypto:sha256, crypto:md5, crypto:blake3), shared with the microcode kernel
+pub mod crypto_backend {
+    include!("../../../../lib_lumen/crypto_backend.rs");
+}
+
+// The env backend (env:get, env:vars), shared with the microcode kernel
+pub mod env_backend {
+    include!("../../../../lib_lumen/env_backend.rs");
+}
+
+// The fs backend (fs:read, fs:write, ...), shared with the microcode kernel
+pub mod fs_backend {
+    include!("../../../../lib_lumen/fs_backend.rs");
+}
+
+// The io backend (io:read_line, io:read_all, io:read_bytes, io:write_bytes), shared with
+// the microcode kernel
+pub mod io_backend {
+    include!("../../../../lib_lumen/io_backend.rs");
+}
+
+// The json backend (json:parse, json:stringify), shared with the microcode kernel
+pub mod json_backend {
+    include!("../../../../lib_lumen/json_backend.rs");
+}
+
+// The net backend (net:connect, net:listen, ...), shared with the microcode kernel
+pub mod net_backend {
+    include!("../../../../lib_lumen/net_backend.rs");
+}
+
+// The random backend (random:int, random:float), shared with the microcode kernel
+pub mod random_backend {
+    include!("../../../../lib_lumen/random_backend.rs");
+}
+
+// The sys backend (sys:platform, sys:arch, sys:cpu_count, sys:cwd, sys:pid), shared with the microcode kernel
+pub mod sys_backend {
+    include!("../../../../lib_lumen/sys_backend.rs");
+}
+
+// The time backend (time:now, time:monotonic_millis, time:format), shared with the microcode kernel
+pub mod time_backend {
+    include!("../../../../lib_lumen/time_backend.rs");
+}
+
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use extern_error::ExternError;
+use pending::ExternFuture;
+use registry::{CapabilityRegistry, ExternCapability, ExternResult, Host, NoHost};
+use crate::languages::lumen::state::State;
+use crate::languages::lumen::statements::functions;
+use crate::languages::lumen::values::LumenFunction;
+use crate::kernel::runtime::{Env, Value};
 use crate::kernel::registry::LumenResult;
+use crate::extern_audit::ExternAudit;
+use crate::extern_timeout::{self, Stopped};
+
+// Each run has its own registry (see lumen/state.rs): the builtin capabilities and the
+// mock layers its program pushed. The functions below take the registry of the run.
+
+/// Report whether a selector would resolve to a capability (real or mocked).
+/// This is the explicit, user-controlled counterpart to FAILURE HONESTY:
+/// extern never falls back silently, but library code may check first and
+/// choose a pure-Lumen path itself.
+pub fn is_available(registry: &CapabilityRegistry, selector: &str) -> LumenResult<bool> {
+    let clauses = selector::parse_selector(selector)?;
+
+    Ok(clauses.iter().any(|clause| {
+        registry.resolve_mock(clause).is_some() || registry.has(clause)
+    }))
+}
+
+/// Find the mock handler (a Lumen function name) that a selector resolves to, if any.
+/// Clauses are tried in order; within a clause a mock shadows the real capability.
+/// Returns None when the first resolvable clause is a real capability.
+pub fn resolve_mock(registry: &CapabilityRegistry, selector: &str) -> LumenResult<Option<String>> {
+    let clauses = selector::parse_selector(selector)?;
+
+    for clause in &clauses {
+        if let Some(handler) = registry.resolve_mock(clause) {
+            return Ok(Some(handler.to_string()));
+        }
+        if registry.has(clause) {
+            return Ok(None);
+        }
+    }
+    Ok(None)
+}
 
-/// Global capability registry (lazily initialized)
-fn get_registry() -> &'static Mutex<CapabilityRegistry> {
-    static REGISTRY: OnceLock<Mutex<CapabilityRegistry>> = OnceLock::new();
-    REGISTRY.get_or_init(|| {
-        let mut registry = CapabilityRegistry::new();
-        capabilities::register_builtins(&mut registry);
-        Mutex::new(registry)
-    })
+/// Register a Lumen function as a mock for every clause of a selector.
+pub fn mock_extern(registry: &mut CapabilityRegistry, selector: &str, handler: &str) -> LumenResult<()> {
+    let clauses = selector::parse_selector(selector)?;
+    for clause in &clauses {
+        registry.mock(clause.backend.as_deref(), &clause.capability, handler);
+    }
+    Ok(())
+}
+
+/// Discard the most recent mock layer.
+pub fn pop_mock_layer(registry: &mut CapabilityRegistry) -> LumenResult<()> {
+    if registry.pop_mock_layer() {
+        Ok(())
+    } else {
+        Err("pop_mocks() called with no active mock layer".into())
+    }
 }
 
 /// Call an extern capability with the given selector and arguments.
-/// This is the boundary crossing function.
+/// This is the boundary crossing function. The capability cannot call back into a program
+/// (see call_extern_in).
 pub fn call_extern(
+    registry: &CapabilityRegistry,
     selector: &str,
     args: Vec<Value>,
-) -> LumenResult<Value> {
+) -> ExternResult {
+    resolve_call(registry, selector, &args)?.run(selector, args, &mut NoHost)
+}
+
+/// call_extern for the program running in `env`: the capability can call back the functions
+/// among the arguments, which run in `env`
+pub fn call_extern_in(env: &mut Env, selector: &str, args: Vec<Value>) -> ExternResult {
+    let call = resolve_call(&State::of(env).capabilities, selector, &args)?;
+    call.run(selector, args, env)
+}
+
+/// call_extern_in, served from the run's cache when the selector is cacheable and was called
+/// with the same arguments before (the result of a successful call is kept there)
+pub fn call_cached(env: &mut Env, selector: &str, args: Vec<Value>) -> ExternResult {
+    if !State::of(env).extern_cache.is_cacheable(selector) {
+        return call_extern_in(env, selector, args);
+    }
+    let key = Env::fingerprint_args(&args);
+    if let Some(value) = State::of(env).extern_cache.get(selector, &key) {
+        return Ok(value);
+    }
+    let value = call_extern_in(env, selector, args)?;
+    State::of(env).extern_cache.insert(selector, key, value.clone());
+    Ok(value)
+}
+
+/// Start an extern call without waiting for it (extern_async): the capability's future
+/// (with --audit, one that logs the call when it finishes) and the call's timeout
+pub fn start_extern(registry: &CapabilityRegistry, selector: &str, args: Vec<Value>) -> Result<(ExternFuture, Option<Duration>), ExternError> {
+    let Call { capability, audit, timeout } = resolve_call(registry, selector, &args)?;
+    let future = capability.call_async(args);
+    let Some((audit, summary, started, clock)) = audit else {
+        return Ok((future, timeout));
+    };
+    let selector = selector.to_string();
+    let future: ExternFuture = Box::pin(async move {
+        let result = future.await;
+        record(&audit, &selector, &summary, started, clock, &result);
+        result
+    });
+    Ok((future, timeout))
+}
+
+/// A running program is the host of the calls it makes: a capability given one of its
+/// functions calls it here, and the call runs to its value before the capability goes on
+impl Host for Env {
+    fn call_back(&mut self, function: &Value, args: Vec<Value>) -> ExternResult {
+        let Some(function) = function.as_any().downcast_ref::<LumenFunction>() else {
+            return Err(ExternError::invalid_argument(format!("{} is not a function", function.repr())));
+        };
+        // In the scopes the function value was taken in, not the capability's caller's
+        if let Some(captured) = &function.captured {
+            self.enter_captured(captured);
+        }
+        let result = functions::call_function(&function.name, args, self)
+            .and_then(|call| crate::kernel::eval::run(Ok(call), self));
+        if function.captured.is_some() {
+            self.leave_captured();
+        }
+        Ok(result?.into_value())
+    }
+}
+
+/// A capability resolved for a call, with what --audit logs once the call finishes and how
+/// long the call may take
+struct Call {
+    capability: Arc<dyn ExternCapability>,
+    audit: Option<(ExternAudit, Vec<String>, SystemTime, Instant)>,
+    timeout: Option<Duration>,
+}
+
+impl Call {
+    /// Make the call, with `host` to call back into; a timed call runs on a thread of its
+    /// own (see lib_lumen/extern_timeout.rs)
+    fn run(self, selector: &str, args: Vec<Value>, host: &mut dyn Host) -> ExternResult {
+        let result = match self.timeout {
+            // The program's functions run in the program, which that thread cannot reach
+            Some(timeout) if !args.iter().any(|arg| arg.as_any().is::<LumenFunction>()) => {
+                let capability = self.capability.clone();
+                extern_timeout::run(timeout, move || capability.call(args))
+                    .unwrap_or_else(|stopped| Err(stopped_error(selector, timeout, stopped)))
+            }
+            _ => self.capability.call_with(args, host),
+        };
+        if let Some((audit, summary, started, clock)) = &self.audit {
+            record(audit, selector, summary, *started, *clock, &result);
+        }
+        result
+    }
+}
+
+/// The capability `selector` resolves to for a call with `args` (with --audit, a failure to
+/// resolve is logged here)
+fn resolve_call(registry: &CapabilityRegistry, selector: &str, args: &[Value]) -> Result<Call, ExternError> {
+    let Some(audit) = registry.audit().cloned() else {
+        let (capability, timeout) = resolve_extern(registry, selector, args)?;
+        return Ok(Call { capability: capability.clone(), audit: None, timeout });
+    };
+    let (summary, started, clock) = (summaries(args), SystemTime::now(), Instant::now());
+    match resolve_extern(registry, selector, args) {
+        Ok((capability, timeout)) => Ok(Call { capability: capability.clone(), audit: Some((audit, summary, started, clock)), timeout }),
+        Err(e) => {
+            record(&audit, selector, &summary, started, clock, &Err(e.clone()));
+            Err(e)
+        }
+    }
+}
+
+/// The arguments of a call as the audit log shows them
+fn summaries(args: &[Value]) -> Vec<String> {
+    args.iter().map(|arg| arg.repr()).collect()
+}
+
+/// Log a finished call to the audit
+fn record(audit: &ExternAudit, selector: &str, args: &[String], started: SystemTime, clock: Instant, result: &ExternResult) {
+    let failure = result.as_ref().err().map(|e| (e.code.as_str(), e.message.as_str()));
+    audit.record(selector, args, started, clock.elapsed(), failure);
+}
+
+/// The error of a timed call that gave no result
+fn stopped_error(selector: &str, timeout: Duration, stopped: Stopped) -> ExternError {
+    match stopped {
+        Stopped::TimedOut => ExternError::timed_out(extern_timeout::timed_out(selector, timeout)),
+        Stopped::Failed(why) => ExternError::new("failed", format!("extern \"{}\": {}", selector, why)),
+    }
+}
+
+/// The capability a selector resolves to (the first clause that resolves wins), once
+/// `args` fit its signature, and how long a call of it may take
+fn resolve_extern<'a>(registry: &'a CapabilityRegistry, selector: &str, args: &[Value]) -> Result<(&'a Arc<dyn ExternCapability>, Option<Duration>), ExternError> {
     // Parse the selector string
-    let clauses = selector::parse_selector(selector)?;
+    let clauses = selector::parse_selector(selector).map_err(ExternError::invalid_argument)?;
 
     // Resolve the capability in order
-    let registry = get_registry();
-    let registry = registry.lock().unwrap();
-
     for clause in &clauses {
-        if let Some(cap) = registry.resolve(&clause.backend, &clause.capability) {
-            // Found a matching capability - call it
-            return cap.call(args);
+        if let Some((cap, timeout)) = registry.resolve_timed(clause) {
+            if let Some(signature) = cap.signature() {
+                signature.check(args).map_err(|e| ExternError::invalid_argument(format!("{}: {}", selector, e)))?;
+            }
+            return Ok((cap, timeout));
         }
     }
 
+    // A capability the run's policy denies is an error naming the flag that allows it
+    if let Some((backend, clause)) = clauses.iter().find_map(|c| registry.denied_backend(c).map(|b| (b, c))) {
+        return Err(ExternError::new("denied", crate::extern_policy::denied(backend, &clause.capability)));
+    }
+
     // No capability found in any clause
-    let first_clause = clauses.first().ok_or_else(|| "Empty selector clauses".to_string())?;
-    Err(format!(
+    let first_clause = clauses.first().ok_or_else(|| ExternError::invalid_argument("Empty selector clauses"))?;
+    Err(ExternError::new("unavailable", format!(
         "No implementation found for capability '{}' with backends {:?}",
         first_clause.capability,
-        clauses.iter().filter_map(|c| c.backend.as_ref()).collect::<Vec<_>>()
-    ))
+        clauses
+            .iter()
+            .filter_map(|c| c.backend.as_ref().map(|b| match c.version {
+                Some(version) => format!("{}@{}", b, version),
+                None => b.clone(),
+            }))
+            .collect::<Vec<_>>()
+    )))
 }
-
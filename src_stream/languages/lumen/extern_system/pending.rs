@@ -0,0 +1,147 @@
+// src_lumen/extern_system/pending.rs
+//
+// Extern calls in flight: extern_async starts one, extern_await waits for it
+//
+//   a = extern_async("http:get", "https://example.org/a")   # a HANDLE, at once
+//   b = extern_async("http:get", "https://example.org/b")   # both are now running
+//   pages = [extern_await(a), extern_await(b)]              # the await points
+//
+// extern_async gets the capability's future from ExternCapability::call_async (by default
+// one that runs `call`) and hands it to the run's executor, which drives it off the
+// interpreter's thread. The interpreter carries on until extern_await, which blocks until
+// that call has finished and gives its value or raises its error. Each handle is awaited
+// once; a call nobody awaits still runs to the end. A mocked selector calls the mock at
+// once, so its handle is ready when extern_async returns.
+//
+// The default executor (ThreadExecutor) gives each call a thread of its own. An embedder
+// with an executor of its own (a thread pool, an async runtime) passes it to
+// Interpreter::with_executor, and capabilities that override call_async then run as
+// tasks there instead of occupying a thread while they wait. Where no thread can be
+// started (wasm32-wasip1 has none) the call fails with code "unsupported", which its
+// extern_await raises; the program is not stopped at the extern_async.
+//
+// A call with a timeout (see lib_lumen/extern_timeout.rs) has it counted from its start:
+// extern_await waits until then at most, and past it raises a "timed_out" error.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use super::extern_error::ExternError;
+use crate::extern_timeout;
+use super::registry::ExternResult;
+
+/// The result of an extern call, some time later
+pub type ExternFuture = Pin<Box<dyn Future<Output = ExternResult> + Send>>;
+
+/// A task an executor drives to completion
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Runs the futures of extern_async calls off the interpreter's thread
+pub trait ExternExecutor: Send + Sync {
+    /// Drive `task` to completion; must not wait for it to finish. An error means `task`
+    /// will never run, and its call fails with it.
+    fn spawn(&self, task: Task) -> io::Result<()>;
+}
+
+/// The default executor: each task on a thread of its own
+pub struct ThreadExecutor;
+
+impl ExternExecutor for ThreadExecutor {
+    fn spawn(&self, task: Task) -> io::Result<()> {
+        thread::Builder::new().name("lumen-extern-async".to_string()).spawn(move || block_on(task)).map(drop)
+    }
+}
+
+/// Wakes a thread parked in block_on
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run `future` to completion on this thread
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// The extern_async calls of one run that no extern_await has taken yet
+#[derive(Default)]
+pub struct PendingCalls {
+    /// Calls started so far, for numbering handles
+    started: u64,
+    /// Where each call's result arrives, by handle name (in a Mutex, since run state is Sync)
+    calls: HashMap<String, Pending>,
+}
+
+/// A call in flight
+struct Pending {
+    result: Mutex<Receiver<ExternResult>>,
+    /// Its selector, when it started and its timeout, if it has one
+    timed: Option<(String, Instant, Duration)>,
+}
+
+impl PendingCalls {
+    /// Start `future` on `executor`, to give up on after `timeout`; returns the handle name
+    /// of the call
+    pub fn start(&mut self, selector: &str, future: ExternFuture, executor: &dyn ExternExecutor, timeout: Option<Duration>) -> String {
+        let (sender, receiver) = mpsc::channel();
+        let timed = timeout.map(|timeout| (selector.to_string(), Instant::now(), timeout));
+        let failed = sender.clone();
+        let started = executor.spawn(Box::pin(async move {
+            // Nobody listens once the run has ended (or the call timed out)
+            let _ = sender.send(future.await);
+        }));
+        if let Err(error) = started {
+            let _ = failed.send(Err(ExternError::io(format!("extern_async(\"{}\"): cannot start the call: {}", selector, error), &error)));
+        }
+        self.add(selector, receiver, timed)
+    }
+
+    /// A call that has already finished with `result` (a mocked selector)
+    pub fn ready(&mut self, selector: &str, result: ExternResult) -> String {
+        let (sender, receiver) = mpsc::channel();
+        let _ = sender.send(result);
+        self.add(selector, receiver, None)
+    }
+
+    fn add(&mut self, selector: &str, receiver: Receiver<ExternResult>, timed: Option<(String, Instant, Duration)>) -> String {
+        self.started += 1;
+        let handle = format!("<extern_async #{} {}>", self.started, selector);
+        self.calls.insert(handle.clone(), Pending { result: Mutex::new(receiver), timed });
+        handle
+    }
+
+    /// Wait for the call `handle` names to finish (the await point)
+    pub fn wait(&mut self, handle: &str) -> ExternResult {
+        let pending = self.calls.remove(handle).ok_or_else(|| {
+            ExternError::invalid_argument(format!("extern_await(): {} is not a pending call (each is awaited once)", handle))
+        })?;
+        let receiver = pending.result.into_inner().unwrap_or_else(PoisonError::into_inner);
+        let dropped = || Err(format!("extern_await(): {} was dropped before it finished", handle).into());
+        let Some((selector, started, timeout)) = pending.timed else {
+            return receiver.recv().unwrap_or_else(|_| dropped());
+        };
+        match receiver.recv_timeout(timeout.saturating_sub(started.elapsed())) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(ExternError::timed_out(extern_timeout::timed_out(&selector, timeout))),
+            Err(RecvTimeoutError::Disconnected) => dropped(),
+        }
+    }
+}
@@ -3,65 +3,347 @@
 // Capability registry and trait definitions.
 // Separates Lumen's semantic contracts from host implementations.
 
-use crate::kernel::registry::LumenResult;
-use crate::kernel::runtime::Value;
+use crate::kernel::runtime::{RuntimeValue, Value};
+use crate::extern_audit::ExternAudit;
+use crate::extern_policy::ExternPolicy;
+use crate::extern_timeout::ExternTimeouts;
+use crate::languages::lumen::expressions::variable::kind_name;
+use crate::languages::lumen::values::{KindValue, LumenKind};
+use super::extern_error::ExternError;
+use super::pending::ExternFuture;
+use super::selector::{SelectorClause, Version};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What a capability call gives: a value, or an error a program can catch with extern_try
+pub type ExternResult = Result<Value, ExternError>;
+
+/// What a capability calls back into the running program through: the functions a program
+/// passes to it arrive as LumenFunction values, and `call_back` runs one to its value
+pub trait Host {
+    fn call_back(&mut self, function: &Value, args: Vec<Value>) -> ExternResult;
+}
+
+/// The host of a call that cannot call back into a program: one started by extern_async,
+/// or made by a frontend without function values (python_core, rust_core)
+pub struct NoHost;
+
+impl Host for NoHost {
+    fn call_back(&mut self, _function: &Value, _args: Vec<Value>) -> ExternResult {
+        Err(ExternError::new("unsupported", "this call cannot call back into the program"))
+    }
+}
 
 /// Trait defining a host capability implementation.
 /// Each capability is responsible for:
-/// - Validating its own arguments
+/// - Validating its own arguments, or declaring a Signature that call_extern checks
 /// - Performing the impure operation
 /// - Returning a Lumen Value
-pub trait ExternCapability: Send + Sync {
+pub trait ExternCapability: Send + Sync + 'static {
     /// Name of the capability (e.g., "print_native", "fs_open")
     fn name(&self) -> &'static str;
 
     /// Call the capability with the given arguments.
     /// Arguments are already evaluated Lumen values.
-    /// Return a Value or a diagnostic error.
-    fn call(&self, args: Vec<Value>) -> LumenResult<Value>;
+    /// Return a Value or an ExternError (a String converts to one with code "failed").
+    fn call(&self, args: Vec<Value>) -> ExternResult;
+
+    /// Call the capability from a running program, which it can call back through `host`
+    /// (e.g. arr:sort_by runs the program's comparison). The default is `call`, for the
+    /// capabilities that take no functions.
+    fn call_with(&self, args: Vec<Value>, host: &mut dyn Host) -> ExternResult {
+        let _ = host;
+        self.call(args)
+    }
+
+    /// The arguments the capability takes. call_extern checks every call against it before
+    /// dispatch, so `call` sees only arguments that fit; None leaves all checks to `call`.
+    fn signature(&self) -> Option<Signature> {
+        None
+    }
+
+    /// The version of the capability, which "backend@2:name" selectors ask for. Registering
+    /// another version keeps this one, so scripts pinned to it go on working.
+    fn version(&self) -> Version {
+        Version::new(1, 0)
+    }
+
+    /// Start the call for extern_async (see pending.rs) and return its result as a future,
+    /// which the run's executor drives. The default future runs `call` when first polled;
+    /// a capability that waits on I/O can return one that yields while it waits.
+    fn call_async(self: Arc<Self>, args: Vec<Value>) -> ExternFuture {
+        Box::pin(async move { self.call(args) })
+    }
+}
+
+/// A shared capability is one too, so a host can register it in many runs
+/// (see Interpreter::with_capability)
+impl<T: ExternCapability + ?Sized> ExternCapability for Arc<T> {
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+
+    fn call(&self, args: Vec<Value>) -> ExternResult {
+        self.as_ref().call(args)
+    }
+
+    fn call_with(&self, args: Vec<Value>, host: &mut dyn Host) -> ExternResult {
+        self.as_ref().call_with(args, host)
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        self.as_ref().signature()
+    }
+
+    fn version(&self) -> Version {
+        self.as_ref().version()
+    }
+
+    fn call_async(self: Arc<Self>, args: Vec<Value>) -> ExternFuture {
+        T::call_async(Arc::clone(&self), args)
+    }
+}
+
+/// The arguments of a capability: how many, and the kind of each
+///
+///   Signature::new(&[Some(KindValue::STRING), Some(KindValue::STRING)])   fs:write(path, text)
+///   Signature::new(&[None]).optional(&[Some(KindValue::INTEGER)])         json:stringify(value[, indent])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Kind of each argument in order (None: any value)
+    params: Vec<Option<KindValue>>,
+    /// How many of the params every call passes; the rest may be left off
+    required: usize,
+}
+
+impl Signature {
+    /// Arguments of these kinds, all required
+    pub fn new(params: &[Option<KindValue>]) -> Self {
+        Self { params: params.to_vec(), required: params.len() }
+    }
+
+    /// Followed by optional arguments of these kinds
+    pub fn optional(mut self, params: &[Option<KindValue>]) -> Self {
+        self.params.extend_from_slice(params);
+        self
+    }
+
+    /// Check a call's arguments ("expected STRING, got INTEGER at arg 2")
+    pub fn check(&self, args: &[Value]) -> Result<(), String> {
+        if args.len() < self.required || args.len() > self.params.len() {
+            let count = match (self.required, self.params.len()) {
+                (1, 1) => "1 argument".to_string(),
+                (low, high) if low == high => format!("{} arguments", low),
+                (low, high) => format!("{} to {} arguments", low, high),
+            };
+            return Err(format!("expected {}, got {}", count, args.len()));
+        }
+        for (i, (arg, kind)) in args.iter().zip(&self.params).enumerate() {
+            let Some(kind) = kind else { continue };
+            let expected = LumenKind::new(*kind).as_display_string();
+            let got = kind_name(arg);
+            if got != expected {
+                return Err(format!("expected {}, got {} at arg {}", expected, got, i + 1));
+            }
+        }
+        Ok(())
+    }
 }
 
-/// Global capability registry.
-/// Maps (backend_name_option, capability_name) pairs to implementations.
+/// Registry key: (backend_name_option, capability_name)
+type CapabilityKey = (Option<String>, String);
+
+/// Capability registry of one run (see lumen/state.rs).
+/// Maps (backend_name_option, capability_name) pairs to implementations, one per version.
+/// A selector clause resolves to the latest version that satisfies its "@version"; one
+/// with a wildcard backend ("fs*") to the first matching backend, by name, that has one.
+///
+/// Mock layers sit on top of the real capabilities. Each layer maps a key to
+/// the name of a Lumen function; the topmost layer that knows a key shadows
+/// every layer below it and the real implementation, whatever its version. Tests push a layer,
+/// register mocks into it, and pop it when done.
+///
+/// A capability the run's policy (--allow-BACKEND, --deny-all) denies stays registered
+/// but does not resolve, as if it were missing. With an audit log (--audit), call_extern
+/// records each call made through the registry. Calls of a capability with a timeout (the
+/// host's, or one the program set with extern_timeout) stop waiting at it.
 pub struct CapabilityRegistry {
-    capabilities: HashMap<(Option<String>, String), Box<dyn ExternCapability>>,
+    /// The versions of each capability, oldest first
+    capabilities: HashMap<CapabilityKey, Vec<Arc<dyn ExternCapability>>>,
+    mock_layers: Vec<HashMap<CapabilityKey, String>>,
+    policy: ExternPolicy,
+    audit: Option<ExternAudit>,
+    /// The host's timeouts and the program's (see extern_timeout.rs)
+    timeouts: ExternTimeouts,
+    program_timeouts: ExternTimeouts,
 }
 
 impl CapabilityRegistry {
     pub fn new() -> Self {
         Self {
             capabilities: HashMap::new(),
+            mock_layers: Vec::new(),
+            policy: ExternPolicy::default(),
+            audit: None,
+            timeouts: ExternTimeouts::default(),
+            program_timeouts: ExternTimeouts::default(),
         }
     }
 
     /// Register a capability with an optional backend.
     /// If backend is None, this is the default implementation.
+    /// It replaces a capability of the same name and version, next to other versions.
     pub fn register(
         &mut self,
         backend: Option<&str>,
         cap: Box<dyn ExternCapability>,
     ) {
         let key = (backend.map(|s| s.to_string()), cap.name().to_string());
-        self.capabilities.insert(key, cap);
+        let versions = self.capabilities.entry(key).or_default();
+        let version = cap.version();
+        versions.retain(|other| other.version() != version);
+        versions.push(Arc::from(cap));
+        versions.sort_by_key(|cap| cap.version());
+    }
+
+    /// Limit the capabilities that resolve to those `policy` allows
+    pub fn set_policy(&mut self, policy: ExternPolicy) {
+        self.policy = policy;
+    }
+
+    /// Log the calls made through the registry to `audit` (None: do not log them)
+    pub fn set_audit(&mut self, audit: Option<ExternAudit>) {
+        self.audit = audit;
+    }
+
+    /// Where calls are logged, if anywhere
+    pub fn audit(&self) -> Option<&ExternAudit> {
+        self.audit.as_ref()
+    }
+
+    /// Stop waiting for calls at the host's `timeouts` (the program cannot lengthen them)
+    pub fn set_timeouts(&mut self, timeouts: ExternTimeouts) {
+        self.timeouts = timeouts;
     }
 
-    /// Resolve a capability by (backend_option, capability_name).
-    /// Returns the implementation if found, otherwise an error.
-    pub fn resolve(
-        &self,
-        backend: &Option<String>,
-        capability: &str,
-    ) -> Option<&(dyn ExternCapability)> {
-        self.capabilities
-            .get(&(backend.clone(), capability.to_string()))
-            .map(|b| b.as_ref())
+    /// The program's timeouts (extern_timeout), which shorten the host's
+    pub fn program_timeouts(&mut self) -> &mut ExternTimeouts {
+        &mut self.program_timeouts
     }
 
-    /// Check if a capability is registered with an optional backend.
-    pub fn has(&self, backend: &Option<String>, capability: &str) -> bool {
-        self.capabilities
-            .contains_key(&(backend.clone(), capability.to_string()))
+    /// Resolve a selector clause to a capability.
+    /// Returns the implementation if found and allowed.
+    pub fn resolve(&self, clause: &SelectorClause) -> Option<&(dyn ExternCapability)> {
+        self.resolve_shared(clause).map(|b| b.as_ref())
+    }
+
+    /// `resolve`, for a caller that keeps the capability past the registry (extern_async)
+    pub fn resolve_shared(&self, clause: &SelectorClause) -> Option<&Arc<dyn ExternCapability>> {
+        self.resolve_timed(clause).map(|(cap, _)| cap)
+    }
+
+    /// `resolve_shared`, with how long a call of the capability may take
+    pub fn resolve_timed(&self, clause: &SelectorClause) -> Option<(&Arc<dyn ExternCapability>, Option<Duration>)> {
+        let (backend, cap) = self
+            .matching(clause)
+            .into_iter()
+            .find(|(backend, _)| self.policy.allows(*backend, &clause.capability))?;
+        Some((cap, self.timeouts.with(&self.program_timeouts, backend, &clause.capability)))
+    }
+
+    /// Check if a selector clause resolves to a capability (registered and allowed).
+    pub fn has(&self, clause: &SelectorClause) -> bool {
+        self.resolve(clause).is_some()
+    }
+
+    /// The backend of a capability the clause names that is registered but denied by the
+    /// policy, if none is allowed.
+    pub fn denied_backend(&self, clause: &SelectorClause) -> Option<&str> {
+        if self.has(clause) {
+            return None;
+        }
+        self.matching(clause).into_iter().find_map(|(backend, _)| backend)
+    }
+
+    /// The backends a clause names that have a capability satisfying it, by backend name,
+    /// each with the latest version that does
+    fn matching(&self, clause: &SelectorClause) -> Vec<(Option<&str>, &Arc<dyn ExternCapability>)> {
+        let mut keys: Vec<&CapabilityKey> = self
+            .capabilities
+            .keys()
+            .filter(|(backend, capability)| *capability == clause.capability && clause.matches_backend(backend.as_deref()))
+            .collect();
+        keys.sort();
+        keys.into_iter()
+            .filter_map(|key| {
+                let latest = self.capabilities[key]
+                    .iter()
+                    .rev()
+                    .find(|cap| clause.version.is_none_or(|wanted| cap.version().satisfies(&wanted)))?;
+                Some((key.0.as_deref(), latest))
+            })
+            .collect()
+    }
+
+    /// List registered capabilities as selector strings ("cap" or "backend:cap"), sorted.
+    pub fn selectors(&self) -> Vec<String> {
+        let mut selectors: Vec<String> = self
+            .capabilities
+            .keys()
+            .filter(|(backend, capability)| self.policy.allows(backend.as_deref(), capability))
+            .map(|(backend, capability)| match backend {
+                Some(b) => format!("{}:{}", b, capability),
+                None => capability.clone(),
+            })
+            .collect();
+        selectors.sort();
+        selectors
+    }
+
+    /// Start a new mock layer shadowing everything registered so far.
+    pub fn push_mock_layer(&mut self) {
+        self.mock_layers.push(HashMap::new());
+    }
+
+    /// Discard the topmost mock layer. Returns false if there was none.
+    pub fn pop_mock_layer(&mut self) -> bool {
+        self.mock_layers.pop().is_some()
+    }
+
+    /// Register a Lumen function as a mock in the topmost layer
+    /// (a layer is created if none is active).
+    pub fn mock(&mut self, backend: Option<&str>, capability: &str, handler: &str) {
+        if self.mock_layers.is_empty() {
+            self.push_mock_layer();
+        }
+        let key = (backend.map(|s| s.to_string()), capability.to_string());
+        if let Some(layer) = self.mock_layers.last_mut() {
+            layer.insert(key, handler.to_string());
+        }
+    }
+
+    /// Resolve a mock handler for a selector clause, searching from the top layer down.
+    /// A mock for the clause's backend (or pattern) as written wins; a wildcard clause also
+    /// finds mocks of the backends it matches, the first by name.
+    pub fn resolve_mock(&self, clause: &SelectorClause) -> Option<&str> {
+        let key = (clause.backend.clone(), clause.capability.clone());
+        self.mock_layers
+            .iter()
+            .rev()
+            .find_map(|layer| {
+                layer.get(&key).or_else(|| {
+                    let mut matches: Vec<_> = layer
+                        .iter()
+                        .filter(|((backend, capability), _)| {
+                            clause.is_pattern() && *capability == clause.capability && clause.matches_backend(backend.as_deref())
+                        })
+                        .collect();
+                    matches.sort_by(|a, b| a.0.cmp(b.0));
+                    matches.first().map(|(_, handler)| *handler)
+                })
+            })
+            .map(|h| h.as_str())
     }
 }
 
@@ -70,3 +352,100 @@ impl Default for CapabilityRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::lumen::extern_system::selector::parse_selector;
+
+    fn clause(selector: &str) -> SelectorClause {
+        parse_selector(selector).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_mock_shadows_lower_layers() {
+        let mut registry = CapabilityRegistry::new();
+        let fs = clause("fs:read");
+        registry.push_mock_layer();
+        registry.mock(Some("fs"), "read", "outer");
+        registry.push_mock_layer();
+        registry.mock(Some("fs"), "read", "inner");
+        assert_eq!(registry.resolve_mock(&fs), Some("inner"));
+        assert_eq!(registry.resolve_mock(&clause("fs@2:read")), Some("inner"));
+        assert_eq!(registry.resolve_mock(&clause("f*:read")), Some("inner"));
+
+        assert!(registry.pop_mock_layer());
+        assert_eq!(registry.resolve_mock(&fs), Some("outer"));
+        assert!(registry.pop_mock_layer());
+        assert_eq!(registry.resolve_mock(&fs), None);
+        assert!(!registry.pop_mock_layer());
+    }
+
+    #[test]
+    fn test_signature_checks_arity_and_kinds() {
+        use crate::languages::lumen::values::{LumenNumber, LumenString};
+
+        let text = || Box::new(LumenString::new("x".to_string())) as Value;
+        let number = || Box::new(LumenNumber::new(num_bigint::BigInt::from(2))) as Value;
+        let write = Signature::new(&[Some(KindValue::STRING), Some(KindValue::STRING)]);
+        assert_eq!(write.check(&[text(), text()]), Ok(()));
+        assert_eq!(write.check(&[text(), number()]), Err("expected STRING, got INTEGER at arg 2".to_string()));
+        assert_eq!(write.check(&[text()]), Err("expected 2 arguments, got 1".to_string()));
+
+        let stringify = Signature::new(&[None]).optional(&[Some(KindValue::INTEGER)]);
+        assert_eq!(stringify.check(&[number()]), Ok(()));
+        assert_eq!(stringify.check(&[text(), number()]), Ok(()));
+        assert_eq!(stringify.check(&[]), Err("expected 1 to 2 arguments, got 0".to_string()));
+        assert_eq!(Signature::new(&[None]).check(&[]), Err("expected 1 argument, got 0".to_string()));
+    }
+
+    #[test]
+    fn test_mock_creates_layer_when_none_active() {
+        let mut registry = CapabilityRegistry::new();
+        registry.mock(None, "print_native", "quiet");
+        assert_eq!(registry.resolve_mock(&clause("print_native")), Some("quiet"));
+        assert_eq!(registry.resolve_mock(&clause("fs:print_native")), None);
+    }
+
+    /// A capability that names its version
+    struct Read(Version);
+
+    impl ExternCapability for Read {
+        fn name(&self) -> &'static str {
+            "read"
+        }
+
+        fn call(&self, _args: Vec<Value>) -> ExternResult {
+            Ok(Box::new(crate::languages::lumen::values::LumenString::new(self.0.to_string())))
+        }
+
+        fn version(&self) -> Version {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_versions_and_wildcards_resolve() {
+        let mut registry = CapabilityRegistry::new();
+        for (backend, major, minor) in [("fs", 1, 0), ("fs", 2, 0), ("fs", 2, 3), ("fs_mem", 1, 0)] {
+            registry.register(Some(backend), Box::new(Read(Version::new(major, minor))));
+        }
+        let version = |registry: &CapabilityRegistry, selector: &str| {
+            registry.resolve(&clause(selector)).map(|cap| cap.version().to_string())
+        };
+        assert_eq!(version(&registry, "fs:read").as_deref(), Some("2.3"));
+        assert_eq!(version(&registry, "fs@1:read").as_deref(), Some("1.0"));
+        assert_eq!(version(&registry, "fs@2.1:read").as_deref(), Some("2.3"));
+        assert_eq!(version(&registry, "fs@3:read"), None);
+        assert_eq!(version(&registry, "fs_*:read").as_deref(), Some("1.0"));
+        assert_eq!(version(&registry, "*@2:read").as_deref(), Some("2.3"));
+        assert_eq!(registry.selectors(), vec!["fs:read", "fs_mem:read"]);
+
+        let mut policy = ExternPolicy::default();
+        policy.apply_flag("--allow-fs_mem").unwrap().unwrap();
+        registry.set_policy(policy);
+        assert_eq!(version(&registry, "fs*:read").as_deref(), Some("1.0"));
+        assert_eq!(registry.denied_backend(&clause("fs@2:read")), Some("fs"));
+        assert_eq!(registry.denied_backend(&clause("fs*:read")), None);
+    }
+}
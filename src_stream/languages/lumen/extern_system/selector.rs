@@ -10,7 +10,9 @@
 // Grammar:
 //   selector ::= capability | backend ":" capability
 //              | backend-list ":" capability
-//   backend ::= word
+//   backend ::= pattern [ "@" version ]
+//   pattern ::= word, where "*" stands for any run of characters
+//   version ::= digits [ "." digits ]
 //   backend-list ::= backend ( "|" backend )*
 //                  | "(" backend-list ")"
 //   capability ::= word
@@ -20,19 +22,98 @@
 //   "fs:open"          (fs backend, open capability)
 //   "fs|mem:read"      (try fs then mem backend, read capability)
 //   "(fs:impl1)|(impl2)"  (complex fallback: fs:impl1 OR impl2)
+//   "fs*:read"         (any backend whose name starts with fs; the first by name wins)
+//   "fs@2:read"        (fs backend at version 2.x; "fs@2.1" is 2.1 or a later 2.x)
+//
+// Capabilities carry a version (ExternCapability::version, 1.0 by default), and a backend
+// may register several versions of one. A clause without "@" takes the latest, so a
+// script can pin the version it was written against while newer ones are added.
+
+use std::fmt;
+
+/// A capability version, major.minor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Whether a capability at this version satisfies `wanted` (same major, minor at least)
+    pub fn satisfies(&self, wanted: &Version) -> bool {
+        self.major == wanted.major && self.minor >= wanted.minor
+    }
+
+    fn parse(text: &str) -> Option<Version> {
+        let (major, minor) = text.split_once('.').unwrap_or((text, "0"));
+        let digits = |s: &str| (!s.is_empty() && s.chars().all(|c| c.is_ascii_digit())).then(|| s.parse().ok()).flatten();
+        Some(Version::new(digits(major)?, digits(minor)?))
+    }
+}
 
-use crate::kernel::registry::LumenResult;
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
 
 /// A selector clause: try to resolve (backend, capability) pair
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SelectorClause {
+    /// The backend, or a pattern with "*" matching several
     pub backend: Option<String>,
+    /// The version the backend's capability must satisfy (None: the latest)
+    pub version: Option<Version>,
     pub capability: String,
 }
 
+impl SelectorClause {
+    /// Whether the clause names backend `backend` (or matches it, with a wildcard)
+    pub fn matches_backend(&self, backend: Option<&str>) -> bool {
+        match (self.backend.as_deref(), backend) {
+            (Some(pattern), Some(backend)) => glob(pattern, backend),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the backend part has a wildcard
+    pub fn is_pattern(&self) -> bool {
+        self.backend.as_deref().is_some_and(|b| b.contains('*'))
+    }
+}
+
+impl fmt::Display for SelectorClause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(backend) = &self.backend {
+            write!(f, "{}", backend)?;
+            if let Some(version) = &self.version {
+                write!(f, "@{}", version)?;
+            }
+            write!(f, ":")?;
+        }
+        write!(f, "{}", self.capability)
+    }
+}
+
+/// Whether `name` matches `pattern`, where "*" stands for any run of characters
+fn glob(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((head, rest)) => {
+            let Some(tail) = name.strip_prefix(head) else { return false };
+            (0..=tail.len()).filter(|&i| tail.is_char_boundary(i)).any(|i| glob(rest, &tail[i..]))
+        }
+    }
+}
+
 /// Parse a selector string into ordered list of resolution clauses
 /// Returns Vec of (backend_option, capability) pairs to try in order
-pub fn parse_selector(input: &str) -> LumenResult<Vec<SelectorClause>> {
+pub fn parse_selector(input: &str) -> Result<Vec<SelectorClause>, String> {
     let mut input = input.trim();
 
     if input.is_empty() {
@@ -75,6 +156,7 @@ pub fn parse_selector(input: &str) -> LumenResult<Vec<SelectorClause>> {
         // No backend specified - try default
         Ok(vec![SelectorClause {
             backend: None,
+            version: None,
             capability,
         }])
     } else {
@@ -83,8 +165,9 @@ pub fn parse_selector(input: &str) -> LumenResult<Vec<SelectorClause>> {
 
         Ok(backends
             .into_iter()
-            .map(|backend| SelectorClause {
+            .map(|(backend, version)| SelectorClause {
                 backend: Some(backend),
+                version,
                 capability: capability.clone(),
             })
             .collect())
@@ -163,8 +246,8 @@ fn split_top_level(input: &str, separator: char) -> Option<Vec<String>> {
     }
 }
 
-/// Parse a backend list: "fs|mem" or "(fs|mem)" or complex nesting
-fn parse_backend_list(input: &str) -> LumenResult<Vec<String>> {
+/// Parse a backend list: "fs|mem" or "(fs|mem)" or complex nesting, each backend with its version
+fn parse_backend_list(input: &str) -> Result<Vec<(String, Option<Version>)>, String> {
     let input = input.trim();
 
     if input.is_empty() {
@@ -225,13 +308,24 @@ fn parse_backend_list(input: &str) -> LumenResult<Vec<String>> {
         })
         .collect();
 
-    for backend in &backends {
-        if !is_valid_name(backend) {
-            return Err(format!("Invalid backend name: '{}'", backend));
-        }
-    }
-
-    Ok(backends)
+    backends
+        .into_iter()
+        .map(|backend| {
+            let (name, version) = match backend.split_once('@') {
+                Some((name, version)) => {
+                    let parsed = Version::parse(version).ok_or_else(|| format!("Invalid backend version: '{}'", backend))?;
+                    (name, Some(parsed))
+                }
+                None => (backend.as_str(), None),
+            };
+            // A wildcard stands for word characters, so the rest must still make a word
+            if is_valid_name(&name.replace('*', "_")) {
+                Ok((name.to_string(), version))
+            } else {
+                Err(format!("Invalid backend name: '{}'", name))
+            }
+        })
+        .collect()
 }
 
 /// Check if a string is a valid identifier (word)
@@ -256,6 +350,7 @@ mod tests {
             result,
             vec![SelectorClause {
                 backend: None,
+                version: None,
                 capability: "print_native".into()
             }]
         );
@@ -268,6 +363,7 @@ mod tests {
             result,
             vec![SelectorClause {
                 backend: Some("fs".into()),
+                version: None,
                 capability: "open".into()
             }]
         );
@@ -298,4 +394,21 @@ mod tests {
     fn test_invalid_bad_backend() {
         assert!(parse_selector("123bad:open").is_err());
     }
+
+    #[test]
+    fn test_parse_wildcards_and_versions() {
+        let result = parse_selector("fs@2|mem*:read").unwrap();
+        assert_eq!((result[0].backend.as_deref(), result[0].version), (Some("fs"), Some(Version::new(2, 0))));
+        assert_eq!((result[1].backend.as_deref(), result[1].version), (Some("mem*"), None));
+        assert_eq!(parse_selector("fs@2.1:read").unwrap()[0].to_string(), "fs@2.1:read");
+        assert!(parse_selector("fs@:read").is_err());
+        assert!(parse_selector("fs@two:read").is_err());
+        assert!(parse_selector("*:read").is_ok());
+
+        let clause = &parse_selector("fs*:read").unwrap()[0];
+        assert!(clause.matches_backend(Some("fs")) && clause.matches_backend(Some("fs_mem")));
+        assert!(!clause.matches_backend(Some("mem")) && !clause.matches_backend(None));
+        assert!(Version::new(2, 3).satisfies(&Version::new(2, 1)));
+        assert!(!Version::new(3, 0).satisfies(&Version::new(2, 0)) && !Version::new(2, 0).satisfies(&Version::new(2, 1)));
+    }
 }
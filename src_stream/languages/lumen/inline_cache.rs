@@ -0,0 +1,116 @@
+// Inline caches: what an operator or call site found out the last time it ran
+//
+// A site inside a loop sees the same kinds of operands, and calls the same function, on
+// every iteration, so it keeps what it resolved the first time instead of working it out
+// again:
+//
+//   OperandCache   an arithmetic or comparison site whose operands have been integers
+//                  goes straight to integer arithmetic, without trying the real and
+//                  rational representations first. The first operands of another kind
+//                  turn the site over to the general path for good.
+//   CallCache      a call site keeps the parameters and body of the function it called,
+//                  with the run it called it in (State::run), and only looks the name up
+//                  again in another run.
+//
+// Nodes are shared between threads (ExprNode is Send + Sync), so the caches are atomics
+// and a mutex rather than cells.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::kernel::ast::StmtNode;
+use crate::kernel::runtime::Env;
+use crate::languages::lumen::state::State;
+use crate::languages::lumen::statements::functions::{self, Body};
+
+const UNSEEN: u8 = 0;
+const INTEGERS: u8 = 1;
+const GENERAL: u8 = 2;
+
+/// The kinds of operands an operator site has seen
+#[derive(Debug)]
+pub struct OperandCache(AtomicU8);
+
+impl OperandCache {
+    /// A cache for a site whose operator has an integer fast path
+    pub fn new() -> Self {
+        OperandCache(AtomicU8::new(UNSEEN))
+    }
+
+    /// A cache for a site that always takes the general path
+    pub fn general() -> Self {
+        OperandCache(AtomicU8::new(GENERAL))
+    }
+
+    /// Whether to try the integer path for these operands: true until they are not both
+    /// integers, then false from then on
+    pub fn integers(&self, both_integers: impl FnOnce() -> bool) -> bool {
+        match self.0.load(Ordering::Relaxed) {
+            GENERAL => false,
+            seen => {
+                let integers = both_integers();
+                if seen == UNSEEN || !integers {
+                    self.0.store(if integers { INTEGERS } else { GENERAL }, Ordering::Relaxed);
+                }
+                integers
+            }
+        }
+    }
+}
+
+impl Default for OperandCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The function a call site called, in the run it called it in
+/// The body is held weakly: a call inside a function's own body must not keep it alive.
+#[derive(Debug, Default)]
+pub struct CallCache(Mutex<Option<Resolved>>);
+
+/// Run, parameters and body
+type Resolved = (u64, Arc<[String]>, Weak<Vec<Box<dyn StmtNode>>>);
+
+impl CallCache {
+    /// The parameters and body of the user-defined function `name` in the run of `env`
+    pub fn resolve(&self, env: &mut Env, name: &str) -> Option<(Arc<[String]>, Body)> {
+        let run = State::of(env).run;
+        let mut cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((cached_run, params, body)) = cached.as_ref() {
+            if let (true, Some(body)) = (*cached_run == run, body.upgrade()) {
+                return Some((Arc::clone(params), body));
+            }
+        }
+
+        let (params, body) = functions::get_function(env, name)?;
+        let params: Arc<[String]> = params.into();
+        *cached = Some((run, Arc::clone(&params), Arc::downgrade(&body)));
+        Some((params, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_the_integer_path_for_good() {
+        let cache = OperandCache::new();
+        assert!(cache.integers(|| true));
+        assert!(cache.integers(|| true));
+        assert!(!cache.integers(|| false));
+        // Integers again do not bring the fast path back
+        assert!(!cache.integers(|| panic!("a general site does not look at its operands")));
+        assert!(!OperandCache::general().integers(|| true));
+    }
+
+    #[test]
+    fn sites_give_the_same_values_after_their_operands_change_kind() {
+        use crate::interpreter::Interpreter;
+        let source = "fn add(a, b)\n    return a + b\nfn less(a, b)\n    return a < b\n\
+                      [add(1, 2), less(1, 2), add(1/2, 1), less(1/2, 1), add(\"a\", \"b\"), add(2, 3)]\n";
+        let value = Interpreter::new().without_prelude().eval(source).unwrap().unwrap();
+        assert_eq!(value.to_string(), "[3, true, 3/2, true, ab, 5]");
+    }
+}
@@ -0,0 +1,336 @@
+// Ball arithmetic for REAL values: the --intervals mode of the stream kernel
+//
+// Under --intervals (Interpreter::with_intervals) every REAL carries a radius next to its
+// value: a bound on how far the value may be from the exact result of the computation
+// that made it. Literals and real() start the bound (what rounding to the precision lost),
+// and every operation adds what its operands' errors can do to its result and what its
+// own rounding loses:
+//
+//   a + b, a - b   ra + rb
+//   a * b          |a| rb + |b| ra + ra rb
+//   a / b          (|a| rb + |b| ra) / (|b| (|b| - rb))      (an error if b's ball holds 0)
+//   a ** n         (|a| + ra)^n - |a|^n
+//   exp, ln, atan  the input error through a bound on the derivative, plus one unit in
+//                  the last digit of the result (see lib_lumen/transcendental.rs)
+//
+// An integer part (int, %, // and ** exponents) is taken only when every value in the
+// ball has the same one; otherwise the program stops with an error instead of guessing.
+// Comparisons look at the values alone. A REAL prints with its certified digits only:
+// the most digits, up to its precision, whose printed value is within one unit in the
+// last digit of every value in its ball (trailing zeros included, since they are
+// certified too). A REAL whose bound is zero is exact and prints as it does without
+// --intervals.
+//
+// Radii are kept rounded up to RADIUS_DIGITS significant digits, so they stay small
+// however long a computation runs, and only ever get looser.
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+use crate::languages::lumen::numeric::bigfloat::BigFloat;
+
+/// Significant digits a radius is rounded up to
+const RADIUS_DIGITS: u32 = 10;
+
+/// An upper bound on the error of a REAL: num/den, at least zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct Radius {
+    num: BigInt,
+    den: BigInt,
+}
+
+impl Radius {
+    pub fn zero() -> Self {
+        Radius { num: BigInt::zero(), den: BigInt::one() }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num.is_zero()
+    }
+
+    /// The smallest radius of RADIUS_DIGITS digits that is at least |num/den|
+    fn at_least(num: &BigInt, den: &BigInt) -> Self {
+        if num.is_zero() {
+            return Radius::zero();
+        }
+        let (num, den) = (num.abs(), den.abs());
+        let (low, high) = (pow10(RADIUS_DIGITS - 1), pow10(RADIUS_DIGITS));
+        let mut exponent = digits(&num) - digits(&den) - RADIUS_DIGITS as i64 + 1;
+        loop {
+            let (scaled_num, scaled_den) = scale(&num, &den, exponent);
+            let mantissa = (&scaled_num + &scaled_den - 1) / &scaled_den;
+            if mantissa >= high {
+                exponent += 1;
+            } else if mantissa < low {
+                exponent -= 1;
+            } else {
+                return Radius::from_scaled(mantissa, exponent);
+            }
+        }
+    }
+
+    /// mantissa * 10^exponent
+    fn from_scaled(mantissa: BigInt, exponent: i64) -> Self {
+        if exponent >= 0 {
+            Radius { num: mantissa * pow10(exponent as u32), den: BigInt::one() }
+        } else {
+            Radius { num: mantissa, den: pow10((-exponent) as u32) }
+        }
+    }
+
+    fn rational(&self) -> Rational {
+        Rational::new(self.num.clone(), self.den.clone())
+    }
+}
+
+/// The error of a value rounded from num/den to `rounded`, when num/den already had `error`
+pub fn after_rounding(error: &Radius, exact: (&BigInt, &BigInt), rounded: (&BigInt, &BigInt)) -> Radius {
+    let lost = Rational::new(exact.0.clone(), exact.1.clone()).sub(&Rational::new(rounded.0.clone(), rounded.1.clone()));
+    error.rational().add(&lost.abs()).upper()
+}
+
+/// The error the exact result of `a op b` has, for + - * /, from the errors of a and b
+pub fn propagate(op: &str, a: (&BigInt, &BigInt), ra: &Radius, b: (&BigInt, &BigInt), rb: &Radius) -> Result<Radius, String> {
+    let (a, ra, b, rb) = (Rational::new(a.0.clone(), a.1.clone()).abs(), ra.rational(), Rational::new(b.0.clone(), b.1.clone()).abs(), rb.rational());
+    let error = match op {
+        "+" | "-" => ra.add(&rb),
+        "*" => a.mul(&rb).add(&b.mul(&ra)).add(&ra.mul(&rb)),
+        "/" => {
+            let smallest = b.sub(&rb);
+            if !smallest.num.is_positive() {
+                return Err("Division by an interval that contains zero".to_string());
+            }
+            a.mul(&rb).add(&b.mul(&ra)).div(&b.mul(&smallest))
+        }
+        _ => return Err(format!("No interval bound for operator '{}'", op)),
+    };
+    Ok(error.upper())
+}
+
+/// The error the exact a^n has, from the error of a
+pub fn power(a: (&BigInt, &BigInt), ra: &Radius, n: u32) -> Radius {
+    let a = Rational::new(a.0.clone(), a.1.clone()).abs();
+    a.add(&ra.rational()).pow(n).sub(&a.pow(n)).upper()
+}
+
+/// The integer part (toward zero) of every value within `radius` of num/den
+pub fn integer_part(value: (&BigInt, &BigInt), radius: &Radius) -> Result<BigInt, String> {
+    let value = Rational::new(value.0.clone(), value.1.clone());
+    let radius = radius.rational();
+    let (low, high) = (value.sub(&radius), value.add(&radius));
+    let (low, high) = (&low.num / &low.den, &high.num / &high.den);
+    if low != high {
+        return Err(format!("The integer part of an interval is uncertain: it is between {} and {}", low, high));
+    }
+    Ok(low)
+}
+
+/// The error of exp, ln or atan rounded to `result` at `precision` digits, for an input
+/// num/den with error `radius`
+pub fn function_error(name: &str, x: (&BigInt, &BigInt), radius: &Radius, result: (&BigInt, &BigInt), precision: usize) -> Result<Radius, String> {
+    let (x, r) = (Rational::new(x.0.clone(), x.1.clone()), radius.rational());
+    let ulp = ulp(result, precision);
+    let spread = match name {
+        // exp(x + r) - exp(x) <= exp(x) (e^r - 1) <= exp(x) r / (1 - r) for r < 1
+        "exp" => {
+            let below_one = Rational::new(BigInt::one(), BigInt::one()).sub(&r);
+            if !below_one.num.is_positive() {
+                return Err("exp() of an interval wider than 1 is not bounded".to_string());
+            }
+            let largest = Rational::new(result.0.clone(), result.1.clone()).abs().add(&ulp);
+            largest.mul(&r).div(&below_one)
+        }
+        // |ln X - ln x| <= r / (x - r)
+        "ln" => {
+            let smallest = x.sub(&r);
+            if !smallest.num.is_positive() {
+                return Err("ln() of an interval that reaches zero or below".to_string());
+            }
+            r.div(&smallest)
+        }
+        // |atan'| <= 1
+        "atan" => r,
+        _ => return Err(format!("No interval bound for '{}'", name)),
+    };
+    Ok(spread.add(&ulp).upper())
+}
+
+/// The error of `result` as log(x) / log(base): `ln_x` and `ln_base` (with their errors)
+/// bound the logarithms, and `result` is compared with their quotient
+pub fn quotient_error(
+    result: (&BigInt, &BigInt),
+    ln_x: (&BigInt, &BigInt),
+    ln_x_error: &Radius,
+    ln_base: (&BigInt, &BigInt),
+    ln_base_error: &Radius,
+) -> Result<Radius, String> {
+    let spread = propagate("/", ln_x, ln_x_error, ln_base, ln_base_error)?;
+    let quotient = Rational::new(ln_x.0.clone(), ln_x.1.clone()).div(&Rational::new(ln_base.0.clone(), ln_base.1.clone()));
+    Ok(after_rounding(&spread, (&quotient.num, &quotient.den), result))
+}
+
+/// num/den with only the digits `radius` leaves certain, at most `precision` of them
+pub fn certified_string(value: (&BigInt, &BigInt), radius: &Radius, precision: usize) -> String {
+    if radius.is_zero() {
+        return BigFloat::round(value.0, value.1, precision).to_decimal_string();
+    }
+    let exact = Rational::new(value.0.clone(), value.1.clone());
+    let radius = radius.rational();
+
+    // Start at the last digit the precision allows, and give up digits until the
+    // printed value is within one unit of its last digit of the whole ball
+    let leading = if exact.num.is_zero() { &radius } else { &exact };
+    let mut exponent = last_digit((&leading.num, &leading.den), precision);
+    loop {
+        let (num, den) = scale(&exact.num.abs(), &exact.den, exponent);
+        let mantissa: BigInt = (num * 2 + &den) / (den * 2);
+        let printed = Rational::new(if exact.num.is_negative() { -&mantissa } else { mantissa.clone() }, BigInt::one())
+            .mul(&power_of_ten(exponent));
+        if printed.sub(&exact).abs().add(&radius).le(&power_of_ten(exponent)) {
+            return positional(exact.num.is_negative() && !mantissa.is_zero(), &mantissa, exponent);
+        }
+        exponent += 1;
+    }
+}
+
+/// The unit in the last of `precision` digits of num/den (zero for zero)
+fn ulp(value: (&BigInt, &BigInt), precision: usize) -> Rational {
+    if value.0.is_zero() {
+        return Rational::new(BigInt::zero(), BigInt::one());
+    }
+    power_of_ten(last_digit(value, precision))
+}
+
+/// The power of ten of the last of `precision` significant digits of num/den (not zero)
+fn last_digit(value: (&BigInt, &BigInt), precision: usize) -> i64 {
+    let rounded = BigFloat::round(value.0, value.1, precision);
+    let leading = digits(&rounded.mantissa.abs()) - 1 + rounded.exponent;
+    leading + 1 - precision.max(1) as i64
+}
+
+/// mantissa * 10^exponent with every digit down to 10^exponent written out
+fn positional(negative: bool, mantissa: &BigInt, exponent: i64) -> String {
+    let sign = if negative { "-" } else { "" };
+    if exponent >= 0 {
+        return format!("{}{}", sign, mantissa * pow10(exponent as u32));
+    }
+    let fraction = (-exponent) as usize;
+    let digits = format!("{:0>width$}", mantissa.to_string(), width = fraction + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - fraction);
+    format!("{}{}.{}", sign, whole, fraction)
+}
+
+/// num/den over 10^exponent, as a fraction of integers (num and den are positive)
+fn scale(num: &BigInt, den: &BigInt, exponent: i64) -> (BigInt, BigInt) {
+    if exponent >= 0 {
+        (num.clone(), den * pow10(exponent as u32))
+    } else {
+        (num * pow10((-exponent) as u32), den.clone())
+    }
+}
+
+fn power_of_ten(exponent: i64) -> Rational {
+    let (num, den) = scale(&BigInt::one(), &BigInt::one(), -exponent);
+    Rational::new(num, den)
+}
+
+fn pow10(digits: u32) -> BigInt {
+    BigInt::from(10).pow(digits)
+}
+
+/// Number of decimal digits in v (v is positive)
+fn digits(v: &BigInt) -> i64 {
+    v.to_string().len() as i64
+}
+
+/// Exact arithmetic on the bounds (denominator positive, not reduced)
+#[derive(Debug, Clone)]
+struct Rational {
+    num: BigInt,
+    den: BigInt,
+}
+
+impl Rational {
+    fn new(num: BigInt, den: BigInt) -> Self {
+        if den.is_negative() {
+            Rational { num: -num, den: -den }
+        } else {
+            Rational { num, den }
+        }
+    }
+
+    fn add(&self, other: &Rational) -> Rational {
+        Rational::new(&self.num * &other.den + &other.num * &self.den, &self.den * &other.den)
+    }
+
+    fn sub(&self, other: &Rational) -> Rational {
+        Rational::new(&self.num * &other.den - &other.num * &self.den, &self.den * &other.den)
+    }
+
+    fn mul(&self, other: &Rational) -> Rational {
+        Rational::new(&self.num * &other.num, &self.den * &other.den)
+    }
+
+    fn div(&self, other: &Rational) -> Rational {
+        Rational::new(&self.num * &other.den, &self.den * &other.num)
+    }
+
+    fn pow(&self, n: u32) -> Rational {
+        Rational::new(self.num.pow(n), self.den.pow(n))
+    }
+
+    fn abs(&self) -> Rational {
+        Rational::new(self.num.abs(), self.den.clone())
+    }
+
+    fn le(&self, other: &Rational) -> bool {
+        &self.num * &other.den <= &other.num * &self.den
+    }
+
+    /// This (non-negative) bound as a radius, rounded up
+    fn upper(&self) -> Radius {
+        Radius::at_least(&self.num, &self.den)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(n: i64) -> BigInt {
+        BigInt::from(n)
+    }
+
+    #[test]
+    fn bounds_grow_with_each_operation_and_limit_the_printed_digits() {
+        // 1/3 rounded to 5 digits is 0.33333, off by 1/300000
+        let third = (int(33_333), int(100_000));
+        let error = after_rounding(&Radius::zero(), (&int(1), &int(3)), (&third.0, &third.1));
+        assert_eq!(error, Radius { num: int(3_333_333_334), den: int(1_000_000_000_000_000) });
+        assert_eq!(certified_string((&third.0, &third.1), &error, 5), "0.33333");
+
+        // Subtracting a nearby value cancels the digits but keeps the error
+        let error = propagate("-", (&third.0, &third.1), &error, (&int(33_332), &int(100_000)), &error).unwrap();
+        assert_eq!(certified_string((&int(1), &int(100_000)), &error, 5), "0.00001");
+        let error = propagate("*", (&int(1), &int(1)), &error, (&int(1), &int(1)), &Radius::from_scaled(int(4), -2)).unwrap();
+        assert_eq!(certified_string((&int(125), &int(100)), &error, 5), "1.3");
+        assert_eq!(certified_string((&int(-125), &int(100)), &error, 5), "-1.3");
+
+        assert!(propagate("/", (&int(1), &int(1)), &Radius::zero(), (&int(1), &int(100)), &Radius::from_scaled(int(1), -2)).is_err());
+        assert_eq!(integer_part((&int(25), &int(10)), &Radius::from_scaled(int(4), -1)), Ok(int(2)));
+        assert!(integer_part((&int(25), &int(10)), &Radius::from_scaled(int(6), -1)).is_err());
+    }
+
+    #[test]
+    fn programs_under_intervals_print_certified_digits() {
+        use crate::interpreter::Interpreter;
+        let source = "x = 1.0 / 3.0\n[x, x * 3.0 - 1.0, x * 3.0, real(1, 5), FEATURES[\"real_backend\"]]\n";
+        let value = Interpreter::new().with_intervals().eval(source).unwrap().unwrap();
+        assert_eq!(value.to_string(), "[0.333333333333333, 0.00000000000000, 1.00000000000000, 1, ball]");
+        let value = Interpreter::new().eval(source).unwrap().unwrap();
+        assert_eq!(value.to_string(), "[0.333333333333333, -0.000000000000001, 0.999999999999999, 1, bigfloat]");
+
+        let error = Interpreter::new().with_intervals().eval("int(1.0 / 3.0 * 3.0)\n").unwrap_err();
+        assert!(error.to_string().contains("integer part of an interval is uncertain"), "{}", error);
+    }
+}
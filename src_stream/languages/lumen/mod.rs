@@ -11,6 +11,14 @@ pub mod expressions;
 pub mod statements;
 pub mod structure;
 pub mod extern_system;
+pub mod control_flow;
+pub mod coverage;
+pub mod trace;
+pub mod warnings;
+pub mod system;
+pub mod state;
+pub mod inline_cache;
+pub mod interval;
 
 // The dispatcher module
 pub mod dispatcher {
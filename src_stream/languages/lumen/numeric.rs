@@ -215,27 +215,14 @@ pub fn power(a: &BigInt, b: &BigInt) -> LumenResult<BigInt> {
     Ok(a.pow(exp))
 }
 
-/// Negate a BigInt
-pub fn negate(a: &BigInt) -> LumenResult<BigInt> {
-    Ok(-a)
+/// Transcendental functions for REAL values (exp, ln, log, atan)
+/// The series code is shared with the microcode kernel.
+pub mod transcendental {
+    include!("../../../lib_lumen/transcendental.rs");
 }
 
-/// Compare less than
-pub fn compare_lt(a: &BigInt, b: &BigInt) -> LumenResult<bool> {
-    Ok(a < b)
-}
-
-/// Compare less than or equal
-pub fn compare_le(a: &BigInt, b: &BigInt) -> LumenResult<bool> {
-    Ok(a <= b)
-}
-
-/// Compare greater than
-pub fn compare_gt(a: &BigInt, b: &BigInt) -> LumenResult<bool> {
-    Ok(a > b)
-}
-
-/// Compare greater than or equal
-pub fn compare_ge(a: &BigInt, b: &BigInt) -> LumenResult<bool> {
-    Ok(a >= b)
+/// Correctly rounded decimal floating point for REAL values
+/// The rounding rule is shared with the microcode kernel.
+pub mod bigfloat {
+    include!("../../../lib_lumen/bigfloat.rs");
 }
@@ -4,9 +4,16 @@
 pub mod precedence;
 pub mod traits;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use crate::kernel::parser::Parser;
 use crate::kernel::registry::{TokenRegistry, LumenResult, err_at};
 use crate::languages::lumen::prelude::LumenParserExt;
+use crate::languages::lumen::statements::functions::FunctionDef;
+use crate::languages::lumen::coverage::CoverageSink;
+use crate::languages::lumen::trace::TraceConfig;
+use crate::languages::lumen::warnings::Warnings;
 
 pub use precedence::Precedence;
 pub use traits::{ExprPrefix, ExprInfix, StmtHandler};
@@ -19,6 +26,46 @@ pub struct Registry {
     prefixes: Vec<Box<dyn ExprPrefix>>,
     infixes: Vec<Box<dyn ExprInfix>>,
     stmts: Vec<Box<dyn StmtHandler>>,
+    prefix_keys: Keyed,
+    infix_keys: Keyed,
+    stmt_keys: Keyed,
+    /// Functions defined by the programs parsed so far (taken over by the run)
+    functions: RefCell<HashMap<String, FunctionDef>>,
+    /// Names of the user program, once warnings are enabled (see warnings.rs)
+    warnings: RefCell<Option<Warnings>>,
+    /// --trace: what to print for the statements parsed from now on (see trace.rs)
+    trace: Option<TraceConfig>,
+    /// --coverage: where the statements parsed from now on are counted (see coverage.rs)
+    coverage: Option<CoverageSink>,
+}
+
+/// Handler indices by the lexeme they start on
+/// Handlers that name no lexemes are in every list, so each list keeps registration order.
+#[derive(Default)]
+struct Keyed {
+    by_lexeme: HashMap<String, Vec<usize>>,
+    any: Vec<usize>,
+}
+
+impl Keyed {
+    fn add(&mut self, index: usize, lexemes: Vec<&str>) {
+        if lexemes.is_empty() {
+            self.any.push(index);
+            for indices in self.by_lexeme.values_mut() {
+                indices.push(index);
+            }
+            return;
+        }
+        for lexeme in lexemes {
+            let any = &self.any;
+            self.by_lexeme.entry(lexeme.to_string()).or_insert_with(|| any.clone()).push(index);
+        }
+    }
+
+    /// Indices of the handlers that may start on `lexeme`, in registration order
+    fn candidates(&self, lexeme: &str) -> &[usize] {
+        self.by_lexeme.get(lexeme).unwrap_or(&self.any)
+    }
 }
 
 impl Registry {
@@ -28,31 +75,76 @@ impl Registry {
             prefixes: Vec::new(),
             infixes: Vec::new(),
             stmts: Vec::new(),
+            prefix_keys: Keyed::default(),
+            infix_keys: Keyed::default(),
+            stmt_keys: Keyed::default(),
+            functions: RefCell::new(HashMap::new()),
+            warnings: RefCell::new(None),
+            trace: None,
+            coverage: None,
         }
     }
 
+    /// Register a function definition as it is parsed (a later one replaces an earlier one)
+    pub fn define_function(&self, name: String, def: FunctionDef) {
+        self.functions.borrow_mut().insert(name, def);
+    }
+
+    /// The functions defined so far, for the run of what was parsed
+    pub fn take_functions(&self) -> HashMap<String, FunctionDef> {
+        self.functions.take()
+    }
+
+    /// What the handlers report for warnings while they parse (None: not collecting)
+    pub fn warnings(&self) -> &RefCell<Option<Warnings>> {
+        &self.warnings
+    }
+
+    pub fn trace(&self) -> Option<&TraceConfig> {
+        self.trace.as_ref()
+    }
+
+    pub fn set_trace(&mut self, config: TraceConfig) {
+        self.trace = Some(config);
+    }
+
+    pub fn coverage(&self) -> Option<&CoverageSink> {
+        self.coverage.as_ref()
+    }
+
+    pub fn set_coverage(&mut self, sink: CoverageSink) {
+        self.coverage = Some(sink);
+    }
+
     pub fn register_prefix(&mut self, h: Box<dyn ExprPrefix>) {
+        self.prefix_keys.add(self.prefixes.len(), h.lexemes());
         self.prefixes.push(h);
     }
 
     pub fn register_infix(&mut self, h: Box<dyn ExprInfix>) {
+        self.infix_keys.add(self.infixes.len(), h.lexemes());
         self.infixes.push(h);
     }
 
     pub fn register_stmt(&mut self, h: Box<dyn StmtHandler>) {
+        self.stmt_keys.add(self.stmts.len(), h.lexemes());
         self.stmts.push(h);
     }
 
+    // Only the handlers keyed by the current lexeme (and the unkeyed ones) are asked
     pub fn find_prefix(&self, parser: &Parser) -> Option<&dyn ExprPrefix> {
-        self.prefixes.iter().map(|b| b.as_ref()).find(|h| h.matches(parser))
+        let candidates = self.prefix_keys.candidates(&parser.peek().lexeme);
+        candidates.iter().map(|&i| self.prefixes[i].as_ref()).find(|h| h.matches(parser))
     }
 
     pub fn find_infix(&self, parser: &Parser) -> Option<&dyn ExprInfix> {
-        self.infixes.iter().map(|b| b.as_ref()).find(|h| h.matches(parser))
+        let candidates = self.infix_keys.candidates(&parser.peek().lexeme);
+        candidates.iter().map(|&i| self.infixes[i].as_ref()).find(|h| h.matches(parser))
     }
 
     pub fn find_stmt(&self, parser: &Parser) -> Option<&dyn StmtHandler> {
-        self.stmts.iter().map(|b| b.as_ref()).find(|h| h.matches(parser))
+        let candidates = self.stmt_keys.candidates(&parser.peek().lexeme);
+        candidates.iter().map(|&i| self.stmts[i].as_ref()).find(|h| h.matches(parser))
     }
 }
 
@@ -94,3 +186,20 @@ pub fn parse_expr_with_prec(
 
     Ok(left)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Keyed;
+
+    #[test]
+    fn keyed_candidates_keep_registration_order() {
+        let mut keyed = Keyed::default();
+        keyed.add(0, Vec::new());
+        keyed.add(1, vec!["let"]);
+        keyed.add(2, Vec::new());
+        keyed.add(3, vec!["let", "fn"]);
+        assert_eq!(keyed.candidates("let"), &[0, 1, 2, 3]);
+        assert_eq!(keyed.candidates("fn"), &[0, 2, 3]);
+        assert_eq!(keyed.candidates("x"), &[0, 2]);
+    }
+}
@@ -44,6 +44,7 @@ pub fn aggregate_patterns() -> PatternSet {
         statements::control_until::patterns(),
         statements::flow_break::patterns(),
         statements::flow_continue::patterns(),
+        statements::flow_exit::patterns(),
         statements::return_stmt::patterns(),
         statements::functions::patterns(),
         statements::system_memoization::patterns(),
@@ -88,6 +89,7 @@ pub fn register_all(registry: &mut Registry) {
         TokenDefinition::keyword("break"),
         TokenDefinition::keyword("continue"),
         TokenDefinition::keyword("return"),
+        TokenDefinition::keyword("exit"),
         TokenDefinition::keyword("fn"),
         TokenDefinition::keyword("emit"),
         TokenDefinition::keyword("push"),
@@ -133,6 +135,7 @@ pub fn register_all(registry: &mut Registry) {
     statements::assignment::register(registry);    // Assignment - must come after keyword handlers
     statements::flow_break::register(registry);    // break statement
     statements::flow_continue::register(registry); // continue statement
+    statements::flow_exit::register(registry);     // exit statement
     statements::return_stmt::register(registry);   // return statement
     statements::functions::register(registry);     // function definition and registry
     statements::expr_stmt::register(registry);     // expression statements (fallback handler)
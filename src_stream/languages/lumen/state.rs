@@ -0,0 +1,93 @@
+// Per-run state of a Lumen program
+//
+// Everything a running program registers lives here, in its environment (see
+// Env::language_state), not in statics: the functions defined while it was parsed, the
+// extern capabilities (limited by --allow-BACKEND / --deny-all, logged by --audit), mock layers, the
+// results cache_extern keeps and calls in flight (extern_async) with the executor that runs them, the
+// names user code may not rebind, the call depth and step count checked against
+// --max-depth / --max-steps, whether REALs carry error bounds (--intervals), and what the
+// stream binary's diagnostics collect (warnings, --coverage counts, --trace loop depth).
+// Programs run by separate interpreters (on one thread or several) never see each
+// other's state.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::kernel::runtime::Env;
+use crate::kernel::runtime::Value;
+use crate::languages::lumen::extern_system::extern_cache::ExternCache;
+use crate::languages::lumen::extern_system::pending::{ExternExecutor, PendingCalls, ThreadExecutor};
+use crate::languages::lumen::extern_system::{capabilities, registry::CapabilityRegistry};
+use crate::languages::lumen::coverage::CoverageSink;
+use crate::languages::lumen::statements::functions::FunctionDef;
+use crate::languages::lumen::warnings::WarningSink;
+use crate::limits::{ExecutionLimits, Usage};
+
+/// Runs started so far in this process, for telling runs apart
+static RUNS: AtomicU64 = AtomicU64::new(0);
+
+/// What one Lumen run has registered
+pub struct State {
+    /// This run, different from every other in the process (see inline_cache.rs)
+    pub run: u64,
+    /// User-defined functions by name
+    pub functions: HashMap<String, FunctionDef>,
+    /// Extern capabilities (the builtin ones) and the program's mock layers
+    pub capabilities: CapabilityRegistry,
+    /// Results of the selectors marked with cache_extern
+    pub extern_cache: ExternCache<Value>,
+    /// extern_async calls not yet awaited
+    pub pending: PendingCalls,
+    /// What runs extern_async calls (ThreadExecutor unless the host gave another)
+    pub executor: Arc<dyn ExternExecutor>,
+    /// Names bound by the host that user code may not rebind (ARGS, FEATURES, host constants)
+    pub protected: HashSet<String>,
+    /// Call depth and steps so far
+    pub usage: Usage,
+    /// --intervals: REALs carry error bounds (see interval.rs)
+    pub intervals: bool,
+    /// Where warnings raised while the program runs go (None: not reported)
+    pub warnings: Option<WarningSink>,
+    /// --coverage: where statement and branch counts go (None: not counted)
+    pub coverage: Option<CoverageSink>,
+    /// --trace: loop bodies running (None: not counted)
+    pub loop_depth: Option<Arc<AtomicUsize>>,
+}
+
+impl State {
+    /// State for a run of a program whose definitions are `functions`
+    pub fn new(functions: HashMap<String, FunctionDef>) -> Self {
+        let mut registry = CapabilityRegistry::new();
+        capabilities::register_builtins(&mut registry);
+        State {
+            run: RUNS.fetch_add(1, Ordering::Relaxed),
+            functions,
+            capabilities: registry,
+            extern_cache: ExternCache::new(),
+            pending: PendingCalls::default(),
+            executor: Arc::new(ThreadExecutor),
+            protected: HashSet::new(),
+            usage: Usage::default(),
+            intervals: false,
+            warnings: None,
+            coverage: None,
+            loop_depth: None,
+        }
+    }
+
+    /// Start a run in `env`, within `limits`
+    pub fn install(env: &mut Env, functions: HashMap<String, FunctionDef>, limits: ExecutionLimits) {
+        let mut state = State::new(functions);
+        state.capabilities.set_policy(limits.externs.clone());
+        state.capabilities.set_audit(limits.audit.clone());
+        state.capabilities.set_timeouts(limits.extern_timeouts.clone());
+        state.usage = Usage::new(limits);
+        *State::of(env) = state;
+    }
+
+    /// The state of the run `env` belongs to (empty if none was installed)
+    pub fn of(env: &mut Env) -> &mut State {
+        env.language_state(|| State::new(HashMap::new()))
+    }
+}
@@ -1,11 +1,15 @@
 use crate::languages::lumen::prelude::*;
 // Array indexed assignment: arr[i] = value
 
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
+use crate::languages::lumen::warnings;
+use crate::languages::lumen::statements::assignment::check_not_protected;
 use crate::languages::lumen::structure::structural::LBRACKET;
 use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::values::Integer;
+use num_traits::ToPrimitive;
 
 #[derive(Debug)]
 pub struct ArrayAssignStmt {
@@ -16,33 +20,38 @@ pub struct ArrayAssignStmt {
 
 impl StmtNode for ArrayAssignStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        // Evaluate the index
-        let index_val = self.index_expr.eval(env)?;
-        let index_bigint = crate::languages::lumen::values::as_number(index_val.as_ref())?;
-        let (sign, digits) = index_bigint.value.to_u32_digits();
-
-        // Check for negative index
-        use num_bigint::Sign;
-        if sign == Sign::Minus {
-            return Err("Array index cannot be negative".to_string());
-        }
+        crate::kernel::eval::run_stmt(self, env)
+    }
 
-        // Get the index value (0 if digits is empty, otherwise digits[0])
-        let idx = if digits.is_empty() {
-            0usize
-        } else if digits.len() == 1 {
-            digits[0] as usize
-        } else {
-            return Err("Array index out of bounds".to_string());
-        };
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        // ARGS is a system-provided immutable array: its elements cannot change either
+        check_not_protected(env, &self.name)?;
 
-        // Evaluate the value to assign
-        let value = self.value_expr.eval(env)?;
+        // Evaluate the index
+        Ok(Step::eval(self.index_expr.as_ref(), move |index_val, _env| {
+            let idx = Self::index(index_val)?;
+
+            // Evaluate the value to assign
+            Ok(Step::eval(self.value_expr.as_ref(), move |value, env| {
+                // Get mutable reference to the array and mutate it
+                env.mutate_array(&self.name, idx, value)?;
+                Ok(Step::control(Control::None))
+            }))
+        }))
+    }
+}
 
-        // Get mutable reference to the array and mutate it
-        env.mutate_array(&self.name, idx, value)?;
+impl ArrayAssignStmt {
+    /// The evaluated index as a position in the array
+    fn index(index_val: Value) -> LumenResult<usize> {
+        let index = crate::languages::lumen::values::as_number(index_val.as_ref())?;
 
-        Ok(Control::None)
+        // Check for negative index
+        if index.value < Integer::Small(0) {
+            return Err("Array index cannot be negative".into());
+        }
+        let idx = index.value.to_usize().ok_or_else(|| "Array index out of bounds".to_string())?;
+        Ok(idx)
     }
 }
 
@@ -120,8 +129,9 @@ impl StmtHandler for ArrayAssignHandler {
     }
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
-        // Parse identifier
-        let mut name = parser.advance().lexeme;
+        // Parse identifier (updating an element uses the array binding)
+        let line = parser.position().0;
+        let mut name = parser.advance().lexeme.into_owned();
         parser.skip_tokens();
 
         // Continue consuming identifier characters if split across tokens
@@ -161,6 +171,7 @@ impl StmtHandler for ArrayAssignHandler {
 
         // Parse value expression
         let value_expr = parser.parse_expr(registry)?;
+        warnings::read(registry, &name, line);
 
         Ok(Box::new(ArrayAssignStmt {
             name,
@@ -3,10 +3,25 @@ use crate::languages::lumen::prelude::*;
 //
 // x = expr
 
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
+use crate::languages::lumen::warnings;
 use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::state::State;
+
+/// Mark a system-provided binding as immutable (ARGS, FEATURES, host constants)
+pub fn protect_binding(env: &mut Env, name: &str) {
+    State::of(env).protected.insert(name.to_string());
+}
+
+/// Reject assignments and let bindings that target a protected name
+pub fn check_not_protected(env: &mut Env, name: &str) -> LumenResult<()> {
+    if State::of(env).protected.contains(name) {
+        return Err(format!("Cannot reassign {} (system-provided immutable value)", name).into());
+    }
+    Ok(())
+}
 
 #[derive(Debug)]
 struct AssignStmt {
@@ -16,13 +31,16 @@ struct AssignStmt {
 
 impl StmtNode for AssignStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        // ARGS is a system-provided immutable semantic value
-        if self.name == "ARGS" {
-            return Err("Cannot reassign ARGS (system-provided immutable value)".to_string());
-        }
-        let val: Value = self.expr.eval(env)?;
-        env.assign(&self.name, val)?;
-        Ok(Control::None)
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        // ARGS, FEATURES and host constants are system-provided immutable semantic values
+        check_not_protected(env, &self.name)?;
+        Ok(Step::eval(self.expr.as_ref(), move |val: Value, env| {
+            env.assign(&self.name, val)?;
+            Ok(Step::control(Control::None))
+        }))
     }
 }
 
@@ -72,7 +90,8 @@ impl StmtHandler for AssignStmtHandler {
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         // Consume the identifier (which may span multiple tokens for the kernel's agnostic lexer)
-        let mut name = parser.advance().lexeme;
+        let (line, col) = parser.position();
+        let mut name = parser.advance().lexeme.into_owned();
         parser.skip_tokens();
 
         // Continue consuming identifier characters if split across tokens
@@ -94,6 +113,7 @@ impl StmtHandler for AssignStmtHandler {
         parser.skip_tokens();
 
         let expr = parser.parse_expr(registry)?;
+        warnings::bind(registry, &name, line, col);
         Ok(Box::new(AssignStmt { name, expr }))
     }
 }
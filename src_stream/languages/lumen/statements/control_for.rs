@@ -7,14 +7,15 @@
 // Desugars into: iterator initialization + while loop
 
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::Env;
+use crate::languages::lumen::control_flow::loop_body;
 use crate::languages::lumen::structure::structural;
+use crate::languages::lumen::warnings;
 use crate::languages::lumen::expressions::range_expr::as_range;
-use crate::languages::lumen::values::LumenNumber;
-use num_bigint::BigInt;
+use crate::languages::lumen::values::{Integer, LumenNumber};
 
 #[derive(Debug)]
 struct ForStmt {
@@ -25,43 +26,33 @@ struct ForStmt {
 
 impl StmtNode for ForStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
         // Evaluate the iterable expression
-        let iterable_val = self.iterable.eval(env)?;
-
-        // Handle range iteration
-        let range = as_range(iterable_val.as_ref())?;
-
-        let mut current = range.start.clone();
-        while current < range.end {
-            // Set loop variable to current value
-            env.assign(&self.var, Box::new(LumenNumber::new(current.clone())))?;
-
-            // Execute loop body in same scope (matches Microcode kernel)
-            let mut break_occurred = false;
-            for stmt in &self.body {
-                match stmt.exec(env)? {
-                    Control::Break => {
-                        break_occurred = true;
-                        break;
-                    }
-                    Control::Continue => break,
-                    Control::ExprValue(_) => {
-                        // Expression statement value - continue loop
-                    }
-                    Control::Return(val) => {
-                        return Ok(Control::Return(val));
-                    }
-                    Control::None => {}
-                }
-            }
-            if break_occurred {
-                return Ok(Control::None);
-            }
-
-            current += BigInt::from(1);
+        Ok(Step::eval(self.iterable.as_ref(), move |iterable_val, env| {
+            // Handle range iteration
+            let range = as_range(iterable_val.as_ref())?;
+            self.iteration(Integer::from(range.start.clone()), Integer::from(range.end.clone()), env)
+        }))
+    }
+}
+
+impl ForStmt {
+    /// Run the body with the loop variable at `current`, then come back for the next value
+    fn iteration<'a>(&'a self, current: Integer, end: Integer, env: &mut Env) -> LumenResult<Step<'a>> {
+        if current >= end {
+            return Ok(Step::control(Control::None));
         }
+        // Set loop variable to current value
+        env.assign(&self.var, Box::new(LumenNumber::new(current.clone())))?;
 
-        Ok(Control::None)
+        // Execute loop body in same scope (matches Microcode kernel)
+        loop_body(&self.body, env, Box::new(move |control, env| match control {
+            Some(control) => Ok(Step::control(control)),
+            None => self.iteration(current.add(&Integer::Small(1)), end, env),
+        }))
     }
 }
 
@@ -72,6 +63,10 @@ impl StmtHandler for ForStmtHandler {
         parser.peek().lexeme == "for"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["for"]
+    }
+
     fn parse(
         &self,
         parser: &mut Parser,
@@ -81,13 +76,14 @@ impl StmtHandler for ForStmtHandler {
         parser.skip_tokens();
 
         // Parse loop variable name
-        let var_name = parser.peek().lexeme.clone();
+        let (line, col) = parser.position();
+        let var_name = parser.peek().lexeme.to_string();
         parser.advance();
         parser.skip_tokens();
 
         // Expect 'in' keyword
         if parser.peek().lexeme != "in" {
-            return Err("Expected 'in' after for loop variable".to_string());
+            return Err("Expected 'in' after for loop variable".into());
         }
         parser.advance();
         parser.skip_tokens();
@@ -95,6 +91,7 @@ impl StmtHandler for ForStmtHandler {
         // Parse iterable expression
         let iterable = parser.parse_expr(registry)?;
         parser.skip_tokens();
+        warnings::bind(registry, &var_name, line, col);
 
         // Parse indented body
         let body = structural::parse_block(parser, registry)?;
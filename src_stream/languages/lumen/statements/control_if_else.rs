@@ -1,69 +1,44 @@
 use crate::languages::lumen::prelude::*;
 // if / else statement
 
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::Env;
 use crate::languages::lumen::structure::structural;
 use crate::languages::lumen::values::as_bool;
+use crate::languages::lumen::control_flow;
+use crate::languages::lumen::coverage;
 
 #[derive(Debug)]
 struct IfStmt {
     cond: Box<dyn ExprNode>,
     then_block: Vec<Box<dyn StmtNode>>,
     else_block: Option<Vec<Box<dyn StmtNode>>>,
+    /// Source line for branch coverage (set only while coverage is recording)
+    coverage_line: Option<usize>,
 }
 
 impl StmtNode for IfStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        let cond = self.cond.eval(env)?;
-        let cond_bool = as_bool(cond.as_ref())?;
-        let branch_taken = cond_bool.value;
-
-        if branch_taken {
-            let mut result = Control::None;
-            for stmt in &self.then_block {
-                let ctl = stmt.exec(env)?;
-                match ctl {
-                    Control::None => {
-                        // Statement completed normally
-                    }
-                    Control::ExprValue(val) => {
-                        // Update result for implicit return from expression
-                        result = Control::ExprValue(val);
-                    }
-                    // Break/Continue/Return control flow
-                    other => {
-                        result = other;
-                        break;
-                    }
-                }
-            }
-            return Ok(result);
-        } else if let Some(ref else_block) = self.else_block {
-            let mut result = Control::None;
-            for stmt in else_block {
-                let ctl = stmt.exec(env)?;
-                match ctl {
-                    Control::None => {
-                        // Statement completed normally
-                    }
-                    Control::ExprValue(val) => {
-                        // Update result for implicit return from expression
-                        result = Control::ExprValue(val);
-                    }
-                    // Break/Continue/Return control flow
-                    other => {
-                        result = other;
-                        break;
-                    }
-                }
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.cond.as_ref(), move |cond, env| {
+            let cond_bool = as_bool(cond.as_ref())?;
+            let branch_taken = cond_bool.value;
+            if let Some(line) = self.coverage_line {
+                coverage::record_branch(env, line, branch_taken);
             }
-            return Ok(result);
-        }
 
-        Ok(Control::None)
+            if branch_taken {
+                return Ok(control_flow::block(&self.then_block));
+            } else if let Some(ref else_block) = self.else_block {
+                return Ok(control_flow::block(else_block));
+            }
+            Ok(Step::control(Control::None))
+        }))
     }
 }
 
@@ -74,7 +49,12 @@ impl StmtHandler for IfStmtHandler {
         parser.peek().lexeme == "if"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["if"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
+        let coverage_line = coverage::branch_site(registry, parser.position().0);
         parser.advance(); // consume 'if'
         parser.skip_tokens();
 
@@ -95,6 +75,7 @@ impl StmtHandler for IfStmtHandler {
             cond,
             then_block,
             else_block,
+            coverage_line,
         }))
     }
 }
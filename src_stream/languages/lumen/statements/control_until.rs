@@ -9,10 +9,11 @@
 // Desugars into: { block; while not expr { block } }
 
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::Env;
+use crate::languages::lumen::control_flow::loop_body;
 use crate::languages::lumen::structure::structural;
 use crate::languages::lumen::values::as_bool;
 
@@ -24,41 +25,32 @@ struct UntilStmt {
 
 impl StmtNode for UntilStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        loop {
-            // Execute body first (at least once) in same scope (matches Microcode kernel)
-            let mut break_occurred = false;
-            for stmt in &self.body {
-                match stmt.exec(env)? {
-                    Control::Break => {
-                        break_occurred = true;
-                        break;
-                    }
-                    Control::Continue => break,
-                    Control::ExprValue(_) => {
-                        // Expression statement value - continue loop
-                    }
-                    Control::Return(val) => {
-                        return Ok(Control::Return(val));
-                    }
-                    Control::None => {}
-                }
-            }
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        self.iteration(env)
+    }
+}
 
-            if break_occurred {
-                return Ok(Control::None);
+impl UntilStmt {
+    /// Run the body, then check the condition and come back here while it is false
+    fn iteration<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        // Execute body first (at least once) in same scope (matches Microcode kernel)
+        loop_body(&self.body, env, Box::new(move |control, _env| {
+            if let Some(control) = control {
+                return Ok(Step::control(control));
             }
 
             // Check condition: exit if true, continue if false
-            let cond = self.condition.eval(env)?;
-            let cond_bool = as_bool(cond.as_ref())?;
-
-            if cond_bool.value {
-                // Condition is true, exit loop
-                break;
-            }
-            // Condition is false, continue looping
-        }
-        Ok(Control::None)
+            Ok(Step::eval(self.condition.as_ref(), move |cond, env| {
+                let cond_bool = as_bool(cond.as_ref())?;
+                if cond_bool.value {
+                    return Ok(Step::control(Control::None));
+                }
+                self.iteration(env)
+            }))
+        }))
     }
 }
 
@@ -69,6 +61,10 @@ impl StmtHandler for UntilStmtHandler {
         parser.peek().lexeme == "until"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["until"]
+    }
+
     fn parse(
         &self,
         parser: &mut Parser,
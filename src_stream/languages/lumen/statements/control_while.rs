@@ -4,10 +4,11 @@ use crate::languages::lumen::prelude::*;
 // while <expr>
 //     <block>
 
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::Env;
+use crate::languages::lumen::control_flow::loop_body;
 use crate::languages::lumen::structure::structural;
 use crate::languages::lumen::values::as_bool;
 
@@ -19,38 +20,32 @@ struct WhileStmt {
 
 impl StmtNode for WhileStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        loop {
-            let cond = self.condition.eval(env)?;
-            let cond_bool = as_bool(cond.as_ref())?;
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(self.iteration())
+    }
+}
 
-            if cond_bool.value {
-                // Loop body executes in the same scope as the enclosing function
-                // No scope is created (matches Microcode kernel behavior)
-                let mut break_occurred = false;
-                for stmt in &self.body {
-                    match stmt.exec(env)? {
-                        Control::Break => {
-                            break_occurred = true;
-                            break;
-                        }
-                        Control::Continue => break,
-                        Control::ExprValue(_) => {
-                            // Expression statement value - continue loop
-                        }
-                        Control::Return(val) => {
-                            return Ok(Control::Return(val));
-                        }
-                        Control::None => {}
-                    }
-                }
-                if break_occurred {
-                    return Ok(Control::None);
-                }
-            } else {
-                break;
+impl WhileStmt {
+    /// Check the condition, then run the body and come back here
+    fn iteration(&self) -> Step<'_> {
+        Step::eval(self.condition.as_ref(), move |cond, env| {
+            let cond_bool = as_bool(cond.as_ref())?;
+            if !cond_bool.value {
+                return Ok(Step::control(Control::None));
             }
-        }
-        Ok(Control::None)
+
+            // Loop body executes in the same scope as the enclosing function
+            // No scope is created (matches Microcode kernel behavior)
+            loop_body(&self.body, env, Box::new(move |control, _env| {
+                Ok(match control {
+                    Some(control) => Step::control(control),
+                    None => self.iteration(),
+                })
+            }))
+        })
     }
 }
 
@@ -61,6 +56,10 @@ impl StmtHandler for WhileStmtHandler {
         parser.peek().lexeme == "while"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["while"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'while'
         parser.skip_tokens();
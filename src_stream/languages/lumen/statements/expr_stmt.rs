@@ -2,7 +2,7 @@
 // Handles bare expressions as statements (for implicit returns and expression statements)
 
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::{Control, StmtNode};
+use crate::kernel::ast::{Control, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::Env;
@@ -14,11 +14,14 @@ struct ExprStmt {
 
 impl StmtNode for ExprStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        let val = self.expr.eval(env)?;
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
         // Expression statements return their value as ExprValue.
         // This allows function bodies to continue executing multiple statements,
         // while explicit return statements break the loop immediately.
-        Ok(Control::ExprValue(val))
+        Ok(Step::eval(self.expr.as_ref(), |val, _env| Ok(Step::control(Control::ExprValue(val)))))
     }
 }
 
@@ -35,7 +38,7 @@ impl StmtHandler for ExprStmtHandler {
             "fn", "let", "print", "extern"
         ];
 
-        if reserved.contains(&lexeme.as_str()) {
+        if reserved.contains(&lexeme.as_ref()) {
             return false;
         }
 
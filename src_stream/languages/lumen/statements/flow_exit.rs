@@ -0,0 +1,70 @@
+// Exit statement handler
+// exit [status]
+//
+// Ends the whole program. LumenError::Exit unwinds through loops and function calls on
+// the error path (see control_flow.rs); the binary turns it into the process exit status.
+
+use crate::languages::lumen::prelude::*;
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
+use crate::kernel::parser::Parser;
+use crate::languages::lumen::patterns::PatternSet;
+use crate::kernel::runtime::Env;
+use crate::languages::lumen::control_flow::check_exit_status;
+use crate::languages::lumen::structure::structural::{DEDENT, EOF, NEWLINE};
+use crate::languages::lumen::values::as_number;
+use num_traits::ToPrimitive;
+
+#[derive(Debug)]
+struct ExitStmt {
+    status: Option<Box<dyn ExprNode>>,
+}
+
+impl StmtNode for ExitStmt {
+    fn exec(&self, env: &mut Env) -> LumenResult<Control> {
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        match &self.status {
+            Some(expr) => Ok(Step::eval(expr.as_ref(), |value, _env| {
+                let number = as_number(value.as_ref()).ok().and_then(|n| n.value.to_i64());
+                Err(LumenError::Exit(check_exit_status(number)?))
+            })),
+            None => Err(LumenError::Exit(0)),
+        }
+    }
+}
+
+pub struct ExitStmtHandler;
+
+impl StmtHandler for ExitStmtHandler {
+    fn matches(&self, parser: &Parser) -> bool {
+        parser.peek().lexeme == "exit"
+    }
+
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["exit"]
+    }
+
+    fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
+        parser.advance(); // consume 'exit'
+        parser.skip_tokens();
+
+        let status = if matches!(parser.peek().lexeme.as_ref(), NEWLINE | DEDENT | EOF) {
+            None
+        } else {
+            Some(parser.parse_expr(registry)?)
+        };
+
+        Ok(Box::new(ExitStmt { status }))
+    }
+}
+
+pub fn patterns() -> PatternSet {
+    PatternSet::new()
+        .with_literals(vec!["exit"])
+}
+
+pub fn register(reg: &mut Registry) {
+    reg.register_stmt(Box::new(ExitStmtHandler));
+}
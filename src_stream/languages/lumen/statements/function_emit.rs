@@ -9,7 +9,7 @@ use crate::languages::lumen::prelude::*;
 // All higher-level I/O behavior (write, print, etc) is implemented in the
 // Lumen standard library using emit() as the foundation.
 
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::Env;
@@ -23,16 +23,20 @@ struct EmitStmt {
 
 impl StmtNode for EmitStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        let val = self.expr.eval(env)?;
+        crate::kernel::eval::run_stmt(self, env)
+    }
 
-        // Require string input - no implicit conversion
-        match as_string(val.as_ref()) {
-            Ok(str_val) => {
-                print!("{}", str_val.value);
-                Ok(Control::None)
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        Ok(Step::eval(self.expr.as_ref(), |val, _env| {
+            // Require string input - no implicit conversion
+            match as_string(val.as_ref()) {
+                Ok(str_val) => {
+                    print!("{}", str_val.value);
+                    Ok(Step::control(Control::None))
+                }
+                Err(_) => Err("emit() requires a string argument".into()),
             }
-            Err(_) => Err("emit() requires a string argument".into()),
-        }
+        }))
     }
 }
 
@@ -43,6 +47,10 @@ impl StmtHandler for EmitStmtHandler {
         parser.peek().lexeme == "emit"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["emit"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         // consume `emit`
         parser.advance();
@@ -2,52 +2,113 @@
 // Lumen functions are user-defined statements that can be called as expressions
 // This module is entirely optional - removing it removes function support
 
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
-use crate::kernel::ast::{StmtNode, Control};
+use std::sync::Arc;
+use crate::kernel::ast::{Control, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::prelude::*;
 use crate::languages::lumen::patterns::PatternSet;
+use crate::languages::lumen::warnings;
 use crate::kernel::runtime::Env;
-use crate::languages::lumen::structure::structural::{LPAREN, RPAREN};
+use crate::languages::lumen::state::State;
+use crate::languages::lumen::structure::structural::{consume_newlines, LPAREN, RPAREN, NEWLINE, INDENT, DEDENT};
+use crate::languages::lumen::expressions::literals;
 
 // ============================================================================
 // FUNCTION REGISTRY
 // ============================================================================
+//
+// Definitions are registered as they are parsed, in the Registry the program is parsed
+// with, so a function can be called above its definition. The run then takes them over
+// (lumen/state.rs) and calls look them up there.
 
-/// Stores a function definition: parameters and statement body
+/// The statements of a function, shared by its definition and its running calls
+pub type Body = Arc<Vec<Box<dyn StmtNode>>>;
+
+/// A function definition: parameters, statement body, and optional docstring
 pub struct FunctionDef {
     pub params: Vec<String>,
-    pub body: Rc<RefCell<Vec<Box<dyn StmtNode>>>>,
+    pub body: Body,
+    pub doc: Option<String>,
 }
 
-thread_local! {
-    /// Global function registry - stores all defined functions
-    /// Maps function name -> FunctionDef
-    static FUNCTION_REGISTRY: RefCell<HashMap<String, FunctionDef>> = RefCell::new(HashMap::new());
+/// Get a function definition by name (the body is shared, not copied)
+pub fn get_function(env: &mut Env, name: &str) -> Option<(Vec<String>, Body)> {
+    State::of(env).functions.get(name).map(|def| (def.params.clone(), Arc::clone(&def.body)))
 }
 
-/// Register a function definition with its parameters and body
-pub fn define_function(name: String, params: Vec<String>, body: Vec<Box<dyn StmtNode>>) {
-    FUNCTION_REGISTRY.with(|registry| {
-        let def = FunctionDef {
-            params,
-            body: Rc::new(RefCell::new(body)),
-        };
-        registry.borrow_mut().insert(name, def);
-    });
+/// Call a user-defined function by name with already-evaluated arguments
+/// Used by callers (e.g. extern mocks) that hold a function name rather than a call expression.
+pub fn call_function<'a>(name: &str, args: Vec<crate::kernel::runtime::Value>, env: &mut Env) -> LumenResult<Step<'a>> {
+    let (params, body) = get_function(env, name)
+        .ok_or_else(|| format!("Undefined function '{}'", name))?;
+    if args.len() != params.len() {
+        return Err(format!(
+            "Function '{}' expects {} arguments, got {}",
+            name,
+            params.len(),
+            args.len()
+        ).into());
+    }
+    call(&params, body, args, env)
 }
 
-/// Get a function definition by name (returns Rc to allow shared access)
-pub fn get_function(name: &str) -> Option<(Vec<String>, Rc<RefCell<Vec<Box<dyn StmtNode>>>>)> {
-    FUNCTION_REGISTRY.with(|registry| {
-        registry.borrow().get(name).map(|def| {
-            (def.params.clone(), Rc::clone(&def.body))
-        })
-    })
+/// Run a call on the work stack: bind the arguments to the parameters in a new scope and
+/// run the body; the step finishes with the call's value and leaves the scope, even on error
+pub fn call<'a>(
+    params: &[String],
+    body: Body,
+    args: Vec<crate::kernel::runtime::Value>,
+    env: &mut Env,
+) -> LumenResult<Step<'a>> {
+    env.push_scope();
+    for (param, arg_val) in params.iter().zip(args) {
+        env.define(param.clone(), arg_val);
+    }
+
+    // SAFETY: the work after the body (below) owns `body`, and everything running the body
+    // waits above it on the work stack, so it is done with the statements before they can
+    // be freed.
+    let statements: &'a [Box<dyn StmtNode>] = unsafe { &*Arc::as_ptr(&body) };
+    match crate::languages::lumen::control_flow::function_body(statements, env) {
+        Ok(step) => Ok(step.then(move |outcome, env| {
+            env.pop_scope();
+            drop(body);
+            outcome.map(Step::Done)
+        })),
+        Err(e) => {
+            env.pop_scope();
+            Err(e)
+        }
+    }
 }
 
+/// Get a function's docstring by name
+/// Returns None if no such function exists, Some(None) if it has no docstring
+pub fn get_doc(env: &mut Env, name: &str) -> Option<Option<String>> {
+    State::of(env).functions.get(name).map(|def| def.doc.clone())
+}
+
+/// Look ahead for a docstring: a string literal standing alone as the first statement
+/// of the function body. The parser position is left unchanged.
+fn peek_docstring(parser: &mut Parser) -> LumenResult<Option<String>> {
+    let start = parser.i;
+    let mut doc = None;
+
+    consume_newlines(parser);
+    if parser.peek().lexeme == INDENT {
+        parser.advance();
+        consume_newlines(parser);
+        if parser.peek().lexeme == "\"" || parser.peek().lexeme == "'" {
+            let text = literals::parse_string_literal_value(parser)?;
+            if parser.peek().lexeme == NEWLINE || parser.peek().lexeme == DEDENT {
+                doc = Some(text);
+            }
+        }
+    }
+
+    parser.i = start;
+    Ok(doc)
+}
 
 // ============================================================================
 // FUNCTION DEFINITION STATEMENT HANDLER
@@ -59,7 +120,7 @@ pub fn get_function(name: &str) -> Option<(Vec<String>, Rc<RefCell<Vec<Box<dyn S
 #[derive(Debug)]
 struct FnDefStmt {
     name: String,
-    // Stores the function definition in the registry during parse time
+    // The definition itself is registered during parsing
 }
 
 impl StmtNode for FnDefStmt {
@@ -76,14 +137,19 @@ impl StmtHandler for FnDefStmtHandler {
         parser.peek().lexeme == "fn"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["fn"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
+        let line = parser.position().0;
         parser.advance(); // consume 'fn'
         parser.skip_tokens();
 
         // Parse function name
         let mut name = String::new();
         if parser.peek().lexeme.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_') {
-            name = parser.advance().lexeme;
+            name = parser.advance().lexeme.into_owned();
             parser.skip_tokens();
 
             // Handle multi-character identifiers split by lexer
@@ -111,12 +177,14 @@ impl StmtHandler for FnDefStmtHandler {
 
         // Parse parameters (comma-separated identifiers)
         let mut params = Vec::new();
+        let mut param_positions = Vec::new();
 
         while parser.peek().lexeme != RPAREN {
             // Parse parameter name
             let mut param_name = String::new();
             if parser.peek().lexeme.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_') {
-                param_name = parser.advance().lexeme;
+                param_positions.push(parser.position());
+                param_name = parser.advance().lexeme.into_owned();
                 parser.skip_tokens();
 
                 // Handle multi-character identifiers
@@ -154,11 +222,20 @@ impl StmtHandler for FnDefStmtHandler {
         parser.advance(); // consume ')'
         parser.skip_tokens();
 
-        // Parse function body (indented block)
-        let body = crate::languages::lumen::structure::structural::parse_block(parser, registry)?;
+        // A leading string literal is the docstring, not part of the executed body
+        let doc = peek_docstring(parser)?;
+
+        // Parse function body (indented block); its names are checked in their own scope
+        warnings::enter_function(registry, &name, &params, &param_positions, line);
+        let body = crate::languages::lumen::structure::structural::parse_block(parser, registry);
+        warnings::leave_function(registry);
+        let mut body = body?;
+        if doc.is_some() {
+            body.remove(0);
+        }
 
         // Register the function
-        define_function(name.clone(), params, body);
+        registry.define_function(name.clone(), FunctionDef { params, body: Arc::new(body), doc });
 
         Ok(Box::new(FnDefStmt { name }))
     }
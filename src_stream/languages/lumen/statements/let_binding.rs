@@ -2,9 +2,10 @@
 // let name [: Type] = expression
 
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
+use crate::languages::lumen::warnings;
 use crate::kernel::runtime::Env;
 
 #[derive(Debug)]
@@ -16,9 +17,15 @@ struct LetStmt {
 
 impl StmtNode for LetStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        let val = self.expr.eval(env)?;
-        env.define(self.name.clone(), val);
-        Ok(Control::None)
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        super::assignment::check_not_protected(env, &self.name)?;
+        Ok(Step::eval(self.expr.as_ref(), move |val, env| {
+            env.define(self.name.clone(), val);
+            Ok(Step::control(Control::None))
+        }))
     }
 }
 
@@ -29,14 +36,19 @@ impl StmtHandler for LetStmtHandler {
         parser.peek().lexeme == "let"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["let"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'let'
         parser.skip_tokens();
 
         // Parse variable name
         let mut name = String::new();
+        let (line, col) = parser.position();
         if parser.peek().lexeme.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_') {
-            name = parser.advance().lexeme;
+            name = parser.advance().lexeme.into_owned();
             parser.skip_tokens();
 
             // Handle multi-character identifiers split by lexer
@@ -63,7 +75,7 @@ impl StmtHandler for LetStmtHandler {
             // Parse type name
             let mut type_name = String::new();
             if parser.peek().lexeme.chars().next().map_or(false, |c| c.is_alphabetic()) {
-                type_name = parser.advance().lexeme;
+                type_name = parser.advance().lexeme.into_owned();
                 parser.skip_tokens();
 
                 loop {
@@ -91,6 +103,7 @@ impl StmtHandler for LetStmtHandler {
 
         // Parse expression
         let expr = parser.parse_expr(registry)?;
+        warnings::bind(registry, &name, line, col);
 
         Ok(Box::new(LetStmt {
             name,
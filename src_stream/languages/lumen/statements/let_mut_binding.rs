@@ -2,9 +2,10 @@
 // let mut name [: Type] = expression
 
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
+use crate::languages::lumen::warnings;
 use crate::kernel::runtime::Env;
 
 #[derive(Debug)]
@@ -16,9 +17,15 @@ struct LetMutStmt {
 
 impl StmtNode for LetMutStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        let val = self.expr.eval(env)?;
-        env.define(self.name.clone(), val);
-        Ok(Control::None)
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        super::assignment::check_not_protected(env, &self.name)?;
+        Ok(Step::eval(self.expr.as_ref(), move |val, env| {
+            env.define(self.name.clone(), val);
+            Ok(Step::control(Control::None))
+        }))
     }
 }
 
@@ -35,6 +42,10 @@ impl StmtHandler for LetMutStmtHandler {
         false
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["let"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'let'
         parser.skip_tokens();
@@ -43,8 +54,9 @@ impl StmtHandler for LetMutStmtHandler {
 
         // Parse variable name
         let mut name = String::new();
+        let (line, col) = parser.position();
         if parser.peek().lexeme.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_') {
-            name = parser.advance().lexeme;
+            name = parser.advance().lexeme.into_owned();
             parser.skip_tokens();
 
             // Handle multi-character identifiers split by lexer
@@ -71,7 +83,7 @@ impl StmtHandler for LetMutStmtHandler {
             // Parse type name
             let mut type_name = String::new();
             if parser.peek().lexeme.chars().next().map_or(false, |c| c.is_alphabetic()) {
-                type_name = parser.advance().lexeme;
+                type_name = parser.advance().lexeme.into_owned();
                 parser.skip_tokens();
 
                 loop {
@@ -99,6 +111,7 @@ impl StmtHandler for LetMutStmtHandler {
 
         // Parse expression
         let expr = parser.parse_expr(registry)?;
+        warnings::bind(registry, &name, line, col);
 
         Ok(Box::new(LetMutStmt {
             name,
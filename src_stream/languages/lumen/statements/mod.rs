@@ -7,6 +7,7 @@ pub mod array_assign;
 pub mod push_stmt;
 pub mod flow_break;
 pub mod flow_continue;
+pub mod flow_exit;
 pub mod control_if_else;
 pub mod function_emit;
 pub mod control_while;
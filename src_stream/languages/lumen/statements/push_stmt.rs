@@ -4,9 +4,11 @@ use crate::languages::lumen::prelude::*;
 // Appends a value to an array, mutating it in place.
 // This is a kernel-level primitive for array mutation.
 
-use crate::kernel::ast::{Control, StmtNode};
+use crate::kernel::ast::{Control, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
+use crate::languages::lumen::warnings;
+use crate::languages::lumen::statements::assignment::check_not_protected;
 use crate::kernel::runtime::Env;
 use crate::languages::lumen::structure::structural::{LPAREN, RPAREN};
 
@@ -18,13 +20,19 @@ struct PushStmt {
 
 impl StmtNode for PushStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        // Evaluate the value to push
-        let value = self.value_expr.eval(env)?;
+        crate::kernel::eval::run_stmt(self, env)
+    }
 
-        // Push to the array by name
-        env.push_array(&self.arr_name, value)?;
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        // ARGS is a system-provided immutable array: it cannot grow either
+        check_not_protected(env, &self.arr_name)?;
 
-        Ok(Control::None)
+        // Evaluate the value to push
+        Ok(Step::eval(self.value_expr.as_ref(), move |value, env| {
+            // Push to the array by name
+            env.push_array(&self.arr_name, value)?;
+            Ok(Step::control(Control::None))
+        }))
     }
 }
 
@@ -35,6 +43,10 @@ impl StmtHandler for PushStmtHandler {
         parser.peek().lexeme == "push"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["push"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         // consume `push`
         parser.advance();
@@ -47,7 +59,8 @@ impl StmtHandler for PushStmtHandler {
         parser.skip_tokens();
 
         // Parse array name (must be an identifier)
-        let mut arr_name = parser.advance().lexeme;
+        let line = parser.position().0;
+        let mut arr_name = parser.advance().lexeme.into_owned();
         parser.skip_tokens();
 
         // Continue consuming identifier characters if split across tokens
@@ -77,6 +90,7 @@ impl StmtHandler for PushStmtHandler {
             return Err("Expected ')' after push arguments".into());
         }
 
+        warnings::read(registry, &arr_name, line);
         Ok(Box::new(PushStmt { arr_name, value_expr }))
     }
 }
@@ -2,7 +2,7 @@
 // return [expression]
 
 use crate::languages::lumen::prelude::*;
-use crate::kernel::ast::{Control, ExprNode, StmtNode};
+use crate::kernel::ast::{Control, ExprNode, Step, StmtNode};
 use crate::kernel::parser::Parser;
 use crate::languages::lumen::patterns::PatternSet;
 use crate::kernel::runtime::Env;
@@ -14,13 +14,15 @@ struct ReturnStmt {
 
 impl StmtNode for ReturnStmt {
     fn exec(&self, env: &mut Env) -> LumenResult<Control> {
-        let val = if let Some(ref expr) = self.value {
-            expr.eval(env)?
-        } else {
-            Box::new(crate::languages::lumen::values::LumenNull) as crate::kernel::runtime::Value
-        };
+        crate::kernel::eval::run_stmt(self, env)
+    }
 
-        Ok(Control::Return(val))
+    fn step<'a>(&'a self, _env: &mut Env) -> LumenResult<Step<'a>> {
+        let Some(ref expr) = self.value else {
+            let null = Box::new(crate::languages::lumen::values::LumenNull) as crate::kernel::runtime::Value;
+            return Ok(Step::control(Control::Return(null)));
+        };
+        Ok(Step::eval(expr.as_ref(), |val, _env| Ok(Step::control(Control::Return(val)))))
     }
 }
 
@@ -31,6 +33,10 @@ impl StmtHandler for ReturnStmtHandler {
         parser.peek().lexeme == "return"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["return"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'return'
         parser.skip_tokens();
@@ -43,6 +43,10 @@ impl crate::languages::lumen::registry::StmtHandler for MemoizationHandler {
         parser.peek().lexeme == "MEMOIZATION"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["MEMOIZATION"]
+    }
+
     fn parse(&self, parser: &mut Parser, _registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'MEMOIZATION'
 
@@ -98,7 +102,7 @@ impl crate::languages::lumen::registry::StmtHandler for MemoizationHandler {
             "true" => true,
             "false" => false,
             _ => {
-                return Err(format!("MEMOIZATION must be set to 'true' or 'false', got: {}", value));
+                return Err(format!("MEMOIZATION must be set to 'true' or 'false', got: {}", value).into());
             }
         };
 
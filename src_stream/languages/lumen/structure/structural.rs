@@ -4,12 +4,13 @@
 // Handles Python-style indentation: 4-space indents, INDENT/DEDENT tokens.
 // Completely language-specific - ALL structural concepts defined here.
 
-use crate::kernel::ast::{Program, StmtNode};
-use crate::kernel::lexer::{Token, SpannedToken, Span};
-use crate::kernel::parser::Parser;
+use crate::kernel::ast::{LocatedStmt, Program, StmtNode};
+use crate::kernel::lexer::{LineCursor, Token, SpannedToken, Span};
+use crate::kernel::parser::{Parser, StatementSyntax};
 use crate::languages::lumen::patterns::PatternSet;
-use crate::kernel::registry::{err_at, LumenResult};
+use crate::kernel::registry::{err_at, LocatedError, LumenError, LumenResult};
 use crate::languages::lumen::registry::Registry;
+use crate::languages::lumen::{coverage, trace};
 
 // --------------------
 // Lumen Token Definitions (lexeme strings)
@@ -31,6 +32,16 @@ pub const DEDENT: &str = "DEDENT";
 // End of file
 pub const EOF: &str = "EOF";
 
+/// Where Lumen statements end, for skipping one that failed to parse
+const STATEMENTS: StatementSyntax = StatementSyntax {
+    block_open: INDENT,
+    block_close: DEDENT,
+    terminators: &[NEWLINE],
+    continuations: &["else"],
+    skip: &[],
+    end: EOF,
+};
+
 // --------------------
 // Structural Tokens Configuration
 // --------------------
@@ -82,10 +93,23 @@ pub fn parse_block(parser: &mut Parser, registry: &Registry) -> LumenResult<Vec<
 
     // Parse statements until DEDENT or EOF
     while parser.peek().lexeme != DEDENT && parser.peek().lexeme != EOF {
-        let s = registry
+        let start = parser.i;
+        let (line, col) = parser.position();
+        let parsed = registry
             .find_stmt(parser)
-            .ok_or_else(|| err_at(parser, "Unknown statement in block"))?
-            .parse(parser, registry)?;
+            .ok_or_else(|| err_at(parser, "Unknown statement in block"))
+            .and_then(|handler| handler.parse(parser, registry));
+        let s = match parsed {
+            Ok(s) => s,
+            Err(e) => {
+                parser.recover(e, start, &STATEMENTS);
+                consume_newlines(parser);
+                continue;
+            }
+        };
+        let s = LocatedStmt::wrap(s, line, col);
+        let s = trace::wrap(registry, s, line, col);
+        let s = coverage::wrap(registry, s, line);
 
         stmts.push(s);
         consume_newlines(parser);
@@ -100,20 +124,37 @@ pub fn parse_block(parser: &mut Parser, registry: &Registry) -> LumenResult<Vec<
 }
 
 /// Lumen-specific program parsing with newline handling.
-pub fn parse_program(parser: &mut Parser, registry: &Registry) -> LumenResult<Program> {
+pub fn parse_program(parser: &mut Parser, registry: &Registry) -> Result<Program, Vec<LocatedError>> {
     let mut stmts = Vec::new();
     consume_newlines(parser);
 
     while parser.peek().lexeme != EOF {
-        let stmt = registry
+        let start = parser.i;
+        let (line, col) = parser.position();
+        let parsed = registry
             .find_stmt(parser)
-            .ok_or_else(|| err_at(parser, "Unknown statement"))?
-            .parse(parser, registry)?;
+            .ok_or_else(|| err_at(parser, "Unknown statement"))
+            .and_then(|handler| handler.parse(parser, registry));
+        let stmt = match parsed {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                parser.recover(e, start, &STATEMENTS);
+                consume_newlines(parser);
+                continue;
+            }
+        };
+        let stmt = LocatedStmt::wrap(stmt, line, col);
+        let stmt = trace::wrap(registry, stmt, line, col);
+        let stmt = coverage::wrap(registry, stmt, line);
 
         stmts.push(stmt);
         consume_newlines(parser);
     }
 
+    // Every statement that failed to parse
+    if !parser.errors.is_empty() {
+        return Err(std::mem::take(&mut parser.errors));
+    }
     Ok(Program::new(stmts))
 }
 
@@ -127,11 +168,21 @@ pub fn parse_program(parser: &mut Parser, registry: &Registry) -> LumenResult<Pr
 ///
 /// Special handling: Newlines inside array literals (bracket depth > 0) are ignored,
 /// allowing multiline array syntax. Newlines are treated as whitespace when inside brackets.
+///
+/// Statement separator: ';' outside strings, brackets, and parentheses ends a
+/// statement exactly like a line break, so `a = 1; b = 2` holds two statements.
+///
+/// Line continuation: a line also continues onto the next one while a '(' is open,
+/// or when it ends with a backslash outside a string literal. The continued line's
+/// indentation is not significant and no NEWLINE is emitted between the two.
 pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> LumenResult<Vec<SpannedToken>> {
     let mut out = Vec::new();
     let mut indents = vec![0usize];
     let mut line_no = 1usize;
+    let mut line_tokens = LineCursor::new(&raw_tokens);
     let mut bracket_depth_global = 0i32;  // Track bracket depth across all lines
+    let mut paren_depth_global = 0i32;    // Track open parentheses across all lines
+    let mut continued = false;            // Previous line ended with a backslash
 
     for raw in source.lines() {
         // Count leading spaces
@@ -188,20 +239,24 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
             }
         }
 
-        // Check if we're inside an array at the start of this line
+        // A trailing backslash outside a string continues this line onto the next
+        let continues = !in_string_single && !in_string_double && rest.trim_end().ends_with('\\');
+
+        // Check if we're inside an array or a continued expression at the start of this line
         let inside_array = bracket_depth_global > 0;
+        let inside_continuation = paren_depth_global > 0 || continued;
 
         // Indentation handling (4-space indents for Lumen)
-        // But skip indentation processing if we're inside an array literal
-        if !inside_array {
+        // But skip indentation processing if we're inside an array literal or a continuation
+        if !inside_array && !inside_continuation {
             let current = *indents.last().unwrap();
             if spaces > current {
                 if (spaces - current) % 4 != 0 {
-                    return Err(format!("Invalid indentation at line {line_no}"));
+                    return Err(LumenError::Raised(LocatedError::at("Invalid indentation", line_no, spaces + 1)));
                 }
                 indents.push(spaces);
                 out.push(SpannedToken {
-                    tok: Token::new(INDENT.to_string(), Span::new(0, 0)),
+                    tok: Token::new(INDENT, Span::new(0, 0)),
                     line: line_no,
                     col: 1,
                 });
@@ -209,13 +264,13 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
                 while *indents.last().unwrap() > spaces {
                     indents.pop();
                     out.push(SpannedToken {
-                        tok: Token::new(DEDENT.to_string(), Span::new(0, 0)),
+                        tok: Token::new(DEDENT, Span::new(0, 0)),
                         line: line_no,
                         col: 1,
                     });
                 }
                 if *indents.last().unwrap() != spaces {
-                    return Err(format!("Indentation mismatch at line {line_no}"));
+                    return Err(LumenError::Raised(LocatedError::at("Indentation mismatch", line_no, spaces + 1)));
                 }
             }
         }
@@ -228,52 +283,64 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
         let mut in_string_double = false;
         let mut bracket_depth_line = bracket_depth_global;  // Start with global bracket depth
 
-        for raw_tok in &raw_tokens {
-            if raw_tok.line == line_no {
-                let lexeme = &raw_tok.tok.lexeme;
-
-                // Track bracket depth
-                if lexeme == "[" && !in_string_single && !in_string_double {
-                    bracket_depth_line += 1;
-                    bracket_depth_global += 1;
-                    out.push(raw_tok.clone());
-                } else if lexeme == "]" && !in_string_single && !in_string_double {
-                    bracket_depth_line -= 1;
-                    bracket_depth_global -= 1;
-                    out.push(raw_tok.clone());
-                } else if lexeme == "'" && !in_string_double {
-                    in_string_single = !in_string_single;
-                    out.push(raw_tok.clone());
-                } else if lexeme == "\"" && !in_string_single {
-                    in_string_double = !in_string_double;
-                    out.push(raw_tok.clone());
-                } else if in_string_single || in_string_double {
-                    // Inside a string - include everything, including whitespace
-                    out.push(raw_tok.clone());
-                } else if bracket_depth_line > 0 {
-                    // Inside an array literal - include everything, including newlines and whitespace
-                    // But skip the actual newline tokens (they're marked specially)
-                    if lexeme == "\n" || lexeme == "\r" {
-                        continue;  // Skip newline characters inside arrays - they're just whitespace
-                    }
-                    out.push(raw_tok.clone());
-                } else {
-                    // Outside both strings and arrays - filter whitespace tokens
-                    if lexeme.len() == 1 {
-                        let ch = lexeme.as_bytes()[0];
-                        if ch == b' ' || ch == b'\t' || ch == b'\n' || ch == b'\r' {
-                            continue;  // Skip whitespace outside strings and arrays
-                        }
+        for raw_tok in line_tokens.line(line_no) {
+            let lexeme = &raw_tok.tok.lexeme;
+
+            // Track bracket depth
+            if lexeme == "[" && !in_string_single && !in_string_double {
+                bracket_depth_line += 1;
+                bracket_depth_global += 1;
+                out.push(raw_tok.clone());
+            } else if lexeme == "]" && !in_string_single && !in_string_double {
+                bracket_depth_line -= 1;
+                bracket_depth_global -= 1;
+                out.push(raw_tok.clone());
+            } else if (lexeme == "(" || lexeme == ")") && !in_string_single && !in_string_double {
+                paren_depth_global += if lexeme == "(" { 1 } else { -1 };
+                out.push(raw_tok.clone());
+            } else if lexeme == "\\" && continues && !in_string_single && !in_string_double {
+                continue;  // Continuation marker - not part of the expression
+            } else if lexeme == "'" && !in_string_double {
+                in_string_single = !in_string_single;
+                out.push(raw_tok.clone());
+            } else if lexeme == "\"" && !in_string_single {
+                in_string_double = !in_string_double;
+                out.push(raw_tok.clone());
+            } else if in_string_single || in_string_double {
+                // Inside a string - include everything, including whitespace
+                out.push(raw_tok.clone());
+            } else if bracket_depth_line > 0 {
+                // Inside an array literal - include everything, including newlines and whitespace
+                // But skip the actual newline tokens (they're marked specially)
+                if lexeme == "\n" || lexeme == "\r" {
+                    continue;  // Skip newline characters inside arrays - they're just whitespace
+                }
+                out.push(raw_tok.clone());
+            } else if lexeme == ";" && paren_depth_global == 0 {
+                // Statement separator - equivalent to ending the line here
+                out.push(SpannedToken {
+                    tok: Token::new(NEWLINE, Span::new(0, 0)),
+                    line: line_no,
+                    col: raw_tok.col,
+                });
+            } else {
+                // Outside both strings and arrays - filter whitespace tokens
+                if lexeme.len() == 1 {
+                    let ch = lexeme.as_bytes()[0];
+                    if ch == b' ' || ch == b'\t' || ch == b'\n' || ch == b'\r' {
+                        continue;  // Skip whitespace outside strings and arrays
                     }
-                    out.push(raw_tok.clone());
                 }
+                out.push(raw_tok.clone());
             }
         }
 
         // Add NEWLINE at end of line, but only if we're not inside an array literal
-        if bracket_depth_global == 0 {
+        // and the line does not continue onto the next one
+        continued = continues;
+        if bracket_depth_global == 0 && paren_depth_global == 0 && !continued {
             out.push(SpannedToken {
-                tok: Token::new(NEWLINE.to_string(), Span::new(0, 0)),
+                tok: Token::new(NEWLINE, Span::new(0, 0)),
                 line: line_no,
                 col: spaces + rest.len() + 1,
             });
@@ -286,7 +353,7 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
     while indents.len() > 1 {
         indents.pop();
         out.push(SpannedToken {
-            tok: Token::new(DEDENT.to_string(), Span::new(0, 0)),
+            tok: Token::new(DEDENT, Span::new(0, 0)),
             line: line_no,
             col: 1,
         });
@@ -294,7 +361,7 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
 
     // Add EOF token
     out.push(SpannedToken {
-        tok: Token::new(EOF.to_string(), Span::new(0, 0)),
+        tok: Token::new(EOF, Span::new(0, 0)),
         line: line_no,
         col: 1,
     });
@@ -0,0 +1,62 @@
+// System-provided bindings of a Lumen program
+//
+// Bound before the first statement runs, by the stream binary and by Interpreter:
+// ARGS, ARGC, the kind constants (INTEGER, ..., NULL), REAL_DEFAULT_PRECISION and FEATURES.
+// ARGS, ARGC and FEATURES are read-only for user code; host constants are bound (and
+// protected) by the caller after these.
+
+use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::state::State;
+use crate::languages::lumen::statements::assignment::protect_binding;
+use crate::languages::lumen::values::{
+    KindValue, LumenArray, LumenBool, LumenKind, LumenMap, LumenNumber, LumenString,
+};
+use num_bigint::BigInt;
+
+/// Bind the system values, with ARGS holding the program arguments and ARGC their count
+pub fn bind(env: &mut Env, program_args: &[String]) {
+    // Bind ARGS: an ARRAY with a STRING per program argument, as the shell passed it
+    // (an argument keeps its spaces), and ARGC: how many there are
+    // Both are immutable and read-only (cannot be reassigned by user code)
+    let args: Vec<Value> = program_args.iter().map(|arg| Box::new(LumenString::new(arg.clone())) as Value).collect();
+    env.define("ARGS".to_string(), Box::new(LumenArray::new(args)));
+    protect_binding(env, "ARGS");
+    env.define("ARGC".to_string(), Box::new(LumenNumber::new(BigInt::from(program_args.len()))));
+    protect_binding(env, "ARGC");
+
+    // Bind kind meta-value constants: INTEGER, RATIONAL, REAL, STRING, BOOLEAN, ARRAY, MAP, NULL, HANDLE, BYTES
+    // These are predefined kernel-level type descriptors that match kind() return values
+    env.define("INTEGER".to_string(), Box::new(LumenKind::new(KindValue::INTEGER)));
+    env.define("RATIONAL".to_string(), Box::new(LumenKind::new(KindValue::RATIONAL)));
+    env.define("REAL".to_string(), Box::new(LumenKind::new(KindValue::REAL)));
+    env.define("STRING".to_string(), Box::new(LumenKind::new(KindValue::STRING)));
+    env.define("BOOLEAN".to_string(), Box::new(LumenKind::new(KindValue::BOOLEAN)));
+    env.define("ARRAY".to_string(), Box::new(LumenKind::new(KindValue::ARRAY)));
+    env.define("MAP".to_string(), Box::new(LumenKind::new(KindValue::MAP)));
+    env.define("NULL".to_string(), Box::new(LumenKind::new(KindValue::NULL)));
+    env.define("HANDLE".to_string(), Box::new(LumenKind::new(KindValue::HANDLE)));
+    env.define("BYTES".to_string(), Box::new(LumenKind::new(KindValue::BYTES)));
+
+    // Bind kernel constant: REAL_DEFAULT_PRECISION
+    env.define("REAL_DEFAULT_PRECISION".to_string(), Box::new(LumenNumber::new(BigInt::from(15))));
+
+    // Bind FEATURES: read-only map of the optional subsystems this kernel provides
+    // Library code checks these to degrade gracefully when a subsystem is missing.
+    let capabilities: Vec<Value> = State::of(env)
+        .capabilities
+        .selectors()
+        .into_iter()
+        .map(|c| Box::new(LumenString::new(c)) as Value)
+        .collect();
+    // Under --intervals REALs are balls: a bigfloat value and a bound on its error
+    let real_backend = if State::of(env).intervals { "ball" } else { "bigfloat" };
+    let features: Vec<(String, Value)> = vec![
+        ("kernel".to_string(), Box::new(LumenString::new("stream".to_string()))),
+        ("real_backend".to_string(), Box::new(LumenString::new(real_backend.to_string()))),
+        ("extern".to_string(), Box::new(LumenBool::new(true))),
+        ("extern_capabilities".to_string(), Box::new(LumenArray::new(capabilities))),
+        ("memoization".to_string(), Box::new(LumenBool::new(true))),
+    ];
+    env.define("FEATURES".to_string(), Box::new(LumenMap::new(features)));
+    protect_binding(env, "FEATURES");
+}
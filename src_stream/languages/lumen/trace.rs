@@ -0,0 +1,143 @@
+// Statement tracing for --trace
+//
+// When tracing is enabled before parsing, every statement of the user program is
+// wrapped in a TracedStmt carrying its source position. Each one prints a line to
+// stderr before it runs:
+//
+//   [trace] 12:5 depth 1 | total = total + i
+//
+// depth is the number of loop bodies currently executing (function calls included),
+// counted in the run's State. With --trace=vars, bindings that a statement added or
+// changed are printed after it. Prelude statements are never traced.
+//
+// Tracing is enabled on the Registry the program is parsed with, so only that program's
+// statements are wrapped.
+//
+// The same wrapper reports the statement events of lib_lumen/spans.rs when a subscriber
+// takes them (cargo feature "tracing"), with or without --trace.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::kernel::ast::{Control, Step, StmtNode};
+use crate::kernel::runtime::Env;
+use crate::languages::lumen::prelude::*;
+use crate::languages::lumen::registry::Registry;
+use crate::languages::lumen::state::State;
+use crate::spans;
+
+/// What --trace prints for the statements of a program (see Registry::trace)
+pub struct TraceConfig {
+    /// Lines before this belong to the prelude
+    first_line: usize,
+    /// User source lines, for printing each statement
+    lines: Vec<String>,
+    vars: bool,
+}
+
+/// Turn tracing on for statements parsed from now on with `registry`
+/// `first_line` is the line where `source` starts in the text handed to the lexer.
+/// The run counts loop depth only if its State::loop_depth is set.
+pub fn enable(registry: &mut Registry, first_line: usize, source: &str, vars: bool) {
+    let lines = source.lines().map(str::to_string).collect();
+    registry.set_trace(TraceConfig { first_line, lines, vars });
+}
+
+/// Wrap a parsed statement so it is traced, or return it unchanged when tracing is off
+pub fn wrap(registry: &Registry, stmt: Box<dyn StmtNode>, line: usize, col: usize) -> Box<dyn StmtNode> {
+    let printed = registry.trace().filter(|config| line >= config.first_line).map(|config| {
+        let user_line = line - config.first_line + 1;
+        let text = config.lines.get(user_line - 1).map(|l| l.trim().to_string()).unwrap_or_default();
+        Printed { line: user_line, text, vars: config.vars }
+    });
+    let observed = spans::statements_observed() && spans::program_line(line).is_some();
+    if printed.is_none() && !observed {
+        return stmt;
+    }
+    Box::new(TracedStmt { inner: stmt, at: (line, col), printed, observed })
+}
+
+/// Marks one loop body as running until dropped (counted only while tracing)
+pub struct LoopBody(Option<Arc<AtomicUsize>>);
+
+impl LoopBody {
+    pub fn enter(env: &mut Env) -> Self {
+        let depth = State::of(env).loop_depth.clone();
+        if let Some(depth) = &depth {
+            depth.fetch_add(1, Ordering::Relaxed);
+        }
+        LoopBody(depth)
+    }
+}
+
+impl Drop for LoopBody {
+    fn drop(&mut self) {
+        if let Some(depth) = &self.0 {
+            depth.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The line --trace prints for a statement
+#[derive(Debug)]
+struct Printed {
+    /// Line in the user program
+    line: usize,
+    text: String,
+    vars: bool,
+}
+
+#[derive(Debug)]
+struct TracedStmt {
+    inner: Box<dyn StmtNode>,
+    /// Position in the lexed text
+    at: (usize, usize),
+    /// What --trace prints for it
+    printed: Option<Printed>,
+    /// Whether it reports a statement event
+    observed: bool,
+}
+
+impl StmtNode for TracedStmt {
+    fn exec(&self, env: &mut Env) -> LumenResult<Control> {
+        crate::kernel::eval::run_stmt(self, env)
+    }
+
+    fn step<'a>(&'a self, env: &mut Env) -> LumenResult<Step<'a>> {
+        if self.observed {
+            spans::statement(self.at.0, self.at.1);
+        }
+        let Some(printed) = &self.printed else {
+            return self.inner.step(env);
+        };
+        let depth = State::of(env).loop_depth.as_ref().map_or(0, |depth| depth.load(Ordering::Relaxed));
+        eprintln!("[trace] {}:{} depth {} | {}", printed.line, self.at.1, depth, printed.text);
+
+        if !printed.vars {
+            return self.inner.step(env);
+        }
+        let before = snapshot(env);
+        Ok(Step::exec(self.inner.as_ref(), move |control, env| {
+            let mut changes: Vec<(String, String)> = snapshot(env)
+                .into_iter()
+                .filter(|(name, value)| before.get(name) != Some(value))
+                .collect();
+            changes.sort();
+            for (name, value) in changes {
+                match before.get(&name) {
+                    Some(old) => eprintln!("[trace]     {} = {} (was {})", name, value, old),
+                    None => eprintln!("[trace]     {} = {}", name, value),
+                }
+            }
+            Ok(Step::control(control))
+        }))
+    }
+}
+
+fn snapshot(env: &Env) -> HashMap<String, String> {
+    env.visible_bindings()
+        .into_iter()
+        .map(|(name, value)| (name, value.as_display_string()))
+        .collect()
+}
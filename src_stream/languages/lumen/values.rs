@@ -4,11 +4,17 @@
 // These are the concrete implementations of the kernel's RuntimeValue trait.
 // Only Lumen code knows what numbers, booleans, and strings mean.
 
-use crate::kernel::runtime::RuntimeValue;
+use crate::kernel::repr::{self, Node};
+use crate::kernel::runtime::{Captured, RuntimeValue};
+use crate::languages::lumen::interval::{self, Radius};
+use crate::languages::lumen::numeric::bigfloat;
 use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
 use num_bigint::BigInt;
 use num_integer::gcd;
-use num_traits::Signed;
+use num_traits::{ToPrimitive, Zero};
 
 /// Lumen rational number value - stored as (numerator, denominator) in canonical reduced form
 /// Always stored reduced: gcd(numerator, denominator) = 1, denominator > 0
@@ -92,7 +98,7 @@ impl RuntimeValue for LumenRational {
             Ok(self.numerator == other_rat.numerator && self.denominator == other_rat.denominator)
         } else if let Some(other_num) = other.as_any().downcast_ref::<LumenNumber>() {
             // Compare rational with integer
-            Ok(self.is_integer() && self.numerator == other_num.value)
+            Ok(self.is_integer() && self.numerator == *other_num.value.big())
         } else {
             Err("Cannot compare rational with non-numeric value".to_string())
         }
@@ -105,17 +111,150 @@ impl RuntimeValue for LumenRational {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + digit_bytes(&self.numerator) + digit_bytes(&self.denominator)
+    }
+}
+
+/// An integer of any size, kept in an i64 while it fits and as a BigInt only beyond that,
+/// so counters and indexes do not allocate digits on every step
+/// Always canonical: Big never holds a value that fits in an i64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Integer {
+    Small(i64),
+    Big(BigInt),
+}
+
+impl Integer {
+    /// The value as a BigInt, borrowed when it is one
+    pub fn big(&self) -> Cow<'_, BigInt> {
+        match self {
+            Integer::Small(n) => Cow::Owned(BigInt::from(*n)),
+            Integer::Big(n) => Cow::Borrowed(n),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Integer::Small(0)
+    }
+
+    /// `op` on two i64s, or `big` on the BigInts when that overflows
+    fn combine(
+        &self,
+        other: &Integer,
+        op: fn(i64, i64) -> Option<i64>,
+        big: fn(&BigInt, &BigInt) -> BigInt,
+    ) -> Integer {
+        if let (Integer::Small(a), Integer::Small(b)) = (self, other) {
+            if let Some(n) = op(*a, *b) {
+                return Integer::Small(n);
+            }
+        }
+        Integer::from(big(&self.big(), &other.big()))
+    }
+
+    pub fn add(&self, other: &Integer) -> Integer {
+        self.combine(other, i64::checked_add, |a, b| a + b)
+    }
+
+    pub fn sub(&self, other: &Integer) -> Integer {
+        self.combine(other, i64::checked_sub, |a, b| a - b)
+    }
+
+    pub fn mul(&self, other: &Integer) -> Integer {
+        self.combine(other, i64::checked_mul, |a, b| a * b)
+    }
+
+    /// Quotient truncated towards zero (the divisor must not be zero)
+    pub fn div(&self, other: &Integer) -> Integer {
+        self.combine(other, i64::checked_div, |a, b| a / b)
+    }
+
+    /// Remainder with the sign of the dividend (the divisor must not be zero)
+    pub fn rem(&self, other: &Integer) -> Integer {
+        self.combine(other, i64::checked_rem, |a, b| a % b)
+    }
+
+    pub fn neg(&self) -> Integer {
+        match self {
+            Integer::Small(n) => n.checked_neg().map_or_else(|| Integer::from(-BigInt::from(*n)), Integer::Small),
+            Integer::Big(n) => Integer::from(-n),
+        }
+    }
+
+    pub fn pow(&self, exponent: u32) -> Integer {
+        match self {
+            Integer::Small(n) => n.checked_pow(exponent).map_or_else(|| Integer::from(BigInt::from(*n).pow(exponent)), Integer::Small),
+            Integer::Big(n) => Integer::from(n.pow(exponent)),
+        }
+    }
 }
 
-/// Lumen number value - stored as BigInt for arbitrary precision
+impl ToPrimitive for Integer {
+    fn to_i64(&self) -> Option<i64> {
+        match self {
+            Integer::Small(n) => Some(*n),
+            Integer::Big(_) => None,
+        }
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        match self {
+            Integer::Small(n) => n.to_u64(),
+            Integer::Big(n) => n.to_u64(),
+        }
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(n: i64) -> Self {
+        Integer::Small(n)
+    }
+}
+
+impl From<BigInt> for Integer {
+    fn from(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(small) => Integer::Small(small),
+            None => Integer::Big(n),
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => a.cmp(b),
+            _ => self.big().cmp(&other.big()),
+        }
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integer::Small(n) => n.fmt(f),
+            Integer::Big(n) => n.fmt(f),
+        }
+    }
+}
+
+/// Lumen number value - an integer of arbitrary precision
 #[derive(Debug, Clone, PartialEq)]
 pub struct LumenNumber {
-    pub value: BigInt,
+    pub value: Integer,
 }
 
 impl LumenNumber {
-    pub fn new(value: BigInt) -> Self {
-        Self { value }
+    pub fn new(value: impl Into<Integer>) -> Self {
+        Self { value: value.into() }
     }
 }
 
@@ -147,6 +286,13 @@ impl RuntimeValue for LumenNumber {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn heap_bytes(&self) -> usize {
+        match &self.value {
+            Integer::Small(_) => std::mem::size_of::<Self>(),
+            Integer::Big(n) => std::mem::size_of::<Self>() + digit_bytes(n),
+        }
+    }
 }
 
 /// Lumen boolean value
@@ -216,6 +362,10 @@ impl RuntimeValue for LumenString {
         self.value.clone()
     }
 
+    fn repr_node(&self) -> Node<'_> {
+        Node::Text(&self.value)
+    }
+
     fn eq_value(&self, other: &dyn RuntimeValue) -> Result<bool, String> {
         if let Some(other_str) = other.as_any().downcast_ref::<LumenString>() {
             Ok(self.value == other_str.value)
@@ -231,6 +381,65 @@ impl RuntimeValue for LumenString {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.value.len()
+    }
+}
+
+/// Lumen bytes value - binary data (io:read_bytes, bytes()); its kind is BYTES
+#[derive(Debug, Clone, PartialEq)]
+pub struct LumenBytes {
+    pub value: Vec<u8>,
+}
+
+impl LumenBytes {
+    pub fn new(value: Vec<u8>) -> Self {
+        Self { value }
+    }
+}
+
+impl RuntimeValue for LumenBytes {
+    fn clone_boxed(&self) -> Box<dyn RuntimeValue> {
+        Box::new(self.clone())
+    }
+
+    fn as_debug_string(&self) -> String {
+        format!("Bytes({})", self.as_display_string())
+    }
+
+    fn as_display_string(&self) -> String {
+        repr::render(&self.repr_node(), &repr::DISPLAY)
+    }
+
+    fn repr_node(&self) -> Node<'_> {
+        Node::Bytes(&self.value)
+    }
+
+    fn eq_value(&self, other: &dyn RuntimeValue) -> Result<bool, String> {
+        if let Some(other_bytes) = other.as_any().downcast_ref::<LumenBytes>() {
+            Ok(self.value == other_bytes.value)
+        } else {
+            Err("Cannot compare bytes with non-bytes".to_string())
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.value.len()
+    }
+}
+
+/// Bytes of the digits of a number (for heap_bytes)
+fn digit_bytes(n: &BigInt) -> usize {
+    (n.bits() / 8) as usize
 }
 
 /// Helper to extract a LumenRational if the value is one.
@@ -264,97 +473,59 @@ pub fn as_string(val: &dyn RuntimeValue) -> Result<&LumenString, String> {
 
 /// Lumen real number value - decimal approximation with configurable precision
 /// Stored as (numerator, denominator) with an associated precision in significant digits
-/// The value is maintained exactly internally but displayed/compared with specified precision
+/// The value is a decimal float of at most that many digits, or inf or nan as n/0
+/// (see lib_lumen/bigfloat.rs)
 #[derive(Debug, Clone, PartialEq)]
 pub struct LumenReal {
     pub numerator: BigInt,
     pub denominator: BigInt,
     pub precision: usize, // Number of significant digits
+    /// Under --intervals, a bound on the value's error (see interval.rs); None otherwise
+    pub radius: Option<Radius>,
 }
 
 impl LumenReal {
     /// Create a real from a numerator and denominator with specified precision
     /// Precision specifies significant digits (default 15)
+    /// A zero denominator gives inf or nan.
     pub fn new(num: BigInt, denom: BigInt, precision: usize) -> Self {
-        // Handle zero denominator
-        if denom == BigInt::from(0) {
-            panic!("Denominator cannot be zero");
-        }
-
-        // If numerator is zero, return 0 with specified precision
-        if num == BigInt::from(0) {
-            return Self {
-                numerator: BigInt::from(0),
-                denominator: BigInt::from(1),
-                precision,
-            };
-        }
+        // Round once to the precision (a decimal float, see lib_lumen/bigfloat.rs)
+        let (numerator, denominator) = bigfloat::round_real(&num, &denom, precision);
+        Self { numerator, denominator, precision, radius: None }
+    }
 
-        // Normalize sign: always keep denominator positive
-        let (numerator, denominator) = if denom < BigInt::from(0) {
-            (-num, -denom)
-        } else {
-            (num, denom)
+    /// `new`, carrying an error bound when `error` (the error of num/denom) is given:
+    /// the bound grows by what the rounding loses (inf and nan have none)
+    pub fn with_error(num: BigInt, denom: BigInt, precision: usize, error: Option<Radius>) -> Self {
+        let mut real = Self::new(num.clone(), denom.clone(), precision);
+        let Some(error) = error.filter(|_| !real.is_special()) else {
+            return real;
         };
-
-        // Reduce to canonical form by dividing by GCD
-        let g = gcd(numerator.clone(), denominator.clone());
-        Self {
-            numerator: numerator / &g,
-            denominator: denominator / &g,
-            precision,
-        }
+        real.radius = Some(interval::after_rounding(&error, (&num, &denom), (&real.numerator, &real.denominator)));
+        real
     }
 
-    /// Convert to integer by truncating toward zero
-    pub fn to_integer(&self) -> BigInt {
-        &self.numerator / &self.denominator
+    /// Whether the value is inf, -inf or nan
+    pub fn is_special(&self) -> bool {
+        self.denominator.is_zero()
     }
 
-    /// Get string representation with the stored precision
-    /// This renders the decimal with significant figures truncated/rounded
-    pub fn as_decimal_string(&self) -> String {
-        // Simple approach: compute as fixed-point and format
-        // For true significant digit handling, we'd need more sophisticated rounding
-        // For now, we'll compute the result and show it with reasonable precision
-
-        let int_part = self.to_integer();
-        let remainder = self.numerator.clone() - (&int_part * &self.denominator);
-
-        if remainder == BigInt::from(0) {
-            return int_part.to_string();
+    /// The integer part, truncated toward zero (an error for inf and nan, or if the error
+    /// bound leaves it uncertain)
+    pub fn integer_part(&self) -> Result<BigInt, String> {
+        match &self.radius {
+            Some(radius) => interval::integer_part((&self.numerator, &self.denominator), radius),
+            None => bigfloat::integer_part(&self.numerator, &self.denominator),
         }
+    }
 
-        // Compute decimal places needed for precision
-        let mut decimal_str = String::new();
-        let mut digit_count = int_part.to_string().len();
-        let target_digits = self.precision;
-        let mut remainder = remainder.abs();
-
-        // If int part has fewer digits than target, include fractional digits
-        let mut frac_digits = if digit_count >= target_digits {
-            0
-        } else {
-            target_digits - digit_count
-        };
-
-        // Compute fractional part
-        let mut has_nonzero = false;
-        while frac_digits > 0 && remainder > BigInt::from(0) {
-            remainder = remainder * BigInt::from(10);
-            let digit = &remainder / &self.denominator;
-            if digit > BigInt::from(0) || has_nonzero {
-                if !has_nonzero {
-                    decimal_str.push('.');
-                    has_nonzero = true;
-                }
-                decimal_str.push_str(&digit.to_string());
-            }
-            remainder = remainder - (&digit * &self.denominator);
-            frac_digits -= 1;
+    /// Decimal notation of the value, rounded to its precision
+    /// With an error bound, only the digits it leaves certain are written.
+    pub fn as_decimal_string(&self) -> String {
+        match &self.radius {
+            Some(radius) => interval::certified_string((&self.numerator, &self.denominator), radius, self.precision),
+            None => bigfloat::decimal_string(&self.numerator, &self.denominator, self.precision),
         }
-
-        format!("{}{}", int_part, decimal_str)
     }
 }
 
@@ -374,13 +545,15 @@ impl RuntimeValue for LumenReal {
     fn eq_value(&self, other: &dyn RuntimeValue) -> Result<bool, String> {
         if let Some(other_real) = other.as_any().downcast_ref::<LumenReal>() {
             // Compare the exact rational values (precision doesn't affect equality of stored value)
-            Ok(self.numerator == other_real.numerator && self.denominator == other_real.denominator)
+            // nan equals nothing, itself included
+            let nan = self.numerator.is_zero() && self.is_special();
+            Ok(self.numerator == other_real.numerator && self.denominator == other_real.denominator && !nan)
         } else if let Some(other_rat) = other.as_any().downcast_ref::<LumenRational>() {
             // Compare real with rational
             Ok(self.numerator == other_rat.numerator && self.denominator == other_rat.denominator)
         } else if let Some(other_num) = other.as_any().downcast_ref::<LumenNumber>() {
             // Compare real with integer
-            Ok(self.numerator == other_num.value && self.denominator == BigInt::from(1))
+            Ok(self.numerator == *other_num.value.big() && self.denominator == BigInt::from(1))
         } else {
             Err("Cannot compare real with non-numeric value".to_string())
         }
@@ -393,6 +566,10 @@ impl RuntimeValue for LumenReal {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + digit_bytes(&self.numerator) + digit_bytes(&self.denominator)
+    }
 }
 
 /// Helper to extract a LumenReal if the value is one.
@@ -402,6 +579,17 @@ pub fn as_real(val: &dyn RuntimeValue) -> Result<&LumenReal, String> {
         .ok_or_else(|| "Expected a real value".to_string())
 }
 
+/// The exact (numerator, denominator) of an integer, rational, or real (inf and nan are n/0)
+pub fn as_exact(val: &dyn RuntimeValue) -> Option<(BigInt, BigInt)> {
+    if let Ok(real) = as_real(val) {
+        return Some((real.numerator.clone(), real.denominator.clone()));
+    }
+    if let Ok(rat) = as_rational(val) {
+        return Some((rat.numerator.clone(), rat.denominator.clone()));
+    }
+    as_number(val).ok().map(|num| (num.value.big().into_owned(), BigInt::from(1)))
+}
+
 /// Lumen null (unit) value
 #[derive(Debug, Clone, PartialEq)]
 pub struct LumenNull;
@@ -470,13 +658,11 @@ impl RuntimeValue for LumenArray {
     }
 
     fn as_display_string(&self) -> String {
-        let elements_str = self
-            .elements
-            .iter()
-            .map(|e| e.as_display_string())
-            .collect::<Vec<_>>()
-            .join(", ");
-        format!("[{}]", elements_str)
+        repr::render(&self.repr_node(), &repr::DISPLAY)
+    }
+
+    fn repr_node(&self) -> Node<'_> {
+        Node::List(self.elements.iter().map(|e| e.repr_node()).collect())
     }
 
     fn eq_value(&self, other: &dyn RuntimeValue) -> Result<bool, String> {
@@ -504,10 +690,82 @@ impl RuntimeValue for LumenArray {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.elements.iter().map(|e| e.heap_bytes()).sum::<usize>()
+    }
+}
+
+/// Lumen map value - string-keyed entries kept in insertion order
+#[derive(Debug, Clone, PartialEq)]
+pub struct LumenMap {
+    pub entries: Vec<(String, Box<dyn RuntimeValue>)>,
+}
+
+impl LumenMap {
+    pub fn new(entries: Vec<(String, Box<dyn RuntimeValue>)>) -> Self {
+        Self { entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&dyn RuntimeValue> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_ref())
+    }
+}
+
+impl RuntimeValue for LumenMap {
+    fn clone_boxed(&self) -> Box<dyn RuntimeValue> {
+        Box::new(self.clone())
+    }
+
+    fn as_debug_string(&self) -> String {
+        let entries_str = self
+            .entries
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v.as_debug_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("Map({{{}}})", entries_str)
+    }
+
+    fn as_display_string(&self) -> String {
+        repr::render(&self.repr_node(), &repr::DISPLAY)
+    }
+
+    fn repr_node(&self) -> Node<'_> {
+        Node::Map(self.entries.iter().map(|(k, v)| (k.as_str(), v.repr_node())).collect())
+    }
+
+    fn eq_value(&self, other: &dyn RuntimeValue) -> Result<bool, String> {
+        if let Some(other_map) = other.as_any().downcast_ref::<LumenMap>() {
+            if self.entries.len() != other_map.entries.len() {
+                return Ok(false);
+            }
+            for ((ka, a), (kb, b)) in self.entries.iter().zip(other_map.entries.iter()) {
+                if ka != kb || !a.eq_value(b.as_ref())? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        } else {
+            Err("Cannot compare map with non-map".to_string())
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.entries.iter().map(|(k, v)| k.len() + v.heap_bytes()).sum::<usize>()
+    }
 }
 
-/// Lumen symbol value - represents symbolic constants like kind names
-/// Used for returning category/type information from introspection functions
+/// Lumen symbol value - an opaque handle from an extern backend (net connections and
+/// listeners); its kind is HANDLE and programs cannot construct one
 #[derive(Debug, Clone, PartialEq)]
 pub struct LumenSymbol {
     pub name: String,
@@ -547,9 +805,67 @@ impl RuntimeValue for LumenSymbol {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.name.len()
+    }
+}
+
+/// Lumen function value - a user-defined function named where a value goes, so a program
+/// can hand it to an extern capability that calls it back (see Host in
+/// extern_system/registry.rs). Calls still name functions directly.
+/// A function value closes over the scopes it was named in: a call back runs in them,
+/// even after the function that created them has returned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LumenFunction {
+    pub name: String,
+    pub params: Vec<String>,
+    pub captured: Option<Captured>,
+}
+
+impl LumenFunction {
+    pub fn new(name: String, params: Vec<String>) -> Self {
+        Self { name, params, captured: None }
+    }
+
+    /// The same function, run in `captured` when called back
+    pub fn closing_over(self, captured: Captured) -> Self {
+        Self { captured: Some(captured), ..self }
+    }
+}
+
+impl RuntimeValue for LumenFunction {
+    fn clone_boxed(&self) -> Box<dyn RuntimeValue> {
+        Box::new(self.clone())
+    }
+
+    fn as_debug_string(&self) -> String {
+        format!("Function({})", self.name)
+    }
+
+    fn as_display_string(&self) -> String {
+        format!("<function({})>", self.params.join(", "))
+    }
+
+    fn eq_value(&self, _other: &dyn RuntimeValue) -> Result<bool, String> {
+        // As in the microcode kernel, no function equals another value
+        Ok(false)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.name.len() + self.params.iter().map(String::len).sum::<usize>()
+    }
 }
 
-/// Kind meta-value enum - the 7 possible runtime type descriptors
+/// Kind meta-value enum - the 10 possible runtime type descriptors
 /// These form a closed set defined by the kernel
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KindValue {
@@ -559,7 +875,10 @@ pub enum KindValue {
     STRING,
     BOOLEAN,
     ARRAY,
+    MAP,
     NULL,
+    HANDLE,
+    BYTES,
 }
 
 /// Lumen kind value - kernel-level type descriptor meta-value
@@ -593,7 +912,10 @@ impl RuntimeValue for LumenKind {
             KindValue::STRING => "STRING".to_string(),
             KindValue::BOOLEAN => "BOOLEAN".to_string(),
             KindValue::ARRAY => "ARRAY".to_string(),
+            KindValue::MAP => "MAP".to_string(),
             KindValue::NULL => "NULL".to_string(),
+            KindValue::HANDLE => "HANDLE".to_string(),
+            KindValue::BYTES => "BYTES".to_string(),
         }
     }
 
@@ -621,3 +943,25 @@ pub fn as_array(val: &dyn RuntimeValue) -> Result<&LumenArray, String> {
         .ok_or_else(|| "Expected an array value".to_string())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_move_to_bigint_on_overflow_and_back() {
+        let max = Integer::from(i64::MAX);
+        let one = Integer::from(1);
+        let beyond = max.add(&one);
+        assert_eq!(beyond, Integer::Big(BigInt::from(i64::MAX) + 1));
+        assert_eq!(beyond.to_string(), "9223372036854775808");
+        assert_eq!(beyond.sub(&one), max);
+        assert!(beyond > max);
+
+        let min = Integer::from(i64::MIN);
+        assert_eq!(min.neg(), beyond);
+        assert_eq!(min.div(&Integer::from(-1)), beyond);
+        assert_eq!(min.rem(&Integer::from(-1)), Integer::from(0));
+        assert_eq!(Integer::from(3).pow(40).to_string(), "12157665459056928801");
+        assert_eq!(Integer::from(BigInt::from(-7)), Integer::Small(-7));
+    }
+}
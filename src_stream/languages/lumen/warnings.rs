@@ -0,0 +1,251 @@
+// Warnings: non-fatal diagnostics for the user program
+//
+// The statement and expression handlers report names as they parse them, to the Registry
+// the program is parsed with; once the program is parsed, `check_names` turns what was
+// collected into warnings:
+//
+//   unused-variable          a variable or parameter that is assigned but never read
+//   shadowed-name            a local with the same name as a global (assignment inside a
+//                            function always binds a new local)
+//
+// While the program runs, comparisons report to the run's sink (State::warnings):
+//
+//   incompatible-comparison  == or != between values of kinds that can never be equal
+//                            (numbers of any kind compare with each other; null is exempt)
+//
+// The name rules are the microcode kernel's lint rules of the same names, except that a
+// parameter named like a global is not reported: taking a global's name for a parameter
+// is common and means nothing else. Names starting with '_' are never reported as unused,
+// and prelude code is never checked. Each warning is a message with the position it is
+// about, for diagnostics::warn. Nothing is collected unless the binary asks for it.
+//
+// With --deny-warnings the program does not run if it parsed with warnings, and an
+// incompatible comparison stops it with a runtime error.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::kernel::control_flow::LocatedError;
+use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::prelude::*;
+use crate::languages::lumen::registry::Registry;
+use crate::languages::lumen::state::State;
+
+/// Names bound and read in one scope (the program top level or one function body)
+#[derive(Debug, Default)]
+struct Names {
+    /// Bound names with the position of their first binding (parameters come first)
+    bound: Vec<(String, usize, usize)>,
+    read: HashSet<String>,
+}
+
+impl Names {
+    fn bind(&mut self, name: &str, line: usize, col: usize) {
+        if !self.bound.iter().any(|(n, _, _)| n == name) {
+            self.bound.push((name.to_string(), line, col));
+        }
+    }
+}
+
+/// A function body being parsed, or parsed
+#[derive(Debug)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    names: Names,
+    /// Defined in the user program (prelude functions are not checked)
+    user: bool,
+}
+
+/// The names of a program being parsed (see Registry::warnings)
+#[derive(Debug, Default)]
+pub struct Warnings {
+    /// Lines before this belong to the prelude
+    first_line: usize,
+    top: Names,
+    /// Functions whose bodies are being parsed, innermost last
+    open: Vec<Function>,
+    functions: Vec<Function>,
+    all_reads: HashSet<String>,
+}
+
+/// Warnings raised while a program runs; the binary keeps a handle to read them after
+#[derive(Debug, Default)]
+pub struct RuntimeWarnings {
+    deny: bool,
+    /// Comparisons already reported, so a comparison in a loop warns once
+    compared: HashSet<(usize, usize)>,
+    found: Vec<LocatedError>,
+}
+
+/// Where a run sends its warnings (State::warnings)
+pub type WarningSink = Arc<Mutex<RuntimeWarnings>>;
+
+impl RuntimeWarnings {
+    /// A sink for a run; with `deny` an incompatible comparison is an error instead
+    pub fn sink(deny: bool) -> WarningSink {
+        Arc::new(Mutex::new(RuntimeWarnings { deny, ..RuntimeWarnings::default() }))
+    }
+
+    /// The warnings raised so far
+    pub fn take(&mut self) -> Vec<LocatedError> {
+        std::mem::take(&mut self.found)
+    }
+}
+
+/// Start collecting names for code parsed from now on with `registry`
+/// `first_line` is the line where the user program starts in the text handed to the lexer.
+pub fn enable(registry: &Registry, first_line: usize) {
+    *registry.warnings().borrow_mut() = Some(Warnings { first_line, ..Warnings::default() });
+}
+
+fn with<R>(registry: &Registry, f: impl FnOnce(&mut Warnings) -> R) -> Option<R> {
+    registry.warnings().borrow_mut().as_mut().map(f)
+}
+
+/// An assignment, let binding or loop variable binding `name` at line:col
+pub fn bind(registry: &Registry, name: &str, line: usize, col: usize) {
+    with(registry, |w| {
+        if line < w.first_line {
+            return;
+        }
+        match w.open.last_mut() {
+            Some(function) => function.names.bind(name, line, col),
+            None => w.top.bind(name, line, col),
+        }
+    });
+}
+
+/// A use of the value of `name` at `line`
+pub fn read(registry: &Registry, name: &str, line: usize) {
+    with(registry, |w| {
+        if line < w.first_line {
+            return;
+        }
+        w.all_reads.insert(name.to_string());
+        // A nested function may read a name of the functions around it, whose scopes it captures
+        for function in &mut w.open {
+            function.names.read.insert(name.to_string());
+        }
+    });
+}
+
+/// The body of function `name`, defined at `line`, starts; its parameters are its first bindings
+pub fn enter_function(registry: &Registry, name: &str, params: &[String], positions: &[(usize, usize)], line: usize) {
+    with(registry, |w| {
+        let mut names = Names::default();
+        for (param, (line, col)) in params.iter().zip(positions) {
+            names.bind(param, *line, *col);
+        }
+        let user = line >= w.first_line;
+        w.open.push(Function { name: name.to_string(), params: params.to_vec(), names, user });
+    });
+}
+
+/// The body of the innermost function has been parsed
+pub fn leave_function(registry: &Registry) {
+    with(registry, |w| {
+        if let Some(function) = w.open.pop().filter(|f| f.user) {
+            w.functions.push(function);
+        }
+    });
+}
+
+/// Warnings about the names of the parsed program, in source order
+pub fn check_names(registry: &Registry) -> Vec<LocatedError> {
+    let mut found = with(registry, |w| {
+        let mut found = Vec::new();
+        // Globals may be read from any function, so they count as used if read anywhere
+        for (name, line, col) in &w.top.bound {
+            if !name.starts_with('_') && !w.all_reads.contains(name) {
+                let message = format!("variable '{}' is assigned but never used", name);
+                found.push((*line, *col, warning("unused-variable", &message, *line, *col)));
+            }
+        }
+        for function in &w.functions {
+            for (name, line, col) in &function.names.bound {
+                let kind = if function.params.contains(name) { "parameter" } else { "variable" };
+                if !name.starts_with('_') && !function.names.read.contains(name) {
+                    let message = format!("{} '{}' is never used in function '{}'", kind, name, function.name);
+                    found.push((*line, *col, warning("unused-variable", &message, *line, *col)));
+                }
+                if !function.params.contains(name) && w.top.bound.iter().any(|(global, _, _)| global == name) {
+                    let message = format!("variable '{}' in function '{}' shadows a global variable", name, function.name);
+                    found.push((*line, *col, warning("shadowed-name", &message, *line, *col)));
+                }
+            }
+        }
+        found
+    })
+    .unwrap_or_default();
+    found.sort_by_key(|(line, col, _)| (*line, *col));
+    found.into_iter().map(|(_, _, warning)| warning).collect()
+}
+
+/// Register a comparison at line:col; returns the site to pass to `incompatible_comparison`, if collecting
+pub fn comparison_site(registry: &Registry, line: usize, col: usize) -> Option<(usize, usize)> {
+    with(registry, |w| (line >= w.first_line).then_some((line, col))).flatten()
+}
+
+/// `left op right` at `site` compared values of kinds that are never equal
+/// Warns once per site; with --deny-warnings the comparison fails instead.
+pub fn incompatible_comparison(env: &mut Env, site: (usize, usize), left: &Value, op: &str, right: &Value) -> LumenResult<()> {
+    let Some(sink) = State::of(env).warnings.clone() else {
+        return Ok(());
+    };
+    let message = format!(
+        "comparing {} {} {} is always {}",
+        kind_name(left),
+        op,
+        kind_name(right),
+        op == "!="
+    );
+    let mut w = sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if w.deny {
+        return Err(format!("warning[incompatible-comparison]: {} (--deny-warnings)", message).into());
+    }
+    if w.compared.insert(site) {
+        w.found.push(warning("incompatible-comparison", &message, site.0, site.1));
+    }
+    Ok(())
+}
+
+fn warning(rule: &str, message: &str, line: usize, col: usize) -> LocatedError {
+    LocatedError::at(format!("warning[{}]: {}", rule, message), line, col)
+}
+
+fn kind_name(value: &Value) -> String {
+    crate::languages::lumen::expressions::variable::kind_name(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_unused_and_shadowed_names_in_user_code() {
+        let registry = Registry::new();
+        bind(&registry, "untracked", 1, 1);
+        enable(&registry, 3);
+        bind(&registry, "prelude_only", 1, 1);
+        bind(&registry, "total", 3, 1);
+        bind(&registry, "_scratch", 4, 1);
+        read(&registry, "total", 4);
+        let params = ["a".to_string(), "total".to_string(), "b".to_string()];
+        enter_function(&registry, "add", &params, &[(5, 8), (5, 11), (5, 18)], 5);
+        bind(&registry, "_scratch", 6, 5);
+        read(&registry, "a", 6);
+        read(&registry, "total", 7);
+        leave_function(&registry);
+        // A parameter named like a global is not reported
+        assert_eq!(
+            check_names(&registry),
+            [
+                LocatedError::at("warning[unused-variable]: parameter 'b' is never used in function 'add'", 5, 18),
+                LocatedError::at("warning[shadowed-name]: variable '_scratch' in function 'add' shadows a global variable", 6, 5),
+            ]
+        );
+        // Another registry collects nothing
+        assert!(check_names(&Registry::new()).is_empty());
+    }
+}
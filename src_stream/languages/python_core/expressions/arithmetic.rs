@@ -32,6 +32,10 @@ impl ExprPrefix for UnaryMinusPrefix {
         parser.peek().lexeme == "-"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["-"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance(); // '-'
         let expr = parser.parse_expr_prec(registry, Precedence::Unary)?;
@@ -82,6 +86,10 @@ impl ExprInfix for ArithmeticInfix {
         parser.peek().lexeme == self.op
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![&self.op]
+    }
+
     fn precedence(&self) -> Precedence {
         self.prec
     }
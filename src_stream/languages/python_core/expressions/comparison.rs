@@ -71,6 +71,10 @@ impl ExprInfix for ComparisonInfix {
         parser.peek().lexeme == self.op
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![&self.op]
+    }
+
     fn precedence(&self) -> Precedence {
         Precedence::Comparison
     }
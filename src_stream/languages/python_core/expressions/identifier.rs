@@ -14,7 +14,7 @@ struct IdentExpr {
 
 impl ExprNode for IdentExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        env.get(&self.name)
+        Ok(env.get(&self.name)?)
     }
 }
 
@@ -26,7 +26,7 @@ impl ExprPrefix for IdentPrefix {
     }
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
-        let name = parser.advance().lexeme;
+        let name = parser.advance().lexeme.into_owned();
         Ok(Box::new(IdentExpr { name }))
     }
 }
@@ -29,7 +29,7 @@ impl ExprPrefix for NumberLiteralPrefix {
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         // Consume the first digit
-        let mut value = parser.advance().lexeme;
+        let mut value = parser.advance().lexeme.into_owned();
 
         // Since the kernel lexer is fully agnostic, it emits each digit as a separate token.
         // We need to consume consecutive digit tokens to build the full number.
@@ -70,8 +70,12 @@ impl ExprPrefix for BoolLiteralPrefix {
         lex == "true" || lex == "false"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["true", "false"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
-        let lexeme = parser.advance().lexeme;
+        let lexeme = parser.advance().lexeme.into_owned();
         let value = lexeme == "true";
         Ok(Box::new(BoolLiteral { value }))
     }
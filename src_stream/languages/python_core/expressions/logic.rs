@@ -26,7 +26,7 @@ impl ExprNode for LogicExpr {
         let result = match self.op.as_str() {
             "and" => left_bool.value && right_bool.value,
             "or" => left_bool.value || right_bool.value,
-            _ => return Err(format!("Invalid logical operator: {}", self.op)),
+            _ => return Err(format!("Invalid logical operator: {}", self.op).into()),
         };
         Ok(Box::new(PythonCoreBool::new(result)))
     }
@@ -47,6 +47,10 @@ impl ExprInfix for LogicInfix {
         parser.peek().lexeme == self.op
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![&self.op]
+    }
+
     fn precedence(&self) -> Precedence {
         Precedence::Logic
     }
@@ -85,6 +89,10 @@ impl ExprPrefix for NotPrefix {
         parser.peek().lexeme == "not"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["not"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance();
         let expr = parser.parse_expr_prec(registry, Precedence::Unary)?;
@@ -4,6 +4,7 @@ use crate::languages::python_core::prelude::*;
 
 pub mod arithmetic;
 pub mod comparison;
+pub mod extern_call;
 pub mod grouping;
 pub mod identifier;
 pub mod literals;
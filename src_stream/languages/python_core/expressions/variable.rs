@@ -16,7 +16,7 @@ struct VarExpr {
 
 impl ExprNode for VarExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        env.get(&self.name)
+        Ok(env.get(&self.name)?)
     }
 }
 
@@ -28,13 +28,13 @@ impl ExprPrefix for VariablePrefix {
         // But exclude reserved keywords
         let lex = &parser.peek().lexeme;
         let is_identifier = lex.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_');
-        let is_reserved = matches!(lex.as_str(), "true" | "false" | "not" | "and" | "or" | "if" | "else" | "while" | "print" | "break" | "continue");
+        let is_reserved = matches!(lex.as_ref(), "true" | "false" | "not" | "and" | "or" | "if" | "else" | "while" | "print" | "break" | "continue" | "extern");
         is_identifier && !is_reserved
     }
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         // Consume the first character of the identifier
-        let mut name = parser.advance().lexeme;
+        let mut name = parser.advance().lexeme.into_owned();
 
         // Since the kernel lexer is agnostic, multi-character identifiers are split into single chars
         // Continue consuming identifier characters
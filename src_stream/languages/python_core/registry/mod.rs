@@ -4,6 +4,8 @@
 pub mod precedence;
 pub mod traits;
 
+use std::collections::HashMap;
+
 use crate::kernel::parser::Parser;
 use crate::kernel::registry::{TokenRegistry, LumenResult, err_at};
 use crate::languages::python_core::prelude::PythonCoreParserExt;
@@ -19,6 +21,38 @@ pub struct Registry {
     prefixes: Vec<Box<dyn ExprPrefix>>,
     infixes: Vec<Box<dyn ExprInfix>>,
     stmts: Vec<Box<dyn StmtHandler>>,
+    prefix_keys: Keyed,
+    infix_keys: Keyed,
+    stmt_keys: Keyed,
+}
+
+/// Handler indices by the lexeme they start on
+/// Handlers that name no lexemes are in every list, so each list keeps registration order.
+#[derive(Default)]
+struct Keyed {
+    by_lexeme: HashMap<String, Vec<usize>>,
+    any: Vec<usize>,
+}
+
+impl Keyed {
+    fn add(&mut self, index: usize, lexemes: Vec<&str>) {
+        if lexemes.is_empty() {
+            self.any.push(index);
+            for indices in self.by_lexeme.values_mut() {
+                indices.push(index);
+            }
+            return;
+        }
+        for lexeme in lexemes {
+            let any = &self.any;
+            self.by_lexeme.entry(lexeme.to_string()).or_insert_with(|| any.clone()).push(index);
+        }
+    }
+
+    /// Indices of the handlers that may start on `lexeme`, in registration order
+    fn candidates(&self, lexeme: &str) -> &[usize] {
+        self.by_lexeme.get(lexeme).unwrap_or(&self.any)
+    }
 }
 
 impl Registry {
@@ -28,31 +62,41 @@ impl Registry {
             prefixes: Vec::new(),
             infixes: Vec::new(),
             stmts: Vec::new(),
+            prefix_keys: Keyed::default(),
+            infix_keys: Keyed::default(),
+            stmt_keys: Keyed::default(),
         }
     }
 
     pub fn register_prefix(&mut self, h: Box<dyn ExprPrefix>) {
+        self.prefix_keys.add(self.prefixes.len(), h.lexemes());
         self.prefixes.push(h);
     }
 
     pub fn register_infix(&mut self, h: Box<dyn ExprInfix>) {
+        self.infix_keys.add(self.infixes.len(), h.lexemes());
         self.infixes.push(h);
     }
 
     pub fn register_stmt(&mut self, h: Box<dyn StmtHandler>) {
+        self.stmt_keys.add(self.stmts.len(), h.lexemes());
         self.stmts.push(h);
     }
 
+    // Only the handlers keyed by the current lexeme (and the unkeyed ones) are asked
     pub fn find_prefix(&self, parser: &Parser) -> Option<&dyn ExprPrefix> {
-        self.prefixes.iter().map(|b| b.as_ref()).find(|h| h.matches(parser))
+        let candidates = self.prefix_keys.candidates(&parser.peek().lexeme);
+        candidates.iter().map(|&i| self.prefixes[i].as_ref()).find(|h| h.matches(parser))
     }
 
     pub fn find_infix(&self, parser: &Parser) -> Option<&dyn ExprInfix> {
-        self.infixes.iter().map(|b| b.as_ref()).find(|h| h.matches(parser))
+        let candidates = self.infix_keys.candidates(&parser.peek().lexeme);
+        candidates.iter().map(|&i| self.infixes[i].as_ref()).find(|h| h.matches(parser))
     }
 
     pub fn find_stmt(&self, parser: &Parser) -> Option<&dyn StmtHandler> {
-        self.stmts.iter().map(|b| b.as_ref()).find(|h| h.matches(parser))
+        let candidates = self.stmt_keys.candidates(&parser.peek().lexeme);
+        candidates.iter().map(|&i| self.stmts[i].as_ref()).find(|h| h.matches(parser))
     }
 }
 
@@ -14,6 +14,12 @@ pub trait ExprPrefix {
     /// Check if this handler matches the current token
     fn matches(&self, parser: &Parser) -> bool;
 
+    /// Lexemes the handler can start on, so the registry only asks it about those
+    /// Empty: it can start on other lexemes too and is asked about every one.
+    fn lexemes(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
     /// Parse the prefix expression
     fn parse(&self, parser: &mut Parser, registry: &super::Registry) -> LumenResult<Box<dyn ExprNode>>;
 }
@@ -24,6 +30,12 @@ pub trait ExprInfix {
     /// Check if this handler matches the current token
     fn matches(&self, parser: &Parser) -> bool;
 
+    /// Lexemes the handler can start on, so the registry only asks it about those
+    /// Empty: it can start on other lexemes too and is asked about every one.
+    fn lexemes(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
     /// Get the operator precedence for this infix operator
     fn precedence(&self) -> Precedence;
 
@@ -37,6 +49,12 @@ pub trait StmtHandler {
     /// Check if this handler matches the current token
     fn matches(&self, parser: &Parser) -> bool;
 
+    /// Lexemes the handler can start on, so the registry only asks it about those
+    /// Empty: it can start on other lexemes too and is asked about every one.
+    fn lexemes(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
     /// Parse the statement
     fn parse(&self, parser: &mut Parser, registry: &super::Registry) -> LumenResult<Box<dyn StmtNode>>;
 }
@@ -69,7 +69,7 @@ impl StmtHandler for AssignStmtHandler {
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         // Consume the identifier (which may span multiple tokens for the kernel's agnostic lexer)
-        let mut name = parser.advance().lexeme;
+        let mut name = parser.advance().lexeme.into_owned();
         parser.skip_tokens();
 
         // Continue consuming identifier characters if split across tokens
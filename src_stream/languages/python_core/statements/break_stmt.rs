@@ -25,6 +25,10 @@ impl StmtHandler for BreakStmtHandler {
         parser.peek().lexeme == "break"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["break"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'break'
         Ok(Box::new(BreakStmt))
@@ -58,6 +58,10 @@ impl StmtHandler for IfStmtHandler {
         parser.peek().lexeme == "if"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["if"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'if'
 
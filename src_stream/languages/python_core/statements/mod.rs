@@ -5,6 +5,7 @@ use crate::languages::python_core::prelude::*;
 pub mod assignment;
 pub mod break_stmt;
 pub mod continue_stmt;
+pub mod extern_stmt;
 pub mod if_else;
 pub mod print;
 pub mod write;
@@ -30,6 +30,10 @@ impl StmtHandler for PrintStmtHandler {
         parser.peek().lexeme == "print"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["print"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         // consume `print`
         parser.advance();
@@ -64,6 +64,10 @@ impl StmtHandler for WhileStmtHandler {
         parser.peek().lexeme == "while"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["while"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'while'
 
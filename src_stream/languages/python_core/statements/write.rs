@@ -30,6 +30,10 @@ impl StmtHandler for WriteStmtHandler {
         parser.peek().lexeme == "write"
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["write"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         // consume `write`
         parser.advance();
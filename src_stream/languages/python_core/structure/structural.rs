@@ -4,10 +4,10 @@
 // Handles PythonCore-style indentation: 4-space indents, INDENT/DEDENT tokens.
 // Completely language-specific - ALL structural concepts defined here.
 
-use crate::kernel::ast::{Program, StmtNode};
-use crate::kernel::lexer::{Token, SpannedToken, Span};
-use crate::kernel::parser::Parser;
-use crate::kernel::registry::{err_at, LumenResult};
+use crate::kernel::ast::{LocatedStmt, Program, StmtNode};
+use crate::kernel::lexer::{LineCursor, Token, SpannedToken, Span};
+use crate::kernel::parser::{Parser, StatementSyntax};
+use crate::kernel::registry::{err_at, LocatedError, LumenError, LumenResult};
 use crate::languages::python_core::registry::Registry;
 use crate::languages::python_core::prelude::PythonCoreParserExt;
 
@@ -27,6 +27,16 @@ pub const DEDENT: &str = "DEDENT";
 // End of file
 pub const EOF: &str = "EOF";
 
+/// Where Mini-PythonCore statements end, for skipping one that failed to parse
+const STATEMENTS: StatementSyntax = StatementSyntax {
+    block_open: INDENT,
+    block_close: DEDENT,
+    terminators: &[NEWLINE],
+    continuations: &["elif", "else"],
+    skip: &[],
+    end: EOF,
+};
+
 // --------------------
 // Structural Tokens Configuration
 // --------------------
@@ -78,10 +88,21 @@ pub fn parse_block(parser: &mut Parser, registry: &Registry) -> LumenResult<Vec<
 
     // Parse statements until DEDENT or EOF
     while parser.peek().lexeme != DEDENT && parser.peek().lexeme != EOF {
-        let s = registry
+        let start = parser.i;
+        let (line, col) = parser.position();
+        let parsed = registry
             .find_stmt(parser)
-            .ok_or_else(|| err_at(parser, "Unknown statement in block"))?
-            .parse(parser, registry)?;
+            .ok_or_else(|| err_at(parser, "Unknown statement in block"))
+            .and_then(|handler| handler.parse(parser, registry));
+        let s = match parsed {
+            Ok(s) => s,
+            Err(e) => {
+                parser.recover(e, start, &STATEMENTS);
+                consume_newlines(parser);
+                continue;
+            }
+        };
+        let s = LocatedStmt::wrap(s, line, col);
 
         stmts.push(s);
         consume_newlines(parser);
@@ -96,20 +117,35 @@ pub fn parse_block(parser: &mut Parser, registry: &Registry) -> LumenResult<Vec<
 }
 
 /// Mini-PythonCore-specific program parsing with newline handling.
-pub fn parse_program(parser: &mut Parser, registry: &Registry) -> LumenResult<Program> {
+pub fn parse_program(parser: &mut Parser, registry: &Registry) -> Result<Program, Vec<LocatedError>> {
     let mut stmts = Vec::new();
     consume_newlines(parser);
 
     while parser.peek().lexeme != EOF {
-        let stmt = registry
+        let start = parser.i;
+        let (line, col) = parser.position();
+        let parsed = registry
             .find_stmt(parser)
-            .ok_or_else(|| err_at(parser, "Unknown statement"))?
-            .parse(parser, registry)?;
+            .ok_or_else(|| err_at(parser, "Unknown statement"))
+            .and_then(|handler| handler.parse(parser, registry));
+        let stmt = match parsed {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                parser.recover(e, start, &STATEMENTS);
+                consume_newlines(parser);
+                continue;
+            }
+        };
+        let stmt = LocatedStmt::wrap(stmt, line, col);
 
         stmts.push(stmt);
         consume_newlines(parser);
     }
 
+    // Every statement that failed to parse
+    if !parser.errors.is_empty() {
+        return Err(std::mem::take(&mut parser.errors));
+    }
     Ok(Program::new(stmts))
 }
 
@@ -124,6 +160,7 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
     let mut out = Vec::new();
     let mut indents = vec![0usize];
     let mut line_no = 1usize;
+    let mut line_tokens = LineCursor::new(&raw_tokens);
 
     for raw in source.lines() {
         // Count leading spaces
@@ -148,11 +185,11 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
         let current = *indents.last().unwrap();
         if spaces > current {
             if (spaces - current) % 4 != 0 {
-                return Err(format!("Invalid indentation at line {line_no}"));
+                return Err(LumenError::Raised(LocatedError::at("Invalid indentation", line_no, spaces + 1)));
             }
             indents.push(spaces);
             out.push(SpannedToken {
-                tok: Token::new(INDENT.to_string(), Span::new(0, 0)),
+                tok: Token::new(INDENT, Span::new(0, 0)),
                 line: line_no,
                 col: 1,
             });
@@ -160,13 +197,13 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
             while *indents.last().unwrap() > spaces {
                 indents.pop();
                 out.push(SpannedToken {
-                    tok: Token::new(DEDENT.to_string(), Span::new(0, 0)),
+                    tok: Token::new(DEDENT, Span::new(0, 0)),
                     line: line_no,
                     col: 1,
                 });
             }
             if *indents.last().unwrap() != spaces {
-                return Err(format!("Indentation mismatch at line {line_no}"));
+                return Err(LumenError::Raised(LocatedError::at("Indentation mismatch", line_no, spaces + 1)));
             }
         }
 
@@ -174,22 +211,20 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
         // IMPORTANT: Filter out single-character whitespace tokens
         // The kernel lexer is now fully agnostic and emits all characters (including spaces, tabs, newlines)
         // Mini-PythonCore's indentation processing needs only the meaningful tokens
-        for raw_tok in &raw_tokens {
-            if raw_tok.line == line_no {
-                // Skip whitespace tokens (single-char spaces, tabs, newlines, carriage returns)
-                if raw_tok.tok.lexeme.len() == 1 {
-                    let ch = raw_tok.tok.lexeme.as_bytes()[0];
-                    if ch == b' ' || ch == b'\t' || ch == b'\n' || ch == b'\r' {
-                        continue;
-                    }
+        for raw_tok in line_tokens.line(line_no) {
+            // Skip whitespace tokens (single-char spaces, tabs, newlines, carriage returns)
+            if raw_tok.tok.lexeme.len() == 1 {
+                let ch = raw_tok.tok.lexeme.as_bytes()[0];
+                if ch == b' ' || ch == b'\t' || ch == b'\n' || ch == b'\r' {
+                    continue;
                 }
-                out.push(raw_tok.clone());
             }
+            out.push(raw_tok.clone());
         }
 
         // Add NEWLINE at end of line
         out.push(SpannedToken {
-            tok: Token::new(NEWLINE.to_string(), Span::new(0, 0)),
+            tok: Token::new(NEWLINE, Span::new(0, 0)),
             line: line_no,
             col: spaces + rest.len() + 1,
         });
@@ -201,7 +236,7 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
     while indents.len() > 1 {
         indents.pop();
         out.push(SpannedToken {
-            tok: Token::new(DEDENT.to_string(), Span::new(0, 0)),
+            tok: Token::new(DEDENT, Span::new(0, 0)),
             line: line_no,
             col: 1,
         });
@@ -209,7 +244,7 @@ pub fn process_indentation(source: &str, raw_tokens: Vec<SpannedToken>) -> Lumen
 
     // Add EOF token
     out.push(SpannedToken {
-        tok: Token::new(EOF.to_string(), Span::new(0, 0)),
+        tok: Token::new(EOF, Span::new(0, 0)),
         line: line_no,
         col: 1,
     });
@@ -40,6 +40,10 @@ impl ExprPrefix for UnaryMinusPrefix {
         parser.peek().lexeme == MINUS
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![MINUS]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance(); // '-'
         let expr = parser.parse_expr_prec(registry, Precedence::Unary)?;
@@ -90,6 +94,10 @@ impl ExprInfix for ArithmeticInfix {
         parser.peek().lexeme == self.op
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![&self.op]
+    }
+
     fn precedence(&self) -> Precedence {
         self.prec
     }
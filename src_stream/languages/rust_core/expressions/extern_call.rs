@@ -0,0 +1,80 @@
+use crate::languages::rust_core::prelude::*;
+// extern("selector", args...) for mini-rust: a host capability (see lumen/extern_system/bridge.rs)
+
+use crate::kernel::ast::ExprNode;
+use crate::kernel::parser::Parser;
+use crate::kernel::registry::LumenResult;
+use crate::kernel::runtime::{Env, Value};
+use crate::languages::lumen::extern_system::bridge::{self, Arg, Returned};
+use crate::languages::rust_core::registry::{ExprPrefix, Registry};
+use crate::languages::rust_core::values::{RustCoreBool, RustCoreNumber};
+
+// --------------------
+// Token definitions
+// --------------------
+
+pub const EXTERN: &str = "extern";
+
+#[derive(Debug)]
+pub struct ExternExpr {
+    selector: String,
+    args: Vec<Arg>,
+}
+
+impl ExprNode for ExternExpr {
+    fn eval(&self, env: &mut Env) -> LumenResult<Value> {
+        let mut args = Vec::with_capacity(self.args.len());
+        for arg in &self.args {
+            args.push(match arg {
+                Arg::Text(text) => bridge::text(text),
+                Arg::Expr(expr) => {
+                    let value = expr.eval(env)?;
+                    if let Some(number) = value.as_any().downcast_ref::<RustCoreNumber>() {
+                        bridge::number(&number.value)?
+                    } else if let Some(boolean) = value.as_any().downcast_ref::<RustCoreBool>() {
+                        bridge::boolean(boolean.value)
+                    } else {
+                        value
+                    }
+                }
+            });
+        }
+        Ok(match bridge::call(env, &self.selector, args)? {
+            Returned::Number(digits) => Box::new(RustCoreNumber::new(digits)),
+            Returned::Bool(value) => Box::new(RustCoreBool::new(value)),
+            Returned::Lumen(value) => value,
+        })
+    }
+}
+
+/// Parse an extern call, at the `extern` keyword
+pub fn parse_extern(parser: &mut Parser, registry: &Registry) -> LumenResult<ExternExpr> {
+    let (selector, args) = bridge::parse_call(parser, |parser| parser.parse_expr(registry), |parser| parser.skip_tokens())?;
+    Ok(ExternExpr { selector, args })
+}
+
+pub struct ExternPrefix;
+
+impl ExprPrefix for ExternPrefix {
+    fn matches(&self, parser: &Parser) -> bool {
+        parser.peek().lexeme == EXTERN
+    }
+
+    fn lexemes(&self) -> Vec<&str> {
+        vec![EXTERN]
+    }
+
+    fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
+        Ok(Box::new(parse_extern(parser, registry)?))
+    }
+}
+
+// --------------------
+// Registration
+// --------------------
+
+pub fn register(reg: &mut Registry) {
+    // No token registration needed - "extern" is registered in the dispatcher
+    // Register handlers
+    reg.register_prefix(Box::new(ExternPrefix));
+}
@@ -35,7 +35,7 @@ impl ExprPrefix for NumberLiteralPrefix {
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         // Consume the first digit
-        let mut value = parser.advance().lexeme;
+        let mut value = parser.advance().lexeme.into_owned();
 
         // Since the kernel lexer is fully agnostic, it emits each digit as a separate token.
         // We need to consume consecutive digit tokens to build the full number.
@@ -75,6 +75,10 @@ impl ExprPrefix for BoolLiteralPrefix {
         (parser.peek().lexeme == "true" || parser.peek().lexeme == "false")
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec!["true", "false"]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         { let value = parser.advance().lexeme == "true"; Ok(Box::new(BoolLiteral { value })) }
     }
@@ -34,7 +34,7 @@ impl ExprNode for LogicExpr {
         let result = match self.op {
             AND => left_bool.value && right_bool.value,
             OR => left_bool.value || right_bool.value,
-            _ => return Err(format!("Invalid logical operator: {}", self.op)),
+            _ => return Err(format!("Invalid logical operator: {}", self.op).into()),
         };
         Ok(Box::new(RustCoreBool::new(result)))
     }
@@ -55,6 +55,10 @@ impl ExprInfix for LogicInfix {
         parser.peek().lexeme == self.op
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![&self.op]
+    }
+
     fn precedence(&self) -> Precedence {
         Precedence::Logic
     }
@@ -93,6 +97,10 @@ impl ExprPrefix for NotPrefix {
         parser.peek().lexeme == NOT
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![NOT]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         parser.advance();
         let expr = parser.parse_expr_prec(registry, Precedence::Unary)?;
@@ -6,6 +6,7 @@ pub mod logic;
 pub mod variable;
 pub mod identifier;
 pub mod grouping;
+pub mod extern_call;
 
 pub fn register_all(registry: &mut crate::languages::rust_core::registry::Registry) {
     literals::register(registry);
@@ -15,4 +16,5 @@ pub fn register_all(registry: &mut crate::languages::rust_core::registry::Regist
     variable::register(registry);
     identifier::register(registry);
     grouping::register(registry);
+    extern_call::register(registry);
 }
@@ -14,7 +14,7 @@ pub struct VariableExpr {
 
 impl ExprNode for VariableExpr {
     fn eval(&self, env: &mut Env) -> LumenResult<Value> {
-        env.get(&self.name).map_err(|_| format!("Undefined variable: {}", self.name))
+        env.get(&self.name).map_err(|_| format!("Undefined variable: {}", self.name).into())
     }
 }
 
@@ -26,13 +26,13 @@ impl ExprPrefix for VariablePrefix {
         // But exclude reserved keywords
         let lex = &parser.peek().lexeme;
         let is_identifier = lex.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_');
-        let is_reserved = matches!(lex.as_str(), "let" | "if" | "else" | "while" | "break" | "continue" | "print" | "true" | "false");
+        let is_reserved = matches!(lex.as_ref(), "let" | "if" | "else" | "while" | "break" | "continue" | "print" | "true" | "false" | "extern");
         is_identifier && !is_reserved
     }
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn ExprNode>> {
         // Consume the first character of the identifier
-        let mut name = parser.advance().lexeme;
+        let mut name = parser.advance().lexeme.into_owned();
 
         // Since the kernel lexer is agnostic, multi-character identifiers are split into single chars
         // Continue consuming identifier characters
@@ -33,6 +33,7 @@ pub fn register_all(registry: &mut Registry) {
         TokenDefinition::recognize("continue"),
         TokenDefinition::recognize("print"),
         TokenDefinition::recognize("write"),
+        TokenDefinition::recognize("extern"),
         TokenDefinition::recognize("true"),
         TokenDefinition::recognize("false"),
     ];
@@ -50,6 +51,7 @@ pub fn register_all(registry: &mut Registry) {
     expressions::arithmetic::register(registry);    // Arithmetic operators
     expressions::comparison::register(registry);    // Comparison operators
     expressions::logic::register(registry);         // Logical operators
+    expressions::extern_call::register(registry);   // extern("selector", args...)
 
     // Statement features
     statements::print::register(registry);          // print! statement
@@ -60,4 +62,5 @@ pub fn register_all(registry: &mut Registry) {
     statements::while_loop::register(registry);     // while loops
     statements::break_stmt::register(registry);     // break statement
     statements::continue_stmt::register(registry);  // continue statement
+    statements::extern_stmt::register(registry);    // extern(...); called for its effect
 }
@@ -74,7 +74,7 @@ impl StmtHandler for AssignStmtHandler {
 
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         // Consume first character of identifier
-        let mut name = parser.advance().lexeme;
+        let mut name = parser.advance().lexeme.into_owned();
 
         // Since kernel lexer is agnostic, consume remaining identifier characters
         loop {
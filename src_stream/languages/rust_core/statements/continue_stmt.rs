@@ -25,6 +25,10 @@ impl StmtHandler for ContinueStmtHandler {
         parser.peek().lexeme == CONTINUE
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![CONTINUE]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'continue'
         Ok(Box::new(ContinueStmt))
@@ -0,0 +1,44 @@
+use crate::languages::rust_core::prelude::*;
+// extern("selector", args...); as a statement for mini-rust, for a capability called for its effect
+
+use crate::kernel::ast::Control;
+use crate::languages::rust_core::expressions::extern_call::{parse_extern, ExternExpr, EXTERN};
+use crate::kernel::runtime::Env;
+
+#[derive(Debug)]
+struct ExternStmt {
+    call: ExternExpr,
+}
+
+impl StmtNode for ExternStmt {
+    fn exec(&self, env: &mut Env) -> LumenResult<Control> {
+        self.call.eval(env)?;
+        Ok(Control::None)
+    }
+}
+
+pub struct ExternStmtHandler;
+
+impl StmtHandler for ExternStmtHandler {
+    fn matches(&self, parser: &Parser) -> bool {
+        parser.peek().lexeme == EXTERN
+    }
+
+    fn lexemes(&self) -> Vec<&str> {
+        vec![EXTERN]
+    }
+
+    fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
+        Ok(Box::new(ExternStmt { call: parse_extern(parser, registry)? }))
+    }
+}
+
+// --------------------
+// Registration
+// --------------------
+
+pub fn register(reg: &mut Registry) {
+    // No token registration needed - "extern" is registered in the dispatcher
+    // Register handlers
+    reg.register_stmt(Box::new(ExternStmtHandler));
+}
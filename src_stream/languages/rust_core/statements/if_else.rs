@@ -66,6 +66,10 @@ impl StmtHandler for IfStmtHandler {
         parser.peek().lexeme == IF
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![IF]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'if'
         parser.skip_tokens();
@@ -35,12 +35,16 @@ impl StmtHandler for LetStmtHandler {
         parser.peek().lexeme == LET
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![LET]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'let'
         parser.skip_tokens();
 
         // Consume first character of identifier
-        let mut name = parser.advance().lexeme;
+        let mut name = parser.advance().lexeme.into_owned();
 
         // Since kernel lexer is agnostic, consume remaining identifier characters
         loop {
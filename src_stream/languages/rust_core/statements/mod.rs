@@ -7,6 +7,7 @@ pub mod while_loop;
 pub mod break_stmt;
 pub mod continue_stmt;
 pub mod let_binding;
+pub mod extern_stmt;
 
 pub fn register_all(registry: &mut crate::languages::rust_core::registry::Registry) {
     assignment::register(registry);
@@ -17,4 +18,5 @@ pub fn register_all(registry: &mut crate::languages::rust_core::registry::Regist
     break_stmt::register(registry);
     continue_stmt::register(registry);
     let_binding::register(registry);
+    extern_stmt::register(registry);
 }
@@ -33,6 +33,10 @@ impl StmtHandler for PrintStmtHandler {
         )
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![PRINT]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'print!'
         parser.skip_tokens();
@@ -67,6 +67,10 @@ impl StmtHandler for WhileStmtHandler {
         parser.peek().lexeme == WHILE
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![WHILE]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'while'
         parser.skip_tokens();
@@ -33,6 +33,10 @@ impl StmtHandler for WriteStmtHandler {
         )
     }
 
+    fn lexemes(&self) -> Vec<&str> {
+        vec![WRITE]
+    }
+
     fn parse(&self, parser: &mut Parser, registry: &super::super::registry::Registry) -> LumenResult<Box<dyn StmtNode>> {
         parser.advance(); // consume 'write!'
         parser.skip_tokens();
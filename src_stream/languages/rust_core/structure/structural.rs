@@ -1,9 +1,9 @@
 // Mini-RustCore structural tokens and parsing helpers
 
-use crate::kernel::ast::{Program, StmtNode};
+use crate::kernel::ast::{LocatedStmt, Program, StmtNode};
 use crate::kernel::lexer::{Token, SpannedToken, Span};
-use crate::kernel::parser::Parser;
-use crate::kernel::registry::{err_at, LumenResult};
+use crate::kernel::parser::{Parser, StatementSyntax};
+use crate::kernel::registry::{err_at, LocatedError, LumenResult};
 use crate::languages::rust_core::registry::Registry;
 use crate::languages::rust_core::prelude::RustCoreParserExt;
 
@@ -23,6 +23,16 @@ pub const SEMICOLON: &str = ";";
 // End of file
 pub const EOF: &str = "EOF";
 
+/// Where Mini-RustCore statements end, for skipping one that failed to parse
+const STATEMENTS: StatementSyntax = StatementSyntax {
+    block_open: LBRACE,
+    block_close: RBRACE,
+    terminators: &[SEMICOLON],
+    continuations: &["else"],
+    skip: &[" ", "\t", "\n", "\r"],
+    end: EOF,
+};
+
 // --------------------
 // Mini-RustCore-specific Parsing Helpers
 // --------------------
@@ -53,10 +63,20 @@ pub fn parse_block(parser: &mut Parser, registry: &Registry) -> LumenResult<Vec<
             break;
         }
 
-        let stmt = registry
+        let start = parser.i;
+        let (line, col) = parser.position();
+        let parsed = registry
             .find_stmt(parser)
-            .ok_or_else(|| err_at(parser, "Unknown statement in block"))?
-            .parse(parser, registry)?;
+            .ok_or_else(|| err_at(parser, "Unknown statement in block"))
+            .and_then(|handler| handler.parse(parser, registry));
+        let stmt = match parsed {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                parser.recover(e, start, &STATEMENTS);
+                continue;
+            }
+        };
+        let stmt = LocatedStmt::wrap(stmt, line, col);
 
         statements.push(stmt);
 
@@ -76,7 +96,7 @@ pub fn parse_block(parser: &mut Parser, registry: &Registry) -> LumenResult<Vec<
 }
 
 /// Parse the main program (sequence of statements)
-pub fn parse_program(parser: &mut Parser, registry: &Registry) -> LumenResult<Program> {
+pub fn parse_program(parser: &mut Parser, registry: &Registry) -> Result<Program, Vec<LocatedError>> {
     let mut statements = Vec::new();
 
     while parser.peek().lexeme != EOF {
@@ -86,10 +106,20 @@ pub fn parse_program(parser: &mut Parser, registry: &Registry) -> LumenResult<Pr
             break;
         }
 
-        let stmt = registry
+        let start = parser.i;
+        let (line, col) = parser.position();
+        let parsed = registry
             .find_stmt(parser)
-            .ok_or_else(|| err_at(parser, "Unknown statement"))?
-            .parse(parser, registry)?;
+            .ok_or_else(|| err_at(parser, "Unknown statement"))
+            .and_then(|handler| handler.parse(parser, registry));
+        let stmt = match parsed {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                parser.recover(e, start, &STATEMENTS);
+                continue;
+            }
+        };
+        let stmt = LocatedStmt::wrap(stmt, line, col);
 
         statements.push(stmt);
 
@@ -101,6 +131,10 @@ pub fn parse_program(parser: &mut Parser, registry: &Registry) -> LumenResult<Pr
         parser.skip_tokens();
     }
 
+    // Every statement that failed to parse
+    if !parser.errors.is_empty() {
+        return Err(std::mem::take(&mut parser.errors));
+    }
     Ok(Program::new(statements))
 }
 
@@ -109,7 +143,7 @@ pub fn process_tokens(raw_tokens: Vec<crate::kernel::lexer::SpannedToken>) -> Lu
     let mut tokens = raw_tokens;
     let line = tokens.last().map(|t| t.line).unwrap_or(1);
     tokens.push(crate::kernel::lexer::SpannedToken {
-        tok: Token::new(EOF.to_string(), Span::new(0, 0)),
+        tok: Token::new(EOF, Span::new(0, 0)),
         line,
         col: 1,
     });
@@ -0,0 +1,63 @@
+// Stream kernel library: the procedural kernel, usable without the binary
+//
+// The kernel is language-agnostic: it lexes with a language's token registry, parses
+// with its handlers and runs the resulting statement nodes (see kernel/mod.rs). The
+// language modules (Lumen, rust_core, python_core) supply everything else.
+//
+// A Rust application embeds a language through Interpreter:
+//
+//   let value = lumen_stream::Interpreter::new().eval("x = 6\nx * 7\n")?;
+//
+// and can give its programs host capabilities of its own, reached through extern like the
+// builtin ones: implement ExternCapability and pass it to Interpreter::with_capability.
+//
+// The stream binary (src_stream/main.rs) is a command line front end over this crate.
+// Completely independent from src_microcode.
+
+pub mod kernel;
+pub mod languages;
+mod interpreter;
+
+// Build-time packaging: embedded .lm file list from lib_lumen/prelude.rs
+mod embedded_files {
+    include!("../lib_lumen/prelude.rs");
+}
+
+// `include "path"` expansion against the embedded files, shared with the kernel binaries
+pub mod includes {
+    include!("../lib_lumen/includes.rs");
+}
+
+// --allow-BACKEND / --deny-all, part of the limits, shared with the kernel binaries
+pub mod extern_policy {
+    include!("../lib_lumen/extern_policy.rs");
+}
+
+// --audit, the log of extern calls, part of the limits, shared with the kernel binaries
+pub mod extern_audit {
+    include!("../lib_lumen/extern_audit.rs");
+}
+
+// --extern-timeout, how long extern calls may take, part of the limits, shared with the kernel binaries
+pub mod extern_timeout {
+    include!("../lib_lumen/extern_timeout.rs");
+}
+
+// --max-depth / --max-steps, shared with the kernel binaries
+pub mod limits {
+    include!("../lib_lumen/limits.rs");
+}
+
+// Stage spans and statement events (cargo feature "tracing"), shared with the other kernel
+pub mod spans {
+    include!("../lib_lumen/spans.rs");
+}
+
+pub use interpreter::{Interpreter, RunError};
+pub use kernel::control_flow::LocatedError;
+pub use limits::ExecutionLimits;
+pub use extern_audit::ExternAudit;
+pub use kernel::runtime::{RuntimeValue, Value};
+pub use languages::lumen::extern_system::extern_error::ExternError;
+pub use languages::lumen::extern_system::registry::{ExternCapability, ExternResult, Signature};
+pub use languages::lumen::extern_system::pending::{ExternExecutor, ExternFuture};
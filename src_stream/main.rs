@@ -1,40 +1,143 @@
 // Stream Kernel Main Entry Point
 // Handles language detection and routing for the stream kernel
-// Usage: stream <file> [--lang <language>]
+// Usage: stream <file... | dir | - | -e code> [--lang <language>] [--check | --tokens | --ast]
 
 use std::env;
 use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::Path;
 use std::process;
+use std::sync::Arc;
 
-mod kernel;
-mod languages;
+// The kernel and languages (src_stream/lib.rs)
+use lumen_stream::{kernel, languages};
+use lumen_stream::includes::process_includes;
+use lumen_stream::spans;
+use lumen_stream::kernel::control_flow::{LocatedError, LumenError};
 
-// Build-time packaging: embedded .lm file list from lib_lumen/prelude.rs
-mod embedded_files {
-    include!("../lib_lumen/prelude.rs");
+// Host constants from --define / --config, shared with the other kernel binary
+mod host_config {
+    include!("../lib_lumen/host_config.rs");
 }
 
+use host_config::ConfigValue;
+
+// Error messages with source excerpts, shared with the other kernel binary
+mod diagnostics {
+    include!("../lib_lumen/diagnostics.rs");
+}
+
+use diagnostics::Sources;
+
+// Programs split across several files, shared with the other kernel binary
+mod program_files {
+    include!("../lib_lumen/program_files.rs");
+}
+
+use program_files::ProgramFile;
+
+// Command-line reading, shared with the other kernel binary and lumen-lang
+mod cli_args {
+    include!("../lib_lumen/cli_args.rs");
+}
+
+use cli_args::{Args, Arity, Flag, Input};
+
+// The LUMEN_TRACE subscriber (cargo feature "tracing"), shared with the other kernel binary
+mod trace_output {
+    include!("../lib_lumen/trace_output.rs");
+}
+
+// Stage timings for --timing, shared with the other kernel binary
+mod timing {
+    include!("../lib_lumen/timing.rs");
+}
+
+use timing::{Timing, TimingFormat};
+
+use lumen_stream::extern_audit::ExternAudit;
+use lumen_stream::extern_policy::ExternPolicy;
+use lumen_stream::limits::{self, ExecutionLimits};
+use lumen_stream::languages::lumen::extern_system::adapter::{self, Adapter};
+
 fn main() {
     let args: Vec<String> = env::args().collect();
+    if let Err(e) = trace_output::install_from_env() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
 
-    // Parse arguments: [binary] <file> [--lang <language>] [program_args...]
-    let (filepath, language, program_args) = parse_args(&args);
+    // Parse arguments: [binary] <file... | dir | - | -e code> [--lang <language>] [--check | --tokens | --ast] [--trace[=vars]] [--coverage | --lcov file] [--timing [json]] [--no-prelude | --prelude file.lm] [--max-depth N] [--max-steps N] [--max-time MS] [--max-heap BYTES] [--allow-BACKEND[=CAPS]]... [--deny-all] [--audit FILE] [--extern-timeout [TARGET=]MS]... [--adapter lib.so]... [--intervals] [--deny-warnings] [--define NAME=value]... [--config file.toml] [program_args...]
+    run(parse_args(&args));
+}
 
-    // Read source file
-    let source = match fs::read_to_string(&filepath) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("Error: Failed to read {}: {}", filepath, e);
-            process::exit(1);
+fn run(options: Options) {
+    let Options { input, language, constants, program_args, inspect, trace, coverage, timing, prelude, limits, adapters, intervals, deny_warnings } = options;
+    if !constants.is_empty() && language != "lumen" {
+        eprintln!("Error: --define and --config are only supported for lumen programs");
+        process::exit(1);
+    }
+    if !matches!(prelude, Prelude::Standard) && language != "lumen" {
+        eprintln!("Error: --no-prelude and --prelude are only supported for lumen programs");
+        process::exit(1);
+    }
+    if limits != ExecutionLimits::default() && language != "lumen" {
+        eprintln!("Error: --max-depth, --max-steps, --max-time, --max-heap, --allow-*, --deny-all, --audit and --extern-timeout are only supported for lumen programs");
+        process::exit(1);
+    }
+    if !adapters.is_empty() && language != "lumen" {
+        eprintln!("Error: --adapter is only supported for lumen programs");
+        process::exit(1);
+    }
+    if intervals && language != "lumen" {
+        eprintln!("Error: --intervals is only supported for lumen programs");
+        process::exit(1);
+    }
+    if deny_warnings && language != "lumen" {
+        eprintln!("Error: --deny-warnings is only supported for lumen programs");
+        process::exit(1);
+    }
+    if trace.is_some() && language != "lumen" {
+        eprintln!("Error: --trace is only supported for lumen programs");
+        process::exit(1);
+    }
+    if coverage.is_some() && language != "lumen" {
+        eprintln!("Error: --coverage and --lcov are only supported for lumen programs");
+        process::exit(1);
+    }
+
+    // Read the source files (joined in order) or standard input, or take the inline -e source as is
+    let files = match input {
+        Input::Inline(code) => vec![ProgramFile { name: "-e".to_string(), text: code }],
+        Input::Stdin => {
+            let mut code = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut code) {
+                eprintln!("Error: Failed to read standard input: {}", e);
+                process::exit(1);
+            }
+            vec![ProgramFile { name: "-".to_string(), text: code }]
         }
+        Input::Files(paths) => match program_files::collect(&paths).and_then(|paths| program_files::read(&paths)) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        },
     };
+    if (trace.is_some() || coverage.is_some()) && files.len() > 1 {
+        eprintln!("Error: --trace, --coverage and --lcov need a program in a single file");
+        process::exit(1);
+    }
 
     // Route to appropriate language
     match language.as_str() {
-        "lumen" => run_lumen_stream(&source, &program_args),
-        "rust_core" => run_rust_core_stream(&source, &program_args),
-        "python_core" => run_python_core_stream(&source, &program_args),
+        "lumen" => {
+            let options = LumenOptions { constants, trace, coverage, prelude, limits, adapters, intervals, deny_warnings };
+            run_lumen_stream(&files, &program_args, inspect, timing, options)
+        }
+        "rust_core" => run_rust_core_stream(&files, &program_args, inspect, timing),
+        "python_core" => run_python_core_stream(&files, &program_args, inspect, timing),
         _ => {
             eprintln!("Error: Unknown language '{}'", language);
             process::exit(1);
@@ -42,39 +145,180 @@ fn main() {
     }
 }
 
-fn parse_args(args: &[String]) -> (String, String, Vec<String>) {
-    if args.len() < 2 {
-        eprintln!("Usage: {} <file> [--lang <language>] [program_args...]", args.get(0).unwrap_or(&"lumen-lang".to_string()));
-        process::exit(1);
-    }
+/// Parsed command line
+struct Options {
+    input: Input,
+    language: String,
+    constants: Vec<(String, ConfigValue)>,
+    program_args: Vec<String>,
+    /// --check / --tokens / --ast: stop after parsing instead of running the program
+    inspect: Option<Inspect>,
+    /// --trace / --trace=vars: print each statement as it executes
+    trace: Option<Trace>,
+    /// --coverage / --lcov FILE: report which statements and branches ran
+    coverage: Option<CoverageOutput>,
+    /// --timing / --timing json: report stage durations and sizes on stderr
+    timing: Option<TimingFormat>,
+    /// Library code loaded before a Lumen program (--no-prelude / --prelude FILE)
+    prelude: Prelude,
+    /// --max-depth / --max-steps / --max-time / --max-heap: stop runaway programs with an error;
+    /// --allow-BACKEND / --deny-all: the extern capabilities they may reach
+    limits: ExecutionLimits,
+    /// --adapter LIB: capability adapter libraries, loaded at startup
+    adapters: Vec<Adapter>,
+    /// --intervals: REALs carry error bounds and print their certified digits
+    intervals: bool,
+    /// --deny-warnings: treat warnings as errors
+    deny_warnings: bool,
+}
 
-    let filepath = args[1].clone();
-    let mut language = String::new();
-    let mut program_args = Vec::new();
+/// What a Lumen program runs after
+enum Prelude {
+    /// lib_lumen/prelude.lm, embedded in the binary
+    Standard,
+    /// --prelude FILE: read from disk; its includes still resolve in the embedded library
+    File(String),
+    /// --no-prelude: only kernel builtins (emit, len, ...) are defined, not print or write
+    Off,
+}
+
+/// What to do with a parsed program instead of running it
+#[derive(Clone, Copy, PartialEq)]
+enum Inspect {
+    /// Report syntax errors only (nonzero exit status on error, no output on success)
+    Check,
+    /// Raw token stream, then the token stream after structure processing
+    Tokens,
+    /// Parsed program
+    Ast,
+}
 
-    // Parse --lang flag
-    let mut consumed_until = 2;
-    if args.len() > 2 && args[2] == "--lang" {
-        if args.len() < 4 {
-            eprintln!("Error: --lang requires an argument");
+/// What --trace prints for each executed statement
+#[derive(Clone, Copy, PartialEq)]
+enum Trace {
+    /// Source location, loop depth and statement text
+    Statements,
+    /// The same, followed by the variables the statement changed
+    Vars,
+}
+
+/// Where the coverage report goes after the program finishes
+#[derive(Clone, PartialEq)]
+enum CoverageOutput {
+    /// Annotated source listing on stderr
+    Listing,
+    /// lcov tracefile written to this path
+    Lcov(String),
+}
+
+fn parse_args(args: &[String]) -> Options {
+    let mut rest = Args::new(&args[1..]);
+    let input = match rest.input() {
+        Ok(Some(input)) => input,
+        // No arguments but piped input: run the program from stdin
+        Ok(None) if !io::stdin().is_terminal() => Input::Stdin,
+        Ok(None) => {
+            eprintln!("Usage: {} <file... | dir | - | -e code> [--lang <language>] [--check | --tokens | --ast] [--trace[=vars]] [--coverage | --lcov file] [--timing [json]] [--no-prelude | --prelude file.lm] [--max-depth N] [--max-steps N] [--max-time MS] [--max-heap BYTES] [--allow-BACKEND[=CAPS]]... [--deny-all] [--audit FILE] [--extern-timeout [TARGET=]MS]... [--adapter lib.so]... [--intervals] [--deny-warnings] [--define NAME=value]... [--config file.toml] [program_args...]", args.get(0).unwrap_or(&"lumen-lang".to_string()));
             process::exit(1);
         }
-        language = args[3].to_lowercase();
-        consumed_until = 4;
+        Err(e) => exit_with_error(e),
+    };
+    let mut language = String::new();
+
+    let mut constants = Vec::new();
+    let mut inspect = None;
+    let mut trace = None;
+    let mut coverage = None;
+    let mut timing = None;
+    let mut prelude = Prelude::Standard;
+    let mut limits = ExecutionLimits::default();
+    let mut adapters = Vec::new();
+    let mut intervals = false;
+    let mut deny_warnings = false;
+
+    // Flags in any order, before program arguments (see flag_arity; -- ends the flags)
+    while let Some(flag) = rest.next_flag(flag_arity) {
+        let Flag { name: flag, value } = flag.unwrap_or_else(|e| exit_with_error(e));
+        let value = value.unwrap_or_default();
+        match flag {
+            "--check" => inspect = Some(Inspect::Check),
+            "--tokens" => inspect = Some(Inspect::Tokens),
+            "--ast" => inspect = Some(Inspect::Ast),
+            "--trace" => trace = Some(Trace::Statements),
+            "--trace=vars" => trace = Some(Trace::Vars),
+            // `--timing json` also selects JSON (json is never a program file)
+            "--timing" if rest.next_if("json") => timing = Some(TimingFormat::Json),
+            "--timing" => timing = Some(TimingFormat::Text),
+            "--timing=json" => timing = Some(TimingFormat::Json),
+            "--coverage" => coverage = Some(CoverageOutput::Listing),
+            "--lcov" => coverage = Some(CoverageOutput::Lcov(value.to_string())),
+            "--intervals" => intervals = true,
+            "--deny-warnings" => deny_warnings = true,
+            "--no-prelude" => prelude = Prelude::Off,
+            "--prelude" => prelude = Prelude::File(value.to_string()),
+            "--lang" => language = value.to_lowercase(),
+            "--max-depth" => limits.max_depth = Some(or_exit(limits::parse_limit(flag, value)) as usize),
+            "--max-steps" => limits.max_steps = Some(or_exit(limits::parse_limit(flag, value))),
+            "--max-time" => limits.max_time = Some(std::time::Duration::from_millis(or_exit(limits::parse_limit(flag, value)))),
+            "--max-heap" => limits.max_heap = Some(or_exit(limits::parse_limit(flag, value)) as usize),
+            "--audit" => limits.audit = Some(or_exit(ExternAudit::open(value))),
+            "--extern-timeout" => or_exit(limits.extern_timeouts.apply_flag(value)),
+            "--adapter" => adapters.push(or_exit(adapter::load(value))),
+            "--define" => or_exit(host_config::parse_define(value).and_then(|c| host_config::merge(&mut constants, vec![c]))),
+            "--config" => or_exit(
+                fs::read_to_string(value)
+                    .map_err(|e| format!("Failed to read {}: {}", value, e))
+                    .and_then(|source| host_config::parse_config(&source))
+                    .map_err(|e| format!("{}: {}", value, e))
+                    .and_then(|c| host_config::merge(&mut constants, c)),
+            ),
+            // Only the microcode kernel reduces programs to instructions (or runs schema languages)
+            _ if MICROCODE_FLAGS.contains(&flag) || flag.starts_with("--opt=") => {
+                exit_with_error(format!("{} is only supported by the microcode kernel", flag))
+            }
+            // --allow-BACKEND[=CAPS] and --deny-all, the other flags flag_arity knows
+            _ => or_exit(limits.externs.apply_flag(flag).unwrap_or(Ok(()))),
+        }
     }
 
-    // Auto-detect language if not specified
+    // Auto-detect language if not specified (inline source defaults to lumen)
     if language.is_empty() {
-        language = detect_language_from_extension(&filepath)
-            .unwrap_or_else(|| "lumen".to_string());
+        language = match &input {
+            Input::Files(paths) => detect_language_from_extension(&paths[0]),
+            Input::Stdin | Input::Inline(_) => None,
+        }
+        .unwrap_or_else(|| "lumen".to_string());
     }
 
     // Remaining arguments are program arguments
-    if args.len() > consumed_until {
-        program_args = args[consumed_until..].to_vec();
+    let program_args = rest.rest().to_vec();
+
+    Options { input, language, constants, program_args, inspect, trace, coverage, timing, prelude, limits, adapters, intervals, deny_warnings }
+}
+
+/// Flags of the microcode kernel, rejected here
+const MICROCODE_FLAGS: [&str; 7] = ["--emit-instructions", "--opt", "--cache", "--emit-program", "--load-program", "--plugin", "--schema"];
+
+/// The flags this kernel reads; any other argument starts the program arguments
+fn flag_arity(flag: &str) -> Option<Arity> {
+    match flag {
+        "--lang" | "--lcov" | "--prelude" | "--max-depth" | "--max-steps" | "--max-time" | "--max-heap" | "--audit"
+        | "--extern-timeout" | "--adapter" | "--define" | "--config" => Some(Arity::Value),
+        "--check" | "--tokens" | "--ast" | "--trace" | "--trace=vars" | "--timing" | "--timing=json" | "--coverage"
+        | "--intervals" | "--deny-warnings" | "--no-prelude" => Some(Arity::Switch),
+        _ if MICROCODE_FLAGS.contains(&flag) || flag.starts_with("--opt=") || ExternPolicy::is_flag(flag) => Some(Arity::Switch),
+        _ => None,
     }
+}
+
+/// The value of a flag, or exit with its error
+fn or_exit<T>(parsed: Result<T, String>) -> T {
+    parsed.unwrap_or_else(|e| exit_with_error(e))
+}
 
-    (filepath, language, program_args)
+fn exit_with_error(message: String) -> ! {
+    eprintln!("Error: {}", message);
+    process::exit(1)
 }
 
 fn detect_language_from_extension(filepath: &str) -> Option<String> {
@@ -91,67 +335,89 @@ fn detect_language_from_extension(filepath: &str) -> Option<String> {
     Some(language.to_string())
 }
 
-/// Generic embedded file lookup: queries the embedded virtual filesystem by path
-/// The kernel performs a simple path-based lookup with no semantic knowledge
-fn get_embedded_file(path: &str) -> Option<&'static str> {
-    embedded_files::EMBEDDED_FILES
-        .iter()
-        .find(|(p, _)| *p == path)
-        .map(|(_, contents)| *contents)
+/// Prelude source with its includes expanded (empty for --no-prelude)
+fn load_prelude(prelude: &Prelude) -> Result<String, String> {
+    match prelude {
+        Prelude::Standard => process_includes(include_str!("../lib_lumen/prelude.lm")),
+        Prelude::File(path) => fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read prelude {}: {}", path, e))
+            .and_then(|source| process_includes(&source)),
+        Prelude::Off => Ok(String::new()),
+    }
 }
 
-/// Process include directives in source code
-/// Recursively expands `include "path"` directives by inlining embedded file contents
-fn process_includes(source: &str) -> Result<String, String> {
-    let mut result = String::new();
-    let mut processed_files = std::collections::HashSet::new();
-
-    fn process_recursive(
-        source: &str,
-        processed_files: &mut std::collections::HashSet<String>,
-        result: &mut String,
-    ) -> Result<(), String> {
-        for line in source.lines() {
-            let trimmed = line.trim();
-
-            // Check if line is an include directive
-            if trimmed.starts_with("include ") {
-                // Extract the file path from: include "path"
-                let rest = trimmed.strip_prefix("include ").unwrap().trim();
-
-                if !rest.starts_with('"') || !rest.ends_with('"') {
-                    return Err(format!("Invalid include syntax: {}", line));
-                }
+/// The program files for error excerpts; `first_line` is where the first one starts in the lexed text
+fn sources(files: &[ProgramFile], first_line: usize) -> Sources<'_> {
+    Sources {
+        files: files.iter().map(|f| (f.name.as_str(), f.text.as_str())).collect(),
+        first_line,
+    }
+}
 
-                let path = &rest[1..rest.len()-1];
+/// Exit after a program failed to lex or parse, reporting each error at its token
+fn exit_syntax_error(kind: &str, errors: Vec<LocatedError>, sources: &Sources) -> ! {
+    for error in &errors {
+        diagnostics::report_at(kind, &error.message, &error.positions, sources);
+    }
+    process::exit(diagnostics::EXIT_SYNTAX_ERROR)
+}
 
-                // Prevent circular includes
-                if processed_files.contains(path) {
-                    continue; // Skip already processed files
-                }
-                processed_files.insert(path.to_string());
-
-                // Retrieve from embedded virtual filesystem
-                let file_contents = get_embedded_file(path)
-                    .ok_or_else(|| format!("File not found in embedded filesystem: {}", path))?;
-
-                // Recursively process the included file
-                process_recursive(file_contents, processed_files, result)?;
-                result.push('\n');
-            } else {
-                // Regular line - keep it
-                result.push_str(line);
-                result.push('\n');
-            }
+/// Exit after an uncaught runtime error, or with the status of an `exit` statement
+fn exit_after_runtime_error(error: LumenError, sources: &Sources) -> ! {
+    match error {
+        LumenError::Exit(status) => {
+            let _ = io::stdout().flush();
+            process::exit(status)
         }
-        Ok(())
+        LumenError::Raised(error) => {
+            diagnostics::report_at("RuntimeError", &error.message, &error.positions, sources);
+            process::exit(diagnostics::EXIT_RUNTIME_ERROR)
+        }
+    }
+}
+
+/// --timing: print the stage report to stderr
+fn report_timing(timer: &Timing, timing: Option<TimingFormat>) {
+    if let Some(format) = timing {
+        eprint!("{}", timer.render("stream", format));
+    }
+}
+
+/// --tokens: one token per line with line:col and the quoted lexeme
+fn print_tokens(title: &str, tokens: &[crate::kernel::lexer::SpannedToken]) {
+    println!("== {} ({}) ==", title, tokens.len());
+    for token in tokens {
+        println!("{:>4}:{:<4}{:?}", token.line, token.col, token.tok.lexeme);
+    }
+}
+
+/// --ast: pretty-print each parsed top-level statement instead of running the program
+fn print_ast(program: &crate::kernel::ast::Program) {
+    for stmt in &program.statements {
+        println!("{:#?}", stmt);
     }
+}
 
-    process_recursive(source, &mut processed_files, &mut result)?;
-    Ok(result)
+/// Options that only Lumen programs take
+struct LumenOptions {
+    constants: Vec<(String, ConfigValue)>,
+    trace: Option<Trace>,
+    coverage: Option<CoverageOutput>,
+    prelude: Prelude,
+    limits: ExecutionLimits,
+    adapters: Vec<Adapter>,
+    intervals: bool,
+    deny_warnings: bool,
 }
 
-fn run_lumen_stream(source: &str, program_args: &[String]) {
+fn run_lumen_stream(
+    files: &[ProgramFile],
+    program_args: &[String],
+    inspect: Option<Inspect>,
+    timing: Option<TimingFormat>,
+    options: LumenOptions,
+) {
+    let LumenOptions { constants, trace, coverage, prelude, limits, adapters, intervals, deny_warnings } = options;
     use crate::kernel::lexer::lex;
     use crate::kernel::parser::Parser;
     use crate::languages::lumen::registry::Registry;
@@ -161,12 +427,9 @@ fn run_lumen_stream(source: &str, program_args: &[String]) {
     let mut registry = Registry::new();
     crate::languages::lumen::dispatcher::register_all(&mut registry);
 
-    // Load bootstrap file (prelude.lm) before user code
+    // Load the bootstrap file (prelude.lm, or the --prelude file) before user code
     // The kernel has no semantic knowledge of what this file does or contains
-    let bootstrap_source = include_str!("../lib_lumen/prelude.lm");
-
-    // Process include directives in bootstrap file
-    let expanded_bootstrap = match process_includes(bootstrap_source) {
+    let expanded_bootstrap = match load_prelude(&prelude) {
         Ok(expanded) => expanded,
         Err(e) => {
             eprintln!("Include error: {}", e);
@@ -174,77 +437,168 @@ fn run_lumen_stream(source: &str, program_args: &[String]) {
         }
     };
 
-    let full_source = format!("{}\n{}", expanded_bootstrap, source);
+    // --check / --tokens / --ast look only at the user program, so the prelude is left out
+    let source = program_files::join(files);
+    let source = source.as_str();
+    let full_source = if inspect.is_some() || matches!(prelude, Prelude::Off) {
+        source.to_string()
+    } else {
+        format!("{}\n{}", expanded_bootstrap, source)
+    };
+    let first_line = full_source[..full_source.len() - source.len()].matches('\n').count() + 1;
+    spans::program_starts_at(first_line);
+    let sources = sources(files, first_line);
 
+    let mut timer = Timing::start();
+    let stage = spans::stage("stream", "lex");
     let raw_tokens = match lex(&full_source, &registry.tokens) {
         Ok(toks) => toks,
         Err(e) => {
-            eprintln!("LexError: {e}");
-            process::exit(1);
+            exit_syntax_error("LexError", vec![e.into_located()], &sources)
         }
     };
+    drop(stage);
+    if inspect == Some(Inspect::Tokens) {
+        print_tokens("raw tokens", &raw_tokens);
+    }
 
+    timer.lap("Lex");
+    timer.count("tokens", raw_tokens.len());
+    let stage = spans::stage("stream", "structure");
     let processed_tokens = match structural::process_indentation(&full_source, raw_tokens) {
         Ok(toks) => toks,
         Err(e) => {
-            eprintln!("IndentationError: {e}");
-            process::exit(1);
+            exit_syntax_error("IndentationError", vec![e.into_located()], &sources)
         }
     };
+    drop(stage);
+    if inspect == Some(Inspect::Tokens) {
+        print_tokens("after structure", &processed_tokens);
+        return;
+    }
 
+    // --trace / --coverage instrument statements parsed from the user program (not the prelude)
+    let tracing = trace.is_some() && inspect.is_none();
+    if let (Some(trace), true) = (trace, tracing) {
+        crate::languages::lumen::trace::enable(&mut registry, first_line, source, trace == Trace::Vars);
+    }
+    let counts = (coverage.is_some() && inspect.is_none())
+        .then(|| crate::languages::lumen::coverage::enable(&mut registry, first_line));
+    // Warnings are collected when running or checking the program
+    if matches!(inspect, None | Some(Inspect::Check)) {
+        crate::languages::lumen::warnings::enable(&registry, first_line);
+    }
+
+    timer.lap("Structure");
+    timer.count("structured_tokens", processed_tokens.len());
+    let stage = spans::stage("stream", "parse");
     let mut parser = match Parser::new_with_tokens(processed_tokens, &registry.tokens) {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("{e}");
-            process::exit(1);
+            exit_syntax_error("ParseError", vec![e.into_located()], &sources)
         }
     };
 
     let program = match structural::parse_program(&mut parser, &registry) {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("{e}");
-            process::exit(1);
+            exit_syntax_error("ParseError", e, &sources)
         }
     };
+    drop(stage);
+
+    let warnings = crate::languages::lumen::warnings::check_names(&registry);
+    for warning in &warnings {
+        diagnostics::warn(&warning.message, &warning.positions, &sources);
+    }
+    if deny_warnings && !warnings.is_empty() {
+        let plural = if warnings.len() == 1 { "" } else { "s" };
+        eprintln!("Error: {} warning{} denied by --deny-warnings", warnings.len(), plural);
+        process::exit(1);
+    }
 
+    match inspect {
+        Some(Inspect::Ast) => return print_ast(&program),
+        Some(_) => return,
+        None => {}
+    }
+
+    // The run owns the functions the program defined, its extern registry and its limits
     // Initialize environment with system values (ARGS, kind constants, etc.)
+    let functions = registry.take_functions();
+    let runtime_warnings = crate::languages::lumen::warnings::RuntimeWarnings::sink(deny_warnings);
+    let sink = Arc::clone(&runtime_warnings);
+    let counted = counts.clone();
     let init_env = move |env: &mut crate::kernel::runtime::Env| {
-        use crate::languages::lumen::values::{LumenString, LumenKind, LumenNumber, KindValue};
-        use num_bigint::BigInt;
-
-        // Bind ARGS: system-provided semantic value containing all program arguments
-        // ARGS is immutable and read-only (cannot be reassigned by user code)
-        let args_str = if program_args.is_empty() {
-            String::new()
-        } else {
-            program_args.join(" ")
-        };
-        env.define("ARGS".to_string(), Box::new(LumenString::new(args_str)));
-
-        // Bind kind meta-value constants: INTEGER, RATIONAL, REAL, STRING, BOOLEAN, ARRAY, NULL
-        // These are predefined kernel-level type descriptors that match kind() return values
-        env.define("INTEGER".to_string(), Box::new(LumenKind::new(KindValue::INTEGER)));
-        env.define("RATIONAL".to_string(), Box::new(LumenKind::new(KindValue::RATIONAL)));
-        env.define("REAL".to_string(), Box::new(LumenKind::new(KindValue::REAL)));
-        env.define("STRING".to_string(), Box::new(LumenKind::new(KindValue::STRING)));
-        env.define("BOOLEAN".to_string(), Box::new(LumenKind::new(KindValue::BOOLEAN)));
-        env.define("ARRAY".to_string(), Box::new(LumenKind::new(KindValue::ARRAY)));
-        env.define("NULL".to_string(), Box::new(LumenKind::new(KindValue::NULL)));
-
-        // Bind kernel constant: REAL_DEFAULT_PRECISION
-        env.define("REAL_DEFAULT_PRECISION".to_string(), Box::new(LumenNumber::new(BigInt::from(15))));
+        use crate::languages::lumen::values::{LumenString, LumenNumber, LumenBool, LumenReal};
+        use crate::languages::lumen::statements::assignment::protect_binding;
+        use crate::kernel::runtime::Value;
+
+        crate::languages::lumen::state::State::install(env, functions, limits);
+        crate::languages::lumen::state::State::of(env).intervals = intervals;
+        crate::languages::lumen::state::State::of(env).warnings = Some(sink);
+        crate::languages::lumen::state::State::of(env).coverage = counted;
+        crate::languages::lumen::state::State::of(env).loop_depth = tracing.then(Arc::default);
+        for adapter in &adapters {
+            adapter.register(&mut crate::languages::lumen::state::State::of(env).capabilities);
+        }
+        crate::languages::lumen::system::bind(env, program_args);
+
+        // Bind host constants (--define / --config); read-only like ARGS
+        for (name, value) in constants {
+            if env.get(&name).is_ok() {
+                return Err(format!("Host constant '{}' collides with a system-provided name", name).into());
+            }
+            let value: Value = match value {
+                ConfigValue::Integer(n) => Box::new(LumenNumber::new(n)),
+                ConfigValue::Real { numerator, denominator, precision } => {
+                    let error = intervals.then(crate::languages::lumen::interval::Radius::zero);
+                    Box::new(LumenReal::with_error(numerator, denominator, precision, error))
+                }
+                ConfigValue::Bool(b) => Box::new(LumenBool::new(b)),
+                ConfigValue::String(s) => Box::new(LumenString::new(s)),
+            };
+            env.define(name.clone(), value);
+            protect_binding(env, &name);
+        }
 
         Ok(())
     };
 
-    if let Err(e) = eval::eval(&program, init_env) {
-        eprintln!("RuntimeError: {e}");
-        process::exit(1);
+    timer.lap("Parse");
+    timer.count("statements", program.statements.len());
+    let stage = spans::stage("stream", "execute");
+    let result = eval::eval(&program, init_env);
+    drop(stage);
+    timer.lap("Execute");
+
+    // The report covers whatever ran, even if the program stopped with an error
+    if let (Some(output), Some(counts)) = (coverage, counts) {
+        let counts = counts.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match output {
+            CoverageOutput::Listing => eprint!("{}", counts.listing(source)),
+            CoverageOutput::Lcov(path) => {
+                if let Err(e) = fs::write(&path, counts.lcov(&files[0].name)) {
+                    eprintln!("Error: Failed to write {}: {}", path, e);
+                    process::exit(1);
+                }
+                eprintln!("{}", counts.summary());
+            }
+        }
+    }
+
+    let warnings = runtime_warnings.lock().map(|mut w| w.take()).unwrap_or_default();
+    for warning in warnings {
+        diagnostics::warn(&warning.message, &warning.positions, &sources);
+    }
+
+    report_timing(&timer, timing);
+    if let Err(e) = result {
+        exit_after_runtime_error(e, &sources);
     }
 }
 
-fn run_rust_core_stream(source: &str, program_args: &[String]) {
+fn run_rust_core_stream(files: &[ProgramFile], program_args: &[String], inspect: Option<Inspect>, timing: Option<TimingFormat>) {
     use crate::kernel::lexer::lex;
     use crate::kernel::parser::Parser;
     use crate::languages::rust_core::registry::Registry;
@@ -253,52 +607,82 @@ fn run_rust_core_stream(source: &str, program_args: &[String]) {
 
     let mut registry = Registry::new();
     crate::languages::rust_core::register_all(&mut registry);
+    let source = program_files::join(files);
+    let source = source.as_str();
+    let sources = sources(files, 1);
 
+    let mut timer = Timing::start();
+    let stage = spans::stage("stream", "lex");
     let raw_tokens = match lex(source, &registry.tokens) {
         Ok(toks) => toks,
         Err(e) => {
-            eprintln!("LexError: {e}");
-            process::exit(1);
+            exit_syntax_error("LexError", vec![e.into_located()], &sources)
         }
     };
+    drop(stage);
+    if inspect == Some(Inspect::Tokens) {
+        print_tokens("raw tokens", &raw_tokens);
+    }
 
+    timer.lap("Lex");
+    timer.count("tokens", raw_tokens.len());
+    let stage = spans::stage("stream", "structure");
     let processed_tokens = match structural::process_tokens(raw_tokens) {
         Ok(toks) => toks,
         Err(e) => {
-            eprintln!("TokenError: {e}");
-            process::exit(1);
+            exit_syntax_error("TokenError", vec![e.into_located()], &sources)
         }
     };
+    drop(stage);
+    if inspect == Some(Inspect::Tokens) {
+        print_tokens("after structure", &processed_tokens);
+        return;
+    }
 
+    timer.lap("Structure");
+    timer.count("structured_tokens", processed_tokens.len());
+    let stage = spans::stage("stream", "parse");
     let mut parser = match Parser::new_with_tokens(processed_tokens, &registry.tokens) {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("{e}");
-            process::exit(1);
+            exit_syntax_error("ParseError", vec![e.into_located()], &sources)
         }
     };
 
     let program = match structural::parse_program(&mut parser, &registry) {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("{e}");
-            process::exit(1);
+            exit_syntax_error("ParseError", e, &sources)
         }
     };
+    drop(stage);
+
+    match inspect {
+        Some(Inspect::Ast) => return print_ast(&program),
+        Some(_) => return,
+        None => {}
+    }
 
     // Initialize environment with system values (ARGS, etc.)
-    // Note: rust_core doesn't have a String value type, so ARGS is not currently supported
+    // Note: rust_core doesn't have a String value type yet; once it does it binds ARGS (an
+    // array of the argument strings) and ARGC as Lumen does (languages/lumen/system.rs)
     let init_env = |_env: &mut crate::kernel::runtime::Env| {
         Ok(())
     };
 
-    if let Err(e) = eval::eval(&program, init_env) {
-        eprintln!("RuntimeError: {e}");
-        process::exit(1);
+    timer.lap("Parse");
+    timer.count("statements", program.statements.len());
+    let stage = spans::stage("stream", "execute");
+    let result = eval::eval(&program, init_env);
+    drop(stage);
+    timer.lap("Execute");
+    report_timing(&timer, timing);
+    if let Err(e) = result {
+        exit_after_runtime_error(e, &sources);
     }
 }
 
-fn run_python_core_stream(source: &str, program_args: &[String]) {
+fn run_python_core_stream(files: &[ProgramFile], program_args: &[String], inspect: Option<Inspect>, timing: Option<TimingFormat>) {
     use crate::kernel::lexer::lex;
     use crate::kernel::parser::Parser;
     use crate::languages::python_core::registry::Registry;
@@ -307,47 +691,77 @@ fn run_python_core_stream(source: &str, program_args: &[String]) {
 
     let mut registry = Registry::new();
     crate::languages::python_core::register_all(&mut registry);
+    let source = program_files::join(files);
+    let source = source.as_str();
+    let sources = sources(files, 1);
 
+    let mut timer = Timing::start();
+    let stage = spans::stage("stream", "lex");
     let raw_tokens = match lex(source, &registry.tokens) {
         Ok(toks) => toks,
         Err(e) => {
-            eprintln!("LexError: {e}");
-            process::exit(1);
+            exit_syntax_error("LexError", vec![e.into_located()], &sources)
         }
     };
+    drop(stage);
+    if inspect == Some(Inspect::Tokens) {
+        print_tokens("raw tokens", &raw_tokens);
+    }
 
+    timer.lap("Lex");
+    timer.count("tokens", raw_tokens.len());
+    let stage = spans::stage("stream", "structure");
     let processed_tokens = match structural::process_indentation(source, raw_tokens) {
         Ok(toks) => toks,
         Err(e) => {
-            eprintln!("IndentationError: {e}");
-            process::exit(1);
+            exit_syntax_error("IndentationError", vec![e.into_located()], &sources)
         }
     };
+    drop(stage);
+    if inspect == Some(Inspect::Tokens) {
+        print_tokens("after structure", &processed_tokens);
+        return;
+    }
 
+    timer.lap("Structure");
+    timer.count("structured_tokens", processed_tokens.len());
+    let stage = spans::stage("stream", "parse");
     let mut parser = match Parser::new_with_tokens(processed_tokens, &registry.tokens) {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("{e}");
-            process::exit(1);
+            exit_syntax_error("ParseError", vec![e.into_located()], &sources)
         }
     };
 
     let program = match structural::parse_program(&mut parser, &registry) {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("{e}");
-            process::exit(1);
+            exit_syntax_error("ParseError", e, &sources)
         }
     };
+    drop(stage);
+
+    match inspect {
+        Some(Inspect::Ast) => return print_ast(&program),
+        Some(_) => return,
+        None => {}
+    }
 
     // Initialize environment with system values (ARGS, etc.)
-    // Note: python_core doesn't have a String value type, so ARGS is not currently supported
+    // Note: python_core doesn't have a String value type yet; once it does it binds ARGS (a
+    // list of the argument strings) and ARGC as Lumen does (languages/lumen/system.rs)
     let init_env = |_env: &mut crate::kernel::runtime::Env| {
         Ok(())
     };
 
-    if let Err(e) = eval::eval(&program, init_env) {
-        eprintln!("RuntimeError: {e}");
-        process::exit(1);
+    timer.lap("Parse");
+    timer.count("statements", program.statements.len());
+    let stage = spans::stage("stream", "execute");
+    let result = eval::eval(&program, init_env);
+    drop(stage);
+    timer.lap("Execute");
+    report_timing(&timer, timing);
+    if let Err(e) = result {
+        exit_after_runtime_error(e, &sources);
     }
 }
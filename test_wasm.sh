@@ -0,0 +1,90 @@
+#!/bin/bash
+
+# Build the kernels for wasm32-wasip1 and run programs on them in a WASM runtime
+# Usage: ./test_wasm.sh
+#
+# Runs each example with wasmtime if it is installed, else with Node's WASI, and compares
+# its output with the native kernel's. Then checks that what WASI lacks (sockets, threads)
+# fails as an extern error instead of aborting the program.
+
+TARGET=wasm32-wasip1
+
+echo "Building the kernels for $TARGET..."
+if ! rustup target list --installed 2>/dev/null | grep -q "^$TARGET$"; then
+    echo "The $TARGET target is not installed (rustup target add $TARGET)"
+    exit 1
+fi
+if ! cargo build --quiet --target "$TARGET" --bin stream --bin microcode 2>/dev/null || ! cargo build --quiet --bin stream --bin microcode 2>/dev/null; then
+    echo "Build failed!"
+    exit 1
+fi
+echo "Built successfully"
+echo ""
+
+# Run a .wasm with its arguments, the current directory preopened
+if command -v wasmtime > /dev/null; then
+    run_wasm() { wasmtime run --dir=. "$@"; }
+elif command -v node > /dev/null; then
+    NODE_WASI=$(mktemp --suffix=.mjs)
+    trap 'rm -f "$NODE_WASI"' EXIT
+    cat > "$NODE_WASI" <<'EOF'
+import { WASI } from 'node:wasi';
+import { readFile } from 'node:fs/promises';
+const [wasm, ...args] = process.argv.slice(2);
+const wasi = new WASI({ version: 'preview1', args: [wasm, ...args], env: {}, preopens: { '.': '.' }, returnOnExit: true });
+const instance = await WebAssembly.instantiate(await WebAssembly.compile(await readFile(wasm)), wasi.getImportObject());
+process.exitCode = wasi.start(instance);
+EOF
+    run_wasm() { node --no-warnings "$NODE_WASI" "$@"; }
+else
+    echo "No WASM runtime: install wasmtime (or Node.js)"
+    exit 1
+fi
+
+FAILED=0
+check() {
+    if [ "$2" == "$3" ]; then
+        echo "✓ $1"
+    else
+        echo "✗ $1"
+        echo "  expected: $2"
+        echo "  got:      $3"
+        FAILED=1
+    fi
+}
+
+for kernel in stream microcode; do
+    for program in examples/lumen/factorial.lm examples/lumen/sieve.lm examples/lumen/string_library_demo.lm; do
+        check "$kernel $program" "$(./target/debug/$kernel "$program" 2>&1)" "$(run_wasm "target/$TARGET/debug/$kernel.wasm" "$program" 2>&1)"
+    done
+
+    # Sockets are unsupported, and so are the threads timed calls run on
+    PROGRAM=$(mktemp -p . --suffix=.lm)
+    cat > "$PROGRAM" <<'EOF'
+print(extern("fs:exists", "test_wasm.sh"))
+print(extern_try("net:connect", "127.0.0.1:1")["error"]["code"])
+extern_timeout("time", 1000)
+print(extern_try("time:now")["error"]["code"])
+EOF
+    check "$kernel: fs works, net and timed calls fail" "$(printf 'true\nunsupported\nfailed')" "$(run_wasm "target/$TARGET/debug/$kernel.wasm" "$(basename "$PROGRAM")" 2>&1)"
+
+    # The stream kernel's extern_async calls need a thread: extern_await raises the error
+    cat > "$PROGRAM" <<'EOF'
+extern_await(extern_async("time:now"))
+print("awaited")
+EOF
+    OUTPUT=$(run_wasm "target/$TARGET/debug/$kernel.wasm" "$(basename "$PROGRAM")" 2>&1)
+    case "$OUTPUT" in
+        awaited|*"cannot start the call"*) check "$kernel: extern_async" "" "" ;;
+        *) check "$kernel: extern_async" "awaited, or the error of a call with no thread" "$OUTPUT" ;;
+    esac
+    rm -f "$PROGRAM"
+done
+
+echo ""
+if [ $FAILED -eq 0 ]; then
+    echo "All WASM tests passed"
+else
+    echo "Some WASM tests failed"
+    exit 1
+fi